@@ -0,0 +1,27 @@
+use clap::Parser;
+use ityfuzz::fuzzers::cairo_fuzzer::{cairo_fuzzer, CairoFuzzConfig};
+
+/// CLI for ItyFuzz for Starknet (Cairo) smart contracts
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct CairoArgs {
+    /// Glob pattern to find contract classes (e.g., "target/dev/*.contract_class.json")
+    #[arg(short, long)]
+    target: String,
+
+    /// Seed for the RNG
+    #[arg(short, long, default_value = "0")]
+    seed: u64,
+
+    /// Work directory
+    #[arg(long, default_value = "./work_dir")]
+    work_dir: String,
+}
+
+pub fn cairo_main(args: CairoArgs) {
+    cairo_fuzzer(&CairoFuzzConfig {
+        target: args.target,
+        work_dir: args.work_dir,
+        seed: args.seed,
+    });
+}
@@ -0,0 +1,192 @@
+/// Configuration file (ityfuzz.toml) for the evm subcommand
+///
+/// Every key is the name of a CLI flag (with `_` or `-`), e.g., `onchain_block_number = 16000000`
+/// or `flashloan = true`, either at the top level or in an `[evm]` table. Arrays are passed as
+/// comma-separated values, and `false` turns off options that are on by default (e.g.,
+/// `revert_coverage = false`). The file is `--config <path>` or ./ityfuzz.toml if present, and flags
+/// given on the command line override it.
+use std::path::Path;
+
+use clap::error::ErrorKind;
+use clap::{ArgAction, Command, CommandFactory};
+use toml::Value;
+
+use crate::evm::EvmArgs;
+
+pub const DEFAULT_CONFIG_FILE: &str = "ityfuzz.toml";
+
+fn flag_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values.iter().map(flag_value).collect::<Vec<_>>().join(","),
+        v => v.to_string(),
+    }
+}
+
+/// Whether `flag` of `command` is a switch (enabling a feature when present) rather than taking a
+/// value, unknown flags are left for clap to report
+fn is_switch(command: &Command, flag: &str) -> bool {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(flag))
+        .map_or(false, |arg| matches!(arg.get_action(), ArgAction::SetTrue))
+}
+
+/// CLI arguments of `command` equivalent to a configuration file
+fn config_args(config: &str, command: &Command) -> Result<Vec<String>, String> {
+    let table = match config.parse::<Value>() {
+        Ok(Value::Table(table)) => table,
+        Ok(_) => return Err("configuration file must be a table".to_string()),
+        Err(e) => return Err(format!("invalid configuration file: {}", e)),
+    };
+    let mut entries = table
+        .iter()
+        .filter(|(k, v)| !v.is_table() && k.as_str() != "config")
+        .chain(table.get("evm").and_then(|v| v.as_table()).into_iter().flatten())
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|(k, _)| k.to_string());
+
+    let mut args = vec![];
+    for (key, value) in entries {
+        let name = key.replace('_', "-");
+        let flag = format!("--{}", name);
+        match value {
+            // switches are off unless present, other booleans take their value
+            Value::Boolean(true) if is_switch(command, &name) => args.push(flag),
+            Value::Boolean(false) if is_switch(command, &name) => {}
+            Value::Boolean(v) => args.push(format!("{}={}", flag, v)),
+            Value::Table(_) => return Err(format!("unsupported table {} in configuration file", key)),
+            v => {
+                args.push(flag);
+                args.push(flag_value(v));
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Insert the arguments from the configuration file before the ones of the command line, so that
+/// the latter override them. Returns the arguments and the path of the configuration file.
+pub fn expand_args(mut args: Vec<String>) -> Result<(Vec<String>, Option<String>), clap::Error> {
+    if args.get(1).map(|cmd| cmd.to_lowercase()) != Some("evm".to_string()) {
+        return Ok((args, None));
+    }
+    let mut command = EvmArgs::command();
+
+    // `--config` itself is parsed (and ignored) by clap
+    let config_path = if let Some(idx) = args.iter().position(|a| a == "--config") {
+        match args.get(idx + 1) {
+            Some(path) => Some(path.clone()),
+            None => return Err(command.error(ErrorKind::InvalidValue, "--config requires a path")),
+        }
+    } else if let Some(arg) = args.iter().find(|a| a.starts_with("--config=")) {
+        Some(arg["--config=".len()..].to_string())
+    } else if Path::new(DEFAULT_CONFIG_FILE).exists() {
+        Some(DEFAULT_CONFIG_FILE.to_string())
+    } else {
+        None
+    };
+
+    let path = match config_path {
+        Some(path) => path,
+        None => return Ok((args, None)),
+    };
+    let config = std::fs::read_to_string(&path)
+        .map_err(|e| command.error(ErrorKind::Io, format!("failed to read {}: {}", path, e)))?;
+    let config_args = config_args(&config, &command)
+        .map_err(|e| command.error(ErrorKind::InvalidValue, format!("{}: {}", path, e)))?;
+    let rest = args.split_off(2);
+    args.extend(config_args);
+    args.extend(rest);
+    Ok((args, Some(path)))
+}
+
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_config_args() {
+        let config = r#"
+            onchain_block_number = 16000000
+            flashloan = true
+            panic_on_bug = false
+            chain-type = "ETH"
+
+            [evm]
+            target = "./build/*"
+            constructor_args = ["a", "b"]
+        "#;
+        assert_eq!(
+            config_args(config, &EvmArgs::command()).unwrap(),
+            strings(&[
+                "--chain-type",
+                "ETH",
+                "--constructor-args",
+                "a,b",
+                "--flashloan",
+                "--onchain-block-number",
+                "16000000",
+                "--target",
+                "./build/*",
+            ])
+        );
+        assert!(config_args("", &EvmArgs::command()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_config_args_off() {
+        // options on by default are turned off with their value
+        let config = "revert_coverage = false\nechidna_oracle = false\npanic_on_bug = true\n";
+        let args = config_args(config, &EvmArgs::command()).unwrap();
+        assert_eq!(args, strings(&["--echidna-oracle=false", "--panic-on-bug", "--revert-coverage=false"]));
+        let parsed = EvmArgs::try_parse_from(strings(&["evm", "-t", "a"]).into_iter().chain(args));
+        assert!(parsed.is_ok());
+        // unknown options are left for clap to reject
+        assert_eq!(
+            config_args("no_such_option = true", &EvmArgs::command()).unwrap(),
+            strings(&["--no-such-option=true"])
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(config_args("target = ", &EvmArgs::command()).is_err());
+        assert!(config_args("[evm.onchain]\nurl = \"a\"", &EvmArgs::command()).is_err());
+    }
+
+    #[test]
+    fn test_expand_args() {
+        let path = std::env::temp_dir().join("ityfuzz_test_expand_args.toml");
+        std::fs::write(&path, "flashloan = true\ntarget = \"a\"\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        // the command line comes last to override the configuration file
+        let (args, config_path) = expand_args(strings(&["ityfuzz", "evm", "--config", path, "-t", "b"])).unwrap();
+        assert_eq!(
+            args,
+            strings(&["ityfuzz", "evm", "--flashloan", "--target", "a", "--config", path, "-t", "b"])
+        );
+        assert_eq!(config_path, Some(path.to_string()));
+        assert_eq!(
+            expand_args(strings(&["ityfuzz", "evm", &format!("--config={}", path)])).unwrap().0[2..4],
+            strings(&["--flashloan", "--target"])
+        );
+        // other subcommands are left untouched
+        let args = strings(&["ityfuzz", "move", "--config", path]);
+        assert_eq!(expand_args(args.clone()).unwrap(), (args, None));
+    }
+
+    #[test]
+    fn test_unreadable_config() {
+        let args = strings(&["ityfuzz", "evm", "--config", "/nonexistent/ityfuzz.toml"]);
+        assert_eq!(expand_args(args).unwrap_err().kind(), ErrorKind::Io);
+        let args = strings(&["ityfuzz", "evm", "--config"]);
+        assert_eq!(expand_args(args).unwrap_err().kind(), ErrorKind::InvalidValue);
+    }
+}
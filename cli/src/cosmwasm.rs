@@ -0,0 +1,33 @@
+use clap::Parser;
+use ityfuzz::fuzzers::cosmwasm_fuzzer::{cosmwasm_fuzzer, CosmWasmFuzzConfig};
+
+/// CLI for ItyFuzz for CosmWasm smart contracts
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct CosmWasmArgs {
+    /// Glob pattern to find contract wasm files (e.g., "artifacts/*.wasm")
+    #[arg(short, long)]
+    target: String,
+
+    /// Directory of JSON schemas generated by `cosmwasm-schema` (e.g., "schema"),
+    /// the schema of `foo_bar.wasm` is `foo-bar.json` or `foo_bar.json`
+    #[arg(long)]
+    schema_dir: Option<String>,
+
+    /// Seed for the RNG
+    #[arg(short, long, default_value = "0")]
+    seed: u64,
+
+    /// Work directory
+    #[arg(long, default_value = "./work_dir")]
+    work_dir: String,
+}
+
+pub fn cosmwasm_main(args: CosmWasmArgs) {
+    cosmwasm_fuzzer(&CosmWasmFuzzConfig {
+        target: args.target,
+        schema_dir: args.schema_dir,
+        work_dir: args.work_dir,
+        seed: args.seed,
+    });
+}
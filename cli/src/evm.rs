@@ -1,33 +1,26 @@
-use clap::Parser;
+use clap::{ArgAction, Parser};
+use tracing::{info, warn};
 use ethers::types::Transaction;
 use hex::{decode, encode};
-use ityfuzz::evm::config::{Config, FuzzerTypes, StorageFetchingMode};
-use ityfuzz::evm::contract_utils::{set_hash, ContractLoader};
-use ityfuzz::evm::host::PANIC_ON_BUG;
-use ityfuzz::evm::input::{ConciseEVMInput, EVMInput};
+use ityfuzz::evm::compare::{compare, load_trials};
+use ityfuzz::evm::config::{FuzzerTypes, StorageFetchingMode};
+use ityfuzz::evm::contract_utils::{detect_evm_version, set_hash, ContractLoader};
+use ityfuzz::evm::host::{evm_version_to_spec_id, PANIC_ON_BUG};
 use ityfuzz::evm::middlewares::middleware::Middleware;
 use ityfuzz::evm::onchain::endpoints::{Chain, OnChainConfig};
+use ityfuzz::evm::onchain::explorer::explorer_adapter_from_str;
 use ityfuzz::evm::onchain::flashloan::{DummyPriceOracle, Flashloan};
 use ityfuzz::evm::oracles::echidna::EchidnaOracle;
-use ityfuzz::evm::oracles::erc20::IERC20OracleFlashloan;
 use ityfuzz::evm::oracles::function::FunctionHarnessOracle;
-use ityfuzz::evm::oracles::selfdestruct::SelfdestructOracle;
-use ityfuzz::evm::oracles::typed_bug::TypedBugOracle;
-use ityfuzz::evm::oracles::v2_pair::PairBalanceOracle;
-use ityfuzz::evm::producers::erc20::ERC20Producer;
-use ityfuzz::evm::producers::pair::PairProducer;
-use ityfuzz::evm::types::{EVMAddress, EVMFuzzState, EVMU256};
-use ityfuzz::evm::vm::EVMState;
-use ityfuzz::fuzzers::evm_fuzzer::evm_fuzzer;
-use ityfuzz::oracle::{Oracle, Producer};
+use ityfuzz::evm::power_schedule::PowerSchedule;
+use ityfuzz::evm::types::EVMAddress;
+use ityfuzz::evm::watch::SourceWatcher;
 use ityfuzz::r#const;
-use ityfuzz::state::FuzzState;
+use ityfuzz::FuzzerBuilder;
 use serde::Deserialize;
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env;
-use std::rc::Rc;
 use std::str::FromStr;
 
 
@@ -79,9 +72,14 @@ struct TXResult {
 }
 
 /// CLI for ItyFuzz for EVM smart contracts
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None, args_override_self = true)]
 pub struct EvmArgs {
+    /// Configuration file, whose keys are the flags below (Default: ./ityfuzz.toml if present).
+    /// Flags given on the command line override it
+    #[arg(long)]
+    config: Option<String>,
+
     /// Glob pattern / address to find contracts
     #[arg(short, long)]
     target: String,
@@ -131,6 +129,10 @@ pub struct EvmArgs {
     #[arg(long)]
     onchain_chain_name: Option<String>,
 
+    /// Onchain Customize - Block explorer API type (etherscan, blockscout, routescan, tronscan)
+    #[arg(long, default_value = "etherscan")]
+    onchain_explorer_type: String,
+
     /// Onchain Etherscan API Key (Default: None)
     #[arg(long)]
     onchain_etherscan_api_key: Option<String>,
@@ -166,20 +168,32 @@ pub struct EvmArgs {
     #[arg(long, default_value = "false")]
     panic_on_bug: bool,
 
-    #[arg(long, default_value = "true")]
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
     selfdestruct_oracle: bool,
 
-    #[arg(long, default_value = "true")]
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
     echidna_oracle: bool,
 
     ///Enable oracle for detecting whether bug() / typed_bug() is called
-    #[arg(long, default_value = "true")]
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
     typed_bug_oracle: bool,
 
     /// Replay?
     #[arg(long)]
     replay_file: Option<String>,
 
+    /// Load a solution (in the replayable format) into the interactive debugger
+    #[arg(long)]
+    debug_file: Option<String>,
+
+    /// Minimize the corpus in this directory (e.g., work_dir/corpus) instead of fuzzing
+    #[arg(long)]
+    cmin_corpus: Option<String>,
+
+    /// Directory of the minimized corpus
+    #[arg(long)]
+    cmin_output: Option<String>,
+
     /// Path of work dir, saves corpus, logs, and other stuffs
     #[arg(long, default_value = "work_dir")]
     work_dir: String,
@@ -210,6 +224,462 @@ pub struct EvmArgs {
     #[arg(long, default_value = "Latest")]
     spec_id: String,
 
+    /// EVM version (hardfork) to fuzz with, e.g., london, shanghai, cancun. Overrides spec id.
+    /// If neither is specified, it is detected from solc metadata of the contracts when possible.
+    #[arg(long)]
+    evm_version: Option<String>,
+
+    /// Upgrade scenario - Proxy to upgrade (address or contract name)
+    #[arg(long)]
+    upgrade_proxy: Option<String>,
+
+    /// Upgrade scenario - New implementation (address or contract name)
+    #[arg(long)]
+    upgrade_implementation: Option<String>,
+
+    /// Upgrade scenario - Storage slot of the implementation (Default: EIP-1967 implementation slot)
+    #[arg(long)]
+    upgrade_slot: Option<String>,
+
+    /// Set EIP-7702 delegation designators on the fuzzer's EOA callers and
+    /// detect contracts whose EOA checks break under delegated EOAs
+    #[arg(long, default_value = "false")]
+    eip7702: bool,
+
+    /// Chain spec file (JSON) declaring chain id, base fee rules, enabled precompiles,
+    /// block gas limit and system contracts of the chain
+    #[arg(long)]
+    chain_spec: Option<String>,
+
+    /// Custom precompiles implemented in WASM, in the format of <address>=<path to wasm>,
+    /// separated by comma (requires wasm_plugins feature)
+    #[arg(long, value_delimiter = ',')]
+    wasm_precompile: Vec<String>,
+
+    /// zkSync Era compatibility: model system contracts and Era's address derivation
+    /// (enabled by default when chain type is zksync)
+    #[arg(long, default_value = "false")]
+    zksync: bool,
+
+    /// Enable oracle for detecting transactions exceeding the gas ceiling and functions
+    /// whose gas grows unboundedly with the state (DoS)
+    #[arg(long, default_value = "false")]
+    gas_bomb_oracle: bool,
+
+    /// Gas ceiling of a transaction for the gas bomb oracle, independent of the gas limit used
+    /// for fuzzing (Default: block gas limit of Ethereum mainnet)
+    #[arg(long, default_value = "30000000")]
+    gas_ceiling: u64,
+
+    /// Enable oracle for detecting swap / deposit functions with unbounded slippage and sandwich
+    /// attacks on victim transactions injected into the sequence
+    #[arg(long, default_value = "false")]
+    sandwich_oracle: bool,
+
+    /// Enable oracle for detecting replayed signatures (EIP-2612 permit, EIP-712 messages) that are
+    /// accepted twice, on another chain, or for an unintended spender. Signatures verified by
+    /// ecrecover are modeled as signed by the callers.
+    #[arg(long, default_value = "false")]
+    signature_oracle: bool,
+
+    /// Enable oracle for detecting authorization based on tx.origin. Transactions of callers can be
+    /// sent through an intermediary contract (e.g., a phishing contract) so that msg.sender differs.
+    #[arg(long, default_value = "false")]
+    tx_origin_oracle: bool,
+
+    /// Enable oracle for detecting reentrancy: storage of a contract modified by reentrant
+    /// transactions while its call is suspended. Functions protected by nonReentrant guards
+    /// (storage sentinels or transient locks) are not reported, reentrancy bypassing the guard
+    /// through other functions or contracts is
+    #[arg(long, default_value = "false")]
+    reentrancy_oracle: bool,
+
+    /// Enable oracle for detecting payouts steerable through the block context: ETH or tokens
+    /// paid to the caller by a contract that read block.coinbase, block.basefee or block.prevrandao
+    /// (e.g., prevrandao-based lotteries)
+    #[arg(long, default_value = "false")]
+    block_context_oracle: bool,
+
+    /// Enable oracle for detecting bugs of constructors: control handed to accounts without code,
+    /// callbacks into contracts under construction, and implementations left uninitialized
+    #[arg(long, default_value = "false")]
+    constructor_oracle: bool,
+
+    /// Enable oracle for detecting low-level calls (send, call) whose success flag is ignored,
+    /// confirmed by re-executing the transaction with the call failing
+    #[arg(long, default_value = "false")]
+    unchecked_call_oracle: bool,
+
+    /// Enable oracle for detecting refunds and keeper payouts computed from tx.gasprice that the
+    /// sender can inflate by raising the fee
+    #[arg(long, default_value = "false")]
+    fee_oracle: bool,
+
+    /// Enable oracle for detecting delegatecalls to attacker-controlled addresses (callers, or
+    /// addresses from calldata / storage). The takeover is confirmed by running a hijack implementation.
+    #[arg(long, default_value = "false")]
+    delegatecall_oracle: bool,
+
+    /// Enable oracle for detecting initializers (initialize, init, setUp, ...) that can be called by
+    /// an arbitrary sender to gain privileges (e.g., uninitialized proxies or implementations)
+    #[arg(long, default_value = "false")]
+    initializer_oracle: bool,
+
+    /// Enable oracle for detecting unsafe downcasts, i.e., arithmetic results silently truncated
+    /// (e.g., `uint128(a * b)`) before being stored
+    #[arg(long, default_value = "false")]
+    truncation_oracle: bool,
+
+    /// Enable oracle for detecting rounding errors, MUL / DIV are also computed in arbitrary
+    /// precision and stored values diverging from it by a unit or more (more than a single
+    /// rounding) are reported
+    #[arg(long, default_value = "false")]
+    rounding_oracle: bool,
+
+    /// Tolerated relative error of the rounding oracle (e.g., 0.01 for 1%)
+    #[arg(long, default_value = "0.01")]
+    rounding_epsilon: f64,
+
+    /// Differential fuzzing - Contract to fuzz (address or contract name)
+    #[arg(long)]
+    differential_target: Option<String>,
+
+    /// Differential fuzzing - Other version of the target (address or contract name), every
+    /// transaction to the target is also executed on it and divergences are reported
+    #[arg(long)]
+    differential_shadow: Option<String>,
+
+    /// Path to geth's `evm` tool. Transactions of violating sequences are re-executed with it and
+    /// divergences from ItyFuzz (e.g., caused by unlimited balances) are written next to the solution
+    #[arg(long)]
+    cross_evm: Option<String>,
+
+    /// Cross-check the sequences of the corpus in this directory (e.g., work_dir/corpus) with
+    /// --cross-evm instead of fuzzing, the report of each is written next to it
+    #[arg(long)]
+    cross_evm_corpus: Option<String>,
+
+    /// Path to anvil (onchain mode only). Violating sequences are replayed on a fresh fork at the
+    /// block fuzzed and only reported if they reproduce, the others are written to
+    /// work_dir/unconfirmed
+    #[arg(long)]
+    shadow_fork: Option<String>,
+
+    /// Path to the solvency invariant (JSON) declaring the contracts of the protocol, the tokens
+    /// counted as its assets and the view functions reporting its liabilities. Sequences after
+    /// which assets are lower than liabilities are reported
+    #[arg(long)]
+    solvency_config: Option<String>,
+
+    /// Path to the invariants (JSON) checked across snapshots of a sequence: view functions read
+    /// every few transactions that must not decrease / increase or exceed a bound (e.g.,
+    /// exchangeRate never decreases, totalDebt stays below the cap). Drifts are reported
+    #[arg(long)]
+    epoch_invariants: Option<String>,
+
+    /// Path to the invariants (JSON) over the events emitted by a sequence: sums of event fields
+    /// matching balance changes (e.g., deposits minus withdrawals equal the vault balance delta)
+    /// and orderings of events (e.g., each Transfer follows an Approval of its owner)
+    #[arg(long)]
+    event_invariants: Option<String>,
+
+    /// View functions reporting the ETH accounted for by a contract, as <contract>:<function>
+    /// separated by comma, e.g., `Vault:totalDeposits()` or `Vault:credits(address)` (summed over
+    /// the callers). The ETH held by these contracts is tracked, and sequences after which they
+    /// account for more ETH than they hold are reported
+    #[arg(long, value_delimiter = ',')]
+    eth_accounting: Vec<String>,
+
+    /// Path to the liquidation scenario (JSON) of a lending market declaring its actors (e.g.,
+    /// borrower, liquidator, attacker), the prices moved within bounds and the health / bad debt
+    /// view functions. Liquidations creating bad debt or hitting healthy positions are reported
+    #[arg(long)]
+    lending_config: Option<String>,
+
+    /// Custom oracles implemented in WASM, paths to the modules separated by comma (requires
+    /// wasm_plugins feature). See src/evm/oracles/plugin.rs for the interface
+    #[arg(long, value_delimiter = ',')]
+    oracle_plugin: Vec<String>,
+
+    /// Middleware hooks implemented in Rhai, paths to the scripts separated by comma (requires
+    /// scripting feature). See src/evm/middlewares/script.rs for the interface
+    #[arg(long, value_delimiter = ',')]
+    middleware_script: Vec<String>,
+
+    /// Listen for runtime control commands (pause, resume, inject_seed, focus, dump, enable_oracles,
+    /// disable_oracles, freeze, unfreeze) on this address
+    /// (e.g., 127.0.0.1:8090)
+    #[arg(long)]
+    control: Option<String>,
+
+    /// Oracles whose violations are not reported, by name separated by comma (e.g.,
+    /// erc20,reentrancy). They can be toggled at runtime on the control socket
+    #[arg(long, value_delimiter = ',')]
+    disable_oracles: Vec<String>,
+
+    /// Contracts (addresses or names separated by comma) trusted and never called directly by the
+    /// fuzzer (e.g., WETH), they are still called by the other contracts
+    #[arg(long, value_delimiter = ',')]
+    freeze: Vec<String>,
+
+    /// Power schedule assigning energy to inputs (default, fast, explore, exploit, rare)
+    #[arg(long, default_value = "default")]
+    power_schedule: String,
+
+    /// Give more energy to inputs executed on states built by longer transaction sequences
+    #[arg(long, default_value = "false")]
+    sequence_energy: bool,
+
+    /// Boost the energy of inputs hitting globally rare branches (with any power schedule)
+    #[arg(long, default_value = "false")]
+    rare_boost: bool,
+
+    /// Trim corpus entries (shorten the sequence leading to their state and their dynamic args)
+    /// while preserving their coverage
+    #[arg(long, default_value = "false")]
+    trim: bool,
+
+    /// Favor covering and mutating the code changed since this git revision (e.g., origin/main),
+    /// requires source maps (see --base-path)
+    #[arg(long)]
+    focus_diff: Option<String>,
+
+    /// Bounds on how far block.timestamp, block.number (ahead of the fork point) and basefee
+    /// may deviate from the fork point, e.g. "timestamp=604800,number=50400,basefee=100000000000"
+    #[arg(long)]
+    env_bounds: Option<String>,
+
+    /// Keep environment values beyond --env-bounds, and classify findings depending on them
+    /// separately instead of clamping them
+    #[arg(long, default_value = "false")]
+    env_bounds_report: bool,
+
+    /// Bounds (in wei) of the gas price and priority fee chosen by the mutator, "min:max"
+    /// (0 to 1000 gwei by default)
+    #[arg(long)]
+    gas_price_bounds: Option<String>,
+
+    /// Screen pending transactions to the targets: each one starts fuzzed sequences, and an
+    /// alert is raised when a pending transaction enables a violation (requires --onchain)
+    #[arg(long, default_value = "false")]
+    mempool: bool,
+
+    /// Bundle config file (key, builders, fees), profitable sequences found on-chain are
+    /// exported as signed Flashbots bundles for whitehat execution
+    #[arg(long)]
+    bundle_config: Option<String>,
+
+    /// Register the fuzzer's contract callers as ERC-777 recipient / sender hooks and ERC-1363
+    /// receivers, so that reentrant sequences are injected during token transfers
+    #[arg(long, default_value = "false")]
+    token_callbacks: bool,
+
+    /// Make the fuzzer's contract callers ERC-721 / ERC-1155 receivers, so that reentrant
+    /// sequences are injected during safeMint / safeTransferFrom
+    #[arg(long, default_value = "false")]
+    nft_callbacks: bool,
+
+    /// Mock addresses without code called by the targets (e.g., dependencies not deployed
+    /// offline) instead of reverting, the return data of such calls is provided by the fuzzer
+    #[arg(long, default_value = "false")]
+    mock_missing: bool,
+
+    /// Move the answers of Chainlink feeds called by the targets within bounds, in the format of
+    /// deviation=<percent>,heartbeat=<seconds> (deviation threshold and heartbeat of the feeds),
+    /// or `compromised` to move them freely
+    #[arg(long)]
+    chainlink: Option<String>,
+
+    /// Report the capital and number of blocks needed by the Uniswap TWAP manipulations
+    /// findings rely on
+    #[arg(long, default_value = "false")]
+    twap_cost: bool,
+
+    /// Downgrade findings relying on TWAP manipulations that need more capital than this
+    /// multiple of the pool reserve (implies --twap-cost)
+    #[arg(long)]
+    twap_max_capital: Option<f64>,
+
+    /// Credit coverage discovered on paths that end up reverting, `--revert-coverage false` rolls
+    /// back the coverage of reverted executions along with their state
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    revert_coverage: bool,
+
+    /// Pick msg.value of payable calls with strategies (zero, dust, values accepted before, the
+    /// whole balance, values compared with msg.value) weighted per function by how often they
+    /// don't revert, instead of random values (`--payable-values false`)
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    payable_values: bool,
+
+    /// Adapt the length of the sequences extended for each function to the lengths yielding new
+    /// coverage (re-adapted whenever coverage plateaus), instead of picking states regardless of
+    /// the sequence leading to them (`--adaptive-length false`)
+    #[arg(long, action = ArgAction::Set, default_value_t = true)]
+    adaptive_length: bool,
+
+    /// Summarize each finding (sequence, broken invariant, suspected root cause) with an LLM,
+    /// reading the API key from ITYFUZZ_LLM_API_KEY (requires llm_triage feature). The oracle
+    /// output, trace and source of the contract are sent to the endpoint
+    #[arg(long, default_value = "false")]
+    llm_triage: bool,
+
+    /// Chat completions endpoint (OpenAI compatible) used by --llm-triage
+    #[arg(long, default_value = "https://api.openai.com/v1/chat/completions")]
+    llm_endpoint: String,
+
+    /// Model used by --llm-triage
+    #[arg(long, default_value = "gpt-4o-mini")]
+    llm_model: String,
+
+    /// Where the corpus and solutions are stored: `disk` (files in the work dir), `sqlite[:<path>]`
+    /// (a database, [WORKDIR]/corpus.db by default, requires sqlite_storage feature) or
+    /// `s3://<bucket>/<prefix>` (an S3-compatible bucket, credentials read from AWS_ACCESS_KEY_ID
+    /// and AWS_SECRET_ACCESS_KEY, requires s3_storage feature)
+    #[arg(long, default_value = "disk")]
+    storage: String,
+
+    /// Endpoint of the S3-compatible service (e.g., MinIO) used by --storage s3://, AWS if not set
+    #[arg(long)]
+    storage_endpoint: Option<String>,
+
+    /// Watch the sources of this Foundry / Hardhat project: once they change, rebuild it and
+    /// restart fuzzing on the new artifacts, re-running the existing corpus first (implies
+    /// --run-forever)
+    #[arg(long)]
+    watch: Option<String>,
+
+    /// Command rebuilding the project in watch mode (Default: forge build / npx hardhat compile)
+    #[arg(long)]
+    watch_build: Option<String>,
+
+    /// Soak mode, for weeks-long continuous fuzzing (implies --run-forever): settings like
+    /// `segment=1d,digest=1h,cull=6h,keep=30,log=10M,webhook=<url>`. Findings and statistics are
+    /// archived per segment, digests are written to soak.log (and posted to the webhook, e.g., a
+    /// Slack incoming webhook) and the corpus is minimized periodically. Use `--soak ""` for the
+    /// defaults
+    #[arg(long)]
+    soak: Option<String>,
+
+    /// Abort transactions executing more than this many instructions (e.g., huge loops in forked
+    /// contracts), aborted executions are discarded
+    #[arg(long)]
+    step_limit: Option<u64>,
+
+    /// Abort transactions running for longer than this many milliseconds
+    #[arg(long)]
+    exec_timeout: Option<u64>,
+
+    /// Memory cap in MB: least recently used on-chain code / storage is evicted beyond a quarter of
+    /// it, and infant states are pruned while the process uses more
+    #[arg(long)]
+    memory_cap: Option<usize>,
+
+    /// Fuzz groups of related targets in this campaign, allocating energy across groups by
+    /// coverage progress: "<group>=<address or contract name>,...;<group>=...", or "auto" for one
+    /// group per target. Addresses of groups are added to the targets
+    #[arg(long)]
+    target_groups: Option<String>,
+
+    /// Key coverage by code hash instead of address in coverage reports, so that contracts sharing
+    /// runtime bytecode (clones, proxies to the same implementation) are reported once
+    #[arg(long, default_value = "false")]
+    coverage_by_code: bool,
+
+    /// With --coverage-by-code, also break the coverage down per address
+    #[arg(long, default_value = "false")]
+    coverage_per_address: bool,
+
+    /// Import Echidna reproducers / corpus entries or Medusa call sequences matched by this glob
+    /// (e.g., "echidna-corpus/coverage/*.txt" or "medusa-corpus/call_sequences/**/*.json") as seeds
+    #[arg(long)]
+    import_corpus: Option<String>,
+
+    /// Only fuzz functions whose whole name matches this regex (e.g., 'deposit|withdraw.*')
+    #[arg(long)]
+    only_fuzz: Option<String>,
+
+    /// Share of generated transactions per function: "<regex>=<weight>,..." or a JSON file
+    /// {"<regex>": weight}. Functions get energy proportional to their weight (Default: 1)
+    #[arg(long)]
+    function_weights: Option<String>,
+
+    /// Path to the pre-state of the senders (JSON): token approvals and positions (deposits,
+    /// stakes, ...) established for each sender before the campaign, so that functions requiring
+    /// them are reachable without replaying the onboarding in every sequence
+    #[arg(long)]
+    pre_state: Option<String>,
+
+    /// Path to the bridge harness (JSON) declaring the messengers (e.g., LayerZero endpoint,
+    /// relayer) delivering messages to the receiver, the bridged token and the escrow modeling the
+    /// remote chain. Messages are forged from the messengers, unbacked, mismatching or replayed
+    /// mints are reported
+    #[arg(long)]
+    bridge_config: Option<String>,
+
+    /// Path to known addresses (JSON) per chain: routers, flashloan providers, wrapped native
+    /// token, stablecoins and oracles, extending or overriding the built-in registry (see
+    /// src/evm/known_addresses.rs)
+    #[arg(long)]
+    known_addresses: Option<String>,
+
+}
+
+/// CLI for debugging a solution of ItyFuzz for EVM smart contracts
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct DebugArgs {
+    /// Solution to debug (e.g., work_dir/vulnerabilities/0_replayable)
+    solution: String,
+
+    #[command(flatten)]
+    evm: EvmArgs,
+}
+
+pub fn debug_main(args: DebugArgs) {
+    let mut evm_args = args.evm;
+    evm_args.debug_file = Some(args.solution);
+    evm_main(evm_args);
+}
+
+#[derive(Parser, Debug)]
+pub struct CminArgs {
+    /// Corpus to minimize (e.g., work_dir/corpus)
+    corpus: String,
+
+    /// Directory of the minimized corpus
+    #[arg(long, default_value = "corpus_min")]
+    output: String,
+
+    #[command(flatten)]
+    evm: EvmArgs,
+}
+
+pub fn cmin_main(args: CminArgs) {
+    let mut evm_args = args.evm;
+    evm_args.cmin_corpus = Some(args.corpus);
+    evm_args.cmin_output = Some(args.output);
+    evm_main(evm_args);
+}
+
+#[derive(Parser, Debug)]
+pub struct CompareArgs {
+    /// Work directory of campaign A, or a directory of work directories (one per trial)
+    a: String,
+
+    /// Work directory of campaign B, or a directory of work directories (one per trial)
+    b: String,
+
+    /// Write the comparison as JSON to this file
+    #[arg(long)]
+    output: Option<String>,
+}
+
+pub fn compare_main(args: CompareArgs) {
+    let comparison = compare(&load_trials(&args.a), &load_trials(&args.b));
+    ityfuzz::status!("{}", comparison.report());
+    if let Some(output) = args.output {
+        std::fs::write(&output, serde_json::to_string_pretty(&comparison).unwrap()).expect("failed to write comparison");
+    }
 }
 
 enum EVMTargetType {
@@ -218,6 +688,23 @@ enum EVMTargetType {
 }
 
 pub fn evm_main(args: EvmArgs) {
+    match args.watch.clone() {
+        Some(project) => {
+            let mut watcher = SourceWatcher::new(&project, args.watch_build.clone());
+            loop {
+                while !watcher.rebuild() {
+                    watcher.wait_for_change();
+                }
+                // returns once the sources change
+                evm_campaign(args.clone());
+                watcher.changed();
+            }
+        }
+        None => evm_campaign(args),
+    }
+}
+
+fn evm_campaign(args: EvmArgs) {
     ityfuzz::telemetry::report_campaign(args.onchain, args.target.clone());
     let target_type: EVMTargetType = match args.target_type {
         Some(v) => match v.as_str() {
@@ -236,6 +723,8 @@ pub fn evm_main(args: EvmArgs) {
         }
     };
 
+    let zksync = args.zksync || args.chain_type.as_ref().map(|x| x.to_lowercase()) == Some("zksync".to_string());
+
     let mut onchain = if args.onchain {
         match args.chain_type {
             Some(chain_str) => {
@@ -259,6 +748,12 @@ pub fn evm_main(args: EvmArgs) {
         None
     };
 
+    if let Some(onchain) = onchain.as_mut() {
+        onchain.set_explorer(
+            explorer_adapter_from_str(&args.onchain_explorer_type).expect("unknown explorer type"),
+        );
+    }
+
     let onchain_clone = onchain.clone();
 
     if onchain.is_some() && args.onchain_etherscan_api_key.is_some() {
@@ -268,12 +763,6 @@ pub fn evm_main(args: EvmArgs) {
             .etherscan_api_key
             .push(args.onchain_etherscan_api_key.unwrap());
     }
-    let pair_producer = Rc::new(RefCell::new(PairProducer::new()));
-    let erc20_producer = Rc::new(RefCell::new(ERC20Producer::new()));
-
-    let mut flashloan_oracle = Rc::new(RefCell::new({
-        IERC20OracleFlashloan::new(pair_producer.clone(), erc20_producer.clone())
-    }));
 
     // let harness_code = "oracle_harness()";
     // let mut harness_hash: [u8; 4] = [0; 4];
@@ -281,73 +770,8 @@ pub fn evm_main(args: EvmArgs) {
     // let mut function_oracle =
     //     FunctionHarnessOracle::new_no_condition(EVMAddress::zero(), Vec::from(harness_hash));
 
-    let mut oracles: Vec<
-        Rc<
-            RefCell<
-                dyn Oracle<
-                    EVMState,
-                    EVMAddress,
-                    _,
-                    _,
-                    EVMAddress,
-                    EVMU256,
-                    Vec<u8>,
-                    EVMInput,
-                    EVMFuzzState,
-                    ConciseEVMInput
-                >,
-            >,
-        >,
-    > = vec![];
-
-    let mut producers: Vec<
-        Rc<
-            RefCell<
-                dyn Producer<
-                    EVMState,
-                    EVMAddress,
-                    _,
-                    _,
-                    EVMAddress,
-                    EVMU256,
-                    Vec<u8>,
-                    EVMInput,
-                    EVMFuzzState,
-                    ConciseEVMInput
-                >,
-            >,
-        >,
-    > = vec![];
-
-    if args.pair_oracle {
-        oracles.push(Rc::new(RefCell::new(PairBalanceOracle::new(
-            pair_producer.clone(),
-        ))));
-    }
-
-    if args.ierc20_oracle {
-        oracles.push(flashloan_oracle.clone());
-    }
-
-    if args.selfdestruct_oracle {
-        oracles.push(Rc::new(RefCell::new(SelfdestructOracle::new())));
-    }
-
-    if args.typed_bug_oracle {
-        oracles.push(Rc::new(RefCell::new(TypedBugOracle::new())));
-
-    }
-
-    if args.ierc20_oracle || args.pair_oracle {
-        producers.push(pair_producer);
-    }
-
-    if args.ierc20_oracle {
-        producers.push(erc20_producer);
-    }
-
     let is_onchain = onchain.is_some();
-    let mut state: EVMFuzzState = FuzzState::new(args.seed);
+    let mut builder = FuzzerBuilder::new(args.seed);
 
     let mut proxy_deploy_codes: Vec<String> = vec![];
 
@@ -387,101 +811,230 @@ pub fn evm_main(args: EvmArgs) {
 
     let constructor_args_map = parse_constructor_args_string(args.constructor_args);
 
-    let config = Config {
-        fuzzer_type: FuzzerTypes::from_str(args.fuzzer_type.as_str()).expect("unknown fuzzer"),
-        contract_loader: match target_type {
-            EVMTargetType::Glob => {
-                ContractLoader::from_glob(
-                    args.target.as_str(),
-                    &mut state,
-                    &proxy_deploy_codes,
-                    &constructor_args_map,
-                )
-            }
-            EVMTargetType::Address => {
-                if onchain.is_none() {
-                    panic!("Onchain is required for address target type");
-                }
-                let mut args_target = args.target.clone();
-
-                if args.ierc20_oracle || args.flashloan {
-                    const ETH_ADDRESS: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
-                    const BSC_ADDRESS: &str = "0x10ed43c718714eb63d5aa57b78b54704e256024e";
-                    if "bsc" == onchain.as_ref().unwrap().chain_name {
-                        if args_target.find(BSC_ADDRESS) == None {
-                            args_target.push_str(",");
-                            args_target.push_str(BSC_ADDRESS);
+    let spec_id = match args.evm_version {
+        Some(ref v) => evm_version_to_spec_id(v)
+            .expect(format!("unknown evm version {}", v).as_str())
+            .to_string(),
+        None => match target_type {
+            EVMTargetType::Glob if args.spec_id == "Latest" => {
+                match detect_evm_version(args.target.as_str()) {
+                    Some(v) => match evm_version_to_spec_id(&v) {
+                        Some(spec_id) => {
+                            info!("Detected EVM version {} from solc metadata", v);
+                            spec_id.to_string()
                         }
-                    } else if "eth" == onchain.as_ref().unwrap().chain_name {
-                        if args_target.find(ETH_ADDRESS) == None {
-                            args_target.push_str(",");
-                            args_target.push_str(ETH_ADDRESS);
+                        None => {
+                            warn!("Unknown EVM version {} in solc metadata, using {}", v, args.spec_id);
+                            args.spec_id.clone()
                         }
-                    }
+                    },
+                    None => args.spec_id.clone(),
                 }
-                let addresses: Vec<EVMAddress> = args_target
-                    .split(",")
-                    .map(|s| EVMAddress::from_str(s).unwrap())
-                    .collect();
-                ContractLoader::from_address(
-                    &mut onchain.as_mut().unwrap(),
-                    HashSet::from_iter(addresses),
-                )
             }
+            _ => args.spec_id.clone(),
         },
-        onchain,
-        concolic: args.concolic,
-        oracle: oracles,
-        producers,
-        flashloan: args.flashloan,
-        price_oracle: match args.flashloan_price_oracle.as_str() {
-            "onchain" => {
-                Box::new(onchain_clone.expect("onchain unavailable but used for flashloan"))
+    };
+
+    let contract_loader = match target_type {
+        EVMTargetType::Glob => {
+            ContractLoader::from_glob(
+                args.target.as_str(),
+                builder.state_mut(),
+                &proxy_deploy_codes,
+                &constructor_args_map,
+            )
+        }
+        EVMTargetType::Address => {
+            if onchain.is_none() {
+                panic!("Onchain is required for address target type");
             }
-            _ => Box::new(DummyPriceOracle {}),
-        },
-        onchain_storage_fetching: if is_onchain {
-            Some(
-                StorageFetchingMode::from_str(args.onchain_storage_fetching.as_str())
-                    .expect("unknown storage fetching mode"),
+            let mut args_target = args.target.clone();
+            // addresses of target groups are targets as well
+            if let Some(groups) = &args.target_groups {
+                for member in groups
+                    .split(';')
+                    .filter_map(|group| group.split_once('=').map(|(_, members)| members))
+                    .flat_map(|members| members.split(','))
+                    .map(|member| member.trim())
+                    .filter(|member| member.starts_with("0x") && !args_target.contains(*member))
+                {
+                    args_target.push_str(",");
+                    args_target.push_str(member);
+                }
+            }
+
+            if args.ierc20_oracle || args.flashloan {
+                const ETH_ADDRESS: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+                const BSC_ADDRESS: &str = "0x10ed43c718714eb63d5aa57b78b54704e256024e";
+                if "bsc" == onchain.as_ref().unwrap().chain_name {
+                    if args_target.find(BSC_ADDRESS) == None {
+                        args_target.push_str(",");
+                        args_target.push_str(BSC_ADDRESS);
+                    }
+                } else if "eth" == onchain.as_ref().unwrap().chain_name {
+                    if args_target.find(ETH_ADDRESS) == None {
+                        args_target.push_str(",");
+                        args_target.push_str(ETH_ADDRESS);
+                    }
+                }
+            }
+            let addresses: Vec<EVMAddress> = args_target
+                .split(",")
+                .map(|s| EVMAddress::from_str(s).unwrap())
+                .collect();
+            ContractLoader::from_address(
+                &mut onchain.as_mut().unwrap(),
+                HashSet::from_iter(addresses),
             )
-        } else {
-            None
-        },
-        replay_file: args.replay_file,
-        flashloan_oracle,
-        selfdestruct_oracle: args.selfdestruct_oracle,
-        work_dir: args.work_dir,
-        write_relationship: args.write_relationship,
-        run_forever: args.run_forever,
-        sha3_bypass: args.sha3_bypass,
-        base_path: args.base_path,
-        echidna_oracle: args.echidna_oracle,
-        panic_on_bug: args.panic_on_bug,
-        spec_id: args.spec_id,
+        }
     };
 
-    match config.fuzzer_type {
-        FuzzerTypes::CMP => evm_fuzzer(config, &mut state),
-        // FuzzerTypes::BASIC => basic_fuzzer(config)
-        _ => {}
+    builder
+        .configure(|config| {
+            config.fuzzer_type = FuzzerTypes::from_str(args.fuzzer_type.as_str()).expect("unknown fuzzer");
+            config.contract_loader = contract_loader;
+            config.onchain = onchain;
+            config.concolic = args.concolic;
+            config.flashloan = args.flashloan;
+            config.price_oracle = match args.flashloan_price_oracle.as_str() {
+                "onchain" => {
+                    Box::new(onchain_clone.expect("onchain unavailable but used for flashloan"))
+                }
+                _ => Box::new(DummyPriceOracle {}),
+            };
+            config.onchain_storage_fetching = if is_onchain {
+                Some(
+                    StorageFetchingMode::from_str(args.onchain_storage_fetching.as_str())
+                        .expect("unknown storage fetching mode"),
+                )
+            } else {
+                None
+            };
+            config.replay_file = args.replay_file;
+            config.debug_file = args.debug_file;
+            config.selfdestruct_oracle = args.selfdestruct_oracle;
+            config.pair_oracle = args.pair_oracle;
+            config.ierc20_oracle = args.ierc20_oracle;
+            config.typed_bug_oracle = args.typed_bug_oracle;
+            config.work_dir = args.work_dir;
+            config.write_relationship = args.write_relationship;
+            config.run_forever = args.run_forever || args.watch.is_some() || args.soak.is_some();
+            config.sha3_bypass = args.sha3_bypass;
+            config.base_path = args.base_path;
+            config.echidna_oracle = args.echidna_oracle;
+            config.panic_on_bug = args.panic_on_bug;
+            config.spec_id = spec_id;
+            config.upgrade_proxy = args.upgrade_proxy;
+            config.upgrade_implementation = args.upgrade_implementation;
+            config.upgrade_slot = args.upgrade_slot;
+            config.eip7702 = args.eip7702;
+            config.chain_spec = args.chain_spec;
+            config.wasm_precompiles = args.wasm_precompile;
+            config.zksync = zksync;
+            config.gas_ceiling = if args.gas_bomb_oracle {
+                Some(args.gas_ceiling)
+            } else {
+                None
+            };
+            config.sandwich_oracle = args.sandwich_oracle;
+            config.signature_oracle = args.signature_oracle;
+            config.tx_origin_oracle = args.tx_origin_oracle;
+            config.reentrancy_oracle = args.reentrancy_oracle;
+            config.block_context_oracle = args.block_context_oracle;
+            config.constructor_oracle = args.constructor_oracle;
+            config.unchecked_call_oracle = args.unchecked_call_oracle;
+            config.fee_oracle = args.fee_oracle;
+            config.delegatecall_oracle = args.delegatecall_oracle;
+            config.initializer_oracle = args.initializer_oracle;
+            config.truncation_oracle = args.truncation_oracle;
+            config.differential_target = args.differential_target;
+            config.differential_shadow = args.differential_shadow;
+            config.cross_evm = args.cross_evm;
+            config.cross_evm_corpus = args.cross_evm_corpus;
+            config.shadow_fork = args.shadow_fork;
+            config.solvency_config = args.solvency_config;
+            config.epoch_invariants = args.epoch_invariants;
+            config.event_invariants = args.event_invariants;
+            config.eth_accounting = args.eth_accounting;
+            config.lending_config = args.lending_config;
+            config.oracle_plugins = args.oracle_plugin;
+            config.middleware_scripts = args.middleware_script;
+            config.control = args.control;
+            config.disabled_oracles = args.disable_oracles;
+            config.frozen_contracts = args.freeze;
+            config.power_schedule = PowerSchedule::from_str(args.power_schedule.as_str()).expect("unknown power schedule");
+            config.sequence_energy = args.sequence_energy;
+            config.rare_boost = args.rare_boost;
+            config.cmin_corpus = args.cmin_corpus;
+            config.cmin_output = args.cmin_output;
+            config.trim = args.trim;
+            config.focus_diff = args.focus_diff;
+            config.env_bounds = args.env_bounds;
+            config.env_bounds_report = args.env_bounds_report;
+            config.gas_price_bounds = args.gas_price_bounds;
+            config.mempool = args.mempool;
+            config.bundle_config = args.bundle_config;
+            config.token_callbacks = args.token_callbacks;
+            config.nft_callbacks = args.nft_callbacks;
+            config.mock_missing = args.mock_missing;
+            config.chainlink = args.chainlink;
+            config.twap_cost = args.twap_cost || args.twap_max_capital.is_some();
+            config.twap_max_capital = args.twap_max_capital;
+            config.revert_coverage = args.revert_coverage;
+            config.payable_values = args.payable_values;
+            config.adaptive_length = args.adaptive_length;
+            config.llm_triage = args.llm_triage;
+            config.llm_endpoint = args.llm_endpoint.clone();
+            config.llm_model = args.llm_model.clone();
+            config.storage = args.storage.clone();
+            config.storage_endpoint = args.storage_endpoint.clone();
+            config.watch = args.watch.clone();
+            config.soak = args.soak.clone();
+            config.step_limit = args.step_limit;
+            config.exec_timeout = args.exec_timeout;
+            config.memory_cap = args.memory_cap.map(|mb| mb * 1024 * 1024);
+            config.target_groups = args.target_groups.clone();
+            config.coverage_by_code = args.coverage_by_code;
+            config.coverage_per_address = args.coverage_per_address;
+            config.import_corpus = args.import_corpus.clone();
+            config.only_fuzz = args.only_fuzz.clone();
+            config.function_weights = args.function_weights.clone();
+            config.pre_state = args.pre_state.clone();
+            config.bridge_config = args.bridge_config.clone();
+            config.known_addresses = args.known_addresses.clone();
+            config.rounding_epsilon = if args.rounding_oracle {
+                Some(args.rounding_epsilon)
+            } else {
+                None
+            };
+        })
+        .run();
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_coverage_flag() {
+        let args = EvmArgs::try_parse_from(["evm", "-t", "a"]).unwrap();
+        assert!(args.revert_coverage);
+        let args = EvmArgs::try_parse_from(["evm", "-t", "a", "--revert-coverage", "false"]).unwrap();
+        assert!(!args.revert_coverage);
+        let args = EvmArgs::try_parse_from(["evm", "-t", "a", "--revert-coverage=false"]).unwrap();
+        assert!(!args.revert_coverage);
+    }
+
+    #[test]
+    fn test_payable_values_flag() {
+        assert!(EvmArgs::try_parse_from(["evm", "-t", "a"]).unwrap().payable_values);
+        let args = EvmArgs::try_parse_from(["evm", "-t", "a", "--payable-values", "false"]).unwrap();
+        assert!(!args.payable_values);
+    }
+
+    #[test]
+    fn test_adaptive_length_flag() {
+        assert!(EvmArgs::try_parse_from(["evm", "-t", "a"]).unwrap().adaptive_length);
+        let args = EvmArgs::try_parse_from(["evm", "-t", "a", "--adaptive-length", "false"]).unwrap();
+        assert!(!args.adaptive_length);
     }
-    //
-    //     Some(v) => {
-    //         match v.as_str() {
-    //             "cmp" => {
-    //                 cmp_fuzzer(&String::from(args.target), args.target_contract);
-    //             }
-    //             "df" => {
-    //                 df_fuzzer(&String::from(args.target), args.target_contract);
-    //             }
-    //             _ => {
-    //                 println!("Fuzzer type not supported");
-    //             }
-    //         }
-    //     },
-    //     _ => {
-    //         df_fuzzer(&String::from(args.target), args.target_contract);
-    //     }
-    // }
 }
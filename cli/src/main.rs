@@ -1,7 +1,13 @@
+#[cfg(feature = "cairo_support")]
+mod cairo;
+mod config_file;
+#[cfg(feature = "cosmwasm_support")]
+mod cosmwasm;
 mod evm;
 mod r#move;
 
 use clap::Parser;
+use tracing::info;
 use ethers::types::Transaction;
 use hex::{decode, encode};
 use ityfuzz::evm::config::{Config, FuzzerTypes, StorageFetchingMode};
@@ -32,8 +38,12 @@ use std::collections::HashSet;
 use std::env;
 use std::rc::Rc;
 use std::str::FromStr;
-use crate::evm::{evm_main, EvmArgs};
+use crate::evm::{cmin_main, compare_main, debug_main, evm_main, CminArgs, CompareArgs, DebugArgs, EvmArgs};
 use crate::r#move::{move_main, MoveArgs};
+#[cfg(feature = "cairo_support")]
+use crate::cairo::{cairo_main, CairoArgs};
+#[cfg(feature = "cosmwasm_support")]
+use crate::cosmwasm::{cosmwasm_main, CosmWasmArgs};
 use clap::Subcommand;
 
 pub fn init_sentry() {
@@ -43,7 +53,7 @@ pub fn init_sentry() {
     }));
     if let Ok(value) = env::var("NO_TELEMETRY") {
         if value == "1" {
-            println!("Telemetry is disabled.");
+            info!("Telemetry is disabled.");
             unsafe {
                 ityfuzz::telemetry::TELEMETRY_ENABLED = false;
             }
@@ -56,17 +66,37 @@ pub fn init_sentry() {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Only log warnings and errors, and don't print status output
+    #[arg(long, global = true, default_value = "false")]
+    quiet: bool,
+
+    /// Log as JSON lines (on stderr, status output stays on stdout)
+    #[arg(long, global = true, default_value = "false")]
+    log_json: bool,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     EVM(EvmArgs),
-    MOVE(MoveArgs)
+    MOVE(MoveArgs),
+    #[cfg(feature = "cairo_support")]
+    CAIRO(CairoArgs),
+    #[cfg(feature = "cosmwasm_support")]
+    COSMWASM(CosmWasmArgs),
+    DEBUG(DebugArgs),
+    CMIN(CminArgs),
+    COMPARE(CompareArgs),
 }
 
 fn main() {
+    let (args, config_path) = config_file::expand_args(env::args().collect()).unwrap_or_else(|e| e.exit());
+    let args = Cli::parse_from(args);
+    ityfuzz::logger::init_logger(args.quiet, args.log_json);
+    if let Some(path) = config_path {
+        info!("Loaded configuration from {}", path);
+    }
     init_sentry();
-    let args = Cli::parse();
     match args.command {
         Commands::EVM(args) => {
             evm_main(args);
@@ -74,6 +104,23 @@ fn main() {
         Commands::MOVE(args) => {
             move_main(args);
         }
+        #[cfg(feature = "cairo_support")]
+        Commands::CAIRO(args) => {
+            cairo_main(args);
+        }
+        #[cfg(feature = "cosmwasm_support")]
+        Commands::COSMWASM(args) => {
+            cosmwasm_main(args);
+        }
+        Commands::DEBUG(args) => {
+            debug_main(args);
+        }
+        Commands::CMIN(args) => {
+            cmin_main(args);
+        }
+        Commands::COMPARE(args) => {
+            compare_main(args);
+        }
     }
 
 }
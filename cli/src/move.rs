@@ -23,6 +23,15 @@ pub struct MoveArgs {
     /// Seed for the RNG
     #[arg(short, long, default_value = "0")]
     seed: u64,
+
+    /// Onchain - Aptos fullnode REST API (e.g., https://fullnode.mainnet.aptoslabs.com/v1),
+    /// target should be addresses separated by comma when set
+    #[arg(long)]
+    onchain_url: Option<String>,
+
+    /// Onchain - Ledger version to fork from (Default: latest)
+    #[arg(long)]
+    onchain_version: Option<u64>,
 }
 
 pub fn move_main(args: MoveArgs) {
@@ -30,6 +39,8 @@ pub fn move_main(args: MoveArgs) {
         target: args.target,
         work_dir: "./work_dir".to_string(),
         seed: args.seed,
+        onchain_url: args.onchain_url,
+        onchain_version: args.onchain_version,
     });
 
 }
@@ -0,0 +1,303 @@
+/// Python bindings of ItyFuzz (EVM), to drive campaigns from notebooks and existing tooling
+///
+/// ```python
+/// import ityfuzz
+///
+/// def no_large_mint(tx):
+///     # tx is the document described in src/evm/oracles/plugin.rs
+///     return [{"id": 1, "message": "minted more than 1e24"}
+///             for b in tx["balances"] if int(b["post"], 16) - int(b["pre"], 16) > 10**24]
+///
+/// campaign = ityfuzz.Campaign("build/*", work_dir="work_dir")
+/// campaign.enable("typed_bug")
+/// campaign.add_oracle("no_large_mint", no_large_mint)
+/// for finding in campaign.run(timeout=600):
+///     print(finding["id"], finding["txs"])
+/// ```
+///
+/// Campaigns run in a child process, as ItyFuzz keeps the state of a campaign in globals.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use ityfuzz::evm::input::{ConciseEVMInput, EVMInput};
+use ityfuzz::evm::middlewares::call_tracer::CallTracer;
+use ityfuzz::evm::oracles::plugin::{parse_violations, report_violations, tx_document};
+use ityfuzz::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use ityfuzz::evm::vm::EVMState;
+use ityfuzz::oracle::{Oracle, OracleCtx};
+use ityfuzz::FuzzerBuilder;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{fork, ForkResult};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use revm_primitives::Bytecode;
+use tracing::warn;
+
+/// Built-in oracles that can be enabled by name
+const BUILTIN_ORACLES: [&str; 9] = [
+    "selfdestruct",
+    "typed_bug",
+    "echidna",
+    "signature",
+    "delegatecall",
+    "tx_origin",
+    "initializer",
+    "truncation",
+    "sandwich",
+];
+
+/// Oracle implemented by a Python callable receiving the transaction document and returning
+/// a list of violations (`{"id": int, "message": str}`)
+struct PyOracle {
+    name: String,
+    callback: PyObject,
+    tracer: Rc<RefCell<CallTracer>>,
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for PyOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let document = tx_document(ctx, self.tracer.borrow().steps.clone()).to_string();
+        let violations = Python::with_gil(|py| -> PyResult<String> {
+            let json = py.import("json")?;
+            let tx = json.call_method1("loads", (document,))?;
+            let ret = self.callback.call1(py, (tx,))?;
+            json.call_method1("dumps", (ret,))?.extract::<String>()
+        });
+        match violations {
+            Ok(out) => report_violations(&self.name, parse_violations(out.as_bytes())),
+            Err(e) => {
+                warn!("[python oracle {}] failed: {}", self.name, e);
+                vec![]
+            }
+        }
+    }
+}
+
+/// Files of each violation found in `work_dir` by id, suffix (`txs` for the sequence) => content
+fn finding_files(work_dir: &str) -> Vec<(u64, HashMap<String, String>)> {
+    let dir = format!("{}/vulnerabilities", work_dir);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut files: HashMap<u64, HashMap<String, String>> = HashMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let (id, suffix) = match name.split_once('_') {
+            Some((id, suffix)) => (id.to_string(), suffix.to_string()),
+            None => (name.clone(), "txs".to_string()),
+        };
+        if let Ok(id) = id.parse::<u64>() {
+            let content = std::fs::read_to_string(entry.path()).unwrap_or_default();
+            files.entry(id).or_default().insert(suffix, content);
+        }
+    }
+    let mut files = files.into_iter().collect::<Vec<_>>();
+    files.sort_by_key(|(id, _)| *id);
+    files
+}
+
+/// Violations found in `work_dir`, each with the transactions, the replayable sequence and
+/// the files written next to them (e.g., `trace.json`, `state_diff.json`, `exploit.sol`)
+#[pyfunction]
+fn findings(py: Python<'_>, work_dir: &str) -> PyResult<Vec<PyObject>> {
+    finding_files(work_dir)
+        .into_iter()
+        .map(|(id, files)| {
+            let finding = PyDict::new(py);
+            finding.set_item("id", id)?;
+            for (suffix, content) in &files {
+                finding.set_item(suffix, content)?;
+            }
+            Ok(finding.into())
+        })
+        .collect()
+}
+
+/// A fuzzing campaign on local build artifacts
+#[pyclass(unsendable)]
+struct Campaign {
+    #[pyo3(get, set)]
+    target: String,
+    #[pyo3(get, set)]
+    work_dir: String,
+    #[pyo3(get, set)]
+    seed: u64,
+    #[pyo3(get, set)]
+    spec_id: String,
+    #[pyo3(get, set)]
+    sha3_bypass: bool,
+    enabled: Vec<String>,
+    oracles: Vec<(String, PyObject)>,
+}
+
+#[pymethods]
+impl Campaign {
+    #[new]
+    #[pyo3(signature = (target, work_dir = "work_dir".to_string(), seed = 0, spec_id = "Latest".to_string()))]
+    fn new(target: String, work_dir: String, seed: u64, spec_id: String) -> Self {
+        Self {
+            target,
+            work_dir,
+            seed,
+            spec_id,
+            sha3_bypass: false,
+            enabled: vec![],
+            oracles: vec![],
+        }
+    }
+
+    /// Enable a built-in oracle (e.g., `typed_bug`, `echidna`, `initializer`)
+    fn enable(&mut self, oracle: &str) -> PyResult<()> {
+        if !BUILTIN_ORACLES.contains(&oracle) {
+            return Err(PyValueError::new_err(format!(
+                "unknown oracle {}, available: {}",
+                oracle,
+                BUILTIN_ORACLES.join(", ")
+            )));
+        }
+        if !self.enabled.iter().any(|o| o == oracle) {
+            self.enabled.push(oracle.to_string());
+        }
+        Ok(())
+    }
+
+    /// Add an oracle implemented in Python, called with the document of each successful
+    /// transaction and returning a list of violations (`{"id": int, "message": str}`)
+    fn add_oracle(&mut self, name: String, callback: PyObject) {
+        self.oracles.push((name, callback));
+    }
+
+    /// Run the campaign until a violation is found (or `timeout` seconds), returns the findings
+    #[pyo3(signature = (timeout = None))]
+    fn run(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Vec<PyObject>> {
+        match unsafe { fork() }.map_err(|e| PyRuntimeError::new_err(e.to_string()))? {
+            ForkResult::Child => {
+                self.fuzz();
+                std::process::exit(0);
+            }
+            ForkResult::Parent { child } => {
+                let start = Instant::now();
+                loop {
+                    match waitpid(child, Some(WaitPidFlag::WNOHANG)) {
+                        Ok(WaitStatus::StillAlive) => {}
+                        Ok(_) => break,
+                        Err(e) => return Err(PyRuntimeError::new_err(e.to_string())),
+                    }
+                    if timeout.map_or(false, |t| start.elapsed() > Duration::from_secs_f64(t)) {
+                        let _ = kill(child, Signal::SIGKILL);
+                        let _ = waitpid(child, None);
+                        break;
+                    }
+                    py.check_signals()?;
+                    py.allow_threads(|| std::thread::sleep(Duration::from_millis(100)));
+                }
+                findings(py, &self.work_dir)
+            }
+        }
+    }
+}
+
+impl Campaign {
+    fn is_enabled(&self, oracle: &str) -> bool {
+        self.enabled.iter().any(|o| o == oracle)
+    }
+
+    fn fuzz(&self) {
+        let tracer = Rc::new(RefCell::new(CallTracer::new()));
+        let mut builder = FuzzerBuilder::new(self.seed)
+            .target_glob(self.target.as_str())
+            .work_dir(self.work_dir.as_str())
+            .spec_id(self.spec_id.as_str())
+            .sha3_bypass(self.sha3_bypass);
+        for (name, callback) in &self.oracles {
+            builder = builder.oracle(Rc::new(RefCell::new(PyOracle {
+                name: name.clone(),
+                callback: callback.clone(),
+                tracer: tracer.clone(),
+            })));
+        }
+        builder
+            .configure(|config| {
+                config.selfdestruct_oracle = self.is_enabled("selfdestruct");
+                config.typed_bug_oracle = self.is_enabled("typed_bug");
+                config.echidna_oracle = self.is_enabled("echidna");
+                config.sandwich_oracle = self.is_enabled("sandwich");
+                config.signature_oracle = self.is_enabled("signature");
+                config.tx_origin_oracle = self.is_enabled("tx_origin");
+                config.delegatecall_oracle = self.is_enabled("delegatecall");
+                config.initializer_oracle = self.is_enabled("initializer");
+                config.truncation_oracle = self.is_enabled("truncation");
+                if !self.oracles.is_empty() {
+                    config.tx_tracer = Some(tracer);
+                }
+            })
+            .run();
+    }
+}
+
+#[pymodule]
+fn ityfuzz(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Campaign>()?;
+    m.add_function(wrap_pyfunction!(findings, m)?)?;
+    Ok(())
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finding_files() {
+        let work_dir = std::env::temp_dir().join(format!("ityfuzz_findings_{}", std::process::id()));
+        let dir = work_dir.join("vulnerabilities");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2"), "tx 2").unwrap();
+        std::fs::write(dir.join("10"), "tx 10").unwrap();
+        std::fs::write(dir.join("10_trace.json"), "[]").unwrap();
+        std::fs::write(dir.join("README"), "not a finding").unwrap();
+
+        let files = finding_files(work_dir.to_str().unwrap());
+        std::fs::remove_dir_all(&work_dir).unwrap();
+        assert_eq!(files.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2, 10]);
+        assert_eq!(files[0].1, HashMap::from([("txs".to_string(), "tx 2".to_string())]));
+        assert_eq!(files[1].1["trace.json"], "[]");
+        assert!(finding_files("does_not_exist").is_empty());
+    }
+
+    #[test]
+    fn test_enable() {
+        let mut campaign = Campaign::new("build/*".to_string(), "work_dir".to_string(), 0, "Latest".to_string());
+        assert!(campaign.enable("typed_bug").is_ok());
+        assert!(campaign.enable("typed_bug").is_ok());
+        assert_eq!(campaign.enabled, vec!["typed_bug".to_string()]);
+        assert!(campaign.enable("no_such_oracle").is_err());
+        assert!(campaign.is_enabled("typed_bug"));
+        assert!(!campaign.is_enabled("echidna"));
+    }
+}
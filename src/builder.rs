@@ -0,0 +1,326 @@
+/// Library API to configure and run EVM campaigns from other crates
+///
+/// ```ignore
+/// use ityfuzz::FuzzerBuilder;
+///
+/// FuzzerBuilder::new(0)
+///     .target_glob("build/*")
+///     .work_dir("work_dir")
+///     .configure(|config| config.typed_bug_oracle = true)
+///     .oracle(Rc::new(RefCell::new(MyOracle::new())))
+///     .middleware(Rc::new(RefCell::new(MyMiddleware::new())))
+///     .run_for(100000);
+/// ```
+///
+/// Everything not covered by the builder can be set on the underlying [`Config`] with
+/// [`FuzzerBuilder::configure`].
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+
+use crate::evm::config::{Config, FuzzerTypes, StorageFetchingMode};
+use crate::evm::contract_utils::ContractLoader;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::middleware::Middleware;
+use crate::evm::onchain::endpoints::OnChainConfig;
+use crate::evm::onchain::flashloan::DummyPriceOracle;
+use crate::evm::power_schedule::PowerSchedule;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzers::evm_fuzzer::evm_fuzzer;
+use crate::oracle::{Oracle, Producer};
+use crate::state::FuzzState;
+
+pub type EVMConfig =
+    Config<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>;
+pub type EVMOracleRef = Rc<
+    RefCell<
+        dyn Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>,
+    >,
+>;
+pub type EVMProducerRef = Rc<
+    RefCell<
+        dyn Producer<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>,
+    >,
+>;
+pub type EVMMiddlewareRef = Rc<RefCell<dyn Middleware<EVMState, EVMInput, EVMFuzzState>>>;
+
+/// Builder of an EVM campaign, defaults match the CLI (offchain, CMP feedback, `work_dir`)
+pub struct FuzzerBuilder {
+    state: EVMFuzzState,
+    config: EVMConfig,
+}
+
+impl FuzzerBuilder {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: FuzzState::new(seed),
+            config: Config {
+                fuzzer_type: FuzzerTypes::CMP,
+                contract_loader: ContractLoader {
+                    contracts: vec![],
+                    abis: vec![],
+                    storage_layouts: HashMap::new(),
+                    links: HashMap::new(),
+                    implementation_source_maps: HashMap::new(),
+                },
+                onchain: None,
+                concolic: false,
+                oracle: vec![],
+                producers: vec![],
+                flashloan: false,
+                price_oracle: Box::new(DummyPriceOracle {}),
+                onchain_storage_fetching: None,
+                replay_file: None,
+                debug_file: None,
+                pair_oracle: false,
+                ierc20_oracle: false,
+                selfdestruct_oracle: false,
+                typed_bug_oracle: false,
+                work_dir: "work_dir".to_string(),
+                write_relationship: false,
+                run_forever: false,
+                sha3_bypass: false,
+                base_path: "".to_string(),
+                echidna_oracle: false,
+                panic_on_bug: false,
+                spec_id: "Latest".to_string(),
+                upgrade_proxy: None,
+                upgrade_implementation: None,
+                upgrade_slot: None,
+                eip7702: false,
+                chain_spec: None,
+                wasm_precompiles: vec![],
+                zksync: false,
+                gas_ceiling: None,
+                sandwich_oracle: false,
+                signature_oracle: false,
+                tx_origin_oracle: false,
+                reentrancy_oracle: false,
+                block_context_oracle: false,
+                constructor_oracle: false,
+                unchecked_call_oracle: false,
+                fee_oracle: false,
+                delegatecall_oracle: false,
+                initializer_oracle: false,
+                truncation_oracle: false,
+                rounding_epsilon: None,
+                differential_target: None,
+                differential_shadow: None,
+                cross_evm: None,
+                cross_evm_corpus: None,
+                shadow_fork: None,
+                solvency_config: None,
+                epoch_invariants: None,
+                event_invariants: None,
+                eth_accounting: vec![],
+                lending_config: None,
+                oracle_plugins: vec![],
+                middleware_scripts: vec![],
+                tx_tracer: None,
+                middlewares: vec![],
+                iterations: None,
+                control: None,
+                disabled_oracles: vec![],
+                frozen_contracts: vec![],
+                power_schedule: PowerSchedule::Default,
+                sequence_energy: false,
+                rare_boost: false,
+                cmin_corpus: None,
+                cmin_output: None,
+                trim: false,
+                focus_diff: None,
+                env_bounds: None,
+                env_bounds_report: false,
+                gas_price_bounds: None,
+                mempool: false,
+                bundle_config: None,
+                token_callbacks: false,
+                nft_callbacks: false,
+                mock_missing: false,
+                chainlink: None,
+                twap_cost: false,
+                twap_max_capital: None,
+                revert_coverage: true,
+                payable_values: true,
+                adaptive_length: true,
+                llm_triage: false,
+                llm_endpoint: String::new(),
+                llm_model: String::new(),
+                storage: "disk".to_string(),
+                storage_endpoint: None,
+                watch: None,
+                soak: None,
+                step_limit: None,
+                exec_timeout: None,
+                memory_cap: None,
+                target_groups: None,
+                coverage_by_code: false,
+                coverage_per_address: false,
+                import_corpus: None,
+                only_fuzz: None,
+                function_weights: None,
+                pre_state: None,
+                bridge_config: None,
+                known_addresses: None,
+            },
+        }
+    }
+
+    /// Fuzz the contracts built in the directory matched by `pattern` (`.abi` / `.bin` pairs)
+    pub fn target_glob(self, pattern: &str) -> Self {
+        self.target_glob_with_args(pattern, &HashMap::new())
+    }
+
+    /// Same as [`FuzzerBuilder::target_glob`], deploying contracts with the given
+    /// constructor arguments (contract name -> arguments)
+    pub fn target_glob_with_args(mut self, pattern: &str, constructor_args: &HashMap<String, Vec<String>>) -> Self {
+        self.config.contract_loader = ContractLoader::from_glob(pattern, &mut self.state, &vec![], constructor_args);
+        self
+    }
+
+    /// Fuzz deployed contracts, fetching code and storage from the chain
+    pub fn target_addresses(mut self, mut onchain: OnChainConfig, addresses: Vec<EVMAddress>) -> Self {
+        self.config.contract_loader = ContractLoader::from_address(&mut onchain, HashSet::from_iter(addresses));
+        self.config.onchain = Some(onchain);
+        if self.config.onchain_storage_fetching.is_none() {
+            self.config.onchain_storage_fetching = Some(StorageFetchingMode::OneByOne);
+        }
+        self
+    }
+
+    pub fn work_dir(mut self, work_dir: &str) -> Self {
+        self.config.work_dir = work_dir.to_string();
+        self
+    }
+
+    pub fn spec_id(mut self, spec_id: &str) -> Self {
+        self.config.spec_id = spec_id.to_string();
+        self
+    }
+
+    pub fn sha3_bypass(mut self, enabled: bool) -> Self {
+        self.config.sha3_bypass = enabled;
+        self
+    }
+
+    pub fn concolic(mut self, enabled: bool) -> Self {
+        self.config.concolic = enabled;
+        self
+    }
+
+    pub fn flashloan(mut self, enabled: bool) -> Self {
+        self.config.flashloan = enabled;
+        self
+    }
+
+    /// Keep fuzzing after a bug is found
+    pub fn run_forever(mut self, enabled: bool) -> Self {
+        self.config.run_forever = enabled;
+        self
+    }
+
+    /// Power schedule of the mutational stage, see [`PowerSchedule`]
+    pub fn power_schedule(mut self, schedule: PowerSchedule, sequence_aware: bool) -> Self {
+        self.config.power_schedule = schedule;
+        self.config.sequence_energy = sequence_aware;
+        self
+    }
+
+    /// Boost the energy of inputs hitting globally rare branches
+    pub fn rare_boost(mut self, enabled: bool) -> Self {
+        self.config.rare_boost = enabled;
+        self
+    }
+
+    pub fn oracle(mut self, oracle: EVMOracleRef) -> Self {
+        self.config.oracle.push(oracle);
+        self
+    }
+
+    pub fn producer(mut self, producer: EVMProducerRef) -> Self {
+        self.config.producers.push(producer);
+        self
+    }
+
+    /// Add a middleware to the host, middlewares are keyed by their type so custom ones
+    /// should return distinct `MiddlewareType::Custom` ids
+    pub fn middleware(mut self, middleware: EVMMiddlewareRef) -> Self {
+        self.config.middlewares.push(middleware);
+        self
+    }
+
+    /// Set anything else on the underlying config
+    pub fn configure(mut self, f: impl FnOnce(&mut EVMConfig)) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    /// State of the campaign, e.g., to add callers before running
+    pub fn state_mut(&mut self) -> &mut EVMFuzzState {
+        &mut self.state
+    }
+
+    /// Run the campaign until a bug is found (or forever with [`FuzzerBuilder::run_forever`]) and
+    /// return the state
+    pub fn run(self) -> EVMFuzzState {
+        self.start()
+    }
+
+    /// Run `iterations` fuzzing iterations (or until a bug is found) and return the state, bugs
+    /// found are in its [`SolutionsMetadata`](crate::fuzzer::SolutionsMetadata)
+    pub fn run_for(mut self, iterations: u64) -> EVMFuzzState {
+        self.config.iterations = Some(iterations);
+        self.start()
+    }
+
+    fn start(mut self) -> EVMFuzzState {
+        match self.config.fuzzer_type {
+            FuzzerTypes::CMP => evm_fuzzer(self.config, &mut self.state),
+            // other fuzzer types are not implemented
+            _ => {}
+        }
+        self.state
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::oracles::typed_bug::TypedBugOracle;
+    use crate::evm::oracles::tx_origin::TxOriginOracle;
+
+    #[test]
+    fn test_defaults() {
+        let builder = FuzzerBuilder::new(0);
+        assert_eq!(builder.config.work_dir, "work_dir");
+        assert_eq!(builder.config.spec_id, "Latest");
+        assert!(builder.config.onchain.is_none());
+        assert!(builder.config.oracle.is_empty());
+        assert!(builder.config.contract_loader.contracts.is_empty());
+    }
+
+    #[test]
+    fn test_setters() {
+        let builder = FuzzerBuilder::new(0)
+            .work_dir("campaign")
+            .spec_id("Shanghai")
+            .sha3_bypass(true)
+            .run_forever(true)
+            .rare_boost(true)
+            .power_schedule(PowerSchedule::Explore, true)
+            .oracle(Rc::new(RefCell::new(TypedBugOracle::new())))
+            .oracle(Rc::new(RefCell::new(TxOriginOracle::new())))
+            .configure(|config| config.tx_origin_oracle = true);
+        let config = &builder.config;
+        assert_eq!(config.work_dir, "campaign");
+        assert_eq!(config.spec_id, "Shanghai");
+        assert!(config.sha3_bypass && config.run_forever && config.rare_boost);
+        assert_eq!(config.power_schedule, PowerSchedule::Explore);
+        assert!(config.sequence_energy);
+        assert_eq!(config.oracle.len(), 2);
+        assert!(config.tx_origin_oracle);
+    }
+}
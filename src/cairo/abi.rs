@@ -0,0 +1,354 @@
+/// Cairo types from the contract ABI, their values and felt252-aware mutation
+use crate::cairo::types::{CairoAddress, Felt252};
+use crate::mutation_utils::ConstantPoolMetadata;
+use crate::state::HasCaller;
+use itertools::Itertools;
+use libafl::mutators::MutationResult;
+use libafl::prelude::{HasMetadata, HasRand, Rand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+pub const CAIRO_MAX_ARRAY_SIZE: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum CairoType {
+    Felt,
+    /// unsigned integers up to u128 (bits)
+    Uint(u16),
+    /// serialized as (low: u128, high: u128)
+    U256,
+    Bool,
+    ContractAddress,
+    /// Array and Span, serialized as length followed by the elements
+    Array(Box<CairoType>),
+    Struct(Vec<CairoType>),
+    /// payloads of the variants, serialized as variant index followed by the payload
+    Enum(Vec<CairoType>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum CairoValue {
+    Felt(Felt252),
+    U256(u128, u128),
+    Array(Vec<CairoValue>),
+    Struct(Vec<CairoValue>),
+    Enum(usize, Box<CairoValue>),
+}
+
+impl CairoType {
+    /// Parse a type in the ABI (e.g., `core::array::Span::<core::felt252>`), `defs` are the
+    /// structs and enums defined in the ABI
+    pub fn parse(ty: &str, defs: &HashMap<String, Value>) -> Self {
+        let generic_inner = |prefix: &str| {
+            ty.strip_prefix(prefix)
+                .and_then(|x| x.strip_suffix('>'))
+                .map(|x| x.to_string())
+        };
+        match ty {
+            "core::felt252" => return CairoType::Felt,
+            "core::bool" => return CairoType::Bool,
+            "core::integer::u256" => return CairoType::U256,
+            "()" => return CairoType::Struct(vec![]),
+            "core::starknet::contract_address::ContractAddress"
+            | "core::starknet::class_hash::ClassHash"
+            | "core::starknet::eth_address::EthAddress" => return CairoType::ContractAddress,
+            _ => {}
+        }
+        if let Some(bits) = ty.strip_prefix("core::integer::u").and_then(|x| x.parse::<u16>().ok()) {
+            return CairoType::Uint(bits);
+        }
+        if let Some(inner) = generic_inner("core::array::Array::<").or(generic_inner("core::array::Span::<")) {
+            return CairoType::Array(Box::new(Self::parse(&inner, defs)));
+        }
+        match defs.get(ty) {
+            Some(def) if def["type"] == "struct" => CairoType::Struct(
+                def["members"]
+                    .as_array()
+                    .map(|members| members.iter().map(|m| Self::parse(m["type"].as_str().unwrap_or(""), defs)).collect())
+                    .unwrap_or_default(),
+            ),
+            Some(def) if def["type"] == "enum" => CairoType::Enum(
+                def["variants"]
+                    .as_array()
+                    .map(|variants| variants.iter().map(|v| Self::parse(v["type"].as_str().unwrap_or(""), defs)).collect())
+                    .unwrap_or_default(),
+            ),
+            // signed integers, bytes31, etc. are felts
+            _ => CairoType::Felt,
+        }
+    }
+
+    pub fn default_value(&self) -> CairoValue {
+        match self {
+            CairoType::Felt | CairoType::Uint(_) | CairoType::Bool | CairoType::ContractAddress => {
+                CairoValue::Felt(Felt252::ZERO)
+            }
+            CairoType::U256 => CairoValue::U256(0, 0),
+            CairoType::Array(_) => CairoValue::Array(vec![]),
+            CairoType::Struct(fields) => CairoValue::Struct(fields.iter().map(|f| f.default_value()).collect()),
+            CairoType::Enum(variants) => CairoValue::Enum(
+                0,
+                Box::new(variants.get(0).map(|v| v.default_value()).unwrap_or(CairoValue::Struct(vec![]))),
+            ),
+        }
+    }
+}
+
+impl CairoValue {
+    /// Serialize the value into calldata
+    pub fn to_calldata(&self, out: &mut Vec<Felt252>) {
+        match self {
+            CairoValue::Felt(v) => out.push(*v),
+            CairoValue::U256(low, high) => {
+                out.push(Felt252::from(*low));
+                out.push(Felt252::from(*high));
+            }
+            CairoValue::Array(items) => {
+                out.push(Felt252::from(items.len()));
+                items.iter().for_each(|item| item.to_calldata(out));
+            }
+            CairoValue::Struct(fields) => fields.iter().for_each(|field| field.to_calldata(out)),
+            CairoValue::Enum(idx, payload) => {
+                out.push(Felt252::from(*idx));
+                payload.to_calldata(out);
+            }
+        }
+    }
+}
+
+impl fmt::Display for CairoValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CairoValue::Felt(v) => write!(f, "{:#x}", v),
+            CairoValue::U256(low, high) => {
+                write!(f, "{:#x}", (primitive_types::U256::from(*high) << 128) | primitive_types::U256::from(*low))
+            }
+            CairoValue::Array(items) => write!(f, "[{}]", items.iter().join(", ")),
+            CairoValue::Struct(fields) => write!(f, "({})", fields.iter().join(", ")),
+            CairoValue::Enum(idx, payload) => write!(f, "variant{}({})", idx, payload),
+        }
+    }
+}
+
+/// Functions in the ABI: (name, input types, is view function)
+pub fn parse_abi(abi: &str) -> Vec<(String, Vec<CairoType>, bool)> {
+    let items = match serde_json::from_str::<Value>(abi) {
+        Ok(Value::Array(items)) => items,
+        _ => return vec![],
+    };
+    let defs = items
+        .iter()
+        .filter(|item| item["type"] == "struct" || item["type"] == "enum")
+        .filter_map(|item| item["name"].as_str().map(|name| (name.to_string(), item.clone())))
+        .collect::<HashMap<_, _>>();
+    // functions are either at top level or in interfaces
+    items
+        .iter()
+        .flat_map(|item| match item["type"].as_str() {
+            Some("function") => vec![item.clone()],
+            Some("interface") => item["items"].as_array().cloned().unwrap_or_default(),
+            _ => vec![],
+        })
+        .filter(|item| item["type"] == "function")
+        .map(|func| {
+            let inputs = func["inputs"]
+                .as_array()
+                .map(|inputs| {
+                    inputs
+                        .iter()
+                        .map(|input| CairoType::parse(input["type"].as_str().unwrap_or(""), &defs))
+                        .collect()
+                })
+                .unwrap_or_default();
+            (
+                func["name"].as_str().unwrap_or("").to_string(),
+                inputs,
+                func["state_mutability"] == "view",
+            )
+        })
+        .collect()
+}
+
+fn rand_u128<S: HasRand>(state: &mut S) -> u128 {
+    (state.rand_mut().next() as u128) << 64 | state.rand_mut().next() as u128
+}
+
+fn uint_max(bits: u16) -> u128 {
+    if bits >= 128 {
+        u128::MAX
+    } else {
+        (1u128 << bits) - 1
+    }
+}
+
+/// Felts that often reach edge cases: 0, 1, -1 (P - 1), 2^128 - 1, 2^128, 2^251
+fn interesting_felt<S: HasRand>(state: &mut S) -> Felt252 {
+    match state.rand_mut().below(6) {
+        0 => Felt252::ZERO,
+        1 => Felt252::ONE,
+        2 => Felt252::MAX,
+        3 => Felt252::from(u128::MAX),
+        4 => Felt252::from(u128::MAX) + Felt252::ONE,
+        _ => Felt252::from_hex_unchecked("0x800000000000000000000000000000000000000000000000000000000000000"),
+    }
+}
+
+fn constant_felt<S: HasRand + HasMetadata>(state: &mut S) -> Option<Felt252> {
+    let len = state.metadata().get::<ConstantPoolMetadata>()?.constants.len();
+    if len == 0 {
+        return None;
+    }
+    let idx = state.rand_mut().below(len as u64) as usize;
+    let constant = state.metadata().get::<ConstantPoolMetadata>()?.constants[idx].clone();
+    Some(Felt252::from_bytes_be_slice(&constant))
+}
+
+fn mutate_uint<S: HasRand + HasMetadata>(state: &mut S, orig: u128, bits: u16) -> u128 {
+    let max = uint_max(bits);
+    let new = match state.rand_mut().below(5) {
+        0 => 0,
+        1 => max,
+        2 => orig.wrapping_add(state.rand_mut().below(16) as u128 + 1) & max,
+        3 => orig.wrapping_sub(state.rand_mut().below(16) as u128 + 1) & max,
+        _ => rand_u128(state) & max,
+    };
+    if new == orig {
+        new ^ 1
+    } else {
+        new
+    }
+}
+
+fn felt_to_u128(felt: &Felt252) -> u128 {
+    u128::from_be_bytes(felt.to_bytes_be()[16..].try_into().unwrap())
+}
+
+/// Mutate a value of given type, values always stay in the range of the type
+pub fn mutate_value<S>(state: &mut S, value: &mut CairoValue, ty: &CairoType) -> MutationResult
+where
+    S: HasRand + HasMetadata + HasCaller<CairoAddress>,
+{
+    match (ty, value) {
+        (CairoType::Felt, CairoValue::Felt(v)) => {
+            *v = match state.rand_mut().below(4) {
+                0 => interesting_felt(state),
+                1 => constant_felt(state).unwrap_or_else(|| interesting_felt(state)),
+                2 => *v + Felt252::from(state.rand_mut().below(16) + 1),
+                _ => Felt252::from(rand_u128(state)),
+            };
+        }
+        (CairoType::Uint(bits), CairoValue::Felt(v)) => {
+            let orig = felt_to_u128(v);
+            *v = match constant_felt(state).map(|c| felt_to_u128(&c)) {
+                Some(c) if c <= uint_max(*bits) && state.rand_mut().below(4) == 0 => Felt252::from(c),
+                _ => Felt252::from(mutate_uint(state, orig, *bits)),
+            };
+        }
+        (CairoType::U256, CairoValue::U256(low, high)) => {
+            if state.rand_mut().below(2) == 0 {
+                *low = mutate_uint(state, *low, 128);
+            } else {
+                *high = mutate_uint(state, *high, 128);
+            }
+        }
+        (CairoType::Bool, CairoValue::Felt(v)) => {
+            *v = if *v == Felt252::ZERO { Felt252::ONE } else { Felt252::ZERO };
+        }
+        (CairoType::ContractAddress, CairoValue::Felt(v)) => {
+            *v = state.get_rand_address();
+        }
+        (CairoType::Array(inner), CairoValue::Array(items)) => match state.rand_mut().below(3) {
+            0 if items.len() < CAIRO_MAX_ARRAY_SIZE => {
+                let mut item = inner.default_value();
+                mutate_value(state, &mut item, inner);
+                items.push(item);
+            }
+            1 if !items.is_empty() => {
+                items.pop();
+            }
+            _ => {
+                if items.is_empty() {
+                    return MutationResult::Skipped;
+                }
+                let idx = state.rand_mut().below(items.len() as u64) as usize;
+                return mutate_value(state, &mut items[idx], inner);
+            }
+        },
+        (CairoType::Struct(fields_ty), CairoValue::Struct(fields)) => {
+            if fields.is_empty() {
+                return MutationResult::Skipped;
+            }
+            let idx = state.rand_mut().below(fields.len() as u64) as usize;
+            return mutate_value(state, &mut fields[idx], &fields_ty[idx]);
+        }
+        (CairoType::Enum(variants), CairoValue::Enum(idx, payload)) => {
+            if variants.is_empty() {
+                return MutationResult::Skipped;
+            }
+            if variants.len() > 1 && state.rand_mut().below(2) == 0 {
+                *idx = state.rand_mut().below(variants.len() as u64) as usize;
+                **payload = variants[*idx].default_value();
+            } else {
+                return mutate_value(state, payload, &variants[*idx]);
+            }
+        }
+        _ => unreachable!("value does not match the type"),
+    }
+    MutationResult::Mutated
+}
+
+mod tests {
+    use super::*;
+    use crate::cairo::types::CairoFuzzState;
+    use crate::state::FuzzState;
+
+    #[test]
+    fn test_parse_abi() {
+        let abi = r#"[
+            {"type": "struct", "name": "demo::Pair", "members": [
+                {"name": "a", "type": "core::integer::u8"},
+                {"name": "b", "type": "core::integer::u256"}
+            ]},
+            {"type": "interface", "name": "demo::IDemo", "items": [
+                {"type": "function", "name": "set", "inputs": [
+                    {"name": "p", "type": "demo::Pair"},
+                    {"name": "xs", "type": "core::array::Span::<core::felt252>"}
+                ], "outputs": [], "state_mutability": "external"}
+            ]}
+        ]"#;
+        let funcs = parse_abi(abi);
+        assert_eq!(funcs.len(), 1);
+        assert_eq!(funcs[0].0, "set");
+        assert_eq!(
+            funcs[0].1,
+            vec![
+                CairoType::Struct(vec![CairoType::Uint(8), CairoType::U256]),
+                CairoType::Array(Box::new(CairoType::Felt)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calldata() {
+        let value = CairoValue::Struct(vec![
+            CairoValue::U256(1, 2),
+            CairoValue::Array(vec![CairoValue::Felt(Felt252::from(3u8))]),
+        ]);
+        let mut calldata = vec![];
+        value.to_calldata(&mut calldata);
+        assert_eq!(
+            calldata,
+            vec![1u8, 2, 1, 3].into_iter().map(Felt252::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_mutate_empty_enum() {
+        let mut state: CairoFuzzState = FuzzState::new(0);
+        let ty = CairoType::Enum(vec![]);
+        let mut value = ty.default_value();
+        assert_eq!(mutate_value(&mut state, &mut value, &ty), MutationResult::Skipped);
+    }
+}
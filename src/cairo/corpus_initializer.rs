@@ -0,0 +1,112 @@
+use crate::cairo::input::CairoInput;
+use tracing::{info, warn};
+use crate::cairo::types::{CairoFuzzState, CairoInfantStateState, CairoStagedVMState, Felt252};
+use crate::cairo::vm::{CairoContract, CairoVM};
+use crate::cairo::vm_state::CairoVMState;
+use crate::generic_vm::vm_executor::GenericVM;
+use crate::mutation_utils::ConstantPoolMetadata;
+use crate::state::HasCaller;
+use crate::state_input::StagedVMState;
+use libafl::corpus::{Corpus, Testcase};
+use libafl::prelude::{HasCorpus, HasMetadata, HasRand, Rand};
+use libafl::schedulers::Scheduler;
+use std::time::Duration;
+
+pub struct CairoCorpusInitializer<'a> {
+    pub state: &'a mut CairoFuzzState,
+    pub executor: &'a mut CairoVM<CairoFuzzState>,
+    pub scheduler: &'a dyn Scheduler<CairoInput, CairoFuzzState>,
+    pub infant_scheduler: &'a dyn Scheduler<CairoStagedVMState, CairoInfantStateState>,
+}
+
+impl<'a> CairoCorpusInitializer<'a> {
+    pub fn new(
+        state: &'a mut CairoFuzzState,
+        executor: &'a mut CairoVM<CairoFuzzState>,
+        scheduler: &'a dyn Scheduler<CairoInput, CairoFuzzState>,
+        infant_scheduler: &'a dyn Scheduler<CairoStagedVMState, CairoInfantStateState>,
+    ) -> Self {
+        Self {
+            state,
+            executor,
+            scheduler,
+            infant_scheduler,
+        }
+    }
+
+    fn random_address(&mut self) -> Felt252 {
+        // addresses are 251 bits
+        let mut bytes = [0u8; 32];
+        for b in bytes.iter_mut() {
+            *b = self.state.rand_mut().below(256) as u8;
+        }
+        bytes[0] &= 0x03;
+        Felt252::from_bytes_be(&bytes)
+    }
+
+    /// Deploy all contract classes (`*.contract_class.json`) matching the glob and
+    /// add their external functions to the corpus
+    pub fn setup(&mut self, target: String) {
+        for _ in 0..3 {
+            let caller = self.random_address();
+            self.state.add_caller(&caller);
+        }
+        self.state.metadata_mut().insert(ConstantPoolMetadata::new());
+
+        let mut deployed = vec![];
+        for path in glob::glob(&target).expect("invalid glob pattern") {
+            let path = path.unwrap();
+            let path = path.to_str().unwrap();
+            if !path.ends_with(".contract_class.json") {
+                continue;
+            }
+            let contract = CairoContract::from_file(path);
+            let address = self.random_address();
+            match self.executor.deploy(contract.clone(), None, address, self.state) {
+                Some(address) => {
+                    info!("deployed {} at {:#x}", path, address);
+                    deployed.push((address, contract));
+                }
+                None => warn!("failed to deploy {}", path),
+            }
+        }
+        assert!(!deployed.is_empty(), "no contract class found");
+
+        // storage after constructors is the initial state
+        let mut default_state = CairoVMState::new();
+        default_state.storage = self.executor.genesis_storage.clone();
+        let mut tc = Testcase::new(StagedVMState::new_with_state(default_state));
+        tc.set_exec_time(Duration::from_secs(0));
+        let idx = self
+            .state
+            .infant_states_state
+            .corpus_mut()
+            .add(tc)
+            .expect("failed to add");
+        self.infant_scheduler
+            .on_add(&mut self.state.infant_states_state, idx)
+            .expect("failed to call infant scheduler on_add");
+
+        for (address, contract) in deployed {
+            for function in contract.functions {
+                if function.view {
+                    continue;
+                }
+                let input = CairoInput {
+                    contract: address,
+                    args: function.inputs.iter().map(|ty| ty.default_value()).collect(),
+                    function,
+                    caller: self.state.get_rand_caller(),
+                    vm_state: StagedVMState::new_uninitialized(),
+                    vm_state_idx: 0,
+                };
+                let mut tc = Testcase::new(input);
+                tc.set_exec_time(Duration::from_secs(0));
+                let idx = self.state.add_tx_to_corpus(tc).expect("failed to add input to corpus");
+                self.scheduler
+                    .on_add(self.state, idx)
+                    .expect("failed to call scheduler on_add");
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::cairo::abi::{mutate_value, CairoValue};
+use crate::cairo::types::{CairoAddress, CairoLoc, CairoStagedVMState, Felt252};
+use crate::cairo::vm::CairoFunction;
+use crate::cairo::vm_state::CairoVMState;
+use crate::evm::abi::BoxedABI;
+use crate::evm::types::EVMU256;
+use crate::generic_vm::vm_executor::ExecutionResult;
+use crate::input::{ConciseSerde, VMInputT};
+use crate::state::{HasCaller, HasItyState};
+use itertools::Itertools;
+use libafl::inputs::Input;
+use libafl::prelude::{HasMaxSize, HasMetadata, HasRand, MutationResult, Rand, State};
+use serde::{Deserialize, Serialize};
+use std::any;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CairoInput {
+    pub contract: CairoAddress,
+    pub function: CairoFunction,
+    pub args: Vec<CairoValue>,
+    pub caller: CairoAddress,
+    pub vm_state: CairoStagedVMState,
+    pub vm_state_idx: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConciseCairoInput {
+    pub contract: CairoAddress,
+    pub function: String,
+    pub selector: Felt252,
+    pub args: Vec<CairoValue>,
+    pub caller: CairoAddress,
+}
+
+impl ConciseSerde for ConciseCairoInput {
+    fn serialize_concise(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Failed to serialize concise input")
+    }
+
+    fn deserialize_concise(data: &[u8]) -> Self {
+        serde_json::from_slice(data).expect("Failed to deserialize concise input")
+    }
+
+    fn serialize_string(&self) -> String {
+        format!(
+            "{:#x} => {:#x}::{}({})",
+            self.caller,
+            self.contract,
+            self.function,
+            self.args.iter().join(", ")
+        )
+    }
+}
+
+impl CairoInput {
+    pub fn calldata(&self) -> Vec<Felt252> {
+        let mut calldata = vec![];
+        self.args.iter().for_each(|arg| arg.to_calldata(&mut calldata));
+        calldata
+    }
+}
+
+impl Input for CairoInput {
+    fn generate_name(&self, idx: usize) -> String {
+        format!("{}_{:#x}_{}", idx, self.contract, self.function.name)
+    }
+}
+
+impl VMInputT<CairoVMState, CairoLoc, CairoAddress, ConciseCairoInput> for CairoInput {
+    fn mutate<S>(&mut self, state: &mut S) -> MutationResult
+    where
+        S: State
+            + HasRand
+            + HasMaxSize
+            + HasItyState<CairoLoc, CairoAddress, CairoVMState, ConciseCairoInput>
+            + HasCaller<CairoAddress>
+            + HasMetadata,
+    {
+        if self.args.is_empty() || state.rand_mut().below(10) == 0 {
+            let old_caller = self.caller;
+            self.caller = state.get_rand_caller();
+            return if old_caller == self.caller {
+                MutationResult::Skipped
+            } else {
+                MutationResult::Mutated
+            };
+        }
+        let nth = state.rand_mut().below(self.args.len() as u64) as usize;
+        mutate_value(state, &mut self.args[nth], &self.function.inputs[nth])
+    }
+
+    fn get_caller_mut(&mut self) -> &mut CairoAddress {
+        &mut self.caller
+    }
+
+    fn get_caller(&self) -> CairoAddress {
+        self.caller
+    }
+
+    fn set_caller(&mut self, caller: CairoAddress) {
+        self.caller = caller;
+    }
+
+    fn get_contract(&self) -> CairoAddress {
+        self.contract
+    }
+
+    fn get_state(&self) -> &CairoVMState {
+        &self.vm_state.state
+    }
+
+    fn get_state_mut(&mut self) -> &mut CairoVMState {
+        &mut self.vm_state.state
+    }
+
+    fn set_staged_state(&mut self, state: CairoStagedVMState, idx: usize) {
+        self.vm_state = state;
+        self.vm_state_idx = idx;
+    }
+
+    fn get_state_idx(&self) -> usize {
+        self.vm_state_idx
+    }
+
+    fn get_staged_state(&self) -> &CairoStagedVMState {
+        &self.vm_state
+    }
+
+    fn set_as_post_exec(&mut self, _out_size: usize) {
+        unreachable!("Cairo has no post execution")
+    }
+
+    fn is_step(&self) -> bool {
+        false
+    }
+
+    fn set_step(&mut self, _gate: bool) {}
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+
+    fn fav_factor(&self) -> f64 {
+        f64::MAX
+    }
+
+    #[cfg(feature = "evm")]
+    fn get_data_abi(&self) -> Option<BoxedABI> {
+        unreachable!("CairoVM does not have an EVM ABI")
+    }
+
+    #[cfg(feature = "evm")]
+    fn get_data_abi_mut(&mut self) -> &mut Option<BoxedABI> {
+        unreachable!("CairoVM does not have an EVM ABI")
+    }
+
+    #[cfg(feature = "evm")]
+    fn get_txn_value_temp(&self) -> Option<EVMU256> {
+        None
+    }
+
+    fn get_direct_data(&self) -> Vec<u8> {
+        self.calldata().iter().flat_map(|felt| felt.to_bytes_be()).collect()
+    }
+
+    fn get_concise<Out: Default>(
+        &self,
+        _exec_res: &ExecutionResult<CairoLoc, CairoAddress, CairoVMState, Out, ConciseCairoInput>,
+    ) -> ConciseCairoInput {
+        ConciseCairoInput {
+            contract: self.contract,
+            function: self.function.name.clone(),
+            selector: self.function.selector,
+            args: self.args.clone(),
+            caller: self.caller,
+        }
+    }
+}
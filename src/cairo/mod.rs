@@ -0,0 +1,7 @@
+pub mod abi;
+pub mod corpus_initializer;
+pub mod input;
+pub mod oracles;
+pub mod types;
+pub mod vm;
+pub mod vm_state;
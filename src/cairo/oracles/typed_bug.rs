@@ -0,0 +1,49 @@
+use crate::cairo::input::{CairoInput, ConciseCairoInput};
+use crate::cairo::oracles::TYPED_BUG_BUG_IDX;
+use crate::cairo::types::{CairoAddress, CairoFuzzState, CairoLoc, CairoOracleCtx, CairoOutput, CairoSlotTy, Felt252};
+use crate::cairo::vm::CairoContract;
+use crate::cairo::vm_state::CairoVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::Oracle;
+use itertools::Itertools;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Reports bugs emitted by contracts through the `AAAA__fuzzland_cairo_bug` event
+pub struct TypedBugOracle;
+
+impl TypedBugOracle {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Oracle<CairoVMState, CairoAddress, CairoContract, Vec<Felt252>, CairoLoc, CairoSlotTy, CairoOutput, CairoInput, CairoFuzzState, ConciseCairoInput>
+for TypedBugOracle {
+    fn transition(&self, _ctx: &mut CairoOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut CairoOracleCtx<'_>,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if ctx.post_state.typed_bug.len() > 0 {
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[typed_bug] {:?} hit at contract {:#x}\n",
+                    ctx.post_state.typed_bug,
+                    ctx.input.contract
+                ).as_str();
+            }
+            ctx.post_state.typed_bug.iter().map(|bug_id| {
+                let mut hasher = DefaultHasher::new();
+                bug_id.hash(&mut hasher);
+                (hasher.finish() << 8) + TYPED_BUG_BUG_IDX
+            }).collect_vec()
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::cairo::input::{CairoInput, ConciseCairoInput};
+use crate::cairo::vm::CairoContract;
+use crate::cairo::vm_state::CairoVMState;
+use crate::oracle::OracleCtx;
+use crate::state::{FuzzState, InfantStateState};
+use crate::state_input::StagedVMState;
+use serde::{Deserialize, Serialize};
+
+pub type Felt252 = starknet_types_core::felt::Felt;
+
+/// Starknet contract address
+pub type CairoAddress = Felt252;
+/// Location of a function, i.e., the selector of the entry point
+pub type CairoLoc = Felt252;
+pub type CairoSlotTy = u128;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CairoOutput {
+    /// returned felts, or the panic data if the execution panicked
+    pub retdata: Vec<Felt252>,
+}
+
+pub type CairoStagedVMState = StagedVMState<CairoLoc, CairoAddress, CairoVMState, ConciseCairoInput>;
+pub type CairoInfantStateState = InfantStateState<CairoLoc, CairoAddress, CairoVMState, ConciseCairoInput>;
+
+pub type CairoFuzzState =
+    FuzzState<CairoInput, CairoVMState, CairoLoc, CairoAddress, CairoOutput, ConciseCairoInput>;
+
+pub type CairoOracleCtx<'a> = OracleCtx<
+    'a,
+    CairoVMState,
+    CairoAddress,
+    CairoContract,
+    Vec<Felt252>,
+    CairoLoc,
+    CairoSlotTy,
+    CairoOutput,
+    CairoInput,
+    CairoFuzzState,
+    ConciseCairoInput,
+>;
+
+/// Convert a felt to the key of coverage / dataflow maps
+pub fn felt_to_map_key(felt: &Felt252) -> usize {
+    let bytes = felt.to_bytes_be();
+    (u64::from_be_bytes(bytes[24..].try_into().unwrap()) as usize) % crate::generic_vm::vm_executor::MAP_SIZE
+}
@@ -0,0 +1,525 @@
+/// Executes Starknet contracts (Sierra) on the Cairo VM, with Sierra statement coverage
+use crate::cairo::abi::{parse_abi, CairoType};
+use tracing::warn;
+use crate::cairo::input::{CairoInput, ConciseCairoInput};
+use crate::cairo::types::{felt_to_map_key, CairoAddress, CairoLoc, CairoOutput, CairoSlotTy, Felt252};
+use crate::cairo::vm_state::CairoVMState;
+use crate::generic_vm::vm_executor::{ExecutionResult, GenericVM, MAP_SIZE};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::mutation_utils::ConstantPoolMetadata;
+use crate::state::HasCaller;
+use crate::state_input::StagedVMState;
+use cairo_lang_runner::profiling::ProfilingInfoCollectionConfig;
+use cairo_lang_runner::{Arg, RunResultValue, SierraCasmRunner, StarknetState};
+use cairo_lang_sierra::program::{GenericArg, Program, Statement, StatementIdx};
+use cairo_lang_sierra_to_casm::metadata::MetadataComputationConfig;
+use cairo_lang_starknet_classes::contract_class::{ContractClass, ContractEntryPoint};
+use cairo_lang_utils::ordered_hash_map::OrderedHashMap;
+use libafl::prelude::HasMetadata;
+use revm_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+pub static mut CAIRO_COV_MAP: [u8; MAP_SIZE] = [0u8; MAP_SIZE];
+/// operands are not traced by the runner, the distance of a branch is the number of its targets
+/// not reached by the execution (like the condition of JUMPI on EVM)
+pub static mut CAIRO_CMP_MAP: [u128; MAP_SIZE] = [u128::MAX; MAP_SIZE];
+pub static mut CAIRO_READ_MAP: [bool; MAP_SIZE] = [false; MAP_SIZE];
+pub static mut CAIRO_WRITE_MAP: [u8; MAP_SIZE] = [0u8; MAP_SIZE];
+pub static mut CAIRO_STATE_CHANGED: bool = false;
+
+/// Gas available to each execution
+pub const CAIRO_GAS_LIMIT: usize = 100_000_000;
+
+/// Name of the event contracts emit to report a typed bug, the first data is the bug ID
+pub const TYPED_BUG_EVENT: &str = "AAAA__fuzzland_cairo_bug";
+
+/// Selector of an entry point / event, keccak256 truncated to 250 bits
+pub fn starknet_keccak(data: &[u8]) -> Felt252 {
+    let mut hash = keccak256(data).0;
+    hash[0] &= 0x03;
+    Felt252::from_bytes_be(&hash)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CairoFunction {
+    pub name: String,
+    pub selector: Felt252,
+    /// index of the function in the Sierra program
+    pub function_idx: usize,
+    pub inputs: Vec<CairoType>,
+    pub view: bool,
+}
+
+/// A compiled Starknet contract class (`*.contract_class.json`)
+#[derive(Clone, Debug)]
+pub struct CairoContract {
+    pub program: Program,
+    pub functions: Vec<CairoFunction>,
+    pub constructor: Option<CairoFunction>,
+}
+
+impl CairoContract {
+    pub fn from_file(path: &str) -> Self {
+        let json = std::fs::read_to_string(path).unwrap_or_else(|_| panic!("failed to read {}", path));
+        let class: ContractClass = serde_json::from_str(&json).expect("invalid contract class");
+        let program = class.extract_sierra_program().expect("invalid sierra program");
+        let abi = class
+            .abi
+            .as_ref()
+            .map(|abi| serde_json::to_string(abi).expect("invalid abi"))
+            .unwrap_or_default();
+        let abi_functions = parse_abi(&abi);
+
+        let to_function = |entry: &ContractEntryPoint| {
+            let selector = Felt252::from_bytes_be_slice(&entry.selector.to_bytes_be());
+            let (name, inputs, view) = abi_functions
+                .iter()
+                .find(|(name, _, _)| starknet_keccak(name.as_bytes()) == selector)
+                .cloned()
+                .unwrap_or((format!("{:#x}", selector), vec![], false));
+            CairoFunction {
+                name,
+                selector,
+                function_idx: entry.function_idx,
+                inputs,
+                view,
+            }
+        };
+        Self {
+            functions: class.entry_points_by_type.external.iter().map(to_function).collect(),
+            constructor: class.entry_points_by_type.constructor.first().map(to_function),
+            program,
+        }
+    }
+
+    /// Constants (felt252_const, u128_const, ...) in the program
+    pub fn constants(&self) -> Vec<Felt252> {
+        self.program
+            .libfunc_declarations
+            .iter()
+            .flat_map(|decl| decl.long_id.generic_args.iter())
+            .filter_map(|arg| match arg {
+                GenericArg::Value(v) => Felt252::from_dec_str(&v.to_string()).ok(),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Statements of a program feeding the cmp and read maps
+#[derive(Clone, Debug, Default)]
+pub struct ProgramInfo {
+    /// branching statements and the statements they branch to
+    pub branches: Vec<(usize, Vec<usize>)>,
+    pub storage_reads: Vec<usize>,
+    /// statements addressing a storage variable (`storage_base_address_const`) and its address
+    pub storage_vars: Vec<(usize, Felt252)>,
+}
+
+impl ProgramInfo {
+    pub fn new(program: &Program) -> Self {
+        let libfuncs = program
+            .libfunc_declarations
+            .iter()
+            .map(|decl| {
+                let value = decl.long_id.generic_args.iter().find_map(|arg| match arg {
+                    GenericArg::Value(v) => Felt252::from_dec_str(&v.to_string()).ok(),
+                    _ => None,
+                });
+                (decl.id.clone(), (decl.long_id.generic_id.0.to_string(), value))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut info = Self::default();
+        for (idx, statement) in program.statements.iter().enumerate() {
+            let invocation = match statement {
+                Statement::Invocation(invocation) => invocation,
+                Statement::Return(_) => continue,
+            };
+            let (name, value) = match libfuncs.get(&invocation.libfunc_id) {
+                Some(libfunc) => libfunc,
+                None => continue,
+            };
+            if invocation.branches.len() > 1 {
+                let targets = invocation
+                    .branches
+                    .iter()
+                    .map(|branch| StatementIdx(idx).next(&branch.target).0)
+                    .collect();
+                info.branches.push((idx, targets));
+            }
+            match (name.as_str(), value) {
+                ("storage_read_syscall", _) => info.storage_reads.push(idx),
+                ("storage_base_address_const", Some(address)) => info.storage_vars.push((idx, *address)),
+                _ => {}
+            }
+        }
+        info
+    }
+
+    /// Distance of each branch reached: the number of its targets not reached
+    pub fn cmp_distances(&self, executed: &HashSet<usize>) -> Vec<(usize, u128)> {
+        self.branches
+            .iter()
+            .filter(|(idx, _)| executed.contains(idx))
+            .map(|(idx, targets)| (*idx, targets.iter().filter(|t| !executed.contains(t)).count() as u128))
+            .collect()
+    }
+
+    /// Storage variables addressed by an execution reading storage (entries of maps are hashed
+    /// at runtime and not covered)
+    pub fn read_slots(&self, executed: &HashSet<usize>) -> Vec<Felt252> {
+        if !self.storage_reads.iter().any(|idx| executed.contains(idx)) {
+            return vec![];
+        }
+        self.storage_vars
+            .iter()
+            .filter(|(idx, _)| executed.contains(idx))
+            .map(|(_, address)| *address)
+            .collect()
+    }
+}
+
+pub struct DeployedCairoContract {
+    pub runner: SierraCasmRunner,
+    pub program: Program,
+    pub functions: Vec<CairoFunction>,
+    pub info: ProgramInfo,
+}
+
+pub struct CairoVM<S> {
+    pub contracts: HashMap<CairoAddress, DeployedCairoContract>,
+    /// storage after running constructors, which is the initial state to fuzz
+    pub genesis_storage: BTreeMap<Felt252, BTreeMap<Felt252, Felt252>>,
+    _phantom: PhantomData<S>,
+}
+
+struct CairoRunResult {
+    reverted: bool,
+    retdata: Vec<Felt252>,
+    storage: BTreeMap<Felt252, BTreeMap<Felt252, Felt252>>,
+    typed_bug: Vec<String>,
+    /// sierra statements executed
+    executed: HashSet<usize>,
+}
+
+impl<S> CairoVM<S> {
+    pub fn new() -> Self {
+        Self {
+            contracts: HashMap::new(),
+            genesis_storage: BTreeMap::new(),
+            _phantom: Default::default(),
+        }
+    }
+
+    fn run(
+        &self,
+        address: CairoAddress,
+        function: &CairoFunction,
+        calldata: Vec<Felt252>,
+        caller: CairoAddress,
+        storage: &BTreeMap<Felt252, BTreeMap<Felt252, Felt252>>,
+    ) -> CairoRunResult {
+        let contract = self.contracts.get(&address).expect("contract not deployed");
+        let mut starknet_state = StarknetState::default();
+        starknet_state.storage = storage
+            .iter()
+            .map(|(addr, slots)| (*addr, slots.iter().map(|(k, v)| (*k, *v)).collect()))
+            .collect();
+        starknet_state.exec_info.caller_address = caller;
+        starknet_state.exec_info.contract_address = address;
+        starknet_state.exec_info.entry_point_selector = function.selector;
+
+        let func = &contract.program.funcs[function.function_idx];
+        let result = match contract.runner.run_function_with_starknet_context(
+            func,
+            &[Arg::Array(calldata.into_iter().map(Arg::Value).collect())],
+            Some(CAIRO_GAS_LIMIT),
+            starknet_state,
+        ) {
+            Ok(result) => result,
+            Err(_) => {
+                return CairoRunResult {
+                    reverted: true,
+                    retdata: vec![],
+                    storage: storage.clone(),
+                    typed_bug: vec![],
+                    executed: HashSet::new(),
+                }
+            }
+        };
+
+        let executed = result
+            .profiling_info
+            .as_ref()
+            .map(|info| info.sierra_statement_weights.iter_sorted().map(|(idx, _)| idx.0).collect())
+            .unwrap_or_default();
+
+        let typed_bug_key = starknet_keccak(TYPED_BUG_EVENT.as_bytes());
+        let typed_bug = result
+            .starknet_state
+            .logs
+            .get(&address)
+            .map(|logs| {
+                logs.events
+                    .iter()
+                    .filter(|(keys, _)| keys.first() == Some(&typed_bug_key))
+                    .map(|(_, data)| format!("bug{}", data.first().copied().unwrap_or_default()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let (reverted, retdata) = match result.value {
+            RunResultValue::Success(values) => (false, values),
+            RunResultValue::Panic(values) => (true, values),
+        };
+        CairoRunResult {
+            reverted,
+            retdata,
+            storage: result
+                .starknet_state
+                .storage
+                .iter()
+                .map(|(addr, slots)| (*addr, slots.iter().map(|(k, v)| (*k, *v)).collect()))
+                .collect(),
+            typed_bug,
+            executed,
+        }
+    }
+
+    /// Record the sierra statement coverage, branch distances and storage reads of an execution
+    fn record_feedback(&self, address: CairoAddress, executed: &HashSet<usize>) {
+        let map_offset = |idx: &usize| {
+            let mut hasher = DefaultHasher::new();
+            address.hash(&mut hasher);
+            idx.hash(&mut hasher);
+            hasher.finish() as usize % MAP_SIZE
+        };
+        let info = &self.contracts.get(&address).expect("contract not deployed").info;
+        unsafe {
+            for idx in executed {
+                let offset = map_offset(idx);
+                CAIRO_COV_MAP[offset] = (CAIRO_COV_MAP[offset] + 1) % 255;
+            }
+            for (idx, distance) in info.cmp_distances(executed) {
+                CAIRO_CMP_MAP[map_offset(&idx)] = distance;
+            }
+            for slot in info.read_slots(executed) {
+                CAIRO_READ_MAP[felt_to_map_key(&slot)] = true;
+            }
+        }
+    }
+}
+
+impl<S> GenericVM<CairoVMState, CairoContract, Vec<Felt252>, CairoLoc, CairoAddress, CairoSlotTy, CairoOutput, CairoInput, S, ConciseCairoInput>
+    for CairoVM<S>
+where
+    S: HasMetadata + HasCaller<CairoAddress>,
+{
+    fn deploy(
+        &mut self,
+        code: CairoContract,
+        constructor_args: Option<Vec<Felt252>>,
+        deployed_address: CairoAddress,
+        state: &mut S,
+    ) -> Option<CairoAddress> {
+        if !state.metadata().contains::<ConstantPoolMetadata>() {
+            state.metadata_mut().insert(ConstantPoolMetadata::new());
+        }
+        let constant_pool = state.metadata_mut().get_mut::<ConstantPoolMetadata>().unwrap();
+        for constant in code.constants() {
+            constant_pool.add_constant(constant.to_bytes_be().to_vec());
+        }
+
+        let runner = SierraCasmRunner::new(
+            code.program.clone(),
+            Some(MetadataComputationConfig::default()),
+            OrderedHashMap::default(),
+            Some(ProfilingInfoCollectionConfig::default()),
+        )
+        .expect("failed to compile sierra program");
+        self.contracts.insert(
+            deployed_address,
+            DeployedCairoContract {
+                runner,
+                program: code.program.clone(),
+                functions: code.functions.clone(),
+                info: ProgramInfo::new(&code.program),
+            },
+        );
+        state.add_address(&deployed_address);
+
+        if let Some(constructor) = &code.constructor {
+            let calldata = constructor_args.unwrap_or_else(|| {
+                let mut calldata = vec![];
+                constructor
+                    .inputs
+                    .iter()
+                    .for_each(|ty| ty.default_value().to_calldata(&mut calldata));
+                calldata
+            });
+            let caller = state.get_rand_caller();
+            let result = self.run(deployed_address, constructor, calldata, caller, &self.genesis_storage);
+            self.record_feedback(deployed_address, &result.executed);
+            if result.reverted {
+                warn!("constructor of {:#x} reverted: {:?}", deployed_address, result.retdata);
+                return None;
+            }
+            self.genesis_storage = result.storage;
+        }
+        Some(deployed_address)
+    }
+
+    fn execute(
+        &mut self,
+        input: &CairoInput,
+        _state: &mut S,
+    ) -> ExecutionResult<CairoLoc, CairoAddress, CairoVMState, CairoOutput, ConciseCairoInput>
+    where
+        CairoVMState: VMStateT,
+    {
+        unsafe {
+            CAIRO_STATE_CHANGED = false;
+        }
+        let pre_state = input.get_state();
+        let result = self.run(
+            input.contract,
+            &input.function,
+            input.calldata(),
+            input.caller,
+            &pre_state.storage,
+        );
+        self.record_feedback(input.contract, &result.executed);
+
+        let mut new_state = pre_state.clone();
+        new_state.typed_bug = result.typed_bug;
+        if !result.reverted {
+            for (addr, slots) in &result.storage {
+                let pre_slots = pre_state.storage.get(addr);
+                for (key, value) in slots {
+                    if pre_slots.and_then(|s| s.get(key)) != Some(value) {
+                        unsafe {
+                            CAIRO_WRITE_MAP[felt_to_map_key(key)] = 1;
+                            CAIRO_STATE_CHANGED = true;
+                        }
+                    }
+                }
+            }
+            new_state.storage = result.storage;
+        }
+
+        ExecutionResult {
+            new_state: StagedVMState::new_with_state(new_state),
+            output: CairoOutput { retdata: result.retdata },
+            reverted: result.reverted,
+            additional_info: None,
+        }
+    }
+
+    /// Calls with the selector of the entry point as first felt, an empty output if the call
+    /// panicked or the entry point doesn't exist. Nothing is recorded in the feedback maps.
+    fn fast_static_call(
+        &mut self,
+        data: &Vec<(CairoAddress, Vec<Felt252>)>,
+        vm_state: &CairoVMState,
+        _state: &mut S,
+    ) -> Vec<CairoOutput> {
+        data.iter()
+            .map(|(address, calldata)| {
+                let function = self.contracts.get(address).and_then(|contract| {
+                    let selector = calldata.first()?;
+                    contract.functions.iter().find(|f| f.selector == *selector).cloned()
+                });
+                match function {
+                    Some(function) => {
+                        let result = self.run(*address, &function, calldata[1..].to_vec(), Felt252::ZERO, &vm_state.storage);
+                        CairoOutput {
+                            retdata: if result.reverted { vec![] } else { result.retdata },
+                        }
+                    }
+                    None => CairoOutput::default(),
+                }
+            })
+            .collect()
+    }
+
+    fn get_jmp(&self) -> &'static mut [u8; MAP_SIZE] {
+        unsafe { &mut CAIRO_COV_MAP }
+    }
+
+    fn get_read(&self) -> &'static mut [bool; MAP_SIZE] {
+        unsafe { &mut CAIRO_READ_MAP }
+    }
+
+    fn get_write(&self) -> &'static mut [u8; MAP_SIZE] {
+        unsafe { &mut CAIRO_WRITE_MAP }
+    }
+
+    fn get_cmp(&self) -> &'static mut [CairoSlotTy; MAP_SIZE] {
+        unsafe { &mut CAIRO_CMP_MAP }
+    }
+
+    fn state_changed(&self) -> bool {
+        unsafe { CAIRO_STATE_CHANGED }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starknet_keccak() {
+        // selector of `transfer`
+        assert_eq!(
+            starknet_keccak(b"transfer"),
+            Felt252::from_hex_unchecked("0x83afd3f4caedc6eebf44246fe54e38c95e3179a5ec9ea81740eca5b482d12e")
+        );
+    }
+
+    #[test]
+    fn test_program_info() {
+        // if x == 0 { read storage variable 1234 } else { return x }
+        let program = cairo_lang_sierra::ProgramParser::new()
+            .parse(
+                r#"
+                type felt252 = felt252;
+                type NonZeroFelt252 = NonZero<felt252>;
+                type StorageBaseAddress = StorageBaseAddress;
+                libfunc felt252_is_zero = felt252_is_zero;
+                libfunc branch_align = branch_align;
+                libfunc drop<NonZeroFelt252> = drop<NonZeroFelt252>;
+                libfunc storage_base_address_const<1234> = storage_base_address_const<1234>;
+                libfunc storage_read_syscall = storage_read_syscall;
+
+                felt252_is_zero([0]) { fallthrough() 4([1]) };
+                branch_align() -> ();
+                storage_base_address_const<1234>() -> ([2]);
+                storage_read_syscall([3], [4], [5], [2]) { fallthrough([0], [4]) 7([0], [4]) };
+                branch_align() -> ();
+                drop<NonZeroFelt252>([1]) -> ();
+                return([0]);
+                return([0]);
+
+                test::f@0([0]: felt252) -> (felt252);
+                "#,
+            )
+            .unwrap();
+        let info = ProgramInfo::new(&program);
+        assert_eq!(info.branches, vec![(0, vec![1, 4]), (3, vec![4, 7])]);
+        assert_eq!(info.storage_vars, vec![(2, Felt252::from(1234u64))]);
+
+        // x != 0: the first branch is not flipped, no storage is read
+        let executed = HashSet::from([0, 4, 5, 6]);
+        assert_eq!(info.cmp_distances(&executed), vec![(0, 1)]);
+        assert!(info.read_slots(&executed).is_empty());
+
+        let executed = HashSet::from([0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(info.cmp_distances(&executed), vec![(0, 0), (3, 1)]);
+        assert_eq!(info.read_slots(&executed), vec![Felt252::from(1234u64)]);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::cairo::types::Felt252;
+use crate::generic_vm::vm_state::VMStateT;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CairoVMState {
+    /// contract address => storage key => value
+    pub storage: BTreeMap<Felt252, BTreeMap<Felt252, Felt252>>,
+    /// typed bugs emitted in the last execution
+    pub typed_bug: Vec<String>,
+}
+
+impl CairoVMState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl VMStateT for CairoVMState {
+    fn get_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.storage.iter().for_each(|(addr, slots)| {
+            addr.hash(&mut hasher);
+            slots.iter().for_each(|(k, v)| {
+                k.hash(&mut hasher);
+                v.hash(&mut hasher);
+            });
+        });
+        hasher.finish()
+    }
+
+    fn has_post_execution(&self) -> bool {
+        false
+    }
+
+    fn get_post_execution_needed_len(&self) -> usize {
+        0
+    }
+
+    fn get_post_execution_pc(&self) -> usize {
+        0
+    }
+
+    fn get_post_execution_len(&self) -> usize {
+        0
+    }
+
+    #[cfg(feature = "full_trace")]
+    fn get_flashloan(&self) -> String {
+        String::new()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
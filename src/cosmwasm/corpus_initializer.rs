@@ -0,0 +1,132 @@
+use crate::cosmwasm::input::CosmWasmInput;
+use tracing::{info, warn};
+use crate::cosmwasm::schema::SchemaType;
+use crate::cosmwasm::types::{CosmWasmFuzzState, CosmWasmInfantStateState, CosmWasmStagedVMState};
+use crate::cosmwasm::vm::{CosmWasmContract, CosmWasmVM};
+use crate::cosmwasm::vm_state::CosmWasmVMState;
+use crate::generic_vm::vm_executor::GenericVM;
+use crate::mutation_utils::ConstantPoolMetadata;
+use crate::state::HasCaller;
+use crate::state_input::StagedVMState;
+use cosmwasm_vm::testing::MockApi;
+use libafl::corpus::{Corpus, Testcase};
+use libafl::prelude::{HasCorpus, HasMetadata};
+use libafl::schedulers::Scheduler;
+use std::path::Path;
+use std::time::Duration;
+
+pub struct CosmWasmCorpusInitializer<'a> {
+    pub state: &'a mut CosmWasmFuzzState,
+    pub executor: &'a mut CosmWasmVM<CosmWasmFuzzState>,
+    pub scheduler: &'a dyn Scheduler<CosmWasmInput, CosmWasmFuzzState>,
+    pub infant_scheduler: &'a dyn Scheduler<CosmWasmStagedVMState, CosmWasmInfantStateState>,
+}
+
+impl<'a> CosmWasmCorpusInitializer<'a> {
+    pub fn new(
+        state: &'a mut CosmWasmFuzzState,
+        executor: &'a mut CosmWasmVM<CosmWasmFuzzState>,
+        scheduler: &'a dyn Scheduler<CosmWasmInput, CosmWasmFuzzState>,
+        infant_scheduler: &'a dyn Scheduler<CosmWasmStagedVMState, CosmWasmInfantStateState>,
+    ) -> Self {
+        Self {
+            state,
+            executor,
+            scheduler,
+            infant_scheduler,
+        }
+    }
+
+    /// Schema of `artifacts/cw20_base.wasm` is `<schema_dir>/cw20-base.json` (or `cw20_base.json`)
+    fn find_schema(wasm_path: &str, schema_dir: &Option<String>) -> Option<String> {
+        let dir = schema_dir.as_ref()?;
+        let stem = Path::new(wasm_path).file_stem()?.to_str()?;
+        [stem.to_string(), stem.replace('_', "-")]
+            .iter()
+            .map(|name| Path::new(dir).join(format!("{}.json", name)))
+            .find(|path| path.exists())
+            .map(|path| path.to_str().unwrap().to_string())
+    }
+
+    /// Instantiate all contracts matching the glob and add their execute messages to the corpus
+    pub fn setup(&mut self, target: String, schema_dir: Option<String>) {
+        let api = MockApi::default();
+        for i in 0..3 {
+            let caller = api.addr_make(&format!("caller{}", i));
+            self.state.add_caller(&caller);
+        }
+        self.state.metadata_mut().insert(ConstantPoolMetadata::new());
+
+        let mut deployed = vec![];
+        for path in glob::glob(&target).expect("invalid glob pattern") {
+            let path = path.unwrap();
+            let path = path.to_str().unwrap();
+            if !path.ends_with(".wasm") {
+                continue;
+            }
+            let schema = Self::find_schema(path, &schema_dir);
+            if schema.is_none() {
+                warn!("no schema found for {}, skipping", path);
+                continue;
+            }
+            let contract = CosmWasmContract::from_files(path, schema.as_deref());
+            let address = api.addr_make(path);
+            match self.executor.deploy(contract.clone(), None, address, self.state) {
+                Some(address) => {
+                    info!("deployed {} at {}", path, address);
+                    deployed.push((address, contract));
+                }
+                None => warn!("failed to deploy {}", path),
+            }
+        }
+        assert!(!deployed.is_empty(), "no contract found");
+
+        // storage after instantiation is the initial state
+        let mut default_state = CosmWasmVMState::new();
+        default_state.storage = self.executor.genesis_storage.clone();
+        let mut tc = Testcase::new(StagedVMState::new_with_state(default_state));
+        tc.set_exec_time(Duration::from_secs(0));
+        let idx = self
+            .state
+            .infant_states_state
+            .corpus_mut()
+            .add(tc)
+            .expect("failed to add");
+        self.infant_scheduler
+            .on_add(&mut self.state.infant_states_state, idx)
+            .expect("failed to call infant scheduler on_add");
+
+        for (address, contract) in deployed {
+            let msg_type = match contract.execute_msg {
+                Some(msg_type) => msg_type,
+                None => continue,
+            };
+            // one seed per variant of the execute message
+            let variants = match &msg_type {
+                SchemaType::Enum(variants) => variants.clone(),
+                _ => vec![],
+            };
+            for (name, payload) in variants {
+                let msg = match payload {
+                    None => serde_json::json!(name),
+                    Some(payload) => serde_json::json!({ name: payload.default_value(self.state) }),
+                };
+                let input = CosmWasmInput {
+                    contract: address.clone(),
+                    msg,
+                    msg_type: msg_type.clone(),
+                    funds: 0,
+                    caller: self.state.get_rand_caller(),
+                    vm_state: StagedVMState::new_uninitialized(),
+                    vm_state_idx: 0,
+                };
+                let mut tc = Testcase::new(input);
+                tc.set_exec_time(Duration::from_secs(0));
+                let idx = self.state.add_tx_to_corpus(tc).expect("failed to add input to corpus");
+                self.scheduler
+                    .on_add(self.state, idx)
+                    .expect("failed to call scheduler on_add");
+            }
+        }
+    }
+}
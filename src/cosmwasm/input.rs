@@ -0,0 +1,185 @@
+use crate::cosmwasm::schema::{mutate_value, SchemaType};
+use crate::cosmwasm::types::{CosmWasmAddress, CosmWasmLoc, CosmWasmStagedVMState};
+use crate::cosmwasm::vm::COSMWASM_DENOM;
+use crate::cosmwasm::vm_state::CosmWasmVMState;
+use crate::evm::abi::BoxedABI;
+use crate::evm::types::EVMU256;
+use crate::generic_vm::vm_executor::ExecutionResult;
+use crate::input::{ConciseSerde, VMInputT};
+use crate::state::{HasCaller, HasItyState};
+use libafl::inputs::Input;
+use libafl::prelude::{HasMaxSize, HasMetadata, HasRand, MutationResult, Rand, State};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::any;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CosmWasmInput {
+    pub contract: CosmWasmAddress,
+    /// the `ExecuteMsg`, always conforms to `msg_type`
+    pub msg: Value,
+    pub msg_type: SchemaType,
+    /// amount of native tokens sent along with the message
+    pub funds: u128,
+    pub caller: CosmWasmAddress,
+    pub vm_state: CosmWasmStagedVMState,
+    pub vm_state_idx: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConciseCosmWasmInput {
+    pub contract: CosmWasmAddress,
+    pub msg: Value,
+    pub funds: u128,
+    pub caller: CosmWasmAddress,
+}
+
+impl ConciseSerde for ConciseCosmWasmInput {
+    fn serialize_concise(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Failed to serialize concise input")
+    }
+
+    fn deserialize_concise(data: &[u8]) -> Self {
+        serde_json::from_slice(data).expect("Failed to deserialize concise input")
+    }
+
+    fn serialize_string(&self) -> String {
+        let funds = if self.funds > 0 {
+            format!(" with {}{}", self.funds, COSMWASM_DENOM)
+        } else {
+            String::new()
+        };
+        format!("{} => {} {}{}", self.caller, self.contract, self.msg, funds)
+    }
+}
+
+impl CosmWasmInput {
+    pub fn msg_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.msg).expect("Failed to serialize message")
+    }
+}
+
+impl Input for CosmWasmInput {
+    fn generate_name(&self, idx: usize) -> String {
+        format!("{}_{}", idx, self.contract)
+    }
+}
+
+impl VMInputT<CosmWasmVMState, CosmWasmLoc, CosmWasmAddress, ConciseCosmWasmInput> for CosmWasmInput {
+    fn mutate<S>(&mut self, state: &mut S) -> MutationResult
+    where
+        S: State
+            + HasRand
+            + HasMaxSize
+            + HasItyState<CosmWasmLoc, CosmWasmAddress, CosmWasmVMState, ConciseCosmWasmInput>
+            + HasCaller<CosmWasmAddress>
+            + HasMetadata,
+    {
+        match state.rand_mut().below(10) {
+            0 => {
+                let old_caller = self.caller.clone();
+                self.caller = state.get_rand_caller();
+                if old_caller == self.caller {
+                    MutationResult::Skipped
+                } else {
+                    MutationResult::Mutated
+                }
+            }
+            1 => {
+                self.funds = match state.rand_mut().below(3) {
+                    0 => 0,
+                    1 => state.rand_mut().below(1000) as u128,
+                    _ => state.rand_mut().next() as u128,
+                };
+                MutationResult::Mutated
+            }
+            _ => mutate_value(state, &mut self.msg, &self.msg_type),
+        }
+    }
+
+    fn get_caller_mut(&mut self) -> &mut CosmWasmAddress {
+        &mut self.caller
+    }
+
+    fn get_caller(&self) -> CosmWasmAddress {
+        self.caller.clone()
+    }
+
+    fn set_caller(&mut self, caller: CosmWasmAddress) {
+        self.caller = caller;
+    }
+
+    fn get_contract(&self) -> CosmWasmAddress {
+        self.contract.clone()
+    }
+
+    fn get_state(&self) -> &CosmWasmVMState {
+        &self.vm_state.state
+    }
+
+    fn get_state_mut(&mut self) -> &mut CosmWasmVMState {
+        &mut self.vm_state.state
+    }
+
+    fn set_staged_state(&mut self, state: CosmWasmStagedVMState, idx: usize) {
+        self.vm_state = state;
+        self.vm_state_idx = idx;
+    }
+
+    fn get_state_idx(&self) -> usize {
+        self.vm_state_idx
+    }
+
+    fn get_staged_state(&self) -> &CosmWasmStagedVMState {
+        &self.vm_state
+    }
+
+    fn set_as_post_exec(&mut self, _out_size: usize) {
+        unreachable!("CosmWasm has no post execution")
+    }
+
+    fn is_step(&self) -> bool {
+        false
+    }
+
+    fn set_step(&mut self, _gate: bool) {}
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+
+    fn fav_factor(&self) -> f64 {
+        f64::MAX
+    }
+
+    #[cfg(feature = "evm")]
+    fn get_data_abi(&self) -> Option<BoxedABI> {
+        unreachable!("CosmWasm VM does not have an EVM ABI")
+    }
+
+    #[cfg(feature = "evm")]
+    fn get_data_abi_mut(&mut self) -> &mut Option<BoxedABI> {
+        unreachable!("CosmWasm VM does not have an EVM ABI")
+    }
+
+    #[cfg(feature = "evm")]
+    fn get_txn_value_temp(&self) -> Option<EVMU256> {
+        None
+    }
+
+    fn get_direct_data(&self) -> Vec<u8> {
+        self.msg_bytes()
+    }
+
+    fn get_concise<Out: Default>(
+        &self,
+        _exec_res: &ExecutionResult<CosmWasmLoc, CosmWasmAddress, CosmWasmVMState, Out, ConciseCosmWasmInput>,
+    ) -> ConciseCosmWasmInput {
+        ConciseCosmWasmInput {
+            contract: self.contract.clone(),
+            msg: self.msg.clone(),
+            funds: self.funds,
+            caller: self.caller.clone(),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+/// Coverage instrumentation of CosmWasm contracts
+///
+/// Every instruction sequence (function body, block, loop body, if / else arm) is prefixed with
+/// a call to the imported `ityfuzz.cov(id)`, which marks the edge from the previously visited
+/// sequence in the coverage map.
+///
+/// Integer comparisons (`i32.eq`, `i64.lt_u`, ...) are replaced by calls to the imported
+/// `ityfuzz.cmp_i32` / `ityfuzz.cmp_i64(a, b, id << 4 | op)`, which record the distance between
+/// the operands in the cmp map and return the result of the comparison.
+use crate::generic_vm::vm_executor::MAP_SIZE;
+use std::mem::discriminant;
+use walrus::ir::{dfs_pre_order_mut, BinaryOp, Call, Const, Instr, InstrLocId, InstrSeq, Value, VisitorMut};
+use walrus::{FunctionId, Module, ValType};
+
+pub const COV_IMPORT_MODULE: &str = "ityfuzz";
+pub const COV_IMPORT_NAME: &str = "cov";
+pub const CMP_I32_IMPORT_NAME: &str = "cmp_i32";
+pub const CMP_I64_IMPORT_NAME: &str = "cmp_i64";
+
+/// Comparisons reported to the cmp hooks, the op of a comparison is its index
+const CMP_OPS: [(BinaryOp, BinaryOp); 10] = [
+    (BinaryOp::I32Eq, BinaryOp::I64Eq),
+    (BinaryOp::I32Ne, BinaryOp::I64Ne),
+    (BinaryOp::I32LtS, BinaryOp::I64LtS),
+    (BinaryOp::I32LtU, BinaryOp::I64LtU),
+    (BinaryOp::I32GtS, BinaryOp::I64GtS),
+    (BinaryOp::I32GtU, BinaryOp::I64GtU),
+    (BinaryOp::I32LeS, BinaryOp::I64LeS),
+    (BinaryOp::I32LeU, BinaryOp::I64LeU),
+    (BinaryOp::I32GeS, BinaryOp::I64GeS),
+    (BinaryOp::I32GeU, BinaryOp::I64GeU),
+];
+
+pub static mut CW_COV_MAP: [u8; MAP_SIZE] = [0u8; MAP_SIZE];
+pub static mut CW_CMP_MAP: [u128; MAP_SIZE] = [u128::MAX; MAP_SIZE];
+/// whether hooks record anything, off during static calls
+pub static mut CW_TRACING: bool = true;
+/// id of the previously visited instruction sequence, shifted as in AFL
+pub static mut CW_PREV_LOC: usize = 0;
+/// distinguishes identical contracts deployed at different addresses
+pub static mut CW_COV_SALT: usize = 0;
+
+/// Called by instrumented contracts
+pub fn cov_hook(id: u32) {
+    unsafe {
+        if !CW_TRACING {
+            return;
+        }
+        let cur = (id as usize) ^ CW_COV_SALT;
+        let idx = (cur ^ CW_PREV_LOC) % MAP_SIZE;
+        CW_COV_MAP[idx] = CW_COV_MAP[idx].wrapping_add(1);
+        CW_PREV_LOC = cur >> 1;
+    }
+}
+
+fn is_signed(op: usize) -> bool {
+    matches!(op, 2 | 4 | 6 | 8)
+}
+
+fn compare(op: usize, a: i128, b: i128) -> bool {
+    match op {
+        0 => a == b,
+        1 => a != b,
+        2 | 3 => a < b,
+        4 | 5 => a > b,
+        6 | 7 => a <= b,
+        _ => a >= b,
+    }
+}
+
+fn cmp_hook(a: i128, b: i128, id_op: u32) -> i32 {
+    let (id, op) = ((id_op >> 4) as usize, (id_op & 0xf) as usize);
+    unsafe {
+        if CW_TRACING {
+            let idx = (id ^ CW_COV_SALT) % MAP_SIZE;
+            let distance = a.abs_diff(b);
+            if distance < CW_CMP_MAP[idx] {
+                CW_CMP_MAP[idx] = distance;
+            }
+        }
+    }
+    compare(op, a, b) as i32
+}
+
+/// Called by instrumented contracts in place of 32-bit comparisons
+pub fn cmp_hook_i32(a: i32, b: i32, id_op: i32) -> i32 {
+    if is_signed(id_op as usize & 0xf) {
+        cmp_hook(a as i128, b as i128, id_op as u32)
+    } else {
+        cmp_hook(a as u32 as i128, b as u32 as i128, id_op as u32)
+    }
+}
+
+/// Called by instrumented contracts in place of 64-bit comparisons
+pub fn cmp_hook_i64(a: i64, b: i64, id_op: i32) -> i32 {
+    if is_signed(id_op as usize & 0xf) {
+        cmp_hook(a as i128, b as i128, id_op as u32)
+    } else {
+        cmp_hook(a as u64 as i128, b as u64 as i128, id_op as u32)
+    }
+}
+
+struct CoverageInserter {
+    cov_func: FunctionId,
+    cmp_i32_func: FunctionId,
+    cmp_i64_func: FunctionId,
+    next_id: u32,
+    next_cmp_id: u32,
+}
+
+impl VisitorMut for CoverageInserter {
+    fn start_instr_seq_mut(&mut self, seq: &mut InstrSeq) {
+        let mut instrs = Vec::with_capacity(seq.instrs.len());
+        for (instr, loc) in seq.instrs.drain(..) {
+            let cmp = match &instr {
+                Instr::Binop(binop) => CMP_OPS.iter().enumerate().find_map(|(op, (op32, op64))| {
+                    if discriminant(&binop.op) == discriminant(op32) {
+                        Some((op, self.cmp_i32_func))
+                    } else if discriminant(&binop.op) == discriminant(op64) {
+                        Some((op, self.cmp_i64_func))
+                    } else {
+                        None
+                    }
+                }),
+                _ => None,
+            };
+            match cmp {
+                Some((op, func)) => {
+                    let id_op = (self.next_cmp_id << 4) | op as u32;
+                    self.next_cmp_id += 1;
+                    instrs.push((Instr::Const(Const { value: Value::I32(id_op as i32) }), loc));
+                    instrs.push((Instr::Call(Call { func }), loc));
+                }
+                None => instrs.push((instr, loc)),
+            }
+        }
+        seq.instrs = instrs;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        seq.instrs.insert(
+            0,
+            (
+                Instr::Const(Const { value: Value::I32(id as i32) }),
+                InstrLocId::default(),
+            ),
+        );
+        seq.instrs
+            .insert(1, (Instr::Call(Call { func: self.cov_func }), InstrLocId::default()));
+    }
+}
+
+/// Instrument a contract, returns the instrumented wasm and the number of instrumented sequences
+pub fn instrument(wasm: &[u8]) -> (Vec<u8>, u32) {
+    let mut module = Module::from_buffer(wasm).expect("invalid wasm module");
+    let ty = module.types.add(&[ValType::I32], &[]);
+    let (cov_func, _) = module.add_import_func(COV_IMPORT_MODULE, COV_IMPORT_NAME, ty);
+    let ty = module.types.add(&[ValType::I32, ValType::I32, ValType::I32], &[ValType::I32]);
+    let (cmp_i32_func, _) = module.add_import_func(COV_IMPORT_MODULE, CMP_I32_IMPORT_NAME, ty);
+    let ty = module.types.add(&[ValType::I64, ValType::I64, ValType::I32], &[ValType::I32]);
+    let (cmp_i64_func, _) = module.add_import_func(COV_IMPORT_MODULE, CMP_I64_IMPORT_NAME, ty);
+
+    let mut inserter = CoverageInserter {
+        cov_func,
+        cmp_i32_func,
+        cmp_i64_func,
+        next_id: 0,
+        next_cmp_id: 0,
+    };
+    for (_, func) in module.funcs.iter_local_mut() {
+        let entry = func.entry_block();
+        dfs_pre_order_mut(&mut inserter, func, entry);
+    }
+    (module.emit_wasm(), inserter.next_id)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instrument() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (export "abs") (param i32) (result i32)
+                    local.get 0
+                    i32.const 0
+                    i32.lt_s
+                    if (result i32)
+                        i32.const 0
+                        local.get 0
+                        i32.sub
+                    else
+                        local.get 0
+                    end))"#,
+        )
+        .unwrap();
+        let (instrumented, count) = instrument(&wasm);
+        // function body, then and else arms
+        assert_eq!(count, 3);
+        let module = Module::from_buffer(&instrumented).unwrap();
+        assert!(module
+            .imports
+            .iter()
+            .any(|import| import.module == COV_IMPORT_MODULE && import.name == COV_IMPORT_NAME));
+        // i32.lt_s is replaced by a call to the hook
+        let abs = module.funcs.iter_local().next().unwrap().1;
+        let body = abs.block(abs.entry_block());
+        assert!(!body.instrs.iter().any(|(instr, _)| matches!(instr, Instr::Binop(_))));
+    }
+
+    #[test]
+    fn test_cmp_hook() {
+        unsafe {
+            CW_COV_SALT = 0;
+            CW_CMP_MAP = [u128::MAX; MAP_SIZE];
+        }
+        // i32.lt_s -1 < 1, comparison 3
+        assert_eq!(cmp_hook_i32(-1, 1, (3 << 4) | 2), 1);
+        assert_eq!(unsafe { CW_CMP_MAP[3] }, 2);
+        // i32.lt_u 0xffffffff < 1
+        assert_eq!(cmp_hook_i32(-1, 1, (3 << 4) | 3), 0);
+        assert_eq!(unsafe { CW_CMP_MAP[3] }, 2);
+        // i64.eq (op 0), closer operands
+        assert_eq!(cmp_hook_i64(41, 42, 3 << 4), 0);
+        assert_eq!(unsafe { CW_CMP_MAP[3] }, 1);
+
+        unsafe {
+            CW_TRACING = false;
+        }
+        assert_eq!(cmp_hook_i64(42, 42, 3 << 4), 1);
+        assert_eq!(unsafe { CW_CMP_MAP[3] }, 1);
+        unsafe {
+            CW_TRACING = true;
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod corpus_initializer;
+pub mod input;
+pub mod instrument;
+pub mod oracles;
+pub mod schema;
+pub mod types;
+pub mod vm;
+pub mod vm_state;
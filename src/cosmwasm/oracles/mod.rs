@@ -0,0 +1,3 @@
+pub mod typed_bug;
+
+pub static TYPED_BUG_BUG_IDX: u64 = 4;
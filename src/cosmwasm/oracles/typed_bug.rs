@@ -0,0 +1,49 @@
+use crate::cosmwasm::input::{ConciseCosmWasmInput, CosmWasmInput};
+use crate::cosmwasm::oracles::TYPED_BUG_BUG_IDX;
+use crate::cosmwasm::types::{CosmWasmAddress, CosmWasmFuzzState, CosmWasmLoc, CosmWasmOracleCtx, CosmWasmOutput, CosmWasmSlotTy};
+use crate::cosmwasm::vm::CosmWasmContract;
+use crate::cosmwasm::vm_state::CosmWasmVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::Oracle;
+use itertools::Itertools;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Reports bugs emitted by contracts through the `AAAA__fuzzland_cosmwasm_bug` response attribute
+pub struct TypedBugOracle;
+
+impl TypedBugOracle {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Oracle<CosmWasmVMState, CosmWasmAddress, CosmWasmContract, Vec<u8>, CosmWasmLoc, CosmWasmSlotTy, CosmWasmOutput, CosmWasmInput, CosmWasmFuzzState, ConciseCosmWasmInput>
+for TypedBugOracle {
+    fn transition(&self, _ctx: &mut CosmWasmOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut CosmWasmOracleCtx<'_>,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if ctx.post_state.typed_bug.len() > 0 {
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[typed_bug] {:?} hit at contract {}\n",
+                    ctx.post_state.typed_bug,
+                    ctx.input.contract
+                ).as_str();
+            }
+            ctx.post_state.typed_bug.iter().map(|bug_id| {
+                let mut hasher = DefaultHasher::new();
+                bug_id.hash(&mut hasher);
+                (hasher.finish() << 8) + TYPED_BUG_BUG_IDX
+            }).collect_vec()
+        } else {
+            vec![]
+        }
+    }
+}
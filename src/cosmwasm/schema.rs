@@ -0,0 +1,368 @@
+/// Message types from the contract's JSON schema, and schema-aware generation / mutation of messages
+use crate::cosmwasm::types::CosmWasmAddress;
+use crate::mutation_utils::ConstantPoolMetadata;
+use crate::state::HasCaller;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use libafl::mutators::MutationResult;
+use libafl::prelude::{HasMetadata, HasRand, Rand};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+pub const COSMWASM_MAX_ARRAY_SIZE: usize = 10;
+/// definitions can be recursive, stop resolving after this depth
+const MAX_SCHEMA_DEPTH: usize = 8;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SchemaType {
+    String,
+    Bool,
+    /// JSON integer (u8 - u64, i8 - i64)
+    Integer { bits: u16, signed: bool },
+    /// integers encoded as decimal strings (Uint64, Uint128, Uint256, Int128, ...)
+    NumString { bits: u16, signed: bool },
+    /// Decimal, Decimal256
+    Decimal,
+    Addr,
+    /// base64 encoded bytes
+    Binary,
+    Array(Box<SchemaType>),
+    /// fields (name, type, required)
+    Object(Vec<(String, SchemaType, bool)>),
+    Optional(Box<SchemaType>),
+    /// variants (name, payload), unit variants have no payload
+    Enum(Vec<(String, Option<SchemaType>)>),
+    /// anything we don't understand, always `null`
+    Unknown,
+}
+
+impl SchemaType {
+    /// Parse a JSON schema node, `root` is the schema containing `definitions`
+    pub fn parse(node: &Value, root: &Value, depth: usize) -> Self {
+        if depth > MAX_SCHEMA_DEPTH {
+            return SchemaType::Unknown;
+        }
+        if let Some(reference) = node["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/definitions/");
+            return match name {
+                "Uint64" | "Uint128" | "Uint256" | "Uint512" | "Timestamp" => SchemaType::NumString {
+                    bits: name.trim_start_matches("Uint").parse().unwrap_or(64),
+                    signed: false,
+                },
+                "Int64" | "Int128" | "Int256" | "Int512" => SchemaType::NumString {
+                    bits: name.trim_start_matches("Int").parse().unwrap_or(64),
+                    signed: true,
+                },
+                "Decimal" | "Decimal256" => SchemaType::Decimal,
+                "Addr" => SchemaType::Addr,
+                "Binary" => SchemaType::Binary,
+                _ => Self::parse(&root["definitions"][name], root, depth + 1),
+            };
+        }
+        // Option<T> is either `anyOf: [T, null]` or `type: [T, "null"]`
+        if let Some(any_of) = node["anyOf"].as_array() {
+            let inner = any_of.iter().find(|x| x["type"] != "null").cloned().unwrap_or(Value::Null);
+            return SchemaType::Optional(Box::new(Self::parse(&inner, root, depth + 1)));
+        }
+        if let Some(types) = node["type"].as_array() {
+            let mut inner = node.clone();
+            inner["type"] = types.iter().find(|x| *x != "null").cloned().unwrap_or(Value::Null);
+            return SchemaType::Optional(Box::new(Self::parse(&inner, root, depth + 1)));
+        }
+        if let Some(one_of) = node["oneOf"].as_array() {
+            return SchemaType::Enum(
+                one_of
+                    .iter()
+                    .flat_map(|variant| match &variant["enum"] {
+                        // unit variants
+                        Value::Array(names) => names
+                            .iter()
+                            .filter_map(|name| name.as_str().map(|n| (n.to_string(), None)))
+                            .collect::<Vec<_>>(),
+                        _ => variant["required"]
+                            .get(0)
+                            .and_then(|name| name.as_str())
+                            .map(|name| {
+                                vec![(
+                                    name.to_string(),
+                                    Some(Self::parse(&variant["properties"][name], root, depth + 1)),
+                                )]
+                            })
+                            .unwrap_or_default(),
+                    })
+                    .collect(),
+            );
+        }
+        match node["type"].as_str() {
+            Some("string") => match &node["enum"] {
+                Value::Array(names) => SchemaType::Enum(
+                    names
+                        .iter()
+                        .filter_map(|name| name.as_str().map(|n| (n.to_string(), None)))
+                        .collect(),
+                ),
+                _ => SchemaType::String,
+            },
+            Some("boolean") => SchemaType::Bool,
+            Some("integer") => {
+                let format = node["format"].as_str().unwrap_or("uint64");
+                SchemaType::Integer {
+                    bits: format.trim_start_matches(|c: char| c.is_alphabetic()).parse().unwrap_or(64),
+                    signed: !format.starts_with('u'),
+                }
+            }
+            Some("array") => SchemaType::Array(Box::new(Self::parse(&node["items"], root, depth + 1))),
+            Some("object") => {
+                let required = node["required"]
+                    .as_array()
+                    .map(|x| x.iter().filter_map(|x| x.as_str()).map(|x| x.to_string()).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                SchemaType::Object(
+                    node["properties"]
+                        .as_object()
+                        .map(|props| {
+                            props
+                                .iter()
+                                .map(|(name, prop)| {
+                                    (name.clone(), Self::parse(prop, root, depth + 1), required.contains(name))
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                )
+            }
+            _ => SchemaType::Unknown,
+        }
+    }
+
+    pub fn default_value<S: HasCaller<CosmWasmAddress>>(&self, state: &mut S) -> Value {
+        match self {
+            SchemaType::String => json!(""),
+            SchemaType::Bool => json!(false),
+            SchemaType::Integer { .. } => json!(0),
+            SchemaType::NumString { .. } => json!("0"),
+            SchemaType::Decimal => json!("0"),
+            SchemaType::Addr => json!(state.get_rand_address()),
+            SchemaType::Binary => json!(""),
+            SchemaType::Array(_) => json!([]),
+            SchemaType::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .filter(|(_, _, required)| *required)
+                    .map(|(name, ty, _)| (name.clone(), ty.default_value(state)))
+                    .collect(),
+            ),
+            SchemaType::Optional(_) => Value::Null,
+            SchemaType::Enum(variants) => match variants.get(0) {
+                Some((name, None)) => json!(name),
+                Some((name, Some(payload))) => json!({ name: payload.default_value(state) }),
+                None => Value::Null,
+            },
+            SchemaType::Unknown => Value::Null,
+        }
+    }
+}
+
+/// Execute messages of a contract, i.e., variants of `ExecuteMsg` in the schema.
+/// Supports both the combined schema (`schema/<contract>.json`) and `schema/execute_msg.json`
+pub fn parse_execute_msg(schema: &Value) -> Option<SchemaType> {
+    let (node, root) = match schema.get("execute") {
+        Some(execute) => (execute, execute),
+        None => (schema, schema),
+    };
+    match SchemaType::parse(node, root, 0) {
+        SchemaType::Unknown => None,
+        ty => Some(ty),
+    }
+}
+
+/// Instantiate message of a contract from the combined schema
+pub fn parse_instantiate_msg(schema: &Value) -> Option<SchemaType> {
+    schema
+        .get("instantiate")
+        .map(|instantiate| SchemaType::parse(instantiate, instantiate, 0))
+}
+
+fn rand_u128<S: HasRand>(state: &mut S) -> u128 {
+    (state.rand_mut().next() as u128) << 64 | state.rand_mut().next() as u128
+}
+
+/// Integers that often reach edge cases, bounded by the type
+fn mutate_integer<S: HasRand + HasMetadata>(state: &mut S, orig: &str, bits: u16, signed: bool) -> String {
+    let bits = bits.min(128);
+    let max = if bits == 128 { u128::MAX } else { (1u128 << bits) - 1 } >> (signed as u32);
+    let orig = orig.parse::<u128>().unwrap_or(0);
+    let constants = state
+        .metadata()
+        .get::<ConstantPoolMetadata>()
+        .map(|x| x.constants.len())
+        .unwrap_or(0);
+    let new = match state.rand_mut().below(6) {
+        0 => 0,
+        1 => max,
+        2 => orig.saturating_add(state.rand_mut().below(16) as u128 + 1).min(max),
+        3 => orig.saturating_sub(state.rand_mut().below(16) as u128 + 1),
+        4 if constants > 0 => {
+            let idx = state.rand_mut().below(constants as u64) as usize;
+            let constant = &state.metadata().get::<ConstantPoolMetadata>().unwrap().constants[idx];
+            String::from_utf8_lossy(constant).parse::<u128>().unwrap_or(0).min(max)
+        }
+        _ => rand_u128(state) % (max.saturating_add(1).max(1)),
+    };
+    if signed && state.rand_mut().below(4) == 0 && new != 0 {
+        format!("-{}", new)
+    } else {
+        new.to_string()
+    }
+}
+
+/// Mutate a message of given type, the message always conforms to the schema
+pub fn mutate_value<S>(state: &mut S, value: &mut Value, ty: &SchemaType) -> MutationResult
+where
+    S: HasRand + HasMetadata + HasCaller<CosmWasmAddress>,
+{
+    match ty {
+        SchemaType::String => {
+            let constants = state.metadata().get::<ConstantPoolMetadata>().map(|x| x.constants.clone());
+            *value = match constants {
+                Some(constants) if !constants.is_empty() && state.rand_mut().below(2) == 0 => {
+                    let idx = state.rand_mut().below(constants.len() as u64) as usize;
+                    json!(String::from_utf8_lossy(&constants[idx]))
+                }
+                _ => json!(format!("{}", state.rand_mut().below(1000))),
+            };
+        }
+        SchemaType::Bool => *value = json!(!value.as_bool().unwrap_or(false)),
+        SchemaType::Integer { bits, signed } => {
+            let new = mutate_integer(state, &value.to_string(), *bits, *signed);
+            *value = serde_json::from_str(&new).unwrap_or(json!(0));
+        }
+        SchemaType::NumString { bits, signed } => {
+            let orig = value.as_str().unwrap_or("0").to_string();
+            *value = json!(mutate_integer(state, &orig, *bits, *signed));
+        }
+        SchemaType::Decimal => {
+            let int = mutate_integer(state, "0", 64, false);
+            *value = json!(format!("{}.{}", int, state.rand_mut().below(1000)));
+        }
+        SchemaType::Addr => *value = json!(state.get_rand_address()),
+        SchemaType::Binary => {
+            let len = state.rand_mut().below(64) as usize;
+            let bytes = (0..len).map(|_| state.rand_mut().below(256) as u8).collect::<Vec<_>>();
+            *value = json!(STANDARD.encode(bytes));
+        }
+        SchemaType::Array(inner) => {
+            let items = match value.as_array_mut() {
+                Some(items) => items,
+                None => return MutationResult::Skipped,
+            };
+            match state.rand_mut().below(3) {
+                0 if items.len() < COSMWASM_MAX_ARRAY_SIZE => {
+                    let mut item = inner.default_value(state);
+                    mutate_value(state, &mut item, inner);
+                    items.push(item);
+                }
+                1 if !items.is_empty() => {
+                    items.pop();
+                }
+                _ => {
+                    if items.is_empty() {
+                        return MutationResult::Skipped;
+                    }
+                    let idx = state.rand_mut().below(items.len() as u64) as usize;
+                    return mutate_value(state, &mut items[idx], inner);
+                }
+            }
+        }
+        SchemaType::Object(fields) => {
+            if fields.is_empty() {
+                return MutationResult::Skipped;
+            }
+            let (name, field_ty, required) = &fields[state.rand_mut().below(fields.len() as u64) as usize];
+            let obj = match value.as_object_mut() {
+                Some(obj) => obj,
+                None => return MutationResult::Skipped,
+            };
+            // optional fields can be omitted
+            if !*required && obj.contains_key(name) && state.rand_mut().below(4) == 0 {
+                obj.remove(name);
+                return MutationResult::Mutated;
+            }
+            let field = obj.entry(name.clone()).or_insert_with(|| field_ty.default_value(state));
+            return mutate_value(state, field, field_ty);
+        }
+        SchemaType::Optional(inner) => {
+            if !value.is_null() && state.rand_mut().below(4) == 0 {
+                *value = Value::Null;
+            } else {
+                if value.is_null() {
+                    *value = inner.default_value(state);
+                }
+                return mutate_value(state, value, inner);
+            }
+        }
+        SchemaType::Enum(variants) => {
+            if variants.is_empty() {
+                return MutationResult::Skipped;
+            }
+            let current = variants.iter().position(|(name, payload)| match payload {
+                None => value.as_str() == Some(name),
+                Some(_) => value.get(name).is_some(),
+            });
+            match current {
+                Some(idx) if variants[idx].1.is_some() && state.rand_mut().below(4) != 0 => {
+                    let (name, payload) = &variants[idx];
+                    return mutate_value(state, &mut value[name], payload.as_ref().unwrap());
+                }
+                _ => {
+                    let idx = state.rand_mut().below(variants.len() as u64) as usize;
+                    *value = match &variants[idx] {
+                        (name, None) => json!(name),
+                        (name, Some(payload)) => {
+                            let mut obj = Map::new();
+                            obj.insert(name.clone(), payload.default_value(state));
+                            Value::Object(obj)
+                        }
+                    };
+                }
+            }
+        }
+        SchemaType::Unknown => return MutationResult::Skipped,
+    }
+    MutationResult::Mutated
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_execute_msg() {
+        let schema: Value = serde_json::from_str(
+            r##"{
+                "execute": {
+                    "oneOf": [
+                        {"type": "string", "enum": ["reset"]},
+                        {"type": "object", "required": ["transfer"], "properties": {
+                            "transfer": {"type": "object", "required": ["amount", "recipient"], "properties": {
+                                "amount": {"$ref": "#/definitions/Uint128"},
+                                "recipient": {"type": "string"},
+                                "memo": {"type": ["string", "null"]}
+                            }}
+                        }}
+                    ],
+                    "definitions": {"Uint128": {"type": "string"}}
+                }
+            }"##,
+        )
+        .unwrap();
+        let ty = parse_execute_msg(&schema).unwrap();
+        match ty {
+            SchemaType::Enum(variants) => {
+                assert_eq!(variants.len(), 2);
+                assert_eq!(variants[0], ("reset".to_string(), None));
+                assert_eq!(variants[1].0, "transfer");
+            }
+            _ => panic!("execute msg should be an enum"),
+        }
+    }
+}
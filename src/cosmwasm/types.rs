@@ -0,0 +1,49 @@
+use crate::cosmwasm::input::{ConciseCosmWasmInput, CosmWasmInput};
+use crate::cosmwasm::vm::CosmWasmContract;
+use crate::cosmwasm::vm_state::CosmWasmVMState;
+use crate::oracle::OracleCtx;
+use crate::state::{FuzzState, InfantStateState};
+use crate::state_input::StagedVMState;
+use serde::{Deserialize, Serialize};
+
+/// Bech32 address of a contract or an account
+pub type CosmWasmAddress = String;
+/// Location of a message, i.e., the address of the contract handling it
+pub type CosmWasmLoc = String;
+pub type CosmWasmSlotTy = u128;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CosmWasmOutput {
+    /// `data` of the response, or the error message if the execution failed
+    pub data: Vec<u8>,
+    /// attributes of the response and its events
+    pub attributes: Vec<(String, String)>,
+}
+
+pub type CosmWasmStagedVMState =
+    StagedVMState<CosmWasmLoc, CosmWasmAddress, CosmWasmVMState, ConciseCosmWasmInput>;
+pub type CosmWasmInfantStateState =
+    InfantStateState<CosmWasmLoc, CosmWasmAddress, CosmWasmVMState, ConciseCosmWasmInput>;
+
+pub type CosmWasmFuzzState = FuzzState<
+    CosmWasmInput,
+    CosmWasmVMState,
+    CosmWasmLoc,
+    CosmWasmAddress,
+    CosmWasmOutput,
+    ConciseCosmWasmInput,
+>;
+
+pub type CosmWasmOracleCtx<'a> = OracleCtx<
+    'a,
+    CosmWasmVMState,
+    CosmWasmAddress,
+    CosmWasmContract,
+    Vec<u8>,
+    CosmWasmLoc,
+    CosmWasmSlotTy,
+    CosmWasmOutput,
+    CosmWasmInput,
+    CosmWasmFuzzState,
+    ConciseCosmWasmInput,
+>;
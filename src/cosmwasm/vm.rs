@@ -0,0 +1,482 @@
+/// Executes CosmWasm contracts on cosmwasm-vm with mocked chain environment
+use crate::cosmwasm::input::{ConciseCosmWasmInput, CosmWasmInput};
+use tracing::warn;
+use crate::cosmwasm::instrument::{
+    cmp_hook_i32, cmp_hook_i64, cov_hook, instrument, CMP_I32_IMPORT_NAME, CMP_I64_IMPORT_NAME, COV_IMPORT_MODULE,
+    COV_IMPORT_NAME, CW_CMP_MAP, CW_COV_MAP, CW_COV_SALT, CW_PREV_LOC, CW_TRACING,
+};
+use crate::cosmwasm::schema::{parse_execute_msg, parse_instantiate_msg, SchemaType};
+use crate::cosmwasm::types::{CosmWasmAddress, CosmWasmLoc, CosmWasmOutput, CosmWasmSlotTy};
+use crate::cosmwasm::vm_state::CosmWasmVMState;
+use crate::generic_vm::vm_executor::{ExecutionResult, GenericVM, MAP_SIZE};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::mutation_utils::ConstantPoolMetadata;
+use crate::state::HasCaller;
+use crate::state_input::StagedVMState;
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{coins, Addr, ContractResult, CosmosMsg, Empty, Order, Record, Response, WasmMsg};
+use cosmwasm_vm::internals::{compile, instance_from_module, make_compiling_engine};
+use cosmwasm_vm::testing::{MockApi, MockQuerier, MockStorage};
+use cosmwasm_vm::{call_execute, call_instantiate, call_query, Backend, BackendResult, Instance, Size, Storage};
+use libafl::prelude::HasMetadata;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use wasmer::{Engine, Exports, Function, Module, Store};
+
+pub static mut CW_READ_MAP: [bool; MAP_SIZE] = [false; MAP_SIZE];
+pub static mut CW_WRITE_MAP: [u8; MAP_SIZE] = [0u8; MAP_SIZE];
+pub static mut CW_STATE_CHANGED: bool = false;
+
+/// CosmWasm gas available to each execution
+pub const COSMWASM_GAS_LIMIT: u64 = 1_000_000_000_000;
+pub const COSMWASM_MEMORY_LIMIT: Size = Size::mebi(64);
+/// Native token sent along with messages
+pub const COSMWASM_DENOM: &str = "ucosm";
+/// Maximum depth of contract calls dispatched through `WasmMsg::Execute`
+pub const COSMWASM_MAX_CALL_DEPTH: usize = 8;
+
+/// Key of the response attribute contracts emit to report a typed bug, the value is the bug ID
+pub const TYPED_BUG_ATTRIBUTE: &str = "AAAA__fuzzland_cosmwasm_bug";
+
+type CosmWasmBackend = Backend<MockApi, TracedStorage, MockQuerier>;
+type CosmWasmInstance = Instance<MockApi, TracedStorage, MockQuerier>;
+
+/// A CosmWasm contract with its message schema
+#[derive(Clone, Debug)]
+pub struct CosmWasmContract {
+    /// instrumented wasm
+    pub code: Vec<u8>,
+    pub execute_msg: Option<SchemaType>,
+    pub instantiate_msg: Option<SchemaType>,
+}
+
+impl CosmWasmContract {
+    /// Load a contract from its wasm and the JSON schema generated by `cosmwasm-schema`
+    pub fn from_files(wasm_path: &str, schema_path: Option<&str>) -> Self {
+        let wasm = std::fs::read(wasm_path).unwrap_or_else(|_| panic!("failed to read {}", wasm_path));
+        let (code, _) = instrument(&wasm);
+        let schema = schema_path.map(|path| {
+            let json = std::fs::read_to_string(path).unwrap_or_else(|_| panic!("failed to read {}", path));
+            serde_json::from_str::<Value>(&json).expect("invalid schema")
+        });
+        Self {
+            code,
+            execute_msg: schema.as_ref().and_then(parse_execute_msg),
+            instantiate_msg: schema.as_ref().and_then(parse_instantiate_msg),
+        }
+    }
+
+    /// Printable strings in the data section, contracts compare messages against them
+    pub fn constants(&self) -> Vec<Vec<u8>> {
+        let module = walrus::Module::from_buffer(&self.code).expect("invalid wasm module");
+        module
+            .data
+            .iter()
+            .flat_map(|data| {
+                data.value
+                    .split(|b| !b.is_ascii_graphic())
+                    .filter(|s| s.len() >= 3 && s.len() <= 32)
+                    .map(|s| s.to_vec())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+pub struct DeployedCosmWasmContract {
+    pub module: Module,
+    pub execute_msg: Option<SchemaType>,
+}
+
+pub struct CosmWasmVM<S> {
+    pub engine: Engine,
+    pub contracts: HashMap<CosmWasmAddress, DeployedCosmWasmContract>,
+    /// storage after instantiation, which is the initial state to fuzz
+    pub genesis_storage: BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>,
+    _phantom: PhantomData<S>,
+}
+
+struct CosmWasmRunResult {
+    reverted: bool,
+    output: CosmWasmOutput,
+    storage: BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+fn map_key(contract: &str, key: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    contract.hash(&mut hasher);
+    key.hash(&mut hasher);
+    hasher.finish() as usize % MAP_SIZE
+}
+
+/// Storage of a contract marking the keys it reads in the read map
+pub struct TracedStorage {
+    pub contract: String,
+    pub storage: MockStorage,
+}
+
+impl TracedStorage {
+    fn mark_read(&self, key: &[u8]) {
+        unsafe {
+            if CW_TRACING {
+                CW_READ_MAP[map_key(&self.contract, key)] = true;
+            }
+        }
+    }
+}
+
+impl Storage for TracedStorage {
+    fn get(&self, key: &[u8]) -> BackendResult<Option<Vec<u8>>> {
+        self.mark_read(key);
+        self.storage.get(key)
+    }
+
+    fn scan(&mut self, start: Option<&[u8]>, end: Option<&[u8]>, order: Order) -> BackendResult<u32> {
+        self.storage.scan(start, end, order)
+    }
+
+    fn next(&mut self, iterator_id: u32) -> BackendResult<Option<Record>> {
+        let (record, gas) = self.storage.next(iterator_id);
+        if let Ok(Some((key, _))) = &record {
+            self.mark_read(key);
+        }
+        (record, gas)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) -> BackendResult<()> {
+        self.storage.set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> BackendResult<()> {
+        self.storage.remove(key)
+    }
+}
+
+impl<S> CosmWasmVM<S> {
+    pub fn new() -> Self {
+        Self {
+            engine: make_compiling_engine(Some(COSMWASM_MEMORY_LIMIT)),
+            contracts: HashMap::new(),
+            genesis_storage: BTreeMap::new(),
+            _phantom: Default::default(),
+        }
+    }
+
+    fn instance(&self, address: &str, storage: Option<&BTreeMap<Vec<u8>, Vec<u8>>>) -> CosmWasmInstance {
+        let contract = self.contracts.get(address).expect("contract not deployed");
+        let mut backend_storage = MockStorage::new();
+        for (k, v) in storage.into_iter().flatten() {
+            backend_storage.set(k, v).0.expect("failed to set storage");
+        }
+        let backend: CosmWasmBackend = Backend {
+            api: MockApi::default(),
+            storage: TracedStorage {
+                contract: address.to_string(),
+                storage: backend_storage,
+            },
+            querier: MockQuerier::new(&[]),
+        };
+
+        let mut store = Store::new(self.engine.clone());
+        let mut cov_exports = Exports::new();
+        cov_exports.insert(COV_IMPORT_NAME, Function::new_typed(&mut store, cov_hook));
+        cov_exports.insert(CMP_I32_IMPORT_NAME, Function::new_typed(&mut store, cmp_hook_i32));
+        cov_exports.insert(CMP_I64_IMPORT_NAME, Function::new_typed(&mut store, cmp_hook_i64));
+        let mut extra_imports = HashMap::new();
+        extra_imports.insert(COV_IMPORT_MODULE, cov_exports);
+
+        unsafe {
+            let mut hasher = DefaultHasher::new();
+            address.hash(&mut hasher);
+            CW_COV_SALT = hasher.finish() as usize;
+            CW_PREV_LOC = 0;
+        }
+        instance_from_module(store, &contract.module, backend, COSMWASM_GAS_LIMIT, Some(extra_imports))
+            .expect("failed to instantiate contract")
+    }
+
+    fn dump_storage(instance: CosmWasmInstance) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        let mut backend = instance.recycle().expect("failed to recycle instance");
+        let mut storage = BTreeMap::new();
+        // not read by the contract
+        let backend_storage = &mut backend.storage.storage;
+        let iterator = backend_storage.scan(None, None, Order::Ascending).0.expect("failed to scan storage");
+        while let Some((k, v)) = backend_storage.next(iterator).0.expect("failed to iterate storage") {
+            storage.insert(k, v);
+        }
+        storage
+    }
+
+    /// Execute a message, and then the `WasmMsg::Execute` messages in the response in order.
+    /// Everything is reverted if any of them fails.
+    fn run(
+        &self,
+        address: &str,
+        msg: &[u8],
+        funds: u128,
+        caller: &str,
+        storage: &BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>,
+        depth: usize,
+    ) -> CosmWasmRunResult {
+        let failed = |data: Vec<u8>| CosmWasmRunResult {
+            reverted: true,
+            output: CosmWasmOutput { data, attributes: vec![] },
+            storage: storage.clone(),
+        };
+        if depth > COSMWASM_MAX_CALL_DEPTH {
+            return failed(b"max call depth exceeded".to_vec());
+        }
+
+        let mut instance = self.instance(address, storage.get(address));
+        let mut env = mock_env();
+        env.contract.address = Addr::unchecked(address);
+        let funds = if funds > 0 { coins(funds, COSMWASM_DENOM) } else { vec![] };
+        let info = mock_info(caller, &funds);
+        let response: Response<Empty> = match call_execute::<_, _, _, Empty>(&mut instance, &env, &info, msg) {
+            Ok(ContractResult::Ok(response)) => response,
+            Ok(ContractResult::Err(err)) => return failed(err.into_bytes()),
+            // traps, out of gas, etc.
+            Err(err) => return failed(err.to_string().into_bytes()),
+        };
+
+        let mut new_storage = storage.clone();
+        new_storage.insert(address.to_string(), Self::dump_storage(instance));
+
+        let mut attributes = response
+            .attributes
+            .iter()
+            .chain(response.events.iter().flat_map(|event| event.attributes.iter()))
+            .map(|attr| (attr.key.clone(), attr.value.clone()))
+            .collect::<Vec<_>>();
+        for sub_msg in &response.messages {
+            if let CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            }) = &sub_msg.msg
+            {
+                if !self.contracts.contains_key(contract_addr) {
+                    continue;
+                }
+                let amount = funds
+                    .iter()
+                    .filter(|coin| coin.denom == COSMWASM_DENOM)
+                    .map(|coin| coin.amount.u128())
+                    .sum();
+                let result = self.run(contract_addr, msg.as_slice(), amount, address, &new_storage, depth + 1);
+                if result.reverted {
+                    return failed(result.output.data);
+                }
+                new_storage = result.storage;
+                attributes.extend(result.output.attributes);
+            }
+        }
+
+        CosmWasmRunResult {
+            reverted: false,
+            output: CosmWasmOutput {
+                data: response.data.map(|data| data.to_vec()).unwrap_or_default(),
+                attributes,
+            },
+            storage: new_storage,
+        }
+    }
+}
+
+impl<S> GenericVM<CosmWasmVMState, CosmWasmContract, Vec<u8>, CosmWasmLoc, CosmWasmAddress, CosmWasmSlotTy, CosmWasmOutput, CosmWasmInput, S, ConciseCosmWasmInput>
+    for CosmWasmVM<S>
+where
+    S: HasMetadata + HasCaller<CosmWasmAddress>,
+{
+    fn deploy(
+        &mut self,
+        code: CosmWasmContract,
+        constructor_args: Option<Vec<u8>>,
+        deployed_address: CosmWasmAddress,
+        state: &mut S,
+    ) -> Option<CosmWasmAddress> {
+        if !state.metadata().contains::<ConstantPoolMetadata>() {
+            state.metadata_mut().insert(ConstantPoolMetadata::new());
+        }
+        let constant_pool = state.metadata_mut().get_mut::<ConstantPoolMetadata>().unwrap();
+        for constant in code.constants() {
+            constant_pool.add_constant(constant);
+        }
+
+        let module = compile(&self.engine, &code.code).expect("failed to compile contract");
+        self.contracts.insert(
+            deployed_address.clone(),
+            DeployedCosmWasmContract {
+                module,
+                execute_msg: code.execute_msg.clone(),
+            },
+        );
+        state.add_address(&deployed_address);
+
+        let msg = constructor_args.unwrap_or_else(|| {
+            let msg = code
+                .instantiate_msg
+                .as_ref()
+                .map(|ty| ty.default_value(state))
+                .unwrap_or(Value::Object(Default::default()));
+            serde_json::to_vec(&msg).unwrap()
+        });
+        let caller = state.get_rand_caller();
+        let mut instance = self.instance(&deployed_address, None);
+        let mut env = mock_env();
+        env.contract.address = Addr::unchecked(&deployed_address);
+        let info = mock_info(&caller, &[]);
+        match call_instantiate::<_, _, _, Empty>(&mut instance, &env, &info, &msg) {
+            Ok(ContractResult::Ok(_)) => {}
+            Ok(ContractResult::Err(err)) => {
+                warn!("instantiation of {} failed: {}", deployed_address, err);
+                self.contracts.remove(&deployed_address);
+                return None;
+            }
+            Err(err) => {
+                warn!("instantiation of {} failed: {}", deployed_address, err);
+                self.contracts.remove(&deployed_address);
+                return None;
+            }
+        }
+        self.genesis_storage
+            .insert(deployed_address.clone(), Self::dump_storage(instance));
+        Some(deployed_address)
+    }
+
+    fn execute(
+        &mut self,
+        input: &CosmWasmInput,
+        _state: &mut S,
+    ) -> ExecutionResult<CosmWasmLoc, CosmWasmAddress, CosmWasmVMState, CosmWasmOutput, ConciseCosmWasmInput>
+    where
+        CosmWasmVMState: VMStateT,
+    {
+        unsafe {
+            CW_STATE_CHANGED = false;
+        }
+        let pre_state = input.get_state();
+        let result = self.run(
+            &input.contract,
+            &input.msg_bytes(),
+            input.funds,
+            &input.caller,
+            &pre_state.storage,
+            0,
+        );
+
+        let mut new_state = pre_state.clone();
+        new_state.typed_bug = result
+            .output
+            .attributes
+            .iter()
+            .filter(|(key, _)| key == TYPED_BUG_ATTRIBUTE)
+            .map(|(_, value)| value.clone())
+            .collect();
+        if !result.reverted {
+            for (addr, slots) in &result.storage {
+                let pre_slots = pre_state.storage.get(addr);
+                for (key, value) in slots {
+                    if pre_slots.and_then(|s| s.get(key)) != Some(value) {
+                        unsafe {
+                            CW_WRITE_MAP[map_key(addr, key)] = 1;
+                            CW_STATE_CHANGED = true;
+                        }
+                    }
+                }
+            }
+            new_state.storage = result.storage;
+        }
+
+        ExecutionResult {
+            new_state: StagedVMState::new_with_state(new_state),
+            output: result.output,
+            reverted: result.reverted,
+            additional_info: None,
+        }
+    }
+
+    /// Query messages, an empty output if the query failed. Nothing is recorded in the feedback
+    /// maps.
+    fn fast_static_call(
+        &mut self,
+        data: &Vec<(CosmWasmAddress, Vec<u8>)>,
+        vm_state: &CosmWasmVMState,
+        _state: &mut S,
+    ) -> Vec<CosmWasmOutput> {
+        unsafe {
+            CW_TRACING = false;
+        }
+        let outputs = data
+            .iter()
+            .map(|(address, msg)| {
+                if !self.contracts.contains_key(address) {
+                    return CosmWasmOutput::default();
+                }
+                let mut instance = self.instance(address, vm_state.storage.get(address));
+                let mut env = mock_env();
+                env.contract.address = Addr::unchecked(address);
+                match call_query(&mut instance, &env, msg) {
+                    Ok(ContractResult::Ok(response)) => CosmWasmOutput {
+                        data: response.to_vec(),
+                        attributes: vec![],
+                    },
+                    _ => CosmWasmOutput::default(),
+                }
+            })
+            .collect();
+        unsafe {
+            CW_TRACING = true;
+        }
+        outputs
+    }
+
+    fn get_jmp(&self) -> &'static mut [u8; MAP_SIZE] {
+        unsafe { &mut CW_COV_MAP }
+    }
+
+    fn get_read(&self) -> &'static mut [bool; MAP_SIZE] {
+        unsafe { &mut CW_READ_MAP }
+    }
+
+    fn get_write(&self) -> &'static mut [u8; MAP_SIZE] {
+        unsafe { &mut CW_WRITE_MAP }
+    }
+
+    fn get_cmp(&self) -> &'static mut [CosmWasmSlotTy; MAP_SIZE] {
+        unsafe { &mut CW_CMP_MAP }
+    }
+
+    fn state_changed(&self) -> bool {
+        unsafe { CW_STATE_CHANGED }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traced_storage() {
+        let mut storage = TracedStorage {
+            contract: "wasm1contract".to_string(),
+            storage: MockStorage::new(),
+        };
+        storage.set(b"balance", b"100").0.unwrap();
+        storage.set(b"owner", b"wasm1owner").0.unwrap();
+        unsafe {
+            CW_READ_MAP = [false; MAP_SIZE];
+        }
+        assert!(!unsafe { CW_READ_MAP[map_key("wasm1contract", b"balance")] });
+        assert_eq!(storage.get(b"balance").0.unwrap(), Some(b"100".to_vec()));
+        assert!(unsafe { CW_READ_MAP[map_key("wasm1contract", b"balance")] });
+
+        // keys iterated over are read too
+        let iterator = storage.scan(Some(b"o"), None, Order::Ascending).0.unwrap();
+        assert!(storage.next(iterator).0.unwrap().is_some());
+        assert!(unsafe { CW_READ_MAP[map_key("wasm1contract", b"owner")] });
+    }
+}
@@ -28,6 +28,11 @@ use super::concolic::concolic_host::Expr;
 /// Mapping from known signature to function name
 static mut FUNCTION_SIG: Lazy<HashMap<[u8; 4], String>> = Lazy::new(|| HashMap::new());
 
+/// Name of a function registered with [`BoxedABI::set_func_with_name`]
+pub fn function_name(function: &[u8; 4]) -> Option<String> {
+    unsafe { FUNCTION_SIG.get(function).cloned() }
+}
+
 /// todo: remove this
 static mut CONCOLIC_COUNTER: u64 = 0;
 
@@ -265,6 +270,34 @@ impl BoxedABI {
     pub fn set_bytes(&mut self, bytes: Vec<u8>) {
         self.b.set_bytes(bytes[4..].to_vec());
     }
+
+    /// Halve the first non-empty dynamic arg (bytes, string or dynamic array), used for trimming
+    /// Returns false if there is nothing to shrink
+    pub fn shrink(&mut self) -> bool {
+        match self.get_type() {
+            TDynamic => {
+                let adyn = self.b.deref_mut().as_any().downcast_mut::<ADynamic>().unwrap();
+                if adyn.data.is_empty() {
+                    return false;
+                }
+                adyn.data.truncate(adyn.data.len() / 2);
+                true
+            }
+            TArray => {
+                let aarray = self.b.deref_mut().as_any().downcast_mut::<AArray>().unwrap();
+                if aarray.dynamic_size && aarray.data.len() > 1 {
+                    aarray.data.truncate(aarray.data.len() / 2);
+                    return true;
+                }
+                aarray.data.iter_mut().any(|item| item.shrink())
+            }
+            TUnknown => {
+                let a_unknown = self.b.deref_mut().as_any().downcast_mut::<AUnknown>().unwrap();
+                a_unknown.concrete.shrink()
+            }
+            TEmpty | T256 => false,
+        }
+    }
 }
 
 
@@ -1357,4 +1390,16 @@ mod tests {
             hex::encode(abibytes)
         );
     }
+
+    #[test]
+    fn test_shrink() {
+        let mut abi = get_abi_type_boxed(&String::from("(uint256,bytes)"));
+        let aarray = abi.b.deref_mut().as_any().downcast_mut::<AArray>().unwrap();
+        aarray.data[1].b.deref_mut().as_any().downcast_mut::<ADynamic>().unwrap().data = vec![1; 4];
+        assert!(abi.shrink());
+        assert_eq!(abi.b.get_size(), 32 + 32 + 2);
+        assert!(abi.shrink());
+        assert!(abi.shrink());
+        assert!(!abi.shrink());
+    }
 }
@@ -0,0 +1,157 @@
+/// Cross-chain message harness for bridges (`--bridge-config`)
+///
+/// The receiving side of a bridge trusts a messenger (e.g., a LayerZero endpoint, a relayer
+/// or an AMB) to deliver messages from the remote chain. The harness lets the fuzzer forge such
+/// messages: calls to the handlers of the receiver (e.g., `lzReceive`) are sent from the
+/// configured messengers, with the source chain, remote sender and payload fuzzed like any other
+/// argument. The remote chain is modeled by an escrow on the local chain: tokens locked there
+/// back the tokens minted by delivered messages, which
+/// [`crate::evm::oracles::bridge::BridgeOracle`] checks.
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::evm::types::{EVMAddress, EVMU256};
+
+/// Messengers delivering messages, forged messages keep them as caller
+pub static mut MESSENGERS: Option<HashSet<EVMAddress>> = None;
+
+pub fn is_messenger(address: &EVMAddress) -> bool {
+    unsafe { MESSENGERS.as_ref().map_or(false, |messengers| messengers.contains(address)) }
+}
+
+/// Tokens locked on the remote side of the bridge, modeled on the local chain
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EscrowConfig {
+    /// Address or contract name of the locked token
+    pub token: String,
+    /// Contracts holding the locked tokens (address or contract name)
+    pub holders: Vec<String>,
+}
+
+fn default_handlers() -> Vec<String> {
+    ["lzReceive", "ccipReceive", "receiveMessage", "handle", "execute"]
+        .map(|h| h.to_string())
+        .to_vec()
+}
+
+/// Bridge harness, loaded from a JSON file:
+/// ```json
+/// {
+///   "messengers": ["0x66a71dcef29a0ffbdbe3c6a460a3b5bc225cd675"],
+///   "receiver": "Bridge",
+///   "handlers": ["lzReceive"],
+///   "token": "WrappedToken",
+///   "escrow": {"token": "USDC", "holders": ["Vault"]},
+///   "amount_word": 6
+/// }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// Addresses delivering messages (endpoints, relayers), mocked if they have no code
+    pub messengers: Vec<String>,
+    /// Address or contract name of the contract receiving messages
+    pub receiver: String,
+    /// Names of the functions of the receiver handling messages
+    #[serde(default = "default_handlers")]
+    pub handlers: Vec<String>,
+    /// Address or contract name of the token minted for incoming messages
+    pub token: String,
+    #[serde(default)]
+    pub escrow: Option<EscrowConfig>,
+    /// Index of the 32-byte word of the calldata of the handler (after the selector) holding the
+    /// amount of the message, minted amounts differing from it are reported
+    #[serde(default)]
+    pub amount_word: Option<usize>,
+}
+
+impl BridgeConfig {
+    pub fn from_file(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).expect("failed to read bridge config");
+        serde_json::from_str(&content).expect("failed to parse bridge config")
+    }
+}
+
+/// Bridge harness, resolved
+#[derive(Clone, Debug)]
+pub struct Bridge {
+    pub messengers: Vec<EVMAddress>,
+    pub receiver: EVMAddress,
+    pub handlers: Vec<String>,
+    pub token: EVMAddress,
+    /// (locked token, holders)
+    pub escrow: Option<(EVMAddress, Vec<EVMAddress>)>,
+    pub amount_word: Option<usize>,
+}
+
+impl Bridge {
+    pub fn new(config: &BridgeConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        assert!(!config.messengers.is_empty(), "bridge requires a messenger");
+        Self {
+            messengers: config.messengers.iter().map(resolve).collect(),
+            receiver: resolve(&config.receiver),
+            handlers: config.handlers.clone(),
+            token: resolve(&config.token),
+            escrow: config
+                .escrow
+                .as_ref()
+                .map(|escrow| (resolve(&escrow.token), escrow.holders.iter().map(resolve).collect())),
+            amount_word: config.amount_word,
+        }
+    }
+
+    /// Whether `function` of `contract` receives messages
+    pub fn is_handler(&self, contract: EVMAddress, function: &str) -> bool {
+        contract == self.receiver && self.handlers.iter().any(|h| h == function)
+    }
+
+    /// Amount of the message delivered with `calldata`
+    pub fn amount(&self, calldata: &[u8]) -> Option<EVMU256> {
+        let offset = 4 + self.amount_word? * 32;
+        calldata.get(offset..offset + 32).map(EVMU256::from_be_slice)
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_config() {
+        let config: BridgeConfig = serde_json::from_str(
+            r#"{"messengers": ["Endpoint"], "receiver": "Bridge", "token": "Wrapped", "amount_word": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(config.handlers, default_handlers());
+        assert!(config.escrow.is_none());
+
+        let names = ["Endpoint", "Bridge", "Wrapped"];
+        let resolve = |name: &String| {
+            let idx = names.iter().position(|n| n == name).unwrap() as u8;
+            EVMAddress::from_slice(&[idx + 1; 20])
+        };
+        let bridge = Bridge::new(&config, &resolve);
+        assert_eq!(bridge.messengers, vec![EVMAddress::from_slice(&[1; 20])]);
+        assert!(bridge.is_handler(bridge.receiver, "lzReceive"));
+        assert!(!bridge.is_handler(bridge.receiver, "withdraw"));
+        assert!(!bridge.is_handler(bridge.token, "lzReceive"));
+    }
+
+    #[test]
+    fn test_amount() {
+        let mut bridge = Bridge {
+            messengers: vec![],
+            receiver: EVMAddress::zero(),
+            handlers: vec![],
+            token: EVMAddress::zero(),
+            escrow: None,
+            amount_word: Some(1),
+        };
+        let mut calldata = vec![0; 36];
+        calldata.extend(EVMU256::from(42).to_be_bytes::<32>());
+        assert_eq!(bridge.amount(&calldata), Some(EVMU256::from(42)));
+        // calldata too short for the word
+        assert_eq!(bridge.amount(&calldata[..40]), None);
+        bridge.amount_word = None;
+        assert_eq!(bridge.amount(&calldata), None);
+    }
+}
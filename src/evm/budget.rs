@@ -0,0 +1,86 @@
+/// Per-execution step / time budget (`--step-limit`, `--exec-timeout`)
+///
+/// Executions running more instructions or for longer than the budget (e.g., huge loops in forked
+/// third-party contracts) are aborted: every call frame halts at its next instruction, the state
+/// is left untouched and the execution is neither credited coverage nor added to the corpus.
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Time is only checked every this many steps
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// Number of executions aborted for exceeding their budget
+pub static mut BUDGET_ABORTS: u64 = 0;
+
+#[derive(Clone, Debug)]
+pub struct ExecBudget {
+    pub max_steps: Option<u64>,
+    pub max_time: Option<Duration>,
+    steps: u64,
+    start: Instant,
+    pub exceeded: bool,
+}
+
+impl ExecBudget {
+    pub fn new(max_steps: Option<u64>, max_time_ms: Option<u64>) -> Self {
+        Self {
+            max_steps,
+            max_time: max_time_ms.map(Duration::from_millis),
+            steps: 0,
+            start: Instant::now(),
+            exceeded: false,
+        }
+    }
+
+    /// Start the budget of a new execution
+    pub fn reset(&mut self) {
+        self.steps = 0;
+        self.start = Instant::now();
+        self.exceeded = false;
+    }
+
+    /// Account for an instruction, returns whether the budget is exceeded
+    pub fn step(&mut self) -> bool {
+        self.steps += 1;
+        if !self.exceeded {
+            self.exceeded = self.max_steps.map_or(false, |max| self.steps > max)
+                || (self.steps % TIME_CHECK_INTERVAL == 0
+                    && self.max_time.map_or(false, |max| self.start.elapsed() > max));
+        }
+        self.exceeded
+    }
+}
+
+/// Count an aborted execution, the count is reported at powers of two
+pub fn record_abort() {
+    unsafe {
+        BUDGET_ABORTS += 1;
+        if BUDGET_ABORTS.is_power_of_two() {
+            info!("[budget] {} executions aborted for exceeding the step / time budget", BUDGET_ABORTS);
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_limit() {
+        let mut budget = ExecBudget::new(Some(3), None);
+        assert!(!budget.step());
+        assert!(!budget.step());
+        assert!(!budget.step());
+        assert!(budget.step());
+        // stays exceeded until the next execution
+        assert!(budget.step());
+        budget.reset();
+        assert!(!budget.step());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let mut budget = ExecBudget::new(None, Some(0));
+        std::thread::sleep(Duration::from_millis(1));
+        assert!((0..TIME_CHECK_INTERVAL).any(|_| budget.step()));
+    }
+}
@@ -0,0 +1,251 @@
+/// Flashbots bundle export of profitable sequences (`--bundle-config <file>`)
+///
+/// When the flashloan / profit oracle fires in on-chain mode, the transactions of the violating
+/// sequence are signed (as EIP-1559 transactions from the whitehat account) with consecutive
+/// nonces, and written as a bundle targeting the next blocks along with the builder endpoints
+/// to submit it to. Bundles are never submitted by ItyFuzz.
+///
+/// The bundle config is a JSON file:
+/// ```json
+/// {
+///   "private_key": "0x...",
+///   "builders": ["https://relay.flashbots.net", "https://rpc.titanbuilder.xyz"],
+///   "blocks_ahead": 1,
+///   "gas_limit": 1000000,
+///   "max_fee_per_gas": "100000000000",
+///   "max_priority_fee_per_gas": "2000000000"
+/// }
+/// ```
+/// The private key can be omitted and provided with the `ITYFUZZ_BUNDLE_KEY` environment variable.
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+use k256::ecdsa::SigningKey;
+use revm_primitives::keccak256;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::evm::input::ConciseEVMInput;
+#[cfg(feature = "flashloan_v2")]
+use crate::evm::input::EVMInputTy;
+use crate::evm::mempool::request;
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::input::ConciseSerde;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleConfig {
+    pub private_key: Option<String>,
+    #[serde(default)]
+    pub builders: Vec<String>,
+    #[serde(default = "default_blocks_ahead")]
+    pub blocks_ahead: u64,
+    #[serde(default = "default_gas_limit")]
+    pub gas_limit: u64,
+    pub max_fee_per_gas: String,
+    pub max_priority_fee_per_gas: String,
+}
+
+fn default_blocks_ahead() -> u64 {
+    1
+}
+
+fn default_gas_limit() -> u64 {
+    1_000_000
+}
+
+impl BundleConfig {
+    pub fn from_file(path: &str) -> Self {
+        let mut buf = String::new();
+        File::open(path)
+            .unwrap_or_else(|_| panic!("bundle config file {} not found", path))
+            .read_to_string(&mut buf)
+            .expect("failed to read bundle config file");
+        serde_json::from_str(&buf).expect("failed to parse bundle config")
+    }
+
+    fn signing_key(&self) -> SigningKey {
+        let key = self
+            .private_key
+            .clone()
+            .or_else(|| std::env::var("ITYFUZZ_BUNDLE_KEY").ok())
+            .expect("no private key in the bundle config or ITYFUZZ_BUNDLE_KEY");
+        let bytes = hex::decode(key.trim().trim_start_matches("0x")).expect("invalid private key");
+        SigningKey::from_slice(&bytes).expect("invalid private key")
+    }
+}
+
+/// RLP item
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+fn rlp_length_prefix(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+    let len_bytes = strip_zeros(&(len as u64).to_be_bytes());
+    [vec![offset + 55 + len_bytes.len() as u8], len_bytes].concat()
+}
+
+fn strip_zeros(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().skip_while(|b| **b == 0).cloned().collect()
+}
+
+impl Rlp {
+    pub fn uint(v: EVMU256) -> Self {
+        Rlp::Bytes(strip_zeros(&v.to_be_bytes::<32>()))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Rlp::Bytes(bytes) if bytes.len() == 1 && bytes[0] < 0x80 => bytes.clone(),
+            Rlp::Bytes(bytes) => [rlp_length_prefix(bytes.len(), 0x80), bytes.clone()].concat(),
+            Rlp::List(items) => rlp_list(items),
+        }
+    }
+}
+
+fn rlp_list(items: &[Rlp]) -> Vec<u8> {
+    let payload = items.iter().flat_map(|item| item.encode()).collect::<Vec<_>>();
+    [rlp_length_prefix(payload.len(), 0xc0), payload].concat()
+}
+
+/// Address of the account of a key
+pub fn key_address(key: &SigningKey) -> EVMAddress {
+    let public = key.verifying_key().to_encoded_point(false);
+    EVMAddress::from_slice(&keccak256(&public.as_bytes()[1..]).0[12..])
+}
+
+/// Signed raw EIP-1559 transaction
+pub fn sign_tx(key: &SigningKey, chain_id: u64, nonce: u64, config: &BundleConfig, tx: &ConciseEVMInput) -> Vec<u8> {
+    let max_priority_fee =
+        EVMU256::from_str(&config.max_priority_fee_per_gas).expect("invalid max_priority_fee_per_gas");
+    let max_fee = EVMU256::from_str(&config.max_fee_per_gas).expect("invalid max_fee_per_gas");
+    let mut fields = vec![
+        Rlp::uint(EVMU256::from(chain_id)),
+        Rlp::uint(EVMU256::from(nonce)),
+        Rlp::uint(max_priority_fee),
+        Rlp::uint(max_fee),
+        Rlp::uint(EVMU256::from(config.gas_limit)),
+        Rlp::Bytes(tx.contract.0.to_vec()),
+        Rlp::uint(tx.txn_value.unwrap_or_default()),
+        Rlp::Bytes(tx.data.as_ref().map(|d| d.get_bytes()).unwrap_or_default()),
+        // access list
+        Rlp::List(vec![]),
+    ];
+    let digest = keccak256(&[vec![0x02], rlp_list(&fields)].concat());
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(&digest.0)
+        .expect("failed to sign transaction");
+    let signature = signature.to_bytes();
+    fields.push(Rlp::uint(EVMU256::from(recovery_id.to_byte())));
+    fields.push(Rlp::Bytes(strip_zeros(&signature[..32])));
+    fields.push(Rlp::Bytes(strip_zeros(&signature[32..])));
+    [vec![0x02], rlp_list(&fields)].concat()
+}
+
+/// Why a transaction of a violating sequence can't be sent as a plain transaction, if so
+fn unsupported(tx: &ConciseEVMInput) -> Option<&'static str> {
+    #[cfg(feature = "flashloan_v2")]
+    if !matches!(tx.input_type, EVMInputTy::ABI | EVMInputTy::ArbitraryCallBoundedAddr) {
+        return Some("token borrowing / liquidation is modeled by the fuzzer");
+    }
+    if tx.step || tx.layer > 0 {
+        return Some("reentrant calls need an attacker contract");
+    }
+    if tx.victim {
+        return Some("victim transactions are sent by someone else");
+    }
+    if tx.via_intermediary {
+        return Some("tx.origin must be another account");
+    }
+    if tx.repeat > 1 {
+        return Some("repeated transactions are not supported");
+    }
+    if !tx.advance.is_zero() {
+        return Some("the sequence spans several blocks");
+    }
+    None
+}
+
+/// Bundle of a violating sequence (in the replayable format), signed with the key of the config
+///
+/// The nonce of the whitehat account and the target block are fetched from the RPC endpoint at
+/// `rpc`. Returns an error if the sequence can't be executed as a bundle of plain transactions
+/// (the generated attacker contract can be used instead).
+pub fn build_bundle(config: &BundleConfig, rpc: &str, chain_id: u64, txs: &str) -> Result<Value, String> {
+    let inputs = parse_sequence(txs);
+    for (idx, tx) in inputs.iter().enumerate() {
+        if let Some(reason) = unsupported(tx) {
+            return Err(format!("transaction {} can't be bundled: {}, use the attacker contract instead", idx, reason));
+        }
+    }
+
+    let key = config.signing_key();
+    let from = key_address(&key);
+    let client = reqwest::blocking::Client::new();
+    let hex_u64 = |v: Option<Value>| {
+        v.as_ref()
+            .and_then(|v| v.as_str())
+            .and_then(|v| u64::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+    };
+    let nonce = hex_u64(request(
+        &client,
+        rpc,
+        "eth_getTransactionCount",
+        json!([format!("{:?}", from), "pending"]),
+    ))
+    .ok_or("failed to fetch the nonce of the whitehat account")?;
+    let block = hex_u64(request(&client, rpc, "eth_blockNumber", json!([]))).ok_or("failed to fetch the block number")?;
+    let target_block = format!("0x{:x}", block + config.blocks_ahead);
+
+    let signed = inputs
+        .iter()
+        .enumerate()
+        .map(|(idx, tx)| format!("0x{}", hex::encode(sign_tx(&key, chain_id, nonce + idx as u64, config, tx))))
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "from": format!("{:?}", from),
+        "target_block": target_block,
+        "builders": config.builders,
+        "transactions": inputs.iter().map(|tx| tx.serialize_string()).collect::<Vec<_>>(),
+        "request": {
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendBundle",
+            "params": [{"txs": signed, "blockNumber": target_block}],
+        },
+    }))
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rlp() {
+        let dog = Rlp::Bytes(b"dog".to_vec());
+        assert_eq!(dog.encode(), hex::decode("83646f67").unwrap());
+        let list = Rlp::List(vec![Rlp::Bytes(b"cat".to_vec()), Rlp::Bytes(b"dog".to_vec())]);
+        assert_eq!(list.encode(), hex::decode("c88363617483646f67").unwrap());
+        assert_eq!(Rlp::uint(EVMU256::ZERO).encode(), vec![0x80]);
+        assert_eq!(Rlp::uint(EVMU256::from(15)).encode(), vec![0x0f]);
+        assert_eq!(Rlp::uint(EVMU256::from(1024)).encode(), vec![0x82, 0x04, 0x00]);
+        let long = Rlp::Bytes(vec![0xaa; 60]);
+        assert_eq!(long.encode()[..2], [0xb8, 60]);
+    }
+
+    #[test]
+    fn test_key_address() {
+        let key = SigningKey::from_slice(
+            &hex::decode("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            key_address(&key),
+            EVMAddress::from_str("2c7536e3605d9c16a7a3d7b1898e529396a65c23").unwrap()
+        );
+    }
+}
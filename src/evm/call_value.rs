@@ -0,0 +1,192 @@
+/// Strategies choosing msg.value of calls to payable functions
+///
+/// Uniformly random values make almost every payable call revert (e.g., `require(msg.value ==
+/// price * amount)`). Instead, a strategy is picked for each mutation of the value: zero, dust (a
+/// few wei), a value already accepted by the function (exact-required), the largest value sent
+/// (the whole balance of the caller), a value compared with msg.value in the function
+/// (solver-derived, recorded by [`crate::evm::middlewares::call_value::CallValueTracker`]), or
+/// random bytes. Strategies are weighted per function by how often the values they picked didn't
+/// revert.
+use std::collections::HashMap;
+
+use crate::evm::types::{EVMAddress, EVMU256};
+
+/// Largest dust value, in wei
+const MAX_DUST: u64 = 1000;
+/// Values remembered per function and strategy
+const MAX_VALUES: usize = 16;
+
+/// Whether values are picked by the strategies, random otherwise (`--payable-values`)
+pub static mut PAYABLE_VALUES: bool = false;
+/// Function (contract, selector) => values learned
+pub static mut FUNCTION_VALUES: Option<HashMap<(EVMAddress, [u8; 4]), FunctionValues>> = None;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueStrategy {
+    Zero,
+    Dust,
+    Accepted,
+    MaxBalance,
+    Solved,
+    Random,
+}
+
+const STRATEGIES: [ValueStrategy; 6] = [
+    ValueStrategy::Zero,
+    ValueStrategy::Dust,
+    ValueStrategy::Accepted,
+    ValueStrategy::MaxBalance,
+    ValueStrategy::Solved,
+    ValueStrategy::Random,
+];
+
+/// Largest value sent, the upper half of the value is never set by the mutator
+pub fn max_value() -> EVMU256 {
+    EVMU256::from(u128::MAX)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FunctionValues {
+    /// Strategy => (values sent, values that didn't revert)
+    pub outcomes: HashMap<ValueStrategy, (u64, u64)>,
+    /// Values that didn't revert, most recent last
+    pub accepted: Vec<EVMU256>,
+    /// Values compared with msg.value
+    pub solved: Vec<EVMU256>,
+}
+
+fn remember(values: &mut Vec<EVMU256>, value: EVMU256) {
+    if let Some(idx) = values.iter().position(|v| *v == value) {
+        values.remove(idx);
+    }
+    values.push(value);
+    if values.len() > MAX_VALUES {
+        values.remove(0);
+    }
+}
+
+impl FunctionValues {
+    /// Strategy that would have picked a value
+    pub fn classify(&self, value: EVMU256) -> ValueStrategy {
+        if value == EVMU256::ZERO {
+            ValueStrategy::Zero
+        } else if value <= EVMU256::from(MAX_DUST) {
+            ValueStrategy::Dust
+        } else if value == max_value() {
+            ValueStrategy::MaxBalance
+        } else if self.solved.contains(&value) {
+            ValueStrategy::Solved
+        } else if self.accepted.contains(&value) {
+            ValueStrategy::Accepted
+        } else {
+            ValueStrategy::Random
+        }
+    }
+
+    pub fn record(&mut self, value: EVMU256, reverted: bool) {
+        let outcome = self.outcomes.entry(self.classify(value)).or_default();
+        outcome.0 += 1;
+        if !reverted {
+            outcome.1 += 1;
+            remember(&mut self.accepted, value);
+        }
+    }
+
+    /// Pick a strategy, weighted by the rate of values that didn't revert (`r` is random)
+    pub fn choose(&self, r: u64) -> ValueStrategy {
+        let available = STRATEGIES
+            .iter()
+            .filter(|s| match s {
+                ValueStrategy::Accepted => !self.accepted.is_empty(),
+                ValueStrategy::Solved => !self.solved.is_empty(),
+                _ => true,
+            })
+            .map(|s| {
+                let (sent, accepted) = self.outcomes.get(s).cloned().unwrap_or_default();
+                // Laplace smoothing, untried strategies start at 50%
+                (*s, (accepted + 1) * 1000 / (sent + 2))
+            })
+            .collect::<Vec<_>>();
+        let total: u64 = available.iter().map(|(_, weight)| weight).sum();
+        let mut point = r % total;
+        for (strategy, weight) in &available {
+            if point < *weight {
+                return *strategy;
+            }
+            point -= weight;
+        }
+        ValueStrategy::Random
+    }
+
+    /// Value picked by a strategy (`r` is random), None for random values
+    pub fn pick(&self, strategy: ValueStrategy, r: u64) -> Option<EVMU256> {
+        match strategy {
+            ValueStrategy::Zero => Some(EVMU256::ZERO),
+            ValueStrategy::Dust => Some(EVMU256::from(1 + r % MAX_DUST)),
+            ValueStrategy::MaxBalance => Some(max_value()),
+            ValueStrategy::Accepted => self.accepted.get(r as usize % self.accepted.len()).cloned(),
+            ValueStrategy::Solved => self.solved.get(r as usize % self.solved.len()).cloned(),
+            ValueStrategy::Random => None,
+        }
+    }
+}
+
+fn function_values() -> &'static mut HashMap<(EVMAddress, [u8; 4]), FunctionValues> {
+    unsafe { FUNCTION_VALUES.get_or_insert_with(HashMap::new) }
+}
+
+/// Record whether a call to a payable function reverted with a value
+pub fn record_value(contract: EVMAddress, data: &[u8], value: EVMU256, reverted: bool) {
+    if !unsafe { PAYABLE_VALUES } || data.len() < 4 {
+        return;
+    }
+    let selector = [data[0], data[1], data[2], data[3]];
+    function_values().entry((contract, selector)).or_default().record(value, reverted);
+}
+
+/// Record a value compared with msg.value in a function
+pub fn record_solved(contract: EVMAddress, selector: [u8; 4], value: EVMU256) {
+    if value == EVMU256::ZERO || value > max_value() {
+        return;
+    }
+    remember(&mut function_values().entry((contract, selector)).or_default().solved, value);
+}
+
+/// Value for a call to a payable function, None if it should be mutated randomly (`r1` and `r2`
+/// are random)
+pub fn pick_value(contract: EVMAddress, selector: [u8; 4], r1: u64, r2: u64) -> Option<EVMU256> {
+    if !unsafe { PAYABLE_VALUES } {
+        return None;
+    }
+    let values = function_values().entry((contract, selector)).or_default();
+    values.pick(values.choose(r1), r2)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategies() {
+        let mut values = FunctionValues::default();
+        let price = EVMU256::from(10u64.pow(18));
+        assert_eq!(values.classify(EVMU256::from(7)), ValueStrategy::Dust);
+        assert_eq!(values.classify(price), ValueStrategy::Random);
+        // only the exact price is accepted
+        for _ in 0..20 {
+            values.record(EVMU256::ZERO, true);
+            values.record(EVMU256::from(7), true);
+            values.record(price + EVMU256::from(1), true);
+        }
+        values.solved.push(price);
+        values.record(price, false);
+        assert_eq!(values.accepted, vec![price]);
+        assert_eq!(values.outcomes[&ValueStrategy::Solved], (1, 1));
+
+        let picks = (0..1000u64).map(|r| values.choose(r * 7919)).collect::<Vec<_>>();
+        let count = |s: ValueStrategy| picks.iter().filter(|p| **p == s).count();
+        assert!(count(ValueStrategy::Solved) > count(ValueStrategy::Zero));
+        assert!(count(ValueStrategy::Accepted) > count(ValueStrategy::Random));
+        assert_eq!(values.pick(ValueStrategy::Solved, 3), Some(price));
+        assert_eq!(values.pick(ValueStrategy::Random, 3), None);
+    }
+}
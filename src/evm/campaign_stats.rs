@@ -0,0 +1,218 @@
+/// Campaign statistics for comparing campaigns quantitatively
+///
+/// `[WORKDIR]/plot_data` is written continuously in the AFL format (one line every
+/// [`PLOT_INTERVAL`]), and `[WORKDIR]/summary.json` / `[WORKDIR]/summary.txt` summarize the
+/// campaign: throughput histogram, coverage curve, violations per oracle and time to the first
+/// violation. The summary is refreshed periodically and whenever a violation is found, so it is
+/// up to date whenever the campaign ends.
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use libafl::feedbacks::MapFeedbackMetadata;
+use libafl::prelude::{Corpus, HasCorpus, HasExecutions, HasNamedMetadata};
+use serde::Serialize;
+
+use crate::evm::types::EVMFuzzState;
+
+/// Interval between two lines of plot_data
+pub const PLOT_INTERVAL: Duration = Duration::from_secs(5);
+/// The summary is refreshed every this many lines of plot_data
+const SUMMARY_INTERVAL: usize = 12;
+/// Maximum number of points of the coverage curve in the summary
+const CURVE_POINTS: usize = 100;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    pub time: f64,
+    pub executions: usize,
+    pub corpus: usize,
+    pub coverage: usize,
+    pub solutions: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct CampaignStats {
+    work_dir: String,
+    start: Instant,
+    last_sample: Option<Instant>,
+    pub samples: Vec<Sample>,
+    /// Oracle (tag of its report, e.g., `typed_bug`) -> violations
+    pub oracle_counts: BTreeMap<String, usize>,
+    /// Seconds to the first violation
+    pub first_violation: Option<f64>,
+    pub violations: usize,
+    /// Indices of the covered entries of the branch map, to compare campaigns (`cli compare`)
+    pub branches: Vec<usize>,
+}
+
+/// Oracles reporting a violation, from the tags (`[tag] ...`) of their reports
+pub fn oracle_tags(report: &str) -> Vec<String> {
+    let mut tags = report
+        .lines()
+        .filter_map(|line| {
+            let start = line.find('[')?;
+            let end = start + line[start..].find(']')?;
+            Some(line[start + 1..end].to_lowercase())
+        })
+        .collect::<Vec<_>>();
+    tags.dedup();
+    if tags.is_empty() {
+        tags.push("other".to_string());
+    }
+    tags
+}
+
+impl CampaignStats {
+    pub fn new(work_dir: &str) -> Self {
+        let _ = std::fs::write(
+            format!("{}/plot_data", work_dir),
+            "# relative_time, execs_done, corpus_count, coverage, solutions, execs_per_sec\n",
+        );
+        Self {
+            work_dir: work_dir.to_string(),
+            start: Instant::now(),
+            last_sample: None,
+            samples: vec![],
+            oracle_counts: BTreeMap::new(),
+            first_violation: None,
+            violations: 0,
+            branches: vec![],
+        }
+    }
+
+    /// Append a line to plot_data if the interval elapsed
+    pub fn sample(&mut self, state: &mut EVMFuzzState) {
+        if self.last_sample.map_or(false, |last| last.elapsed() < PLOT_INTERVAL) {
+            return;
+        }
+        self.last_sample = Some(Instant::now());
+        if let Some(meta) = state.named_metadata().get::<MapFeedbackMetadata<u8>>("jmp") {
+            self.branches = meta
+                .history_map
+                .iter()
+                .enumerate()
+                .filter(|(_, hit)| **hit > 0)
+                .map(|(idx, _)| idx)
+                .collect();
+        }
+        let coverage = self.branches.len();
+        let sample = Sample {
+            time: self.start.elapsed().as_secs_f64(),
+            executions: *state.executions(),
+            corpus: state.corpus().count(),
+            coverage,
+            solutions: self.violations,
+        };
+        let rate = match self.samples.last() {
+            Some(last) => (sample.executions - last.executions) as f64 / (sample.time - last.time).max(1e-3),
+            None => sample.executions as f64 / sample.time.max(1e-3),
+        };
+        if let Ok(mut file) = OpenOptions::new().append(true).open(format!("{}/plot_data", self.work_dir)) {
+            let _ = writeln!(
+                file,
+                "{}, {}, {}, {}, {}, {:.2}",
+                sample.time as u64, sample.executions, sample.corpus, sample.coverage, sample.solutions, rate
+            );
+        }
+        self.samples.push(sample);
+        if self.samples.len() % SUMMARY_INTERVAL == 0 {
+            self.write_summary();
+        }
+    }
+
+    /// Record a violation given the report of the oracles
+    pub fn record_violation(&mut self, report: &str) {
+        self.first_violation.get_or_insert(self.start.elapsed().as_secs_f64());
+        self.violations += 1;
+        for tag in oracle_tags(report) {
+            *self.oracle_counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    /// Executions per second between consecutive samples, bucketed by powers of two (lower bound
+    /// of the bucket => samples)
+    fn throughput_histogram(&self) -> BTreeMap<u64, usize> {
+        let mut histogram = BTreeMap::new();
+        for pair in self.samples.windows(2) {
+            let rate = (pair[1].executions - pair[0].executions) as f64 / (pair[1].time - pair[0].time).max(1e-3);
+            let rate = (rate as u64).max(1);
+            *histogram.entry(1u64 << (63 - rate.leading_zeros())).or_default() += 1;
+        }
+        histogram
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let last = self.samples.last();
+        let step = (self.samples.len() / CURVE_POINTS).max(1);
+        let curve = self
+            .samples
+            .iter()
+            .step_by(step)
+            .chain(last)
+            .map(|s| (s.time as u64, s.coverage))
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "duration": elapsed,
+            "executions": last.map_or(0, |s| s.executions),
+            "average_execs_per_sec": last.map_or(0.0, |s| s.executions as f64 / elapsed.max(1e-3)),
+            "throughput_histogram": self.throughput_histogram(),
+            "corpus": last.map_or(0, |s| s.corpus),
+            "coverage": last.map_or(0, |s| s.coverage),
+            "coverage_curve": curve,
+            "violations": self.oracle_counts,
+            "time_to_first_violation": self.first_violation,
+            "branches": self.branches,
+        })
+    }
+
+    pub fn write_summary(&self) {
+        let summary = self.summary();
+        let _ = std::fs::write(
+            format!("{}/summary.json", self.work_dir),
+            serde_json::to_string_pretty(&summary).unwrap(),
+        );
+
+        let mut text = format!(
+            "duration: {:.0}s\nexecutions: {} ({:.1}/s)\ncorpus: {}\ncoverage: {} branches\n",
+            summary["duration"].as_f64().unwrap(),
+            summary["executions"],
+            summary["average_execs_per_sec"].as_f64().unwrap(),
+            summary["corpus"],
+            summary["coverage"],
+        );
+        text.push_str(&match self.first_violation {
+            Some(t) => format!("time to first violation: {:.0}s\n", t),
+            None => "time to first violation: -\n".to_string(),
+        });
+        text.push_str("violations:\n");
+        for (oracle, count) in &self.oracle_counts {
+            text.push_str(&format!("  {}: {}\n", oracle, count));
+        }
+        text.push_str("throughput (execs/s: samples):\n");
+        for (bucket, count) in self.throughput_histogram() {
+            text.push_str(&format!("  {}-{}: {}\n", bucket, bucket * 2, count));
+        }
+        text.push_str("coverage curve (s: branches):\n");
+        for (time, coverage) in serde_json::from_value::<Vec<(u64, usize)>>(summary["coverage_curve"].clone()).unwrap() {
+            text.push_str(&format!("  {}: {}\n", time, coverage));
+        }
+        let _ = std::fs::write(format!("{}/summary.txt", self.work_dir), text);
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oracle_tags() {
+        assert_eq!(oracle_tags("[typed_bug] 0x01 hit at contract 0x02\n"), vec!["typed_bug"]);
+        assert_eq!(
+            oracle_tags("💰[Flashloan] Earned 1 more than owed 0\n[solvency] protocol holds 0\n"),
+            vec!["flashloan", "solvency"]
+        );
+        assert_eq!(oracle_tags("Imbalanced Pair: 0x01\n"), vec!["other"]);
+    }
+}
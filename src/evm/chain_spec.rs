@@ -0,0 +1,135 @@
+/// Chain specification for fuzzing appchains and L2s with non-standard environments
+use crate::evm::contract_utils::{ABIConfig, ContractInfo, ContractLoader};
+use crate::evm::types::{EVMAddress, EVMU256};
+use revm_primitives::Env;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+/// Rule for the base fee of blocks, base fee chosen by the fuzzer is clamped into [min, max]
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct BaseFeeRule {
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+/// Contract deployed by the chain itself (e.g., system contracts of L2s)
+#[derive(Debug, Clone, Deserialize)]
+pub struct SystemContract {
+    pub name: Option<String>,
+    pub address: String,
+    /// deployed bytecode in hex
+    pub code: String,
+    /// JSON ABI, functions in it are fuzzed
+    pub abi: Option<serde_json::Value>,
+}
+
+/// Chain spec file (JSON), all fields are optional
+///
+/// ```json
+/// {
+///   "chain_id": 1337,
+///   "base_fee": {"min": "0x0", "max": "0x3b9aca00"},
+///   "block_gas_limit": "0x1c9c380",
+///   "precompiles": ["0x0000000000000000000000000000000000000001"],
+///   "system_contracts": [{"address": "0x...", "code": "0x..."}]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ChainSpec {
+    pub chain_id: Option<u64>,
+    pub base_fee: Option<BaseFeeRule>,
+    pub block_gas_limit: Option<String>,
+    /// enabled precompiles, all precompiles are enabled if not specified
+    pub precompiles: Option<Vec<String>>,
+    #[serde(default)]
+    pub system_contracts: Vec<SystemContract>,
+}
+
+fn parse_u256(v: &str) -> EVMU256 {
+    EVMU256::from_str(v).unwrap_or_else(|_| panic!("invalid number in chain spec: {}", v))
+}
+
+fn parse_address(v: &str) -> EVMAddress {
+    EVMAddress::from_str(v).unwrap_or_else(|_| panic!("invalid address in chain spec: {}", v))
+}
+
+impl ChainSpec {
+    pub fn from_file(path: &str) -> Self {
+        let mut buf = String::new();
+        File::open(path)
+            .unwrap_or_else(|_| panic!("chain spec file {} not found", path))
+            .read_to_string(&mut buf)
+            .expect("failed to read chain spec file");
+        Self::from_str(&buf)
+    }
+
+    pub fn from_str(data: &str) -> Self {
+        serde_json::from_str(data).expect("failed to parse chain spec")
+    }
+
+    /// Enforce the chain spec on the environment of a transaction
+    pub fn apply_env(&self, env: &mut Env) {
+        if let Some(chain_id) = self.chain_id {
+            env.cfg.chain_id = EVMU256::from(chain_id);
+        }
+        if let Some(gas_limit) = &self.block_gas_limit {
+            env.block.gas_limit = parse_u256(gas_limit);
+        }
+        if let Some(rule) = &self.base_fee {
+            if let Some(min) = &rule.min {
+                env.block.basefee = env.block.basefee.max(parse_u256(min));
+            }
+            if let Some(max) = &rule.max {
+                env.block.basefee = env.block.basefee.min(parse_u256(max));
+            }
+        }
+    }
+
+    pub fn enabled_precompiles(&self) -> Option<HashSet<EVMAddress>> {
+        self.precompiles
+            .as_ref()
+            .map(|addrs| addrs.iter().map(|x| parse_address(x)).collect())
+    }
+
+    /// Contract infos of system contracts, deployed at their addresses
+    pub fn system_contracts(&self) -> Vec<ContractInfo> {
+        self.system_contracts
+            .iter()
+            .map(|contract| {
+                let code = contract.code.trim_start_matches("0x");
+                ContractInfo {
+                    name: contract.name.clone().unwrap_or(contract.address.clone()),
+                    code: hex::decode(code).expect("invalid system contract code"),
+                    abi: match &contract.abi {
+                        Some(abi) => ContractLoader::parse_abi_str(&abi.to_string()),
+                        None => Vec::<ABIConfig>::new(),
+                    },
+                    is_code_deployed: true,
+                    constructor_args: vec![],
+                    deployed_address: parse_address(&contract.address),
+                    source_map: None,
+                }
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_env() {
+        let spec = ChainSpec::from_str(
+            r#"{"chain_id": 1337, "base_fee": {"min": "0x10", "max": "0x20"}, "block_gas_limit": "0x1000"}"#,
+        );
+        let mut env = Env::default();
+        env.block.basefee = EVMU256::from(0x100);
+        spec.apply_env(&mut env);
+        assert_eq!(env.cfg.chain_id, EVMU256::from(1337));
+        assert_eq!(env.block.gas_limit, EVMU256::from(0x1000));
+        assert_eq!(env.block.basefee, EVMU256::from(0x20));
+    }
+}
@@ -0,0 +1,172 @@
+/// Corpus minimization (`cli cmin`)
+///
+/// Replays each sequence of a corpus (in the replayable format) from the initial state, and
+/// keeps the smallest sequences preserving the coverage (branches with AFL-style hit count
+/// buckets) and the shapes of the resulting VM states (slots written, zero or not).
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use glob::glob;
+use itertools::Itertools;
+
+use crate::evm::host::JMP_MAP;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::replay::replay;
+use crate::evm::types::{EVMFuzzState, EVMStagedVMState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+use crate::generic_vm::vm_executor::MAP_SIZE;
+
+/// A replayed sequence of the corpus
+#[derive(Clone, Debug)]
+pub struct CorpusEntry {
+    pub path: String,
+    pub txs: String,
+    pub tx_count: usize,
+    /// Branch (index in the jump map) * 8 + hit count bucket
+    pub features: HashSet<usize>,
+    /// Hash of the slots written and whether they are zero
+    pub shape: u64,
+}
+
+fn bucket(hits: u8) -> usize {
+    match hits {
+        0 => 0,
+        1 => 1,
+        2 => 2,
+        3 => 3,
+        4..=7 => 4,
+        8..=15 => 5,
+        16..=31 => 6,
+        _ => 7,
+    }
+}
+
+fn state_shape(state: &EVMState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (addr, slots) in state.state.iter().sorted_by_key(|(addr, _)| **addr) {
+        addr.hash(&mut hasher);
+        for (slot, value) in slots.iter().sorted_by_key(|(slot, _)| **slot) {
+            slot.hash(&mut hasher);
+            (*value == EVMU256::ZERO).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Replay a sequence and collect its features
+pub fn replay_entry(
+    executor: &mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &mut EVMFuzzState,
+    initial_state: &EVMStagedVMState,
+    path: &str,
+    txs: &str,
+) -> CorpusEntry {
+    unsafe {
+        JMP_MAP = [0; MAP_SIZE];
+    }
+    let mut tx_count = 0;
+    let mut features = vec![];
+    // the coverage maps are restored after replaying, collect the features as they grow
+    let final_state = replay(executor, state, initial_state, txs, |_, _| {
+        tx_count += 1;
+        features = unsafe {
+            (0..MAP_SIZE)
+                .filter(|idx| JMP_MAP[*idx] > 0)
+                .map(|idx| idx * 8 + bucket(JMP_MAP[idx]))
+                .collect()
+        };
+    });
+    CorpusEntry {
+        path: path.to_string(),
+        txs: txs.to_string(),
+        tx_count,
+        features,
+        shape: state_shape(&final_state.state),
+    }
+}
+
+/// Indices of a minimal subset of `entries` preserving all features and state shapes,
+/// preferring shorter sequences
+pub fn select(entries: &Vec<CorpusEntry>) -> Vec<usize> {
+    let order = (0..entries.len())
+        .sorted_by_key(|idx| (entries[*idx].tx_count, entries[*idx].txs.len()))
+        .collect_vec();
+    let mut selected = vec![];
+    let mut covered: HashSet<usize> = HashSet::new();
+    let mut shapes: HashSet<u64> = HashSet::new();
+    for idx in order {
+        let entry = &entries[idx];
+        let new_features = entry.features.iter().any(|f| !covered.contains(f));
+        if new_features || !shapes.contains(&entry.shape) {
+            covered.extend(entry.features.iter());
+            shapes.insert(entry.shape);
+            selected.push(idx);
+        }
+    }
+    selected.sort();
+    selected
+}
+
+/// Minimize the corpus in `corpus_dir` into `output_dir`, returns (corpus size, minimized size)
+pub fn minimize_corpus(
+    executor: &mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &mut EVMFuzzState,
+    initial_state: &EVMStagedVMState,
+    corpus_dir: &str,
+    output_dir: &str,
+) -> (usize, usize) {
+    let mut entries = vec![];
+    for file in glob(&format!("{}/*_replayable", corpus_dir)).expect("Failed to read glob pattern") {
+        let path = file.expect("glob issue");
+        let txs = std::fs::read_to_string(&path).expect("Failed to read corpus file");
+        entries.push(replay_entry(executor, state, initial_state, path.to_str().unwrap(), &txs));
+    }
+    let selected = select(&entries);
+
+    let output = Path::new(output_dir);
+    if !output.exists() {
+        std::fs::create_dir_all(output).unwrap();
+    }
+    let mut kept_shapes: HashMap<u64, usize> = HashMap::new();
+    for (count, idx) in selected.iter().enumerate() {
+        let entry = &entries[*idx];
+        *kept_shapes.entry(entry.shape).or_default() += 1;
+        std::fs::write(format!("{}/{}_replayable", output_dir, count), &entry.txs).expect("Failed to write corpus file");
+    }
+    crate::status!(
+        "cmin: kept {} of {} sequences ({} branches / buckets, {} state shapes)",
+        selected.len(),
+        entries.len(),
+        entries.iter().flat_map(|e| e.features.iter()).collect::<HashSet<_>>().len(),
+        kept_shapes.len()
+    );
+    (entries.len(), selected.len())
+}
+
+mod tests {
+    use super::*;
+
+    fn entry(tx_count: usize, features: Vec<usize>, shape: u64) -> CorpusEntry {
+        CorpusEntry {
+            path: "".to_string(),
+            txs: "".to_string(),
+            tx_count,
+            features: features.into_iter().collect(),
+            shape,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let entries = vec![
+            entry(3, vec![1, 2, 3], 0),
+            entry(1, vec![1, 2], 0),
+            entry(2, vec![3], 0),
+            entry(2, vec![1], 1),
+            entry(4, vec![2], 1),
+        ];
+        assert_eq!(select(&entries), vec![1, 2, 3]);
+    }
+}
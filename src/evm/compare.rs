@@ -0,0 +1,371 @@
+/// Comparison of campaigns for A/B experiments (`cli compare`)
+///
+/// Each side is a work directory, or a directory of work directories (one per trial). The
+/// summaries (see [`crate::evm::campaign_stats`]) and plot_data of the trials are aligned: branches
+/// and oracles reached by only one side, coverage over time, and the significance of the
+/// differences of final coverage and time to the first violation (Mann-Whitney U test, with the
+/// Vargha-Delaney A12 effect size) and of the oracles violated (Fisher's exact test).
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Number of points of the aligned coverage curves
+const CURVE_POINTS: usize = 10;
+/// Maximum number of branches listed in the text report
+const LISTED_BRANCHES: usize = 20;
+
+/// A campaign, loaded from its work directory
+#[derive(Clone, Debug, Default)]
+pub struct Trial {
+    pub path: String,
+    pub duration: f64,
+    pub executions: u64,
+    pub coverage: usize,
+    /// Oracle => violations
+    pub violations: BTreeMap<String, usize>,
+    pub time_to_first_violation: Option<f64>,
+    pub branches: HashSet<usize>,
+    /// (elapsed seconds, coverage) from plot_data
+    pub curve: Vec<(f64, usize)>,
+}
+
+impl Trial {
+    pub fn load(work_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(work_dir.join("summary.json")).ok()?;
+        let summary: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let curve = std::fs::read_to_string(work_dir.join("plot_data"))
+            .map(|plot| {
+                plot.lines()
+                    .filter(|line| !line.starts_with('#'))
+                    .filter_map(|line| {
+                        let fields = line.split(',').map(|f| f.trim()).collect::<Vec<_>>();
+                        Some((fields.first()?.parse().ok()?, fields.get(3)?.parse().ok()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            path: work_dir.display().to_string(),
+            duration: summary["duration"].as_f64().unwrap_or_default(),
+            executions: summary["executions"].as_u64().unwrap_or_default(),
+            coverage: summary["coverage"].as_u64().unwrap_or_default() as usize,
+            violations: serde_json::from_value(summary["violations"].clone()).unwrap_or_default(),
+            time_to_first_violation: summary["time_to_first_violation"].as_f64(),
+            branches: serde_json::from_value(summary["branches"].clone()).unwrap_or_default(),
+            curve,
+        })
+    }
+
+    /// Coverage reached at `time` (seconds)
+    pub fn coverage_at(&self, time: f64) -> usize {
+        self.curve
+            .iter()
+            .take_while(|(t, _)| *t <= time)
+            .last()
+            .map_or(0, |(_, coverage)| *coverage)
+    }
+}
+
+/// Trials of a side: the work directory, or the work directories it contains
+pub fn load_trials(dir: &str) -> Vec<Trial> {
+    let path = Path::new(dir);
+    if let Some(trial) = Trial::load(path) {
+        return vec![trial];
+    }
+    let mut trials = std::fs::read_dir(path)
+        .unwrap_or_else(|_| panic!("failed to read {}", dir))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Trial::load(&entry.path()))
+        .collect::<Vec<_>>();
+    trials.sort_by(|a, b| a.path.cmp(&b.path));
+    assert!(!trials.is_empty(), "no campaign (summary.json) found in {}", dir);
+    trials
+}
+
+/// Standard normal CDF (Abramowitz and Stegun 7.1.26)
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+/// Two-sided Mann-Whitney U test (normal approximation with tie correction), returns (A12, p)
+/// where A12 is the probability that a value of `a` exceeds a value of `b` (ties counting half)
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> (f64, f64) {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return (0.5, 1.0);
+    }
+    let mut values = a.iter().map(|v| (*v, true)).chain(b.iter().map(|v| (*v, false))).collect::<Vec<_>>();
+    values.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+    let n = values.len();
+    let (mut rank_a, mut ties, mut i) = (0.0, 0.0, 0);
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && values[j + 1].0 == values[i].0 {
+            j += 1;
+        }
+        // average rank of the tied values (ranks start at 1)
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        rank_a += rank * values[i..=j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let t = (j - i + 1) as f64;
+        ties += t * t * t - t;
+        i = j + 1;
+    }
+    let u = rank_a - n1 * (n1 + 1.0) / 2.0;
+    let a12 = u / (n1 * n2);
+    let n = n as f64;
+    let sigma = (n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)))).sqrt();
+    if sigma == 0.0 {
+        return (a12, 1.0);
+    }
+    let mu = n1 * n2 / 2.0;
+    let z = ((u - mu).abs() - 0.5).max(0.0) / sigma;
+    (a12, (2.0 * (1.0 - normal_cdf(z))).min(1.0))
+}
+
+fn ln_factorial(n: usize) -> f64 {
+    (2..=n).map(|i| (i as f64).ln()).sum()
+}
+
+/// Two-sided Fisher's exact test of `hits_a` of `n_a` trials against `hits_b` of `n_b` trials
+pub fn fisher_exact(hits_a: usize, n_a: usize, hits_b: usize, n_b: usize) -> f64 {
+    let hits = hits_a + hits_b;
+    let n = n_a + n_b;
+    let probability = |k: usize| {
+        (ln_factorial(hits) + ln_factorial(n - hits) + ln_factorial(n_a) + ln_factorial(n_b)
+            - ln_factorial(n)
+            - ln_factorial(k)
+            - ln_factorial(hits - k)
+            - ln_factorial(n_a - k)
+            - ln_factorial(n_b + k - hits))
+            .exp()
+    };
+    let observed = probability(hits_a);
+    let low = hits.saturating_sub(n_b);
+    let high = hits.min(n_a);
+    (low..=high)
+        .map(probability)
+        .filter(|p| *p <= observed * (1.0 + 1e-7))
+        .sum::<f64>()
+        .min(1.0)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Metric {
+    pub mean_a: f64,
+    pub mean_b: f64,
+    pub a12: f64,
+    pub p_value: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OracleComparison {
+    /// Trials of each side violating the oracle
+    pub trials_a: usize,
+    pub trials_b: usize,
+    pub violations_a: usize,
+    pub violations_b: usize,
+    pub p_value: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Comparison {
+    pub trials_a: usize,
+    pub trials_b: usize,
+    pub coverage: Metric,
+    /// Seconds, trials without violation count as never violating
+    pub time_to_first_violation: Metric,
+    pub executions_per_sec: Metric,
+    /// Branch map indices reached by some trial of one side only => trials reaching them
+    pub branches_only_a: BTreeMap<usize, usize>,
+    pub branches_only_b: BTreeMap<usize, usize>,
+    pub oracles: BTreeMap<String, OracleComparison>,
+    /// (seconds, mean coverage of A, mean coverage of B)
+    pub curve: Vec<(f64, f64, f64)>,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    let finite = values.iter().filter(|v| v.is_finite()).collect::<Vec<_>>();
+    if finite.is_empty() {
+        return f64::NAN;
+    }
+    finite.iter().copied().sum::<f64>() / finite.len() as f64
+}
+
+fn metric(a: Vec<f64>, b: Vec<f64>) -> Metric {
+    let (a12, p_value) = mann_whitney(&a, &b);
+    Metric {
+        mean_a: mean(&a),
+        mean_b: mean(&b),
+        a12,
+        p_value,
+    }
+}
+
+/// Branches reached by `trials` and never by `others` => trials reaching them
+fn branches_only(trials: &[Trial], others: &[Trial]) -> BTreeMap<usize, usize> {
+    let reached = others.iter().flat_map(|t| t.branches.iter()).collect::<HashSet<_>>();
+    let mut only = BTreeMap::new();
+    for branch in trials.iter().flat_map(|t| t.branches.iter()) {
+        if !reached.contains(branch) {
+            *only.entry(*branch).or_default() += 1;
+        }
+    }
+    only
+}
+
+pub fn compare(a: &[Trial], b: &[Trial]) -> Comparison {
+    let metric_of = |f: &dyn Fn(&Trial) -> f64| metric(a.iter().map(f).collect(), b.iter().map(f).collect());
+
+    let oracles = a
+        .iter()
+        .chain(b.iter())
+        .flat_map(|t| t.violations.keys().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|oracle| {
+            let hits = |trials: &[Trial]| trials.iter().filter(|t| t.violations.get(&oracle).map_or(false, |v| *v > 0)).count();
+            let total = |trials: &[Trial]| trials.iter().map(|t| t.violations.get(&oracle).cloned().unwrap_or(0)).sum();
+            let (trials_a, trials_b) = (hits(a), hits(b));
+            let comparison = OracleComparison {
+                trials_a,
+                trials_b,
+                violations_a: total(a),
+                violations_b: total(b),
+                p_value: fisher_exact(trials_a, a.len(), trials_b, b.len()),
+            };
+            (oracle, comparison)
+        })
+        .collect();
+
+    // up to the end of the shortest trial, so that every trial contributes to every point
+    let end = a.iter().chain(b.iter()).map(|t| t.duration).fold(f64::INFINITY, f64::min);
+    let curve = if end.is_finite() && end > 0.0 {
+        (1..=CURVE_POINTS)
+            .map(|i| {
+                let time = end * i as f64 / CURVE_POINTS as f64;
+                let at = |trials: &[Trial]| mean(&trials.iter().map(|t| t.coverage_at(time) as f64).collect::<Vec<_>>());
+                (time, at(a), at(b))
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    Comparison {
+        trials_a: a.len(),
+        trials_b: b.len(),
+        coverage: metric_of(&|t| t.coverage as f64),
+        time_to_first_violation: metric_of(&|t| t.time_to_first_violation.unwrap_or(f64::INFINITY)),
+        executions_per_sec: metric_of(&|t| t.executions as f64 / t.duration.max(1e-3)),
+        branches_only_a: branches_only(a, b),
+        branches_only_b: branches_only(b, a),
+        oracles,
+        curve,
+    }
+}
+
+fn significance(p_value: f64) -> &'static str {
+    if p_value < 0.05 {
+        "significant"
+    } else {
+        "not significant"
+    }
+}
+
+fn list_branches(branches: &BTreeMap<usize, usize>, trials: usize) -> String {
+    let mut listed = branches
+        .iter()
+        .take(LISTED_BRANCHES)
+        .map(|(branch, hits)| format!("{:#x} ({}/{})", branch, hits, trials))
+        .collect::<Vec<_>>();
+    if branches.len() > LISTED_BRANCHES {
+        listed.push("...".to_string());
+    }
+    listed.join(", ")
+}
+
+impl Comparison {
+    pub fn report(&self) -> String {
+        let mut text = format!("trials: A {}, B {}\n", self.trials_a, self.trials_b);
+        for (name, metric) in [
+            ("coverage", &self.coverage),
+            ("time to first violation (s)", &self.time_to_first_violation),
+            ("execs/s", &self.executions_per_sec),
+        ] {
+            text.push_str(&format!(
+                "{}: A {:.1}, B {:.1} (A12 {:.2}, p {:.4}, {})\n",
+                name,
+                metric.mean_a,
+                metric.mean_b,
+                metric.a12,
+                metric.p_value,
+                significance(metric.p_value)
+            ));
+        }
+        text.push_str(&format!(
+            "branches only reached by A: {}\n  {}\n",
+            self.branches_only_a.len(),
+            list_branches(&self.branches_only_a, self.trials_a)
+        ));
+        text.push_str(&format!(
+            "branches only reached by B: {}\n  {}\n",
+            self.branches_only_b.len(),
+            list_branches(&self.branches_only_b, self.trials_b)
+        ));
+        text.push_str("oracles (trials violating, violations):\n");
+        for (oracle, cmp) in &self.oracles {
+            let only = match (cmp.trials_a, cmp.trials_b) {
+                (_, 0) => " [only A]",
+                (0, _) => " [only B]",
+                _ => "",
+            };
+            text.push_str(&format!(
+                "  {}: A {}/{} ({}), B {}/{} ({}), p {:.4}{}\n",
+                oracle,
+                cmp.trials_a,
+                self.trials_a,
+                cmp.violations_a,
+                cmp.trials_b,
+                self.trials_b,
+                cmp.violations_b,
+                cmp.p_value,
+                only
+            ));
+        }
+        text.push_str("coverage curve (s: A, B):\n");
+        for (time, a, b) in &self.curve {
+            text.push_str(&format!("  {:.0}: {:.1}, {:.1}\n", time, a, b));
+        }
+        text
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mann_whitney() {
+        let (a12, p) = mann_whitney(&[10.0, 11.0, 12.0, 13.0, 14.0], &[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(a12, 1.0);
+        assert!(p < 0.05);
+        let (a12, p) = mann_whitney(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]);
+        assert_eq!(a12, 0.5);
+        assert!(p > 0.5);
+    }
+
+    #[test]
+    fn test_fisher_exact() {
+        assert!((fisher_exact(5, 5, 0, 5) - 0.00794).abs() < 1e-4);
+        assert!((fisher_exact(2, 4, 2, 4) - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,5 @@
 use bytes::Bytes;
+use tracing::debug;
 
 use crate::evm::abi::BoxedABI;
 use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
@@ -603,8 +604,8 @@ where
         // TODO: Figure out the corresponding MiddlewareOp to add
         // We may need coverage map here to decide whether to add a new input to the
         // corpus or not.
-        println!("concolic {:?}", interp.stack);
-        println!("{:?}", self.symbolic_stack);
+        debug!("concolic {:?}", interp.stack);
+        debug!("{:?}", self.symbolic_stack);
         let bv: Vec<Option<Box<Expr>>> = match *interp.instruction_pointer {
             // ADD
             0x01 => {
@@ -1008,7 +1009,7 @@ where
         //     .expect("Failed loading input")
         //     .clone();
         for s in solutions {
-            println!("Solution: {:?}", s);
+            debug!("Solution: {:?}", s);
             // let mut new_input = input.clone();
             // new_input
             //     .get_data_abi_mut()
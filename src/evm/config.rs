@@ -1,8 +1,13 @@
 /// Configuration for the EVM fuzzer
 use crate::evm::contract_utils::{ContractInfo, ContractLoader};
+use crate::evm::input::EVMInput;
+use crate::evm::middlewares::middleware::Middleware;
+use crate::evm::middlewares::call_tracer::CallTracer;
 use crate::evm::onchain::endpoints::{OnChainConfig, PriceOracle};
 
-use crate::evm::oracles::erc20::IERC20OracleFlashloan;
+use crate::evm::power_schedule::PowerSchedule;
+use crate::evm::types::EVMFuzzState;
+use crate::evm::vm::EVMState;
 use crate::oracle::{Oracle, Producer};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -52,8 +57,15 @@ pub struct Config<VS, Addr, Code, By, Loc, SlotTy, Out, I, S, CI> {
     pub producers: Vec<Rc<RefCell<dyn Producer<VS, Addr, Code, By, Loc, SlotTy, Out, I, S, CI>>>>,
     pub price_oracle: Box<dyn PriceOracle>,
     pub replay_file: Option<String>,
-    pub flashloan_oracle: Rc<RefCell<IERC20OracleFlashloan>>,
+    /// Solution to load into the interactive debugger
+    pub debug_file: Option<String>,
+    /// Detect imbalanced Uniswap V2 pairs
+    pub pair_oracle: bool,
+    /// Detect profits of the attacker in ERC20 tokens (with flashloans)
+    pub ierc20_oracle: bool,
     pub selfdestruct_oracle: bool,
+    /// Report bugs flagged by the targets (`typed_bug(...)`, `bug()`)
+    pub typed_bug_oracle: bool,
     pub work_dir: String,
     pub write_relationship: bool,
     pub run_forever: bool,
@@ -62,4 +74,159 @@ pub struct Config<VS, Addr, Code, By, Loc, SlotTy, Out, I, S, CI> {
     pub echidna_oracle: bool,
     pub panic_on_bug: bool,
     pub spec_id: String,
+    pub upgrade_proxy: Option<String>,
+    pub upgrade_implementation: Option<String>,
+    pub upgrade_slot: Option<String>,
+    /// EOA callers delegate to a contract (EIP-7702), detect contracts assuming they are EOAs
+    pub eip7702: bool,
+    pub chain_spec: Option<String>,
+    pub wasm_precompiles: Vec<String>,
+    pub zksync: bool,
+    /// Gas ceiling of a transaction, enables gas estimation and the gas bomb oracle
+    pub gas_ceiling: Option<u64>,
+    /// Generate victim transactions and detect slippage / sandwich vulnerabilities
+    pub sandwich_oracle: bool,
+    /// Model signatures verified by ecrecover and detect signature replays
+    pub signature_oracle: bool,
+    /// Send transactions through an intermediary contract and detect tx.origin authorization
+    pub tx_origin_oracle: bool,
+    pub reentrancy_oracle: bool,
+    /// Detect payouts depending on block.coinbase, block.basefee or block.prevrandao
+    pub block_context_oracle: bool,
+    /// Detect bugs of the constructors of the targets
+    pub constructor_oracle: bool,
+    /// Detect low-level calls whose failure is ignored
+    pub unchecked_call_oracle: bool,
+    /// Detect payouts the sender can game by choosing the gas price
+    pub fee_oracle: bool,
+    /// Run the hijack implementation on delegatecalls to attacker-controlled addresses and detect
+    /// takeovers through them
+    pub delegatecall_oracle: bool,
+    /// Detect initializers that can be called by an arbitrary sender
+    pub initializer_oracle: bool,
+    /// Track narrowing casts and detect truncated values being stored
+    pub truncation_oracle: bool,
+    /// Tolerated relative error of stored values to their exact value, enables the rounding oracle
+    pub rounding_epsilon: Option<f64>,
+    /// Differential fuzzing, contract to fuzz and its other version (address or contract name)
+    pub differential_target: Option<String>,
+    pub differential_shadow: Option<String>,
+    /// Path to geth's `evm` tool, violating sequences are cross-checked with it if set
+    pub cross_evm: Option<String>,
+    /// Corpus directory whose sequences are cross-checked with `cross_evm` instead of fuzzing
+    pub cross_evm_corpus: Option<String>,
+    /// Path to the node forking the chain (anvil), findings of onchain mode are only reported if
+    /// they reproduce on a fork at the same block
+    pub shadow_fork: Option<String>,
+    /// Path to the solvency invariant of the protocol (JSON), enables the solvency oracle
+    pub solvency_config: Option<String>,
+    /// Path to the invariants checked across snapshots of a sequence (JSON), enables the epoch oracle
+    pub epoch_invariants: Option<String>,
+    /// Path to the invariants over the events emitted by a sequence (JSON), enables the event oracle
+    pub event_invariants: Option<String>,
+    /// View functions reporting the ETH accounted for by contracts (`<contract>:<function>`),
+    /// enables the ETH conservation oracle
+    pub eth_accounting: Vec<String>,
+    /// Path to the liquidation scenario of a lending market (JSON), enables the liquidation oracle
+    pub lending_config: Option<String>,
+    /// Custom oracles implemented in WASM (paths to the modules)
+    pub oracle_plugins: Vec<String>,
+    /// Middleware hooks implemented in Rhai (paths to the scripts)
+    pub middleware_scripts: Vec<String>,
+    /// Tracer of the last transaction, shared with custom oracles (e.g., Python oracles)
+    pub tx_tracer: Option<Rc<RefCell<CallTracer>>>,
+    /// Additional middlewares added to the host (e.g., by library users)
+    pub middlewares: Vec<Rc<RefCell<dyn Middleware<EVMState, EVMInput, EVMFuzzState>>>>,
+    /// Number of fuzzing iterations, fuzz until a bug is found if unset
+    pub iterations: Option<u64>,
+    /// Address of the control socket (e.g., `127.0.0.1:8090`), enables runtime control
+    pub control: Option<String>,
+    /// Oracles not reported (by name, e.g., `erc20`), can be re-enabled on the control socket
+    pub disabled_oracles: Vec<String>,
+    /// Contracts (addresses or names) never called directly by the fuzzer, e.g., trusted
+    /// infrastructure like WETH
+    pub frozen_contracts: Vec<String>,
+    /// Power schedule assigning energy to inputs
+    pub power_schedule: PowerSchedule,
+    /// Give more energy to inputs executed on states built by longer sequences
+    pub sequence_energy: bool,
+    /// Boost the energy of inputs hitting globally rare branches
+    pub rare_boost: bool,
+    /// Corpus to minimize (directory of replayable sequences) and directory of the minimized corpus
+    pub cmin_corpus: Option<String>,
+    pub cmin_output: Option<String>,
+    /// Trim corpus entries (shorter sequences and args) preserving their coverage
+    pub trim: bool,
+    /// Git revision, code changed since then is favored (directed fuzzing)
+    pub focus_diff: Option<String>,
+    /// Bounds on the environment relative to the fork point (e.g., `timestamp=604800,number=50400`)
+    pub env_bounds: Option<String>,
+    /// Keep environment values beyond the bounds and report findings depending on them,
+    /// instead of clamping them
+    pub env_bounds_report: bool,
+    /// Bounds of the gas price chosen by the mutator (`min:max`, in wei)
+    pub gas_price_bounds: Option<String>,
+    /// Screen pending transactions to the targets (on-chain only)
+    pub mempool: bool,
+    /// Bundle config file, profitable sequences are exported as signed bundles (on-chain only)
+    pub bundle_config: Option<String>,
+    /// Register the contract callers as ERC-777 / ERC-1363 token hooks, reentering during transfers
+    pub token_callbacks: bool,
+    /// Make the contract callers ERC-721 / ERC-1155 receivers, reentering during safe transfers
+    pub nft_callbacks: bool,
+    /// Mock addresses without code called by the targets, with return data provided by the fuzzer
+    pub mock_missing: bool,
+    /// Bounds of the moves of Chainlink feeds (e.g., `deviation=2,heartbeat=3600`, or `compromised`)
+    pub chainlink: Option<String>,
+    /// Report the capital and blocks needed by the TWAP manipulations findings rely on
+    pub twap_cost: bool,
+    /// Findings relying on TWAP manipulations needing more capital (in multiples of the pool
+    /// reserve) are downgraded
+    pub twap_max_capital: Option<f64>,
+    /// Credit coverage of executions that end up reverting
+    pub revert_coverage: bool,
+    /// Pick msg.value of payable calls with strategies learned per function rather than randomly
+    pub payable_values: bool,
+    /// Pick states built by sequences of the lengths yielding coverage for each function
+    pub adaptive_length: bool,
+    /// Summarize findings with an LLM (requires llm_triage feature)
+    pub llm_triage: bool,
+    /// Chat completions endpoint of the LLM (OpenAI compatible)
+    pub llm_endpoint: String,
+    pub llm_model: String,
+    /// Backend storing the corpus and solutions: `disk`, `sqlite[:<path>]` or
+    /// `s3://<bucket>/<prefix>` (see [`crate::storage`])
+    pub storage: String,
+    /// Endpoint of the S3-compatible service, AWS if not set
+    pub storage_endpoint: Option<String>,
+    /// Project whose sources are watched: the campaign returns once they change (`--watch`)
+    pub watch: Option<String>,
+    /// Soak mode settings (`--soak`): the campaign runs forever in segments, with periodic digests
+    /// and corpus culls
+    pub soak: Option<String>,
+    /// Maximum number of instructions executed by a transaction, longer executions are aborted
+    pub step_limit: Option<u64>,
+    /// Maximum time (in milliseconds) taken by a transaction, longer executions are aborted
+    pub exec_timeout: Option<u64>,
+    /// Memory cap (in bytes): fetched on-chain code / storage take at most a quarter of it, and
+    /// infant states are pruned while the process uses more
+    pub memory_cap: Option<usize>,
+    /// Groups of targets energy is allocated across by coverage progress (`--target-groups`)
+    pub target_groups: Option<String>,
+    /// Aggregate the coverage of contracts sharing runtime bytecode in coverage reports
+    pub coverage_by_code: bool,
+    /// With `coverage_by_code`, also report the coverage of each address
+    pub coverage_per_address: bool,
+    /// Echidna / Medusa corpus files (glob) imported as seeds
+    pub import_corpus: Option<String>,
+    /// Only functions whose name matches this regex are fuzzed
+    pub only_fuzz: Option<String>,
+    /// Weights table of functions (`pattern=weight,...` or a JSON file)
+    pub function_weights: Option<String>,
+    /// Path to the approvals and positions of the senders established before the campaign (JSON)
+    pub pre_state: Option<String>,
+    /// Path to the bridge harness (JSON), forges messages from its messengers and enables the bridge oracle
+    pub bridge_config: Option<String>,
+    /// Path to known addresses (JSON) extending the built-in registry of routers, tokens and flashloan providers
+    pub known_addresses: Option<String>,
 }
@@ -3,6 +3,7 @@ use crate::evm::types::{
 };
 /// Load contract from file system or remote
 use glob::glob;
+use tracing::{debug, info, warn};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
@@ -14,8 +15,10 @@ use std::path::Path;
 extern crate crypto;
 
 use crate::evm::abi::get_abi_type_boxed_with_address;
+use crate::evm::linker::{link_libraries, strip_placeholders, LinkReference};
 use crate::evm::onchain::endpoints::OnChainConfig;
 use crate::evm::srcmap::parser::{decode_instructions, SourceMapLocation};
+use crate::evm::storage_layout::{parse_combined_layouts, StorageLayout};
 
 use self::crypto::digest::Digest;
 use self::crypto::sha3::Sha3;
@@ -35,6 +38,9 @@ pub struct ABIConfig {
     pub is_static: bool,
     pub is_payable: bool,
     pub is_constructor: bool,
+    /// Names of the arguments (empty if unknown)
+    #[serde(default)]
+    pub input_names: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +64,13 @@ pub struct ABIInfo {
 pub struct ContractLoader {
     pub contracts: Vec<ContractInfo>,
     pub abis: Vec<ABIInfo>,
+    /// Storage layouts from solc, by contract name
+    pub storage_layouts: HashMap<String, StorageLayout>,
+    /// References to external libraries in the bytecode of the contracts, by contract name
+    pub links: HashMap<String, Vec<LinkReference>>,
+    /// Source maps of code the contracts delegate to (e.g., implementations behind proxies),
+    /// by address
+    pub implementation_source_maps: HashMap<EVMAddress, HashMap<usize, SourceMapLocation>>,
 }
 
 pub fn set_hash(name: &str, out: &mut [u8]) {
@@ -126,10 +139,16 @@ impl ContractLoader {
                         is_static: abi["stateMutability"].as_str().unwrap_or_default() == "view",
                         is_payable: abi["stateMutability"].as_str().unwrap_or_default() == "payable",
                         is_constructor: abi["type"] == "constructor",
+                        input_names: abi["inputs"]
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|input| input["name"].as_str().unwrap_or_default().to_string())
+                            .collect(),
                     };
                     let function_to_hash = format!("{}({})", name, abi_name.join(","));
                     // print name and abi_name
-                    println!("{}({})", name, abi_name.join(","));
+                    debug!("{}({})", name, abi_name.join(","));
 
                     set_hash(function_to_hash.as_str(), &mut abi_config.function);
                     Some(abi_config)
@@ -147,6 +166,13 @@ impl ContractLoader {
         hex::decode(data).expect("Failed to parse hex file")
     }
 
+    /// Bytecode in a BIN file, which may be unlinked, and the libraries it references
+    fn parse_bin_file(path: &Path) -> (Vec<u8>, Vec<LinkReference>) {
+        let data = std::fs::read_to_string(path).expect("Failed to read bin file");
+        let (code, links) = strip_placeholders(&data);
+        (hex::decode(code).expect("Failed to parse bin file"), links)
+    }
+
     fn constructor_args_encode(constructor_args: &Vec<String>) -> Vec<u8> {
         constructor_args
             .iter()
@@ -204,8 +230,9 @@ impl ContractLoader {
             source: prefix.to_string(),
             abi: vec![],
         };
+        let mut links = vec![];
 
-        println!("Loading contract {}", prefix);
+        info!("Loading contract {}", prefix);
 
         // Load contract, ABI, and address from file
         for i in glob(prefix).expect("not such path for prefix") {
@@ -218,7 +245,9 @@ impl ContractLoader {
                         // println!("ABI: {:?}", result.abis);
                     } else if path.to_str().unwrap().ends_with(".bin") {
                         // this is an BIN file
-                        contract_result.code = Self::parse_hex_file(&path);
+                        let (code, bin_links) = Self::parse_bin_file(&path);
+                        contract_result.code = code;
+                        links = bin_links;
                     } else if path.to_str().unwrap().ends_with(".address") {
                         // this is deployed address
                         contract_result
@@ -226,10 +255,10 @@ impl ContractLoader {
                             .0
                             .clone_from_slice(Self::parse_hex_file(&path).as_slice());
                     } else {
-                        println!("Found unknown file: {:?}", path.display())
+                        warn!("Found unknown file: {:?}", path.display())
                     }
                 }
-                Err(e) => println!("{:?}", e),
+                Err(e) => warn!("{:?}", e),
             }
         }
 
@@ -238,13 +267,13 @@ impl ContractLoader {
                 get_abi_type_boxed_with_address(&abi.abi, fixed_address(FIX_DEPLOYER).0.to_vec());
             abi_instance.set_func_with_name(abi.function, abi.function_name.clone());
             if contract_result.constructor_args.len() == 0 {
-                println!("No constructor args found, using default constructor args");
+                info!("No constructor args found, using default constructor args");
                 contract_result.constructor_args = abi_instance.get().get_bytes();
             }
             // println!("Constructor args: {:?}", result.constructor_args);
             contract_result.code.extend(contract_result.constructor_args.clone());
         } else {
-            println!("No constructor in ABI found, skipping");
+            info!("No constructor in ABI found, skipping");
         }
 
         // now check if contract is deployed through proxy by checking function signatures
@@ -285,6 +314,9 @@ impl ContractLoader {
                 vec![]
             },
             abis: vec![abi_result],
+            storage_layouts: HashMap::new(),
+            links: HashMap::from([(prefix.to_string(), links)]),
+            implementation_source_maps: HashMap::new(),
         };
     }
 
@@ -303,6 +335,8 @@ impl ContractLoader {
     ) -> Self {
         let mut prefix_file_count: HashMap<String, u8> = HashMap::new();
         let mut contract_combined_json_info = None;
+        let mut storage_layouts = HashMap::new();
+        let mut qualified_names = vec![];
         for i in glob(p).expect("not such folder") {
             match i {
                 Ok(path) => {
@@ -317,11 +351,18 @@ impl ContractLoader {
                             .or_insert(0) += 1;
                     } else if path_str.ends_with("combined.json") {
                         contract_combined_json_info = Some(path_str.to_string());
+                    } else if path_str.ends_with("_meta.json") {
+                        // solc metadata, used for detecting EVM version
+                    } else if path_str.ends_with("_storage.json") {
+                        // solc storage layout, used for labeling storage slots in reports
+                        let name = path.file_name().unwrap().to_str().unwrap().replace("_storage.json", "");
+                        let layout = std::fs::read_to_string(&path).expect("failed to read storage layout");
+                        storage_layouts.insert(name, StorageLayout::from_json(&layout));
                     } else {
-                        println!("Found unknown file in folder: {:?}", path.display())
+                        warn!("Found unknown file in folder: {:?}", path.display())
                     }
                 }
-                Err(e) => println!("{:?}", e),
+                Err(e) => warn!("{:?}", e),
             }
         }
 
@@ -331,12 +372,22 @@ impl ContractLoader {
                 let mut combined_json = File::open(file_name).unwrap();
                 let mut buf = String::new();
                 combined_json.read_to_string(&mut buf).unwrap();
+                for (name, layout) in parse_combined_layouts(&buf) {
+                    storage_layouts.entry(name).or_insert(layout);
+                }
+                // fully qualified names (path:Name), resolving library placeholders
+                if let Ok(Value::Object(json)) = serde_json::from_str::<Value>(&buf) {
+                    if let Some(Value::Object(compiled)) = json.get("contracts") {
+                        qualified_names = compiled.keys().cloned().collect();
+                    }
+                }
                 Some(parse_combined_json(buf))
             }
         };
 
         let mut contracts: Vec<ContractInfo> = vec![];
         let mut abis: Vec<ABIInfo> = vec![];
+        let mut links = HashMap::new();
         for (prefix, count) in prefix_file_count.iter().sorted_by_key(|(k, _)| k.clone()) {
             let p = prefix.to_string();
             if *count > 0 {
@@ -358,25 +409,63 @@ impl ContractLoader {
                 );
                 prefix_loader.contracts.iter().for_each(|c| contracts.push(c.clone()));
                 prefix_loader.abis.iter().for_each(|a| abis.push(a.clone()));
+                links.extend(prefix_loader.links);
             }
         }
+        link_libraries(&mut contracts, &links, &qualified_names);
+
+        ContractLoader {
+            contracts,
+            abis,
+            storage_layouts,
+            links,
+            implementation_source_maps: HashMap::new(),
+        }
+    }
 
-        ContractLoader { contracts, abis }
+    /// Append functions of `other` to `abi`, skipping constructors and known selectors
+    fn merge_abi(abi: &mut Vec<ABIConfig>, other: Vec<ABIConfig>) {
+        let known = abi.iter().map(|x| x.function).collect::<HashSet<_>>();
+        abi.extend(
+            other
+                .into_iter()
+                .filter(|x| !x.is_constructor && !known.contains(&x.function)),
+        );
     }
 
     pub fn from_address(onchain: &mut OnChainConfig, address: HashSet<EVMAddress>) -> Self {
         let mut contracts: Vec<ContractInfo> = vec![];
         let mut abis: Vec<ABIInfo> = vec![];
+        let mut implementation_source_maps = HashMap::new();
         for addr in address {
             let abi = onchain.fetch_abi(addr);
             let contract_code = onchain.get_contract_code(addr, false);
 
-            let abi_parsed = if let Some(abi) = abi {
+            let mut abi_parsed = if let Some(abi) = abi {
                 Self::parse_abi_str(&abi)
             } else {
-                println!("ABI not found for {}, we'll decompile", addr);
+                info!("ABI not found for {}, we'll decompile", addr);
                 vec![]
             };
+
+            // fuzz through the proxy / diamond with the implementations' ABI
+            let mut implementations = onchain.get_diamond_facets(addr);
+            if implementations.len() > 0 {
+                info!("{:?} is a diamond with facets {:?}", addr, implementations);
+            } else if let Some(implementation) = onchain.get_proxy_implementation(addr) {
+                info!("{:?} is a proxy of {:?}", addr, implementation);
+                implementations.push(implementation);
+            }
+            for implementation in implementations {
+                match onchain.fetch_abi(implementation) {
+                    Some(impl_abi) => Self::merge_abi(&mut abi_parsed, Self::parse_abi_str(&impl_abi)),
+                    None => warn!("ABI not found for implementation {:?}", implementation),
+                }
+                // coverage of the delegated code is recorded at the implementation's address
+                if let Some(source_map) = onchain.fetch_source_map(implementation) {
+                    implementation_source_maps.insert(implementation, source_map);
+                }
+            }
             contracts.push(ContractInfo {
                 name: addr.to_string(),
                 code: contract_code.bytes().to_vec(),
@@ -391,8 +480,58 @@ impl ContractLoader {
                 abi: abi_parsed,
             });
         }
-        Self { contracts, abis }
+        Self {
+            contracts,
+            abis,
+            storage_layouts: HashMap::new(),
+            links: HashMap::new(),
+            implementation_source_maps,
+        }
+    }
+}
+
+/// Detect the EVM version contracts in `p` are compiled for from solc metadata,
+/// found either in combined.json (`--combined-json metadata,...`) or `*_meta.json` (`--metadata`)
+pub fn detect_evm_version(p: &str) -> Option<String> {
+    let mut versions: Vec<String> = vec![];
+    let mut add_metadata = |metadata: &Value| {
+        if let Some(version) = metadata["settings"]["evmVersion"].as_str() {
+            if !versions.contains(&version.to_string()) {
+                versions.push(version.to_string());
+            }
+        }
+    };
+    for path in glob(p).expect("not such folder").flatten() {
+        let path_str = path.to_str().unwrap();
+        if !path_str.ends_with("combined.json") && !path_str.ends_with("_meta.json") {
+            continue;
+        }
+        let mut buf = String::new();
+        File::open(&path).unwrap().read_to_string(&mut buf).unwrap();
+        let json = match serde_json::from_str::<Value>(&buf) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        if path_str.ends_with("_meta.json") {
+            add_metadata(&json);
+            continue;
+        }
+        if let Some(contracts) = json["contracts"].as_object() {
+            for (_, contract_info) in contracts {
+                // metadata is a JSON string in combined.json
+                if let Some(metadata) = contract_info["metadata"]
+                    .as_str()
+                    .and_then(|x| serde_json::from_str::<Value>(x).ok())
+                {
+                    add_metadata(&metadata);
+                }
+            }
+        }
+    }
+    if versions.len() > 1 {
+        warn!("Contracts are compiled for different EVM versions {:?}, using {}", versions, versions[0]);
     }
+    versions.into_iter().next()
 }
 
 type ContractSourceMap = HashMap<usize, SourceMapLocation>;
@@ -421,7 +560,8 @@ pub fn parse_combined_json(json: String) -> ContractsSourceMapInfo {
         let bin_runtime = contract_info["bin-runtime"]
             .as_str()
             .expect("bin-runtime not found");
-        let bin_runtime_bytes = hex::decode(bin_runtime).expect("bin-runtime is not hex");
+        // library placeholders have the length of the addresses linked
+        let bin_runtime_bytes = hex::decode(strip_placeholders(bin_runtime).0).expect("bin-runtime is not hex");
 
         let srcmap_runtime = contract_info["srcmap-runtime"]
             .as_str()
@@ -0,0 +1,222 @@
+/// Control socket of a running campaign (`--control <addr>`)
+///
+/// Requests are JSON-RPC 2.0, one per line, e.g.:
+/// ```text
+/// {"jsonrpc": "2.0", "id": 1, "method": "pause"}
+/// {"jsonrpc": "2.0", "id": 2, "method": "resume"}
+/// {"jsonrpc": "2.0", "id": 3, "method": "inject_seed", "params": {"tx": "<replayable tx>"}}
+/// {"jsonrpc": "2.0", "id": 4, "method": "focus", "params": {"functions": ["0xa9059cbb"]}}
+/// {"jsonrpc": "2.0", "id": 5, "method": "dump"}
+/// {"jsonrpc": "2.0", "id": 6, "method": "disable_oracles", "params": {"oracles": ["erc20", "reentrancy"]}}
+/// {"jsonrpc": "2.0", "id": 7, "method": "enable_oracles", "params": {"oracles": ["erc20"]}}
+/// {"jsonrpc": "2.0", "id": 8, "method": "freeze", "params": {"contracts": ["WETH", "0xc02a..."]}}
+/// {"jsonrpc": "2.0", "id": 9, "method": "unfreeze", "params": {"contracts": ["WETH"]}}
+/// ```
+/// Seeds use the format of replayable transactions (`*_replayable` files), an empty focus
+/// mutates all functions again. Oracles are named as in `--disable-oracles`, and frozen contracts
+/// (addresses or names) are trusted: they are only called by other contracts, never directly by
+/// the fuzzer. Commands are applied between fuzzing iterations.
+use std::collections::HashSet;
+use tracing::info;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use serde_json::{json, Value};
+
+use crate::evm::types::EVMAddress;
+
+/// Functions mutated by the fuzzer, all if unset
+pub static mut FOCUS: Option<HashSet<[u8; 4]>> = None;
+/// Contracts not called directly by the fuzzer
+pub static mut FROZEN: Option<HashSet<EVMAddress>> = None;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    InjectSeed(String),
+    Focus(Vec<[u8; 4]>),
+    Dump,
+    /// Oracles to enable (true) or disable (false)
+    ToggleOracles(Vec<String>, bool),
+    /// Contracts to freeze (true) or unfreeze (false)
+    Freeze(Vec<String>, bool),
+}
+
+/// Whether the fuzzer should mutate calls to `function`
+pub fn is_focused(function: &[u8; 4]) -> bool {
+    match unsafe { &FOCUS } {
+        Some(focus) => focus.contains(function),
+        None => true,
+    }
+}
+
+pub fn set_focus(functions: Vec<[u8; 4]>) {
+    unsafe {
+        FOCUS = if functions.is_empty() {
+            None
+        } else {
+            Some(functions.into_iter().collect())
+        };
+    }
+}
+
+/// Whether the fuzzer may send transactions to `contract`
+pub fn is_frozen(contract: &EVMAddress) -> bool {
+    match unsafe { &FROZEN } {
+        Some(frozen) => frozen.contains(contract),
+        None => false,
+    }
+}
+
+pub fn set_frozen(contract: EVMAddress, frozen: bool) {
+    unsafe {
+        let contracts = FROZEN.get_or_insert_with(HashSet::new);
+        if frozen {
+            contracts.insert(contract);
+        } else {
+            contracts.remove(&contract);
+        }
+    }
+}
+
+fn strings(params: &Value, key: &str) -> Result<Vec<String>, String> {
+    params
+        .get(key)
+        .and_then(|v| v.as_array())
+        .ok_or(format!("params.{} is required", key))?
+        .iter()
+        .map(|v| v.as_str().map(|v| v.to_string()).ok_or(format!("{} should be strings", key)))
+        .collect()
+}
+
+/// Parse a request into its id and command
+pub fn parse_request(line: &str) -> Result<(Value, ControlCommand), String> {
+    let request: Value = serde_json::from_str(line).map_err(|e| format!("invalid request: {}", e))?;
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let command = match request.get("method").and_then(|m| m.as_str()) {
+        Some("pause") => ControlCommand::Pause,
+        Some("resume") => ControlCommand::Resume,
+        Some("dump") => ControlCommand::Dump,
+        Some("inject_seed") => ControlCommand::InjectSeed(
+            params
+                .get("tx")
+                .and_then(|tx| tx.as_str())
+                .ok_or("inject_seed requires params.tx")?
+                .to_string(),
+        ),
+        Some("focus") => ControlCommand::Focus(
+            params
+                .get("functions")
+                .and_then(|f| f.as_array())
+                .ok_or("focus requires params.functions")?
+                .iter()
+                .map(|f| {
+                    let f = f.as_str().ok_or("functions should be strings")?;
+                    let bytes = hex::decode(f.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+                    <[u8; 4]>::try_from(bytes.as_slice()).map_err(|_| format!("invalid selector {}", f))
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+        ),
+        Some("enable_oracles") => ControlCommand::ToggleOracles(strings(&params, "oracles")?, true),
+        Some("disable_oracles") => ControlCommand::ToggleOracles(strings(&params, "oracles")?, false),
+        Some("freeze") => ControlCommand::Freeze(strings(&params, "contracts")?, true),
+        Some("unfreeze") => ControlCommand::Freeze(strings(&params, "contracts")?, false),
+        Some(method) => return Err(format!("unknown method {}", method)),
+        None => return Err("missing method".to_string()),
+    };
+    Ok((id, command))
+}
+
+fn handle(stream: TcpStream, sender: Sender<ControlCommand>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match parse_request(&line) {
+            Ok((id, command)) => {
+                if sender.send(command).is_err() {
+                    return;
+                }
+                json!({"jsonrpc": "2.0", "id": id, "result": "ok"})
+            }
+            Err(e) => json!({"jsonrpc": "2.0", "id": Value::Null, "error": {"code": -32600, "message": e}}),
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+/// Listen on `addr` (e.g., `127.0.0.1:8090`) in the background, commands are received
+/// by the fuzzer through the returned channel
+pub fn serve(addr: &str) -> Receiver<ControlCommand> {
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| panic!("failed to bind control socket {}: {}", addr, e));
+    info!("Control socket listening on {}", addr);
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            std::thread::spawn(move || handle(stream, sender));
+        }
+    });
+    receiver
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let (id, command) = parse_request(r#"{"jsonrpc": "2.0", "id": 4, "method": "focus", "params": {"functions": ["0xa9059cbb"]}}"#).unwrap();
+        assert_eq!(id, json!(4));
+        assert_eq!(command, ControlCommand::Focus(vec![[0xa9, 0x05, 0x9c, 0xbb]]));
+        assert_eq!(parse_request(r#"{"method": "pause"}"#).unwrap().1, ControlCommand::Pause);
+        assert!(parse_request(r#"{"method": "inject_seed"}"#).is_err());
+        assert!(parse_request(r#"{"method": "stop"}"#).is_err());
+        assert_eq!(
+            parse_request(r#"{"method": "disable_oracles", "params": {"oracles": ["erc20"]}}"#).unwrap().1,
+            ControlCommand::ToggleOracles(vec!["erc20".to_string()], false)
+        );
+        assert!(parse_request(r#"{"method": "freeze", "params": {"contracts": [1]}}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_toggles() {
+        let command = |line: &str| parse_request(line).unwrap().1;
+        assert_eq!(
+            command(r#"{"method": "enable_oracles", "params": {"oracles": ["erc20", "fee"]}}"#),
+            ControlCommand::ToggleOracles(vec!["erc20".to_string(), "fee".to_string()], true)
+        );
+        assert_eq!(
+            command(r#"{"method": "freeze", "params": {"contracts": ["WETH"]}}"#),
+            ControlCommand::Freeze(vec!["WETH".to_string()], true)
+        );
+        assert_eq!(
+            command(r#"{"method": "unfreeze", "params": {"contracts": []}}"#),
+            ControlCommand::Freeze(vec![], false)
+        );
+        assert!(parse_request(r#"{"method": "enable_oracles", "params": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_frozen() {
+        let (contract, other) = (EVMAddress::from_slice(&[0xf1; 20]), EVMAddress::from_slice(&[0xf2; 20]));
+        assert!(!is_frozen(&contract));
+        set_frozen(contract, true);
+        assert!(is_frozen(&contract));
+        assert!(!is_frozen(&other));
+        set_frozen(contract, false);
+        assert!(!is_frozen(&contract));
+    }
+}
@@ -0,0 +1,387 @@
+/// Import of Echidna / Medusa corpora as seeds (`--import-corpus`)
+///
+/// Echidna reproducers / corpus entries (`corpus/reproducers/*.txt`, `corpus/coverage/*.txt`,
+/// JSON arrays of transactions with typed ABI arguments) and Medusa call sequences
+/// (`corpus/call_sequences/**/*.json`, JSON arrays of calls with raw calldata) are converted into
+/// transaction sequences and executed at the beginning of the campaign, so that the sequences
+/// increasing coverage become part of the corpus.
+///
+/// Both fuzzers deploy the targets and send transactions from their own addresses. Senders are
+/// mapped onto the callers of ItyFuzz in order of appearance, and calls to unknown addresses are
+/// sent to the target with a function of the same selector.
+use std::collections::HashMap;
+use tracing::warn;
+use std::str::FromStr;
+
+use glob::glob;
+use itertools::Itertools;
+use libafl::prelude::HasMetadata;
+use serde_json::Value;
+
+use crate::evm::abi::ABIAddressToInstanceMap;
+use crate::evm::contract_utils::set_hash;
+use crate::evm::input::{BlockAdvance, ConciseEVMInput};
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMU256};
+
+/// A transaction of an imported sequence
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedTx {
+    pub from: EVMAddress,
+    pub to: EVMAddress,
+    pub value: EVMU256,
+    pub data: Vec<u8>,
+    pub advance: BlockAdvance,
+}
+
+/// Number given as a JSON number, a decimal string or a hex string
+fn number(v: &Value) -> Option<EVMU256> {
+    match v {
+        Value::Number(n) => EVMU256::from_str_radix(&n.to_string(), 10).ok(),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) if hex.is_empty() => Some(EVMU256::ZERO),
+            Some(hex) => EVMU256::from_str_radix(hex, 16).ok(),
+            None => EVMU256::from_str_radix(s, 10).ok(),
+        },
+        _ => None,
+    }
+}
+
+fn address(v: &Value) -> Option<EVMAddress> {
+    let s = v.as_str()?.trim_start_matches("0x");
+    // Echidna / Medusa addresses may be abbreviated (e.g., 0x10000)
+    EVMAddress::from_str(&format!("{:0>40}", s)).ok()
+}
+
+/// Bytes given as a hex string, or as the raw string otherwise
+fn raw_bytes(v: &Value) -> Vec<u8> {
+    let s = v.as_str().unwrap_or_default();
+    match s.strip_prefix("0x").and_then(|hex| hex::decode(hex).ok()) {
+        Some(bytes) => bytes,
+        None => s.as_bytes().to_vec(),
+    }
+}
+
+fn word(v: EVMU256) -> Vec<u8> {
+    v.to_be_bytes::<32>().to_vec()
+}
+
+/// Pad to a multiple of 32 bytes
+fn pad(mut bytes: Vec<u8>) -> Vec<u8> {
+    bytes.resize((bytes.len() + 31) / 32 * 32, 0);
+    bytes
+}
+
+/// An Echidna ABI value: its canonical type and encoding
+struct EncodedValue {
+    ty: String,
+    dynamic: bool,
+    encoding: Vec<u8>,
+}
+
+/// Canonical name of an Echidna ABI type (`{"tag": "AbiUIntType", "contents": 256}`)
+fn echidna_type(ty: &Value) -> Option<String> {
+    let contents = ty.get("contents");
+    Some(match ty.get("tag")?.as_str()? {
+        "AbiUIntType" => format!("uint{}", contents?.as_u64()?),
+        "AbiIntType" => format!("int{}", contents?.as_u64()?),
+        "AbiAddressType" => "address".to_string(),
+        "AbiBoolType" => "bool".to_string(),
+        "AbiBytesType" => format!("bytes{}", contents?.as_u64()?),
+        "AbiBytesDynamicType" => "bytes".to_string(),
+        "AbiStringType" => "string".to_string(),
+        "AbiArrayDynamicType" => format!("{}[]", echidna_type(contents?)?),
+        "AbiArrayType" => {
+            let contents = contents?.as_array()?;
+            format!("{}[{}]", echidna_type(contents.get(1)?)?, contents.get(0)?.as_u64()?)
+        }
+        "AbiTupleType" => format!(
+            "({})",
+            contents?.as_array()?.iter().map(echidna_type).collect::<Option<Vec<_>>>()?.join(",")
+        ),
+        _ => return None,
+    })
+}
+
+/// ABI encoding of a list of values (head / tail)
+fn encode_values(values: &[EncodedValue]) -> Vec<u8> {
+    let head_size = values
+        .iter()
+        .map(|v| if v.dynamic { 32 } else { v.encoding.len() })
+        .sum::<usize>();
+    let mut head = vec![];
+    let mut tail = vec![];
+    for v in values {
+        if v.dynamic {
+            head.extend(word(EVMU256::from(head_size + tail.len())));
+            tail.extend(v.encoding.clone());
+        } else {
+            head.extend(v.encoding.clone());
+        }
+    }
+    [head, tail].concat()
+}
+
+/// Encode an Echidna ABI value (`{"tag": "AbiUInt", "contents": [256, 5]}`)
+fn echidna_value(v: &Value) -> Option<EncodedValue> {
+    let contents = v.get("contents");
+    let fixed = |ty: String, encoding: Vec<u8>| Some(EncodedValue { ty, dynamic: false, encoding });
+    match v.get("tag")?.as_str()? {
+        "AbiUInt" => {
+            let contents = contents?.as_array()?;
+            fixed(format!("uint{}", contents.get(0)?.as_u64()?), word(number(contents.get(1)?)?))
+        }
+        "AbiInt" => {
+            let contents = contents?.as_array()?;
+            let (negative, magnitude) = match contents.get(1)? {
+                Value::Number(n) if n.to_string().starts_with('-') => {
+                    (true, EVMU256::from_str_radix(&n.to_string()[1..], 10).ok()?)
+                }
+                Value::String(s) if s.starts_with('-') => (true, EVMU256::from_str_radix(&s[1..], 10).ok()?),
+                n => (false, number(n)?),
+            };
+            let value = if negative { EVMU256::ZERO.wrapping_sub(magnitude) } else { magnitude };
+            fixed(format!("int{}", contents.get(0)?.as_u64()?), word(value))
+        }
+        "AbiAddress" => {
+            let mut encoding = vec![0; 12];
+            encoding.extend_from_slice(&address(contents?)?.0);
+            fixed("address".to_string(), encoding)
+        }
+        "AbiBool" => fixed("bool".to_string(), word(EVMU256::from(contents?.as_bool()? as u8))),
+        "AbiBytes" => {
+            let contents = contents?.as_array()?;
+            fixed(format!("bytes{}", contents.get(0)?.as_u64()?), pad(raw_bytes(contents.get(1)?)))
+        }
+        "AbiBytesDynamic" | "AbiString" => {
+            let bytes = raw_bytes(contents?);
+            Some(EncodedValue {
+                ty: if v["tag"] == "AbiString" { "string" } else { "bytes" }.to_string(),
+                dynamic: true,
+                encoding: [word(EVMU256::from(bytes.len())), pad(bytes)].concat(),
+            })
+        }
+        "AbiArrayDynamic" => {
+            let contents = contents?.as_array()?;
+            let items = contents.get(1)?.as_array()?.iter().map(echidna_value).collect::<Option<Vec<_>>>()?;
+            Some(EncodedValue {
+                ty: format!("{}[]", echidna_type(contents.get(0)?)?),
+                dynamic: true,
+                encoding: [word(EVMU256::from(items.len())), encode_values(&items)].concat(),
+            })
+        }
+        "AbiArray" => {
+            let contents = contents?.as_array()?;
+            let items = contents.get(2)?.as_array()?.iter().map(echidna_value).collect::<Option<Vec<_>>>()?;
+            Some(EncodedValue {
+                ty: format!("{}[{}]", echidna_type(contents.get(1)?)?, contents.get(0)?.as_u64()?),
+                dynamic: items.iter().any(|item| item.dynamic),
+                encoding: encode_values(&items),
+            })
+        }
+        "AbiTuple" => {
+            let items = contents?.as_array()?.iter().map(echidna_value).collect::<Option<Vec<_>>>()?;
+            Some(EncodedValue {
+                ty: format!("({})", items.iter().map(|item| item.ty.clone()).join(",")),
+                dynamic: items.iter().any(|item| item.dynamic),
+                encoding: encode_values(&items),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parse an Echidna transaction, None if it is not a call (e.g., NoCall delays)
+fn echidna_tx(tx: &Value) -> Option<ImportedTx> {
+    let call = tx.get("call")?;
+    let data = match call.get("tag")?.as_str()? {
+        "SolCall" => {
+            let contents = call.get("contents")?.as_array()?;
+            let args = contents.get(1)?.as_array()?.iter().map(echidna_value).collect::<Option<Vec<_>>>()?;
+            let signature = format!(
+                "{}({})",
+                contents.get(0)?.as_str()?,
+                args.iter().map(|arg| arg.ty.clone()).join(",")
+            );
+            let mut selector = [0u8; 4];
+            set_hash(&signature, &mut selector);
+            [selector.to_vec(), encode_values(&args)].concat()
+        }
+        "SolCalldata" => raw_bytes(call.get("contents")?),
+        _ => return None,
+    };
+    // delay is (seconds, blocks)
+    let delay = tx.get("delay").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+    let delay_of = |i: usize| delay.get(i).and_then(number).map_or(0, |d| u64::try_from(d).unwrap_or(0));
+    Some(ImportedTx {
+        from: address(tx.get("src")?)?,
+        to: address(tx.get("dst")?)?,
+        value: tx.get("value").and_then(number).unwrap_or_default(),
+        data,
+        advance: BlockAdvance {
+            blocks: delay_of(1),
+            seconds: delay_of(0),
+        },
+    })
+}
+
+/// Parse a Medusa call sequence element
+fn medusa_tx(element: &Value) -> Option<ImportedTx> {
+    let call = element.get("call")?;
+    let delay = |name: &str| element.get(name).and_then(|d| d.as_u64()).unwrap_or(0);
+    Some(ImportedTx {
+        from: address(call.get("from")?)?,
+        to: address(call.get("to")?)?,
+        value: call.get("value").and_then(number).unwrap_or_default(),
+        data: raw_bytes(call.get("data")?),
+        advance: BlockAdvance {
+            blocks: delay("blockNumberDelay"),
+            seconds: delay("blockTimestampDelay"),
+        },
+    })
+}
+
+/// Parse an Echidna or Medusa sequence, None if the content is in neither format
+pub fn parse_sequence(content: &str) -> Option<Vec<ImportedTx>> {
+    let txs = serde_json::from_str::<Value>(content).ok()?.as_array()?.clone();
+    if txs.iter().all(|tx| tx.get("call").map_or(false, |call| call.get("tag").is_some())) {
+        // Echidna transactions without calls only advance time / blocks
+        let mut sequence: Vec<ImportedTx> = vec![];
+        let mut pending = BlockAdvance::default();
+        for tx in &txs {
+            match echidna_tx(tx) {
+                Some(mut tx) => {
+                    tx.advance.blocks += pending.blocks;
+                    tx.advance.seconds += pending.seconds;
+                    pending = BlockAdvance::default();
+                    sequence.push(tx);
+                }
+                None => {
+                    let delay = tx.get("delay").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+                    let delay_of = |i: usize| delay.get(i).and_then(number).map_or(0, |d| u64::try_from(d).unwrap_or(0));
+                    pending.seconds += delay_of(0);
+                    pending.blocks += delay_of(1);
+                }
+            }
+        }
+        Some(sequence)
+    } else {
+        txs.iter().map(medusa_tx).collect()
+    }
+}
+
+/// Load the sequences in the files matched by `pattern`
+pub fn load_sequences(pattern: &str) -> Vec<Vec<ImportedTx>> {
+    glob(pattern)
+        .expect("Failed to read glob pattern")
+        .flatten()
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            let sequence = parse_sequence(&content);
+            if sequence.is_none() {
+                warn!("[import] {} is neither an Echidna nor a Medusa sequence", path.display());
+            }
+            sequence.filter(|sequence| !sequence.is_empty())
+        })
+        .collect()
+}
+
+/// Convert an imported sequence into inputs of the campaign, dropping calls to unknown functions
+pub fn to_concise(sequence: &[ImportedTx], state: &EVMFuzzState) -> Vec<ConciseEVMInput> {
+    let abis = match state.metadata().get::<ABIAddressToInstanceMap>() {
+        Some(abis) => abis,
+        None => return vec![],
+    };
+    let mut senders: HashMap<EVMAddress, EVMAddress> = HashMap::new();
+    sequence
+        .iter()
+        .filter(|tx| tx.data.len() >= 4)
+        .filter_map(|tx| {
+            let selector: [u8; 4] = tx.data[..4].try_into().unwrap();
+            let find = |address: &EVMAddress| abis.map.get(address)?.iter().find(|abi| abi.function == selector);
+            let (contract, abi) = match find(&tx.to) {
+                Some(abi) => (tx.to, abi),
+                None => abis
+                    .map
+                    .keys()
+                    .sorted()
+                    .find_map(|address| Some((*address, find(address)?)))?,
+            };
+            let mut data = abi.clone();
+            data.set_bytes(tx.data.clone());
+            let next = senders.len();
+            let caller = *senders
+                .entry(tx.from)
+                .or_insert_with(|| state.callers_pool[next % state.callers_pool.len()]);
+            Some(ConciseEVMInput {
+                caller,
+                contract,
+                data: Some(data),
+                txn_value: if tx.value == EVMU256::ZERO { None } else { Some(tx.value) },
+                advance: tx.advance,
+                call_leak: u32::MAX,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_echidna() {
+        let sequence = parse_sequence(
+            r#"[
+                {"call": {"tag": "NoCall"}, "src": "0x10000", "dst": "0x0", "gas": 0, "gasprice": "0x0", "value": "0x0", "delay": ["0x3c", "0x1"]},
+                {"call": {"tag": "SolCall", "contents": ["setX", [{"tag": "AbiUInt", "contents": [256, 5]}]]},
+                 "src": "0x10000", "dst": "0x00a329c0648769a73afac7f9381e08fb43dbea72", "gas": 12500000, "gasprice": "0x0",
+                 "value": "0x0", "delay": ["0x0", "0x0"]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(sequence.len(), 1);
+        let mut selector = [0u8; 4];
+        set_hash("setX(uint256)", &mut selector);
+        assert_eq!(sequence[0].data, [selector.to_vec(), word(EVMU256::from(5))].concat());
+        assert_eq!(sequence[0].from, EVMAddress::from_str("0000000000000000000000000000000000010000").unwrap());
+        assert_eq!(sequence[0].advance, BlockAdvance { blocks: 1, seconds: 60 });
+    }
+
+    #[test]
+    fn test_encode_dynamic() {
+        let value: Value = serde_json::from_str(
+            r#"{"tag": "AbiTuple", "contents": [
+                {"tag": "AbiUInt", "contents": [8, 1]},
+                {"tag": "AbiString", "contents": "ab"}
+            ]}"#,
+        )
+        .unwrap();
+        let encoded = echidna_value(&value).unwrap();
+        assert_eq!(encoded.ty, "(uint8,string)");
+        assert!(encoded.dynamic);
+        assert_eq!(
+            hex::encode(encoded.encoding),
+            [
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "6162000000000000000000000000000000000000000000000000000000000000",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_parse_medusa() {
+        let sequence = parse_sequence(
+            r#"[{"call": {"from": "0x0000000000000000000000000000000000010000",
+                          "to": "0xa647ff3c36cfab592509e13860ab8c4f28781a66", "nonce": 1, "value": "0x10",
+                          "gasLimit": 12500000, "data": "0xa9059cbb"},
+                 "blockNumberDelay": 2, "blockTimestampDelay": 30}]"#,
+        )
+        .unwrap();
+        assert_eq!(sequence[0].data, vec![0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(sequence[0].value, EVMU256::from(16));
+        assert_eq!(sequence[0].advance, BlockAdvance { blocks: 2, seconds: 30 });
+    }
+}
@@ -1,12 +1,17 @@
 /// Utilities to initialize the corpus
 /// Add all potential calls with default args to the corpus
 use crate::evm::abi::{BoxedABI, get_abi_type_boxed};
+use tracing::{info, warn};
+use crate::evm::bridge::{Bridge, MESSENGERS};
 use crate::evm::bytecode_analyzer;
+use crate::evm::function_weights::is_fuzzed;
 use crate::evm::contract_utils::{ABIConfig, ABIInfo, ContractInfo, ContractLoader, extract_sig_from_contract};
-use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputTy};
+use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputTy, INTERMEDIARY, INTERMEDIARY_TXS};
 use crate::evm::mutator::AccessPattern;
 
 use crate::evm::onchain::onchain::BLACKLIST_ADDR;
+use crate::evm::lending::LendingSetup;
+use crate::evm::pre_state::PreState;
 use crate::evm::types::{fixed_address, EVMAddress, EVMFuzzState, EVMInfantStateState, EVMStagedVMState, EVMU256, ProjectSourceMapTy};
 use crate::evm::vm::{EVMExecutor, EVMState};
 use crate::generic_vm::vm_executor::GenericVM;
@@ -38,10 +43,7 @@ use libafl::impl_serdeany;
 use libafl::prelude::HasMetadata;
 use serde::{Deserialize, Serialize};
 use crate::{dump_file, dump_txn};
-use std::fs::File;
-use std::path::Path;
 use crate::input::ConciseSerde;
-use std::io::Write;
 use crate::generic_vm::vm_executor::ExecutionResult;
 use crate::evm::types::EVMExecutionResult;
 use crate::evm::onchain::abi_decompiler::fetch_abi_heimdall;
@@ -54,6 +56,12 @@ pub struct EVMCorpusInitializer<'a> {
     #[cfg(feature = "use_presets")]
     presets: Vec<&'a dyn Preset<EVMInput, EVMFuzzState, EVMState>>,
     work_dir: String,
+    /// Approvals and positions of the senders, established after the deployment
+    pre_state: Option<PreState>,
+    /// Bridge whose messages are forged from its messengers
+    bridge: Option<Bridge>,
+    /// Funding and positions of the actors of a liquidation scenario
+    lending: Option<LendingSetup>,
 }
 
 pub struct EVMInitializationArtifacts {
@@ -146,6 +154,9 @@ impl<'a> EVMCorpusInitializer<'a> {
             #[cfg(feature = "use_presets")]
             presets: vec![],
             work_dir,
+            pre_state: None,
+            bridge: None,
+            lending: None,
         }
     }
 
@@ -154,17 +165,36 @@ impl<'a> EVMCorpusInitializer<'a> {
         self.presets.push(preset);
     }
 
+    pub fn set_pre_state(&mut self, pre_state: PreState) {
+        self.pre_state = Some(pre_state);
+    }
+
+    pub fn set_lending(&mut self, lending: LendingSetup) {
+        self.lending = Some(lending);
+    }
+
+    pub fn set_bridge(&mut self, bridge: Bridge) {
+        unsafe {
+            MESSENGERS = Some(bridge.messengers.iter().cloned().collect());
+        }
+        self.bridge = Some(bridge);
+    }
+
     pub fn initialize(&mut self, loader: &mut ContractLoader) -> EVMInitializationArtifacts{
         self.state.metadata_mut().insert(ABIMap::new());
         self.setup_default_callers();
         self.setup_contract_callers();
+        self.setup_messengers();
+        self.setup_intermediary();
         self.initialize_contract(loader);
+        self.setup_pre_state();
+        self.setup_lending();
         self.initialize_corpus(loader)
     }
 
     pub fn initialize_contract(&mut self, loader: &mut ContractLoader) {
         for contract in &mut loader.contracts {
-            println!("Deploying contract: {}", contract.name);
+            info!("Deploying contract: {}", contract.name);
             let deployed_address = if !contract.is_code_deployed {
                 match self.executor.deploy(
                     Bytecode::new_raw(Bytes::from(contract.code.clone())),
@@ -174,7 +204,7 @@ impl<'a> EVMCorpusInitializer<'a> {
                 ) {
                     Some(addr) => addr,
                     None => {
-                        println!("Failed to deploy contract: {}", contract.name);
+                        warn!("Failed to deploy contract: {}", contract.name);
                         // we could also panic here
                         continue;
                     }
@@ -208,7 +238,7 @@ impl<'a> EVMCorpusInitializer<'a> {
                 // 1. Extract abi from bytecode, and see do we have any function sig available in state
                 // 2. Use Heimdall to extract abi
                 // 3. Reconfirm on failures of heimdall
-                println!("Contract {} has no abi", contract.name);
+                info!("Contract {} has no abi", contract.name);
                 let contract_code = hex::encode(contract.code.clone());
                 let sigs = extract_sig_from_contract(&contract_code);
                 let mut unknown_sigs: usize = 0;
@@ -221,7 +251,7 @@ impl<'a> EVMCorpusInitializer<'a> {
                 }
 
                 if unknown_sigs >= sigs.len() / 30 {
-                    println!("Too many unknown function signature for {:?}, we are going to decompile this contract using Heimdall", contract.name);
+                    info!("Too many unknown function signature for {:?}, we are going to decompile this contract using Heimdall", contract.name);
                     let abis = fetch_abi_heimdall(contract_code)
                         .iter()
                         .map(|abi| {
@@ -280,10 +310,21 @@ impl<'a> EVMCorpusInitializer<'a> {
                     input_type: EVMInputTy::ABI,
                     randomness: vec![0],
                     repeat: 1,
+                    blob_env: Default::default(),
+                    victim: false,
+                    via_intermediary: false,
+                    advance: Default::default(),
+                    force_feed: EVMU256::ZERO,
                 };
                 add_input_to_corpus!(self.state, self.scheduler, input);
             }
         }
+        for (address, source_map) in &loader.implementation_source_maps {
+            artifacts
+                .address_to_sourcemap
+                .entry(*address)
+                .or_insert_with(|| Some(source_map.clone()));
+        }
         artifacts.initial_state = StagedVMState::new_with_state(
             self.executor.host.evmstate.clone(),
         );
@@ -302,6 +343,85 @@ impl<'a> EVMCorpusInitializer<'a> {
         artifacts
     }
 
+    /// Give the approvals and open the positions of the pre-state for each sender
+    pub fn setup_pre_state(&mut self) {
+        let pre_state = match self.pre_state.take() {
+            Some(pre_state) => pre_state,
+            None => return,
+        };
+        for sender in &pre_state.senders {
+            self.state.add_caller(sender);
+        }
+        let senders = if pre_state.senders.is_empty() {
+            self.state.callers_pool.clone()
+        } else {
+            pre_state.senders.clone()
+        };
+        for sender in senders {
+            for call in &pre_state.calls {
+                if !self
+                    .executor
+                    .setup_call(call.contract, call.calldata(sender), call.value, sender, self.state)
+                {
+                    warn!("Pre-state: {} on {:?} failed for sender {:?}", call.function, call.contract, sender);
+                }
+            }
+        }
+    }
+
+    /// Fund the actors of the liquidation scenario and open their positions
+    pub fn setup_lending(&mut self) {
+        let lending = match self.lending.take() {
+            Some(lending) => lending,
+            None => return,
+        };
+        for actor in &lending.actors {
+            self.state.add_caller(actor);
+        }
+        for (token, slot, amount) in &lending.balances {
+            self.executor.host.evmstate.get_or_default(*token).insert(*slot, *amount);
+        }
+        for (actor, calls) in &lending.positions {
+            for call in calls {
+                if !self
+                    .executor
+                    .setup_call(call.contract, call.calldata(*actor), call.value, *actor, self.state)
+                {
+                    warn!("Lending: {} on {:?} failed for actor {:?}", call.function, call.contract, actor);
+                }
+            }
+        }
+    }
+
+    /// Mock the messengers of the bridge without code (e.g., offline), so that calls sending
+    /// messages through them succeed
+    pub fn setup_messengers(&mut self) {
+        let messengers = match &self.bridge {
+            Some(bridge) => bridge.messengers.clone(),
+            None => return,
+        };
+        for messenger in messengers {
+            if !self.executor.host.code.contains_key(&messenger) {
+                self.executor
+                    .host
+                    .set_code(messenger, Bytecode::new_raw(Bytes::from(vec![0x00])), self.state);
+            }
+        }
+    }
+
+    /// Deploy the intermediary (when transactions are sent through it), so that contracts see
+    /// code at msg.sender and calls back into it (e.g., sending ETH) succeed
+    pub fn setup_intermediary(&mut self) {
+        if unsafe { !INTERMEDIARY_TXS } {
+            return;
+        }
+        self.executor.host.set_code(
+            fixed_address(INTERMEDIARY),
+            Bytecode::new_raw(Bytes::from(vec![0x00])),
+            self.state,
+        );
+    }
+
     pub fn setup_default_callers(&mut self) {
         let default_callers = HashSet::from([
             fixed_address("8EF508Aca04B32Ff3ba5003177cb18BfA6Cd79dd"),
@@ -359,6 +479,9 @@ impl<'a> EVMCorpusInitializer<'a> {
         if abi.is_static {
             return;
         }
+        if !is_fuzzed(&abi.function_name) {
+            return;
+        }
         let mut abi_instance = get_abi_type_boxed(&abi.abi);
         abi_instance.set_func_with_name(abi.function, abi.function_name.clone());
 
@@ -387,8 +510,23 @@ impl<'a> EVMCorpusInitializer<'a> {
             direct_data: Default::default(),
             randomness: vec![0],
             repeat: 1,
+            blob_env: Default::default(),
+            victim: false,
+            via_intermediary: false,
+            advance: Default::default(),
+            force_feed: EVMU256::ZERO,
         };
         add_input_to_corpus!(self.state, scheduler, input.clone());
+        // messages forged from each messenger of the bridge
+        if let Some(bridge) = &self.bridge {
+            if bridge.is_handler(deployed_address, &abi.function_name) {
+                for messenger in bridge.messengers.clone() {
+                    let mut message = input.clone();
+                    message.caller = messenger;
+                    add_input_to_corpus!(self.state, scheduler, message);
+                }
+            }
+        }
         #[cfg(feature = "print_txn_corpus")]
         {
             let corpus_dir = format!("{}/corpus", self.work_dir.as_str()).to_string();
@@ -0,0 +1,429 @@
+/// Cross-implementation check of sequences against an external EVM (geth's `evm` tool)
+///
+/// Each transaction of a violating sequence (or of a corpus entry) is executed by `evm run` on
+/// the state ItyFuzz executes it on, and divergences in success, return data or storage written
+/// are reported. They are usually caused by behaviors of the fuzzing host (e.g., unlimited
+/// balances, middlewares), i.e., the finding may be a false positive.
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::process::Command;
+use std::str::FromStr;
+
+use glob::glob;
+use revm_primitives::Env;
+use serde_json::{json, Map, Value};
+
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::replay::SolutionReplay;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMStagedVMState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+
+/// Balance of every account in the external EVM, as balances are not tracked by ItyFuzz
+const BALANCE: &str = "0x100000000000000000000000000000000000000000000000000";
+const GAS: &str = "30000000";
+
+fn hex_address(address: &EVMAddress) -> String {
+    format!("0x{}", hex::encode(address.0))
+}
+
+fn hex_word(v: &EVMU256) -> String {
+    format!("0x{}", hex::encode(v.to_be_bytes::<32>()))
+}
+
+fn saturating_u64(v: &EVMU256) -> u64 {
+    if *v > EVMU256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        v.as_limbs()[0]
+    }
+}
+
+/// Genesis file with the chain config and block of `env`
+fn genesis(env: &Env, alloc: Map<String, Value>) -> Value {
+    json!({
+        "config": {
+            "chainId": saturating_u64(&env.cfg.chain_id),
+            "homesteadBlock": 0, "eip150Block": 0, "eip155Block": 0, "eip158Block": 0,
+            "byzantiumBlock": 0, "constantinopleBlock": 0, "petersburgBlock": 0, "istanbulBlock": 0,
+            "berlinBlock": 0, "londonBlock": 0, "mergeNetsplitBlock": 0,
+            "terminalTotalDifficulty": 0, "terminalTotalDifficultyPassed": true,
+            "shanghaiTime": 0, "cancunTime": 0
+        },
+        "alloc": alloc,
+        "number": format!("{:#x}", saturating_u64(&env.block.number)),
+        "timestamp": format!("{:#x}", saturating_u64(&env.block.timestamp)),
+        "gasLimit": format!("{:#x}", 30_000_000u64),
+        "difficulty": "0x0",
+    })
+}
+
+/// Prestate (a genesis file) of the external EVM from the state of ItyFuzz
+fn prestate(
+    executor: &EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    vm_state: &EVMState,
+    callers: &Vec<EVMAddress>,
+) -> Value {
+    let mut alloc: HashMap<EVMAddress, Map<String, Value>> = HashMap::new();
+    let addresses = callers
+        .iter()
+        .chain(executor.host.code.keys())
+        .chain(vm_state.state.keys());
+    for address in addresses {
+        let mut account = Map::new();
+        account.insert("balance".to_string(), json!(BALANCE));
+        if let Some(code) = executor.host.code.get(address) {
+            account.insert(
+                "code".to_string(),
+                json!(format!("0x{}", hex::encode(code.original_bytecode_slice()))),
+            );
+        }
+        if let Some(storage) = vm_state.state.get(address) {
+            account.insert(
+                "storage".to_string(),
+                Value::Object(
+                    storage
+                        .iter()
+                        .map(|(slot, value)| (hex_word(slot), json!(hex_word(value))))
+                        .collect(),
+                ),
+            );
+        }
+        alloc.insert(*address, account);
+    }
+    genesis(
+        &executor.host.env,
+        alloc
+            .into_iter()
+            .map(|(address, account)| (hex_address(&address), Value::Object(account)))
+            .collect(),
+    )
+}
+
+/// Outcome of a transaction on the external EVM
+#[derive(Debug, Default)]
+struct ExternalResult {
+    success: bool,
+    output: Vec<u8>,
+    /// Storage of each account after the transaction
+    storage: HashMap<EVMAddress, HashMap<EVMU256, EVMU256>>,
+}
+
+fn parse_hex_word(v: &str) -> Option<EVMU256> {
+    match v.trim_start_matches("0x") {
+        "" => Some(EVMU256::ZERO),
+        v => EVMU256::from_str_radix(v, 16).ok(),
+    }
+}
+
+/// Parse the output of `evm run --json --dump`: a JSON line per step, the result of the call
+/// (`{"output": .., "gasUsed": .., "error": ..}`) and the state dumped over several lines
+/// (`{"root": .., "accounts": ..}`). Other lines (e.g., warnings) are ignored.
+fn parse_external(out: &str) -> Result<ExternalResult, String> {
+    let mut values = vec![];
+    let mut block: Option<String> = None;
+    for line in out.lines() {
+        match &mut block {
+            Some(lines) => {
+                lines.push_str(line);
+                if line == "}" {
+                    values.extend(serde_json::from_str::<Value>(lines).ok());
+                    block = None;
+                }
+            }
+            None if line == "{" => block = Some(line.to_string()),
+            None if line.starts_with('{') => values.extend(serde_json::from_str::<Value>(line).ok()),
+            None => {}
+        }
+    }
+
+    let end = values
+        .iter()
+        .rev()
+        .find(|v| v.get("gasUsed").is_some() && v.get("pc").is_none())
+        .ok_or("no result in the output of the external EVM")?;
+    let output = end["output"].as_str().unwrap_or_default().trim_start_matches("0x");
+    let mut result = ExternalResult {
+        success: end.get("error").map_or(true, |e| e.as_str().unwrap_or_default().is_empty()),
+        output: hex::decode(output).map_err(|e| format!("invalid output of the external EVM: {}", e))?,
+        storage: HashMap::new(),
+    };
+
+    let accounts = values
+        .iter()
+        .rev()
+        .find_map(|v| v.get("accounts").and_then(|a| a.as_object()))
+        .ok_or("no state dump in the output of the external EVM")?;
+    for (address, account) in accounts {
+        let address = match EVMAddress::from_str(address.trim_start_matches("0x")) {
+            Ok(address) => address,
+            // accounts without address preimage
+            Err(_) => continue,
+        };
+        let storage = match account.get("storage").and_then(|s| s.as_object()) {
+            Some(storage) => storage,
+            None => continue,
+        };
+        let slots = result.storage.entry(address).or_default();
+        for (slot, value) in storage {
+            match (parse_hex_word(slot), value.as_str().and_then(parse_hex_word)) {
+                (Some(slot), Some(value)) => {
+                    slots.insert(slot, value);
+                }
+                _ => return Err(format!("invalid storage of {:?} in the output of the external EVM", address)),
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn run_external(evm: &str, prestate_path: &str, tx: &ConciseEVMInput, code: &[u8]) -> Result<ExternalResult, String> {
+    let data = tx.data.as_ref().map(|d| d.get_bytes()).unwrap_or_default();
+    let out = Command::new(evm)
+        .args([
+            "run",
+            "--json",
+            "--dump",
+            "--prestate",
+            prestate_path,
+            "--sender",
+            &hex_address(&tx.caller),
+            "--receiver",
+            &hex_address(&tx.contract),
+            "--code",
+            &hex::encode(code),
+            "--input",
+            &hex::encode(data),
+            "--value",
+            &tx.txn_value.unwrap_or_default().to_string(),
+            "--gas",
+            GAS,
+        ])
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", evm, e))?;
+    if !out.status.success() {
+        return Err(format!(
+            "{} exited with {}: {}",
+            evm,
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    // the trace goes to stderr in some versions of geth
+    parse_external(&format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    ))
+}
+
+/// Slots written by ItyFuzz or by the external EVM whose values after the transaction differ,
+/// as (address, slot, value of ItyFuzz, value of the external EVM). Missing slots are zero.
+fn storage_divergences(
+    pre: &EVMState,
+    post: &EVMState,
+    external: &HashMap<EVMAddress, HashMap<EVMU256, EVMU256>>,
+) -> Vec<(EVMAddress, EVMU256, EVMU256, EVMU256)> {
+    let value = |storage: Option<&HashMap<EVMU256, EVMU256>>, slot: &EVMU256| {
+        storage.and_then(|s| s.get(slot)).cloned().unwrap_or(EVMU256::ZERO)
+    };
+    let slots: BTreeSet<(EVMAddress, EVMU256)> = post
+        .state
+        .iter()
+        .map(|(address, storage)| (address, storage.as_ref()))
+        .chain(external.iter())
+        .flat_map(|(address, storage)| storage.keys().map(move |slot| (*address, *slot)))
+        .collect();
+    slots
+        .into_iter()
+        .filter_map(|(address, slot)| {
+            let before = value(pre.get(&address), &slot);
+            let ours = value(post.get(&address), &slot);
+            let theirs = value(external.get(&address), &slot);
+            (ours != theirs && (ours != before || theirs != before)).then_some((address, slot, ours, theirs))
+        })
+        .collect()
+}
+
+/// Re-execute the transactions of a sequence replayed on ItyFuzz on the external EVM at `evm`,
+/// returns a report line per transaction
+pub fn cross_check(
+    executor: &EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &EVMFuzzState,
+    replay: &SolutionReplay,
+    evm: &str,
+    work_dir: &str,
+) -> Vec<String> {
+    let dir = format!("{}/cross_evm", work_dir);
+    fs::create_dir_all(&dir).expect("failed to create cross evm directory");
+    let prestate_path = format!("{}/prestate.json", dir);
+
+    let mut report = vec![];
+    for (idx, tx) in replay.txs.iter().enumerate() {
+        let (concise, res) = (&tx.concise, &tx.result);
+
+        // reentrant transactions only exist in ItyFuzz
+        let leaked = replay.txs.get(idx + 1).map_or(false, |next| next.concise.step);
+        if concise.step || concise.layer > 0 || leaked {
+            report.push(format!("[{}] skipped (control leak / reentrancy)", idx));
+            continue;
+        }
+        if concise.via_intermediary {
+            report.push(format!("[{}] skipped (sent through the intermediary contract)", idx));
+            continue;
+        }
+        let code = match executor.host.code.get(&concise.contract) {
+            Some(code) => code.original_bytecode_slice().to_vec(),
+            None => {
+                report.push(format!("[{}] skipped (no code)", idx));
+                continue;
+            }
+        };
+        let prestate = prestate(executor, &tx.pre_state.state, &state.callers_pool);
+        fs::write(&prestate_path, serde_json::to_string(&prestate).unwrap()).expect("failed to write prestate");
+        let external = match run_external(evm, &prestate_path, concise, &code) {
+            Ok(external) => external,
+            Err(e) => {
+                report.push(format!("[{}] error: {}", idx, e));
+                continue;
+            }
+        };
+        if external.success != !res.reverted {
+            report.push(format!(
+                "[{}] divergence: ItyFuzz {} while external EVM {}",
+                idx,
+                if res.reverted { "reverts" } else { "succeeds" },
+                if external.success { "succeeds" } else { "reverts" }
+            ));
+            continue;
+        }
+        if external.success && external.output != res.output {
+            report.push(format!(
+                "[{}] divergence: ItyFuzz returns 0x{} while external EVM returns 0x{}",
+                idx,
+                hex::encode(&res.output),
+                hex::encode(&external.output)
+            ));
+            continue;
+        }
+        let divergences = storage_divergences(&tx.pre_state.state, &tx.post_state().state, &external.storage);
+        if divergences.is_empty() {
+            report.push(format!("[{}] ok", idx));
+        }
+        for (address, slot, ours, theirs) in divergences {
+            report.push(format!(
+                "[{}] divergence: slot {} of {} is {} on ItyFuzz while {} on external EVM",
+                idx,
+                hex_word(&slot),
+                hex_address(&address),
+                hex_word(&ours),
+                hex_word(&theirs)
+            ));
+        }
+    }
+    report
+}
+
+/// Cross-check each sequence of a corpus (in the replayable format) in `corpus_dir`, the report
+/// of each is written next to it. Returns the number of sequences diverging.
+pub fn cross_check_corpus(
+    executor: &mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &mut EVMFuzzState,
+    initial_state: &EVMStagedVMState,
+    corpus_dir: &str,
+    evm: &str,
+    work_dir: &str,
+) -> usize {
+    let (mut total, mut diverging) = (0, 0);
+    for file in glob(&format!("{}/*_replayable", corpus_dir)).expect("Failed to read glob pattern") {
+        let path = file.expect("glob issue");
+        let txs = fs::read_to_string(&path).expect("Failed to read corpus file");
+        let replay = SolutionReplay::new(executor, state, initial_state, &txs);
+        let report = cross_check(executor, state, &replay, evm, work_dir);
+        if report.iter().any(|line| line.contains("divergence:")) {
+            diverging += 1;
+        }
+        total += 1;
+        fs::write(format!("{}.cross_evm", path.display()), report.join("\n")).expect("Failed to write cross evm report");
+    }
+    crate::status!("cross-evm: {} of {} corpus sequences diverge", diverging, total);
+    diverging
+}
+
+mod tests {
+    use super::*;
+
+    fn address(byte: u8) -> EVMAddress {
+        EVMAddress::from_slice(&[byte; 20])
+    }
+
+    #[test]
+    fn test_genesis_numbers() {
+        let mut env = Env::default();
+        env.cfg.chain_id = EVMU256::from(56);
+        env.block.number = EVMU256::from(100);
+        env.block.timestamp = EVMU256::MAX;
+        let genesis = genesis(&env, Map::new());
+        assert_eq!(genesis["config"]["chainId"], json!(56));
+        assert_eq!(genesis["number"], json!("0x64"));
+        assert_eq!(genesis["timestamp"], json!("0xffffffffffffffff"));
+    }
+
+    #[test]
+    fn test_parse_external() {
+        let out = r#"WARN [01-01|00:00:00.000] Sanitizing invalid parameter
+{"pc":0,"op":96,"gas":"0x1c9c380","gasCost":"0x3","memSize":0,"stack":[],"depth":1,"refund":0,"opName":"PUSH1"}
+{"output":"000000000000000000000000000000000000000000000000000000000000002a","gasUsed":"0x5208"}
+{
+    "root": "0x0000000000000000000000000000000000000000000000000000000000000000",
+    "accounts": {
+        "0x0101010101010101010101010101010101010101": {
+            "balance": "0",
+            "nonce": 0,
+            "storage": {
+                "0x0000000000000000000000000000000000000000000000000000000000000001": "2a"
+            }
+        },
+        "pre(0x1234)": {
+            "balance": "0"
+        }
+    }
+}"#;
+        let result = parse_external(out).unwrap();
+        assert!(result.success);
+        assert_eq!(result.output.len(), 32);
+        assert_eq!(result.output[31], 0x2a);
+        assert_eq!(result.storage[&address(1)][&EVMU256::from(1)], EVMU256::from(0x2a));
+
+        let reverted = "{\"output\":\"\",\"gasUsed\":\"0x0\",\"error\":\"execution reverted\"}\n{\n\"accounts\": {}\n}";
+        let result = parse_external(reverted).unwrap();
+        assert!(!result.success);
+        assert!(result.output.is_empty());
+
+        assert!(parse_external("0x\n error: execution reverted").is_err());
+    }
+
+    #[test]
+    fn test_storage_divergences() {
+        let (contract, slot) = (address(1), EVMU256::from(1));
+        let mut pre = EVMState::new();
+        pre.insert(contract, HashMap::from([(slot, EVMU256::from(1))]));
+        let mut post = pre.clone();
+        post.get_mut(&contract).unwrap().insert(slot, EVMU256::from(2));
+
+        // same write on both
+        let external = HashMap::from([(contract, HashMap::from([(slot, EVMU256::from(2))]))]);
+        assert!(storage_divergences(&pre, &post, &external).is_empty());
+
+        // zero slots are dropped by the external EVM
+        let mut cleared = pre.clone();
+        cleared.get_mut(&contract).unwrap().insert(slot, EVMU256::ZERO);
+        assert!(storage_divergences(&pre, &cleared, &HashMap::new()).is_empty());
+
+        // the write is missing on the external EVM
+        let external = HashMap::from([(contract, HashMap::from([(slot, EVMU256::from(1))]))]);
+        assert_eq!(
+            storage_divergences(&pre, &post, &external),
+            vec![(contract, slot, EVMU256::from(2), EVMU256::from(1))]
+        );
+    }
+}
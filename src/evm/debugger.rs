@@ -0,0 +1,370 @@
+/// Interactive debugger for violating sequences
+///
+/// Loads a solution (in the replayable format) and executes it transaction by
+/// transaction, allowing to inspect the storage / token balances between
+/// transactions, tweak the transactions and re-run them.
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use bytes::Bytes;
+
+use crate::evm::abi::{AArray, ABILossyType};
+use crate::evm::host::CALL_UNTIL;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::call_tracer::CallTracer;
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMStagedVMState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+use crate::generic_vm::vm_executor::GenericVM;
+use crate::input::ConciseSerde;
+
+const HELP: &str = "commands:
+  n, next                       execute the next transaction
+  c, continue                   execute all remaining transactions
+  l, list                       list the transactions of the sequence
+  s, storage <addr> [slot]      show storage of an address
+  b, balance <token> <holder>   show ERC20 balance of holder (ETH balances are not tracked)
+  call <addr> <calldata>        static call an address with the calldata (hex)
+  arg <tx> <idx> <hex>          set the idx-th argument of a transaction
+  calldata <tx> <hex>           set the calldata of a transaction (including the function hash)
+  caller <tx> <addr>            set the caller of a transaction
+  value <tx> <wei>              set the value of a transaction
+  r, rerun                      re-execute the sequence up to the current transaction
+  restart                       go back to the initial state
+  q, quit                       exit";
+
+/// Tweaked calldata of a transaction, used instead of the ABI when set
+type DirectData = Option<Bytes>;
+
+pub struct Debugger<'a> {
+    executor: &'a mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    tracer: Rc<RefCell<CallTracer>>,
+    txs: Vec<(ConciseEVMInput, DirectData)>,
+    /// `states[i]` is the VM state before executing the i-th transaction
+    states: Vec<EVMStagedVMState>,
+}
+
+fn parse_address(s: &str) -> Option<EVMAddress> {
+    EVMAddress::from_str(s.trim_start_matches("0x")).ok()
+}
+
+fn parse_u256(s: &str) -> Option<EVMU256> {
+    match s.strip_prefix("0x") {
+        Some(h) => EVMU256::from_str_radix(h, 16).ok(),
+        None => EVMU256::from_str_radix(s, 10).ok(),
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    hex::decode(s.trim_start_matches("0x")).ok()
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(
+        executor: &'a mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+        initial_state: EVMStagedVMState,
+        txs: &str,
+    ) -> Self {
+        let tracer = Rc::new(RefCell::new(CallTracer::new()));
+        executor.host.add_middlewares(tracer.clone());
+        Self {
+            executor,
+            tracer,
+            txs: parse_sequence(txs).into_iter().map(|tx| (tx, None)).collect(),
+            states: vec![initial_state],
+        }
+    }
+
+    fn cursor(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    fn current_state(&self) -> &EVMState {
+        &self.states.last().unwrap().state
+    }
+
+    fn describe(&self, idx: usize) -> String {
+        let (tx, direct_data) = &self.txs[idx];
+        match direct_data {
+            Some(data) => format!(
+                "{:?} => {:?} 0x{} (value: {})",
+                tx.caller,
+                tx.contract,
+                hex::encode(data),
+                tx.txn_value.unwrap_or_default()
+            ),
+            None => tx.serialize_string(),
+        }
+    }
+
+    /// Execute the transaction at the cursor, returns false if there is nothing to execute
+    fn step(&mut self, state: &mut EVMFuzzState, verbose: bool) -> bool {
+        let idx = self.cursor();
+        if idx >= self.txs.len() {
+            println!("end of sequence");
+            return false;
+        }
+        let (tx, direct_data) = &self.txs[idx];
+        let (mut input, call_until) = tx.to_input(self.states[idx].clone());
+        if let Some(data) = direct_data {
+            input.data = None;
+            input.direct_data = data.clone();
+        }
+        unsafe {
+            CALL_UNTIL = call_until;
+        }
+        let res = self.executor.execute(&input, state);
+        unsafe {
+            CALL_UNTIL = u32::MAX;
+        }
+        let steps = self.tracer.borrow_mut().take();
+
+        if verbose {
+            println!("[{}] {}", idx, self.describe(idx));
+            println!("reverted: {}", res.reverted);
+            println!("output: 0x{}", hex::encode(&res.output));
+            if res.new_state.state.bug_hit || !res.new_state.state.typed_bug.is_empty() {
+                println!("bug hit: {:?}", res.new_state.state.typed_bug);
+            }
+            println!("steps:");
+            for step in steps {
+                println!("  {}", step);
+            }
+        }
+        // a reverted transaction leaves the state untouched
+        let new_state = if res.reverted {
+            self.states[idx].clone()
+        } else {
+            res.new_state
+        };
+        self.states.push(new_state);
+        true
+    }
+
+    fn static_call(&mut self, state: &mut EVMFuzzState, address: EVMAddress, data: Vec<u8>) -> Vec<u8> {
+        if !self.executor.host.code.contains_key(&address) {
+            println!("no code at {:?}", address);
+            return vec![];
+        }
+        let vm_state = self.current_state().clone();
+        self.executor
+            .fast_static_call(&vec![(address, Bytes::from(data))], &vm_state, state)
+            .pop()
+            .unwrap_or_default()
+    }
+
+    fn show_storage(&self, address: EVMAddress, slot: Option<EVMU256>) {
+        match self.current_state().state.get(&address) {
+            None => println!("no storage for {:?}", address),
+            Some(storage) => match slot {
+                Some(slot) => println!("{:#x}", storage.get(&slot).cloned().unwrap_or_default()),
+                None => {
+                    let mut slots = storage.iter().collect::<Vec<_>>();
+                    slots.sort_by_key(|(k, _)| **k);
+                    for (k, v) in slots {
+                        println!("{:#x} => {:#x}", k, v);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Set the `arg_idx`-th argument of the `idx`-th transaction
+    fn set_arg(&mut self, idx: usize, arg_idx: usize, bytes: Vec<u8>) -> Result<(), String> {
+        let (tx, direct_data) = self.txs.get_mut(idx).ok_or("no such transaction")?;
+        let abi = tx.data.as_mut().ok_or("transaction has no ABI, use calldata instead")?;
+        let args = abi
+            .b
+            .as_any()
+            .downcast_mut::<AArray>()
+            .ok_or("unsupported arguments, use calldata instead")?;
+        let arg = args.data.get_mut(arg_idx).ok_or("no such argument")?;
+        match arg.get_type() {
+            ABILossyType::T256 if bytes.len() <= 32 => arg.b.set_bytes(bytes),
+            ABILossyType::TDynamic => arg.b.set_bytes(bytes),
+            _ => return Err("unsupported argument type, use calldata instead".to_string()),
+        }
+        *direct_data = None;
+        Ok(())
+    }
+
+    /// Discard the states after the `idx`-th transaction as it has been changed, the cursor goes
+    /// back to it
+    fn invalidate(&mut self, idx: usize) {
+        if idx < self.cursor() {
+            self.states.truncate(idx + 1);
+            println!("transaction {} changed, back to before it", idx);
+        }
+    }
+
+    fn rerun(&mut self, state: &mut EVMFuzzState) {
+        let target = self.cursor();
+        self.states.truncate(1);
+        while self.cursor() < target {
+            self.step(state, self.cursor() + 1 == target);
+        }
+    }
+
+    fn handle(&mut self, state: &mut EVMFuzzState, cmd: &[&str]) -> Result<(), String> {
+        let tx_idx = |s: &str| usize::from_str(s).map_err(|_| "invalid transaction index".to_string());
+        match cmd {
+            ["n"] | ["next"] => {
+                self.step(state, true);
+            }
+            ["c"] | ["continue"] => while self.step(state, true) {},
+            ["l"] | ["list"] => {
+                for idx in 0..self.txs.len() {
+                    let marker = if idx == self.cursor() { "=>" } else { "  " };
+                    println!("{} [{}] {}", marker, idx, self.describe(idx));
+                }
+            }
+            ["s", addr] | ["storage", addr] => {
+                self.show_storage(parse_address(addr).ok_or("invalid address")?, None)
+            }
+            ["s", addr, slot] | ["storage", addr, slot] => self.show_storage(
+                parse_address(addr).ok_or("invalid address")?,
+                Some(parse_u256(slot).ok_or("invalid slot")?),
+            ),
+            ["b", token, holder] | ["balance", token, holder] => {
+                let token = parse_address(token).ok_or("invalid address")?;
+                let holder = parse_address(holder).ok_or("invalid address")?;
+                // balanceOf(address)
+                let mut data = vec![0x70, 0xa0, 0x82, 0x31];
+                data.extend([0; 12]);
+                data.extend(holder.0);
+                let out = self.static_call(state, token, data);
+                if out.len() >= 32 {
+                    println!("{}", EVMU256::from_be_slice(&out[..32]));
+                } else {
+                    println!("balanceOf failed: 0x{}", hex::encode(out));
+                }
+            }
+            ["call", addr, data] => {
+                let address = parse_address(addr).ok_or("invalid address")?;
+                let data = parse_hex(data).ok_or("invalid calldata")?;
+                println!("0x{}", hex::encode(self.static_call(state, address, data)));
+            }
+            ["arg", idx, arg_idx, value] => {
+                let idx = tx_idx(idx)?;
+                let arg_idx = usize::from_str(arg_idx).map_err(|_| "invalid argument index")?;
+                self.set_arg(idx, arg_idx, parse_hex(value).ok_or("invalid value")?)?;
+                println!("[{}] {}", idx, self.describe(idx));
+                self.invalidate(idx);
+            }
+            ["calldata", idx, data] => {
+                let idx = tx_idx(idx)?;
+                let data = parse_hex(data).ok_or("invalid calldata")?;
+                self.txs.get_mut(idx).ok_or("no such transaction")?.1 = Some(Bytes::from(data));
+                self.invalidate(idx);
+            }
+            ["caller", idx, addr] => {
+                let idx = tx_idx(idx)?;
+                let caller = parse_address(addr).ok_or("invalid address")?;
+                self.txs.get_mut(idx).ok_or("no such transaction")?.0.caller = caller;
+                self.invalidate(idx);
+            }
+            ["value", idx, value] => {
+                let idx = tx_idx(idx)?;
+                let value = parse_u256(value).ok_or("invalid value")?;
+                self.txs.get_mut(idx).ok_or("no such transaction")?.0.txn_value = Some(value);
+                self.invalidate(idx);
+            }
+            ["r"] | ["rerun"] => self.rerun(state),
+            ["restart"] => self.states.truncate(1),
+            _ => println!("{}", HELP),
+        }
+        Ok(())
+    }
+
+    /// Run the REPL until the user quits or stdin is closed
+    pub fn run(&mut self, state: &mut EVMFuzzState) {
+        println!("loaded {} transactions, type `help` for commands", self.txs.len());
+        let stdin = std::io::stdin();
+        loop {
+            print!("(debug {}/{}) ", self.cursor(), self.txs.len());
+            std::io::stdout().flush().expect("failed to flush stdout");
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).expect("failed to read stdin") == 0 {
+                break;
+            }
+            let cmd = line.split_whitespace().collect::<Vec<_>>();
+            match cmd.as_slice() {
+                [] => continue,
+                ["q"] | ["quit"] => break,
+                cmd => {
+                    if let Err(e) = self.handle(state, cmd) {
+                        println!("error: {}", e);
+                    }
+                }
+            }
+        }
+        self.executor.host.remove_middlewares(self.tracer.clone());
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::host::FuzzHost;
+    use crate::evm::types::generate_random_address;
+    use crate::state::FuzzState;
+    use libafl::prelude::StdScheduler;
+    use std::sync::Arc;
+
+    fn executor(state: &mut EVMFuzzState) -> EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput> {
+        EVMExecutor::new(
+            FuzzHost::new(Arc::new(StdScheduler::new()), "work_dir".to_string()),
+            generate_random_address(state),
+        )
+    }
+
+    /// Debugger with `count` transactions, all executed
+    fn executed<'a>(
+        executor: &'a mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+        count: usize,
+    ) -> Debugger<'a> {
+        let mut debugger = Debugger::new(executor, EVMStagedVMState::new_with_state(EVMState::new()), "");
+        debugger.txs = vec![(ConciseEVMInput::default(), None); count];
+        for idx in 0..count {
+            let mut vm_state = debugger.states[idx].clone();
+            vm_state
+                .state
+                .insert(EVMAddress::zero(), [(EVMU256::ZERO, EVMU256::from(idx + 1))].into_iter().collect());
+            debugger.states.push(vm_state);
+        }
+        debugger
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut state: EVMFuzzState = FuzzState::new(0);
+        let mut executor = executor(&mut state);
+        let mut debugger = executed(&mut executor, 3);
+        assert_eq!(debugger.cursor(), 3);
+
+        // changing the second transaction discards the states after it
+        debugger.handle(&mut state, &["value", "1", "5"]).unwrap();
+        assert_eq!(debugger.cursor(), 1);
+        assert_eq!(debugger.txs[1].0.txn_value, Some(EVMU256::from(5)));
+        assert_eq!(
+            debugger.current_state().get(&EVMAddress::zero()).unwrap()[&EVMU256::ZERO],
+            EVMU256::from(1)
+        );
+
+        // transactions not executed yet do not move the cursor
+        debugger.handle(&mut state, &["caller", "2", "0x0000000000000000000000000000000000000001"]).unwrap();
+        assert_eq!(debugger.cursor(), 1);
+    }
+
+    #[test]
+    fn test_invalidate_last() {
+        let mut state: EVMFuzzState = FuzzState::new(0);
+        let mut executor = executor(&mut state);
+        let mut debugger = executed(&mut executor, 2);
+        debugger.handle(&mut state, &["calldata", "1", "0x12345678"]).unwrap();
+        assert_eq!(debugger.cursor(), 1);
+        assert!(debugger.handle(&mut state, &["calldata", "5", "0x12345678"]).is_err());
+        assert_eq!(debugger.cursor(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+/// Differential fuzzing of two versions of a contract (e.g., before and after a patch, or a
+/// Solidity and a Vyper implementation)
+///
+/// Every transaction to the target is also executed on the shadow version with the same
+/// caller, value and calldata, starting from the same state, and their effects are compared.
+/// The address of the shadow version is replaced by the address of the target in its effects
+/// (e.g., `address(this)` returned, or the key of a token balance).
+use std::collections::{BTreeMap, HashMap};
+
+use bytes::Bytes;
+use revm_primitives::{keccak256, B256};
+
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::evm::vm::EVMState;
+
+/// Effects of a transaction on one of the versions
+#[derive(Clone, Debug, Default)]
+pub struct Effects {
+    pub success: bool,
+    pub output: Vec<u8>,
+    /// Emitted events (topics, data)
+    pub logs: Vec<(Vec<B256>, Bytes)>,
+    /// Values written to the storage of the version itself, sorted as storage layouts of
+    /// both versions may differ
+    pub own_writes: Vec<EVMU256>,
+    /// Storage writes to other contracts
+    pub external_writes: BTreeMap<(EVMAddress, EVMU256), EVMU256>,
+}
+
+/// Mappings keyed by the address are normalized when declared in one of the first slots
+const MAPPING_SLOTS: u64 = 256;
+
+/// Replaces an address by another in effects: in bytes, words and slots of mappings keyed by the
+/// address (`mapping(address => ...)` of Solidity and `HashMap[address, ...]` of Vyper)
+#[derive(Clone, Debug)]
+pub struct AddressNormalizer {
+    pub from: EVMAddress,
+    pub to: EVMAddress,
+    slots: HashMap<EVMU256, EVMU256>,
+}
+
+impl AddressNormalizer {
+    pub fn new(from: EVMAddress, to: EVMAddress) -> Self {
+        let word = |address: &EVMAddress| {
+            let mut word = [0u8; 32];
+            word[12..].copy_from_slice(&address.0);
+            word
+        };
+        let hash = |a: &[u8; 32], b: &[u8; 32]| EVMU256::from_be_bytes(keccak256(&[a.as_slice(), b.as_slice()].concat()).0);
+        let mut slots = HashMap::new();
+        for slot in 0..MAPPING_SLOTS {
+            let slot = EVMU256::from(slot).to_be_bytes::<32>();
+            slots.insert(hash(&word(&from), &slot), hash(&word(&to), &slot));
+            slots.insert(hash(&slot, &word(&from)), hash(&slot, &word(&to)));
+        }
+        Self { from, to, slots }
+    }
+
+    pub fn bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        let mut idx = 0;
+        while idx + 20 <= data.len() {
+            if data[idx..idx + 20] == self.from.0 {
+                data[idx..idx + 20].copy_from_slice(&self.to.0);
+                idx += 20;
+            } else {
+                idx += 1;
+            }
+        }
+        data
+    }
+
+    pub fn word(&self, value: EVMU256) -> EVMU256 {
+        EVMU256::from_be_slice(&self.bytes(&value.to_be_bytes::<32>()))
+    }
+
+    pub fn slot(&self, slot: EVMU256) -> EVMU256 {
+        self.slots.get(&slot).cloned().unwrap_or(slot)
+    }
+}
+
+impl Effects {
+    /// Effects of a transaction to `contract` from the state before and after it, writes to
+    /// `ignored` contracts (i.e., the other version) are skipped. `normalizer` replaces the
+    /// address of the shadow version by the address of the target.
+    pub fn new(
+        contract: EVMAddress,
+        ignored: EVMAddress,
+        success: bool,
+        output: Vec<u8>,
+        logs: Vec<(Vec<B256>, Bytes)>,
+        pre: &EVMState,
+        post: &EVMState,
+        normalizer: Option<&AddressNormalizer>,
+    ) -> Self {
+        let empty = HashMap::new();
+        let mut own_writes = vec![];
+        let mut external_writes = BTreeMap::new();
+        if success {
+            for (address, storage) in &post.state {
+                if *address == ignored {
+                    continue;
+                }
+                let before = pre.get(address).unwrap_or(&empty);
+                for (slot, value) in storage.iter() {
+                    if before.get(slot) == Some(value) {
+                        continue;
+                    }
+                    let value = normalizer.map_or(*value, |n| n.word(*value));
+                    if *address == contract {
+                        own_writes.push(value);
+                    } else {
+                        let slot = normalizer.map_or(*slot, |n| n.slot(*slot));
+                        external_writes.insert((*address, slot), value);
+                    }
+                }
+            }
+        }
+        own_writes.sort();
+        let (output, logs) = match normalizer {
+            Some(n) => (
+                n.bytes(&output),
+                logs.into_iter()
+                    .map(|(topics, data)| {
+                        let topics = topics.iter().map(|t| B256::from_slice(&n.bytes(&t.0))).collect();
+                        (topics, Bytes::from(n.bytes(&data)))
+                    })
+                    .collect(),
+            ),
+            None => (output, logs),
+        };
+        Self {
+            success,
+            output,
+            logs,
+            own_writes,
+            external_writes,
+        }
+    }
+}
+
+/// Describe the first divergence of the effects of both versions, if any
+pub fn divergence(target: &Effects, shadow: &Effects) -> Option<String> {
+    if target.success != shadow.success {
+        return Some(format!(
+            "target {} while shadow {}",
+            if target.success { "succeeds" } else { "reverts" },
+            if shadow.success { "succeeds" } else { "reverts" }
+        ));
+    }
+    if target.output != shadow.output {
+        return Some(format!(
+            "return data 0x{} != 0x{}",
+            hex::encode(&target.output),
+            hex::encode(&shadow.output)
+        ));
+    }
+    if !target.success {
+        return None;
+    }
+    if target.logs != shadow.logs {
+        return Some(format!(
+            "events differ ({} events != {} events)",
+            target.logs.len(),
+            shadow.logs.len()
+        ));
+    }
+    if target.own_writes != shadow.own_writes {
+        return Some(format!(
+            "own storage writes {:?} != {:?}",
+            target.own_writes, shadow.own_writes
+        ));
+    }
+    if target.external_writes != shadow.external_writes {
+        return Some("storage writes to other contracts differ".to_string());
+    }
+    None
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divergence() {
+        let effects = |success: bool, output: Vec<u8>, own_writes: Vec<u64>| Effects {
+            success,
+            output,
+            own_writes: own_writes.into_iter().map(EVMU256::from).collect(),
+            ..Default::default()
+        };
+        assert_eq!(divergence(&effects(true, vec![1], vec![1, 2]), &effects(true, vec![1], vec![1, 2])), None);
+        assert!(divergence(&effects(true, vec![1], vec![]), &effects(false, vec![], vec![])).is_some());
+        assert!(divergence(&effects(true, vec![1], vec![]), &effects(true, vec![2], vec![])).is_some());
+        assert!(divergence(&effects(true, vec![], vec![1]), &effects(true, vec![], vec![2])).is_some());
+        // revert reasons are compared, effects are not
+        assert_eq!(divergence(&effects(false, vec![], vec![1]), &effects(false, vec![], vec![2])), None);
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let (target, shadow) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let normalizer = AddressNormalizer::new(shadow, target);
+        let word = |address: EVMAddress| EVMU256::from_be_slice(&address.0);
+
+        // `return address(this)`
+        let output = word(shadow).to_be_bytes::<32>().to_vec();
+        assert_eq!(normalizer.bytes(&output), word(target).to_be_bytes::<32>().to_vec());
+        assert_eq!(normalizer.word(word(shadow)), word(target));
+        assert_eq!(normalizer.word(EVMU256::from(42)), EVMU256::from(42));
+
+        // balanceOf[address(this)] of a token with balances in slot 3
+        let balance_slot = |address: EVMAddress| {
+            let mut preimage = [0u8; 64];
+            preimage[12..32].copy_from_slice(&address.0);
+            preimage[63] = 3;
+            EVMU256::from_be_bytes(keccak256(&preimage).0)
+        };
+        assert_eq!(normalizer.slot(balance_slot(shadow)), balance_slot(target));
+        assert_eq!(normalizer.slot(EVMU256::from(3)), EVMU256::from(3));
+    }
+}
@@ -0,0 +1,170 @@
+/// Bounds on how far the fuzzer may bend the environment relative to the fork point (`--env-bounds`)
+///
+/// block.timestamp and block.number may only move forward from the fork point, by at most the
+/// given amount, and basefee may deviate from it by at most the given amount. By default, values
+/// beyond the bounds are clamped. With `--env-bounds-report`, they are kept, and violating
+/// sequences whose outcome changes once they are clamped are classified as depending on an
+/// unrealistic environment.
+///
+/// prevrandao is not bounded: it is known before a transaction is included and validators can
+/// bias it, so any value is realistic.
+use std::str::FromStr;
+
+use revm_primitives::Env;
+
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::replay::SolutionReplay;
+use crate::evm::types::{EVMFuzzState, EVMStagedVMState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+
+/// Environment of the block the campaign is forked from
+#[derive(Clone, Debug, Default)]
+pub struct ForkPoint {
+    pub number: EVMU256,
+    pub timestamp: EVMU256,
+    pub basefee: EVMU256,
+}
+
+impl ForkPoint {
+    pub fn from_env(env: &Env) -> Self {
+        Self {
+            number: env.block.number,
+            timestamp: env.block.timestamp,
+            basefee: env.block.basefee,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EnvBounds {
+    pub fork: ForkPoint,
+    /// Maximum seconds block.timestamp may be ahead of the fork point
+    pub timestamp: Option<u64>,
+    /// Maximum blocks block.number may be ahead of the fork point
+    pub number: Option<u64>,
+    /// Maximum deviation (in wei) of basefee from the fork point
+    pub basefee: Option<EVMU256>,
+    /// Whether values beyond the bounds are clamped (otherwise findings depending on them are
+    /// reported)
+    pub enforce: bool,
+}
+
+impl EnvBounds {
+    /// Parse bounds like `timestamp=604800,number=50400,basefee=100000000000`
+    pub fn parse(spec: &str, fork: ForkPoint, enforce: bool) -> Self {
+        let mut bounds = Self {
+            fork,
+            enforce,
+            ..Default::default()
+        };
+        for item in spec.split(',').filter(|item| !item.is_empty()) {
+            let (key, value) = item
+                .split_once('=')
+                .unwrap_or_else(|| panic!("invalid env bound {}, expected <field>=<max deviation>", item));
+            match key.trim() {
+                "timestamp" => bounds.timestamp = Some(value.trim().parse().expect("invalid timestamp bound")),
+                "number" => bounds.number = Some(value.trim().parse().expect("invalid number bound")),
+                "basefee" => bounds.basefee = Some(EVMU256::from_str(value.trim()).expect("invalid basefee bound")),
+                other => panic!("unknown env bound {}, expected timestamp, number or basefee", other),
+            }
+        }
+        bounds
+    }
+
+    fn timestamp_range(&self) -> Option<(EVMU256, EVMU256)> {
+        self.timestamp
+            .map(|max| (self.fork.timestamp, self.fork.timestamp.saturating_add(EVMU256::from(max))))
+    }
+
+    fn number_range(&self) -> Option<(EVMU256, EVMU256)> {
+        self.number
+            .map(|max| (self.fork.number, self.fork.number.saturating_add(EVMU256::from(max))))
+    }
+
+    fn basefee_range(&self) -> Option<(EVMU256, EVMU256)> {
+        self.basefee
+            .map(|max| (self.fork.basefee.saturating_sub(max), self.fork.basefee.saturating_add(max)))
+    }
+
+    /// Clamp the environment of a transaction into the bounds
+    pub fn clamp(&self, env: &mut Env) {
+        if let Some((min, max)) = self.timestamp_range() {
+            env.block.timestamp = env.block.timestamp.max(min).min(max);
+        }
+        if let Some((min, max)) = self.number_range() {
+            env.block.number = env.block.number.max(min).min(max);
+        }
+        if let Some((min, max)) = self.basefee_range() {
+            env.block.basefee = env.block.basefee.max(min).min(max);
+        }
+    }
+
+    /// Values of the environment beyond the bounds
+    pub fn violations(&self, env: &Env) -> Vec<String> {
+        let mut violations = vec![];
+        let mut check = |name: &str, value: EVMU256, range: Option<(EVMU256, EVMU256)>| {
+            if let Some((min, max)) = range {
+                if value < min || value > max {
+                    violations.push(format!("{} = {} (allowed: {} - {})", name, value, min, max));
+                }
+            }
+        };
+        check("block.timestamp", env.block.timestamp, self.timestamp_range());
+        check("block.number", env.block.number, self.number_range());
+        check("basefee", env.block.basefee, self.basefee_range());
+        violations
+    }
+}
+
+/// Environment values beyond the bounds a replayed violating sequence depends on, i.e., empty if
+/// the sequence stays within the bounds or behaves the same once they are clamped
+pub fn unrealistic_env(
+    executor: &mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &mut EVMFuzzState,
+    initial_state: &EVMStagedVMState,
+    txs: &str,
+    replay: &SolutionReplay,
+) -> Vec<String> {
+    let bounds = match executor.host.env_bounds.clone() {
+        Some(bounds) if !bounds.enforce => bounds,
+        _ => return vec![],
+    };
+    if replay.env_violations.is_empty() {
+        return vec![];
+    }
+    executor.host.env_bounds = Some(EnvBounds {
+        enforce: true,
+        ..bounds.clone()
+    });
+    let clamped = SolutionReplay::new(executor, state, initial_state, txs);
+    executor.host.env_bounds = Some(bounds);
+    if clamped.outcomes() == replay.outcomes() {
+        return vec![];
+    }
+    replay.env_violations.clone()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_bounds() {
+        let fork = ForkPoint {
+            number: EVMU256::from(1000),
+            timestamp: EVMU256::from(100000),
+            basefee: EVMU256::from(50),
+        };
+        let bounds = EnvBounds::parse("timestamp=3600,number=10,basefee=20", fork, true);
+        let mut env = Env::default();
+        env.block.number = EVMU256::from(1005);
+        env.block.timestamp = EVMU256::from(200000);
+        env.block.basefee = EVMU256::from(0);
+        let violations = bounds.violations(&env);
+        assert_eq!(violations.len(), 2);
+        bounds.clamp(&mut env);
+        assert_eq!(env.block.number, EVMU256::from(1005));
+        assert_eq!(env.block.timestamp, EVMU256::from(103600));
+        assert_eq!(env.block.basefee, EVMU256::from(30));
+        assert!(bounds.violations(&env).is_empty());
+    }
+}
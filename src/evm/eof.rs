@@ -0,0 +1,358 @@
+/// EOF (EVM Object Format, EIP-3540) containers
+///
+/// revm does not know EOF yet, so EOF contracts are executed on an image of their code sections
+/// laid out back to back (the header, sub-containers and data are dropped), and the EOF
+/// instructions are emulated by the host on that image: relative jumps (RJUMP / RJUMPI / RJUMPV),
+/// functions (CALLF / RETF / JUMPF), data section access (DATALOAD* / DATASIZE / DATACOPY),
+/// DUPN / SWAPN / EXCHANGE and RETURNCONTRACT (so EOF init code deploys its runtime
+/// sub-container). EOFCREATE and the EXT*CALL instructions are not supported.
+///
+/// PCs of EOF contracts (coverage, source maps, etc.) are offsets in the image.
+use revm_interpreter::{InstructionResult, Interpreter};
+
+use crate::evm::types::{as_u64, EVMU256};
+
+pub const MAGIC: [u8; 2] = [0xef, 0x00];
+const VERSION: u8 = 0x01;
+
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_CONTAINER: u8 = 0x03;
+const KIND_DATA: u8 = 0xff;
+const TERMINATOR: u8 = 0x00;
+
+pub const DATALOAD: u8 = 0xd0;
+pub const DATALOADN: u8 = 0xd1;
+pub const DATASIZE: u8 = 0xd2;
+pub const DATACOPY: u8 = 0xd3;
+pub const RJUMP: u8 = 0xe0;
+pub const RJUMPI: u8 = 0xe1;
+pub const RJUMPV: u8 = 0xe2;
+pub const CALLF: u8 = 0xe3;
+pub const RETF: u8 = 0xe4;
+pub const JUMPF: u8 = 0xe5;
+pub const DUPN: u8 = 0xe6;
+pub const SWAPN: u8 = 0xe7;
+pub const EXCHANGE: u8 = 0xe8;
+pub const RETURNCONTRACT: u8 = 0xee;
+
+/// Maximum depth of the return stack (EIP-4750)
+const MAX_RETURN_STACK: usize = 1024;
+
+/// Type of a code section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionType {
+    pub inputs: u8,
+    /// 0x80 for non-returning sections
+    pub outputs: u8,
+    pub max_stack_increase: u16,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EofContainer {
+    pub types: Vec<SectionType>,
+    pub code_sections: Vec<Vec<u8>>,
+    pub containers: Vec<Vec<u8>>,
+    pub data: Vec<u8>,
+    /// Offset of each code section in the image
+    pub code_offsets: Vec<usize>,
+    /// Code sections laid out back to back, the code executed
+    pub image: Vec<u8>,
+}
+
+pub fn is_eof(code: &[u8]) -> bool {
+    code.starts_with(&MAGIC)
+}
+
+/// Whether the host emulates the instruction for EOF contracts
+pub fn is_eof_instruction(op: u8) -> bool {
+    matches!(op, DATALOAD..=DATACOPY | RJUMP..=EXCHANGE | RETURNCONTRACT)
+}
+
+/// Size of the immediates of the instruction at `pc` of EOF code
+pub fn immediate_size(code: &[u8], pc: usize) -> usize {
+    match code[pc] {
+        op @ 0x60..=0x7f => (op - 0x5f) as usize,
+        RJUMP | RJUMPI | CALLF | JUMPF | DATALOADN => 2,
+        DUPN | SWAPN | EXCHANGE | RETURNCONTRACT | 0xec => 1,
+        RJUMPV => 1 + 2 * (*code.get(pc + 1).unwrap_or(&0) as usize + 1),
+        _ => 0,
+    }
+}
+
+/// PCs of the instructions of EOF code (immediates skipped)
+pub fn instructions(code: &[u8]) -> Vec<usize> {
+    let mut pcs = vec![];
+    let mut pc = 0;
+    while pc < code.len() {
+        pcs.push(pc);
+        pc += 1 + immediate_size(code, pc);
+    }
+    pcs
+}
+
+fn read_u16(code: &[u8], at: usize) -> usize {
+    match code.get(at..at + 2) {
+        Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+        None => 0,
+    }
+}
+
+fn relative(code: &[u8], at: usize, base: usize) -> usize {
+    (base as isize + read_u16(code, at) as u16 as i16 as isize) as usize
+}
+
+/// Destinations of a relative jump at `pc`, the fall through first (for conditional jumps)
+pub fn relative_targets(code: &[u8], pc: usize) -> Vec<usize> {
+    let next = pc + 1 + immediate_size(code, pc);
+    match code[pc] {
+        RJUMP => vec![relative(code, pc + 1, next)],
+        RJUMPI => vec![next, relative(code, pc + 1, next)],
+        RJUMPV => {
+            let cases = code[pc + 1] as usize + 1;
+            let mut targets = vec![next];
+            targets.extend((0..cases).map(|i| relative(code, pc + 2 + 2 * i, next)));
+            targets
+        }
+        _ => vec![],
+    }
+}
+
+impl EofContainer {
+    /// Parse an EOF container, None if it is malformed
+    pub fn parse(code: &[u8]) -> Option<Self> {
+        if !is_eof(code) || code.get(2) != Some(&VERSION) {
+            return None;
+        }
+        let mut at = 3;
+        let expect = |kind: u8, at: &mut usize| -> Option<()> {
+            if code.get(*at) != Some(&kind) {
+                return None;
+            }
+            *at += 1;
+            Some(())
+        };
+
+        expect(KIND_TYPES, &mut at)?;
+        let types_size = read_u16(code, at);
+        at += 2;
+        expect(KIND_CODE, &mut at)?;
+        let num_code = read_u16(code, at);
+        at += 2;
+        let code_sizes = (0..num_code).map(|i| read_u16(code, at + 2 * i)).collect::<Vec<_>>();
+        at += 2 * num_code;
+        let mut container_sizes = vec![];
+        if code.get(at) == Some(&KIND_CONTAINER) {
+            at += 1;
+            let num_containers = read_u16(code, at);
+            at += 2;
+            for i in 0..num_containers {
+                let size = code.get(at + 4 * i..at + 4 * i + 4)?;
+                container_sizes.push(u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize);
+            }
+            at += 4 * num_containers;
+        }
+        expect(KIND_DATA, &mut at)?;
+        // the data size is not checked, data sections of init containers are truncated (the
+        // aux data is appended at deployment)
+        at += 2;
+        expect(TERMINATOR, &mut at)?;
+        if num_code == 0 || types_size != 4 * num_code {
+            return None;
+        }
+
+        let mut take = |size: usize| -> Option<Vec<u8>> {
+            let section = code.get(at..at + size)?.to_vec();
+            at += size;
+            Some(section)
+        };
+        let types = take(types_size)?
+            .chunks(4)
+            .map(|t| SectionType {
+                inputs: t[0],
+                outputs: t[1],
+                max_stack_increase: u16::from_be_bytes([t[2], t[3]]),
+            })
+            .collect();
+        let code_sections = code_sizes.iter().map(|size| take(*size)).collect::<Option<Vec<_>>>()?;
+        let containers = container_sizes.iter().map(|size| take(*size)).collect::<Option<Vec<_>>>()?;
+        let data = code[at..].to_vec();
+
+        let mut code_offsets = vec![];
+        let mut image = vec![];
+        for section in &code_sections {
+            code_offsets.push(image.len());
+            image.extend_from_slice(section);
+        }
+        Some(Self {
+            types,
+            code_sections,
+            containers,
+            data,
+            code_offsets,
+            image,
+        })
+    }
+
+    /// Execute the EOF instruction at the instruction pointer on the image, `return_stack` is the
+    /// return stack of the call frame. Returns the branch taken by a conditional jump (pc,
+    /// destination), for coverage, or the result the frame ends with.
+    pub unsafe fn step(
+        &self,
+        interp: &mut Interpreter,
+        return_stack: &mut Vec<usize>,
+    ) -> Result<Option<(usize, usize)>, InstructionResult> {
+        let pc = interp.program_counter();
+        let code = &self.image;
+        let op = code[pc];
+        let next = pc + 1 + immediate_size(code, pc);
+        let jump = |interp: &mut Interpreter, to: usize| -> Result<(), InstructionResult> {
+            if to >= code.len() {
+                return Err(InstructionResult::InvalidJump);
+            }
+            interp.instruction_pointer = interp.contract.bytecode.as_ptr().add(to);
+            Ok(())
+        };
+        let pop = |interp: &mut Interpreter| interp.stack.pop();
+        let push = |interp: &mut Interpreter, v: EVMU256| interp.stack.push(v);
+        let data_word = |offset: usize| {
+            let mut word = [0u8; 32];
+            for (i, byte) in word.iter_mut().enumerate() {
+                *byte = *self.data.get(offset.saturating_add(i)).unwrap_or(&0);
+            }
+            EVMU256::from_be_bytes(word)
+        };
+
+        match op {
+            RJUMP => return jump(interp, relative_targets(code, pc)[0]).map(|_| None),
+            RJUMPI | RJUMPV => {
+                let selector = pop(interp)?;
+                let targets = relative_targets(code, pc);
+                let to = if op == RJUMPI {
+                    targets[if selector == EVMU256::ZERO { 0 } else { 1 }]
+                } else if selector < EVMU256::from(targets.len() - 1) {
+                    targets[as_u64(selector) as usize + 1]
+                } else {
+                    targets[0]
+                };
+                jump(interp, to)?;
+                return Ok(Some((pc, to)));
+            }
+            CALLF | JUMPF => {
+                let section = read_u16(code, pc + 1);
+                let to = *self.code_offsets.get(section).ok_or(InstructionResult::InvalidJump)?;
+                if op == CALLF {
+                    if return_stack.len() >= MAX_RETURN_STACK {
+                        return Err(InstructionResult::CallTooDeep);
+                    }
+                    return_stack.push(next);
+                }
+                jump(interp, to)?;
+                return Ok(None);
+            }
+            RETF => {
+                let to = return_stack.pop().ok_or(InstructionResult::StackUnderflow)?;
+                jump(interp, to)?;
+                return Ok(None);
+            }
+            DATALOAD => {
+                let offset = pop(interp)?;
+                push(interp, data_word(usize::try_from(offset).unwrap_or(usize::MAX)))?;
+            }
+            DATALOADN => push(interp, data_word(read_u16(code, pc + 1)))?,
+            DATASIZE => push(interp, EVMU256::from(self.data.len()))?,
+            DATACOPY => {
+                let (mem_offset, offset, size) = (pop(interp)?, pop(interp)?, pop(interp)?);
+                let (mem_offset, size) = (as_u64(mem_offset) as usize, as_u64(size) as usize);
+                if size > 0 {
+                    let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+                    let bytes = (0..size)
+                        .map(|i| *self.data.get(offset.saturating_add(i)).unwrap_or(&0))
+                        .collect::<Vec<_>>();
+                    let end = mem_offset + size;
+                    if interp.memory.len() < end {
+                        interp.memory.resize((end + 31) / 32 * 32);
+                    }
+                    interp.memory.set(mem_offset, &bytes);
+                }
+            }
+            DUPN => {
+                let n = code[pc + 1] as usize + 1;
+                let len = interp.stack.len();
+                if len < n {
+                    return Err(InstructionResult::StackUnderflow);
+                }
+                let v = interp.stack.data()[len - n];
+                push(interp, v)?;
+            }
+            SWAPN | EXCHANGE => {
+                let imm = code[pc + 1] as usize;
+                let (a, b) = if op == SWAPN {
+                    (0, imm + 1)
+                } else {
+                    let n = (imm >> 4) + 1;
+                    (n, n + (imm & 0x0f) + 1)
+                };
+                let len = interp.stack.len();
+                if len <= b {
+                    return Err(InstructionResult::StackUnderflow);
+                }
+                interp.stack.data_mut().swap(len - 1 - a, len - 1 - b);
+            }
+            RETURNCONTRACT => {
+                let container = self
+                    .containers
+                    .get(code[pc + 1] as usize)
+                    .ok_or(InstructionResult::InvalidJump)?;
+                let (aux_offset, aux_size) = (pop(interp)?, pop(interp)?);
+                let (aux_offset, aux_size) = (as_u64(aux_offset) as usize, as_u64(aux_size) as usize);
+                if interp.memory.len() < aux_offset + aux_size {
+                    interp.memory.resize((aux_offset + aux_size + 31) / 32 * 32);
+                }
+                let mut runtime = container.clone();
+                runtime.extend_from_slice(interp.memory.get_slice(aux_offset, aux_size));
+                // return the runtime container from the end of memory
+                let start = interp.memory.len();
+                interp.memory.resize((start + runtime.len() + 31) / 32 * 32);
+                interp.memory.set(start, &runtime);
+                interp.return_range = start..start + runtime.len();
+                return Err(InstructionResult::Return);
+            }
+            _ => return Err(InstructionResult::OpcodeNotFound),
+        }
+        jump(interp, next)?;
+        Ok(None)
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let container = hex::decode(concat!(
+            "ef0001",
+            "010008",         // types: 2 sections
+            "02000200",       // code: 2 sections
+            "0a0001",         // of 10 and 1 bytes
+            "ff0002",         // data: 2 bytes
+            "00",
+            "00800001",       // section 0: non-returning
+            "00000000",       // section 1
+            "6001e1000100e3000100", // PUSH1 1, RJUMPI +1, STOP, CALLF 1, STOP
+            "e4",             // RETF
+            "abcd",
+        ))
+        .unwrap();
+        let eof = EofContainer::parse(&container).unwrap();
+        assert_eq!(eof.types.len(), 2);
+        assert_eq!(eof.types[0].outputs, 0x80);
+        assert_eq!(eof.code_offsets, vec![0, 10]);
+        assert_eq!(eof.image.len(), 11);
+        assert_eq!(eof.data, vec![0xab, 0xcd]);
+        assert_eq!(instructions(&eof.image), vec![0, 2, 5, 6, 9, 10]);
+        assert_eq!(relative_targets(&eof.image, 2), vec![5, 6]);
+        assert!(EofContainer::parse(&container[..20]).is_none());
+        assert!(EofContainer::parse(&hex::decode("6080604052").unwrap()).is_none());
+    }
+}
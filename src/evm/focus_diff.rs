@@ -0,0 +1,154 @@
+/// Directed fuzzing of the code changed since a git revision (`--focus-diff <rev>`)
+///
+/// Lines changed in Solidity files (`git diff <rev>`) are mapped to the PCs of the deployed
+/// contracts through their source maps. Executions reaching these PCs are tracked by the
+/// [`DiffCoverage`](crate::evm::middlewares::diff_coverage::DiffCoverage) middleware, which
+/// reports newly reached changed code as coverage, and inputs reaching it get more energy.
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+use crate::evm::srcmap::parser::BASE_PATH;
+use crate::evm::types::{EVMAddress, ProjectSourceMapTy};
+
+/// Changed lines (1-indexed) of each file in a unified diff (without context lines)
+pub fn parse_diff(diff: &str) -> HashMap<String, HashSet<usize>> {
+    let mut changed: HashMap<String, HashSet<usize>> = HashMap::new();
+    let mut file = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = match path {
+                "/dev/null" => None,
+                path => Some(path.trim_start_matches("b/").to_string()),
+            };
+            continue;
+        }
+        let hunk = match line.strip_prefix("@@ ") {
+            Some(hunk) => hunk,
+            None => continue,
+        };
+        // @@ -a,b +c,d @@
+        let added = match hunk.split(' ').find(|part| part.starts_with('+')) {
+            Some(added) => &added[1..],
+            None => continue,
+        };
+        let (start, count) = match added.split_once(',') {
+            Some((start, count)) => (start.parse::<usize>(), count.parse::<usize>()),
+            None => (added.parse::<usize>(), Ok(1)),
+        };
+        if let (Some(file), Ok(start), Ok(count)) = (&file, start, count) {
+            // deletions are attributed to the line following them
+            let lines = changed.entry(file.clone()).or_default();
+            if count == 0 {
+                lines.insert(start + 1);
+            }
+            lines.extend(start..start + count);
+        }
+    }
+    changed
+}
+
+/// Changed lines of Solidity files since `rev`
+pub fn changed_lines(rev: &str) -> HashMap<String, HashSet<usize>> {
+    let output = Command::new("git")
+        .args(["diff", "--unified=0", rev, "--", "*.sol"])
+        .output()
+        .expect("failed to run git diff");
+    if !output.status.success() {
+        panic!("git diff {} failed: {}", rev, String::from_utf8_lossy(&output.stderr));
+    }
+    parse_diff(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Whether a source file of a source map and a file of the diff are the same
+fn same_file(source: &str, diff_file: &str) -> bool {
+    let source = source.trim_start_matches("./");
+    source.ends_with(diff_file) || diff_file.ends_with(source)
+}
+
+/// Line (1-indexed) of each byte offset of a file
+fn line_starts(content: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    for (idx, byte) in content.bytes().enumerate() {
+        if byte == b'\n' {
+            starts.push(idx + 1);
+        }
+    }
+    starts
+}
+
+fn line_of(starts: &Vec<usize>, offset: usize) -> usize {
+    match starts.binary_search(&offset) {
+        Ok(idx) => idx + 1,
+        Err(idx) => idx,
+    }
+}
+
+/// PCs of each contract whose source location overlaps a changed line
+pub fn changed_pcs(
+    changed: &HashMap<String, HashSet<usize>>,
+    source_maps: &ProjectSourceMapTy,
+) -> HashMap<EVMAddress, HashSet<usize>> {
+    let mut files: HashMap<String, Option<Vec<usize>>> = HashMap::new();
+    let mut pcs: HashMap<EVMAddress, HashSet<usize>> = HashMap::new();
+    for (address, source_map) in source_maps {
+        let source_map = match source_map {
+            Some(source_map) => source_map,
+            None => continue,
+        };
+        for (pc, loc) in source_map {
+            let source = match &loc.file {
+                Some(source) => source,
+                None => continue,
+            };
+            let lines = match changed.iter().find(|(file, _)| same_file(source, file)) {
+                Some((_, lines)) => lines,
+                None => continue,
+            };
+            let starts = files.entry(source.clone()).or_insert_with(|| {
+                std::fs::read_to_string(unsafe { BASE_PATH.clone() } + source.as_str())
+                    .ok()
+                    .map(|content| line_starts(&content))
+            });
+            if let Some(starts) = starts {
+                let (first, last) = (line_of(starts, loc.offset), line_of(starts, loc.offset + loc.length));
+                // skip locations spanning whole contracts / functions
+                if last - first > 3 {
+                    continue;
+                }
+                if (first..=last).any(|line| lines.contains(&line)) {
+                    pcs.entry(*address).or_default().insert(*pc);
+                }
+            }
+        }
+    }
+    pcs
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "diff --git a/src/Vault.sol b/src/Vault.sol
+--- a/src/Vault.sol
++++ b/src/Vault.sol
+@@ -10,2 +10,3 @@ contract Vault {
++        require(x > 0);
+@@ -20 +21 @@ contract Vault {
+@@ -30,2 +31,0 @@ contract Vault {
+";
+        let changed = parse_diff(diff);
+        let mut lines = changed["src/Vault.sol"].iter().cloned().collect::<Vec<_>>();
+        lines.sort();
+        assert_eq!(lines, vec![10, 11, 12, 21, 32]);
+    }
+
+    #[test]
+    fn test_line_of() {
+        let starts = line_starts("a\nbc\n\nd");
+        assert_eq!(line_of(&starts, 0), 1);
+        assert_eq!(line_of(&starts, 3), 2);
+        assert_eq!(line_of(&starts, 5), 3);
+        assert_eq!(line_of(&starts, 6), 4);
+    }
+}
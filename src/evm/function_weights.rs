@@ -0,0 +1,134 @@
+/// Per-function fuzzing weights (`--function-weights`) and regex-based focus (`--only-fuzz`)
+///
+/// `--only-fuzz 'deposit|withdraw.*'` only adds calls to functions whose whole name matches the
+/// regex to the corpus (hard exclusion of the others). The weights table gives functions a share
+/// of the generated transactions instead: the energy of an input is scaled by the weight of the
+/// function it calls, relative to the average weight of the functions of the targets, so that a
+/// function of weight 4 receives about four times the transactions of a function of weight 1.
+/// The table is `pattern=weight,...` or a file with a JSON object `{"pattern": weight}`, the first
+/// pattern matching a function gives its weight (1 if none matches).
+use std::collections::HashMap;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::evm::abi::function_name;
+
+/// Functions added to the corpus (all if None)
+pub static mut ONLY_FUZZ: Option<Regex> = None;
+
+/// Range of the energy multiplier of a function
+const MIN_WEIGHT: f64 = 0.05;
+const MAX_WEIGHT: f64 = 16.0;
+
+/// Regex matching whole function names
+fn anchored(pattern: &str) -> Regex {
+    Regex::new(&format!("^(?:{})$", pattern)).unwrap_or_else(|e| panic!("invalid function regex {}: {}", pattern, e))
+}
+
+pub fn set_only_fuzz(pattern: &str) {
+    unsafe {
+        ONLY_FUZZ = Some(anchored(pattern));
+    }
+}
+
+/// Whether calls to the function are added to the corpus
+pub fn is_fuzzed(name: &str) -> bool {
+    match unsafe { &ONLY_FUZZ } {
+        Some(only) => only.is_match(name),
+        None => true,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionWeights {
+    table: Vec<(Regex, f64)>,
+    /// Average weight of the functions of the targets
+    mean: f64,
+    /// Function => energy multiplier
+    cache: HashMap<[u8; 4], f64>,
+}
+
+/// Parse `pattern=weight,...`, or the JSON object `{"pattern": weight}` in the file at `spec`
+pub fn parse_table(spec: &str) -> Vec<(String, f64)> {
+    if Path::new(spec).is_file() {
+        let content = std::fs::read_to_string(spec).expect("failed to read function weights");
+        let table: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&content).expect("function weights should be a JSON object");
+        return table
+            .into_iter()
+            .map(|(pattern, weight)| (pattern, weight.as_f64().expect("function weight should be a number")))
+            .collect();
+    }
+    spec.split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (pattern, weight) = entry.rsplit_once('=').expect("function weight should be <pattern>=<weight>");
+            (
+                pattern.trim().to_string(),
+                weight.trim().parse().expect("function weight should be a number"),
+            )
+        })
+        .collect()
+}
+
+impl FunctionWeights {
+    /// Weights table over the functions of the targets (by name)
+    pub fn new(table: Vec<(String, f64)>, functions: &[String]) -> Self {
+        let mut weights = Self {
+            table: table.iter().map(|(pattern, weight)| (anchored(pattern), *weight)).collect(),
+            mean: 1.0,
+            cache: HashMap::new(),
+        };
+        let fuzzed = functions.iter().filter(|name| is_fuzzed(name)).collect::<Vec<_>>();
+        if !fuzzed.is_empty() {
+            weights.mean = fuzzed.iter().map(|name| weights.raw_weight(name)).sum::<f64>() / fuzzed.len() as f64;
+        }
+        weights
+    }
+
+    fn raw_weight(&self, name: &str) -> f64 {
+        self.table
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(name))
+            .map_or(1.0, |(_, weight)| *weight)
+    }
+
+    /// Energy multiplier of inputs calling `function`
+    pub fn weight(&mut self, function: &[u8; 4]) -> f64 {
+        if let Some(weight) = self.cache.get(function) {
+            return *weight;
+        }
+        let raw = match function_name(function) {
+            Some(name) => self.raw_weight(&name),
+            None => 1.0,
+        };
+        let weight = (raw / self.mean.max(f64::EPSILON)).clamp(MIN_WEIGHT, MAX_WEIGHT);
+        self.cache.insert(*function, weight);
+        weight
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table() {
+        assert_eq!(
+            parse_table("deposit=4, withdraw.*=2"),
+            vec![("deposit".to_string(), 4.0), ("withdraw.*".to_string(), 2.0)]
+        );
+    }
+
+    #[test]
+    fn test_weights() {
+        let functions = ["deposit", "withdraw", "withdrawAll", "approve"].map(|f| f.to_string());
+        let weights = FunctionWeights::new(parse_table("deposit=5,withdraw.*=1,approve=1"), &functions);
+        // (5 + 1 + 1 + 1) / 4
+        assert_eq!(weights.mean, 2.0);
+        assert_eq!(weights.raw_weight("deposit"), 5.0);
+        assert_eq!(weights.raw_weight("withdrawAll"), 1.0);
+        // patterns match whole names
+        assert_eq!(weights.raw_weight("depositFor"), 1.0);
+    }
+}
@@ -0,0 +1,105 @@
+/// Gas estimation for executions, revm is built without gas measuring
+///
+/// Costs follow the Berlin / London schedule with all accounts and slots considered cold,
+/// memory expansion and refunds are ignored. This is good enough to tell whether a transaction
+/// stays within the block gas limit and how its cost grows with the state.
+use crate::evm::types::EVMU256;
+use crate::evm::vm::EVMState;
+use revm_interpreter::Interpreter;
+
+/// Default gas ceiling of a transaction, i.e., the block gas limit of Ethereum mainnet
+pub const DEFAULT_GAS_CEILING: u64 = 30_000_000;
+
+/// Length operand of an instruction, saturated so that huge lengths don't overflow
+fn length(len: EVMU256) -> u64 {
+    len.try_into().unwrap_or(u32::MAX as u64)
+}
+
+fn words(len: EVMU256) -> u64 {
+    (length(len) + 31) / 32
+}
+
+/// Gas of the instruction the interpreter is about to execute
+pub fn opcode_gas(interp: &Interpreter, evmstate: &EVMState) -> u64 {
+    let op = unsafe { *interp.instruction_pointer };
+    let peek = |idx: usize| -> EVMU256 {
+        let len = interp.stack.len();
+        if idx < len {
+            interp.stack.data()[len - 1 - idx]
+        } else {
+            EVMU256::ZERO
+        }
+    };
+    match op {
+        // STOP, RETURN, REVERT, INVALID
+        0x00 | 0xf3 | 0xfd | 0xfe => 0,
+        // JUMPDEST
+        0x5b => 1,
+        // ADDRESS, ORIGIN, CALLER, CALLVALUE, CALLDATASIZE, CODESIZE, GASPRICE, RETURNDATASIZE,
+        // COINBASE, TIMESTAMP, NUMBER, DIFFICULTY, GASLIMIT, CHAINID, BASEFEE, POP, PC, MSIZE, GAS, PUSH0
+        0x30 | 0x32..=0x34 | 0x36 | 0x38 | 0x3a | 0x3d | 0x41..=0x46 | 0x48 | 0x50 | 0x58..=0x5a | 0x5f => 2,
+        // arithmetic, comparison, bitwise, CALLDATALOAD, MLOAD, MSTORE, MSTORE8, PUSH, DUP, SWAP
+        0x01 | 0x03 | 0x10..=0x1d | 0x35 | 0x51..=0x53 | 0x60..=0x9f => 3,
+        // MUL, DIV, SDIV, MOD, SMOD, SIGNEXTEND, SELFBALANCE
+        0x02 | 0x04..=0x07 | 0x0b | 0x47 => 5,
+        // ADDMOD, MULMOD, JUMP
+        0x08 | 0x09 | 0x56 => 8,
+        // JUMPI
+        0x57 => 10,
+        // EXP
+        0x0a => {
+            let exponent = peek(1);
+            10 + 50 * ((exponent.bit_len() as u64 + 7) / 8)
+        }
+        // SHA3
+        0x20 => 30 + 6 * words(peek(1)),
+        // CALLDATACOPY, CODECOPY, RETURNDATACOPY
+        0x37 | 0x39 | 0x3e => 3 + 3 * words(peek(2)),
+        // EXTCODECOPY
+        0x3c => 2600 + 3 * words(peek(3)),
+        // BALANCE, EXTCODESIZE, EXTCODEHASH
+        0x31 | 0x3b | 0x3f => 2600,
+        // BLOCKHASH
+        0x40 => 20,
+        // SLOAD
+        0x54 => 2100,
+        // SSTORE
+        0x55 => {
+            let current = evmstate
+                .get(&interp.contract.address)
+                .and_then(|slots| slots.get(&peek(0)))
+                .cloned()
+                .unwrap_or(EVMU256::ZERO);
+            let new = peek(1);
+            if current == new {
+                2200
+            } else if current == EVMU256::ZERO {
+                22100
+            } else {
+                5000
+            }
+        }
+        // TLOAD, TSTORE
+        0x5c | 0x5d => 100,
+        // MCOPY
+        0x5e => 3 + 3 * words(peek(2)),
+        // LOG0 - LOG4
+        0xa0..=0xa4 => 375 + 375 * (op - 0xa0) as u64 + 8 * length(peek(1)),
+        // CREATE, CREATE2
+        0xf0 => 32000,
+        0xf5 => 32000 + 6 * words(peek(2)),
+        // CALL, CALLCODE
+        0xf1 | 0xf2 => {
+            if peek(2) != EVMU256::ZERO {
+                2600 + 9000
+            } else {
+                2600
+            }
+        }
+        // DELEGATECALL, STATICCALL
+        0xf4 | 0xfa => 2600,
+        // SELFDESTRUCT
+        0xff => 5000,
+        _ => 3,
+    }
+}
@@ -1,5 +1,20 @@
+use crate::evm::budget::ExecBudget;
+use tracing::{debug, info};
 use crate::evm::bytecode_analyzer;
-use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
+use crate::evm::chain_spec::ChainSpec;
+use crate::evm::env_bounds::EnvBounds;
+use crate::evm::eof::{is_eof, is_eof_instruction, EofContainer};
+use crate::evm::gas::opcode_gas;
+use crate::evm::precompiles::PrecompileHandler;
+use crate::evm::signature::{ecrecover, ECRECOVER_ADDRESS};
+use crate::evm::token_callbacks::TokenCallbacks;
+use crate::evm::mocks::MockSynthesis;
+use crate::evm::middlewares::chainlink::{ChainlinkFeeds, FeedCall};
+use crate::evm::middlewares::native_flow::native_balance;
+use crate::evm::oracles::delegatecall::hijack_storage_write;
+use crate::evm::oracles::eip7702::{delegation_code, delegation_designator};
+use crate::evm::zksync::{create2_address, create_address, is_system_contract, ZkSyncEnv};
+use crate::evm::input::{BlobEnv, ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
 use crate::evm::middlewares::middleware::{add_corpus, CallMiddlewareReturn, Middleware, MiddlewareType};
 use crate::evm::mutator::AccessPattern;
 
@@ -29,10 +44,10 @@ use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use hex::FromHex;
 use revm::precompile::{Precompile, Precompiles};
-use revm_interpreter::{BytecodeLocked, CallContext, CallInputs, CallScheme, Contract, CreateInputs, Gas, Host, InstructionResult, Interpreter, SelfDestructResult};
+use revm_interpreter::{BytecodeLocked, CallContext, CallInputs, CallScheme, Contract, CreateInputs, Gas, Host, InstructionResult, Interpreter, SelfDestructResult, Stack};
 use revm_interpreter::analysis::to_analysed;
-use revm_primitives::{B256, Bytecode, Env, LatestSpec, Spec};
-use crate::evm::types::{as_u64, bytes_to_u64, EVMAddress, EVMU256, generate_random_address, is_zero};
+use revm_primitives::{B256, Bytecode, CreateScheme, Env, keccak256, LatestSpec, Spec};
+use crate::evm::types::{as_u64, bytes_to_u64, convert_u256_to_h160, fixed_address, EVMAddress, EVMU256, generate_random_address, is_zero};
 
 use crate::evm::uniswap::{generate_uniswap_router_call, TokenContext};
 use crate::evm::vm::{EVMState, IN_DEPLOY, IS_FAST_CALL_STATIC};
@@ -78,6 +93,12 @@ pub static mut PANIC_ON_BUG: bool = false;
 // for debugging purpose, return ControlLeak when the calls amount exceeds this value
 pub static mut CALL_UNTIL: u32 = u32::MAX;
 
+/// Cancun opcodes, not yet known by revm
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4a;
+pub const TLOAD: u8 = 0x5c;
+pub const TSTORE: u8 = 0x5d;
+
 /// Shall we dump the contract calls
 pub static mut WRITE_RELATIONSHIPS: bool = false;
 
@@ -96,6 +117,66 @@ pub fn is_precompile(address: EVMAddress, num_of_precompiles: usize) -> bool {
     num.wrapping_sub(1) < num_of_precompiles as u16
 }
 
+/// Emulate TLOAD / TSTORE (EIP-1153) of the contract at `address` on its stack
+pub fn emulate_transient(
+    op: u8,
+    address: EVMAddress,
+    stack: &mut Stack,
+    is_static: bool,
+    transient_storage: &mut HashMap<EVMAddress, HashMap<EVMU256, EVMU256>>,
+) -> Result<(), InstructionResult> {
+    if op == TLOAD {
+        let key = stack.pop()?;
+        let value = transient_storage
+            .get(&address)
+            .and_then(|slots| slots.get(&key))
+            .cloned()
+            .unwrap_or(EVMU256::ZERO);
+        return stack.push(value);
+    }
+    if is_static {
+        return Err(InstructionResult::StateChangeDuringStaticCall);
+    }
+    let (key, value) = match (stack.pop(), stack.pop()) {
+        (Ok(key), Ok(value)) => (key, value),
+        _ => return Err(InstructionResult::StackUnderflow),
+    };
+    transient_storage.entry(address).or_default().insert(key, value);
+    Ok(())
+}
+
+/// Whether the address appears in the data (e.g., as an argument in calldata)
+pub fn contains_address(data: &[u8], address: &EVMAddress) -> bool {
+    data.windows(20).any(|w| w == address.0)
+}
+
+/// Whether Cancun opcodes (blobs, transient storage) are enabled, they are emulated by
+/// FuzzHost on top of the latest spec
+pub fn is_cancun(spec_id: SpecId) -> bool {
+    SpecId::enabled(spec_id, SpecId::CANCUN)
+}
+
+/// Convert solc's `evmVersion` (e.g., shanghai, cancun) to the name of revm's SpecId
+pub fn evm_version_to_spec_id(evm_version: &str) -> Option<&'static str> {
+    Some(match evm_version.to_lowercase().as_str() {
+        "frontier" => "Frontier",
+        "homestead" => "Homestead",
+        "tangerinewhistle" | "tangerine" => "Tangerine",
+        "spuriousdragon" | "spurious" => "Spurious",
+        "byzantium" => "Byzantium",
+        "constantinople" => "Constantinople",
+        "petersburg" => "Petersburg",
+        "istanbul" => "Istanbul",
+        "muirglacier" => "MuirGlacier",
+        "berlin" => "Berlin",
+        "london" => "London",
+        "paris" | "merge" => "Merge",
+        "shanghai" => "Shanghai",
+        "cancun" => "Cancun",
+        "latest" => "Latest",
+        _ => return None,
+    })
+}
 
 pub struct FuzzHost<VS, I, S>
 where
@@ -154,6 +235,57 @@ where
     pub spec_id: SpecId,
     /// Precompiles
     pub precompiles: Precompiles,
+    /// Blob hashes and blob base fee of current transaction
+    pub blob_env: BlobEnv,
+    /// EIP-7702 delegated EOAs (EOA -> delegated code address)
+    pub delegations: HashMap<EVMAddress, EVMAddress>,
+    /// Whether current execution checked whether a delegated EOA is an EOA
+    /// (i.e., msg.sender == tx.origin or extcodesize)
+    pub eoa_checked: bool,
+    /// Whether control is handed to a delegated EOA after it passes an EOA check
+    pub delegated_eoa_hit: bool,
+    /// Token hooks (ERC-777 / ERC-1363) implemented by the callers
+    pub token_callbacks: Option<TokenCallbacks>,
+    /// Return data of the hook that leaked control, returned once execution resumes
+    pub leak_return: Option<Vec<u8>>,
+    /// Mocks of addresses without code called by the targets
+    pub mocks: Option<MockSynthesis>,
+    /// Chainlink feeds whose answers are moved by the fuzzer
+    pub chainlink: Option<ChainlinkFeeds>,
+    /// Custom chain spec (chain id, base fee, gas limit, precompiles, ...)
+    pub chain_spec: Option<ChainSpec>,
+    /// Bounds on the environment relative to the fork point
+    pub env_bounds: Option<EnvBounds>,
+    /// Precompiles enabled by chain spec, all precompiles are enabled if None
+    pub enabled_precompiles: Option<HashSet<EVMAddress>>,
+    /// User-defined precompiles
+    pub custom_precompiles: HashMap<EVMAddress, PrecompileHandler>,
+    /// zkSync Era compatibility (system contracts, address derivation)
+    pub zksync: Option<ZkSyncEnv>,
+    /// Gas ceiling of a transaction, gas is estimated and transactions exceeding it are halted
+    /// (no gas estimation if None)
+    pub gas_ceiling: Option<u64>,
+    /// Estimated gas used by current transaction
+    pub gas_used: u64,
+    /// Step / time budget of an execution, executions exceeding it are aborted (unbounded if None)
+    pub budget: Option<ExecBudget>,
+    /// Signers of modeled signatures, ecrecover is replaced by the signature model if set
+    pub signers: Option<Vec<EVMAddress>>,
+    /// Whether delegatecalls to attacker-controlled addresses run the hijack implementation
+    pub hijack_delegatecall: bool,
+    /// Calldata of current transaction, addresses in it are chosen by the attacker
+    pub tx_input: Bytes,
+    /// Whether the instruction of current step is emulated (not supported by revm), the next
+    /// instruction is then stepped right away
+    pub emulated: bool,
+    /// Events emitted by current transaction (topics, data), recorded if Some
+    pub recorded_logs: Option<Vec<(Vec<B256>, Bytes)>>,
+    /// Set by middlewares to revert the current call frame at the current instruction
+    pub veto: bool,
+    /// EOF contracts, executed on the image of their code sections
+    pub eof: HashMap<EVMAddress, Rc<EofContainer>>,
+    /// Return stacks (CALLF / RETF) of the call frames executing EOF code, by interpreter
+    pub eof_return_stacks: HashMap<usize, Vec<usize>>,
 }
 
 impl<VS, I, S> Debug for FuzzHost<VS, I, S>
@@ -224,6 +356,30 @@ where
             work_dir: self.work_dir.clone(),
             spec_id: self.spec_id.clone(),
             precompiles: Precompiles::default(),
+            blob_env: self.blob_env.clone(),
+            delegations: self.delegations.clone(),
+            eoa_checked: self.eoa_checked,
+            delegated_eoa_hit: self.delegated_eoa_hit,
+            token_callbacks: self.token_callbacks.clone(),
+            leak_return: self.leak_return.clone(),
+            mocks: self.mocks.clone(),
+            chainlink: self.chainlink.clone(),
+            chain_spec: self.chain_spec.clone(),
+            env_bounds: self.env_bounds.clone(),
+            enabled_precompiles: self.enabled_precompiles.clone(),
+            custom_precompiles: self.custom_precompiles.clone(),
+            zksync: self.zksync.clone(),
+            gas_ceiling: self.gas_ceiling,
+            gas_used: 0,
+            budget: self.budget.clone(),
+            signers: self.signers.clone(),
+            hijack_delegatecall: self.hijack_delegatecall,
+            tx_input: self.tx_input.clone(),
+            emulated: false,
+            recorded_logs: None,
+            veto: false,
+            eof: self.eof.clone(),
+            eof_return_stacks: Default::default(),
         }
     }
 }
@@ -278,6 +434,30 @@ where
             work_dir: workdir.clone(),
             spec_id: SpecId::LATEST,
             precompiles: Default::default(),
+            blob_env: Default::default(),
+            delegations: Default::default(),
+            eoa_checked: false,
+            delegated_eoa_hit: false,
+            token_callbacks: None,
+            leak_return: None,
+            mocks: None,
+            chainlink: None,
+            chain_spec: None,
+            env_bounds: None,
+            enabled_precompiles: None,
+            custom_precompiles: HashMap::new(),
+            zksync: None,
+            gas_ceiling: None,
+            gas_used: 0,
+            budget: None,
+            signers: None,
+            hijack_delegatecall: false,
+            tx_input: Bytes::new(),
+            emulated: false,
+            recorded_logs: None,
+            veto: false,
+            eof: Default::default(),
+            eof_return_stacks: Default::default(),
         };
         // ret.env.block.timestamp = EVMU256::max_value();
         ret
@@ -287,6 +467,26 @@ where
         self.spec_id = SpecId::from(spec_id.as_str());
     }
 
+    pub fn set_chain_spec(&mut self, chain_spec: ChainSpec) {
+        self.enabled_precompiles = chain_spec.enabled_precompiles();
+        if self.enabled_precompiles.is_some() {
+            self.precompiles = Precompiles::latest().clone();
+        }
+        self.chain_spec = Some(chain_spec);
+    }
+
+    /// Register a user-defined precompile at `address`, calls to it are handled by `handler`
+    pub fn register_precompile(&mut self, address: EVMAddress, handler: PrecompileHandler) {
+        self.custom_precompiles.insert(address, handler);
+    }
+
+    fn is_enabled_precompile(&self, address: EVMAddress) -> bool {
+        match &self.enabled_precompiles {
+            Some(enabled) => enabled.contains(&address) && self.precompiles.contains(&address),
+            None => is_precompile(address, self.precompiles.len()),
+        }
+    }
+
     /// custom spec id run_inspect
     pub fn run_inspect(
         &mut self,
@@ -306,10 +506,48 @@ where
             SpecId::LONDON => interp.run_inspect::<S, FuzzHost<VS, I, S>, LondonSpec>(self, state),
             SpecId::MERGE => interp.run_inspect::<S, FuzzHost<VS, I, S>, MergeSpec>(self, state),
             SpecId::SHANGHAI => interp.run_inspect::<S, FuzzHost<VS, I, S>, ShanghaiSpec>(self, state),
+            // Cancun opcodes are emulated in step
+            SpecId::CANCUN => interp.run_inspect::<S, FuzzHost<VS, I, S>, LatestSpec>(self, state),
             _=> interp.run_inspect::<S, FuzzHost<VS, I, S>, LatestSpec>(self, state),
         }
     }
 
+    pub fn set_gas_ceiling(&mut self, gas_ceiling: u64) {
+        self.gas_ceiling = Some(gas_ceiling);
+    }
+
+    /// Model signatures verified by ecrecover, fresh signatures are signed by one of `signers`
+    pub fn set_signers(&mut self, signers: Vec<EVMAddress>) {
+        self.signers = Some(signers);
+    }
+
+    /// Whether the address is chosen by the attacker, i.e., taken from the calldata of current
+    /// transaction or from storage the calldata of an earlier one was written to, and the attacker
+    /// can deploy code at it, i.e., one of the callers, or an address without code (excluding the
+    /// zero address and precompiles). Hard-coded addresses without code (e.g., a library not
+    /// deployed) are not attacker-controlled.
+    fn is_attacker_controlled(&self, address: &EVMAddress, state: &S) -> bool {
+        let chosen = contains_address(&self.tx_input, address) || self.evmstate.attacker_addresses.contains(address);
+        let deployable = state.has_caller(address)
+            || (!self.code.contains_key(address)
+                && *address != EVMAddress::zero()
+                && !is_precompile(*address, self.precompiles.len()));
+        chosen && deployable
+    }
+
+    /// Whether current execution is aborted for exceeding its step / time budget
+    pub fn budget_exceeded(&self) -> bool {
+        self.budget.as_ref().map_or(false, |budget| budget.exceeded)
+    }
+
+    /// Whether current transaction is halted for exceeding the gas ceiling
+    pub fn gas_exceeded(&self) -> bool {
+        match self.gas_ceiling {
+            Some(ceiling) => self.gas_used > ceiling,
+            None => false,
+        }
+    }
+
     pub fn remove_all_middlewares(&mut self) {
         self.middlewares_enabled = false;
         self.middlewares.deref().borrow_mut().clear();
@@ -396,6 +634,34 @@ where
         }
     }
 
+    /// Output of a call to a Chainlink feed, with the answer moved if the fuzzer moved the feed
+    fn move_feed_answer(
+        &mut self,
+        call: FeedCall,
+        feed: EVMAddress,
+        res: (InstructionResult, Gas, Bytes),
+    ) -> (InstructionResult, Gas, Bytes) {
+        let chainlink = self.chainlink.as_mut().unwrap();
+        chainlink.in_feed = false;
+        let idx = chainlink.observe(feed);
+        if !matches!(res.0, Return | Continue | InstructionResult::Stop) {
+            return res;
+        }
+        match ChainlinkFeeds::get_move(&self.evmstate, idx) {
+            Some((percent, staleness)) => (
+                res.0,
+                res.1,
+                Bytes::from(call.perturb(&res.2, percent, staleness, self.env.block.timestamp)),
+            ),
+            None => res,
+        }
+    }
+
+    /// Set EIP-7702 delegation designator of an EOA
+    pub fn set_delegation(&mut self, eoa: EVMAddress, target: EVMAddress) {
+        self.delegations.insert(eoa, target);
+    }
+
     pub fn set_codedata(&mut self, address: EVMAddress, mut code: Bytecode) {
         self.setcode_data.insert(address, code);
     }
@@ -405,6 +671,16 @@ where
     }
 
     pub fn set_code(&mut self, address: EVMAddress, mut code: Bytecode, state: &mut S) {
+        // delegated EOAs (e.g., forked from chain) are modeled, their code is the designator
+        if let Some(target) = delegation_designator(code.bytes()) {
+            self.set_delegation(address, target);
+            return;
+        }
+        // middlewares see the EOF container, the image of its code sections is executed
+        let eof = match is_eof(code.bytes()) {
+            true => EofContainer::parse(code.bytes()).map(Rc::new),
+            false => None,
+        };
         unsafe {
             if self.middlewares_enabled {
                 match self.flashloan_middleware.clone() {
@@ -424,6 +700,15 @@ where
                 }
             }
         }
+        match eof {
+            Some(container) => {
+                code = Bytecode::new_raw(Bytes::from(container.image.clone()));
+                self.eof.insert(address, container);
+            }
+            None => {
+                self.eof.remove(&address);
+            }
+        }
         assert!(self
             .code
             .insert(
@@ -433,6 +718,19 @@ where
             .is_none());
     }
 
+    /// Init code to execute for deploying at `address`, the image of the code sections of EOF
+    /// init containers
+    pub fn load_init_code(&mut self, address: EVMAddress, init_code: Bytes) -> Bytecode {
+        if is_eof(&init_code) {
+            if let Some(container) = EofContainer::parse(&init_code) {
+                let image = Bytecode::new_raw(Bytes::from(container.image.clone()));
+                self.eof.insert(address, Rc::new(container));
+                return image;
+            }
+        }
+        Bytecode::new_raw(init_code)
+    }
+
     pub fn find_static_call_read_slot(
         &self,
         address: EVMAddress,
@@ -534,13 +832,56 @@ where
             return middleware_result.unwrap();
         }
 
-        // if calling sender, then definitely control leak
-        if self.origin == input.contract {
+        // delegatecall to an attacker-controlled address, take over the caller
+        if self.hijack_delegatecall
+            && matches!(input.context.scheme, CallScheme::DelegateCall | CallScheme::CallCode)
+            && self.is_attacker_controlled(&input.contract, state)
+        {
+            let (slot, value) = hijack_storage_write(input.contract);
+            match self.evmstate.get_mut(&input.context.address) {
+                Some(account) => {
+                    account.insert(slot, value);
+                }
+                None => {
+                    self.evmstate.insert(input.context.address, HashMap::from([(slot, value)]));
+                }
+            }
+            return (Continue, Gas::new(0), Bytes::new());
+        }
+
+        // calling delegated EOA that passed the EOA check earlier
+        if self.delegations.contains_key(&input.contract) && self.eoa_checked {
+            self.delegated_eoa_hit = true;
+        }
+
+        // if calling sender or delegated EOAs, then definitely control leak
+        if self.origin == input.contract || self.delegations.contains_key(&input.contract) {
             record_func_hash!();
             // println!("call self {:?} -> {:?} with {:?}", input.context.caller, input.contract, hex::encode(input.input.clone()));
             return (ControlLeak, Gas::new(0), Bytes::new());
         }
 
+        // token hooks of the callers, reenter during the transfer
+        if let Some(ret) = self
+            .token_callbacks
+            .as_ref()
+            .and_then(|callbacks| callbacks.hook_return(&input.contract, &input.input))
+        {
+            record_func_hash!();
+            self.leak_return = Some(ret);
+            return (ControlLeak, Gas::new(0), Bytes::new());
+        }
+
+        // missing dependency, the return data is provided by the fuzzer
+        let missing = !self.code.contains_key(&input.context.code_address);
+        if let Some(mocks) = self.mocks.as_mut() {
+            if missing && input.input.len() >= 4 && mocks.is_mocked(&input.context.code_address) {
+                mocks.observe(input.context.code_address, &input.input);
+                record_func_hash!();
+                return (ControlLeak, Gas::new(0), Bytes::new());
+            }
+        }
+
         let mut input_seq = input.input.to_vec();
 
         // check whether the whole CALLDATAVALUE can be arbitrary
@@ -692,13 +1033,15 @@ macro_rules! u256_to_u8 {
 }
 
 
-impl<VS, I, S> Host<S> for FuzzHost<VS, I, S>
+impl<VS, I, S> FuzzHost<VS, I, S>
 where
     S: State +HasRand + HasCaller<EVMAddress> + Debug + Clone + HasCorpus<I> + HasMetadata + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput> +  'static,
     I: VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
     VS: VMStateT,
 {
-    fn step(&mut self, interp: &mut Interpreter, state: &mut S) -> InstructionResult {
+    /// Step processing of the instruction about to execute, instructions revm doesn't support are
+    /// emulated here (setting [`FuzzHost::emulated`])
+    fn step_instruction(&mut self, interp: &mut Interpreter, state: &mut S) -> InstructionResult {
         unsafe {
             if self.middlewares_enabled {
                 match self.flashloan_middleware.clone() {
@@ -726,6 +1069,90 @@ where
                         self.set_code(address.clone(), code.clone(), state);
                     }
                 }
+
+                if self.veto {
+                    self.veto = false;
+                    return InstructionResult::Revert;
+                }
+            }
+
+            if let Some(ceiling) = self.gas_ceiling {
+                if !IS_FAST_CALL_STATIC {
+                    self.gas_used += opcode_gas(interp, &self.evmstate);
+                    if self.gas_used > ceiling {
+                        return InstructionResult::OutOfGas;
+                    }
+                }
+            }
+
+            if let Some(budget) = self.budget.as_mut() {
+                if !IS_FAST_CALL_STATIC && budget.step() {
+                    return InstructionResult::OutOfGas;
+                }
+            }
+
+            // EOF instructions are not supported by revm either, emulate them on the image of
+            // the code sections (see `crate::evm::eof`)
+            let eof = self.eof.get(&interp.contract.address).cloned();
+            let frame = &*interp as *const Interpreter as usize;
+            if eof.is_some() && interp.program_counter() == 0 {
+                self.eof_return_stacks.remove(&frame);
+            }
+
+            // EOF and Cancun opcodes are not supported by revm yet, emulate them here (when fuzzing
+            // with the latest spec) and move on to the next instruction
+            if let Some(container) = eof.as_ref().filter(|_| is_eof_instruction(*interp.instruction_pointer)) {
+                let return_stack = self.eof_return_stacks.entry(frame).or_default();
+                match container.step(interp, return_stack) {
+                    Ok(Some((pc, to))) => {
+                        // conditional relative jump, counted like JUMPI
+                        let idx = (pc * to) % MAP_SIZE;
+                        if JMP_MAP[idx] == 0 {
+                            self.coverage_changed = true;
+                        }
+                        if JMP_MAP[idx] < 255 {
+                            JMP_MAP[idx] += 1;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(result) => return result,
+                }
+                self.emulated = true;
+                return Continue;
+            }
+            if is_cancun(self.spec_id)
+                && matches!(*interp.instruction_pointer, BLOBHASH | BLOBBASEFEE | TLOAD | TSTORE)
+            {
+                let address = interp.contract.address;
+                self.access_pattern
+                    .deref()
+                    .borrow_mut()
+                    .decode_instruction(interp);
+                let op = *interp.instruction_pointer;
+                let result = match op {
+                    BLOBHASH => interp.stack.pop().and_then(|idx| {
+                        let hash = if idx < EVMU256::from(self.blob_env.blob_hashes.len()) {
+                            self.blob_env.blob_hashes[as_u64(idx) as usize]
+                        } else {
+                            EVMU256::ZERO
+                        };
+                        interp.stack.push(hash)
+                    }),
+                    BLOBBASEFEE => interp.stack.push(self.blob_env.blob_basefee),
+                    _ => emulate_transient(
+                        op,
+                        address,
+                        &mut interp.stack,
+                        interp.is_static,
+                        &mut self.evmstate.transient_storage,
+                    ),
+                };
+                if let Err(e) = result {
+                    return e;
+                }
+                interp.instruction_pointer = interp.instruction_pointer.add(1);
+                self.emulated = true;
+                return Continue;
             }
 
             if IS_FAST_CALL_STATIC {
@@ -737,6 +1164,15 @@ where
                     interp.stack.data()[interp.stack.len() - 1 - $idx]
                 };
             }
+
+            // EIP-7702: record whether delegated EOAs are checked against being EOA with
+            // msg.sender == tx.origin (EQ), extcodesize checks reject them (the designator is code)
+            if self.delegations.len() > 0 && *interp.instruction_pointer == 0x14 {
+                let v = fast_peek!(0);
+                if v == fast_peek!(1) && self.delegations.contains_key(&convert_u256_to_h160(v)) {
+                    self.eoa_checked = true;
+                }
+            }
             match *interp.instruction_pointer {
                 // 0xfd => {
                 //     println!("fd {} @ {:?}", interp.program_counter(), interp.contract.address);
@@ -878,6 +1314,24 @@ where
         }
         return Continue;
     }
+}
+
+impl<VS, I, S> Host<S> for FuzzHost<VS, I, S>
+where
+    S: State +HasRand + HasCaller<EVMAddress> + Debug + Clone + HasCorpus<I> + HasMetadata + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput> +  'static,
+    I: VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+    VS: VMStateT,
+{
+    fn step(&mut self, interp: &mut Interpreter, state: &mut S) -> InstructionResult {
+        // emulated instructions are stepped like any other instruction (middlewares, gas,
+        // coverage) before moving on to the next one
+        loop {
+            let ret = self.step_instruction(interp, state);
+            if ret != Continue || !std::mem::take(&mut self.emulated) {
+                return ret;
+            }
+        }
+    }
 
     fn step_end(&mut self, _interp: &mut Interpreter, _ret: InstructionResult, _: &mut S) -> InstructionResult {
         return Continue;
@@ -901,19 +1355,35 @@ where
         )
     }
 
-    fn balance(&mut self, _address: EVMAddress) -> Option<(EVMU256, bool)> {
-        // println!("balance");
-
-        Some((EVMU256::MAX, true))
+    fn balance(&mut self, address: EVMAddress) -> Option<(EVMU256, bool)> {
+        // accounts whose ETH is not tracked have an unlimited balance
+        Some((native_balance(&self.evmstate, &address).unwrap_or(EVMU256::MAX), true))
     }
 
     fn code(&mut self, address: EVMAddress) -> Option<(Arc<BytecodeLocked>, bool)> {
         // println!("code");
         match self.code.get(&address) {
             Some(code) => Some((code.clone(), true)),
-            None => Some((Arc::new(
-                BytecodeLocked::default()
-            ), true)),
+            None => match self.delegations.get(&address) {
+                // EIP-7702 delegation designator: 0xef0100 || address
+                Some(target) => Some((Arc::new(
+                    BytecodeLocked::try_from(to_analysed(Bytecode::new_raw(Bytes::from(
+                        delegation_code(target)
+                    )))).unwrap()
+                ), true)),
+                // the registry is modeled when not deployed, it must look like a contract
+                None if self.token_callbacks.is_some() && address == TokenCallbacks::registry() => Some((Arc::new(
+                    BytecodeLocked::try_from(to_analysed(Bytecode::new_raw(Bytes::from(vec![0x00])))).unwrap()
+                ), true)),
+                // mocked dependencies must look like contracts
+                None if self.mocks.as_ref().map_or(false, |mocks| mocks.is_mocked(&address))
+                    && !is_precompile(address, self.precompiles.len()) => Some((Arc::new(
+                    BytecodeLocked::try_from(to_analysed(Bytecode::new_raw(Bytes::from(vec![0x00])))).unwrap()
+                ), true)),
+                None => Some((Arc::new(
+                    BytecodeLocked::default()
+                ), true)),
+            },
         }
     }
 
@@ -954,11 +1424,22 @@ where
                 self.evmstate.insert(address, account);
             }
         };
+        // addresses from the calldata written to storage stay attacker-controlled
+        if self.hijack_delegatecall && value != EVMU256::ZERO && value.leading_zeros() >= 96 {
+            let written = convert_u256_to_h160(value);
+            if contains_address(&self.tx_input, &written) {
+                self.evmstate.attacker_addresses.insert(written);
+            }
+        }
 
         Some((EVMU256::from(0), EVMU256::from(0), EVMU256::from(0), true))
     }
 
     fn log(&mut self, _address: EVMAddress, _topics: Vec<B256>, _data: Bytes) {
+        if let Some(logs) = &mut self.recorded_logs {
+            logs.push((_topics.clone(), _data.clone()));
+        }
+
         // flag check
         if  _topics.len() == 1 {
             let current_flag = (*_topics.last().unwrap()).0;
@@ -990,7 +1471,7 @@ where
                 .duration_since(UNIX_EPOCH)
                 .expect("Time went backwards");
             let timestamp = now.as_nanos();
-            println!("log@{} {:?}", timestamp, hex::encode(_data));
+            debug!("log@{} {:?}", timestamp, hex::encode(_data));
         }
     }
 
@@ -1006,11 +1487,29 @@ where
         unsafe {
             if unsafe {CONCRETE_CREATE || IN_DEPLOY} {
                 // todo: use nonce + hash instead
-                let r_addr = generate_random_address(state);
+                let r_addr = match &self.zksync {
+                    // Era derives addresses from the hash of zkEVM bytecode,
+                    // we use the hash of the init code instead
+                    Some(_) => match inputs.scheme {
+                        CreateScheme::Create2 { salt } => create2_address(
+                            inputs.caller,
+                            salt,
+                            keccak256(&inputs.init_code).0,
+                            &[],
+                        ),
+                        CreateScheme::Create => {
+                            let nonce = self.evmstate.deployment_nonces.entry(inputs.caller).or_insert(0);
+                            *nonce += 1;
+                            create_address(inputs.caller, *nonce - 1)
+                        }
+                    },
+                    None => generate_random_address(state),
+                };
+                let init_code = self.load_init_code(r_addr, inputs.init_code.clone());
                 let mut interp = Interpreter::new(
                     Contract::new_with_context(
                         Bytes::new(),
-                        Bytecode::new_raw(inputs.init_code.clone()),
+                        init_code,
                         &CallContext {
                             address: r_addr,
                             caller: inputs.caller,
@@ -1045,7 +1544,7 @@ where
                         }
 
                         if unknown_sigs >= sigs.len() / 30 {
-                            println!("Too many unknown function signature for newly created contract, we are going to decompile this contract using Heimdall");
+                            info!("Too many unknown function signature for newly created contract, we are going to decompile this contract using Heimdall");
                             let abis = fetch_abi_heimdall(contract_code_str)
                                 .iter()
                                 .map(|abi| {
@@ -1101,6 +1600,11 @@ where
                                     direct_data: Default::default(),
                                     randomness: vec![0],
                                     repeat: 1,
+                                    blob_env: Default::default(),
+                                    victim: false,
+                                    via_intermediary: false,
+                                    advance: Default::default(),
+                                    force_feed: EVMU256::ZERO,
                                 };
                                 add_corpus(self, state, &input);
                             });
@@ -1133,14 +1637,138 @@ where
     }
 
     fn call(&mut self, input: &mut CallInputs, state: &mut S) -> (InstructionResult, Gas, Bytes) {
-        if is_precompile(input.contract, self.precompiles.len()) {
+        if let Some(zksync) = &self.zksync {
+            if is_system_contract(input.contract) && !self.code.contains_key(&input.contract) {
+                return match zksync.call_system_contract(
+                    input.contract,
+                    &input.input,
+                    self.env.block.number,
+                    self.env.block.timestamp,
+                    self.env.tx.gas_price,
+                ) {
+                    Some(out) => (InstructionResult::Return, Gas::new(0), out),
+                    None => (InstructionResult::Revert, Gas::new(0), Bytes::new()),
+                };
+            }
+        }
+
+        if let Some(handler) = self.custom_precompiles.get(&input.contract).cloned() {
+            let out = handler.deref().borrow_mut().call(&input.input);
+            return match out {
+                Some(out) => (InstructionResult::Return, Gas::new(0), Bytes::from(out)),
+                None => (InstructionResult::Revert, Gas::new(0), Bytes::new()),
+            };
+        }
+
+        if let Some(signers) = &self.signers {
+            if input.contract == fixed_address(ECRECOVER_ADDRESS) {
+                let out = ecrecover(
+                    signers,
+                    &mut self.evmstate.signatures,
+                    &input.input,
+                    EVMU256::from(self.env.cfg.chain_id),
+                    self.origin,
+                );
+                return (InstructionResult::Return, Gas::new(0), Bytes::from(out));
+            }
+        }
+
+        if let Some(callbacks) = &self.token_callbacks {
+            if input.contract == TokenCallbacks::registry() {
+                let deployed = self.code.contains_key(&input.contract);
+                if let Some(out) = callbacks.registry_call(&input.input, deployed) {
+                    return (InstructionResult::Return, Gas::new(0), out);
+                }
+            }
+        }
+
+        if self.is_enabled_precompile(input.contract) {
             return self.call_precompile(input, state);
         }
 
-        if unsafe { IS_FAST_CALL_STATIC } {
+        // answers of Chainlink feeds are moved by the fuzzer
+        let feed_call = match &mut self.chainlink {
+            Some(chainlink) if !chainlink.in_feed => FeedCall::from_input(&input.input).map(|call| {
+                chainlink.in_feed = true;
+                call
+            }),
+            _ => None,
+        };
+
+        let res = if unsafe { IS_FAST_CALL_STATIC } {
             self.call_forbid_control_leak(input, state)
         } else {
             self.call_allow_control_leak(input, state)
+        };
+
+        match feed_call {
+            Some(call) => self.move_feed_answer(call, input.contract, res),
+            None => res,
         }
     }
 }
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evm_version_to_spec_id() {
+        let spec_id = |v: &str| evm_version_to_spec_id(v).map(SpecId::from);
+        assert_eq!(spec_id("cancun"), Some(SpecId::CANCUN));
+        assert_eq!(spec_id("Shanghai"), Some(SpecId::SHANGHAI));
+        assert_eq!(spec_id("paris"), Some(SpecId::MERGE));
+        assert_eq!(spec_id("latest"), Some(SpecId::LATEST));
+        assert_eq!(spec_id("prague2"), None);
+    }
+
+    #[test]
+    fn test_is_cancun() {
+        assert!(is_cancun(SpecId::CANCUN));
+        assert!(is_cancun(SpecId::LATEST));
+        assert!(!is_cancun(SpecId::SHANGHAI));
+        assert!(!is_cancun(SpecId::LONDON));
+    }
+
+    #[test]
+    fn test_contains_address() {
+        let address = EVMAddress::from_slice(&[7; 20]);
+        let mut calldata = vec![0xab, 0xcd, 0xef, 0x01];
+        calldata.extend([vec![0; 12], address.0.to_vec()].concat());
+        assert!(contains_address(&calldata, &address));
+        assert!(!contains_address(&calldata, &EVMAddress::from_slice(&[8; 20])));
+        assert!(!contains_address(&address.0[..19], &address));
+    }
+
+    #[test]
+    fn test_emulate_transient() {
+        let (lock, other) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let mut transient_storage = HashMap::new();
+        let mut stack = Stack::new();
+
+        // tstore(0, 1)
+        stack.push(EVMU256::from(1)).unwrap();
+        stack.push(EVMU256::ZERO).unwrap();
+        assert_eq!(emulate_transient(TSTORE, lock, &mut stack, false, &mut transient_storage), Ok(()));
+        assert_eq!(stack.len(), 0);
+
+        // tload(0) of the contract and of another contract
+        stack.push(EVMU256::ZERO).unwrap();
+        assert_eq!(emulate_transient(TLOAD, lock, &mut stack, false, &mut transient_storage), Ok(()));
+        assert_eq!(stack.pop(), Ok(EVMU256::from(1)));
+        stack.push(EVMU256::ZERO).unwrap();
+        assert_eq!(emulate_transient(TLOAD, other, &mut stack, false, &mut transient_storage), Ok(()));
+        assert_eq!(stack.pop(), Ok(EVMU256::ZERO));
+
+        // no writes in static calls, nor with a missing operand
+        stack.push(EVMU256::ZERO).unwrap();
+        assert_eq!(
+            emulate_transient(TSTORE, lock, &mut stack, true, &mut transient_storage),
+            Err(InstructionResult::StateChangeDuringStaticCall)
+        );
+        assert_eq!(
+            emulate_transient(TSTORE, lock, &mut stack, false, &mut transient_storage),
+            Err(InstructionResult::StackUnderflow)
+        );
+        assert!(emulate_transient(TLOAD, lock, &mut stack, false, &mut transient_storage).is_err());
+    }
+}
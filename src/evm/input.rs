@@ -1,7 +1,10 @@
 use crate::evm::abi::{AEmpty, AUnknown, BoxedABI};
+use crate::evm::call_value::pick_value;
+use crate::evm::middlewares::native_flow::is_tracked;
 use crate::mutation_utils::byte_mutator;
 use crate::evm::mutator::AccessPattern;
-use crate::evm::types::{EVMAddress, EVMExecutionResult, EVMStagedVMState, EVMU256, EVMU512};
+use crate::evm::signature::set_signature_args;
+use crate::evm::types::{fixed_address, EVMAddress, EVMExecutionResult, EVMStagedVMState, EVMU256, EVMU512};
 use crate::evm::vm::EVMState;
 use crate::input::{ConciseSerde, VMInputT};
 use crate::state::{HasCaller, HasItyState};
@@ -12,7 +15,7 @@ use libafl::inputs::Input;
 use libafl::mutators::MutationResult;
 use libafl::prelude::{HasBytesVec, HasMaxSize, HasMetadata, HasRand, Rand, State};
 use primitive_types::U512;
-use revm_primitives::Env;
+use revm_primitives::{B256, Env};
 use serde::{Deserialize, Deserializer, Serialize};
 
 use bytes::Bytes;
@@ -43,6 +46,144 @@ impl Default for EVMInputTy {
     }
 }
 
+/// Cancun blob environment (EIP-4844 / EIP-7516), not yet part of revm's [`Env`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlobEnv {
+    /// Versioned hashes of the blobs of the transaction, returned by BLOBHASH
+    pub blob_hashes: Vec<EVMU256>,
+    /// Blob base fee of the block, returned by BLOBBASEFEE
+    pub blob_basefee: EVMU256,
+}
+
+impl BlobEnv {
+    /// Add a new blob hash or mutate an existing one, left unchanged when the mutation is skipped
+    pub fn mutate_blob_hash<S>(&mut self, state_: &mut S) -> MutationResult
+    where
+        S: State + HasRand + HasMetadata,
+    {
+        let pushed = self.blob_hashes.len() < MAX_BLOBS_PER_TX
+            && (self.blob_hashes.is_empty() || state_.rand_mut().below(2) == 0);
+        if pushed {
+            self.blob_hashes.push(EVMU256::ZERO);
+        }
+        let idx = state_.rand_mut().below(self.blob_hashes.len() as u64) as usize;
+        let mut input_vec = self.blob_hashes[idx].to_be_bytes::<32>().to_vec();
+        let mut wrapper = MutatorInput::new(&mut input_vec);
+        if byte_mutator(state_, &mut wrapper, None) == MutationResult::Skipped {
+            if pushed {
+                self.blob_hashes.pop();
+            }
+            return MutationResult::Skipped;
+        }
+        // versioned hashes start with the KZG version byte
+        input_vec[0] = 0x01;
+        self.blob_hashes[idx] = EVMU256::try_from_be_slice(input_vec.as_slice()).unwrap();
+        MutationResult::Mutated
+    }
+}
+
+/// Blocks and time advanced before a transaction (or by a sequence of transactions)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockAdvance {
+    pub blocks: u64,
+    pub seconds: u64,
+}
+
+impl BlockAdvance {
+    pub fn is_zero(&self) -> bool {
+        self.blocks == 0 && self.seconds == 0
+    }
+
+    pub fn add(&self, other: BlockAdvance) -> BlockAdvance {
+        BlockAdvance {
+            blocks: self.blocks.saturating_add(other.blocks),
+            seconds: self.seconds.saturating_add(other.seconds),
+        }
+    }
+
+    /// An interesting duration, a few blocks, or no advance
+    pub fn random<S: HasRand>(state: &mut S) -> BlockAdvance {
+        match state.rand_mut().below(4) {
+            0 => BlockAdvance::default(),
+            1 => {
+                let blocks = 1 + state.rand_mut().below(16);
+                BlockAdvance {
+                    blocks,
+                    seconds: blocks * SECONDS_PER_BLOCK,
+                }
+            }
+            _ => {
+                let seconds = INTERESTING_DURATIONS
+                    [state.rand_mut().below(INTERESTING_DURATIONS.len() as u64) as usize];
+                BlockAdvance {
+                    blocks: (seconds / SECONDS_PER_BLOCK).max(1),
+                    seconds,
+                }
+            }
+        }
+    }
+}
+
+/// Seconds per block when advancing blocks
+pub const SECONDS_PER_BLOCK: u64 = 12;
+
+/// Durations (in seconds) time-dependent logic usually relies on (e.g., TWAP windows, epochs,
+/// vesting periods)
+pub const INTERESTING_DURATIONS: [u64; 8] = [12, 60, 600, 3600, 86400, 7 * 86400, 30 * 86400, 365 * 86400];
+
+/// Maximum amount of blobs per transaction
+pub const MAX_BLOBS_PER_TX: usize = 6;
+
+/// Sender of victim transactions, i.e., transactions of a user (e.g., a swap) that the
+/// attacker can front-run and back-run
+pub const VICTIM_CALLER: &str = "000000000000000000000000000000000071c714";
+
+/// Whether the mutator generates victim transactions
+pub static mut VICTIM_TXS: bool = false;
+
+/// Contract controlled by the attacker forwarding transactions of callers (e.g., a phishing
+/// contract), so that msg.sender differs from tx.origin
+pub const INTERMEDIARY: &str = "0000000000000000000000000000000000f0a4d3";
+
+/// Whether the mutator generates transactions sent through [`INTERMEDIARY`]
+pub static mut INTERMEDIARY_TXS: bool = false;
+
+/// Actors of scenarios (role, address), transactions they send are labeled with their role
+pub static mut ACTORS: Vec<(String, EVMAddress)> = vec![];
+
+/// Default upper bound of the gas price (1000 gwei)
+pub const DEFAULT_MAX_GAS_PRICE: u128 = 1_000_000_000_000;
+
+/// Bounds (in wei) of the gas price and priority fee chosen by the mutator (`--gas-price-bounds`)
+pub static mut GAS_PRICE_BOUNDS: (u128, u128) = (0, DEFAULT_MAX_GAS_PRICE);
+
+/// Parse gas price bounds like `1000000000:500000000000` (min:max, in wei)
+pub fn parse_gas_price_bounds(spec: &str) -> (u128, u128) {
+    let (min, max) = spec
+        .split_once(':')
+        .unwrap_or_else(|| panic!("invalid gas price bounds {}, expected <min>:<max>", spec));
+    let min = min.trim().parse::<u128>().expect("invalid minimum gas price");
+    let max = max.trim().parse::<u128>().expect("invalid maximum gas price");
+    assert!(min <= max, "minimum gas price exceeds the maximum");
+    (min, max)
+}
+
+/// A gas price within `bounds` and, for EIP-1559 fees, a priority fee up to it
+pub fn random_fee<S: HasRand>(state: &mut S, (min, max): (u128, u128)) -> (EVMU256, Option<EVMU256>) {
+    // the bounds themselves are the most interesting prices
+    let pick = |state: &mut S, low: u128, high: u128| match state.rand_mut().below(4) {
+        0 => low,
+        1 => high,
+        _ => low + state.rand_mut().next() as u128 % (high - low).saturating_add(1),
+    };
+    let price = pick(state, min, max);
+    let priority_fee = match state.rand_mut().below(2) {
+        0 => None,
+        _ => Some(EVMU256::from(pick(state, 0, price))),
+    };
+    (EVMU256::from(price), priority_fee)
+}
+
 /// EVM Input Trait
 pub trait EVMInputT {
     /// Set the contract and ABI
@@ -51,6 +192,9 @@ pub trait EVMInputT {
     /// Set the caller
     fn set_caller_evm(&mut self, caller: EVMAddress);
 
+    /// Get the contract
+    fn get_contract_evm(&self) -> EVMAddress;
+
     /// Get the ABI encoded input
     fn to_bytes(&self) -> Vec<u8>;
 
@@ -60,6 +204,12 @@ pub trait EVMInputT {
     /// Get revm environment (block, timestamp, etc.) mutably
     fn get_vm_env_mut(&mut self) -> &mut Env;
 
+    /// Get the blob environment (blob hashes and blob base fee)
+    fn get_blob_env(&self) -> &BlobEnv;
+
+    /// Get the blob environment (blob hashes and blob base fee) mutably
+    fn get_blob_env_mut(&mut self) -> &mut BlobEnv;
+
     /// Get the access pattern of the input, used by the mutator to determine what to mutate
     fn get_access_pattern(&self) -> &Rc<RefCell<AccessPattern>>;
 
@@ -88,6 +238,30 @@ pub trait EVMInputT {
     fn set_liquidation_percent(&mut self, v: u8);
 
     fn get_repeat(&self) -> usize;
+
+    /// Whether the input is a victim transaction (sent by [`VICTIM_CALLER`])
+    fn is_victim(&self) -> bool;
+
+    /// Set whether the input is a victim transaction, also sets the caller for victim transactions
+    fn set_victim(&mut self, v: bool);
+
+    /// Whether the input is sent through [`INTERMEDIARY`] (i.e., msg.sender is the intermediary)
+    fn is_via_intermediary(&self) -> bool;
+
+    /// Set whether the input is sent through [`INTERMEDIARY`]
+    fn set_via_intermediary(&mut self, v: bool);
+
+    /// Get the blocks and time advanced before the transaction
+    fn get_advance(&self) -> BlockAdvance;
+
+    /// Set the blocks and time advanced before the transaction
+    fn set_advance(&mut self, v: BlockAdvance);
+
+    /// Get the ETH force-fed to the contract before the transaction
+    fn get_force_feed(&self) -> EVMU256;
+
+    /// Set the ETH force-fed to the contract before the transaction
+    fn set_force_feed(&mut self, v: EVMU256);
 }
 
 
@@ -140,6 +314,22 @@ pub struct EVMInput {
 
     /// Execute the transaction multiple times
     pub repeat: usize,
+
+    /// Blob hashes and blob base fee
+    pub blob_env: BlobEnv,
+
+    /// Whether this is a victim transaction
+    pub victim: bool,
+
+    /// Whether the transaction is sent through the intermediary contract
+    pub via_intermediary: bool,
+
+    /// Blocks and time advanced before the transaction
+    pub advance: BlockAdvance,
+
+    /// ETH force-fed to the contract before the transaction, without running its code (through
+    /// a selfdestruct or as the coinbase), zero if none
+    pub force_feed: EVMU256,
 }
 
 /// EVM Input Minimum for Deserializing
@@ -182,6 +372,26 @@ pub struct ConciseEVMInput {
 
     /// When to control leak, after `call_leak` number of calls
     pub call_leak: u32,
+
+    /// Blob hashes and blob base fee
+    #[serde(default)]
+    pub blob_env: BlobEnv,
+
+    /// Whether this is a victim transaction
+    #[serde(default)]
+    pub victim: bool,
+
+    /// Whether the transaction is sent through the intermediary contract
+    #[serde(default)]
+    pub via_intermediary: bool,
+
+    /// Blocks and time advanced before the transaction
+    #[serde(default)]
+    pub advance: BlockAdvance,
+
+    /// ETH force-fed to the contract before the transaction
+    #[serde(default)]
+    pub force_feed: EVMU256,
 }
 
 
@@ -207,7 +417,12 @@ impl ConciseEVMInput {
             call_leak: match execution_result.additional_info {
                 Some(ref info) => info[0] as u32,
                 None => u32::MAX
-            }
+            },
+            blob_env: input.get_blob_env().clone(),
+            victim: input.is_victim(),
+            via_intermediary: input.is_via_intermediary(),
+            advance: input.get_advance(),
+            force_feed: input.get_force_feed(),
         }
     }
 
@@ -230,6 +445,11 @@ impl ConciseEVMInput {
                 direct_data: Bytes::new(),
                 randomness: self.randomness.clone(),
                 repeat: self.repeat,
+                blob_env: self.blob_env.clone(),
+                victim: self.victim,
+                via_intermediary: self.via_intermediary,
+                advance: self.advance,
+                force_feed: self.force_feed,
             }, self.call_leak
         )
     }
@@ -316,6 +536,10 @@ impl EVMInputT for EVMInput {
         self.caller = caller;
     }
 
+    fn get_contract_evm(&self) -> EVMAddress {
+        self.contract
+    }
+
     fn to_bytes(&self) -> Vec<u8> {
         match self.data {
             Some(ref d) => d.get_bytes(),
@@ -331,6 +555,14 @@ impl EVMInputT for EVMInput {
         &self.env
     }
 
+    fn get_blob_env(&self) -> &BlobEnv {
+        &self.blob_env
+    }
+
+    fn get_blob_env_mut(&mut self) -> &mut BlobEnv {
+        &mut self.blob_env
+    }
+
     fn get_access_pattern(&self) -> &Rc<RefCell<AccessPattern>> {
         &self.access_pattern
     }
@@ -369,6 +601,41 @@ impl EVMInputT for EVMInput {
     fn get_repeat(&self) -> usize {
         self.repeat
     }
+
+    fn is_victim(&self) -> bool {
+        self.victim
+    }
+
+    fn set_victim(&mut self, v: bool) {
+        self.victim = v;
+        if v {
+            self.caller = fixed_address(VICTIM_CALLER);
+        }
+    }
+
+    fn is_via_intermediary(&self) -> bool {
+        self.via_intermediary
+    }
+
+    fn set_via_intermediary(&mut self, v: bool) {
+        self.via_intermediary = v;
+    }
+
+    fn get_advance(&self) -> BlockAdvance {
+        self.advance
+    }
+
+    fn set_advance(&mut self, v: BlockAdvance) {
+        self.advance = v;
+    }
+
+    fn get_force_feed(&self) -> EVMU256 {
+        self.force_feed
+    }
+
+    fn set_force_feed(&mut self, v: EVMU256) {
+        self.force_feed = v;
+    }
 }
 
 
@@ -474,22 +741,123 @@ impl EVMInput {
     impl_env_mutator_u256!(number, block);
     impl_env_mutator_u256!(chain_id, cfg);
 
-    pub fn prevrandao<S>(_input: &mut EVMInput, _state_: &mut S) -> MutationResult
+    /// Mutate prevrandao (also the difficulty, read by the same opcode before the merge)
+    pub fn prevrandao<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
     where
         S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
     {
-        // not supported yet
-        // unreachable!();
-        return MutationResult::Skipped;
+        let block = &mut input.get_vm_env_mut().block;
+        let mut input_vec = block.prevrandao.map_or(vec![0; 32], |r| r.0.to_vec());
+        let mut wrapper = MutatorInput::new(&mut input_vec);
+        let res = byte_mutator(state_, &mut wrapper, None);
+        if res == MutationResult::Skipped {
+            return res;
+        }
+        block.prevrandao = Some(B256::from_slice(&input_vec));
+        block.difficulty = EVMU256::try_from_be_slice(&input_vec.as_slice()).unwrap();
+        res
     }
 
-    pub fn gas_price<S>(_input: &mut EVMInput, _state_: &mut S) -> MutationResult
+    /// Advance blocks and time before the transaction, either by an interesting duration, a
+    /// few blocks, or back to no advance
+    pub fn advance<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
     where
         S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
     {
-        // not supported yet
-        // unreachable!();
-        return MutationResult::Skipped;
+        let advance = BlockAdvance::random(state_);
+        if advance == input.advance {
+            return MutationResult::Skipped;
+        }
+        input.advance = advance;
+        MutationResult::Mutated
+    }
+
+    /// Force-feed ETH to the contract before the transaction (dust, a round amount of ether, or
+    /// back to none), for contracts reading their own balance
+    pub fn force_feed<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
+    where
+        S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
+    {
+        let ether = EVMU256::from(10).pow(EVMU256::from(18));
+        let force_feed = match state_.rand_mut().below(4) {
+            0 => EVMU256::ZERO,
+            1 => EVMU256::from(1 + state_.rand_mut().below(1000)),
+            _ => ether * EVMU256::from(1u64 << state_.rand_mut().below(8)),
+        };
+        if force_feed == input.force_feed {
+            return MutationResult::Skipped;
+        }
+        input.force_feed = force_feed;
+        MutationResult::Mutated
+    }
+
+    /// Mutate the gas price within [`GAS_PRICE_BOUNDS`], either as a legacy gas price or as an
+    /// EIP-1559 fee (max fee and priority fee, the effective price being
+    /// min(max fee, basefee + priority fee))
+    pub fn gas_price<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
+    where
+        S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
+    {
+        let (price, priority_fee) = random_fee(state_, unsafe { GAS_PRICE_BOUNDS });
+        let tx = &mut input.get_vm_env_mut().tx;
+        if tx.gas_price == price && tx.gas_priority_fee == priority_fee {
+            return MutationResult::Skipped;
+        }
+        tx.gas_price = price;
+        tx.gas_priority_fee = priority_fee;
+        MutationResult::Mutated
+    }
+
+    pub fn blob_basefee<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
+    where
+        S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
+    {
+        let mut input_vec = input.blob_env.blob_basefee.to_be_bytes::<32>().to_vec();
+        let mut wrapper = MutatorInput::new(&mut input_vec);
+        let res = byte_mutator(state_, &mut wrapper, None);
+        if res == MutationResult::Skipped {
+            return res;
+        }
+        input.blob_env.blob_basefee = EVMU256::try_from_be_slice(&input_vec.as_slice()).unwrap();
+        res
+    }
+
+    pub fn blob_hash<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
+    where
+        S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
+    {
+        input.blob_env.mutate_blob_hash(state_)
+    }
+
+    /// Replay a signature observed in the sequence in another context: as the (v, r, s) arguments
+    /// of the transaction, on another chain id, or from another sender than the last one using it
+    pub fn signature<S>(input: &mut EVMInput, state_: &mut S) -> MutationResult
+    where
+        S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
+    {
+        let records = &input.sstate.state.signatures;
+        if records.is_empty() {
+            return MutationResult::Skipped;
+        }
+        let record = records[state_.rand_mut().below(records.len() as u64) as usize].clone();
+        match state_.rand_mut().below(3) {
+            0 => match input.data.as_mut() {
+                Some(abi) if set_signature_args(abi, &record) => MutationResult::Mutated,
+                _ => MutationResult::Skipped,
+            },
+            1 => {
+                input.get_vm_env_mut().cfg.chain_id = record.last_chain_id.wrapping_add(EVMU256::from(1));
+                MutationResult::Mutated
+            }
+            _ => {
+                let caller = state_.get_rand_caller();
+                if caller == record.last_caller || caller == input.get_caller() {
+                    return MutationResult::Skipped;
+                }
+                input.set_caller(caller);
+                MutationResult::Mutated
+            }
+        }
     }
 
     pub fn balance<S>(_input: &mut EVMInput, _state_: &mut S) -> MutationResult
@@ -518,6 +886,17 @@ impl EVMInput {
     where
         S: State + HasCaller<EVMAddress> + HasRand + HasMetadata,
     {
+        // values picked by a strategy learned for the function, random bytes otherwise
+        if let Some(abi) = &input.data {
+            let (r1, r2) = (state_.rand_mut().next(), state_.rand_mut().next());
+            if let Some(value) = pick_value(input.get_contract(), abi.function, r1, r2) {
+                if input.get_txn_value() == Some(value) {
+                    return MutationResult::Skipped;
+                }
+                input.set_txn_value(value);
+                return MutationResult::Mutated;
+            }
+        }
         let vm_slots = if let Some(s) = input.get_state().get(&input.get_contract()) {
             Some(s.clone())
         } else {
@@ -574,8 +953,17 @@ impl EVMInput {
         add_mutator!(coinbase);
         add_mutator!(gas_limit);
         add_mutator!(number);
+        add_mutator!(advance, ap.timestamp || ap.number || !self.advance.is_zero());
+        add_mutator!(
+            force_feed,
+            is_tracked(&self.contract)
+                && (ap.self_balance || ap.balance.contains(&self.contract) || !self.force_feed.is_zero())
+        );
         add_mutator!(chain_id);
+        add_mutator!(signature, !self.sstate.state.signatures.is_empty());
         add_mutator!(prevrandao);
+        add_mutator!(blob_hash);
+        add_mutator!(blob_basefee);
 
         if mutators.len() == 0 {
             return MutationResult::Skipped;
@@ -605,6 +993,21 @@ impl ConciseSerde for ConciseEVMInput {
             s.push_str(" ");
         }
 
+        if self.victim {
+            s.push_str("[victim] ");
+        }
+        if self.via_intermediary {
+            s.push_str("[via intermediary] ");
+        }
+        if !self.advance.is_zero() {
+            s.push_str(format!("[+{} blocks, +{}s] ", self.advance.blocks, self.advance.seconds).as_str());
+        }
+        if self.force_feed != EVMU256::ZERO {
+            s.push_str(format!("[force-fed {} wei] ", self.force_feed).as_str());
+        }
+        if let Some((role, _)) = unsafe { ACTORS.iter().find(|(_, actor)| *actor == self.caller) } {
+            s.push_str(format!("[{}] ", role).as_str());
+        }
         s.push_str(self.pretty_txn().expect("Failed to pretty print txn").as_str());
         s
     }
@@ -749,3 +1152,94 @@ impl Input for EVMInput {
         // todo!()
     }
 }
+
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::evm::types::EVMFuzzState;
+    use crate::state::FuzzState;
+
+    #[test]
+    fn test_mutate_blob_hash() {
+        let mut state: EVMFuzzState = FuzzState::new(0);
+        let mut blob_env = BlobEnv::default();
+        for _ in 0..1000 {
+            let before = blob_env.clone();
+            match blob_env.mutate_blob_hash(&mut state) {
+                MutationResult::Skipped => assert_eq!(blob_env, before),
+                MutationResult::Mutated => {
+                    assert!(blob_env.blob_hashes.len() <= MAX_BLOBS_PER_TX);
+                    assert!(blob_env
+                        .blob_hashes
+                        .iter()
+                        .all(|hash| hash.to_be_bytes::<32>()[0] == 0x01));
+                }
+            }
+        }
+        assert!(!blob_env.blob_hashes.is_empty());
+    }
+
+    #[test]
+    fn test_random_advance() {
+        let mut state: EVMFuzzState = FuzzState::new(0);
+        let mut seen = HashSet::new();
+        for _ in 0..1000 {
+            let advance = BlockAdvance::random(&mut state);
+            // time never moves without blocks, nor blocks without time
+            assert_eq!(advance.blocks == 0, advance.seconds == 0);
+            assert!(
+                advance.seconds == advance.blocks * SECONDS_PER_BLOCK ||
+                    INTERESTING_DURATIONS.contains(&advance.seconds)
+            );
+            seen.insert(advance);
+        }
+        assert!(seen.contains(&BlockAdvance::default()));
+        assert!(INTERESTING_DURATIONS
+            .iter()
+            .all(|seconds| seen.iter().any(|advance| advance.seconds == *seconds)));
+    }
+
+    #[test]
+    fn test_parse_gas_price_bounds() {
+        assert_eq!(parse_gas_price_bounds("1000000000:500000000000"), (1_000_000_000, 500_000_000_000));
+        assert_eq!(parse_gas_price_bounds(" 0 : 0 "), (0, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_inverted_gas_price_bounds() {
+        parse_gas_price_bounds("10:1");
+    }
+
+    #[test]
+    fn test_random_fee() {
+        let mut state: EVMFuzzState = FuzzState::new(0);
+        let bounds = (1_000, 5_000);
+        let (mut seen_legacy, mut seen_eip1559) = (false, false);
+        for _ in 0..1000 {
+            let (price, priority_fee) = random_fee(&mut state, bounds);
+            assert!(price >= EVMU256::from(bounds.0) && price <= EVMU256::from(bounds.1));
+            match priority_fee {
+                Some(fee) => {
+                    assert!(fee <= price);
+                    seen_eip1559 = true;
+                }
+                None => seen_legacy = true,
+            }
+        }
+        assert!(seen_legacy && seen_eip1559);
+        assert_eq!(random_fee(&mut state, (7, 7)).0, EVMU256::from(7));
+    }
+
+    #[test]
+    fn test_add_advance() {
+        let advance = BlockAdvance { blocks: 1, seconds: 12 };
+        assert_eq!(advance.add(advance), BlockAdvance { blocks: 2, seconds: 24 });
+        assert_eq!(advance.add(BlockAdvance::default()), advance);
+        let max = BlockAdvance { blocks: u64::MAX, seconds: u64::MAX };
+        assert_eq!(max.add(advance), max);
+        assert!(!advance.add(BlockAdvance::default()).is_zero());
+        assert!(BlockAdvance::default().add(BlockAdvance::default()).is_zero());
+    }
+}
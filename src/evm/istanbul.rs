@@ -0,0 +1,189 @@
+/// Coverage in the Istanbul (nyc) JSON format, as written by solidity-coverage and
+/// hardhat-coverage (`[WORKDIR]/coverage.json`)
+///
+/// Instructions are mapped to the source through the source maps of the targets: each innermost
+/// source range executed by some instruction is a statement, and each JUMPI is an `if` branch
+/// whose locations are the jump taken and the fall through. Hit counts are 1 if covered, 0
+/// otherwise. Functions are not tracked, `fnMap` is left empty.
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{json, Value};
+
+use crate::evm::middlewares::coverage::Coverage;
+use crate::evm::srcmap::parser::{SourceMapLocation, BASE_PATH};
+use crate::evm::types::ProjectSourceMapTy;
+
+/// (offset, length) of a source range in a file
+type Range = (usize, usize);
+
+#[derive(Default)]
+struct FileCoverage {
+    /// Range => covered
+    statements: HashMap<Range, bool>,
+    /// Range => (taken covered, fall through covered)
+    branches: HashMap<Range, (bool, bool)>,
+}
+
+/// Line (from 1) and column (from 0) of each offset of `source`
+fn position(source: &str, offset: usize) -> Value {
+    let offset = offset.min(source.len());
+    let before = &source.as_bytes()[..offset];
+    let line = before.iter().filter(|c| **c == b'\n').count() + 1;
+    let column = offset - before.iter().rposition(|c| *c == b'\n').map_or(0, |nl| nl + 1);
+    json!({"line": line, "column": column})
+}
+
+fn location(source: &str, (offset, length): Range) -> Value {
+    json!({"start": position(source, offset), "end": position(source, offset + length)})
+}
+
+/// Ranges of `ranges` that contain no other range (e.g., drop the range of a whole function
+/// the dispatcher maps to when its statements are mapped as well)
+fn innermost(ranges: &HashMap<Range, bool>) -> Vec<(Range, bool)> {
+    let mut kept = ranges
+        .iter()
+        .filter(|((offset, length), _)| {
+            !ranges.keys().any(|(o, l)| {
+                (*o, *l) != (*offset, *length) && *o >= *offset && *o + *l <= *offset + *length
+            })
+        })
+        .map(|(range, covered)| (*range, *covered))
+        .collect::<Vec<_>>();
+    kept.sort();
+    kept
+}
+
+/// Istanbul coverage of the files of the targets, `read` returns the content of a file
+pub fn istanbul_report(
+    coverage: &Coverage,
+    source_map: &ProjectSourceMapTy,
+    read: &dyn Fn(&str) -> Option<String>,
+) -> Value {
+    let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+    for (addr, pcs) in &coverage.total_instr_set {
+        let map = match source_map.get(addr) {
+            Some(Some(map)) => map,
+            _ => continue,
+        };
+        let skip = coverage.skip_pcs.get(addr).cloned().unwrap_or_default();
+        let covered = coverage.pc_coverage.get(addr).cloned().unwrap_or_default();
+        let jumpis = coverage.total_jumpi_set.get(addr).cloned().unwrap_or_default();
+        let branches = coverage.jumpi_coverage.get(addr).cloned().unwrap_or_default();
+        for pc in pcs.iter().filter(|pc| !skip.contains(pc)) {
+            let loc = match map.get(pc) {
+                Some(loc @ SourceMapLocation { file: Some(_), .. }) => loc,
+                _ => continue,
+            };
+            let file = files.entry(loc.file.clone().unwrap()).or_default();
+            let range = (loc.offset, loc.length);
+            *file.statements.entry(range).or_default() |= covered.contains(pc);
+            if jumpis.contains(pc) {
+                // the condition recorded is whether the jump falls through
+                let branch = file.branches.entry(range).or_default();
+                branch.0 |= branches.contains(&(*pc, false));
+                branch.1 |= branches.contains(&(*pc, true));
+            }
+        }
+    }
+
+    let mut report = serde_json::Map::new();
+    for (file, cov) in files {
+        let source = match read(&file) {
+            Some(source) => source,
+            None => continue,
+        };
+        let (mut statement_map, mut s, mut l) = (serde_json::Map::new(), serde_json::Map::new(), BTreeMap::new());
+        for (idx, (range, covered)) in innermost(&cov.statements).into_iter().enumerate() {
+            let loc = location(&source, range);
+            let line = loc["start"]["line"].as_u64().unwrap();
+            let hits = l.entry(line).or_insert(0u64);
+            *hits = (*hits).max(covered as u64);
+            statement_map.insert(idx.to_string(), loc);
+            s.insert(idx.to_string(), json!(covered as u64));
+        }
+        let (mut branch_map, mut b) = (serde_json::Map::new(), serde_json::Map::new());
+        let mut branches = cov.branches.into_iter().collect::<Vec<_>>();
+        branches.sort();
+        for (idx, (range, (taken, fall_through))) in branches.into_iter().enumerate() {
+            let loc = location(&source, range);
+            branch_map.insert(
+                idx.to_string(),
+                json!({
+                    "line": loc["start"]["line"],
+                    "type": "if",
+                    "loc": loc,
+                    "locations": [loc, loc],
+                }),
+            );
+            b.insert(idx.to_string(), json!([taken as u64, fall_through as u64]));
+        }
+        let path = match std::fs::canonicalize(unsafe { BASE_PATH.clone() } + file.as_str()) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => file.clone(),
+        };
+        report.insert(
+            path.clone(),
+            json!({
+                "path": path,
+                "statementMap": statement_map,
+                "fnMap": {},
+                "branchMap": branch_map,
+                "s": s,
+                "f": {},
+                "b": b,
+                "l": l.into_iter().map(|(line, hits)| (line.to_string(), json!(hits))).collect::<serde_json::Map<_, _>>(),
+            }),
+        );
+    }
+    Value::Object(report)
+}
+
+/// Write `[WORKDIR]/coverage.json`
+pub fn write_istanbul_report(coverage: &Coverage, source_map: &ProjectSourceMapTy) {
+    let report = istanbul_report(coverage, source_map, &|file| {
+        std::fs::read_to_string(unsafe { BASE_PATH.clone() } + file).ok()
+    });
+    let _ = std::fs::write(
+        format!("{}/coverage.json", coverage.work_dir),
+        serde_json::to_string_pretty(&report).unwrap(),
+    );
+}
+
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::evm::types::EVMAddress;
+
+    #[test]
+    fn test_istanbul_report() {
+        let source = "contract A {\n  function f(uint x) public {\n    if (x > 1) { y = 1; }\n  }\n}\n";
+        let file = Some("A.sol".to_string());
+        let stmt = source.find("y = 1").unwrap();
+        let cond = source.find("if").unwrap();
+        let func = source.find("function").unwrap();
+        let addr = EVMAddress::zero();
+
+        let mut coverage = Coverage::new();
+        coverage.total_instr_set.insert(addr, HashSet::from([0, 1, 2, 3]));
+        coverage.total_jumpi_set.insert(addr, HashSet::from([1]));
+        coverage.pc_coverage.insert(addr, HashSet::from([0, 1]));
+        coverage.jumpi_coverage.insert(addr, HashSet::from([(1, true)]));
+        let map = HashMap::from([
+            (0, SourceMapLocation::new(file.clone(), func, source.len() - func - 4)),
+            (1, SourceMapLocation::new(file.clone(), cond, 21)),
+            (2, SourceMapLocation::new(file.clone(), stmt, 5)),
+            (3, SourceMapLocation::new(file.clone(), stmt, 5)),
+        ]);
+        let source_map = HashMap::from([(addr, Some(map))]);
+
+        let report = istanbul_report(&coverage, &source_map, &|_| Some(source.to_string()));
+        let a = &report["A.sol"];
+        // the function range contains the others, the condition contains the assignment
+        assert_eq!(a["statementMap"].as_object().unwrap().len(), 1);
+        assert_eq!(a["statementMap"]["0"]["start"], json!({"line": 3, "column": 17}));
+        assert_eq!(a["s"]["0"], json!(0));
+        assert_eq!(a["branchMap"]["0"]["line"], json!(3));
+        assert_eq!(a["b"]["0"], json!([0, 1]));
+        assert_eq!(a["l"]["3"], json!(0));
+    }
+}
@@ -0,0 +1,272 @@
+/// Registry of well-known addresses per chain (`--known-addresses`)
+///
+/// Routers, flashloan providers, wrapped native tokens, stablecoins and price oracles of each
+/// chain, consulted by the onchain mode (pegged tokens of the swap paths, contracts not fetched),
+/// the Uniswap models, the exploit generator (flashloan provider) and the mutator (tokens used as
+/// address arguments). The defaults below can be extended or overridden by a JSON file with the
+/// same layout, entries of the file replacing the defaults with the same name:
+/// ```json
+/// {
+///   "eth": {
+///     "stablecoins": {"crvUSD": "0xf939e0a03fb07f59a73314e73794be0e57ac1b4e"},
+///     "flashloan_providers": {"balancer": "0xba12222222228d8ba445958a75a0704d566bf2c8"}
+///   }
+/// }
+/// ```
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::evm::types::EVMAddress;
+
+/// A Uniswap V2-like deployment
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RouterInfo {
+    pub name: String,
+    pub router: String,
+    pub factory: String,
+    /// Fee of the pools (in 1/10000)
+    pub pool_fee: usize,
+    /// Hash of the init code of the pairs (hex)
+    pub init_code_hash: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChainAddresses {
+    /// Symbol of the wrapped native token, a key of `pegged`
+    #[serde(default)]
+    pub native: Option<String>,
+    /// Tokens swap paths end at (wrapped native token, stablecoins, majors): symbol => address
+    #[serde(default)]
+    pub pegged: BTreeMap<String, String>,
+    #[serde(default)]
+    pub stablecoins: BTreeMap<String, String>,
+    #[serde(default)]
+    pub routers: Vec<RouterInfo>,
+    #[serde(default)]
+    pub flashloan_providers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub oracles: BTreeMap<String, String>,
+    /// Contracts never fetched nor fuzzed in the onchain mode (e.g., large pools and routers)
+    #[serde(default)]
+    pub skip: Vec<String>,
+}
+
+const DEFAULT_KNOWN_ADDRESSES: &str = r#"{
+  "eth": {
+    "native": "WETH",
+    "pegged": {
+      "WETH": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+      "USDC": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+      "USDT": "0xdac17f958d2ee523a2206206994597c13d831ec7",
+      "DAI": "0x6b175474e89094c44da98b954eedeac495271d0f",
+      "WBTC": "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599",
+      "WMATIC": "0x7d1afa7b718fb893db30a3abc0cfc608aacfebb0"
+    },
+    "stablecoins": {
+      "USDC": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+      "USDT": "0xdac17f958d2ee523a2206206994597c13d831ec7",
+      "DAI": "0x6b175474e89094c44da98b954eedeac495271d0f"
+    },
+    "routers": [{
+      "name": "uniswapv2",
+      "router": "0x7a250d5630b4cf539739df2c5dacb4c659f2488d",
+      "factory": "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f",
+      "pool_fee": 3,
+      "init_code_hash": "96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+    }],
+    "flashloan_providers": {
+      "balancer": "0xba12222222228d8ba445958a75a0704d566bf2c8"
+    },
+    "oracles": {
+      "chainlink_eth_usd": "0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419"
+    },
+    "skip": [
+      "0x3cb4ca3c9dc0e02d252098eebb3871ac7a43c54d",
+      "0x6aed013308d847cb87502d86e7d9720b17b4c1f2",
+      "0x5a58505a96d1dbf8df91cb21b54419fc36e93fde",
+      "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640",
+      "0xa40cac1b04d7491bdfb42ccac97dff25e0efb09e",
+      "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+    ]
+  },
+  "bsc": {
+    "native": "WBNB",
+    "pegged": {
+      "WBNB": "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c",
+      "USDC": "0x8ac76a51cc950d9822d68b83fe1ad97b32cd580d",
+      "USDT": "0x55d398326f99059ff775485246999027b3197955",
+      "DAI": "0x1af3f329e8be154074d8769d1ffa4ee058b1dbc3",
+      "WBTC": "0x7130d2a12b9bcbfae4f2634d864a1ee1ce3ead9c",
+      "WETH": "0x2170ed0880ac9a755fd29b2688956bd959f933f8",
+      "BUSD": "0xe9e7cea3dedca5984780bafc599bd69add087d56",
+      "CAKE": "0x0e09fabb73bd3ade0a17ecc321fd13a19e81ce82"
+    },
+    "stablecoins": {
+      "USDC": "0x8ac76a51cc950d9822d68b83fe1ad97b32cd580d",
+      "USDT": "0x55d398326f99059ff775485246999027b3197955",
+      "DAI": "0x1af3f329e8be154074d8769d1ffa4ee058b1dbc3",
+      "BUSD": "0xe9e7cea3dedca5984780bafc599bd69add087d56"
+    },
+    "routers": [{
+      "name": "pancakeswap",
+      "router": "0x10ed43c718714eb63d5aa57b78b54704e256024e",
+      "factory": "0xca143ce32fe78f1f7019d7d551a6402fc5350c73",
+      "pool_fee": 25,
+      "init_code_hash": "00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5"
+    }],
+    "skip": [
+      "0xca143ce32fe78f1f7019d7d551a6402fc5350c73",
+      "0x6cd71a07e72c514f5d511651f6808c6395353968",
+      "0x10ed43c718714eb63d5aa57b78b54704e256024e"
+    ]
+  },
+  "polygon": {
+    "native": "WMATIC",
+    "pegged": {
+      "WMATIC": "0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270",
+      "USDC": "0x2791bca1f2de4661ed88a30c99a7a9449aa84174",
+      "USDT": "0xc2132d05d31c914a87c6611c10748aeb04b58e8f",
+      "DAI": "0x8f3cf7ad23cd3cadbd9735aff958023239c6a063",
+      "WBTC": "0x1bfd67037b42cf73acf2047067bd4f2c47d9bfd6",
+      "WETH": "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619"
+    },
+    "stablecoins": {
+      "USDC": "0x2791bca1f2de4661ed88a30c99a7a9449aa84174",
+      "USDT": "0xc2132d05d31c914a87c6611c10748aeb04b58e8f",
+      "DAI": "0x8f3cf7ad23cd3cadbd9735aff958023239c6a063"
+    },
+    "flashloan_providers": {
+      "balancer": "0xba12222222228d8ba445958a75a0704d566bf2c8"
+    }
+  }
+}"#;
+
+/// Known addresses of all chains (by lowercase chain name)
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KnownAddresses {
+    #[serde(flatten)]
+    pub chains: BTreeMap<String, ChainAddresses>,
+}
+
+static mut KNOWN_ADDRESSES: Option<KnownAddresses> = None;
+
+/// The registry in use, the defaults unless [`load_known_addresses`] extended them
+pub fn known_addresses() -> &'static KnownAddresses {
+    unsafe { KNOWN_ADDRESSES.get_or_insert_with(KnownAddresses::default_registry) }
+}
+
+/// Extend the defaults with the registry in the file at `path`
+pub fn load_known_addresses(path: &str) {
+    let content = std::fs::read_to_string(path).expect("failed to read known addresses");
+    let overrides: KnownAddresses = serde_json::from_str(&content).expect("failed to parse known addresses");
+    let mut registry = KnownAddresses::default_registry();
+    registry.merge(overrides);
+    unsafe {
+        KNOWN_ADDRESSES = Some(registry);
+    }
+}
+
+fn parse_address(address: &String) -> EVMAddress {
+    EVMAddress::from_str(address).unwrap_or_else(|_| panic!("invalid known address {}", address))
+}
+
+impl KnownAddresses {
+    pub fn default_registry() -> Self {
+        serde_json::from_str(DEFAULT_KNOWN_ADDRESSES).expect("invalid default known addresses")
+    }
+
+    /// Add the entries of `other`, replacing the entries with the same name
+    pub fn merge(&mut self, other: KnownAddresses) {
+        for (name, chain) in other.chains {
+            let current = self.chains.entry(name).or_default();
+            if chain.native.is_some() {
+                current.native = chain.native;
+            }
+            current.pegged.extend(chain.pegged);
+            current.stablecoins.extend(chain.stablecoins);
+            current.flashloan_providers.extend(chain.flashloan_providers);
+            current.oracles.extend(chain.oracles);
+            for router in chain.routers {
+                current.routers.retain(|r| r.name != router.name);
+                current.routers.push(router);
+            }
+            for skip in chain.skip {
+                if !current.skip.contains(&skip) {
+                    current.skip.push(skip);
+                }
+            }
+        }
+    }
+
+    pub fn chain(&self, chain: &str) -> Option<&ChainAddresses> {
+        self.chains.get(&chain.to_lowercase())
+    }
+
+    /// Contracts not fetched in the onchain mode, over all chains
+    pub fn skipped(&self) -> Vec<EVMAddress> {
+        self.chains
+            .values()
+            .flat_map(|chain| chain.skip.iter().map(parse_address))
+            .collect()
+    }
+}
+
+impl ChainAddresses {
+    /// Address of the wrapped native token (e.g., WETH)
+    pub fn native_token(&self) -> Option<&String> {
+        self.pegged.get(self.native.as_ref()?)
+    }
+
+    /// Tokens worth using as address arguments (pegged tokens and stablecoins)
+    pub fn tokens(&self) -> Vec<EVMAddress> {
+        let mut tokens = self
+            .pegged
+            .values()
+            .chain(self.stablecoins.values())
+            .map(parse_address)
+            .collect::<Vec<_>>();
+        tokens.sort();
+        tokens.dedup();
+        tokens
+    }
+
+    /// Address of the flashloan provider `name`, or of any provider
+    pub fn flashloan_provider(&self, name: Option<&str>) -> Option<EVMAddress> {
+        match name {
+            Some(name) => self.flashloan_providers.get(name),
+            None => self.flashloan_providers.values().next(),
+        }
+        .map(parse_address)
+    }
+
+    pub fn router(&self, name: &str) -> Option<&RouterInfo> {
+        self.routers.iter().find(|r| r.name == name)
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut registry = KnownAddresses::default_registry();
+        let eth = registry.chain("ETH").unwrap();
+        assert_eq!(eth.native_token().unwrap(), "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        assert!(eth.router("uniswapv2").is_some());
+
+        let overrides: KnownAddresses = serde_json::from_str(
+            r#"{
+                "eth": {"stablecoins": {"crvUSD": "0xf939e0a03fb07f59a73314e73794be0e57ac1b4e"}},
+                "base": {"native": "WETH", "pegged": {"WETH": "0x4200000000000000000000000000000000000006"}}
+            }"#,
+        )
+        .unwrap();
+        registry.merge(overrides);
+        let eth = registry.chain("eth").unwrap();
+        assert_eq!(eth.stablecoins.len(), 4);
+        assert_eq!(eth.native.as_deref(), Some("WETH"));
+        assert!(registry.chain("base").unwrap().native_token().is_some());
+    }
+}
@@ -0,0 +1,289 @@
+/// Liquidation scenarios of lending markets
+///
+/// A scenario declares the actors of the market (e.g., borrower, liquidator, attacker), the
+/// price feeds the fuzzer may move within bounds and the view functions reporting the health
+/// of positions and the bad debt of the market. Actors become callers of the fuzzer, prices
+/// are moved by calling [`crate::evm::middlewares::price_mover::PRICE_TRIGGER`], and
+/// [`crate::evm::oracles::liquidation::LiquidationOracle`] checks that liquidations neither
+/// create bad debt nor hit healthy positions.
+///
+/// Before the campaign, actors are funded (their balances are written in the storage of the
+/// tokens) and open their positions (e.g., deposit collateral and borrow against it), so that
+/// the fuzzer starts from a market with positions to liquidate.
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use revm_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+
+use crate::evm::pre_state::{parse_arg, parse_amount, PositionConfig, SetupCall};
+use crate::evm::types::{EVMAddress, EVMU256};
+
+/// A price stored in a feed (e.g., a mock oracle) that the fuzzer moves
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PriceConfig {
+    /// Address or contract name of the feed
+    pub feed: String,
+    /// Storage slot of the price
+    pub slot: String,
+    /// Price the bounds are relative to, the price in the feed when it is first moved if
+    /// not set
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Bounds of the price, in percent of the base price
+    #[serde(default = "default_min_percent")]
+    pub min_percent: u64,
+    #[serde(default = "default_max_percent")]
+    pub max_percent: u64,
+}
+
+/// Tokens given to each actor
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FundConfig {
+    /// Address or contract name of the token
+    pub token: String,
+    /// Slot of the `balanceOf` mapping of the token
+    pub balance_slot: String,
+    pub amount: String,
+}
+
+fn default_min_percent() -> u64 {
+    50
+}
+
+fn default_max_percent() -> u64 {
+    150
+}
+
+fn default_health_threshold() -> String {
+    "1000000000000000000".to_string()
+}
+
+fn default_liquidate() -> String {
+    "liquidat".to_string()
+}
+
+/// Liquidation scenario, loaded from a JSON file:
+/// ```json
+/// {
+///   "market": "Pool",
+///   "actors": {
+///     "borrower": "0x00000000000000000000000000000000000b0a01",
+///     "liquidator": "0x00000000000000000000000000000000000b0a02",
+///     "attacker": "0x00000000000000000000000000000000000b0a03"
+///   },
+///   "funds": [{"token": "WETH", "balance_slot": "0x3", "amount": "100000000000000000000"}],
+///   "positions": {
+///     "borrower": [
+///       {"contract": "WETH", "function": "approve(address,uint256)", "args": ["Pool", "max"]},
+///       {"contract": "Pool", "function": "deposit(uint256)", "args": ["10000000000000000000"]},
+///       {"contract": "Pool", "function": "borrow(uint256)", "args": ["5000000000000000000"]}
+///     ]
+///   },
+///   "prices": [{"feed": "PriceOracle", "slot": "0x2", "min_percent": 20, "max_percent": 200}],
+///   "health": "healthFactor(address)",
+///   "bad_debt": "badDebt()"
+/// }
+/// ```
+/// Arguments of the calls opening positions are given as in [`crate::evm::pre_state`], `sender`
+/// being the actor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LendingConfig {
+    /// Address or contract name of the market
+    pub market: String,
+    /// Role => address of the actors
+    pub actors: BTreeMap<String, String>,
+    /// Tokens given to each actor
+    #[serde(default)]
+    pub funds: Vec<FundConfig>,
+    /// Role => calls made by the actor to open its position
+    #[serde(default)]
+    pub positions: BTreeMap<String, Vec<PositionConfig>>,
+    #[serde(default)]
+    pub prices: Vec<PriceConfig>,
+    /// View function of the market returning the health factor of an account
+    pub health: String,
+    /// Accounts with a health factor below it can be liquidated (1e18 by default)
+    #[serde(default = "default_health_threshold")]
+    pub health_threshold: String,
+    /// View function of the market returning its bad debt
+    #[serde(default)]
+    pub bad_debt: Option<String>,
+    /// Prefix of the names of the liquidation functions (e.g., `liquidate`, `liquidationCall`)
+    #[serde(default = "default_liquidate")]
+    pub liquidate: String,
+}
+
+impl LendingConfig {
+    pub fn from_file(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).expect("failed to read lending config");
+        let config: Self = serde_json::from_str(&content).expect("failed to parse lending config");
+        config.validate();
+        config
+    }
+
+    /// Panics on scenarios that can't be run
+    pub fn validate(&self) {
+        assert!(!self.actors.is_empty(), "lending config declares no actor");
+        for price in &self.prices {
+            assert!(
+                price.min_percent <= price.max_percent,
+                "price of {} has min_percent {} above max_percent {}",
+                price.feed,
+                price.min_percent,
+                price.max_percent
+            );
+        }
+        for role in self.positions.keys() {
+            assert!(self.actors.contains_key(role), "position of unknown actor {}", role);
+        }
+        for fund in &self.funds {
+            EVMU256::from_str(&fund.balance_slot)
+                .unwrap_or_else(|_| panic!("invalid balance slot {} of {}", fund.balance_slot, fund.token));
+            parse_amount(&fund.amount).unwrap_or_else(|| panic!("invalid amount {} of {}", fund.amount, fund.token));
+        }
+        EVMU256::from_str(&self.health_threshold).expect("invalid health threshold");
+    }
+}
+
+/// Funding and positions of the actors, resolved
+#[derive(Clone, Debug, Default)]
+pub struct LendingSetup {
+    pub actors: Vec<EVMAddress>,
+    /// (token, slot, balance) written in the storage of the tokens
+    pub balances: Vec<(EVMAddress, EVMU256, EVMU256)>,
+    /// Calls made by each actor to open its position
+    pub positions: Vec<(EVMAddress, Vec<SetupCall>)>,
+}
+
+/// Slot of `key` in a Solidity mapping at `slot`
+fn mapping_slot(key: EVMAddress, slot: EVMU256) -> EVMU256 {
+    let mut preimage = [0u8; 64];
+    preimage[12..32].copy_from_slice(&key.0);
+    preimage[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+    EVMU256::from_be_bytes(keccak256(&preimage).0)
+}
+
+impl LendingSetup {
+    pub fn new(config: &LendingConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        let actors = config.actors.values().map(resolve).collect::<Vec<_>>();
+        let balances = config
+            .funds
+            .iter()
+            .flat_map(|fund| {
+                let token = resolve(&fund.token);
+                let slot = EVMU256::from_str(&fund.balance_slot).unwrap();
+                let amount = parse_amount(&fund.amount).unwrap();
+                actors.iter().map(move |actor| (token, mapping_slot(*actor, slot), amount))
+            })
+            .collect();
+        let positions = config
+            .positions
+            .iter()
+            .map(|(role, calls)| {
+                let calls = calls
+                    .iter()
+                    .map(|call| SetupCall {
+                        contract: resolve(&call.contract),
+                        function: call.function.clone(),
+                        args: call.args.iter().map(|arg| parse_arg(arg, resolve)).collect(),
+                        value: call.value.as_ref().map_or(EVMU256::ZERO, |value| {
+                            parse_amount(value).unwrap_or_else(|| panic!("invalid value {} of {}", value, call.function))
+                        }),
+                    })
+                    .collect();
+                (resolve(&config.actors[role]), calls)
+            })
+            .collect();
+        Self {
+            actors,
+            balances,
+            positions,
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config: LendingConfig = serde_json::from_str(
+            r#"{
+                "market": "Pool",
+                "actors": {"borrower": "0x00000000000000000000000000000000000b0a01"},
+                "prices": [{"feed": "PriceOracle", "slot": "0x2"}],
+                "health": "healthFactor(address)"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.prices[0].min_percent, 50);
+        assert_eq!(config.prices[0].max_percent, 150);
+        assert_eq!(config.health_threshold, "1000000000000000000");
+        assert_eq!(config.liquidate, "liquidat");
+        assert!(config.bad_debt.is_none());
+        config.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "above max_percent")]
+    fn test_validate_bounds() {
+        let config: LendingConfig = serde_json::from_str(
+            r#"{
+                "market": "Pool",
+                "actors": {"borrower": "0x00000000000000000000000000000000000b0a01"},
+                "prices": [{"feed": "PriceOracle", "slot": "0x2", "min_percent": 150, "max_percent": 50}],
+                "health": "healthFactor(address)"
+            }"#,
+        )
+        .unwrap();
+        config.validate();
+    }
+
+    #[test]
+    fn test_setup() {
+        let config: LendingConfig = serde_json::from_str(
+            r#"{
+                "market": "Pool",
+                "actors": {
+                    "borrower": "0x00000000000000000000000000000000000b0a01",
+                    "liquidator": "0x00000000000000000000000000000000000b0a02"
+                },
+                "funds": [{"token": "WETH", "balance_slot": "0x3", "amount": "1000"}],
+                "positions": {
+                    "borrower": [{"contract": "Pool", "function": "borrow(uint256,address)", "args": ["500", "sender"]}]
+                },
+                "health": "healthFactor(address)"
+            }"#,
+        )
+        .unwrap();
+        config.validate();
+        let weth = EVMAddress::from_str("0x0000000000000000000000000000000000000001").unwrap();
+        let pool = EVMAddress::from_str("0x0000000000000000000000000000000000000002").unwrap();
+        let setup = LendingSetup::new(&config, &|name| match name.as_str() {
+            "WETH" => weth,
+            "Pool" => pool,
+            _ => EVMAddress::from_str(name).unwrap(),
+        });
+        let borrower = setup.actors[0];
+        // both actors are funded
+        assert_eq!(setup.balances.len(), 2);
+        assert_eq!(setup.balances[0], (weth, mapping_slot(borrower, EVMU256::from(3)), EVMU256::from(1000)));
+        // only the borrower opens a position
+        assert_eq!(setup.positions.len(), 1);
+        let (actor, calls) = &setup.positions[0];
+        assert_eq!(*actor, borrower);
+        assert_eq!(calls[0].contract, pool);
+        assert_eq!(calls[0].calldata(borrower)[48..68], borrower.0);
+    }
+
+    #[test]
+    fn test_mapping_slot() {
+        // balanceOf[0x...01] with balanceOf at slot 0
+        let key = EVMAddress::from_str("0x0000000000000000000000000000000000000001").unwrap();
+        assert_eq!(
+            mapping_slot(key, EVMU256::ZERO),
+            EVMU256::from_str("0xada5013122d395ba3c54772283fb069b10426056ef8ca54750cb9bb552a59e7d").unwrap()
+        );
+    }
+}
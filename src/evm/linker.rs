@@ -0,0 +1,179 @@
+/// Linking of external libraries in offline builds
+///
+/// Bytecode of contracts using external libraries is unlinked: each use of a library is a
+/// placeholder for its address, `__$<keccak256("path:Name")[..17]>$__` since solc 0.5 and
+/// `__path:Name___...` before. Placeholders are replaced by zeros when the bytecode is loaded,
+/// then by the address of the library once all contracts are loaded. Libraries are deployed as
+/// any other contract, before the contracts linking them, so that their code is covered as well.
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+use crate::evm::contract_utils::{set_hash, ContractInfo};
+
+/// Length of a placeholder (in hex), the length of an address
+const PLACEHOLDER_LEN: usize = 40;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkReference {
+    /// Offset of the address in the bytecode (in bytes)
+    pub offset: usize,
+    /// Fully qualified name of the library (`path:Name`) if known, otherwise the hash in the
+    /// placeholder (`$...$`)
+    pub library: String,
+}
+
+/// Hash of a fully qualified library name in placeholders (solc 0.5+)
+fn placeholder_hash(qualified_name: &str) -> String {
+    let mut hash = [0u8; 32];
+    set_hash(qualified_name, &mut hash);
+    hex::encode(&hash[..17])
+}
+
+/// Bytecode (hex) with placeholders replaced by zeros, and the libraries it references
+///
+/// `code` is the content of a `.bin` file, possibly followed by the placeholders solc lists
+/// in comments (`// $hash$ -> path:Name`)
+pub fn strip_placeholders(code: &str) -> (String, Vec<LinkReference>) {
+    let mut names = HashMap::new();
+    let mut hex = String::new();
+    for line in code.lines().map(|line| line.trim()) {
+        if let Some(comment) = line.strip_prefix("//") {
+            if let Some((placeholder, name)) = comment.split_once("->") {
+                names.insert(placeholder.trim().to_string(), name.trim().to_string());
+            }
+        } else {
+            hex.push_str(line);
+        }
+    }
+    let hex = hex.trim_start_matches("0x").to_string();
+
+    let mut stripped = String::with_capacity(hex.len());
+    let mut references = vec![];
+    let mut idx = 0;
+    while idx < hex.len() {
+        if !hex[idx..].starts_with("__") || idx + PLACEHOLDER_LEN > hex.len() {
+            let next = hex[idx..].find("__").map_or(hex.len(), |offset| idx + offset.max(1));
+            stripped.push_str(&hex[idx..next]);
+            idx = next;
+            continue;
+        }
+        let placeholder = &hex[idx + 2..idx + PLACEHOLDER_LEN - 2];
+        let library = if placeholder.starts_with('$') {
+            names.get(placeholder).cloned().unwrap_or(placeholder.to_string())
+        } else {
+            placeholder.trim_end_matches('_').to_string()
+        };
+        references.push(LinkReference {
+            offset: idx / 2,
+            library,
+        });
+        stripped.push_str(&"0".repeat(PLACEHOLDER_LEN));
+        idx += PLACEHOLDER_LEN;
+    }
+    (stripped, references)
+}
+
+/// Name of the contract a library reference resolves to, using the fully qualified names of
+/// the contracts compiled (from combined.json) for the hashes not listed by solc
+fn library_name(library: &str, qualified_names: &Vec<String>) -> Option<String> {
+    let qualified = if library.starts_with('$') {
+        let hash = library.trim_matches('$');
+        qualified_names.iter().find(|name| placeholder_hash(name) == hash)?.as_str()
+    } else {
+        library
+    };
+    qualified.split(':').last().map(|name| name.to_string())
+}
+
+/// Name of a contract loaded from `path/Name*`
+fn contract_name(contract: &ContractInfo) -> String {
+    contract.name.split('/').last().unwrap().replace('*', "")
+}
+
+/// Write the addresses of the libraries in the bytecode of the contracts linking them, and
+/// order the contracts so that libraries are deployed first
+///
+/// `links` are the library references of the contracts, by contract name
+pub fn link_libraries(
+    contracts: &mut Vec<ContractInfo>,
+    links: &HashMap<String, Vec<LinkReference>>,
+    qualified_names: &Vec<String>,
+) {
+    let addresses = contracts
+        .iter()
+        .map(|contract| (contract_name(contract), contract.deployed_address))
+        .collect::<HashMap<_, _>>();
+    let mut dependencies: HashMap<String, HashSet<String>> = HashMap::new();
+    for contract in contracts.iter_mut() {
+        let name = contract_name(contract);
+        for reference in links.get(&contract.name).unwrap_or(&vec![]) {
+            let library = match library_name(&reference.library, qualified_names) {
+                Some(library) if addresses.contains_key(&library) => library,
+                _ => panic!(
+                    "{} links library {} which is not compiled, add it to the build",
+                    name, reference.library
+                ),
+            };
+            let slot = &mut contract.code[reference.offset..reference.offset + PLACEHOLDER_LEN / 2];
+            // the bytecode may have been replaced (e.g., by the one of a proxy deployment)
+            if slot.iter().all(|b| *b == 0) {
+                slot.copy_from_slice(&addresses[&library].0);
+            }
+            info!("Linked library {} to {} at {:?}", library, name, addresses[&library]);
+            dependencies.entry(name.clone()).or_default().insert(library);
+        }
+    }
+
+    // libraries first, in the order they are needed
+    let mut deployed = HashSet::new();
+    let mut ordered = vec![];
+    let mut remaining = std::mem::take(contracts);
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|contract| {
+            dependencies
+                .get(&contract_name(contract))
+                .map_or(true, |libraries| libraries.iter().all(|l| deployed.contains(l)))
+        });
+        if ready.is_empty() {
+            panic!(
+                "circular library links between {:?}",
+                blocked.iter().map(contract_name).collect::<Vec<_>>()
+            );
+        }
+        deployed.extend(ready.iter().map(contract_name));
+        ordered.extend(ready);
+        remaining = blocked;
+    }
+    *contracts = ordered;
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_placeholders() {
+        let hash = placeholder_hash("src/Math.sol:Math");
+        let code = format!(
+            "6080__${}$__6000__Old.sol:Old___________________________73\n\n// ${}$ -> src/Math.sol:Math\n",
+            hash, hash
+        );
+        let (hex, references) = strip_placeholders(&code);
+        assert_eq!(hex, format!("6080{}6000{}73", "0".repeat(40), "0".repeat(40)));
+        assert_eq!(
+            references,
+            vec![
+                LinkReference { offset: 2, library: "src/Math.sol:Math".to_string() },
+                LinkReference { offset: 24, library: "Old.sol:Old".to_string() },
+            ]
+        );
+        assert!(hex::decode(hex).is_ok());
+
+        // hashes not listed by solc are resolved with the names of the contracts compiled
+        let unlisted = format!("${}$", hash);
+        assert_eq!(library_name(&unlisted, &vec![]), None);
+        assert_eq!(
+            library_name(&unlisted, &vec!["src/Token.sol:Token".to_string(), "src/Math.sol:Math".to_string()]),
+            Some("Math".to_string())
+        );
+    }
+}
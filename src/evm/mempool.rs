@@ -0,0 +1,143 @@
+/// Mempool screening (`--mempool`)
+///
+/// Pending transactions sent to the targets are polled from the RPC endpoint of the on-chain
+/// campaign. Each one is executed on the initial state, and the resulting state is added to the
+/// infant state corpus (and favored by the scheduler), so that fuzzed sequences start with it.
+/// Violations found on such states are reported as alerts naming the pending transaction.
+use std::collections::HashSet;
+use tracing::info;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use libafl::prelude::HasMetadata;
+use serde_json::{json, Value};
+
+use crate::evm::abi::ABIAddressToInstanceMap;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMStagedVMState, EVMU256};
+
+/// Interval between polls of pending transactions
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A pending transaction sent to a target
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingTx {
+    pub hash: String,
+    pub from: EVMAddress,
+    pub to: EVMAddress,
+    pub value: EVMU256,
+    pub input: Vec<u8>,
+}
+
+impl PendingTx {
+    /// Parse a transaction returned by `eth_getTransactionByHash`, None for contract creations
+    pub fn from_json(tx: &Value) -> Option<Self> {
+        let hex_field = |name: &str| tx.get(name).and_then(|v| v.as_str()).map(|v| v.trim_start_matches("0x"));
+        Some(Self {
+            hash: tx.get("hash")?.as_str()?.to_string(),
+            from: EVMAddress::from_str(hex_field("from")?).ok()?,
+            to: EVMAddress::from_str(hex_field("to")?).ok()?,
+            value: EVMU256::from_str_radix(hex_field("value").unwrap_or("0"), 16).ok()?,
+            input: hex::decode(hex_field("input").unwrap_or("")).ok()?,
+        })
+    }
+
+    /// Whether a transaction of a violating sequence is this pending transaction
+    pub fn matches(&self, tx: &ConciseEVMInput) -> bool {
+        tx.caller == self.from
+            && tx.contract == self.to
+            && tx.data.as_ref().map(|data| data.get_bytes()) == Some(self.input.clone())
+    }
+
+    /// Input of the pending transaction on `sstate`, None if the called function is unknown
+    pub fn to_input(&self, state: &EVMFuzzState, sstate: EVMStagedVMState) -> Option<EVMInput> {
+        if self.input.len() < 4 {
+            return None;
+        }
+        let selector: [u8; 4] = self.input[..4].try_into().unwrap();
+        let mut data = state
+            .metadata()
+            .get::<ABIAddressToInstanceMap>()?
+            .map
+            .get(&self.to)?
+            .iter()
+            .find(|abi| abi.function == selector)?
+            .clone();
+        data.set_bytes(self.input.clone());
+        let concise = ConciseEVMInput {
+            caller: self.from,
+            contract: self.to,
+            data: Some(data),
+            txn_value: if self.value == EVMU256::ZERO { None } else { Some(self.value) },
+            call_leak: u32::MAX,
+            ..Default::default()
+        };
+        Some(concise.to_input(sstate).0)
+    }
+}
+
+/// JSON-RPC request, returns the result
+pub fn request(client: &reqwest::blocking::Client, url: &str, method: &str, params: Value) -> Option<Value> {
+    let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+    let resp: Value = client.post(url).json(&body).send().ok()?.json().ok()?;
+    resp.get("result").cloned()
+}
+
+/// Poll pending transactions sent to `targets` from the RPC endpoint at `url`
+pub fn subscribe(url: String, targets: HashSet<EVMAddress>) -> Receiver<PendingTx> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let client = reqwest::blocking::Client::new();
+        let filter = request(&client, &url, "eth_newPendingTransactionFilter", json!([]))
+            .expect("endpoint does not support pending transaction filters");
+        info!("[mempool] watching pending transactions to {} targets", targets.len());
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let hashes = match request(&client, &url, "eth_getFilterChanges", json!([filter])) {
+                Some(Value::Array(hashes)) => hashes,
+                _ => continue,
+            };
+            for hash in hashes {
+                let tx = request(&client, &url, "eth_getTransactionByHash", json!([hash]))
+                    .and_then(|tx| PendingTx::from_json(&tx));
+                if let Some(tx) = tx.filter(|tx| targets.contains(&tx.to)) {
+                    if sender.send(tx).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// Pending transactions in a violating sequence
+pub fn pending_in_sequence<'a>(pending: &'a [PendingTx], txs: &str) -> Vec<&'a PendingTx> {
+    let txs = parse_sequence(txs);
+    pending
+        .iter()
+        .filter(|p| txs.iter().any(|tx| p.matches(tx)))
+        .collect()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_tx_from_json() {
+        let tx = PendingTx::from_json(&json!({
+            "hash": "0xabcd",
+            "from": "0x1111111111111111111111111111111111111111",
+            "to": "0x2222222222222222222222222222222222222222",
+            "value": "0x10",
+            "input": "0xa9059cbb"
+        }))
+        .unwrap();
+        assert_eq!(tx.value, EVMU256::from(16));
+        assert_eq!(tx.input, vec![0xa9, 0x05, 0x9c, 0xbb]);
+        assert!(PendingTx::from_json(&json!({"hash": "0xabcd", "from": "0x1111111111111111111111111111111111111111", "to": null})).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{as_u64, convert_u256_to_h160, EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const GASPRICE: u8 = 0x3a;
+const COINBASE: u8 = 0x41;
+const PREVRANDAO: u8 = 0x44;
+const BASEFEE: u8 = 0x48;
+const CALL: u8 = 0xf1;
+/// transfer(address,uint256)
+const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// A payout to the caller (ETH or token transfer) made by a contract that read block context
+/// (coinbase, basefee or prevrandao) or the gas price earlier in the transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BlockContextPayout {
+    pub contract: EVMAddress,
+    /// pc of the call paying out
+    pub pc: usize,
+    /// Block context read by the contract (e.g., `prevrandao`)
+    pub reads: Vec<String>,
+    pub recipient: EVMAddress,
+    /// Token transferred (ETH if None)
+    pub token: Option<EVMAddress>,
+    pub amount: EVMU256,
+}
+
+/// Records payouts depending on the block context: ETH sent or tokens transferred to the caller
+/// (or tx.origin) by a contract that read block.coinbase, block.basefee or block.prevrandao in
+/// the same transaction, e.g., a lottery drawing its winner from prevrandao. As these values are
+/// mutated by the fuzzer (and chosen or known in advance by validators and searchers), such
+/// payouts can be steered by an attacker. Payouts after reading tx.gasprice (refunds, keeper
+/// rewards) are recorded as well, the gas price being chosen by the sender. Payouts are recorded
+/// in [`crate::evm::vm::EVMState::block_context_payouts`].
+#[derive(Clone, Debug, Default)]
+pub struct BlockContextTracker {
+    /// Contract => block context it read in the current transaction
+    readers: HashMap<EVMAddress, BTreeSet<&'static str>>,
+}
+
+impl BlockContextTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Block context (or gas price) read by `op`
+fn context_read(op: u8) -> Option<&'static str> {
+    match op {
+        COINBASE => Some("coinbase"),
+        PREVRANDAO => Some("prevrandao"),
+        BASEFEE => Some("basefee"),
+        GASPRICE => Some("gasprice"),
+        _ => None,
+    }
+}
+
+/// Payout (recipient, token, amount) to the attacker made by a call to `target` with `value`
+/// and (the first 68 bytes of) calldata `data`: ETH sent or a token `transfer`
+fn payout<F>(
+    target: EVMAddress,
+    value: EVMU256,
+    data: &[u8],
+    is_attacker: F,
+) -> Option<(EVMAddress, Option<EVMAddress>, EVMU256)>
+where
+    F: Fn(EVMAddress) -> bool,
+{
+    if value > EVMU256::ZERO && is_attacker(target) {
+        return Some((target, None, value));
+    }
+    if data.len() < 68 || data[0..4] != TRANSFER {
+        return None;
+    }
+    let recipient = convert_u256_to_h160(EVMU256::from_be_slice(&data[4..36]));
+    let amount = EVMU256::from_be_slice(&data[36..68]);
+    (amount > EVMU256::ZERO && is_attacker(recipient)).then_some((recipient, Some(target), amount))
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for BlockContextTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let contract = interp.contract.address;
+        // a call from tx.origin starting is a new transaction
+        if interp.program_counter() == 0 && interp.contract.caller == host.origin {
+            self.readers.clear();
+        }
+        let op = *interp.instruction_pointer;
+        if let Some(read) = context_read(op) {
+            self.readers.entry(contract).or_default().insert(read);
+            return;
+        }
+        if op != CALL {
+            return;
+        }
+        let reads = match self.readers.get(&contract) {
+            Some(reads) if interp.stack.len() >= 5 => reads,
+            _ => return,
+        };
+        let peek = |idx: usize| interp.stack.peek(idx).unwrap();
+        let is_attacker = |addr: EVMAddress| addr == interp.contract.caller || addr == host.origin;
+        let data = if peek(4) >= EVMU256::from(68) {
+            interp.memory.get_slice(as_u64(peek(3)) as usize, 68).to_vec()
+        } else {
+            vec![]
+        };
+        if let Some((recipient, token, amount)) = payout(convert_u256_to_h160(peek(1)), peek(2), &data, is_attacker) {
+            let pc = interp.program_counter();
+            let payouts = &mut host.evmstate.block_context_payouts;
+            if !payouts.iter().any(|p| p.contract == contract && p.pc == pc) {
+                payouts.push(BlockContextPayout {
+                    contract,
+                    pc,
+                    reads: reads.iter().map(|r| r.to_string()).collect(),
+                    recipient,
+                    token,
+                    amount,
+                });
+            }
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::BlockContext
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::convert_h160_to_u256;
+
+    fn transfer_data(recipient: EVMAddress, amount: u64) -> Vec<u8> {
+        let mut data = TRANSFER.to_vec();
+        data.extend(convert_h160_to_u256(recipient).to_be_bytes::<32>());
+        data.extend(EVMU256::from(amount).to_be_bytes::<32>());
+        data
+    }
+
+    #[test]
+    fn test_context_read() {
+        assert_eq!(context_read(PREVRANDAO), Some("prevrandao"));
+        assert_eq!(context_read(COINBASE), Some("coinbase"));
+        assert_eq!(context_read(GASPRICE), Some("gasprice"));
+        assert_eq!(context_read(CALL), None);
+    }
+
+    #[test]
+    fn test_payout() {
+        let (attacker, token, other) = (
+            EVMAddress::from_slice(&[1; 20]),
+            EVMAddress::from_slice(&[2; 20]),
+            EVMAddress::from_slice(&[3; 20]),
+        );
+        let is_attacker = |addr: EVMAddress| addr == attacker;
+        let value = EVMU256::from(100);
+
+        assert_eq!(payout(attacker, value, &[], is_attacker), Some((attacker, None, value)));
+        assert_eq!(payout(other, value, &[], is_attacker), None);
+        assert_eq!(
+            payout(token, EVMU256::ZERO, &transfer_data(attacker, 100), is_attacker),
+            Some((attacker, Some(token), value))
+        );
+        // transfers to others or of nothing are not payouts
+        assert_eq!(payout(token, EVMU256::ZERO, &transfer_data(other, 100), is_attacker), None);
+        assert_eq!(payout(token, EVMU256::ZERO, &transfer_data(attacker, 0), is_attacker), None);
+        let mut approve = transfer_data(attacker, 100);
+        approve[0..4].copy_from_slice(&[0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(payout(token, EVMU256::ZERO, &approve, is_attacker), None);
+    }
+}
@@ -4,10 +4,12 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::time::{SystemTime, UNIX_EPOCH};
 use itertools::Itertools;
+use tracing::{debug, trace};
 use libafl::inputs::Input;
 use libafl::prelude::{HasCorpus, HasMetadata, State};
 use revm_interpreter::Interpreter;
 use revm_primitives::Bytecode;
+use crate::evm::eof::{self, relative_targets, EofContainer, RJUMP, RJUMPI, RJUMPV};
 use crate::evm::host::FuzzHost;
 use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
 use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
@@ -23,6 +25,19 @@ pub fn branch_pc(bytecode: &Bytecode) -> (usize, usize) {
     let mut i = 0;
     let bytes = bytecode.bytes();
 
+    // EOF containers: relative jumps on the image of the code sections
+    if let Some(container) = EofContainer::parse(bytes) {
+        let code = &container.image;
+        for pc in eof::instructions(code) {
+            match code[pc] {
+                RJUMP => JUMPCount += 1,
+                RJUMPI | RJUMPV => JUMPICount += relative_targets(code, pc).len(),
+                _ => (),
+            }
+        }
+        return (JUMPCount, JUMPICount);
+    }
+
     while i < bytes.len() {
         let op = *bytes.get(i).unwrap();
         i += 1;
@@ -65,13 +80,11 @@ impl BranchCoverage {
     }
 
     pub fn record_branch_coverage(&mut self, source_map: &ProjectSourceMapTy) {
-        /*
-        println!("total_instr: {:?}", self.total_instr);
-        println!("total_instr_set: {:?}", self.total_instr_set);
-        println!("pc_coverage: {:?}",  self.pc_coverage);
-        println!("total_jump_branch: {:?}", self.total_jump_branch);
-        println!("total_jumpi_branch: {:?}", self.total_jumpi_branch);
-         */
+        debug!("total_instr: {:?}", self.total_instr);
+        debug!("total_instr_set: {:?}", self.total_instr_set);
+        debug!("pc_coverage: {:?}", self.pc_coverage);
+        debug!("total_jump_branch: {:?}", self.total_jump_branch);
+        debug!("total_jumpi_branch: {:?}", self.total_jumpi_branch);
 
         let mut data = format!(
             "===================Branch Coverage Report =================== \n{}",
@@ -96,7 +109,7 @@ impl BranchCoverage {
                 .join("\n")
         );
 
-        println!("\n\n{}", data);
+        crate::status!("\n\n{}", data);
 
         let mut file = OpenOptions::new()
             .write(true)
@@ -135,9 +148,22 @@ impl<I, VS, S> Middleware<VS, I, S> for BranchCoverage
         let mut total_brash = 1;
         let mut jmppc: usize = 0;
         self.pc_coverage.entry(address).or_default().insert(pc);
-        match *interp.instruction_pointer {
+        let eof = host.eof.get(&address).cloned();
+        let mut op = *interp.instruction_pointer;
+        // relative jumps of EOF code, RJUMP counted as JUMP and RJUMPI as JUMPI
+        if let Some(container) = &eof {
+            match op {
+                RJUMP => op = 0x56,
+                RJUMPI => {
+                    jmppc = relative_targets(&container.image, pc)[1];
+                    op = 0x57;
+                }
+                _ => {}
+            }
+        }
+        match op {
             0x56 => { // JUMP
-                // println!("JUMPI: {:#X} {:?}, {:#X}", pc,  address, as_u64(interp.stack.peek(0).unwrap()) as usize);
+                trace!("JUMP: {:#X} {:?}, {:#X}", pc, address, as_u64(interp.stack.peek(0).unwrap()) as usize);
                 if self.total_instr_set.get(&address).is_none() {
                     is_insert = true;
                 } else if  !self.total_instr_set.get(&address).unwrap().contains(&pc) {
@@ -146,8 +172,10 @@ impl<I, VS, S> Middleware<VS, I, S> for BranchCoverage
                 }
             }
             0x57 => { // JUMPI
-                // println!("JUMPI: {:#X} {:?}, {:#X}", pc,  address, as_u64(interp.stack.peek(0).unwrap()) as usize);
-                jmppc = as_u64(interp.stack.peek(0).unwrap()) as usize;
+                trace!("JUMPI: {:#X} {:?}, {:#X}", pc, address, as_u64(interp.stack.peek(0).unwrap()) as usize);
+                if eof.is_none() {
+                    jmppc = as_u64(interp.stack.peek(0).unwrap()) as usize;
+                }
                 if self.total_instr_set.get(&address).is_none(){
                     is_insert = true;
                     is_insert_jumpi = true;
@@ -184,7 +212,7 @@ impl<I, VS, S> Middleware<VS, I, S> for BranchCoverage
     }
 
     unsafe fn on_insert(&mut self, bytecode: &mut Bytecode, address: EVMAddress, host: &mut FuzzHost<VS, I, S>, state: &mut S) {
-        // println!("on_insert: {:#X} {:?}", address, hex::encode(bytecode.clone().bytecode.as_ref()));
+        trace!("on_insert: {:?} {}", address, hex::encode(bytecode.bytes()));
         self.work_dir = host.work_dir.clone();
         let total = branch_pc(&bytecode.clone());
         self.total_jump_branch.insert(address, total.0);
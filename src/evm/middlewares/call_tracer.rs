@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, HasRand, State};
+use revm_interpreter::{Host, Interpreter};
+use revm_primitives::{keccak256, Bytecode};
+use serde::{Deserialize, Serialize};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{as_u64, EVMAddress, EVMU256};
+use crate::evm::vm::IS_FAST_CALL_STATIC;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+/// A step of the execution worth looking at when debugging a finding
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TraceStep {
+    Call {
+        kind: String,
+        from: EVMAddress,
+        to: EVMAddress,
+        value: EVMU256,
+        input: String,
+    },
+    Create {
+        kind: String,
+        from: EVMAddress,
+        value: EVMU256,
+        init_code_len: usize,
+    },
+    SLoad {
+        address: EVMAddress,
+        slot: EVMU256,
+        value: EVMU256,
+        /// Name of the variable in the slot, from the storage layout of the contract
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    SStore {
+        address: EVMAddress,
+        slot: EVMU256,
+        old: EVMU256,
+        new: EVMU256,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    Log {
+        address: EVMAddress,
+        topics: Vec<EVMU256>,
+        data: String,
+    },
+    Revert {
+        address: EVMAddress,
+        pc: usize,
+        data: String,
+    },
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceStep::Call { kind, from, to, value, input } => {
+                write!(f, "{} {:?} => {:?} 0x{} (value: {})", kind, from, to, input, value)
+            }
+            TraceStep::Create { kind, from, value, init_code_len } => {
+                write!(f, "{} by {:?} ({} bytes, value: {})", kind, from, init_code_len, value)
+            }
+            TraceStep::SLoad { address, slot, value, label } => {
+                write!(f, "SLOAD {:?}[{}] = {:#x}", address, slot_name(slot, label), value)
+            }
+            TraceStep::SStore { address, slot, old, new, label } => {
+                write!(f, "SSTORE {:?}[{}] {:#x} -> {:#x}", address, slot_name(slot, label), old, new)
+            }
+            TraceStep::Log { address, topics, data } => {
+                write!(f, "LOG {:?} topics: {:?} data: 0x{}", address, topics, data)
+            }
+            TraceStep::Revert { address, pc, data } => {
+                write!(f, "REVERT {:?}@{} 0x{}", address, pc, data)
+            }
+        }
+    }
+}
+
+fn slot_name(slot: &EVMU256, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{:#x} ({})", slot, label),
+        None => format!("{:#x}", slot),
+    }
+}
+
+impl TraceStep {
+    /// Label the storage slot accessed by the step, if any
+    pub fn label_slot(&mut self, labeler: impl Fn(&EVMAddress, EVMU256) -> Option<String>) {
+        match self {
+            TraceStep::SLoad { address, slot, label, .. } | TraceStep::SStore { address, slot, label, .. } => {
+                *label = labeler(address, *slot);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Trace of a transaction in a violating sequence
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxTrace {
+    pub tx: String,
+    pub reverted: bool,
+    pub output: String,
+    pub steps: Vec<TraceStep>,
+}
+
+/// Records internal calls, storage accesses, logs and reverts of executions
+#[derive(Clone, Debug, Default)]
+pub struct CallTracer {
+    pub steps: Vec<TraceStep>,
+    /// Preimages of the 32 / 64 bytes hashed by SHA3, i.e., of the slots of array elements and
+    /// mapping values
+    pub preimages: HashMap<EVMU256, Vec<u8>>,
+}
+
+impl CallTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the steps recorded so far
+    pub fn take(&mut self) -> Vec<TraceStep> {
+        std::mem::take(&mut self.steps)
+    }
+}
+
+fn peek(interp: &Interpreter, idx: usize) -> EVMU256 {
+    let len = interp.stack.len();
+    if idx < len {
+        interp.stack.data()[len - 1 - idx]
+    } else {
+        EVMU256::ZERO
+    }
+}
+
+fn read_memory(interp: &Interpreter, offset: EVMU256, len: EVMU256) -> Vec<u8> {
+    let (offset, len) = (as_u64(offset) as usize, as_u64(len) as usize);
+    let memory = interp.memory.data();
+    match offset.checked_add(len) {
+        Some(end) if end <= memory.len() => memory[offset..end].to_vec(),
+        _ => vec![],
+    }
+}
+
+fn to_address(v: EVMU256) -> EVMAddress {
+    EVMAddress::from_slice(&v.to_be_bytes::<32>()[12..])
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for CallTracer
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasRand
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone
+        + 'static,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        // static calls of oracles are not part of the transaction
+        if IS_FAST_CALL_STATIC {
+            return;
+        }
+        let address = interp.contract.address;
+        let step = match *interp.instruction_pointer {
+            // CALL, CALLCODE
+            op @ (0xf1 | 0xf2) => TraceStep::Call {
+                kind: if op == 0xf1 { "CALL" } else { "CALLCODE" }.to_string(),
+                from: address,
+                to: to_address(peek(interp, 1)),
+                value: peek(interp, 2),
+                input: hex::encode(read_memory(interp, peek(interp, 3), peek(interp, 4))),
+            },
+            // DELEGATECALL, STATICCALL
+            op @ (0xf4 | 0xfa) => TraceStep::Call {
+                kind: if op == 0xf4 { "DELEGATECALL" } else { "STATICCALL" }.to_string(),
+                from: address,
+                to: to_address(peek(interp, 1)),
+                value: EVMU256::ZERO,
+                input: hex::encode(read_memory(interp, peek(interp, 2), peek(interp, 3))),
+            },
+            // CREATE, CREATE2
+            op @ (0xf0 | 0xf5) => TraceStep::Create {
+                kind: if op == 0xf0 { "CREATE" } else { "CREATE2" }.to_string(),
+                from: address,
+                value: peek(interp, 0),
+                init_code_len: as_u64(peek(interp, 2)) as usize,
+            },
+            // SHA3
+            0x20 => {
+                if peek(interp, 1) == EVMU256::from(32) || peek(interp, 1) == EVMU256::from(64) {
+                    let preimage = read_memory(interp, peek(interp, 0), peek(interp, 1));
+                    if !preimage.is_empty() {
+                        self.preimages
+                            .insert(EVMU256::from_be_bytes(keccak256(&preimage).0), preimage);
+                    }
+                }
+                return;
+            }
+            // SLOAD
+            0x54 => {
+                let slot = peek(interp, 0);
+                TraceStep::SLoad {
+                    address,
+                    slot,
+                    value: <FuzzHost<VS, I, S> as Host<S>>::sload(host, address, slot).map(|x| x.0).unwrap_or_default(),
+                    label: None,
+                }
+            }
+            // SSTORE
+            0x55 => {
+                let slot = peek(interp, 0);
+                TraceStep::SStore {
+                    address,
+                    slot,
+                    old: <FuzzHost<VS, I, S> as Host<S>>::sload(host, address, slot).map(|x| x.0).unwrap_or_default(),
+                    new: peek(interp, 1),
+                    label: None,
+                }
+            }
+            // LOG0 - LOG4
+            op @ 0xa0..=0xa4 => TraceStep::Log {
+                address,
+                topics: (0..(op - 0xa0) as usize).map(|i| peek(interp, 2 + i)).collect(),
+                data: hex::encode(read_memory(interp, peek(interp, 0), peek(interp, 1))),
+            },
+            // REVERT
+            0xfd => TraceStep::Revert {
+                address,
+                pc: interp.program_counter(),
+                data: hex::encode(read_memory(interp, peek(interp, 0), peek(interp, 1))),
+            },
+            _ => return,
+        };
+        self.steps.push(step);
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::CallTracer
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use crate::evm::call_value::record_solved;
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const LT: u8 = 0x10;
+const GT: u8 = 0x11;
+const EQ: u8 = 0x14;
+const CALLVALUE: u8 = 0x34;
+
+/// Records the values compared with msg.value by payable functions (e.g., `price * amount` in
+/// `require(msg.value == price * amount)`), used as values by
+/// [`crate::evm::call_value::pick_value`]. Once a function reads msg.value, the other operand of
+/// each comparison with the value sent is recorded, along with the next value for strict
+/// comparisons.
+#[derive(Clone, Debug, Default)]
+pub struct CallValueTracker {
+    /// Function (contract, selector) that read msg.value last, and the value
+    reader: Option<(EVMAddress, [u8; 4], EVMU256)>,
+}
+
+impl CallValueTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for CallValueTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        _host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        match *interp.instruction_pointer {
+            CALLVALUE => {
+                let input = &interp.contract.input;
+                if input.len() >= 4 {
+                    let selector = [input[0], input[1], input[2], input[3]];
+                    self.reader = Some((interp.contract.address, selector, interp.contract.value));
+                }
+            }
+            op @ (LT | GT | EQ) if interp.stack.len() >= 2 => {
+                let (contract, selector, value) = match self.reader {
+                    Some(reader) if reader.0 == interp.contract.address => reader,
+                    _ => return,
+                };
+                let (a, b) = (interp.stack.peek(0).unwrap(), interp.stack.peek(1).unwrap());
+                let other = if a == value && b != value {
+                    b
+                } else if b == value && a != value {
+                    a
+                } else {
+                    return;
+                };
+                record_solved(contract, selector, other);
+                if op != EQ {
+                    record_solved(contract, selector, other.saturating_add(EVMU256::from(1)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::CallValue
+    }
+}
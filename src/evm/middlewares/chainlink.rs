@@ -0,0 +1,298 @@
+/// Chainlink feed manipulation (`--chainlink <bounds>`)
+///
+/// Feeds are recognized when a contract calls `latestRoundData()`, `latestAnswer()` or
+/// `latestTimestamp()` on them. Calling [`CHAINLINK_TRIGGER`] moves the answer of a feed within
+/// its deviation threshold (an answer may lag the market by up to the threshold before the feed
+/// is updated) and makes it stale by up to its heartbeat. In compromised mode, answers are moved
+/// anywhere between 0 and 100x and may be arbitrarily stale. Moves are stored in the storage of
+/// the trigger, so they are part of the VM state.
+use std::fmt::Debug;
+use tracing::info;
+
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+
+use crate::evm::contract_utils::{set_hash, ABIConfig, ContractInfo};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::{fixed_address, EVMAddress, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+/// Address of the dummy contract, calling it moves the answer of a feed
+pub const CHAINLINK_TRIGGER: &str = "000000000000000000000000000000000000c11e";
+/// Function exposed by the dummy contract, (index of the feed, answer point, staleness point)
+pub const MOVE_ANSWER_FUNCTION: &str = "ityfuzz_move_answer(uint256,uint256,uint256)";
+/// Maximum answer in compromised mode, in percent of the on-chain answer
+const COMPROMISED_MAX_PERCENT: u64 = 10000;
+
+/// Bounds of the moves of the feeds
+#[derive(Clone, Debug, Default)]
+pub struct FeedBounds {
+    /// Deviation threshold, in percent of the on-chain answer
+    pub deviation: u64,
+    /// Heartbeat, in seconds
+    pub heartbeat: u64,
+    /// Whether the feeds are assumed compromised (answers are not bounded)
+    pub compromised: bool,
+}
+
+impl FeedBounds {
+    /// Parse bounds like `deviation=2,heartbeat=3600`, or `compromised`
+    pub fn parse(spec: &str) -> Self {
+        let mut bounds = Self::default();
+        for item in spec.split(',').filter(|item| !item.is_empty()) {
+            if item.trim() == "compromised" {
+                bounds.compromised = true;
+                continue;
+            }
+            let (key, value) = item
+                .split_once('=')
+                .unwrap_or_else(|| panic!("invalid chainlink bound {}, expected <field>=<value>", item));
+            match key.trim() {
+                "deviation" => bounds.deviation = value.trim().parse().expect("invalid deviation bound"),
+                "heartbeat" => bounds.heartbeat = value.trim().parse().expect("invalid heartbeat bound"),
+                other => panic!("unknown chainlink bound {}, expected deviation, heartbeat or compromised", other),
+            }
+        }
+        bounds
+    }
+
+    /// Answer at a point of the bounds, in percent of the on-chain answer
+    pub fn percent(&self, point: EVMU256) -> u64 {
+        let (min, max) = if self.compromised {
+            (0, COMPROMISED_MAX_PERCENT)
+        } else {
+            (100 - self.deviation.min(100), 100 + self.deviation)
+        };
+        min + (point % EVMU256::from(max - min + 1)).as_limbs()[0]
+    }
+
+    /// Staleness (in seconds) of the answer at a point of the bounds
+    pub fn staleness(&self, point: EVMU256) -> u64 {
+        if self.compromised {
+            point.as_limbs()[0]
+        } else {
+            (point % EVMU256::from(self.heartbeat + 1)).as_limbs()[0]
+        }
+    }
+}
+
+/// View functions of feeds returning the answer or its timestamp
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedCall {
+    LatestRoundData,
+    LatestAnswer,
+    LatestTimestamp,
+}
+
+impl FeedCall {
+    pub fn from_input(input: &[u8]) -> Option<Self> {
+        match input.get(..4)? {
+            [0xfe, 0xaf, 0x96, 0x8c] => Some(Self::LatestRoundData),
+            [0x50, 0xd2, 0x5b, 0xcd] => Some(Self::LatestAnswer),
+            [0x82, 0x05, 0xbf, 0x6a] => Some(Self::LatestTimestamp),
+            _ => None,
+        }
+    }
+
+    /// Output of the call with the answer moved to `percent` of the on-chain answer and updated
+    /// `staleness` seconds before `now`
+    pub fn perturb(&self, output: &[u8], percent: u64, staleness: u64, now: EVMU256) -> Vec<u8> {
+        let (answer_offset, updated_offset) = match self {
+            Self::LatestRoundData => (Some(32), Some(96)),
+            Self::LatestAnswer => (Some(0), None),
+            Self::LatestTimestamp => (None, Some(0)),
+        };
+        let mut output = output.to_vec();
+        let int_max = (EVMU256::from(1) << 255) - EVMU256::from(1);
+        if let Some(offset) = answer_offset.filter(|offset| output.len() >= offset + 32) {
+            let answer = EVMU256::from_be_slice(&output[offset..offset + 32]);
+            // negative answers are left untouched
+            if answer <= int_max {
+                let moved = (answer.saturating_mul(EVMU256::from(percent)) / EVMU256::from(100)).min(int_max);
+                output[offset..offset + 32].copy_from_slice(&moved.to_be_bytes::<32>());
+            }
+        }
+        if let Some(offset) = updated_offset.filter(|offset| output.len() >= offset + 32 && staleness > 0) {
+            let updated = now.saturating_sub(EVMU256::from(staleness));
+            output[offset..offset + 32].copy_from_slice(&updated.to_be_bytes::<32>());
+        }
+        output
+    }
+}
+
+/// Feeds recognized so far and the bounds of their moves
+#[derive(Clone, Debug, Default)]
+pub struct ChainlinkFeeds {
+    pub bounds: FeedBounds,
+    pub feeds: Vec<EVMAddress>,
+    /// Whether a feed is being called, calls of proxies to their aggregator are not moved again
+    pub in_feed: bool,
+}
+
+impl ChainlinkFeeds {
+    pub fn new(bounds: FeedBounds) -> Self {
+        Self {
+            bounds,
+            ..Default::default()
+        }
+    }
+
+    /// Index of a feed, recognized on its first call
+    pub fn observe(&mut self, feed: EVMAddress) -> usize {
+        match self.feeds.iter().position(|f| *f == feed) {
+            Some(idx) => idx,
+            None => {
+                info!("[chainlink] recognized feed 0x{}", hex::encode(feed));
+                self.feeds.push(feed);
+                self.feeds.len() - 1
+            }
+        }
+    }
+
+    /// Move of a feed (percent of the on-chain answer, staleness), None if it is not moved
+    pub fn get_move(state: &EVMState, idx: usize) -> Option<(u64, u64)> {
+        let storage = state.state.get(&fixed_address(CHAINLINK_TRIGGER))?;
+        let percent = storage.get(&EVMU256::from(2 * idx))?.as_limbs()[0];
+        let staleness = storage.get(&EVMU256::from(2 * idx + 1)).map_or(0, |s| s.as_limbs()[0]);
+        // percents are stored plus one, so that zero means not moved
+        if percent == 0 {
+            return None;
+        }
+        Some((percent - 1, staleness))
+    }
+
+    /// Moves of the feeds in a violating sequence, i.e., the price deviations it requires
+    pub fn describe_moves(&self, txs: &str) -> Vec<String> {
+        let trigger = fixed_address(CHAINLINK_TRIGGER);
+        parse_sequence(txs)
+            .into_iter()
+            .filter(|tx| tx.contract == trigger)
+            .filter_map(|tx| {
+                let input = tx.data?.get_bytes();
+                if input.len() < 100 || self.feeds.is_empty() {
+                    return None;
+                }
+                let idx = (EVMU256::from_be_slice(&input[4..36]) % EVMU256::from(self.feeds.len())).as_limbs()[0];
+                let percent = self.bounds.percent(EVMU256::from_be_slice(&input[36..68]));
+                let staleness = self.bounds.staleness(EVMU256::from_be_slice(&input[68..100]));
+                Some(format!(
+                    "feed 0x{}: answer at {}% of the on-chain answer (deviation {}%), updated {}s ago",
+                    hex::encode(self.feeds[idx as usize]),
+                    percent,
+                    percent.abs_diff(100),
+                    staleness
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Dummy contract (a single STOP) exposing [`MOVE_ANSWER_FUNCTION`]
+pub fn chainlink_trigger_contract() -> ContractInfo {
+    let mut abi = ABIConfig {
+        abi: "(uint256,uint256,uint256)".to_string(),
+        function: [0; 4],
+        function_name: "ityfuzz_move_answer".to_string(),
+        is_static: false,
+        is_payable: false,
+        is_constructor: false,
+        input_names: vec!["feed".to_string(), "answer".to_string(), "staleness".to_string()],
+    };
+    set_hash(MOVE_ANSWER_FUNCTION, &mut abi.function);
+    ContractInfo {
+        name: "ChainlinkTrigger".to_string(),
+        code: vec![0x00],
+        abi: vec![abi],
+        is_code_deployed: true,
+        constructor_args: vec![],
+        deployed_address: fixed_address(CHAINLINK_TRIGGER),
+        source_map: None,
+    }
+}
+
+/// Stores the moves of the feeds whenever the trigger is called
+#[derive(Clone, Debug)]
+pub struct ChainlinkMover {
+    pub trigger: EVMAddress,
+}
+
+impl ChainlinkMover {
+    pub fn new() -> Self {
+        Self {
+            trigger: fixed_address(CHAINLINK_TRIGGER),
+        }
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for ChainlinkMover
+where
+    I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+    VS: VMStateT,
+    S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(&mut self, interp: &mut Interpreter, host: &mut FuzzHost<VS, I, S>, _state: &mut S) {
+        if interp.contract.address != self.trigger || interp.program_counter() != 0 {
+            return;
+        }
+        let input = &interp.contract.input;
+        let chainlink = match &host.chainlink {
+            Some(chainlink) if !chainlink.feeds.is_empty() && input.len() >= 100 => chainlink,
+            _ => return,
+        };
+        let idx = (EVMU256::from_be_slice(&input[4..36]) % EVMU256::from(chainlink.feeds.len())).as_limbs()[0];
+        let percent = chainlink.bounds.percent(EVMU256::from_be_slice(&input[36..68]));
+        let staleness = chainlink.bounds.staleness(EVMU256::from_be_slice(&input[68..100]));
+        let storage = host.evmstate.get_or_default(self.trigger);
+        storage.insert(EVMU256::from(2 * idx), EVMU256::from(percent + 1));
+        storage.insert(EVMU256::from(2 * idx + 1), EVMU256::from(staleness));
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::Chainlink
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_bounds() {
+        let bounds = FeedBounds::parse("deviation=2,heartbeat=3600");
+        assert_eq!(bounds.percent(EVMU256::ZERO), 98);
+        assert_eq!(bounds.percent(EVMU256::from(4)), 102);
+        assert_eq!(bounds.percent(EVMU256::from(5)), 98);
+        assert_eq!(bounds.staleness(EVMU256::from(3601)), 0);
+        let compromised = FeedBounds::parse("compromised");
+        assert_eq!(compromised.percent(EVMU256::from(10000)), 10000);
+    }
+
+    #[test]
+    fn test_perturb() {
+        let mut output = vec![0u8; 160];
+        output[32..64].copy_from_slice(&EVMU256::from(2000).to_be_bytes::<32>());
+        output[96..128].copy_from_slice(&EVMU256::from(100).to_be_bytes::<32>());
+        let moved = FeedCall::from_input(&[0xfe, 0xaf, 0x96, 0x8c])
+            .unwrap()
+            .perturb(&output, 98, 60, EVMU256::from(1000));
+        assert_eq!(EVMU256::from_be_slice(&moved[32..64]), EVMU256::from(1960));
+        assert_eq!(EVMU256::from_be_slice(&moved[96..128]), EVMU256::from(940));
+        assert_eq!(FeedCall::from_input(&[0x50, 0xd2, 0x5b, 0xcd]), Some(FeedCall::LatestAnswer));
+        assert_eq!(FeedCall::from_input(&[0x00]), None);
+    }
+}
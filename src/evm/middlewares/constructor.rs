@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::str::FromStr;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use crate::evm::host::{is_precompile, FuzzHost};
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{convert_u256_to_h160, EVMAddress, EVMU256};
+use crate::evm::vm::IN_DEPLOY;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const CALL: u8 = 0xf1;
+const CALLCODE: u8 = 0xf2;
+const DELEGATECALL: u8 = 0xf4;
+const STATICCALL: u8 = 0xfa;
+
+/// ERC-7201 slot of OpenZeppelin v5 `Initializable`
+pub const OZ_INITIALIZABLE_SLOT: &str = "0xf0c57e16840df040f15088dc2f81fe391c3923bec73e23a9662efc9c229c6a00";
+
+/// A bug of the construction of a contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConstructorFinding {
+    /// The constructor handed control to an account without code (e.g., passed as a
+    /// constructor argument), which can be an attacker contract
+    ExternalCall { contract: EVMAddress, pc: usize, target: EVMAddress },
+    /// A contract under construction is called back before its constructor returned
+    Reentrancy { contract: EVMAddress, caller: EVMAddress },
+    /// The constructor left an OpenZeppelin `Initializable` uninitialized (no
+    /// `_disableInitializers()`), anyone can initialize it
+    Uninitialized { contract: EVMAddress },
+}
+
+impl ConstructorFinding {
+    pub fn contract(&self) -> EVMAddress {
+        match self {
+            Self::ExternalCall { contract, .. } | Self::Reentrancy { contract, .. } | Self::Uninitialized { contract } => {
+                *contract
+            }
+        }
+    }
+}
+
+/// Runs during the deployment of the targets and records bugs of their constructors in
+/// [`crate::evm::vm::EVMState::constructor_findings`]: control handed to accounts without code,
+/// callbacks into contracts under construction, and upgradeable implementations left
+/// uninitialized.
+#[derive(Clone, Debug, Default)]
+pub struct ConstructorTracker {
+    /// Contracts whose constructor is running
+    constructing: HashSet<EVMAddress>,
+}
+
+impl ConstructorTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a frame of `contract` (called by `caller`) starting, `deployed` if the contract
+    /// already has code
+    fn enter(&mut self, contract: EVMAddress, caller: EVMAddress, deployed: bool) -> Option<ConstructorFinding> {
+        if self.constructing.contains(&contract) {
+            return Some(ConstructorFinding::Reentrancy { contract, caller });
+        }
+        if !deployed {
+            self.constructing.insert(contract);
+        }
+        None
+    }
+
+    /// Track a call from `contract` (at `pc`) to `target`, `has_code` if the target has code or
+    /// is a precompile
+    fn call(&self, contract: EVMAddress, pc: usize, target: EVMAddress, has_code: bool) -> Option<ConstructorFinding> {
+        if self.constructing.contains(&target) {
+            Some(ConstructorFinding::Reentrancy { contract: target, caller: contract })
+        } else if !has_code {
+            Some(ConstructorFinding::ExternalCall { contract, pc, target })
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the runtime `code` of a contract uses OpenZeppelin v5 `Initializable` while its
+/// slot holds `initializable` (zero if the contract was never initialized)
+fn left_uninitialized(code: &[u8], initializable: EVMU256) -> bool {
+    let slot = EVMU256::from_str(OZ_INITIALIZABLE_SLOT).unwrap();
+    let references_slot = code.windows(32).any(|w| w == slot.to_be_bytes::<32>().as_slice());
+    references_slot && initializable == EVMU256::ZERO
+}
+
+fn record<VS, I, S>(host: &mut FuzzHost<VS, I, S>, finding: ConstructorFinding)
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State + HasCaller<EVMAddress> + HasCorpus<I> + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput> + HasMetadata + Debug + Clone,
+{
+    if !host.evmstate.constructor_findings.contains(&finding) {
+        host.evmstate.constructor_findings.push(finding);
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for ConstructorTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        if !IN_DEPLOY {
+            return;
+        }
+        let contract = interp.contract.address;
+        if interp.program_counter() == 0 {
+            if let Some(finding) = self.enter(contract, interp.contract.caller, host.code.contains_key(&contract)) {
+                record(host, finding);
+            }
+        }
+        if !matches!(*interp.instruction_pointer, CALL | CALLCODE | DELEGATECALL | STATICCALL) || interp.stack.len() < 2 {
+            return;
+        }
+        let target = convert_u256_to_h160(interp.stack.peek(1).unwrap());
+        let has_code = host.code.contains_key(&target) || is_precompile(target, host.precompiles.len());
+        if let Some(finding) = self.call(contract, interp.program_counter(), target, has_code) {
+            record(host, finding);
+        }
+    }
+
+    unsafe fn on_insert(&mut self, bytecode: &mut Bytecode, address: EVMAddress, host: &mut FuzzHost<VS, I, S>, _state: &mut S) {
+        if !self.constructing.remove(&address) {
+            return;
+        }
+        let slot = EVMU256::from_str(OZ_INITIALIZABLE_SLOT).unwrap();
+        let initializable = host
+            .evmstate
+            .get(&address)
+            .and_then(|storage| storage.get(&slot))
+            .cloned()
+            .unwrap_or(EVMU256::ZERO);
+        if left_uninitialized(bytecode.bytes(), initializable) {
+            record(host, ConstructorFinding::Uninitialized { contract: address });
+        }
+    }
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::Constructor
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructor_reentrancy() {
+        let (deployer, contract, attacker) = (
+            EVMAddress::from_slice(&[1; 20]),
+            EVMAddress::from_slice(&[2; 20]),
+            EVMAddress::from_slice(&[3; 20]),
+        );
+        let mut tracker = ConstructorTracker::new();
+        assert_eq!(tracker.enter(contract, deployer, false), None);
+        // the constructor calls an account without code
+        assert_eq!(
+            tracker.call(contract, 10, attacker, false),
+            Some(ConstructorFinding::ExternalCall { contract, pc: 10, target: attacker })
+        );
+        assert_eq!(tracker.call(contract, 10, deployer, true), None);
+        // which calls back into the contract under construction
+        assert_eq!(
+            tracker.enter(contract, attacker, false),
+            Some(ConstructorFinding::Reentrancy { contract, caller: attacker })
+        );
+        assert_eq!(
+            tracker.call(attacker, 20, contract, false),
+            Some(ConstructorFinding::Reentrancy { contract, caller: attacker })
+        );
+    }
+
+    #[test]
+    fn test_deployed_contracts() {
+        let (caller, contract) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let mut tracker = ConstructorTracker::new();
+        assert_eq!(tracker.enter(contract, caller, true), None);
+        assert!(tracker.constructing.is_empty());
+        assert_eq!(tracker.enter(contract, caller, true), None);
+    }
+
+    #[test]
+    fn test_left_uninitialized() {
+        let slot = EVMU256::from_str(OZ_INITIALIZABLE_SLOT).unwrap();
+        let mut code = vec![0x7f];
+        code.extend(slot.to_be_bytes::<32>());
+        code.push(0x54);
+        assert!(left_uninitialized(&code, EVMU256::ZERO));
+        // `_disableInitializers()` sets the version to max
+        assert!(!left_uninitialized(&code, EVMU256::from(u64::MAX)));
+        assert!(!left_uninitialized(&[0x60, 0x00, 0x54], EVMU256::ZERO));
+    }
+}
@@ -4,27 +4,40 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::ops::AddAssign;
 use std::time::{SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
 use itertools::Itertools;
 use libafl::inputs::Input;
 use libafl::prelude::{HasCorpus, HasMetadata, State};
 use revm_interpreter::Interpreter;
 use revm_interpreter::opcode::{INVALID, JUMPDEST, JUMPI, REVERT, STOP};
-use revm_primitives::Bytecode;
-use crate::evm::host::FuzzHost;
+use revm_primitives::{B256, Bytecode, keccak256};
+use crate::evm::eof::{self, EofContainer, RJUMPI, RJUMPV};
+use crate::evm::host::{FuzzHost, GLOBAL_CALL_CONTEXT};
 use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
+use crate::evm::istanbul::write_istanbul_report;
 use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
 use crate::evm::srcmap::parser::{pretty_print_source_map, SourceMapAvailability, SourceMapLocation};
 use crate::evm::srcmap::parser::SourceMapAvailability::Available;
 use crate::generic_vm::vm_state::VMStateT;
 use crate::input::VMInputT;
 use crate::state::{HasCaller, HasCurrentInputIdx, HasItyState};
-use crate::evm::types::{EVMAddress, is_zero, ProjectSourceMapTy};
+use crate::evm::types::{EVMAddress, EVMU256, is_zero, ProjectSourceMapTy};
 use crate::evm::vm::IN_DEPLOY;
 
 
 /// Finds all PCs (offsets of bytecode) that are instructions / JUMPDEST
 /// Returns a tuple of (instruction PCs, JUMPI PCs, Skip PCs)
+///
+/// For EOF containers, PCs are offsets in the image of the code sections, and conditional
+/// relative jumps (RJUMPI / RJUMPV) are counted as JUMPIs
 pub fn instructions_pc(bytecode: &Bytecode) -> (HashSet<usize>, HashSet<usize>, HashSet<usize>) {
+    if let Some(container) = EofContainer::parse(bytecode.bytes()) {
+        let code = &container.image;
+        let pcs = eof::instructions(code);
+        let branches = pcs.iter().filter(|pc| matches!(code[**pc], RJUMPI | RJUMPV)).cloned().collect();
+        let skip = pcs.iter().filter(|pc| matches!(code[**pc], STOP | INVALID)).cloned().collect();
+        return (pcs.into_iter().collect(), branches, skip);
+    }
     let mut i = 0;
     let bytes = bytecode.bytes();
     let mut complete_bytes = vec![];
@@ -58,6 +71,58 @@ pub struct Coverage {
     pub jumpi_coverage: HashMap<EVMAddress, HashSet<(usize, bool)>>,
     pub skip_pcs: HashMap<EVMAddress, HashSet<usize>>,
     pub work_dir: String,
+    /// Hash of the runtime bytecode of each contract
+    pub code_hashes: HashMap<EVMAddress, B256>,
+    /// Aggregate the coverage of contracts sharing bytecode (clones, proxies to the same
+    /// implementation) in the report
+    pub by_code: bool,
+    /// With `by_code`, also report the coverage of each address
+    pub per_address: bool,
+    /// Coverage of the constructors executed when deploying the targets
+    pub constructors: HashMap<EVMAddress, ConstructorCoverage>,
+}
+
+/// Coverage of the execution of the init code of a contract
+#[derive(Clone, Debug, Default)]
+pub struct ConstructorCoverage {
+    pub code: Vec<u8>,
+    pub instructions: HashSet<usize>,
+    pub jumpis: HashSet<usize>,
+    pub covered: HashSet<usize>,
+    pub branches: HashSet<(usize, bool)>,
+}
+
+impl ConstructorCoverage {
+    pub fn new(code: &[u8], eof: Option<&EofContainer>) -> Self {
+        let (instructions, jumpis, skip) = match eof {
+            Some(container) => {
+                let pcs = eof::instructions(&container.image);
+                let jumpis = pcs.iter().filter(|pc| matches!(container.image[**pc], RJUMPI | RJUMPV)).cloned().collect();
+                (pcs.into_iter().collect(), jumpis, HashSet::new())
+            }
+            None => instructions_pc(&Bytecode::new_raw(Bytes::copy_from_slice(code))),
+        };
+        Self {
+            code: code.to_vec(),
+            instructions: instructions.difference(&skip).cloned().collect(),
+            jumpis,
+            ..Default::default()
+        }
+    }
+
+    /// Drop the runtime code (and what follows it, metadata and constructor arguments) embedded
+    /// in the init code, it is not executed by the constructor
+    pub fn strip_runtime(&mut self, runtime: &[u8]) {
+        let prefix = &runtime[..runtime.len().min(64)];
+        if prefix.is_empty() {
+            return;
+        }
+        if let Some(offset) = self.code.windows(prefix.len()).skip(1).position(|w| w == prefix) {
+            let end = offset + 1;
+            self.instructions.retain(|pc| *pc < end);
+            self.jumpis.retain(|pc| *pc < end);
+        }
+    }
 }
 
 
@@ -71,10 +136,104 @@ impl Coverage {
             jumpi_coverage: Default::default(),
             skip_pcs: Default::default(),
             work_dir: "work_dir".to_string(),
+            code_hashes: Default::default(),
+            by_code: false,
+            per_address: false,
+            constructors: Default::default(),
         }
     }
 
+    /// Merge the coverage of contracts sharing bytecode into one of them (preferably one with a
+    /// source map), returns the merged coverage and the clones merged into each contract
+    pub fn dedup_by_code(&self, source_map: &ProjectSourceMapTy) -> (Self, HashMap<EVMAddress, Vec<EVMAddress>>) {
+        let mut by_hash: HashMap<B256, Vec<EVMAddress>> = HashMap::new();
+        for addr in self.total_instr_set.keys().sorted() {
+            by_hash.entry(self.code_hashes[addr]).or_default().push(*addr);
+        }
+
+        let mut deduped = Self {
+            pc_coverage: HashMap::new(),
+            total_instr: HashMap::new(),
+            total_instr_set: HashMap::new(),
+            total_jumpi_set: HashMap::new(),
+            jumpi_coverage: HashMap::new(),
+            skip_pcs: HashMap::new(),
+            ..self.clone()
+        };
+        let mut clones = HashMap::new();
+        for mut addrs in by_hash.into_values() {
+            let canonical = addrs
+                .iter()
+                .position(|addr| matches!(source_map.get(addr), Some(Some(_))))
+                .unwrap_or(0);
+            let canonical = addrs.remove(canonical);
+            deduped.total_instr.insert(canonical, self.total_instr[&canonical]);
+            deduped.total_instr_set.insert(canonical, self.total_instr_set[&canonical].clone());
+            deduped.total_jumpi_set.insert(canonical, self.total_jumpi_set[&canonical].clone());
+            deduped.skip_pcs.insert(canonical, self.skip_pcs[&canonical].clone());
+            let mut pcs = HashSet::new();
+            let mut jumpis = HashSet::new();
+            for addr in addrs.iter().chain(std::iter::once(&canonical)) {
+                pcs.extend(self.pc_coverage.get(addr).into_iter().flatten());
+                jumpis.extend(self.jumpi_coverage.get(addr).into_iter().flatten());
+            }
+            deduped.pc_coverage.insert(canonical, pcs);
+            deduped.jumpi_coverage.insert(canonical, jumpis);
+            clones.insert(canonical, addrs);
+        }
+        (deduped, clones)
+    }
+
     pub fn record_instruction_coverage(&mut self, source_map: &ProjectSourceMapTy) {
+        let data = if self.by_code {
+            let (deduped, clones) = self.dedup_by_code(source_map);
+            let mut data = deduped.report(source_map, &clones);
+            if self.per_address {
+                data.push_str("=================== Per-Address Coverage ===================\n");
+                data.push_str(self.report(source_map, &HashMap::new()).as_str());
+            }
+            data
+        } else {
+            self.report(source_map, &HashMap::new())
+        };
+        let data = data + &self.constructor_report();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .append(false)
+            .create(true)
+            .open(format!("{}/cov_{}.txt", self.work_dir.clone(), SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()))
+            .unwrap();
+        file.write_all(data.as_bytes()).unwrap();
+        write_istanbul_report(self, source_map);
+    }
+
+    /// Coverage of the constructors, instructions and branches not covered are listed by PC
+    fn constructor_report(&self) -> String {
+        let mut data = "=================== Constructor Coverage ===================\n".to_string();
+        for (addr, cov) in self.constructors.iter().sorted_by_key(|(addr, _)| **addr) {
+            let covered = cov.instructions.intersection(&cov.covered).count();
+            let branches = cov.branches.iter().filter(|(pc, _)| cov.jumpis.contains(pc)).count();
+            data.push_str(&format!(
+                "Contract: {:?}, Instruction Coverage: {} / {} ({:.2}%), Branch Coverage: {} / {}\n",
+                addr,
+                covered,
+                cov.instructions.len(),
+                covered as f64 / cov.instructions.len().max(1) as f64 * 100.0,
+                branches,
+                cov.jumpis.len() * 2
+            ));
+            let not_covered = cov.instructions.difference(&cov.covered).sorted().collect_vec();
+            if !not_covered.is_empty() {
+                data.push_str(&format!("Not Covered PC: {:?}\n", not_covered));
+            }
+        }
+        crate::status!("\n{}", data);
+        data + "\n"
+    }
+
+    /// Coverage report, contracts are annotated with the number of clones merged into them
+    fn report(&self, source_map: &ProjectSourceMapTy, clones: &HashMap<EVMAddress, Vec<EVMAddress>>) -> String {
         // println!("total_instr: {:?}", self.total_instr);
         // println!("total_instr_set: {:?}", self.total_instr_set);
         // println!("pc_coverage: {:?}",  self.pc_coverage);
@@ -227,6 +386,10 @@ impl Coverage {
             "=================== Coverage Report ===================\n",
 
         );
+        let clones_of = |k: &EVMAddress| match clones.get(k) {
+            Some(addrs) if !addrs.is_empty() => format!(" (+{} clones)", addrs.len()),
+            _ => String::new(),
+        };
 
         self.total_instr
             .keys()
@@ -234,8 +397,9 @@ impl Coverage {
                 let cov = real_pc_coverage.get(k).unwrap_or(&Default::default()).len();
                 let total = real_total_instr_set.get(k).unwrap_or(&Default::default()).len();
                 if total > 2 {
-                    data.push_str(format!("Contract: {:?}, Instruction Coverage: {} / {} ({:.2}%)\n",
+                    data.push_str(format!("Contract: {:?}{}, Instruction Coverage: {} / {} ({:.2}%)\n",
                             k,
+                            clones_of(k),
                             cov,
                             total,
                             cov as f64 / total as f64 * 100.0
@@ -248,8 +412,9 @@ impl Coverage {
             .for_each(|k| {
                 let (cov, total) = branch_coverage.get(k).unwrap_or(&(0, 1));
                 if *total > 2 {
-                    data.push_str(format!("Contract: {:?}, Branch Coverage: {} / {} ({:.2}%)\n",
+                    data.push_str(format!("Contract: {:?}{}, Branch Coverage: {} / {} ({:.2}%)\n",
                                           k,
+                                          clones_of(k),
                                           *cov,
                                           *total,
                                           *cov as f64 / *total as f64 * 100.0
@@ -257,18 +422,11 @@ impl Coverage {
                 }
             });
 
-        println!("\n\n{}", data);
+        crate::status!("\n\n{}", data);
 
         data.push_str(detail_cov_report.as_str());
         data.push_str("\n\n\n");
-
-        let mut file = OpenOptions::new()
-            .write(true)
-            .append(false)
-            .create(true)
-            .open(format!("{}/cov_{}.txt", self.work_dir.clone(), SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()))
-            .unwrap();
-        file.write_all(data.as_bytes()).unwrap();
+        data
     }
 }
 
@@ -293,16 +451,50 @@ impl<I, VS, S> Middleware<VS, I, S> for Coverage
         state: &mut S,
     ) {
         if IN_DEPLOY {
+            // constructors run once, when deploying the targets
+            let address = interp.contract.address;
+            if host.code.contains_key(&address) {
+                return;
+            }
+            let constructor = self.constructors.entry(address).or_insert_with(|| {
+                ConstructorCoverage::new(
+                    interp.contract.bytecode.original_bytecode_slice(),
+                    host.eof.get(&address).map(|c| c.as_ref()),
+                )
+            });
+            let pc = interp.program_counter();
+            constructor.covered.insert(pc);
+            match *interp.instruction_pointer {
+                JUMPI => {
+                    constructor.branches.insert((pc, is_zero(interp.stack.peek(1).unwrap())));
+                }
+                RJUMPI if host.eof.contains_key(&address) => {
+                    constructor.branches.insert((pc, is_zero(interp.stack.peek(0).unwrap())));
+                }
+                _ => {}
+            }
             return;
         }
-        let address = interp.contract.address;
+        // attribute coverage to the code being executed (e.g., facets / implementations
+        // behind a proxy) instead of the storage context
+        let address = match GLOBAL_CALL_CONTEXT {
+            Some(ref ctx) if self.total_instr_set.contains_key(&ctx.code_address) => ctx.code_address,
+            _ => interp.contract.address,
+        };
         let pc = interp.program_counter().clone();
         self.pc_coverage.entry(address).or_default().insert(pc);
 
-        if *interp.instruction_pointer == JUMPI {
-            let condition = is_zero(interp.stack.peek(1).unwrap());
-            self.jumpi_coverage.entry(address).or_default().insert((pc, condition));
-        }
+        let condition = match *interp.instruction_pointer {
+            JUMPI => is_zero(interp.stack.peek(1).unwrap()),
+            RJUMPI if host.eof.contains_key(&interp.contract.address) => is_zero(interp.stack.peek(0).unwrap()),
+            // fall through of RJUMPV (case out of the jump table) as the not taken branch
+            RJUMPV if host.eof.contains_key(&interp.contract.address) => {
+                let cases = *interp.instruction_pointer.add(1) as usize + 1;
+                interp.stack.peek(0).unwrap() >= EVMU256::from(cases)
+            }
+            _ => return,
+        };
+        self.jumpi_coverage.entry(address).or_default().insert((pc, condition));
     }
 
     unsafe fn on_insert(&mut self, bytecode: &mut Bytecode, address: EVMAddress, host: &mut FuzzHost<VS, I, S>, state: &mut S) {
@@ -312,6 +504,10 @@ impl<I, VS, S> Middleware<VS, I, S> for Coverage
         self.total_instr_set.insert(address, pcs);
         self.skip_pcs.insert(address, skip_pcs);
         self.total_jumpi_set.insert(address, jumpis);
+        self.code_hashes.insert(address, keccak256(bytecode.bytes()));
+        if let Some(constructor) = self.constructors.get_mut(&address) {
+            constructor.strip_runtime(bytecode.bytes());
+        }
     }
 
     fn get_type(&self) -> MiddlewareType {
@@ -334,4 +530,55 @@ mod tests {
 
         assert_eq!(pcs.len(), 1107);
     }
+
+    #[test]
+    fn test_dedup_by_code() {
+        let mut cov = Coverage::new();
+        let clone_a = EVMAddress::from_slice(&[1; 20]);
+        let clone_b = EVMAddress::from_slice(&[2; 20]);
+        let other = EVMAddress::from_slice(&[3; 20]);
+        for (addr, code) in [(clone_a, "6001600157"), (clone_b, "6001600157"), (other, "600100")] {
+            let (pcs, jumpis, skip_pcs) = instructions_pc(&Bytecode::new_raw(Bytes::from(hex::decode(code).unwrap())));
+            cov.total_instr.insert(addr, pcs.len());
+            cov.total_instr_set.insert(addr, pcs);
+            cov.total_jumpi_set.insert(addr, jumpis);
+            cov.skip_pcs.insert(addr, skip_pcs);
+            cov.code_hashes.insert(addr, keccak256(&hex::decode(code).unwrap()));
+        }
+        cov.pc_coverage.insert(clone_a, HashSet::from([0, 2]));
+        cov.pc_coverage.insert(clone_b, HashSet::from([0, 4]));
+        cov.jumpi_coverage.insert(clone_b, HashSet::from([(4, true)]));
+
+        // clone_b has a source map, so it is the one reported
+        let source_map = HashMap::from([(clone_b, Some(HashMap::new()))]);
+        let (deduped, clones) = cov.dedup_by_code(&source_map);
+        assert_eq!(deduped.total_instr.len(), 2);
+        assert_eq!(clones[&clone_b], vec![clone_a]);
+        assert!(clones[&other].is_empty());
+        assert_eq!(deduped.pc_coverage[&clone_b], HashSet::from([0, 2, 4]));
+        assert_eq!(deduped.jumpi_coverage[&clone_b], HashSet::from([(4, true)]));
+    }
+
+    #[test]
+    fn test_constructor_coverage() {
+        // PUSH1 1 PUSH1 7 JUMPI PUSH1 0, followed by the runtime code PUSH1 1 PUSH1 1 STOP
+        let runtime = hex::decode("6001600100").unwrap();
+        let init = [hex::decode("60016007576000").unwrap(), runtime.clone()].concat();
+        let mut cov = ConstructorCoverage::new(&init, None);
+        assert_eq!(cov.instructions.len(), 6);
+        assert_eq!(cov.jumpis, HashSet::from([4]));
+
+        // the runtime code embedded is not executed by the constructor
+        cov.strip_runtime(&runtime);
+        assert_eq!(cov.instructions, HashSet::from([0, 2, 4, 5]));
+        cov.covered.extend([0, 2, 4]);
+        cov.branches.insert((4, false));
+
+        let mut coverage = Coverage::new();
+        let address = EVMAddress::from_slice(&[1; 20]);
+        coverage.constructors.insert(address, cov);
+        let report = coverage.constructor_report();
+        assert!(report.contains("Instruction Coverage: 3 / 4 (75.00%), Branch Coverage: 1 / 2"));
+        assert!(report.contains("Not Covered PC: [5]"));
+    }
 }
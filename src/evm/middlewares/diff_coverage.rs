@@ -0,0 +1,92 @@
+/// Tracks executions reaching changed code (`--focus-diff`)
+///
+/// Changed PCs are reported as coverage in the jump map (so that inputs reaching them are kept
+/// in the corpus), and [`DIFF_HITS`] counts how many times they were executed (used by the
+/// power schedule to give more energy to inputs reaching them).
+use std::collections::hash_map::DefaultHasher;
+use tracing::info;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+
+use crate::evm::host::{FuzzHost, GLOBAL_CALL_CONTEXT, JMP_MAP};
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::EVMAddress;
+use crate::evm::vm::IN_DEPLOY;
+use crate::generic_vm::vm_executor::MAP_SIZE;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+/// Times changed PCs were executed
+pub static mut DIFF_HITS: u64 = 0;
+
+#[derive(Clone, Debug, Default)]
+pub struct DiffCoverage {
+    /// Changed PCs of each contract
+    pub targets: HashMap<EVMAddress, HashSet<usize>>,
+    /// Changed PCs already executed
+    pub covered: HashSet<(EVMAddress, usize)>,
+}
+
+impl DiffCoverage {
+    pub fn new(targets: HashMap<EVMAddress, HashSet<usize>>) -> Self {
+        Self {
+            targets,
+            covered: HashSet::new(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.targets.values().map(|pcs| pcs.len()).sum()
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for DiffCoverage
+where
+    I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+    VS: VMStateT,
+    S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(&mut self, interp: &mut Interpreter, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {
+        if IN_DEPLOY {
+            return;
+        }
+        let address = match GLOBAL_CALL_CONTEXT {
+            Some(ref ctx) if self.targets.contains_key(&ctx.code_address) => ctx.code_address,
+            _ => interp.contract.address,
+        };
+        let pc = interp.program_counter();
+        if !self.targets.get(&address).map_or(false, |pcs| pcs.contains(&pc)) {
+            return;
+        }
+        DIFF_HITS += 1;
+        let mut hasher = DefaultHasher::new();
+        (address, pc).hash(&mut hasher);
+        let idx = (hasher.finish() as usize) % MAP_SIZE;
+        if JMP_MAP[idx] < 255 {
+            JMP_MAP[idx] += 1;
+        }
+        if self.covered.insert((address, pc)) {
+            info!("[focus-diff] reached {}/{} changed PCs", self.covered.len(), self.total());
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::DiffCoverage
+    }
+}
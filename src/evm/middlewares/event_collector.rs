@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{as_u64, EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const LOG1: u8 = 0xa1;
+const LOG4: u8 = 0xa4;
+/// Events recorded per sequence, later events are dropped
+pub const MAX_EVENTS: usize = 1024;
+/// Bytes of data recorded per event
+const MAX_EVENT_DATA: usize = 1024;
+
+/// An event emitted in a sequence
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EmittedEvent {
+    pub address: EVMAddress,
+    pub topics: Vec<EVMU256>,
+    pub data: Vec<u8>,
+}
+
+/// Records the events of interest (by topic0) emitted along a sequence into
+/// [`crate::evm::vm::EVMState::events`], for the invariants of
+/// [`crate::evm::oracles::event::EventOracle`]. Events of calls reverted later in the
+/// transaction are recorded as well.
+#[derive(Clone, Debug, Default)]
+pub struct EventCollector {
+    pub topics: HashSet<EVMU256>,
+}
+
+impl EventCollector {
+    pub fn new(topics: HashSet<EVMU256>) -> Self {
+        Self { topics }
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for EventCollector
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let op = *interp.instruction_pointer;
+        if !(LOG1..=LOG4).contains(&op) || host.evmstate.events.len() >= MAX_EVENTS {
+            return;
+        }
+        let count = (op - LOG1 + 1) as usize;
+        if interp.stack.len() < 2 + count || !self.topics.contains(&interp.stack.peek(2).unwrap()) {
+            return;
+        }
+        let topics = (0..count).map(|idx| interp.stack.peek(2 + idx).unwrap()).collect();
+        let (offset, size) = (as_u64(interp.stack.peek(0).unwrap()) as usize, as_u64(interp.stack.peek(1).unwrap()) as usize);
+        let memory = interp.memory.data();
+        let data = match offset.checked_add(size.min(MAX_EVENT_DATA)) {
+            Some(end) if end <= memory.len() => memory[offset..end].to_vec(),
+            // memory is expanded (zeroed) by the LOG itself
+            _ => {
+                let mut data = memory.get(offset..).unwrap_or_default().to_vec();
+                data.resize(size.min(MAX_EVENT_DATA), 0);
+                data
+            }
+        };
+        host.evmstate.events.push(EmittedEvent {
+            address: interp.contract.address,
+            topics,
+            data,
+        });
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::EventCollector
+    }
+}
@@ -29,7 +29,24 @@ pub enum MiddlewareType {
     InstructionCoverage,
     BranchCoverage,
     Sha3Bypass,
-    Sha3TaintAnalysis
+    Sha3TaintAnalysis,
+    Upgrade,
+    CallTracer,
+    OriginAuth,
+    Truncation,
+    Rounding,
+    PriceMover,
+    Script,
+    DiffCoverage,
+    Chainlink,
+    BlockContext,
+    Constructor,
+    UncheckedCall,
+    EventCollector,
+    CallValue,
+    NativeFlow,
+    /// Middlewares implemented outside of ItyFuzz, distinguished by their id
+    Custom(u32),
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Copy)]
@@ -1,4 +1,20 @@
 pub mod coverage;
 pub mod middleware;
+pub mod block_context;
 pub mod branch_coverage;
+pub mod call_tracer;
+pub mod call_value;
+pub mod chainlink;
+pub mod constructor;
+pub mod diff_coverage;
+pub mod event_collector;
+pub mod native_flow;
+pub mod origin_auth;
+pub mod price_mover;
+pub mod rounding;
+#[cfg(feature = "scripting")]
+pub mod script;
 pub mod sha3_bypass;
+pub mod truncation;
+pub mod unchecked_call;
+pub mod upgrade;
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{convert_u256_to_h160, EVMAddress, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const CALL: u8 = 0xf1;
+const SELFDESTRUCT: u8 = 0xff;
+
+/// Contracts whose native ETH balance is tracked (the contracts of `--eth-accounting`). Other
+/// accounts keep an unlimited balance.
+pub static mut NATIVE_TRACKED: Option<HashSet<EVMAddress>> = None;
+
+pub fn is_tracked(address: &EVMAddress) -> bool {
+    unsafe { NATIVE_TRACKED.as_ref().map_or(false, |tracked| tracked.contains(address)) }
+}
+
+/// ETH held by an account in a state, None if it is not tracked
+pub fn native_balance(state: &EVMState, address: &EVMAddress) -> Option<EVMU256> {
+    if !is_tracked(address) {
+        return None;
+    }
+    Some(state.native_balances.get(address).cloned().unwrap_or(EVMU256::ZERO))
+}
+
+/// Move ETH between accounts, only the tracked sides are accounted
+pub fn transfer(state: &mut EVMState, from: Option<EVMAddress>, to: EVMAddress, value: EVMU256) {
+    if value == EVMU256::ZERO {
+        return;
+    }
+    if let Some(from) = from.filter(is_tracked) {
+        let balance = state.native_balances.entry(from).or_default();
+        *balance = balance.saturating_sub(value);
+    }
+    if is_tracked(&to) {
+        let balance = state.native_balances.entry(to).or_default();
+        *balance = balance.saturating_add(value);
+    }
+}
+
+/// Tracks the native ETH sent by CALLs with value (once they succeed) and the balances
+/// transferred by SELFDESTRUCT (force-fed to the beneficiary, whose code doesn't run), in
+/// [`crate::evm::vm::EVMState::native_balances`]. ETH sent along with the transactions and
+/// force-fed before them is accounted by the VM.
+///
+/// Transfers of calls succeeding in a frame that reverts later are not rolled back.
+#[derive(Clone, Debug, Default)]
+pub struct NativeFlowTracker {
+    /// Interpreter => (from, to, value, stack height without the result) of the call executing
+    calling: HashMap<usize, (EVMAddress, EVMAddress, EVMU256, usize)>,
+}
+
+impl NativeFlowTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for NativeFlowTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let key = interp as *const Interpreter as usize;
+        if interp.program_counter() == 0 {
+            self.calling.remove(&key);
+        }
+        // the call returned, its success flag is on top of the stack
+        if let Some((from, to, value, base)) = self.calling.remove(&key) {
+            if interp.stack.len() == base + 1 && interp.stack.peek(0).unwrap() != EVMU256::ZERO {
+                transfer(&mut host.evmstate, Some(from), to, value);
+            }
+        }
+
+        match *interp.instruction_pointer {
+            CALL if interp.stack.len() >= 7 => {
+                let value = interp.stack.peek(2).unwrap();
+                let from = interp.contract.address;
+                let to = convert_u256_to_h160(interp.stack.peek(1).unwrap());
+                if value != EVMU256::ZERO && (is_tracked(&from) || is_tracked(&to)) {
+                    self.calling.insert(key, (from, to, value, interp.stack.len() - 7));
+                }
+            }
+            SELFDESTRUCT if interp.stack.len() >= 1 => {
+                let from = interp.contract.address;
+                if let Some(balance) = native_balance(&host.evmstate, &from) {
+                    let to = convert_u256_to_h160(interp.stack.peek(0).unwrap());
+                    transfer(&mut host.evmstate, Some(from), to, balance);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::NativeFlow
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_transfers() {
+        let (vault, user, other) = (
+            EVMAddress::from_slice(&[1; 20]),
+            EVMAddress::from_slice(&[2; 20]),
+            EVMAddress::from_slice(&[3; 20]),
+        );
+        unsafe {
+            NATIVE_TRACKED = Some(HashSet::from([vault, other]));
+        }
+        let mut state = EVMState::default();
+        assert_eq!(native_balance(&state, &vault), Some(EVMU256::ZERO));
+        assert_eq!(native_balance(&state, &user), None);
+
+        // deposit of an untracked user, then a withdrawal larger than the balance
+        transfer(&mut state, Some(user), vault, EVMU256::from(100));
+        transfer(&mut state, Some(vault), other, EVMU256::from(30));
+        assert_eq!(native_balance(&state, &vault), Some(EVMU256::from(70)));
+        assert_eq!(native_balance(&state, &other), Some(EVMU256::from(30)));
+        transfer(&mut state, Some(vault), user, EVMU256::from(500));
+        assert_eq!(native_balance(&state, &vault), Some(EVMU256::ZERO));
+        unsafe {
+            NATIVE_TRACKED = None;
+        }
+    }
+}
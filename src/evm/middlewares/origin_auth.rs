@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT, INTERMEDIARY};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{convert_h160_to_u256, fixed_address, EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const ORIGIN: u8 = 0x32;
+const EQ: u8 = 0x14;
+
+/// Records authorization checks based on tx.origin (`tx.origin == owner`) that pass while
+/// msg.sender differs from tx.origin, i.e., the check is passed by an intermediary contract
+/// (e.g., a phishing contract the owner interacts with).
+///
+/// An EQ is considered such a check when the contract read ORIGIN earlier and both operands
+/// are the origin (contracts reading ORIGIN are forgotten when a transaction starts). Passed
+/// checks (contract, pc of the EQ) are recorded in [`crate::evm::vm::EVMState::origin_auth`].
+#[derive(Clone, Debug, Default)]
+pub struct OriginAuth {
+    /// Contracts that read ORIGIN
+    pub origin_readers: HashSet<EVMAddress>,
+}
+
+impl OriginAuth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track an instruction of `contract` (called by `caller`) about to execute on `stack`
+    /// (bottom first), returns the pc of a passed tx.origin check
+    fn step(
+        &mut self,
+        contract: EVMAddress,
+        caller: EVMAddress,
+        origin: EVMAddress,
+        pc: usize,
+        op: u8,
+        stack: &[EVMU256],
+    ) -> Option<usize> {
+        // a call from tx.origin (or through the intermediary) starting is a new transaction
+        if pc == 0 && (caller == origin || caller == fixed_address(INTERMEDIARY)) {
+            self.origin_readers.clear();
+        }
+        match op {
+            ORIGIN => {
+                self.origin_readers.insert(contract);
+                None
+            }
+            EQ => {
+                if caller == origin || !self.origin_readers.contains(&contract) {
+                    return None;
+                }
+                let origin = convert_h160_to_u256(origin);
+                let len = stack.len();
+                (len >= 2 && stack[len - 1] == origin && stack[len - 2] == origin).then_some(pc)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for OriginAuth
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        if let Some(pc) = self.step(
+            interp.contract.address,
+            interp.contract.caller,
+            host.origin,
+            interp.program_counter(),
+            *interp.instruction_pointer,
+            interp.stack.data(),
+        ) {
+            host.evmstate.origin_auth.insert((interp.contract.address, pc));
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::OriginAuth
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_check() {
+        let (contract, origin) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let intermediary = fixed_address(INTERMEDIARY);
+        let word = convert_h160_to_u256(origin);
+        let mut tracker = OriginAuth::new();
+
+        // called by the owner directly, not through an intermediary
+        assert_eq!(tracker.step(contract, origin, origin, 0, ORIGIN, &[]), None);
+        assert_eq!(tracker.step(contract, origin, origin, 5, EQ, &[word, word]), None);
+
+        assert_eq!(tracker.step(contract, intermediary, origin, 0, ORIGIN, &[]), None);
+        assert_eq!(tracker.step(contract, intermediary, origin, 5, EQ, &[word, EVMU256::ZERO]), None);
+        assert_eq!(tracker.step(contract, intermediary, origin, 5, EQ, &[word, word]), Some(5));
+    }
+
+    #[test]
+    fn test_readers_reset() {
+        let (contract, origin) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let intermediary = fixed_address(INTERMEDIARY);
+        let word = convert_h160_to_u256(origin);
+        let mut tracker = OriginAuth::new();
+
+        tracker.step(contract, intermediary, origin, 3, ORIGIN, &[]);
+        assert!(tracker.origin_readers.contains(&contract));
+        // the next transaction starts without the readers of the previous one
+        assert_eq!(tracker.step(contract, intermediary, origin, 0, 0x60, &[]), None);
+        assert!(tracker.origin_readers.is_empty());
+        assert_eq!(tracker.step(contract, intermediary, origin, 5, EQ, &[word, word]), None);
+    }
+}
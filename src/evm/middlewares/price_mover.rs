@@ -0,0 +1,142 @@
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use crate::evm::contract_utils::{set_hash, ABIConfig, ContractInfo};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+use crate::evm::types::{fixed_address, EVMAddress, EVMU256};
+
+/// Address of the dummy contract, calling it moves a price
+pub const PRICE_TRIGGER: &str = "00000000000000000000000000000000009e1ce5";
+/// Function exposed by the dummy contract, (index of the price feed, new price in the bounds)
+pub const MOVE_PRICE_FUNCTION: &str = "ityfuzz_move_price(uint256,uint256)";
+
+/// A price stored in a feed, resolved
+#[derive(Clone, Debug)]
+pub struct Price {
+    pub feed: EVMAddress,
+    pub slot: EVMU256,
+    pub base: Option<EVMU256>,
+    pub min_percent: u64,
+    pub max_percent: u64,
+}
+
+impl Price {
+    /// Price at a point of the bounds, in percent of the base price
+    fn moved(&self, base: EVMU256, point: EVMU256) -> EVMU256 {
+        let range = EVMU256::from(self.max_percent - self.min_percent + 1);
+        let percent = EVMU256::from(self.min_percent) + point % range;
+        base.saturating_mul(percent) / EVMU256::from(100)
+    }
+}
+
+/// Moves prices of lending scenarios within their bounds whenever the price trigger is called,
+/// so that the fuzzer can interleave price changes with transactions of the actors.
+#[derive(Clone, Debug)]
+pub struct PriceMover {
+    pub prices: Vec<Price>,
+    pub trigger: EVMAddress,
+}
+
+impl PriceMover {
+    pub fn new(prices: Vec<Price>) -> Self {
+        assert!(!prices.is_empty(), "no price to move");
+        Self {
+            prices,
+            trigger: fixed_address(PRICE_TRIGGER),
+        }
+    }
+}
+
+/// Dummy contract (a single STOP) exposing [`MOVE_PRICE_FUNCTION`]
+pub fn price_trigger_contract() -> ContractInfo {
+    let mut abi = ABIConfig {
+        abi: "(uint256,uint256)".to_string(),
+        function: [0; 4],
+        function_name: "ityfuzz_move_price".to_string(),
+        is_static: false,
+        is_payable: false,
+        is_constructor: false,
+        input_names: vec!["feed".to_string(), "point".to_string()],
+    };
+    set_hash(MOVE_PRICE_FUNCTION, &mut abi.function);
+    ContractInfo {
+        name: "PriceTrigger".to_string(),
+        code: vec![0x00],
+        abi: vec![abi],
+        is_code_deployed: true,
+        constructor_args: vec![],
+        deployed_address: fixed_address(PRICE_TRIGGER),
+        source_map: None,
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for PriceMover
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        if interp.contract.address != self.trigger || interp.program_counter() != 0 {
+            return;
+        }
+        let input = &interp.contract.input;
+        if input.len() < 68 {
+            return;
+        }
+        let idx = EVMU256::from_be_slice(&input[4..36]) % EVMU256::from(self.prices.len());
+        let point = EVMU256::from_be_slice(&input[36..68]);
+        let price = &mut self.prices[idx.as_limbs()[0] as usize];
+
+        let account = host.evmstate.get_or_default(price.feed);
+        // the price in the feed is the base price if not configured
+        let base = *price
+            .base
+            .get_or_insert_with(|| account.get(&price.slot).cloned().unwrap_or_default());
+        account.insert(price.slot, price.moved(base, point));
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::PriceMover
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moved() {
+        let price = Price {
+            feed: EVMAddress::zero(),
+            slot: EVMU256::ZERO,
+            base: None,
+            min_percent: 50,
+            max_percent: 150,
+        };
+        let base = EVMU256::from(1000);
+        assert_eq!(price.moved(base, EVMU256::ZERO), EVMU256::from(500));
+        assert_eq!(price.moved(base, EVMU256::from(100)), EVMU256::from(1500));
+        // wraps around the bounds
+        assert_eq!(price.moved(base, EVMU256::from(101)), EVMU256::from(500));
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{EVMAddress, EVMU256, EVMU512};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const ADD: u8 = 0x01;
+const MUL: u8 = 0x02;
+const SUB: u8 = 0x03;
+const DIV: u8 = 0x04;
+const SSTORE: u8 = 0x55;
+
+/// Call frames tracked before they are dropped
+const MAX_FRAMES: usize = 1024;
+/// Scale of relative errors (parts per million)
+const PPM: u64 = 1_000_000;
+
+/// Exact value of a computation, numerator / denominator
+type Fraction = (EVMU512, EVMU512);
+
+/// A stored value diverging from the result of the same computation in arbitrary precision
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RoundingError {
+    pub contract: EVMAddress,
+    /// pc of the SSTORE
+    pub pc: usize,
+    pub slot: EVMU256,
+    pub stored: EVMU256,
+    /// Exact value, in decimal with 6 fractional digits
+    pub exact: String,
+    /// Relative error in parts per million
+    pub error_ppm: u64,
+}
+
+fn gcd(mut a: EVMU512, mut b: EVMU512) -> EVMU512 {
+    while b != EVMU512::ZERO {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+fn reduce((n, d): Fraction) -> Fraction {
+    let g = gcd(n, d);
+    if g <= EVMU512::from(1) {
+        (n, d)
+    } else {
+        (n / g, d / g)
+    }
+}
+
+fn mul(a: Fraction, b: Fraction) -> Option<Fraction> {
+    Some(reduce((a.0.checked_mul(b.0)?, a.1.checked_mul(b.1)?)))
+}
+
+fn div(a: Fraction, b: Fraction) -> Option<Fraction> {
+    if b.0 == EVMU512::ZERO {
+        return None;
+    }
+    Some(reduce((a.0.checked_mul(b.1)?, a.1.checked_mul(b.0)?)))
+}
+
+fn add(a: Fraction, b: Fraction) -> Option<Fraction> {
+    let n = a.0.checked_mul(b.1)?.checked_add(b.0.checked_mul(a.1)?)?;
+    Some(reduce((n, a.1.checked_mul(b.1)?)))
+}
+
+fn sub(a: Fraction, b: Fraction) -> Option<Fraction> {
+    let n = a.0.checked_mul(b.1)?.checked_sub(b.0.checked_mul(a.1)?)?;
+    Some(reduce((n, a.1.checked_mul(b.1)?)))
+}
+
+fn to_decimal((n, d): Fraction) -> String {
+    let fractional = ((n % d) * EVMU512::from(PPM) / d).as_limbs()[0];
+    format!("{}.{:06}", n / d, fractional)
+}
+
+/// Relative error of `value` to the exact value, in parts per million
+fn error_ppm(value: EVMU256, (n, d): Fraction) -> Option<u64> {
+    if n == EVMU512::ZERO {
+        return None;
+    }
+    let scaled = EVMU512::from(value).checked_mul(d)?;
+    let diff = if scaled > n { scaled - n } else { n - scaled };
+    let ppm = diff.checked_mul(EVMU512::from(PPM))? / n;
+    Some(if ppm > EVMU512::from(u64::MAX) { u64::MAX } else { ppm.as_limbs()[0] })
+}
+
+/// Whether a stored value is off its exact value by a unit or more, i.e., by more than a single
+/// rounding (e.g., a fee `amount * 3 / 1000`) can be
+fn exceeds_unit(value: EVMU256, (n, d): Fraction) -> bool {
+    match EVMU512::from(value).checked_mul(d) {
+        Some(scaled) => (if scaled > n { scaled - n } else { n - scaled }) >= d,
+        None => false,
+    }
+}
+
+/// Whether an instruction pushes a result (DUP / SWAP aside)
+fn pushes(op: u8) -> bool {
+    !matches!(
+        op,
+        0x00 | 0x37 | 0x39 | 0x3c | 0x3e | 0x50 | 0x52 | 0x53 | 0x55 | 0x56 | 0x57 | 0x5b | 0x5d | 0x5e
+            | 0xa0..=0xa4 | 0xf3 | 0xfd | 0xfe | 0xff
+    )
+}
+
+/// Exact values on the stack of a call frame
+#[derive(Clone, Debug, Default)]
+struct Frame {
+    /// stack index => integer value and exact value of the result of a shadowed instruction
+    slots: HashMap<usize, (EVMU256, Fraction)>,
+    /// last instruction executed
+    last: Option<u8>,
+    /// exact value of the result of the last instruction
+    pending: Option<Fraction>,
+}
+
+impl Frame {
+    /// Exact value of the stack item at `idx`, if it is the result of a shadowed instruction
+    fn exact(&self, stack: &[EVMU256], idx: usize) -> Option<Fraction> {
+        self.slots
+            .get(&idx)
+            .filter(|(value, _)| *value == stack[idx])
+            .map(|(_, exact)| *exact)
+    }
+
+    fn fraction(&self, stack: &[EVMU256], idx: usize) -> Fraction {
+        self.exact(stack, idx)
+            .unwrap_or((EVMU512::from(stack[idx]), EVMU512::from(1)))
+    }
+
+    /// Shadow an instruction about to execute on `stack` (bottom first), returns the slot, value
+    /// and exact value of a SSTORE of a tracked value
+    fn step(&mut self, op: u8, stack: &[EVMU256]) -> Option<(EVMU256, EVMU256, Fraction)> {
+        let len = stack.len();
+        if let Some(last) = self.last.take() {
+            if !(0x80..=0x9f).contains(&last) {
+                // items popped or pushed by the last instruction lose their exact value
+                let base = if pushes(last) { len.saturating_sub(1) } else { len };
+                self.slots.retain(|idx, _| *idx < base);
+                if let Some((n, d)) = self.pending.take() {
+                    let bound = EVMU512::from(EVMU256::MAX) + EVMU512::from(1);
+                    // exact results, results truncated to 0 and overflowed results are not tracked
+                    let exact = len > 0 && d == EVMU512::from(1) && n == EVMU512::from(stack[len - 1]);
+                    if len > 0 && !exact && n / d < bound && stack[len - 1] != EVMU256::ZERO {
+                        self.slots.insert(len - 1, (stack[len - 1], (n, d)));
+                    }
+                }
+            }
+        }
+        self.last = Some(op);
+
+        match op {
+            // DUPn copies the exact value of the item
+            0x80..=0x8f => {
+                let n = (op - 0x7f) as usize;
+                if let Some(exact) = len.checked_sub(n).and_then(|idx| self.slots.get(&idx).cloned()) {
+                    self.slots.insert(len, exact);
+                }
+            }
+            // SWAPn exchanges the exact values
+            0x90..=0x9f => {
+                let n = (op - 0x8f) as usize;
+                if len > n {
+                    let (top, other) = (len - 1, len - 1 - n);
+                    let (top_exact, other_exact) = (self.slots.remove(&top), self.slots.remove(&other));
+                    if let Some(exact) = top_exact {
+                        self.slots.insert(other, exact);
+                    }
+                    if let Some(exact) = other_exact {
+                        self.slots.insert(top, exact);
+                    }
+                }
+            }
+            DIV | MUL | ADD | SUB if len >= 2 => {
+                let (a, b) = (len - 1, len - 2);
+                let tracked = self.exact(stack, a).is_some() || self.exact(stack, b).is_some();
+                let (a, b) = (self.fraction(stack, a), self.fraction(stack, b));
+                self.pending = match op {
+                    DIV => div(a, b),
+                    MUL if tracked => mul(a, b),
+                    ADD if tracked => add(a, b),
+                    SUB if tracked => sub(a, b),
+                    _ => None,
+                };
+            }
+            SSTORE if len >= 2 => {
+                return self
+                    .exact(stack, len - 2)
+                    .map(|exact| (stack[len - 1], stack[len - 2], exact));
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Shadows MUL / DIV (and ADD / SUB on their results) with arbitrary-precision rational
+/// arithmetic, and records stored values diverging from their exact value by a unit or more and
+/// by more than `epsilon_ppm` (e.g., rounding errors amplified by a multiplication after a
+/// division, or accumulated over several operations) in
+/// [`crate::evm::vm::EVMState::rounding_errors`].
+///
+/// Exact values follow the stack items produced by the shadowed instructions (through DUP and
+/// SWAP), they are lost once the items are popped or stored in memory. Results truncated to 0
+/// are not tracked.
+#[derive(Clone, Debug, Default)]
+pub struct RoundingTracker {
+    pub epsilon_ppm: u64,
+    /// interpreter => exact values on its stack
+    frames: HashMap<usize, Frame>,
+}
+
+impl RoundingTracker {
+    /// `epsilon` is the tolerated relative error (e.g., 0.01 for 1%)
+    pub fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon_ppm: (epsilon * PPM as f64) as u64,
+            ..Default::default()
+        }
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for RoundingTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let key = interp as *const Interpreter as usize;
+        if interp.program_counter() == 0 {
+            self.frames.remove(&key);
+        }
+        if self.frames.len() >= MAX_FRAMES {
+            self.frames.clear();
+        }
+        let frame = self.frames.entry(key).or_default();
+        let (slot, value, exact) = match frame.step(*interp.instruction_pointer, interp.stack.data()) {
+            Some(stored) => stored,
+            None => return,
+        };
+        if !exceeds_unit(value, exact) {
+            return;
+        }
+        match error_ppm(value, exact) {
+            Some(error_ppm) if error_ppm > self.epsilon_ppm => {
+                let (contract, pc) = (interp.contract.address, interp.program_counter());
+                let errors = &mut host.evmstate.rounding_errors;
+                if !errors.iter().any(|e| e.contract == contract && e.pc == pc) {
+                    errors.push(RoundingError {
+                        contract,
+                        pc,
+                        slot,
+                        stored: value,
+                        exact: to_decimal(exact),
+                        error_ppm,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::Rounding
+    }
+}
+
+mod tests {
+    use super::*;
+
+    fn frac(n: u64, d: u64) -> Fraction {
+        (EVMU512::from(n), EVMU512::from(d))
+    }
+
+    #[test]
+    fn test_fraction() {
+        // 10 * 3 / 4 = 7.5
+        let exact = div(mul(frac(10, 1), frac(3, 1)).unwrap(), frac(4, 1)).unwrap();
+        assert_eq!(exact, frac(15, 2));
+        assert_eq!(to_decimal(exact), "7.500000");
+        assert_eq!(sub(frac(1, 2), frac(1, 1)), None);
+        assert_eq!(add(frac(1, 2), frac(1, 3)), Some(frac(5, 6)));
+    }
+
+    #[test]
+    fn test_error_ppm() {
+        // 7 stored for 7.5
+        assert_eq!(error_ppm(EVMU256::from(7), frac(15, 2)), Some(66_666));
+        // 0 shares stored for 0.5
+        assert_eq!(error_ppm(EVMU256::ZERO, frac(1, 2)), Some(1_000_000));
+    }
+
+    const PUSH: u8 = 0x60;
+    const SWAP1: u8 = 0x90;
+    const POP: u8 = 0x50;
+
+    /// Run instructions (PUSH with its value) shadowed by a frame, returns the tracked SSTOREs
+    fn run(code: &[(u8, u64)]) -> Vec<(EVMU256, EVMU256, Fraction)> {
+        let (mut frame, mut stack, mut stored) = (Frame::default(), vec![], vec![]);
+        for (op, value) in code {
+            stored.extend(frame.step(*op, &stack));
+            match *op {
+                PUSH => stack.push(EVMU256::from(*value)),
+                POP => {
+                    stack.pop();
+                }
+                SWAP1 => {
+                    let len = stack.len();
+                    stack.swap(len - 1, len - 2);
+                }
+                MUL => {
+                    let (a, b) = (stack.pop().unwrap(), stack.pop().unwrap());
+                    stack.push(a * b);
+                }
+                DIV => {
+                    let (a, b) = (stack.pop().unwrap(), stack.pop().unwrap());
+                    stack.push(a / b);
+                }
+                SSTORE => {
+                    stack.truncate(stack.len() - 2);
+                }
+                _ => unreachable!(),
+            }
+        }
+        stored
+    }
+
+    #[test]
+    fn test_fee_not_reported() {
+        // sstore(0, 500 * 3 / 1000): 1 stored for 1.5
+        let stored = run(&[(PUSH, 1000), (PUSH, 3), (PUSH, 500), (MUL, 0), (DIV, 0), (PUSH, 0), (SSTORE, 0)]);
+        assert_eq!(stored.len(), 1);
+        let (_, value, exact) = stored[0];
+        assert_eq!((value, exact), (EVMU256::from(1), frac(3, 2)));
+        assert!(!exceeds_unit(value, exact));
+    }
+
+    #[test]
+    fn test_amplified_rounding() {
+        // sstore(0, 1500 / 1000 * 1000): 1000 stored for 1500
+        let stored = run(&[(PUSH, 1000), (PUSH, 1000), (PUSH, 1500), (DIV, 0), (MUL, 0), (PUSH, 0), (SSTORE, 0)]);
+        let (_, value, exact) = stored[0];
+        assert_eq!((value, exact), (EVMU256::from(1000), frac(1500, 1)));
+        assert!(exceeds_unit(value, exact));
+        assert_eq!(error_ppm(value, exact), Some(333_333));
+    }
+
+    #[test]
+    fn test_provenance() {
+        // 500 / 1000 truncated to 0 is not tracked, nor a later 0 stored from the same stack slot
+        let stored = run(&[(PUSH, 1000), (PUSH, 500), (DIV, 0), (POP, 0), (PUSH, 0), (PUSH, 1), (SSTORE, 0)]);
+        assert!(stored.is_empty());
+        // 3 / 2 popped, then the same value pushed at the same index by another instruction
+        let stored = run(&[(PUSH, 2), (PUSH, 3), (DIV, 0), (POP, 0), (PUSH, 1), (PUSH, 1), (SSTORE, 0)]);
+        assert!(stored.is_empty());
+        // the exact value follows SWAP
+        let stored = run(&[(PUSH, 7), (PUSH, 2), (PUSH, 3), (DIV, 0), (SWAP1, 0), (SSTORE, 0)]);
+        assert_eq!(stored, vec![(EVMU256::from(7), EVMU256::from(1), frac(3, 2))]);
+    }
+}
@@ -0,0 +1,204 @@
+/// Middleware hooks implemented in Rhai scripts (`--middleware-script`), to prototype
+/// instrumentation without recompiling ItyFuzz
+///
+/// A script declares the opcodes it hooks and handles them:
+/// ```rhai
+/// // opcodes passed to on_step, required
+/// fn opcodes() { [0x55, 0xf1] }
+///
+/// // step: #{ address, caller, pc, opcode, stack } where stack holds the top 8 items
+/// // (top first), addresses and words are hex strings
+/// fn on_step(step) {
+///     if step.opcode == 0x55 {
+///         print(`SSTORE ${step.address}[${step.stack[0]}] = ${step.stack[1]}`);
+///     }
+///     if step.opcode == 0xf1 && step.stack[1] == "0x000000000000000000000000000000000000dead" {
+///         return #{ veto: true };
+///     }
+///     // or overwrite stack items (index from top): #{ stack: #{ "1": "0x0" } }
+/// }
+///
+/// // called when code is deployed, optional
+/// fn on_insert(address, code_len) {}
+/// ```
+/// Vetoing reverts the current call frame at the hooked instruction.
+use std::collections::HashSet;
+use tracing::warn;
+use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST, INT};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+/// Stack items passed to the script
+const STACK_ITEMS: usize = 8;
+
+/// Actions requested by the script
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScriptAction {
+    pub veto: bool,
+    /// (index from top, value)
+    pub stack: Vec<(usize, EVMU256)>,
+}
+
+impl ScriptAction {
+    /// Parse the value returned by `on_step`, unit if there is no action
+    pub fn from_dynamic(ret: Dynamic) -> Self {
+        let map = match ret.try_cast::<Map>() {
+            Some(map) => map,
+            None => return Self::default(),
+        };
+        let veto = map
+            .get("veto")
+            .and_then(|v| v.as_bool().ok())
+            .unwrap_or(false);
+        let stack = map
+            .get("stack")
+            .and_then(|v| v.clone().try_cast::<Map>())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|(idx, value)| {
+                        let idx = idx.parse::<usize>().ok()?;
+                        let value = EVMU256::from_str(&value.clone().into_string().ok()?).ok()?;
+                        Some((idx, value))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { veto, stack }
+    }
+}
+
+pub struct ScriptMiddleware {
+    pub path: String,
+    engine: Engine,
+    ast: AST,
+    opcodes: HashSet<u8>,
+    has_on_insert: bool,
+}
+
+impl Debug for ScriptMiddleware {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptMiddleware")
+            .field("path", &self.path)
+            .field("opcodes", &self.opcodes)
+            .finish()
+    }
+}
+
+impl ScriptMiddleware {
+    pub fn from_file(path: &str) -> Self {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_file(path.into())
+            .unwrap_or_else(|e| panic!("failed to compile middleware script {}: {}", path, e));
+        let opcodes = engine
+            .call_fn::<Array>(&mut Scope::new(), &ast, "opcodes", ())
+            .unwrap_or_else(|e| panic!("middleware script {} does not declare opcodes: {}", path, e))
+            .into_iter()
+            .map(|op| op.as_int().expect("opcodes should be integers") as u8)
+            .collect();
+        let has_on_insert = ast.iter_functions().any(|f| f.name == "on_insert");
+        Self {
+            path: path.to_string(),
+            engine,
+            ast,
+            opcodes,
+            has_on_insert,
+        }
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for ScriptMiddleware
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let opcode = *interp.instruction_pointer;
+        if !self.opcodes.contains(&opcode) {
+            return;
+        }
+        let len = interp.stack.len();
+        let stack = (0..len.min(STACK_ITEMS))
+            .map(|idx| Dynamic::from(format!("{:#066x}", interp.stack.data()[len - 1 - idx])))
+            .collect::<Array>();
+        let mut step = Map::new();
+        step.insert("address".into(), Dynamic::from(format!("{:?}", interp.contract.address)));
+        step.insert("caller".into(), Dynamic::from(format!("{:?}", interp.contract.caller)));
+        step.insert("pc".into(), Dynamic::from(interp.program_counter() as INT));
+        step.insert("opcode".into(), Dynamic::from(opcode as INT));
+        step.insert("stack".into(), Dynamic::from(stack));
+
+        let ret = match self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "on_step", (step,)) {
+            Ok(ret) => ret,
+            Err(e) => {
+                warn!("[script {}] on_step failed: {}", self.path, e);
+                return;
+            }
+        };
+        let action = ScriptAction::from_dynamic(ret);
+        let data = interp.stack.data_mut();
+        for (idx, value) in action.stack {
+            if idx < len {
+                data[len - 1 - idx] = value;
+            }
+        }
+        if action.veto {
+            host.veto = true;
+        }
+    }
+
+    unsafe fn on_insert(&mut self, bytecode: &mut Bytecode, address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {
+        if !self.has_on_insert {
+            return;
+        }
+        let args = (format!("{:?}", address), bytecode.len() as INT);
+        if let Err(e) = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "on_insert", args) {
+            warn!("[script {}] on_insert failed: {}", self.path, e);
+        }
+    }
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::Script
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action() {
+        let engine = Engine::new();
+        let ret = engine
+            .eval::<Dynamic>(r#"#{ veto: true, stack: #{ "1": "0x2a", "x": "0x1" } }"#)
+            .unwrap();
+        assert_eq!(
+            ScriptAction::from_dynamic(ret),
+            ScriptAction { veto: true, stack: vec![(1, EVMU256::from(42))] }
+        );
+        assert_eq!(ScriptAction::from_dynamic(Dynamic::UNIT), ScriptAction::default());
+    }
+}
@@ -373,6 +373,11 @@ mod tests {
             input_type: EVMInputTy::ABI,
             randomness: vec![],
             repeat: 1,
+            blob_env: Default::default(),
+            victim: false,
+            via_intermediary: false,
+            advance: Default::default(),
+            force_feed: EVMU256::ZERO,
         };
 
         let res = evm_executor.execute(&input, &mut state);
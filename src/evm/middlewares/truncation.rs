@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+/// Arithmetic whose result can be truncated: ADD, MUL, SUB, DIV, SDIV, EXP, SHL
+const ARITHMETIC: [u8; 7] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x0a, 0x1b];
+const DIV: u8 = 0x04;
+const AND: u8 = 0x16;
+const SIGNEXTEND: u8 = 0x0b;
+const SHR: u8 = 0x1c;
+const SLOAD: u8 = 0x54;
+const SSTORE: u8 = 0x55;
+
+/// Arithmetic results and truncations kept per contract before they are dropped
+const MAX_TRACKED: usize = 256;
+
+/// A value silently truncated by a narrowing cast (e.g., `uint128(a * b)`) and then stored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Truncation {
+    pub contract: EVMAddress,
+    /// pc of the narrowing operation
+    pub pc: usize,
+    /// Width of the target type
+    pub bits: usize,
+    pub original: EVMU256,
+    pub truncated: EVMU256,
+}
+
+fn low_mask(bits: usize) -> EVMU256 {
+    (EVMU256::from(1) << bits) - EVMU256::from(1)
+}
+
+/// Width of a mask of a narrower unsigned type (uint8 ... uint248, addresses excluded)
+fn mask_bits(v: EVMU256) -> Option<usize> {
+    let bits = 256 - v.leading_zeros();
+    if bits == 0 || bits >= 256 || bits % 8 != 0 || bits == 160 || v != low_mask(bits) {
+        return None;
+    }
+    Some(bits)
+}
+
+fn sign_extend(v: EVMU256, bits: usize) -> EVMU256 {
+    let mask = low_mask(bits);
+    if v.bit(bits - 1) {
+        v | !mask
+    } else {
+        v & mask
+    }
+}
+
+/// Records narrowing casts of arithmetic results that lose value (AND with a mask of a narrower
+/// unsigned type, or SIGNEXTEND of a narrower signed type, both unchecked in Solidity) when the
+/// truncated value is stored afterwards (also packed with other variables). Truncations are
+/// recorded in [`crate::evm::vm::EVMState::truncations`].
+///
+/// Truncations to 0 are ignored, as they cannot be told apart from unrelated zeros being stored.
+/// Masks of storage words shifted by DIV / SHR are ignored too, that's how legacy solc reads
+/// packed variables (`sload(slot) / 0x100 ** offset & mask`).
+#[derive(Clone, Debug, Default)]
+pub struct TruncationTracker {
+    /// Last instruction, and whether it shifts a storage word, its result is on top of the stack
+    last: Option<(u8, bool)>,
+    /// Recent arithmetic results
+    arithmetic_results: HashSet<EVMU256>,
+    /// Recent storage words loaded, and shifted by DIV / SHR
+    storage_words: HashSet<EVMU256>,
+    /// Truncations not stored yet
+    pending: Vec<Truncation>,
+}
+
+impl TruncationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn truncate(&mut self, contract: EVMAddress, pc: usize, bits: usize, original: EVMU256, truncated: EVMU256) {
+        if truncated == original || truncated & low_mask(bits) == EVMU256::ZERO {
+            return;
+        }
+        if self.pending.len() >= MAX_TRACKED {
+            self.pending.remove(0);
+        }
+        self.pending.push(Truncation {
+            contract,
+            pc,
+            bits,
+            original,
+            truncated,
+        });
+    }
+
+    /// Track an instruction about to execute on `stack` (bottom first), returns the truncation
+    /// stored by a SSTORE
+    fn step(&mut self, contract: EVMAddress, pc: usize, op: u8, stack: &[EVMU256]) -> Option<Truncation> {
+        let len = stack.len();
+        let peek = |idx: usize| stack[len - 1 - idx];
+
+        if let Some((last, shifts_storage)) = self.last.take() {
+            if len > 0 {
+                if last == SLOAD || shifts_storage {
+                    if self.storage_words.len() >= MAX_TRACKED {
+                        self.storage_words.clear();
+                    }
+                    self.storage_words.insert(peek(0));
+                } else if ARITHMETIC.contains(&last) {
+                    if self.arithmetic_results.len() >= MAX_TRACKED {
+                        self.arithmetic_results.clear();
+                    }
+                    self.arithmetic_results.insert(peek(0));
+                }
+            }
+        }
+        let shifts_storage = match op {
+            DIV if len >= 2 => self.storage_words.contains(&peek(0)),
+            SHR if len >= 2 => self.storage_words.contains(&peek(1)),
+            _ => false,
+        };
+        self.last = Some((op, shifts_storage));
+        if len < 2 {
+            return None;
+        }
+
+        match op {
+            AND => {
+                let (a, b) = (peek(0), peek(1));
+                for (mask, v) in [(a, b), (b, a)] {
+                    if let Some(bits) = mask_bits(mask) {
+                        if self.arithmetic_results.contains(&v) && !self.storage_words.contains(&v) {
+                            self.truncate(contract, pc, bits, v, v & mask);
+                        }
+                    }
+                }
+            }
+            SIGNEXTEND => {
+                let (b, v) = (peek(0), peek(1));
+                if b < EVMU256::from(31) && self.arithmetic_results.contains(&v) && !self.storage_words.contains(&v) {
+                    let bits = (b.as_limbs()[0] as usize + 1) * 8;
+                    self.truncate(contract, pc, bits, v, sign_extend(v, bits));
+                }
+            }
+            SSTORE => {
+                let value = peek(1);
+                let stored = self.pending.iter().position(|t| {
+                    let mask = low_mask(t.bits);
+                    t.contract == contract
+                        && (0..256)
+                            .step_by(8)
+                            .any(|shift| (value >> shift) & mask == t.truncated & mask)
+                });
+                if let Some(idx) = stored {
+                    return Some(self.pending.remove(idx));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for TruncationTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let (contract, pc) = (interp.contract.address, interp.program_counter());
+        if let Some(t) = self.step(contract, pc, *interp.instruction_pointer, interp.stack.data()) {
+            let truncations = &mut host.evmstate.truncations;
+            if !truncations.iter().any(|x| x.contract == t.contract && x.pc == t.pc) {
+                truncations.push(t);
+            }
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::Truncation
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_bits() {
+        assert_eq!(mask_bits(EVMU256::from(0xff)), Some(8));
+        assert_eq!(mask_bits(low_mask(128)), Some(128));
+        assert_eq!(mask_bits(low_mask(160)), None);
+        assert_eq!(mask_bits(EVMU256::MAX), None);
+        assert_eq!(mask_bits(EVMU256::from(0xfe)), None);
+    }
+
+    #[test]
+    fn test_sign_extend() {
+        assert_eq!(sign_extend(EVMU256::from(0x7f), 8), EVMU256::from(0x7f));
+        assert_eq!(sign_extend(EVMU256::from(0x80), 8), !EVMU256::from(0x7f));
+        assert_eq!(sign_extend(EVMU256::from(0x17f), 8), EVMU256::from(0x7f));
+    }
+
+    const ADD: u8 = 0x01;
+    const PUSH: u8 = 0x60;
+    const SWAP1: u8 = 0x90;
+
+    /// Run instructions (PUSH with its value, SLOAD with the word loaded), returns the
+    /// truncations stored
+    fn run(code: &[(u8, EVMU256)]) -> Vec<Truncation> {
+        let (mut tracker, mut stack, mut stored) = (TruncationTracker::new(), vec![], vec![]);
+        for (pc, (op, value)) in code.iter().enumerate() {
+            stored.extend(tracker.step(EVMAddress::zero(), pc, *op, &stack));
+            match *op {
+                PUSH => stack.push(*value),
+                SLOAD => *stack.last_mut().unwrap() = *value,
+                SWAP1 => {
+                    let len = stack.len();
+                    stack.swap(len - 1, len - 2);
+                }
+                ADD | DIV | AND => {
+                    let (a, b) = (stack.pop().unwrap(), stack.pop().unwrap());
+                    stack.push(match *op {
+                        ADD => a.wrapping_add(b),
+                        DIV => a / b,
+                        _ => a & b,
+                    });
+                }
+                SSTORE => stack.truncate(stack.len() - 2),
+                _ => unreachable!(),
+            }
+        }
+        stored
+    }
+
+    #[test]
+    fn test_packed_struct() {
+        // struct { uint64 a; uint64 b; uint64 c; } in slot 0, with a = 1, b = 2, c = 3
+        let word = (EVMU256::from(3) << 128) | (EVMU256::from(2) << 64) | EVMU256::from(1);
+        let (zero, mask) = (EVMU256::ZERO, low_mask(64));
+        for offset in [0, 64, 128] {
+            // x.b = x.b, reading the field as legacy solc does: sload(0) / 0x100 ** offset & mask
+            let stored = run(&[
+                (PUSH, zero),
+                (SLOAD, word),
+                (PUSH, EVMU256::from(1) << offset),
+                (SWAP1, zero),
+                (DIV, zero),
+                (PUSH, mask),
+                (AND, zero),
+                (PUSH, zero),
+                (SLOAD, word),
+                (PUSH, zero),
+                (SSTORE, zero),
+            ]);
+            assert!(stored.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_truncated_sum() {
+        // sstore(0, uint64(a + b)) with a + b overflowing 64 bits
+        let stored = run(&[
+            (PUSH, low_mask(64)),
+            (PUSH, EVMU256::from(5)),
+            (ADD, EVMU256::ZERO),
+            (PUSH, low_mask(64)),
+            (AND, EVMU256::ZERO),
+            (PUSH, EVMU256::ZERO),
+            (SSTORE, EVMU256::ZERO),
+        ]);
+        assert_eq!(stored.len(), 1);
+        assert_eq!((stored[0].bits, stored[0].truncated), (64, EVMU256::from(4)));
+    }
+}
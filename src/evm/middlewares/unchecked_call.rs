@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{CallMiddlewareReturn, Middleware, MiddlewareType};
+use crate::evm::types::{convert_u256_to_h160, EVMAddress, EVMU256};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+
+const STOP: u8 = 0x00;
+const POP: u8 = 0x50;
+const SSTORE: u8 = 0x55;
+const TSTORE: u8 = 0x5d;
+const CREATE: u8 = 0xf0;
+const CALL: u8 = 0xf1;
+const CALLCODE: u8 = 0xf2;
+const RETURN: u8 = 0xf3;
+const DELEGATECALL: u8 = 0xf4;
+const CREATE2: u8 = 0xf5;
+const SELFDESTRUCT: u8 = 0xff;
+
+/// Call site whose result is forced to fail, set by
+/// [`crate::evm::oracles::unchecked_call::UncheckedCallOracle`] when re-executing a transaction
+pub static mut FORCE_FAIL: Option<(EVMAddress, usize)> = None;
+/// Whether the call site of [`FORCE_FAIL`] was reached
+pub static mut FORCED: bool = false;
+
+/// A low-level call whose success flag was dropped: the caller changed state (or returned)
+/// without branching on it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UncheckedCall {
+    pub contract: EVMAddress,
+    /// pc of the call
+    pub pc: usize,
+    pub target: EVMAddress,
+    pub value: EVMU256,
+}
+
+/// Stack inputs and outputs of an opcode (DUPs and SWAPs are handled separately)
+fn stack_io(op: u8) -> (usize, usize) {
+    match op {
+        0x01..=0x07 | 0x0a | 0x0b | 0x10..=0x14 | 0x16..=0x18 | 0x1a..=0x1d | 0x20 => (2, 1),
+        0x08 | 0x09 => (3, 1),
+        0x15 | 0x19 | 0x31 | 0x35 | 0x3b | 0x3f | 0x40 | 0x49 | 0x51 | 0x54 | 0x5c => (1, 1),
+        0x30 | 0x32..=0x34 | 0x36 | 0x38 | 0x3a | 0x3d | 0x41..=0x48 | 0x4a | 0x58..=0x5a | 0x5f..=0x7f => (0, 1),
+        0x37 | 0x39 | 0x3e | 0x5e => (3, 0),
+        0x3c => (4, 0),
+        0x50 | 0x56 | 0xff => (1, 0),
+        0x52 | 0x53 | 0x55 | 0x57 | 0x5d | 0xf3 | 0xfd => (2, 0),
+        0xa0..=0xa4 => (2 + (op - 0xa0) as usize, 0),
+        0xf0 => (3, 1),
+        0xf1 | 0xf2 => (7, 1),
+        0xf4 | 0xfa => (6, 1),
+        0xf5 => (4, 1),
+        _ => (0, 0),
+    }
+}
+
+/// Operations committing state, the success flag of a call must be checked before them
+fn is_state_change(op: u8) -> bool {
+    matches!(op, STOP | SSTORE | TSTORE | CREATE | CALL | CALLCODE | RETURN | DELEGATECALL | CREATE2 | SELFDESTRUCT)
+        || (0xa0..=0xa4).contains(&op)
+}
+
+enum Flow {
+    Continue,
+    /// The flag (or a value derived from it) was used, e.g., by a JUMPI or stored
+    Checked,
+    /// State changed before the flag was used
+    Unchecked,
+}
+
+/// Follow the positions (from the bottom of the stack) holding the success flag or values
+/// derived from it (e.g., ISZERO) through an instruction
+fn track(op: u8, tainted: &mut Vec<usize>, len: usize) -> Flow {
+    match op {
+        0x80..=0x8f => {
+            let src = len.wrapping_sub((op - 0x7f) as usize);
+            if tainted.contains(&src) {
+                tainted.push(len);
+            }
+            return Flow::Continue;
+        }
+        0x90..=0x9f => {
+            let (a, b) = (len.wrapping_sub(1), len.wrapping_sub(2 + (op - 0x90) as usize));
+            for p in tainted.iter_mut() {
+                if *p == a {
+                    *p = b;
+                } else if *p == b {
+                    *p = a;
+                }
+            }
+            return Flow::Continue;
+        }
+        _ => {}
+    }
+    let (inputs, outputs) = stack_io(op);
+    let bottom = len.saturating_sub(inputs);
+    let consumed = tainted.iter().any(|p| *p >= bottom);
+    tainted.retain(|p| *p < bottom);
+    if consumed {
+        match op {
+            POP => {}
+            // arithmetic, comparison and bitwise operations carry the flag
+            0x01..=0x1d if outputs == 1 => tainted.push(bottom),
+            _ => return Flow::Checked,
+        }
+    }
+    if is_state_change(op) {
+        return Flow::Unchecked;
+    }
+    Flow::Continue
+}
+
+#[derive(Clone, Debug)]
+struct Pending {
+    call: UncheckedCall,
+    /// Stack height once the call returned, without its result
+    base: usize,
+    tainted: Vec<usize>,
+}
+
+/// Records low-level calls (CALL, CALLCODE, DELEGATECALL, e.g., `send` or `call{value: x}("")`
+/// in payout loops) whose success flag is dropped: the caller changes state or returns before
+/// branching on the flag. Calls are recorded in [`crate::evm::vm::EVMState::unchecked_calls`].
+#[derive(Clone, Debug, Default)]
+pub struct UncheckedCallTracker {
+    /// Interpreter => call executing (result not yet pushed)
+    calling: HashMap<usize, Pending>,
+    /// Interpreter => call whose flag is followed
+    pending: HashMap<usize, Pending>,
+}
+
+impl UncheckedCallTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for UncheckedCallTracker
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        let key = interp as *const Interpreter as usize;
+        let pc = interp.program_counter();
+        let len = interp.stack.len();
+        if pc == 0 {
+            self.calling.remove(&key);
+            self.pending.remove(&key);
+        }
+        // the call returned, its result is on top of the stack
+        if let Some(mut call) = self.calling.remove(&key) {
+            if len == call.base + 1 {
+                call.tainted = vec![call.base];
+                self.pending.insert(key, call);
+            }
+        }
+
+        let op = *interp.instruction_pointer;
+        if let Some(pending) = self.pending.get_mut(&key) {
+            match track(op, &mut pending.tainted, len) {
+                Flow::Continue => {}
+                Flow::Checked => {
+                    self.pending.remove(&key);
+                }
+                Flow::Unchecked => {
+                    let call = self.pending.remove(&key).unwrap().call;
+                    let calls = &mut host.evmstate.unchecked_calls;
+                    if !calls.iter().any(|c| c.contract == call.contract && c.pc == call.pc) {
+                        calls.push(call);
+                    }
+                }
+            }
+        }
+
+        if !matches!(op, CALL | CALLCODE | DELEGATECALL) {
+            return;
+        }
+        let (inputs, _) = stack_io(op);
+        if len < inputs {
+            return;
+        }
+        let contract = interp.contract.address;
+        if FORCE_FAIL == Some((contract, pc)) {
+            host.middlewares_latent_call_actions.push(CallMiddlewareReturn::ReturnRevert);
+            FORCED = true;
+        }
+        let value = if op == DELEGATECALL { EVMU256::ZERO } else { interp.stack.peek(2).unwrap() };
+        self.calling.insert(
+            key,
+            Pending {
+                call: UncheckedCall {
+                    contract,
+                    pc,
+                    target: convert_u256_to_h160(interp.stack.peek(1).unwrap()),
+                    value,
+                },
+                base: len - inputs,
+                tainted: vec![],
+            },
+        );
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::UncheckedCall
+    }
+}
+
+mod tests {
+    use super::*;
+
+    /// Replay `ops` with the flag on top of a stack of `len` items
+    fn replay(ops: &[u8], len: usize) -> Vec<bool> {
+        let mut tainted = vec![len - 1];
+        let mut len = len;
+        let mut unchecked = vec![];
+        for op in ops {
+            let flow = track(*op, &mut tainted, len);
+            unchecked.push(matches!(flow, Flow::Unchecked));
+            if matches!(flow, Flow::Checked) {
+                break;
+            }
+            len = match op {
+                0x80..=0x8f => len + 1,
+                0x90..=0x9f => len,
+                _ => {
+                    let (inputs, outputs) = stack_io(*op);
+                    len - inputs + outputs
+                }
+            };
+        }
+        unchecked
+    }
+
+    #[test]
+    fn test_track() {
+        // send(...) dropped: POP, then SSTORE
+        assert_eq!(replay(&[POP, 0x60, 0x60, SSTORE], 4), vec![false, false, false, true]);
+        // require(ok): ISZERO, PUSH, JUMPI is checked before the SSTORE
+        assert_eq!(replay(&[0x15, 0x60, 0x57, SSTORE], 4), vec![false, false, false]);
+        // DUP / SWAP keep following the flag
+        assert_eq!(replay(&[0x60, 0x90, 0x80, 0x15, 0x60, 0x57], 4).iter().any(|u| *u), false);
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::Debug;
+use libafl::inputs::Input;
+use libafl::prelude::{HasCorpus, HasMetadata, State};
+use revm_interpreter::Interpreter;
+use revm_primitives::Bytecode;
+use crate::evm::contract_utils::{set_hash, ABIConfig, ContractInfo};
+use crate::evm::host::FuzzHost;
+use crate::evm::input::{ConciseEVMInput, EVMInputT};
+use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::VMInputT;
+use crate::state::{HasCaller, HasItyState};
+use crate::evm::types::{convert_h160_to_u256, fixed_address, EVMAddress, EVMU256};
+
+/// Address of the dummy contract, calling it performs the upgrade
+pub const UPGRADE_TRIGGER: &str = "00000000000000000000000000000000007e9ad3";
+/// Function exposed by the dummy contract so that the upgrade is part of the corpus
+pub const UPGRADE_FUNCTION: &str = "ityfuzz_upgrade()";
+/// bytes32(uint256(keccak256('eip1967.proxy.implementation')) - 1)
+pub const EIP1967_IMPLEMENTATION_SLOT: &str =
+    "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+
+/// Performs the upgrade of `proxy` to `implementation` whenever the upgrade trigger is called,
+/// so that the fuzzer can interleave transactions before and after the upgrade.
+#[derive(Clone, Debug)]
+pub struct Upgrade {
+    pub proxy: EVMAddress,
+    pub implementation: EVMAddress,
+    pub slot: EVMU256,
+    pub trigger: EVMAddress,
+}
+
+impl Upgrade {
+    pub fn new(proxy: EVMAddress, implementation: EVMAddress, slot: EVMU256) -> Self {
+        Self {
+            proxy,
+            implementation,
+            slot,
+            trigger: fixed_address(UPGRADE_TRIGGER),
+        }
+    }
+}
+
+/// Dummy contract (a single STOP) exposing [`UPGRADE_FUNCTION`], calling it performs the upgrade
+pub fn upgrade_trigger_contract() -> ContractInfo {
+    let mut abi = ABIConfig {
+        abi: "()".to_string(),
+        function: [0; 4],
+        function_name: UPGRADE_FUNCTION.trim_end_matches("()").to_string(),
+        is_static: false,
+        is_payable: false,
+        is_constructor: false,
+        input_names: vec![],
+    };
+    set_hash(UPGRADE_FUNCTION, &mut abi.function);
+    ContractInfo {
+        name: "UpgradeTrigger".to_string(),
+        code: vec![0x00],
+        abi: vec![abi],
+        is_code_deployed: true,
+        constructor_args: vec![],
+        deployed_address: fixed_address(UPGRADE_TRIGGER),
+        source_map: None,
+    }
+}
+
+impl<I, VS, S> Middleware<VS, I, S> for Upgrade
+    where
+        I: Input + VMInputT<VS, EVMAddress, EVMAddress, ConciseEVMInput> + EVMInputT + 'static,
+        VS: VMStateT,
+        S: State
+        + HasCaller<EVMAddress>
+        + HasCorpus<I>
+        + HasItyState<EVMAddress, EVMAddress, VS, ConciseEVMInput>
+        + HasMetadata
+        + Debug
+        + Clone,
+{
+    unsafe fn on_step(
+        &mut self,
+        interp: &mut Interpreter,
+        host: &mut FuzzHost<VS, I, S>,
+        _state: &mut S,
+    ) {
+        if interp.contract.address != self.trigger || interp.program_counter() != 0 {
+            return;
+        }
+        let implementation = convert_h160_to_u256(self.implementation);
+        match host.evmstate.get_mut(&self.proxy) {
+            Some(account) => {
+                account.insert(self.slot, implementation);
+            }
+            None => {
+                let mut account = std::collections::HashMap::new();
+                account.insert(self.slot, implementation);
+                host.evmstate.insert(self.proxy, account);
+            }
+        }
+    }
+
+    unsafe fn on_insert(&mut self, _bytecode: &mut Bytecode, _address: EVMAddress, _host: &mut FuzzHost<VS, I, S>, _state: &mut S) {}
+
+    fn get_type(&self) -> MiddlewareType {
+        MiddlewareType::Upgrade
+    }
+}
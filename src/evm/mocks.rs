@@ -0,0 +1,69 @@
+/// Mocks of missing external dependencies (`--mock-missing`)
+///
+/// Calls with a selector to an address without code (other than the callers, the zero address
+/// and precompiles) are served by a permissive mock instead of reverting: control is leaked to
+/// the fuzzer, so the return data of the call is provided (and mutated) by the step input that
+/// resumes execution. Such addresses also look like contracts to EXTCODESIZE, so that the checks
+/// before high-level calls pass. Selectors observed on each mocked address are reported.
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::info;
+
+use crate::evm::types::EVMAddress;
+
+#[derive(Clone, Debug, Default)]
+pub struct MockSynthesis {
+    /// Accounts never mocked (i.e., the callers)
+    pub excluded: HashSet<EVMAddress>,
+    /// Selectors observed on each mocked address
+    pub observed: HashMap<EVMAddress, BTreeSet<[u8; 4]>>,
+}
+
+impl MockSynthesis {
+    pub fn new(excluded: HashSet<EVMAddress>) -> Self {
+        Self {
+            excluded,
+            observed: HashMap::new(),
+        }
+    }
+
+    /// Whether a codeless address is mocked
+    pub fn is_mocked(&self, address: &EVMAddress) -> bool {
+        *address != EVMAddress::zero() && !self.excluded.contains(address)
+    }
+
+    /// Record a call with `input` to a mocked address, returns whether the selector is new
+    pub fn observe(&mut self, address: EVMAddress, input: &[u8]) -> bool {
+        if input.len() < 4 {
+            return false;
+        }
+        let selector: [u8; 4] = input[..4].try_into().unwrap();
+        let new = self.observed.entry(address).or_default().insert(selector);
+        if new {
+            info!(
+                "[mock] 0x{} mocked, observed selectors: {}",
+                hex::encode(address),
+                self.observed[&address].iter().map(hex::encode).collect::<Vec<_>>().join(", ")
+            );
+        }
+        new
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    #[test]
+    fn test_mock_observe() {
+        let caller = fixed_address("8EF508Aca04B32Ff3ba5003177cb18BfA6Cd79dd");
+        let dep = fixed_address("1111111111111111111111111111111111111111");
+        let mut mocks = MockSynthesis::new(HashSet::from([caller]));
+        assert!(!mocks.is_mocked(&caller));
+        assert!(!mocks.is_mocked(&EVMAddress::zero()));
+        assert!(mocks.is_mocked(&dep));
+        assert!(mocks.observe(dep, &[0x70, 0xa0, 0x82, 0x31, 0x00]));
+        assert!(!mocks.observe(dep, &[0x70, 0xa0, 0x82, 0x31]));
+        assert!(!mocks.observe(dep, &[]));
+        assert_eq!(mocks.observed[&dep].len(), 1);
+    }
+}
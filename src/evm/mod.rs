@@ -1,20 +1,65 @@
 pub mod abi;
 pub mod bytecode_analyzer;
+pub mod chain_spec;
+pub mod cmin;
 pub mod concolic;
 pub mod config;
 pub mod contract_utils;
+pub mod control;
 pub mod corpus_initializer;
+pub mod cross_evm;
+pub mod debugger;
+pub mod differential;
+pub mod exploit;
+pub mod gas;
 pub mod host;
 pub mod input;
+pub mod lending;
 pub mod middlewares;
 pub mod mutator;
 pub mod onchain;
 pub mod oracle;
 pub mod oracles;
+pub mod precompiles;
+pub mod power_schedule;
 pub mod presets;
 pub mod producers;
+pub mod signature;
 pub mod srcmap;
+pub mod replay;
+pub mod state_diff;
+pub mod trimming;
 pub mod types;
 pub mod uniswap;
 pub mod vm;
+pub mod zksync;
 pub mod feedbacks;
+pub mod focus_diff;
+pub mod env_bounds;
+pub mod mempool;
+pub mod bundle;
+pub mod token_callbacks;
+pub mod mocks;
+pub mod twap;
+pub mod storage_layout;
+pub mod watch;
+pub mod budget;
+pub mod target_groups;
+pub mod campaign_stats;
+pub mod receipts;
+pub mod corpus_import;
+pub mod function_weights;
+pub mod eof;
+pub mod pre_state;
+pub mod bridge;
+pub mod known_addresses;
+pub mod compare;
+pub mod istanbul;
+pub mod soak;
+pub mod shadow_fork;
+pub mod call_value;
+pub mod linker;
+pub mod findings;
+pub mod seq_length;
+#[cfg(feature = "llm_triage")]
+pub mod triage;
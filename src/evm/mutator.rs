@@ -1,5 +1,5 @@
 /// Mutator for EVM inputs
-use crate::evm::input::EVMInputT;
+use crate::evm::input::{EVMInputT, INTERMEDIARY_TXS, VICTIM_TXS};
 
 use crate::generic_vm::vm_state::VMStateT;
 use crate::input::{ConciseSerde, VMInputT};
@@ -17,6 +17,9 @@ use crate::evm::input::EVMInputTy::Borrow;
 use std::fmt::Debug;
 use revm_interpreter::Interpreter;
 use crate::evm::abi::ABIAddressToInstanceMap;
+use crate::evm::bridge::is_messenger;
+use crate::evm::control::{is_focused, is_frozen};
+use crate::evm::seq_length::{bucket, preferred_bucket};
 use crate::evm::types::{convert_u256_to_h160, EVMAddress};
 use crate::evm::vm::{Constraint, EVMState, EVMStateT};
 
@@ -33,6 +36,7 @@ use crate::state_input::StagedVMState;
 pub struct AccessPattern {
     pub caller: bool,       // or origin
     pub balance: Vec<EVMAddress>, // balance queried for accounts
+    pub self_balance: bool,
     pub call_value: bool,
     pub gas_price: bool,
     pub number: bool,
@@ -42,6 +46,8 @@ pub struct AccessPattern {
     pub gas_limit: bool,
     pub chain_id: bool,
     pub basefee: bool,
+    pub blob_hash: bool,
+    pub blob_basefee: bool,
 }
 
 impl AccessPattern {
@@ -49,6 +55,7 @@ impl AccessPattern {
     pub fn new() -> Self {
         Self {
             balance: vec![],
+            self_balance: false,
             caller: false,
             call_value: false,
             gas_price: false,
@@ -59,6 +66,8 @@ impl AccessPattern {
             gas_limit: false,
             chain_id: false,
             basefee: false,
+            blob_hash: false,
+            blob_basefee: false,
         }
     }
 
@@ -69,6 +78,7 @@ impl AccessPattern {
                 .balance
                 .push(convert_u256_to_h160(interp.stack.peek(0).unwrap())),
             0x33 => self.caller = true,
+            0x47 => self.self_balance = true,
             0x34 => {
                 // prevent initial check of dispatch to fallback
                 if interp.program_counter() > 0xb {
@@ -83,11 +93,16 @@ impl AccessPattern {
             0x45 => self.gas_limit = true,
             0x46 => self.chain_id = true,
             0x48 => self.basefee = true,
+            0x49 => self.blob_hash = true,
+            0x4a => self.blob_basefee = true,
             _ => {}
         }
     }
 }
 
+/// Infant states sampled to find one built by a sequence of the preferred length
+const MAX_LENGTH_TRIES: usize = 8;
+
 /// [`FuzzMutator`] is a mutator that mutates the input based on the ABI and access pattern
 pub struct FuzzMutator<'a, VS, Loc, Addr, SC, CI>
     where
@@ -119,6 +134,35 @@ impl<'a, VS, Loc, Addr, SC, CI> FuzzMutator<'a, VS, Loc, Addr, SC, CI>
         }
     }
 
+    /// Pick an infant state for the input, with adaptive sequence lengths, preferring states
+    /// built by sequences of the length yielding coverage for the function of the input
+    fn get_infant_state<I, S>(&self, state: &mut S, input: &I) -> (usize, StagedVMState<Loc, Addr, VS, CI>)
+        where
+            I: VMInputT<VS, Loc, Addr, CI> + Input + EVMInputT,
+            S: State + HasRand + HasItyState<Loc, Addr, VS, CI>,
+            VS: EVMStateT,
+    {
+        let r = state.rand_mut().next();
+        let preferred = input
+            .get_data_abi()
+            .and_then(|abi| preferred_bucket(input.get_contract_evm(), abi.function, r));
+        let mut picked = state.get_infant_state(self.infant_scheduler).unwrap();
+        if let Some(preferred) = preferred {
+            // the depth of the state is the length of the sequence before the input
+            let distance = |depth: usize| bucket(depth + 1).abs_diff(preferred);
+            for _ in 0..MAX_LENGTH_TRIES {
+                if distance(picked.1.state.get_depth()) == 0 {
+                    break;
+                }
+                let other = state.get_infant_state(self.infant_scheduler).unwrap();
+                if distance(other.1.state.get_depth()) < distance(picked.1.state.get_depth()) {
+                    picked = other;
+                }
+            }
+        }
+        picked
+    }
+
     fn ensures_constraint<I, S>(input: &mut I, state: &mut S, constraints: Vec<Constraint>)
         where
             I: VMInputT<VS, Loc, Addr, CI> + Input + EVMInputT,
@@ -177,9 +221,18 @@ impl<'a, VS, Loc, Addr, I, S, SC, CI> Mutator<I, S> for FuzzMutator<'a, VS, Loc,
         input: &mut I,
         _stage_idx: i32,
     ) -> Result<MutationResult, Error> {
+        // skip functions out of the focus set through the control socket
+        if let Some(abi) = input.get_data_abi() && !is_focused(&abi.function) {
+            return Ok(MutationResult::Skipped);
+        }
+        // frozen contracts are not called directly
+        if is_frozen(&input.get_contract_evm()) {
+            return Ok(MutationResult::Skipped);
+        }
+
         // if the VM state of the input is not initialized, swap it with a state initialized
         if !input.get_staged_state().initialized {
-            let concrete = state.get_infant_state(self.infant_scheduler).unwrap();
+            let concrete = self.get_infant_state(state, input);
             input.set_staged_state(concrete.1, concrete.0);
         }
 
@@ -256,11 +309,11 @@ impl<'a, VS, Loc, Addr, I, S, SC, CI> Mutator<I, S> for FuzzMutator<'a, VS, Loc,
                     already_crossed = true;
                     // cross over infant state
                     let old_idx = input.get_state_idx();
-                    let (idx, new_state) = state.get_infant_state(self.infant_scheduler).unwrap();
+                    let (idx, new_state) = self.get_infant_state(state, input);
                     if idx == old_idx {
                         return MutationResult::Skipped;
                     }
-                    if !state.has_caller(&input.get_caller()) {
+                    if !state.has_caller(&input.get_caller()) && !input.is_victim() && !is_messenger(&input.get_caller()) {
                         input.set_caller(state.get_rand_caller());
                     }
 
@@ -287,6 +340,21 @@ impl<'a, VS, Loc, Addr, I, S, SC, CI> Mutator<I, S> for FuzzMutator<'a, VS, Loc,
                     input.set_randomness(vec![rand_u8; 1]);
                     MutationResult::Mutated
                 }
+                // turn the input into a victim transaction or back
+                12 if unsafe { VICTIM_TXS } => {
+                    if input.is_victim() {
+                        input.set_victim(false);
+                        input.set_caller(state.get_rand_caller());
+                    } else {
+                        input.set_victim(true);
+                    }
+                    MutationResult::Mutated
+                }
+                // send the input through the intermediary contract or directly
+                13 if unsafe { INTERMEDIARY_TXS } => {
+                    input.set_via_intermediary(!input.is_via_intermediary());
+                    MutationResult::Mutated
+                }
                 _ => input.mutate(state),
             }
         };
@@ -1,4 +1,5 @@
 use std::collections::hash_map::DefaultHasher;
+use tracing::debug;
 use std::error::Error;
 use crate::evm::contract_utils::ABIConfig;
 use heimdall::decompile::decompile_with_bytecode;
@@ -16,7 +17,7 @@ pub fn fetch_abi_heimdall(bytecode: String) -> Vec<ABIConfig> {
     let cache = FileSystemCache::new("cache/heimdall");
     match cache.load(cache_key.as_str()) {
         Ok(res) => {
-            println!("using cached result of decompiling contract");
+            debug!("using cached result of decompiling contract");
             return serde_json::from_str(res.as_str()).unwrap();
         }
         Err(_) => {}
@@ -44,6 +45,7 @@ pub fn fetch_abi_heimdall(bytecode: String) -> Vec<ABIConfig> {
                     is_static: func.state_mutability == "view",
                     is_payable: func.state_mutability == "payable",
                     is_constructor: false,
+                    input_names: vec![],
                 };
                 abi_config
                     .function
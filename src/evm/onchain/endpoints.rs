@@ -1,8 +1,11 @@
 use crate::cache::{Cache, FileSystemCache};
+use tracing::{debug, error, info, warn};
+use crate::evm::known_addresses::known_addresses;
 use crate::evm::uniswap::{
     get_uniswap_info, PairContext, PathContext, TokenContext, UniswapProvider,
 };
 use bytes::Bytes;
+use itertools::Itertools;
 use reqwest::header::HeaderMap;
 use retry::OperationResult;
 use retry::{delay::Fixed, retry_with_index};
@@ -22,9 +25,23 @@ use std::time::Duration;
 use revm_interpreter::analysis::to_analysed;
 use revm_primitives::bitvec::macros::internal::funty::Integral;
 use revm_primitives::{Bytecode, LatestSpec};
+use crate::evm::middlewares::upgrade::EIP1967_IMPLEMENTATION_SLOT;
 use crate::evm::types::{EVMAddress, EVMU256};
+use crate::evm::onchain::explorer::{ContractSource, EtherscanAdapter, ExplorerAdapter};
+use crate::evm::onchain::lru::LruCache;
+use crate::evm::srcmap::parser::{decode_instructions, SourceMapLocation};
 
 const MAX_HOPS: u32 = 2; // Assuming the value of MAX_HOPS
+/// Size of a cached storage slot (address, slot and value), in bytes
+const SLOT_ENTRY_SIZE: usize = 84;
+
+/// bytes32(uint256(keccak256('eip1967.proxy.beacon')) - 1)
+const EIP1967_BEACON_SLOT: &str = "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+/// keccak256("PROXIABLE")
+const EIP1822_PROXIABLE_SLOT: &str = "0xc5f16f0fcc639fa48a6947836d9850f504798e3d3584a7eeac2e6d9dc6a4ba7e";
+
+/// Sourcify API, serving the runtime source maps of verified contracts
+const SOURCIFY_API: &str = "https://sourcify.dev/server";
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Copy)]
 pub enum Chain {
@@ -44,6 +61,7 @@ pub enum Chain {
     CELO,
     ZKEVM,
     ZKEVM_TESTNET,
+    ZKSYNC,
     LOCAL,
 }
 
@@ -72,6 +90,7 @@ impl Chain {
             "CELO" | "celo" => Some(Self::CELO),
             "ZKEVM" | "zkevm" => Some(Self::ZKEVM),
             "ZKEVM_TESTNET" | "zkevm_testnet" => Some(Self::ZKEVM_TESTNET),
+            "ZKSYNC" | "zksync" => Some(Self::ZKSYNC),
             "LOCAL" | "local" => Some(Self::LOCAL),
             _ => None,
         }
@@ -95,6 +114,7 @@ impl Chain {
             Chain::CELO => 42220,
             Chain::ZKEVM => 1101,
             Chain::ZKEVM_TESTNET => 1442,
+            Chain::ZKSYNC => 324,
             Chain::LOCAL => 31337,
         }
     }
@@ -117,6 +137,7 @@ impl Chain {
             Chain::CELO => "celo",
             Chain::ZKEVM => "zkevm",
             Chain::ZKEVM_TESTNET => "zkevm_testnet",
+            Chain::ZKSYNC => "zksync",
             Chain::LOCAL => "local",
         }
         .to_string()
@@ -140,6 +161,7 @@ impl Chain {
             Chain::CELO => "https://rpc.ankr.com/celo",
             Chain::ZKEVM => "https://rpc.ankr.com/polygon_zkevm",
             Chain::ZKEVM_TESTNET => "https://rpc.ankr.com/polygon_zkevm_testnet",
+            Chain::ZKSYNC => "https://mainnet.era.zksync.io",
             Chain::LOCAL => "http://localhost:8545",
         }
         .to_string()
@@ -163,6 +185,7 @@ impl Chain {
             Chain::CELO => "https://api.celoscan.io/api",
             Chain::ZKEVM => "https://api-zkevm.polygonscan.com/api",
             Chain::ZKEVM_TESTNET => "https://api-testnet-zkevm.polygonscan.com/api",
+            Chain::ZKSYNC => "https://block-explorer-api.mainnet.zksync.io/api",
             Chain::LOCAL => "http://localhost:8080/abi/",
         }
         .to_string()
@@ -232,16 +255,20 @@ pub struct OnChainConfig {
 
     pub etherscan_api_key: Vec<String>,
     pub etherscan_base: String,
+    /// how to talk to the block explorer at `etherscan_base`
+    pub explorer: Arc<dyn ExplorerAdapter>,
 
     pub chain_name: String,
 
-    slot_cache: HashMap<(EVMAddress, EVMU256), EVMU256>,
-    code_cache: HashMap<EVMAddress, Bytecode>,
+    slot_cache: LruCache<(EVMAddress, EVMU256), EVMU256>,
+    code_cache: LruCache<EVMAddress, Bytecode>,
     price_cache: HashMap<EVMAddress, Option<(u32, u32)>>,
     abi_cache: HashMap<EVMAddress, Option<String>>,
     storage_all_cache: HashMap<EVMAddress, Option<Arc<HashMap<String, EVMU256>>>>,
     storage_dump_cache: HashMap<EVMAddress, Option<Arc<HashMap<EVMU256, EVMU256>>>>,
     uniswap_path_cache: HashMap<EVMAddress, TokenContext>,
+    proxy_cache: HashMap<EVMAddress, Option<EVMAddress>>,
+    diamond_cache: HashMap<EVMAddress, Vec<EVMAddress>>,
     rpc_cache: FileSystemCache,
 }
 
@@ -278,6 +305,7 @@ impl OnChainConfig {
             block_hash: None,
             etherscan_api_key: vec![],
             etherscan_base,
+            explorer: Arc::new(EtherscanAdapter),
             chain_name: chain_name,
             slot_cache: Default::default(),
             code_cache: Default::default(),
@@ -287,6 +315,8 @@ impl OnChainConfig {
             storage_all_cache: Default::default(),
             storage_dump_cache: Default::default(),
             uniswap_path_cache: Default::default(),
+            proxy_cache: Default::default(),
+            diamond_cache: Default::default(),
             rpc_cache: FileSystemCache::new("./cache"),
         }
     }
@@ -317,20 +347,20 @@ impl OnChainConfig {
                     match text {
                         Ok(t) => {
                             if t.contains("Max rate limit reached") {
-                                println!("Etherscan max rate limit reached, retrying...");
+                                warn!("Etherscan max rate limit reached, retrying...");
                                 return OperationResult::Retry("Rate limit reached".to_string());
                             } else {
                                 return OperationResult::Ok(t);
                             }
                         }
                         Err(e) => {
-                            println!("{:?}", e);
+                            warn!("{:?}", e);
                             return OperationResult::Retry("failed to parse response".to_string());
                         }
                     }
                 }
                 Err(e) => {
-                    println!("Error: {}", e);
+                    error!("Error: {}", e);
                     return OperationResult::Retry("failed to send request".to_string());
                 }
             }
@@ -343,7 +373,7 @@ impl OnChainConfig {
                 Some(t)
             }
             Err(e) => {
-                println!("Error: {}", e);
+                error!("Error: {}", e);
                 None
             }
         }
@@ -379,13 +409,13 @@ impl OnChainConfig {
                             return OperationResult::Ok(t);
                         }
                         Err(e) => {
-                            println!("{:?}", e);
+                            warn!("{:?}", e);
                             return OperationResult::Retry("failed to parse response".to_string());
                         }
                     }
                 }
                 Err(e) => {
-                    println!("Error: {}", e);
+                    error!("Error: {}", e);
                     return OperationResult::Retry("failed to send request".to_string());
                 }
             }
@@ -397,7 +427,7 @@ impl OnChainConfig {
                 Some(t)
             }
             Err(e) => {
-                println!("Error: {}", e);
+                error!("Error: {}", e);
                 None
             }
         }
@@ -470,6 +500,19 @@ impl OnChainConfig {
         return self.block_hash.as_ref().unwrap();
     }
 
+    /// Number, timestamp and basefee of the block
+    pub fn fetch_blk_env(&self) -> (EVMU256, EVMU256, EVMU256) {
+        let params = format!("[\"{}\",false]", self.block_number);
+        let res = self
+            ._request("eth_getBlockByNumber".to_string(), params)
+            .expect("fail to get block");
+        let field = |name: &str| match res[name].as_str() {
+            Some(v) => EVMU256::from_str_radix(v.trim_start_matches("0x"), 16).unwrap(),
+            None => EVMU256::ZERO,
+        };
+        (field("number"), field("timestamp"), field("baseFeePerGas"))
+    }
+
     pub fn fetch_storage_dump(&mut self, address: EVMAddress) -> Option<Arc<HashMap<EVMU256, EVMU256>>> {
         if let Some(storage) = self.storage_dump_cache.get(&address) {
             return storage.clone();
@@ -520,46 +563,65 @@ impl OnChainConfig {
         }
     }
 
+    /// Cap the memory used by fetched code and storage (in bytes, split evenly between them),
+    /// least recently used entries are evicted beyond it
+    pub fn set_memory_cap(&mut self, cap: usize) {
+        self.code_cache.cap = Some(cap / 2);
+        self.slot_cache.cap = Some(cap / 2);
+    }
+
+    /// Estimated memory used by fetched code and storage, in bytes
+    pub fn cache_memory(&self) -> usize {
+        self.code_cache.bytes + self.slot_cache.bytes
+    }
+
+    pub fn set_explorer(&mut self, explorer: Arc<dyn ExplorerAdapter>) {
+        self.explorer = explorer;
+    }
+
     pub fn fetch_abi_uncached(&self, address: EVMAddress) -> Option<String> {
-        let endpoint = format!(
-            "{}?module=contract&action=getabi&address={:?}&format=json&apikey={}",
-            self.etherscan_base,
+        let endpoint = self.explorer.abi_url(
+            &self.etherscan_base,
             address,
-            if self.etherscan_api_key.len() > 0 {
+            &if self.etherscan_api_key.len() > 0 {
                 self.etherscan_api_key[rand::random::<usize>() % self.etherscan_api_key.len()]
                     .clone()
             } else {
                 "".to_string()
-            }
+            },
         );
-        println!("fetching abi from {}", endpoint);
+        debug!("fetching abi from {}", endpoint);
         match self.get(endpoint.clone()) {
             Some(resp) => {
-                let json = serde_json::from_str::<Value>(&resp);
-                match json {
-                    Ok(json) => {
-                        let result_parsed = json["result"].as_str();
-                        match result_parsed {
-                            Some(result) => {
-                                if result == "Contract source code not verified" {
-                                    None
-                                } else {
-                                    Some(result.to_string())
-                                }
-                            }
-                            _ => None,
-                        }
-                    }
-                    Err(_) => None,
+                let abi = self.explorer.parse_abi(&resp);
+                if abi.is_none() {
+                    info!("no abi found from {}", endpoint);
                 }
+                abi
             }
             None => {
-                println!("failed to fetch abi from {}", endpoint);
+                warn!("failed to fetch abi from {}", endpoint);
                 return None;
             }
         }
     }
 
+    /// Verified source of `address` from the block explorer
+    pub fn fetch_source(&self, address: EVMAddress) -> Option<ContractSource> {
+        let api_key = if self.etherscan_api_key.len() > 0 {
+            self.etherscan_api_key[rand::random::<usize>() % self.etherscan_api_key.len()].clone()
+        } else {
+            "".to_string()
+        };
+        let endpoint = self.explorer.source_url(&self.etherscan_base, address, &api_key)?;
+        debug!("fetching source from {}", endpoint);
+        let source = self.get(endpoint.clone()).and_then(|resp| self.explorer.parse_source(&resp));
+        if source.is_none() {
+            info!("no source found from {}", endpoint);
+        }
+        source
+    }
+
     pub fn fetch_abi(&mut self, address: EVMAddress) -> Option<String> {
         if self.abi_cache.contains_key(&address) {
             return self.abi_cache.get(&address).unwrap().clone();
@@ -584,14 +646,14 @@ impl OnChainConfig {
                         return json.get("result").cloned();
                     }
                     Err(e) => {
-                        println!("{:?}", e);
+                        warn!("{:?}", e);
                         return None;
                     }
                 }
             }
 
             None => {
-                println!("failed to fetch from {}", self.endpoint_url);
+                warn!("failed to fetch from {}", self.endpoint_url);
                 return None;
             }
         }
@@ -612,28 +674,28 @@ impl OnChainConfig {
                         return json.get("result").cloned();
                     }
                     Err(e) => {
-                        println!("{:?}", e);
+                        warn!("{:?}", e);
                         return None;
                     }
                 }
             }
 
             None => {
-                println!("failed to fetch from {}", self.endpoint_url);
+                warn!("failed to fetch from {}", self.endpoint_url);
                 return None;
             }
         }
     }
 
     pub fn get_contract_code(&mut self, address: EVMAddress, force_cache: bool) -> Bytecode {
-        if self.code_cache.contains_key(&address) {
-            return self.code_cache[&address].clone();
+        if let Some(code) = self.code_cache.get(&address) {
+            return code;
         }
         if force_cache {
             return Bytecode::default();
         }
 
-        println!("fetching code from {}", hex::encode(address));
+        debug!("fetching code from {}", hex::encode(address));
 
         let resp_string = {
             let mut params = String::from("[");
@@ -651,18 +713,18 @@ impl OnChainConfig {
         };
         let code = resp_string.trim_start_matches("0x");
         if code.len() == 0 {
-            self.code_cache.insert(address, Bytecode::new());
+            self.code_cache.insert(address, Bytecode::new(), 0);
             return Bytecode::new();
         }
         let code = hex::decode(code).unwrap();
         let bytes = to_analysed(Bytecode::new_raw(Bytes::from(code)));
-        self.code_cache.insert(address, bytes.clone());
+        self.code_cache.insert(address, bytes.clone(), bytes.len());
         return bytes;
     }
 
     pub fn get_contract_slot(&mut self, address: EVMAddress, slot: EVMU256, force_cache: bool) -> EVMU256 {
-        if self.slot_cache.contains_key(&(address, slot)) {
-            return self.slot_cache[&(address, slot)];
+        if let Some(value) = self.slot_cache.get(&(address, slot)) {
+            return value;
         }
         if force_cache {
             return EVMU256::ZERO;
@@ -687,14 +749,99 @@ impl OnChainConfig {
         let slot_suffix = resp_string.trim_start_matches("0x");
 
         if slot_suffix.len() == 0 {
-            self.slot_cache.insert((address, slot), EVMU256::ZERO);
+            self.slot_cache.insert((address, slot), EVMU256::ZERO, SLOT_ENTRY_SIZE);
             return EVMU256::ZERO;
         }
         let slot_value = EVMU256::try_from_be_slice(&hex::decode(slot_suffix).unwrap()).unwrap();
-        self.slot_cache.insert((address, slot), slot_value);
+        self.slot_cache.insert((address, slot), slot_value, SLOT_ENTRY_SIZE);
         return slot_value;
     }
 
+    /// Resolve the implementation address behind an EIP-1967 / EIP-1822 proxy.
+    /// Checks the implementation slot, the UUPS (PROXIABLE) slot and finally the
+    /// beacon slot (calling `implementation()` on the beacon).
+    /// Returns None if the address does not look like a proxy.
+    pub fn get_proxy_implementation(&mut self, address: EVMAddress) -> Option<EVMAddress> {
+        if self.proxy_cache.contains_key(&address) {
+            return self.proxy_cache[&address];
+        }
+        let to_address = |v: EVMU256| EVMAddress::from_slice(&v.to_be_bytes::<32>()[12..]);
+
+        let mut implementation = None;
+        for slot in [EIP1967_IMPLEMENTATION_SLOT, EIP1822_PROXIABLE_SLOT] {
+            let value = self.get_contract_slot(address, EVMU256::from_str(slot).unwrap(), false);
+            if value != EVMU256::ZERO {
+                implementation = Some(to_address(value));
+                break;
+            }
+        }
+
+        if implementation.is_none() {
+            let beacon = self.get_contract_slot(
+                address,
+                EVMU256::from_str(EIP1967_BEACON_SLOT).unwrap(),
+                false,
+            );
+            if beacon != EVMU256::ZERO {
+                // implementation() returns an abi encoded address
+                implementation = self
+                    .eth_call(to_address(beacon), "0x5c60da1b")
+                    .filter(|x| x.len() == 64)
+                    .and_then(|x| EVMU256::from_str_radix(&x, 16).ok())
+                    .filter(|x| *x != EVMU256::ZERO)
+                    .map(to_address);
+            }
+        }
+
+        // implementation without code is not a proxy we can fuzz through
+        let implementation = implementation
+            .filter(|x| *x != address && !self.get_contract_code(*x, false).is_empty());
+        self.proxy_cache.insert(address, implementation);
+        implementation
+    }
+
+    /// Find all facets of an EIP-2535 diamond through the loupe's `facetAddresses()`.
+    /// Returns an empty vector if the address is not a diamond.
+    pub fn get_diamond_facets(&mut self, address: EVMAddress) -> Vec<EVMAddress> {
+        if self.diamond_cache.contains_key(&address) {
+            return self.diamond_cache[&address].clone();
+        }
+        let facets = self
+            .eth_call(address, "0x52ef6b2c")
+            .map(|x| decode_address_array(&x))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|x| *x != address && !self.get_contract_code(*x, false).is_empty())
+            .unique()
+            .collect_vec();
+        self.diamond_cache.insert(address, facets.clone());
+        facets
+    }
+
+    /// Runtime source map of a contract verified on Sourcify, None if it is not verified
+    pub fn fetch_source_map(&self, address: EVMAddress) -> Option<HashMap<usize, SourceMapLocation>> {
+        let endpoint = format!(
+            "{}/v2/contract/{}/{:?}?fields=runtimeBytecode,sourceIds",
+            SOURCIFY_API, self.chain_id, address
+        );
+        debug!("fetching source map from {}", endpoint);
+        let source_map = self.get(endpoint.clone()).and_then(|resp| parse_sourcify_source_map(&resp));
+        if source_map.is_none() {
+            info!("no source map found from {}", endpoint);
+        }
+        source_map
+    }
+
+    /// Call `to` with `data` at the configured block, returns the hex output without 0x prefix
+    fn eth_call(&self, to: EVMAddress, data: &str) -> Option<String> {
+        let params = json!([{
+            "to": format!("0x{:x}", to),
+            "data": data,
+        }, self.block_number]);
+        self._request("eth_call".to_string(), params.to_string())
+            .and_then(|resp| resp.as_str().map(|x| x.trim_start_matches("0x").to_string()))
+    }
+
     pub fn fetch_uniswap_path(&self, token_address: EVMAddress) -> TokenContext {
         let token = format!("{:?}", token_address);
         let info: Info = self.find_path_subgraph(&self.chain_name, &token, &self.block_number);
@@ -796,7 +943,7 @@ impl OnChainConfig {
 impl OnChainConfig {
     fn get_pair(&self, token: &str, network: &str, block: &str) -> Vec<PairData> {
         let block_int;
-        println!("get pair {} {} {}", token, network, block);
+        debug!("get pair {} {} {}", token, network, block);
         match block {
             "latest" => block_int = self.get_latest_block() - 50,
             _ => block_int = u64::from_str_radix(&block.trim_start_matches("0x"), 16).unwrap(),
@@ -838,7 +985,7 @@ impl OnChainConfig {
     }
 
     fn get_pair_pegged(&self, token: &str, network: &str, block: &str) -> Vec<PairData> {
-        println!("get pair pegged {} {} {}", token, network, block);
+        debug!("get pair pegged {} {} {}", token, network, block);
         let block_int = if block != "latest" {
             u64::from_str_radix(&block.trim_start_matches("0x"), 16)
                 .expect("failed to parse block number")
@@ -885,73 +1032,21 @@ impl OnChainConfig {
     }
 
     fn get_weth(&self, network: &str) -> String {
-        let pegged_token = self.get_pegged_token(network);
-
-        match network {
-            "eth" => return pegged_token.get("WETH").unwrap().to_string(),
-            "bsc" => return pegged_token.get("WBNB").unwrap().to_string(),
-            "polygon" => return pegged_token.get("WMATIC").unwrap().to_string(),
-            "mumbai" => panic!("Not supported"),
-            _ => panic!("Unknown network"),
-        }
-    }
-
-    fn get_latest_block(&self) -> u64 {
-        let block = {
-            let mut params = String::from("[");
-            params.push_str("]");
-            let resp = self._request("eth_blockNumber".to_string(), params);
-            match resp {
-                Some(resp) => {
-                    let r = &resp.as_str().unwrap().trim_start_matches("0x");
-                    let data = u64::from_str_radix(r, 16).unwrap();
-                    data
-                }
-                None => 0,
-            }
-        };
-        block
+        known_addresses()
+            .chain(network)
+            .and_then(|addresses| addresses.native_token())
+            .unwrap_or_else(|| panic!("[Flashloan] Wrapped native token of {} is not known", network))
+            .to_string()
     }
 
     fn get_pegged_token(&self, network: &str) -> HashMap<String, String> {
-        match network {
-            "eth" => [
-                ("WETH", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
-                ("USDC", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
-                ("USDT", "0xdac17f958d2ee523a2206206994597c13d831ec7"),
-                ("DAI", "0x6b175474e89094c44da98b954eedeac495271d0f"),
-                ("WBTC", "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"),
-                ("WMATIC", "0x7d1afa7b718fb893db30a3abc0cfc608aacfebb0"),
-            ]
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect(),
-            "bsc" => [
-                ("WBNB", "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"),
-                ("USDC", "0x8ac76a51cc950d9822d68b83fe1ad97b32cd580d"),
-                ("USDT", "0x55d398326f99059ff775485246999027b3197955"),
-                ("DAI", "0x1af3f329e8be154074d8769d1ffa4ee058b1dbc3"),
-                ("WBTC", "0x7130d2a12b9bcbfae4f2634d864a1ee1ce3ead9c"),
-                ("WETH", "0x2170ed0880ac9a755fd29b2688956bd959f933f8"),
-                ("BUSD", "0xe9e7cea3dedca5984780bafc599bd69add087d56"),
-                ("CAKE", "0x0e09fabb73bd3ade0a17ecc321fd13a19e81ce82"),
-            ]
+        known_addresses()
+            .chain(network)
+            .unwrap_or_else(|| panic!("[Flashloan] Network {} is not supported", network))
+            .pegged
             .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect(),
-            "polygon" => [
-                ("WMATIC", "0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270"),
-                ("USDC", "0x2791bca1f2de4661ed88a30c99a7a9449aa84174"),
-                ("USDT", "0xc2132d05d31c914a87c6611c10748aeb04b58e8f"),
-                ("DAI", "0x8f3cf7ad23cd3cadbd9735aff958023239c6a063"),
-                ("WBTC", "0x1bfd67037b42cf73acf2047067bd4f2c47d9bfd6"),
-                ("WETH", "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619"),
-            ]
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect(),
-            _ => panic!("[Flashloan] Network is not supported"),
-        }
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
     }
 
     fn fetch_reserve(&self, pair: &str, block: &str) -> (String, String) {
@@ -1212,6 +1307,50 @@ impl PriceOracle for OnChainConfig {
     }
 }
 
+/// Decode the runtime source map of a Sourcify contract response, sources are ordered by their id
+fn parse_sourcify_source_map(resp: &str) -> Option<HashMap<usize, SourceMapLocation>> {
+    let json = serde_json::from_str::<Value>(resp).ok()?;
+    let bytecode = hex::decode(json["runtimeBytecode"]["onchainBytecode"].as_str()?.trim_start_matches("0x")).ok()?;
+    let source_map = json["runtimeBytecode"]["sourceMap"].as_str()?;
+    let mut files = vec![];
+    for (path, source) in json["sourceIds"].as_object()? {
+        let id = source["id"].as_u64()? as usize;
+        if files.len() <= id {
+            files.resize(id + 1, String::new());
+        }
+        files[id] = path.clone();
+    }
+    Some(decode_instructions(bytecode, source_map.to_string(), &files))
+}
+
+/// Decode an abi encoded `address[]` return value (hex without 0x prefix)
+fn decode_address_array(data: &str) -> Vec<EVMAddress> {
+    let data = match hex::decode(data) {
+        Ok(data) => data,
+        Err(_) => return vec![],
+    };
+    if data.len() < 64 {
+        return vec![];
+    }
+    let word = |idx: usize| EVMU256::try_from_be_slice(&data[idx..idx + 32]).unwrap();
+    let offset = word(0);
+    if offset > EVMU256::from(data.len() - 32) {
+        return vec![];
+    }
+    let offset = offset.as_limbs()[0] as usize;
+    let len = word(offset);
+    if len > EVMU256::from((data.len() - offset - 32) / 32) {
+        return vec![];
+    }
+    let len = len.as_limbs()[0] as usize;
+    (0..len)
+        .map(|i| {
+            let start = offset + 32 + i * 32;
+            EVMAddress::from_slice(&data[start + 12..start + 32])
+        })
+        .collect()
+}
+
 fn get_header() -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert("authority", "etherscan.io".parse().unwrap());
@@ -1267,7 +1406,7 @@ fn get_uniswap_api(network: &str) -> HashMap<&str, HashMap<&str, &str>> {
             api.insert("v2", v2);
         }
         _ => {
-            println!("[Flashloan] Network {} is not supported for ERC20 Flashloan", network)
+            warn!("[Flashloan] Network {} is not supported for ERC20 Flashloan", network)
         },
     }
 
@@ -1278,6 +1417,20 @@ mod tests {
     use super::*;
     use crate::evm::onchain::endpoints::Chain::{BSC, ETH};
 
+    #[test]
+    fn test_parse_sourcify_source_map() {
+        let resp = r#"{
+            "runtimeBytecode": {"onchainBytecode": "0x6080604052", "sourceMap": "10:20:1:-:0;;"},
+            "sourceIds": {"contracts/Lib.sol": {"id": 0}, "contracts/Impl.sol": {"id": 1}}
+        }"#;
+        let source_map = parse_sourcify_source_map(resp).unwrap();
+        // PUSH1 0x80, PUSH1 0x40, MSTORE
+        assert_eq!(source_map.len(), 3);
+        assert_eq!(source_map[&0].file, Some("contracts/Impl.sol".to_string()));
+        assert_eq!((source_map[&2].offset, source_map[&2].length), (10, 20));
+        assert!(parse_sourcify_source_map(r#"{"error": "Contract not found"}"#).is_none());
+    }
+
     #[test]
     fn test_onchain_config() {
         let mut config = OnChainConfig::new(BSC, 0);
@@ -1309,6 +1462,28 @@ mod tests {
         println!("{:?}", v)
     }
 
+    #[test]
+    fn test_decode_address_array() {
+        let data = format!(
+            "{:0>64}{:0>64}{:0>64}{:0>64}",
+            "20", "2", "10ed43c718714eb63d5aa57b78b54704e256024e", "b486857fac4254a7ffb3b1955ee0c0a2b2ca75ab"
+        );
+        assert_eq!(
+            decode_address_array(&data),
+            vec![
+                EVMAddress::from_str("0x10ed43c718714eb63d5aa57b78b54704e256024e").unwrap(),
+                EVMAddress::from_str("0xb486857fac4254a7ffb3b1955ee0c0a2b2ca75ab").unwrap(),
+            ]
+        );
+        assert!(decode_address_array("").is_empty());
+        // a length beyond the low limb is not truncated to 2
+        let data = format!(
+            "{:0>64}{:0>64}{:0>64}{:0>64}",
+            "20", "10000000000000002", "10ed43c718714eb63d5aa57b78b54704e256024e", "b486857fac4254a7ffb3b1955ee0c0a2b2ca75ab"
+        );
+        assert!(decode_address_array(&data).is_empty());
+    }
+
     #[test]
     fn test_fetch_abi() {
         let mut config = OnChainConfig::new(BSC, 0);
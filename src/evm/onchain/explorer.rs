@@ -0,0 +1,307 @@
+/// Adapters for block explorers with divergent APIs
+use crate::evm::types::EVMAddress;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+/// Verified source of a contract
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractSource {
+    pub name: String,
+    pub compiler_version: String,
+    /// Content of each source file, by path
+    pub files: BTreeMap<String, String>,
+}
+
+impl ContractSource {
+    /// Write the source files under `dir`, skipping paths escaping it
+    pub fn write(&self, dir: &str) {
+        for (path, content) in &self.files {
+            let relative = Path::new(path);
+            if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+                continue;
+            }
+            let file = Path::new(dir).join(relative);
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).expect("failed to create source directory");
+            }
+            std::fs::write(file, content).expect("failed to write source file");
+        }
+    }
+}
+
+/// Adapter of a block explorer, which tells how to fetch ABI and verified source of a contract
+pub trait ExplorerAdapter: Debug {
+    /// URL for fetching ABI of `address`
+    fn abi_url(&self, base: &str, address: EVMAddress, api_key: &str) -> String;
+
+    /// Extract ABI (JSON string) from the response, None if the contract is not verified
+    /// or the explorer returns an error
+    fn parse_abi(&self, resp: &str) -> Option<String>;
+
+    /// URL for fetching the verified source of `address`, None if the explorer does not
+    /// serve sources
+    fn source_url(&self, base: &str, address: EVMAddress, api_key: &str) -> Option<String>;
+
+    /// Extract the verified source from the response, None if the contract is not verified
+    /// or the explorer returns an error
+    fn parse_source(&self, resp: &str) -> Option<ContractSource>;
+}
+
+/// Only return ABIs that are valid JSON arrays, explorers put error messages
+/// (e.g., rate limit reached) in the same field as the ABI
+fn valid_abi(abi: &Value) -> Option<String> {
+    match abi {
+        Value::Array(_) => Some(abi.to_string()),
+        Value::String(s) => match serde_json::from_str::<Value>(s) {
+            Ok(Value::Array(_)) => Some(s.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Source files of Etherscan's `SourceCode`: a single file, a JSON object of files or a solc
+/// standard JSON input (wrapped in double braces)
+fn etherscan_files(name: &str, source_code: &str) -> BTreeMap<String, String> {
+    let json = match source_code.strip_prefix("{{").and_then(|s| s.strip_suffix("}}")) {
+        Some(inner) => serde_json::from_str::<Value>(&format!("{{{}}}", inner)).ok(),
+        None if source_code.starts_with('{') => serde_json::from_str::<Value>(source_code).ok(),
+        None => None,
+    };
+    let files = match &json {
+        Some(json) if json["sources"].is_object() => &json["sources"],
+        Some(json) => json,
+        None => return BTreeMap::from([(format!("{}.sol", name), source_code.to_string())]),
+    };
+    files
+        .as_object()
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|(path, file)| Some((path.clone(), file["content"].as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Etherscan and explorers compatible with it (e.g., Blockscout's and routescan's Etherscan-style API)
+#[derive(Debug, Clone, Default)]
+pub struct EtherscanAdapter;
+
+impl ExplorerAdapter for EtherscanAdapter {
+    fn abi_url(&self, base: &str, address: EVMAddress, api_key: &str) -> String {
+        format!(
+            "{}?module=contract&action=getabi&address={:?}&format=json&apikey={}",
+            base, address, api_key
+        )
+    }
+
+    fn parse_abi(&self, resp: &str) -> Option<String> {
+        let json = serde_json::from_str::<Value>(resp).ok()?;
+        valid_abi(&json["result"])
+    }
+
+    fn source_url(&self, base: &str, address: EVMAddress, api_key: &str) -> Option<String> {
+        Some(format!(
+            "{}?module=contract&action=getsourcecode&address={:?}&apikey={}",
+            base, address, api_key
+        ))
+    }
+
+    fn parse_source(&self, resp: &str) -> Option<ContractSource> {
+        let json = serde_json::from_str::<Value>(resp).ok()?;
+        let contract = &json["result"][0];
+        let (name, source_code) = (contract["ContractName"].as_str()?, contract["SourceCode"].as_str()?);
+        if source_code.is_empty() {
+            return None;
+        }
+        Some(ContractSource {
+            name: name.to_string(),
+            compiler_version: contract["CompilerVersion"].as_str().unwrap_or_default().to_string(),
+            files: etherscan_files(name, source_code),
+        })
+    }
+}
+
+/// Blockscout v2 API (`/api/v2/smart-contracts/{address}`)
+#[derive(Debug, Clone, Default)]
+pub struct BlockscoutAdapter;
+
+impl ExplorerAdapter for BlockscoutAdapter {
+    fn abi_url(&self, base: &str, address: EVMAddress, _api_key: &str) -> String {
+        format!("{}/v2/smart-contracts/{:?}", base.trim_end_matches('/'), address)
+    }
+
+    fn parse_abi(&self, resp: &str) -> Option<String> {
+        let json = serde_json::from_str::<Value>(resp).ok()?;
+        valid_abi(&json["abi"])
+    }
+
+    fn source_url(&self, base: &str, address: EVMAddress, api_key: &str) -> Option<String> {
+        // the same endpoint serves the ABI and the sources
+        Some(self.abi_url(base, address, api_key))
+    }
+
+    fn parse_source(&self, resp: &str) -> Option<ContractSource> {
+        let json = serde_json::from_str::<Value>(resp).ok()?;
+        let mut files = BTreeMap::from([(json["file_path"].as_str()?.to_string(), json["source_code"].as_str()?.to_string())]);
+        if let Some(additional) = json["additional_sources"].as_array() {
+            files.extend(additional.iter().filter_map(|file| {
+                Some((file["file_path"].as_str()?.to_string(), file["source_code"].as_str()?.to_string()))
+            }));
+        }
+        Some(ContractSource {
+            name: json["name"].as_str().unwrap_or_default().to_string(),
+            compiler_version: json["compiler_version"].as_str().unwrap_or_default().to_string(),
+            files,
+        })
+    }
+}
+
+/// Tronscan API (`/api/contract?contract={address}`), addresses are in Tron's hex format (41 || address)
+#[derive(Debug, Clone, Default)]
+pub struct TronscanAdapter;
+
+impl ExplorerAdapter for TronscanAdapter {
+    fn abi_url(&self, base: &str, address: EVMAddress, _api_key: &str) -> String {
+        format!(
+            "{}/contract?contract=41{}",
+            base.trim_end_matches('/'),
+            hex::encode(address.0)
+        )
+    }
+
+    fn parse_abi(&self, resp: &str) -> Option<String> {
+        let json = serde_json::from_str::<Value>(resp).ok()?;
+        let abi = &json["data"][0]["abi"];
+        // Tronscan wraps the ABI as {"entrys": [...]}, with capitalized types
+        match &abi["entrys"] {
+            Value::Array(entries) => Some(
+                Value::Array(
+                    entries
+                        .iter()
+                        .map(|entry| {
+                            let mut entry = entry.clone();
+                            if let Some(ty) = entry["type"].as_str() {
+                                entry["type"] = Value::String(ty.to_lowercase());
+                            }
+                            entry
+                        })
+                        .collect(),
+                )
+                .to_string(),
+            ),
+            _ => valid_abi(abi),
+        }
+    }
+
+    // Tronscan has no public API for verified sources
+    fn source_url(&self, _base: &str, _address: EVMAddress, _api_key: &str) -> Option<String> {
+        None
+    }
+
+    fn parse_source(&self, _resp: &str) -> Option<ContractSource> {
+        None
+    }
+}
+
+pub fn explorer_adapter_from_str(s: &str) -> Option<Arc<dyn ExplorerAdapter>> {
+    match s.to_lowercase().as_str() {
+        "etherscan" | "routescan" => Some(Arc::new(EtherscanAdapter)),
+        "blockscout" => Some(Arc::new(BlockscoutAdapter)),
+        "tronscan" => Some(Arc::new(TronscanAdapter)),
+        _ => None,
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etherscan_error_is_not_abi() {
+        let adapter = EtherscanAdapter;
+        assert_eq!(
+            adapter.parse_abi(r#"{"status":"0","message":"NOTOK","result":"Max rate limit reached"}"#),
+            None
+        );
+        assert_eq!(
+            adapter.parse_abi(r#"{"status":"1","message":"OK","result":"[{\"type\":\"fallback\"}]"}"#),
+            Some("[{\"type\":\"fallback\"}]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_blockscout_abi() {
+        let adapter = BlockscoutAdapter;
+        assert_eq!(
+            adapter.parse_abi(r#"{"abi":[{"type":"fallback"}]}"#),
+            Some("[{\"type\":\"fallback\"}]".to_string())
+        );
+        assert_eq!(adapter.parse_abi(r#"{"message":"Not found"}"#), None);
+    }
+
+    #[test]
+    fn test_etherscan_source() {
+        let adapter = EtherscanAdapter;
+        let resp = |source_code: &str| {
+            serde_json::json!({"status": "1", "result": [{
+                "ContractName": "Vault", "CompilerVersion": "v0.8.20+commit.a1b79de6", "SourceCode": source_code,
+            }]})
+            .to_string()
+        };
+        let single = adapter.parse_source(&resp("contract Vault {}")).unwrap();
+        assert_eq!(single.name, "Vault");
+        assert_eq!(single.compiler_version, "v0.8.20+commit.a1b79de6");
+        assert_eq!(single.files, BTreeMap::from([("Vault.sol".to_string(), "contract Vault {}".to_string())]));
+
+        let standard_json = adapter
+            .parse_source(&resp(r#"{{"language": "Solidity", "sources": {"src/Vault.sol": {"content": "contract Vault {}"}}}}"#))
+            .unwrap();
+        assert_eq!(standard_json.files, BTreeMap::from([("src/Vault.sol".to_string(), "contract Vault {}".to_string())]));
+
+        let multi_file = adapter.parse_source(&resp(r#"{"a.sol": {"content": "A"}, "b.sol": {"content": "B"}}"#)).unwrap();
+        assert_eq!(multi_file.files.len(), 2);
+
+        assert_eq!(adapter.parse_source(&resp("")), None);
+        assert_eq!(adapter.parse_source(r#"{"status":"0","message":"NOTOK","result":"Invalid API Key"}"#), None);
+    }
+
+    #[test]
+    fn test_blockscout_source() {
+        let adapter = BlockscoutAdapter;
+        let source = adapter
+            .parse_source(
+                r#"{"name": "Vault", "compiler_version": "v0.8.20", "file_path": "src/Vault.sol", "source_code": "V",
+                    "additional_sources": [{"file_path": "src/Lib.sol", "source_code": "L"}]}"#,
+            )
+            .unwrap();
+        assert_eq!(source.name, "Vault");
+        assert_eq!(
+            source.files,
+            BTreeMap::from([("src/Lib.sol".to_string(), "L".to_string()), ("src/Vault.sol".to_string(), "V".to_string())])
+        );
+        assert_eq!(adapter.parse_source(r#"{"message":"Not found"}"#), None);
+        assert!(TronscanAdapter.source_url("", EVMAddress::zero(), "").is_none());
+    }
+
+    #[test]
+    fn test_write_source() {
+        let dir = std::env::temp_dir().join(format!("ityfuzz_sources_{}", std::process::id()));
+        let source = ContractSource {
+            files: BTreeMap::from([
+                ("src/Vault.sol".to_string(), "V".to_string()),
+                ("../escaped.sol".to_string(), "E".to_string()),
+                ("/etc/absolute.sol".to_string(), "A".to_string()),
+            ]),
+            ..Default::default()
+        };
+        source.write(dir.join("sources").to_str().unwrap());
+        assert_eq!(std::fs::read_to_string(dir.join("sources/src/Vault.sol")).unwrap(), "V");
+        assert!(!dir.join("escaped.sol").exists());
+        assert_eq!(std::fs::read_dir(dir.join("sources")).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,6 +4,7 @@
 // when transfer, transferFrom, and src is not our, return success, reduce owed
 
 use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
+use tracing::{debug, info};
 use crate::evm::middlewares::middleware::CallMiddlewareReturn::ReturnSuccess;
 use crate::evm::middlewares::middleware::{Middleware, MiddlewareOp, MiddlewareType};
 use crate::evm::mutator::AccessPattern;
@@ -33,6 +34,7 @@ use std::ops::Deref;
 use crate::evm::contract_utils::ABIConfig;
 use crate::evm::onchain::onchain::OnChain;
 use crate::evm::oracles::erc20::IERC20OracleFlashloan;
+use crate::evm::receipts::Receipt;
 use crate::get_token_ctx;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -129,6 +131,11 @@ where
                 direct_data: Default::default(),
                 randomness: vec![0],
                 repeat: 1,
+                blob_env: Default::default(),
+                victim: false,
+                via_intermediary: false,
+                advance: Default::default(),
+                force_feed: EVMU256::ZERO,
             }
         }
         .as_any()
@@ -242,14 +249,26 @@ where
                     is_erc20 = true;
                 }
             } else {
-                println!("Ignoring token {:?}", addr);
+                debug!("Ignoring token {:?}", addr);
+            }
+        }
+
+        // LP tokens, V3 positions and lending receipts are valued through their underlying tokens,
+        // V3 positions (not ERC20) are rechecked like tokens
+        if let Some(receipt) = Receipt::detect(&abi_names) {
+            if let Ok(mut oracle) = self.flashloan_oracle.deref().try_borrow_mut() {
+                info!("{:?} receipt detected @ address {:?}", receipt, addr);
+                if receipt == Receipt::V3Positions {
+                    self.erc20_address.insert(addr.clone());
+                }
+                oracle.register_receipt(addr.clone(), receipt);
             }
         }
 
         // if the contract is pair
         if abi_signatures_pair.iter().all(|x| abi_names.contains(x)) {
             self.pair_address.insert(addr.clone());
-            println!("pair detected @ address {:?}", addr);
+            info!("pair detected @ address {:?}", addr);
             is_pair = true;
         }
 
@@ -0,0 +1,114 @@
+/// Memory-bounded cache of fetched on-chain data with least-recently-used eviction
+///
+/// The size of each entry is estimated by the cache owner. Once the total exceeds the cap, the
+/// least recently used entries are evicted. Evicted entries are fetched again when needed, which
+/// is cheap as RPC responses are also cached on disk.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Estimated bookkeeping overhead of an entry, in bytes
+pub const ENTRY_OVERHEAD: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct LruCache<K, V> {
+    entries: HashMap<K, (V, usize, u64)>,
+    /// Last use => key
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    /// Estimated memory used by the entries, in bytes
+    pub bytes: usize,
+    /// Memory cap in bytes, unbounded if None
+    pub cap: Option<usize>,
+    pub evictions: usize,
+}
+
+impl<K, V> Default for LruCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            bytes: 0,
+            cap: None,
+            evictions: 0,
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Get an entry, marking it as recently used
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (value, _, last_use) = self.entries.get_mut(key)?;
+        self.order.remove(last_use);
+        *last_use = tick;
+        self.order.insert(tick, key.clone());
+        Some(value.clone())
+    }
+
+    /// Insert an entry of `size` bytes, evicting least recently used entries if over the cap
+    pub fn insert(&mut self, key: K, value: V, size: usize) {
+        self.remove(&key);
+        self.tick += 1;
+        let size = size + ENTRY_OVERHEAD;
+        self.entries.insert(key.clone(), (value, size, self.tick));
+        self.order.insert(self.tick, key);
+        self.bytes += size;
+        if let Some(cap) = self.cap {
+            // the entry just inserted is kept
+            while self.bytes > cap && self.entries.len() > 1 {
+                let (_, oldest) = self.order.pop_first().unwrap();
+                self.remove_entry(&oldest);
+                self.evictions += 1;
+            }
+        }
+    }
+
+    fn remove_entry(&mut self, key: &K) {
+        if let Some((_, size, _)) = self.entries.remove(key) {
+            self.bytes -= size;
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some((_, _, last_use)) = self.entries.get(key) {
+            self.order.remove(&last_use.clone());
+            self.remove_entry(key);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = LruCache::default();
+        cache.cap = Some(3 * (ENTRY_OVERHEAD + 10));
+        cache.insert(1, "a", 10);
+        cache.insert(2, "b", 10);
+        cache.insert(3, "c", 10);
+        // 1 becomes the most recently used
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(4, "d", 10);
+        assert!(!cache.contains_key(&2));
+        assert!(cache.contains_key(&1) && cache.contains_key(&3) && cache.contains_key(&4));
+        assert_eq!(cache.evictions, 1);
+        assert_eq!(cache.bytes, 3 * (ENTRY_OVERHEAD + 10));
+
+        // replacing an entry doesn't count twice
+        cache.insert(4, "e", 10);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&4), Some("e"));
+    }
+}
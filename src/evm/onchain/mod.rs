@@ -1,5 +1,7 @@
 pub mod abi_decompiler;
 pub mod endpoints;
+pub mod explorer;
 pub mod flashloan;
+pub mod lru;
 pub mod onchain;
 pub mod selfdestruct;
\ No newline at end of file
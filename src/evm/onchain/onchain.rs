@@ -1,8 +1,11 @@
 use crate::evm::abi::{get_abi_type_boxed, register_abi_instance};
+use tracing::{debug, info};
 use crate::evm::bytecode_analyzer;
 use crate::evm::config::StorageFetchingMode;
 use crate::evm::contract_utils::{ABIConfig, ContractLoader, extract_sig_from_contract};
+use crate::evm::function_weights::is_fuzzed;
 use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
+use crate::evm::known_addresses::known_addresses;
 
 use crate::evm::host::FuzzHost;
 use crate::evm::middlewares::middleware::{add_corpus, Middleware, MiddlewareType};
@@ -85,19 +88,7 @@ where
 {
     pub fn new(endpoint: OnChainConfig, storage_fetching: StorageFetchingMode) -> Self {
         unsafe {
-            BLACKLIST_ADDR = Some(HashSet::from([
-                EVMAddress::from_str("0x3cb4ca3c9dc0e02d252098eebb3871ac7a43c54d").unwrap(),
-                EVMAddress::from_str("0x6aed013308d847cb87502d86e7d9720b17b4c1f2").unwrap(),
-                EVMAddress::from_str("0x5a58505a96d1dbf8df91cb21b54419fc36e93fde").unwrap(),
-                EVMAddress::from_str("0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640").unwrap(),
-                EVMAddress::from_str("0xa40cac1b04d7491bdfb42ccac97dff25e0efb09e").unwrap(),
-                // uniswap router
-                EVMAddress::from_str("0xca143ce32fe78f1f7019d7d551a6402fc5350c73").unwrap(),
-                EVMAddress::from_str("0x7a250d5630b4cf539739df2c5dacb4c659f2488d").unwrap(),
-                // pancake router
-                EVMAddress::from_str("0x6CD71A07E72C514f5d511651F6808c6395353968").unwrap(),
-                EVMAddress::from_str("0x10ed43c718714eb63d5aa57b78b54704e256024e").unwrap(),
-            ]));
+            BLACKLIST_ADDR = Some(HashSet::from_iter(known_addresses().skipped()));
         }
         Self {
             loaded_data: Default::default(),
@@ -106,19 +97,8 @@ where
             calls: Default::default(),
             locs: Default::default(),
             endpoint,
-            blacklist: HashSet::from([
-                EVMAddress::from_str("0x3cb4ca3c9dc0e02d252098eebb3871ac7a43c54d").unwrap(),
-                EVMAddress::from_str("0x6aed013308d847cb87502d86e7d9720b17b4c1f2").unwrap(),
-                EVMAddress::from_str("0x5a58505a96d1dbf8df91cb21b54419fc36e93fde").unwrap(),
-                EVMAddress::from_str("0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640").unwrap(),
-                EVMAddress::from_str("0xa40cac1b04d7491bdfb42ccac97dff25e0efb09e").unwrap(),
-                // uniswap router
-                EVMAddress::from_str("0xca143ce32fe78f1f7019d7d551a6402fc5350c73").unwrap(),
-                EVMAddress::from_str("0x7a250d5630b4cf539739df2c5dacb4c659f2488d").unwrap(),
-                // pancake router
-                EVMAddress::from_str("0x6CD71A07E72C514f5d511651F6808c6395353968").unwrap(),
-                EVMAddress::from_str("0x10ed43c718714eb63d5aa57b78b54704e256024e").unwrap(),
-            ]),
+            // large pools and routers
+            blacklist: HashSet::from_iter(known_addresses().skipped()),
             storage_all: Default::default(),
             storage_dump: Default::default(),
             phantom: Default::default(),
@@ -273,7 +253,7 @@ where
                 if !self.loaded_code.contains(&address_h160) && !host.code.contains_key(&address_h160) {
                     bytecode_analyzer::add_analysis_result_to_state(&contract_code, state);
                     host.set_codedata(address_h160, contract_code.clone());
-                    println!("fetching code from {:?} due to call by {:?}",
+                    debug!("fetching code from {:?} due to call by {:?}",
                              address_h160, caller);
                 }
                 if unsafe { IS_FAST_CALL } || self.blacklist.contains(&address_h160) ||
@@ -289,7 +269,7 @@ where
                     _ => false,
                 };
 
-                println!("fetching abi {:?}", address_h160);
+                debug!("fetching abi {:?}", address_h160);
                 let abi = self.endpoint.fetch_abi(address_h160);
 
                 let mut parsed_abi = vec![];
@@ -301,7 +281,7 @@ where
                         // 1. Extract abi from bytecode, and see do we have any function sig available in state
                         // 2. Use Heimdall to extract abi
                         // 3. Reconfirm on failures of heimdall
-                        println!("Contract {:?} has no abi", address_h160);
+                        info!("Contract {:?} has no abi", address_h160);
                         let contract_code_str = hex::encode(contract_code.bytes());
                         let sigs = extract_sig_from_contract(&contract_code_str);
                         let mut unknown_sigs: usize = 0;
@@ -314,7 +294,7 @@ where
                         }
 
                         if unknown_sigs >= sigs.len() / 30 {
-                            println!("Too many unknown function signature for {:?}, we are going to decompile this contract using Heimdall", address_h160);
+                            info!("Too many unknown function signature for {:?}, we are going to decompile this contract using Heimdall", address_h160);
                             let abis = fetch_abi_heimdall(contract_code_str)
                                 .iter()
                                 .map(|abi| {
@@ -347,7 +327,7 @@ where
                             host.add_one_hashes(caller, hash);
                         }
                     }
-                    println!("Propagating hashes {:?} for proxy {:?}",
+                    debug!("Propagating hashes {:?} for proxy {:?}",
                              abi_hashes_to_add
                                  .iter()
                                 .map(|x| hex::encode(x))
@@ -383,6 +363,7 @@ where
                     .iter()
                     .filter(|v| !v.is_constructor)
                     .filter( |v| abi_hashes_to_add.contains(&v.function))
+                    .filter(|v| is_fuzzed(&v.function_name))
                     .for_each(|abi| {
                         #[cfg(not(feature = "fuzz_static"))]
                         if abi.is_static {
@@ -416,6 +397,11 @@ where
                             direct_data: Default::default(),
                             randomness: vec![0],
                             repeat: 1,
+                            blob_env: Default::default(),
+                            victim: false,
+                            via_intermediary: false,
+                            advance: Default::default(),
+                            force_feed: EVMU256::ZERO,
                         };
                         add_corpus(host, state, &input);
                     });
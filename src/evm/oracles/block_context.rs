@@ -0,0 +1,118 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
+use crate::evm::middlewares::block_context::BlockContextPayout;
+use crate::evm::oracles::BLOCK_CONTEXT_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::input::VMInputT;
+use crate::oracle::{Oracle, OracleCtx};
+use crate::state::HasExecutionResult;
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Detects payouts steerable through the block context (MEV context): reported when a
+/// transaction receives ETH or tokens from a contract that read block.coinbase, block.basefee
+/// or block.prevrandao before paying out (recorded by
+/// [`crate::evm::middlewares::block_context::BlockContextTracker`]), e.g., a prevrandao-based
+/// lottery paying the caller. ETH payouts are only reported when they exceed the value sent.
+pub struct BlockContextOracle;
+
+impl BlockContextOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Whether a payout of a transaction sending `sent` wei is reported
+fn reportable(payout: &BlockContextPayout, sent: EVMU256) -> bool {
+    (payout.token.is_some() || payout.amount > sent) &&
+        // payouts only depending on the gas price are checked by the fee oracle
+        payout.reads.iter().any(|r| r != "gasprice")
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for BlockContextOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if ctx.fuzz_state.get_execution_result().reverted {
+            return vec![];
+        }
+        let sent = ctx.input.get_txn_value().unwrap_or(EVMU256::ZERO);
+        let block = &ctx.input.get_vm_env().block;
+        let previous = ctx.pre_state.block_context_payouts.len();
+        ctx.post_state
+            .block_context_payouts
+            .iter()
+            .skip(previous)
+            .filter(|p| reportable(p, sent))
+            .map(|p| {
+                let asset = match p.token {
+                    Some(token) => format!("token {:?}", token),
+                    None => "wei".to_string(),
+                };
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[block_context] {:?} (pc {}) pays {} {} to {:?} depending on {} (coinbase: {:?}, basefee: {}, prevrandao: {:?})\n",
+                        p.contract,
+                        p.pc,
+                        p.amount,
+                        asset,
+                        p.recipient,
+                        p.reads.join(", "),
+                        block.coinbase,
+                        block.basefee,
+                        block.prevrandao
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                p.contract.hash(&mut hasher);
+                p.pc.hash(&mut hasher);
+                (hasher.finish() << 8) + BLOCK_CONTEXT_BUG_IDX
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reportable() {
+        let payout = |reads: &[&str], token: Option<EVMAddress>, amount: u64| BlockContextPayout {
+            contract: EVMAddress::from_slice(&[1; 20]),
+            pc: 0,
+            reads: reads.iter().map(|r| r.to_string()).collect(),
+            recipient: EVMAddress::from_slice(&[2; 20]),
+            token,
+            amount: EVMU256::from(amount),
+        };
+        let sent = EVMU256::from(100);
+        assert!(reportable(&payout(&["prevrandao"], None, 200), sent));
+        // ETH payouts must exceed the value sent, e.g., not a refund
+        assert!(!reportable(&payout(&["prevrandao"], None, 100), sent));
+        assert!(reportable(&payout(&["coinbase"], Some(EVMAddress::from_slice(&[3; 20])), 1), sent));
+        assert!(!reportable(&payout(&["gasprice"], None, 200), sent));
+        assert!(reportable(&payout(&["basefee", "gasprice"], None, 200), sent));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::evm::bridge::Bridge;
+use crate::evm::contract_utils::set_hash;
+use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
+use crate::evm::oracles::BRIDGE_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Number of states whose delivered messages are tracked
+const MAX_TRACKED_STATES: usize = 4096;
+
+/// Detects mints of a bridge that are not backed or do not match their message:
+/// - the supply of the bridged token exceeds the tokens locked in the escrow modeling the remote
+///   chain (only transitions from a backed supply are reported),
+/// - a message delivered by a messenger mints an amount differing from the amount it carries
+///   (parsing / decimals bugs),
+/// - the same message mints twice in a sequence (replay).
+pub struct BridgeOracle {
+    pub bridge: Bridge,
+    /// totalSupply of the bridged token followed by balanceOf of the escrow holders
+    pub batch_call_txs: Vec<(EVMAddress, Bytes)>,
+    /// Hash of a VM state => hashes of the messages that minted in the sequence leading to it
+    minted: RefCell<HashMap<u64, Vec<u64>>>,
+}
+
+impl BridgeOracle {
+    pub fn new(bridge: Bridge) -> Self {
+        let mut total_supply = [0u8; 4];
+        set_hash("totalSupply()", &mut total_supply);
+        let mut batch_call_txs = vec![(bridge.token, Bytes::from(total_supply.to_vec()))];
+        if let Some((token, holders)) = &bridge.escrow {
+            let mut balance_of = [0u8; 4];
+            set_hash("balanceOf(address)", &mut balance_of);
+            for holder in holders {
+                let mut data = balance_of.to_vec();
+                data.extend([0; 12]);
+                data.extend(holder.0);
+                batch_call_txs.push((*token, Bytes::from(data)));
+            }
+        }
+        Self {
+            bridge,
+            batch_call_txs,
+            minted: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// (supply of the bridged token, tokens locked) from the outputs of the batch calls
+    fn amounts(outputs: &Vec<Vec<u8>>) -> (EVMU256, EVMU256) {
+        let amount = |out: &Vec<u8>| {
+            if out.len() >= 32 {
+                EVMU256::from_be_slice(&out[..32])
+            } else {
+                EVMU256::ZERO
+            }
+        };
+        let supply = outputs.first().map_or(EVMU256::ZERO, amount);
+        let locked = outputs
+            .iter()
+            .skip(1)
+            .fold(EVMU256::ZERO, |locked, out| locked.saturating_add(amount(out)));
+        (supply, locked)
+    }
+
+    fn check(&self, ctx: &mut EVMOracleCtx<'_>, delivered: &mut Vec<u64>) -> Vec<u64> {
+        let after = Self::amounts(&ctx.call_post_batch(&self.batch_call_txs));
+        let before = Self::amounts(&ctx.call_pre_batch(&self.batch_call_txs));
+        let is_delivery =
+            self.bridge.messengers.contains(&ctx.input.caller) && ctx.input.contract == self.bridge.receiver;
+        let calldata = if is_delivery { Some(ctx.input.to_bytes()) } else { None };
+        self.findings(before, after, calldata.as_deref(), delivered)
+            .into_iter()
+            .map(|(kind, message)| self.report(message, kind))
+            .collect()
+    }
+
+    /// Bugs (kind, message) of a transaction changing (supply, locked) from `before` to
+    /// `after`, `message` is the calldata of a delivered message
+    fn findings(
+        &self,
+        before: (EVMU256, EVMU256),
+        after: (EVMU256, EVMU256),
+        message: Option<&[u8]>,
+        delivered: &mut Vec<u64>,
+    ) -> Vec<(u8, String)> {
+        let ((supply_before, locked_before), (supply, locked)) = (before, after);
+        if supply <= supply_before {
+            return vec![];
+        }
+        let minted = supply - supply_before;
+        let mut bugs = vec![];
+        if self.bridge.escrow.is_some() && supply > locked && supply_before <= locked_before {
+            bugs.push((0, format!("bridged supply {} exceeds the {} locked on the remote side", supply, locked)));
+        }
+
+        let calldata = match message {
+            Some(calldata) => calldata,
+            None => return bugs,
+        };
+        if let Some(amount) = self.bridge.amount(calldata) {
+            if amount != minted {
+                bugs.push((1, format!("message carrying {} minted {}", amount, minted)));
+            }
+        }
+        let mut hasher = DefaultHasher::new();
+        calldata.hash(&mut hasher);
+        let message = hasher.finish();
+        if delivered.contains(&message) {
+            bugs.push((2, format!("message replayed, minted {} again", minted)));
+        } else {
+            delivered.push(message);
+        }
+        bugs
+    }
+
+    fn report(&self, message: String, kind: u8) -> u64 {
+        unsafe {
+            ORACLE_OUTPUT += format!("[bridge] {}\n", message).as_str();
+        }
+        let mut hasher = DefaultHasher::new();
+        self.bridge.receiver.hash(&mut hasher);
+        kind.hash(&mut hasher);
+        (hasher.finish() << 8) + BRIDGE_BUG_IDX
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for BridgeOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        // messages that minted are carried along the sequence
+        let mut delivered = self
+            .minted
+            .borrow()
+            .get(&ctx.pre_state.get_hash())
+            .cloned()
+            .unwrap_or_default();
+        let bugs = self.check(ctx, &mut delivered);
+        let mut states = self.minted.borrow_mut();
+        if states.len() >= MAX_TRACKED_STATES {
+            states.clear();
+        }
+        states.insert(ctx.post_state.get_hash(), delivered);
+        bugs
+    }
+}
+
+mod tests {
+    use super::*;
+
+    fn oracle(escrow: bool) -> BridgeOracle {
+        let address = |b: u8| EVMAddress::from_slice(&[b; 20]);
+        BridgeOracle::new(Bridge {
+            messengers: vec![address(1)],
+            receiver: address(2),
+            handlers: vec!["lzReceive".to_string()],
+            token: address(3),
+            escrow: escrow.then(|| (address(4), vec![address(5), address(6)])),
+            amount_word: Some(1),
+        })
+    }
+
+    fn message(amount: u64) -> Vec<u8> {
+        let mut calldata = vec![0; 36];
+        calldata.extend(EVMU256::from(amount).to_be_bytes::<32>());
+        calldata
+    }
+
+    fn word(v: u64) -> Vec<u8> {
+        EVMU256::from(v).to_be_bytes::<32>().to_vec()
+    }
+
+    #[test]
+    fn test_batch_calls() {
+        assert_eq!(oracle(false).batch_call_txs.len(), 1);
+        // balances of both holders of the escrow
+        assert_eq!(oracle(true).batch_call_txs.len(), 3);
+        assert_eq!(
+            BridgeOracle::amounts(&vec![word(10), word(4), vec![], word(5)]),
+            (EVMU256::from(10), EVMU256::from(9))
+        );
+    }
+
+    #[test]
+    fn test_unbacked_supply() {
+        let oracle = oracle(true);
+        let amounts = |supply: u64, locked: u64| (EVMU256::from(supply), EVMU256::from(locked));
+        let kinds = |before, after| {
+            oracle
+                .findings(before, after, None, &mut vec![])
+                .into_iter()
+                .map(|(kind, _)| kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(amounts(10, 10), amounts(20, 10)), vec![0]);
+        assert!(kinds(amounts(10, 10), amounts(20, 20)).is_empty());
+        // already unbacked before the transaction
+        assert!(kinds(amounts(20, 10), amounts(30, 10)).is_empty());
+        assert!(kinds(amounts(20, 10), amounts(10, 10)).is_empty());
+    }
+
+    #[test]
+    fn test_message_amount_and_replay() {
+        let oracle = oracle(false);
+        let (before, after) = ((EVMU256::from(10), EVMU256::ZERO), (EVMU256::from(15), EVMU256::ZERO));
+        let mut delivered = vec![];
+        assert!(oracle.findings(before, after, Some(&message(5)), &mut delivered).is_empty());
+        assert_eq!(delivered.len(), 1);
+        // the same message minting again
+        let bugs = oracle.findings(before, after, Some(&message(5)), &mut delivered);
+        assert_eq!(bugs.iter().map(|(kind, _)| *kind).collect::<Vec<_>>(), vec![2]);
+        let bugs = oracle.findings(before, after, Some(&message(50)), &mut delivered);
+        assert_eq!(bugs, vec![(1, "message carrying 50 minted 5".to_string())]);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::constructor::ConstructorFinding;
+use crate::evm::oracles::CONSTRUCTOR_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Reports bugs of the constructors of the targets (recorded during their deployment by
+/// [`crate::evm::middlewares::constructor::ConstructorTracker`]). They are part of the initial
+/// state, so each is reported once, with the first transaction executed.
+pub struct ConstructorOracle;
+
+impl ConstructorOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for ConstructorOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        ctx.post_state
+            .constructor_findings
+            .iter()
+            .map(|finding| {
+                let report = match finding {
+                    ConstructorFinding::ExternalCall { contract, pc, target } => format!(
+                        "constructor of {:?} hands control to {:?} (pc {}), an account without code that can be an attacker contract",
+                        contract, target, pc
+                    ),
+                    ConstructorFinding::Reentrancy { contract, caller } => format!(
+                        "{:?} is called back by {:?} before its constructor returned",
+                        contract, caller
+                    ),
+                    ConstructorFinding::Uninitialized { contract } => format!(
+                        "constructor of {:?} leaves it uninitialized (no _disableInitializers()), anyone can call its initializer",
+                        contract
+                    ),
+                };
+                unsafe {
+                    ORACLE_OUTPUT += format!("[constructor] {}\n", report).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                finding.hash(&mut hasher);
+                (hasher.finish() << 8) + CONSTRUCTOR_BUG_IDX
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,97 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::DELEGATECALL_BUG_IDX;
+use crate::evm::types::{convert_h160_to_u256, convert_u256_to_h160, EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Storage slot written by the hijack implementation, bytes32(uint256(keccak256('ityfuzz.hijack')) - 1)
+pub const HIJACK_SLOT: &str = "0x2dc8d6370600b321fd193b6ce8ed2e96e8b56bafc8ed3147e5c0095348c7f6e4";
+
+/// The hijack implementation executed by a delegatecall to an untrusted address: it writes the
+/// address of the implementation to [`HIJACK_SLOT`] of the calling contract, which proves
+/// arbitrary storage writes (e.g., taking over the owner, or selfdestruct) in its context.
+pub fn hijack_storage_write(implementation: EVMAddress) -> (EVMU256, EVMU256) {
+    (
+        EVMU256::from_str(HIJACK_SLOT).unwrap(),
+        convert_h160_to_u256(implementation),
+    )
+}
+
+/// Detects delegatecalls to addresses controlled by the attacker (callers, or addresses without
+/// code, taken from calldata or from storage calldata was written to). Such delegatecalls run the hijack
+/// implementation (see [`hijack_storage_write`]), and the takeover is reported when its
+/// storage write persists after the transaction.
+pub struct DelegatecallOracle;
+
+impl DelegatecallOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for DelegatecallOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let slot = EVMU256::from_str(HIJACK_SLOT).unwrap();
+        ctx.post_state
+            .state
+            .iter()
+            .filter_map(|(address, storage)| {
+                let hijacked = storage.get(&slot).cloned().unwrap_or_default();
+                let before = ctx.pre_state.get(address).and_then(|s| s.get(&slot).cloned()).unwrap_or_default();
+                if hijacked == EVMU256::ZERO || hijacked == before {
+                    return None;
+                }
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[delegatecall] {:?} is taken over by delegatecall to attacker-controlled {:?}\n",
+                        address,
+                        convert_u256_to_h160(hijacked)
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                address.hash(&mut hasher);
+                Some((hasher.finish() << 8) + DELEGATECALL_BUG_IDX)
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    #[test]
+    fn test_hijack_storage_write() {
+        let implementation = fixed_address("00000000000000000000000000000000deadbeef");
+        let (slot, value) = hijack_storage_write(implementation);
+        assert_eq!(slot, EVMU256::from_str(HIJACK_SLOT).unwrap());
+        assert_eq!(value, convert_h160_to_u256(implementation));
+    }
+}
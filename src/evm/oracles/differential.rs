@@ -0,0 +1,64 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::DIFFERENTIAL_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Reports transactions whose effects (success, return data, events, storage writes) diverge
+/// between the target and the shadow version (see [`crate::evm::differential`]).
+pub struct DifferentialOracle;
+
+impl DifferentialOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for DifferentialOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let divergence = match &ctx.post_state.differential {
+            Some(divergence) => divergence,
+            None => return vec![],
+        };
+        let function = ctx.input.data.as_ref().map(|abi| abi.function).unwrap_or_default();
+        unsafe {
+            ORACLE_OUTPUT += format!(
+                "[differential] versions diverge on {:?}::0x{}: {}\n",
+                ctx.input.contract,
+                hex::encode(function),
+                divergence
+            ).as_str();
+        }
+        // one report per function and kind of divergence
+        let mut hasher = DefaultHasher::new();
+        function.hash(&mut hasher);
+        divergence.split(' ').take(2).collect::<Vec<_>>().hash(&mut hasher);
+        vec![(hasher.finish() << 8) + DIFFERENTIAL_BUG_IDX]
+    }
+}
@@ -0,0 +1,90 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::DELEGATED_EOA_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use crate::fuzzer::ORACLE_OUTPUT;
+
+/// Address the fuzzer's EOAs delegate to when EIP-7702 is enabled
+pub const DELEGATION_TARGET: &str = "0000000000000000000000000000000000007702";
+
+/// Code of an account delegating to `target` (EIP-7702 delegation designator)
+pub fn delegation_code(target: &EVMAddress) -> Vec<u8> {
+    [vec![0xef, 0x01, 0x00], target.0.to_vec()].concat()
+}
+
+/// Target of the delegation designator `0xef0100 || address`, if the code is one
+pub fn delegation_designator(code: &[u8]) -> Option<EVMAddress> {
+    if code.len() == 23 && code[..3] == [0xef, 0x01, 0x00] {
+        Some(EVMAddress::from_slice(&code[3..]))
+    } else {
+        None
+    }
+}
+
+/// Detects contracts that assume an account is an EOA (`msg.sender == tx.origin`) and then hand
+/// control to the account, which no longer holds once the EOA has delegated code (EIP-7702).
+/// `extcodesize(account) == 0` checks are not affected, delegated EOAs have the 23 bytes of the
+/// delegation designator as code.
+pub struct DelegatedEOAOracle;
+
+impl DelegatedEOAOracle {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for DelegatedEOAOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if ctx.post_state.delegated_eoa_hit {
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[eip7702] EOA check (msg.sender == tx.origin) bypassed by delegated EOA at contract {:?}\n",
+                    ctx.input.contract
+                ).as_str();
+            }
+            vec![DELEGATED_EOA_BUG_IDX]
+        } else {
+            vec![]
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delegation_designator() {
+        let target = EVMAddress::from_slice(&[0x77; 20]);
+        let code = delegation_code(&target);
+        assert_eq!(code.len(), 23);
+        assert_eq!(delegation_designator(&code), Some(target));
+        // padded by the analysis, or regular code starting like a designator
+        assert_eq!(delegation_designator(&[code.clone(), vec![0]].concat()), None);
+        assert_eq!(delegation_designator(&code[..22]), None);
+        assert_eq!(delegation_designator(&[0x60, 0x80, 0x60, 0x40]), None);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::evm::contract_utils::set_hash;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::EPOCH_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::generic_vm::vm_state::VMStateT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Number of states whose epoch is tracked
+const MAX_TRACKED_STATES: usize = 4096;
+
+/// How a probe may evolve from one snapshot to the next
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EpochRule {
+    /// Never lower than at the previous snapshot (e.g., `exchangeRate()`)
+    NonDecreasing,
+    /// Never higher than at the previous snapshot
+    NonIncreasing,
+    /// Never higher than the bound (e.g., `totalDebt()` below `debtCap()`)
+    AtMost,
+    /// Never lower than the bound
+    AtLeast,
+}
+
+/// A view function read at each snapshot
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    pub name: String,
+    /// Address or contract name
+    pub contract: String,
+    /// Function signature, e.g., `exchangeRate()`
+    pub function: String,
+    /// ABI-encoded arguments (hex)
+    #[serde(default)]
+    pub args: Option<String>,
+    pub rule: EpochRule,
+    /// Bound of `at_most` / `at_least`: the name of another probe or a number
+    #[serde(default)]
+    pub bound: Option<String>,
+    /// Drift allowed against the rule, in wei
+    #[serde(default)]
+    pub tolerance: u64,
+}
+
+/// Invariants checked across snapshots, loaded from a JSON file:
+/// ```json
+/// {
+///   "epoch": 8,
+///   "probes": [
+///     {"name": "rate", "contract": "Vault", "function": "exchangeRate()", "rule": "non_decreasing"},
+///     {"name": "cap", "contract": "Market", "function": "debtCap()", "rule": "non_increasing"},
+///     {"name": "debt", "contract": "Market", "function": "totalDebt()", "rule": "at_most", "bound": "cap"}
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EpochConfig {
+    /// Transactions between two snapshots
+    #[serde(default = "default_epoch")]
+    pub epoch: usize,
+    pub probes: Vec<ProbeConfig>,
+}
+
+fn default_epoch() -> usize {
+    8
+}
+
+impl EpochConfig {
+    pub fn from_file(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).expect("failed to read epoch invariants");
+        serde_json::from_str(&content).expect("failed to parse epoch invariants")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Probe(usize),
+    Value(EVMU256),
+}
+
+/// A probe, resolved
+#[derive(Clone, Debug)]
+pub struct Probe {
+    pub name: String,
+    pub rule: EpochRule,
+    pub bound: Option<Bound>,
+    pub tolerance: EVMU256,
+}
+
+/// Values of the probes at the last snapshot and transactions executed since
+#[derive(Clone, Debug)]
+struct Epoch {
+    snapshot: Vec<EVMU256>,
+    txs: usize,
+}
+
+/// Detects slow drift of the accounting of a protocol: every `epoch` transactions of a sequence,
+/// the probes are read and compared to their values at the previous snapshot (monotonicity) and
+/// to their bounds. Violations are reported even if no single transaction tripped an oracle,
+/// e.g., an exchange rate losing a few wei to rounding at each deposit.
+pub struct EpochOracle {
+    pub epoch: usize,
+    pub probes: Vec<Probe>,
+    pub batch_call_txs: Vec<(EVMAddress, Bytes)>,
+    /// Hash of a VM state => its epoch
+    epochs: RefCell<HashMap<u64, Epoch>>,
+}
+
+impl EpochOracle {
+    pub fn new(config: &EpochConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        assert!(config.epoch > 0, "epoch should be at least one transaction");
+        let names = config.probes.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+        let mut probes = vec![];
+        let mut batch_call_txs = vec![];
+        for probe in &config.probes {
+            let bound = match (probe.rule, &probe.bound) {
+                (EpochRule::AtMost | EpochRule::AtLeast, Some(bound)) => {
+                    Some(match names.iter().position(|n| n == bound) {
+                        Some(idx) => Bound::Probe(idx),
+                        None => Bound::Value(
+                            EVMU256::from_str(bound)
+                                .unwrap_or_else(|_| panic!("bound {} of probe {} is not a probe or a number", bound, probe.name)),
+                        ),
+                    })
+                }
+                (EpochRule::AtMost | EpochRule::AtLeast, None) => panic!("probe {} requires a bound", probe.name),
+                _ => None,
+            };
+            let mut data = vec![0u8; 4];
+            set_hash(&probe.function, &mut data);
+            if let Some(args) = &probe.args {
+                data.extend(hex::decode(args.trim_start_matches("0x")).expect("probe arguments should be hex"));
+            }
+            batch_call_txs.push((resolve(&probe.contract), Bytes::from(data)));
+            probes.push(Probe {
+                name: probe.name.clone(),
+                rule: probe.rule,
+                bound,
+                tolerance: EVMU256::from(probe.tolerance),
+            });
+        }
+        Self {
+            epoch: config.epoch,
+            probes,
+            batch_call_txs,
+            epochs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn values(outputs: &Vec<Vec<u8>>) -> Vec<EVMU256> {
+        outputs
+            .iter()
+            .map(|out| {
+                if out.len() >= 32 {
+                    EVMU256::from_be_slice(&out[..32])
+                } else {
+                    EVMU256::ZERO
+                }
+            })
+            .collect()
+    }
+
+    /// Violations (probe, description) of the snapshot `after` following `before`
+    fn violations(&self, before: &[EVMU256], after: &[EVMU256]) -> Vec<(usize, String)> {
+        let mut violations = vec![];
+        for (idx, probe) in self.probes.iter().enumerate() {
+            let (prev, now) = (before[idx], after[idx]);
+            let bound = match &probe.bound {
+                Some(Bound::Probe(other)) => after[*other],
+                Some(Bound::Value(value)) => *value,
+                None => EVMU256::ZERO,
+            };
+            let violated = match probe.rule {
+                EpochRule::NonDecreasing => now.saturating_add(probe.tolerance) < prev,
+                EpochRule::NonIncreasing => now > prev.saturating_add(probe.tolerance),
+                EpochRule::AtMost => now > bound.saturating_add(probe.tolerance),
+                EpochRule::AtLeast => now.saturating_add(probe.tolerance) < bound,
+            };
+            if !violated {
+                continue;
+            }
+            violations.push((
+                idx,
+                match probe.rule {
+                    EpochRule::NonDecreasing => format!("{} decreased from {} to {}", probe.name, prev, now),
+                    EpochRule::NonIncreasing => format!("{} increased from {} to {}", probe.name, prev, now),
+                    EpochRule::AtMost => format!("{} = {} exceeded its bound {}", probe.name, now, bound),
+                    EpochRule::AtLeast => format!("{} = {} fell below its bound {}", probe.name, now, bound),
+                },
+            ));
+        }
+        violations
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for EpochOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if self.probes.is_empty() {
+            return vec![];
+        }
+        let pre_hash = ctx.pre_state.get_hash();
+        let post_hash = ctx.post_state.get_hash();
+        // the first snapshot is taken at the first state of the sequence
+        let mut epoch = match self.epochs.borrow().get(&pre_hash) {
+            Some(epoch) => epoch.clone(),
+            None => Epoch {
+                snapshot: Self::values(&ctx.call_pre_batch(&self.batch_call_txs)),
+                txs: 0,
+            },
+        };
+        epoch.txs += 1;
+
+        let mut bugs = vec![];
+        if epoch.txs >= self.epoch {
+            let snapshot = Self::values(&ctx.call_post_batch(&self.batch_call_txs));
+            for (idx, description) in self.violations(&epoch.snapshot, &snapshot) {
+                unsafe {
+                    ORACLE_OUTPUT += format!("[epoch] {} over the last {} transactions\n", description, epoch.txs).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                self.probes[idx].name.hash(&mut hasher);
+                bugs.push((hasher.finish() << 8) + EPOCH_BUG_IDX);
+            }
+            epoch = Epoch { snapshot, txs: 0 };
+        }
+
+        let mut epochs = self.epochs.borrow_mut();
+        if epochs.len() >= MAX_TRACKED_STATES {
+            epochs.clear();
+        }
+        epochs.insert(post_hash, epoch);
+        bugs
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations() {
+        let config: EpochConfig = serde_json::from_str(
+            r#"{
+                "probes": [
+                    {"name": "rate", "contract": "0x0000000000000000000000000000000000000001", "function": "exchangeRate()", "rule": "non_decreasing", "tolerance": 1},
+                    {"name": "cap", "contract": "0x0000000000000000000000000000000000000002", "function": "debtCap()", "rule": "non_increasing"},
+                    {"name": "debt", "contract": "0x0000000000000000000000000000000000000002", "function": "totalDebt()", "rule": "at_most", "bound": "cap"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.epoch, 8);
+        let oracle = EpochOracle::new(&config, &|addr| EVMAddress::from_str(addr).unwrap());
+        assert_eq!(oracle.probes[2].bound, Some(Bound::Probe(1)));
+
+        let values = |v: [u64; 3]| v.map(EVMU256::from).to_vec();
+        // rounding within the tolerance
+        assert!(oracle.violations(&values([100, 50, 10]), &values([99, 50, 50])).is_empty());
+        let violations = oracle.violations(&values([100, 50, 10]), &values([98, 50, 51]));
+        assert_eq!(violations.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(), vec![0, 2]);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
 use crate::evm::producers::pair::PairProducer;
 use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256, EVMU512};
-use crate::evm::uniswap::{liquidate_all_token, TokenContext};
+use crate::evm::uniswap::{acquire_all_token, liquidate_all_token, TokenContext};
 use crate::evm::vm::EVMState;
 use crate::oracle::Oracle;
 use crate::state::HasExecutionResult;
@@ -14,6 +14,7 @@ use std::ops::Deref;
 use std::rc::Rc;
 use crate::evm::oracles::ERC20_BUG_IDX;
 use crate::evm::producers::erc20::ERC20Producer;
+use crate::evm::receipts::Receipt;
 use crate::fuzzer::ORACLE_OUTPUT;
 
 pub struct IERC20OracleFlashloan {
@@ -26,6 +27,9 @@ pub struct IERC20OracleFlashloan {
     pub pair_producer: Rc<RefCell<PairProducer>>,
     #[cfg(feature = "flashloan_v2")]
     pub erc20_producer: Rc<RefCell<ERC20Producer>>,
+    /// LP tokens, V3 position managers and lending receipts, valued through their underlying tokens
+    #[cfg(feature = "flashloan_v2")]
+    pub receipts: HashMap<EVMAddress, Receipt>,
 }
 
 impl IERC20OracleFlashloan {
@@ -43,7 +47,8 @@ impl IERC20OracleFlashloan {
             known_tokens: HashMap::new(),
             known_pair_reserve_slot: HashMap::new(),
             pair_producer,
-            erc20_producer
+            erc20_producer,
+            receipts: HashMap::new(),
         }
     }
 
@@ -52,6 +57,11 @@ impl IERC20OracleFlashloan {
         self.known_tokens.insert(token, token_ctx);
     }
 
+    #[cfg(feature = "flashloan_v2")]
+    pub fn register_receipt(&mut self, token: EVMAddress, receipt: Receipt) {
+        self.receipts.insert(token, receipt);
+    }
+
     #[cfg(feature = "flashloan_v2")]
     pub fn register_pair_reserve_slot(&mut self, pair: EVMAddress, slot: EVMU256) {
         self.known_pair_reserve_slot.insert(pair, slot);
@@ -110,7 +120,22 @@ impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>,
         let mut liquidations_owed = Vec::new();
         let mut liquidations_earned = Vec::new();
 
-        for ((_, token), (prev_balance, new_balance)) in self.erc20_producer.deref().borrow().balances.iter() {
+        // balance changes of receipt tokens are changes of their underlying tokens
+        let balances = self.erc20_producer.deref().borrow().balances.clone();
+        let mut changes = vec![];
+        for ((caller, token), (prev_balance, new_balance)) in balances {
+            match self.receipts.get(&token) {
+                Some(receipt) => changes.extend(
+                    receipt
+                        .underlying_changes(ctx, token, caller, prev_balance, new_balance)
+                        .into_iter()
+                        .filter(|(underlying, _, _)| self.known_tokens.contains_key(underlying)),
+                ),
+                None => changes.push((token, prev_balance, new_balance)),
+            }
+        }
+
+        for (token, prev_balance, new_balance) in changes.iter() {
             let token_info = self.known_tokens.get(token).expect("Token not found");
             // ctx.fuzz_state.get_execution_result_mut().new_state.state.flashloan_data.extra_info += format!("Balance: {} -> {} for {:?} @ {:?}\n", prev_balance, new_balance, caller, token).as_str();
 
@@ -142,8 +167,10 @@ impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>,
         }
         let exec_res = ctx.fuzz_state.get_execution_result_mut();
 
+        // borrowed tokens are bought back through the DEX routes (with price impact), so the
+        // profit accounts for what repaying them actually costs
         let (liquidation_owed, _) =
-            liquidate_all_token(liquidations_owed.clone(), prev_reserves.clone());
+            acquire_all_token(liquidations_owed.clone(), prev_reserves.clone());
 
         unliquidated_tokens.iter().for_each(|(token, amount)| {
             let token_info = self.known_tokens.get(token).expect("Token not found");
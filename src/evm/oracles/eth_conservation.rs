@@ -0,0 +1,181 @@
+use crate::evm::contract_utils::set_hash;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::native_flow::native_balance;
+use crate::evm::oracles::ETH_CONSERVATION_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use itertools::Itertools;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// A view function reporting ETH a contract accounts for: a total (e.g., `totalDeposits()`), or
+/// a per-user credit (e.g., `credits(address)`), summed over the callers
+#[derive(Clone, Debug)]
+pub struct Accounting {
+    pub contract: EVMAddress,
+    pub function: String,
+    pub per_user: bool,
+    pub selector: [u8; 4],
+}
+
+impl Accounting {
+    /// Parse `<contract>:<function>`, e.g., `Vault:totalDeposits()`, the contract being an
+    /// address or a contract name
+    pub fn parse(spec: &str, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        let (contract, function) = spec
+            .split_once(':')
+            .unwrap_or_else(|| panic!("ETH accounting {} should be <contract>:<function>", spec));
+        let per_user = match function.split_once('(').map(|(_, args)| args) {
+            Some(")") => false,
+            Some("address)") => true,
+            _ => panic!("ETH accounting {} should take no argument or an address", function),
+        };
+        let mut selector = [0u8; 4];
+        set_hash(function, &mut selector);
+        Self {
+            contract: resolve(&contract.to_string()),
+            function: function.to_string(),
+            per_user,
+            selector,
+        }
+    }
+}
+
+/// Detects contracts whose ETH accounting (the sum of their accounting functions) exceeds the ETH
+/// they actually hold, tracked along the sequence from the ETH sent with transactions and calls,
+/// transferred by selfdestruct and force-fed to them. Only transitions from a state where the
+/// accounting is covered are reported. ETH held in excess of the accounting (e.g., force-fed) is
+/// expected and not reported by itself.
+pub struct EthConservationOracle {
+    pub accounting: Vec<Accounting>,
+}
+
+impl EthConservationOracle {
+    pub fn new(specs: &Vec<String>, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        Self {
+            accounting: specs.iter().map(|spec| Accounting::parse(spec, resolve)).collect(),
+        }
+    }
+
+    /// Calls of the accounting functions, per-user ones for each caller
+    fn batch_call_txs(&self, callers: &Vec<EVMAddress>) -> Vec<(EVMAddress, Bytes)> {
+        let mut txs = vec![];
+        for accounting in &self.accounting {
+            if accounting.per_user {
+                for caller in callers {
+                    let mut data = accounting.selector.to_vec();
+                    data.extend([0; 12]);
+                    data.extend(caller.0);
+                    txs.push((accounting.contract, Bytes::from(data)));
+                }
+            } else {
+                txs.push((accounting.contract, Bytes::from(accounting.selector.to_vec())));
+            }
+        }
+        txs
+    }
+
+    /// ETH accounted for by each contract from the outputs of the batch calls
+    fn accounted(&self, outputs: &Vec<Vec<u8>>, callers: usize) -> HashMap<EVMAddress, EVMU256> {
+        let mut accounted: HashMap<EVMAddress, EVMU256> = HashMap::new();
+        let mut outputs = outputs.iter();
+        for accounting in &self.accounting {
+            let calls = if accounting.per_user { callers } else { 1 };
+            let total = accounted.entry(accounting.contract).or_default();
+            for out in outputs.by_ref().take(calls) {
+                if out.len() >= 32 {
+                    *total = total.saturating_add(EVMU256::from_be_slice(&out[..32]));
+                }
+            }
+        }
+        accounted
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for EthConservationOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let callers = ctx.fuzz_state.callers_pool.clone();
+        let txs = self.batch_call_txs(&callers);
+        let deficit = |accounted: &HashMap<EVMAddress, EVMU256>, state: &EVMState| {
+            accounted
+                .iter()
+                .filter_map(|(contract, accounted)| {
+                    let held = native_balance(state, contract)?;
+                    (*accounted > held).then_some((*contract, *accounted, held))
+                })
+                .collect::<Vec<_>>()
+        };
+        let after = deficit(&self.accounted(&ctx.call_post_batch(&txs), callers.len()), &ctx.post_state);
+        if after.is_empty() {
+            return vec![];
+        }
+        let before = deficit(&self.accounted(&ctx.call_pre_batch(&txs), callers.len()), ctx.pre_state);
+        after
+            .into_iter()
+            .filter(|(contract, _, _)| !before.iter().any(|(c, _, _)| c == contract))
+            .sorted_by_key(|(contract, _, _)| *contract)
+            .map(|(contract, accounted, held)| {
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[eth_conservation] {:?} accounts for {} wei but holds {} wei\n",
+                        contract, accounted, held
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                contract.hash(&mut hasher);
+                (hasher.finish() << 8) + ETH_CONSERVATION_BUG_IDX
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounting() {
+        let resolve = |s: &String| s.parse::<EVMAddress>().unwrap();
+        let vault = "0x0000000000000000000000000000000000000001";
+        let oracle = EthConservationOracle::new(
+            &vec![format!("{}:totalDeposits()", vault), format!("{}:credits(address)", vault)],
+            &resolve,
+        );
+        assert!(!oracle.accounting[0].per_user && oracle.accounting[1].per_user);
+
+        let callers = vec![EVMAddress::from_slice(&[2; 20]), EVMAddress::from_slice(&[3; 20])];
+        let txs = oracle.batch_call_txs(&callers);
+        assert_eq!(txs.len(), 3);
+        assert_eq!(&txs[2].1[16..], &[3; 20]);
+
+        let word = |v: u64| EVMU256::from(v).to_be_bytes::<32>().to_vec();
+        // total of 100, credits of 60 + 50
+        let accounted = oracle.accounted(&vec![word(100), word(60), word(50)], callers.len());
+        assert_eq!(accounted[&resolve(&vault.to_string())], EVMU256::from(210));
+    }
+}
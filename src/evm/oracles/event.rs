@@ -0,0 +1,475 @@
+use crate::evm::contract_utils::set_hash;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::event_collector::EmittedEvent;
+use crate::evm::oracles::EVENT_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::{keccak256, Bytecode};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// An event invariants refer to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventConfig {
+    pub name: String,
+    /// Signature with the indexed parameters marked and optionally named, e.g.,
+    /// `Deposit(address indexed user,uint256 amount)`
+    pub signature: String,
+    /// Address or contract name of the emitter, any contract if not given
+    #[serde(default)]
+    pub contract: Option<String>,
+}
+
+/// How the sum of the event fields compares to the balance delta
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SumRule {
+    #[default]
+    Equals,
+    AtMost,
+    AtLeast,
+}
+
+/// Balance of a holder in a token
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BalanceConfig {
+    /// Address or contract name of the token
+    pub token: String,
+    /// Address or contract name of the holder
+    pub holder: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InvariantConfig {
+    /// The signed sum of event fields (`+Event.field` / `-Event.field` terms) over each
+    /// transaction compares to the change of a balance by the rule
+    Sum {
+        name: String,
+        terms: Vec<String>,
+        #[serde(default)]
+        rule: SumRule,
+        balance_delta: BalanceConfig,
+        /// Difference allowed, in wei
+        #[serde(default)]
+        tolerance: u64,
+    },
+    /// Each `event` follows an `after` event of the sequence not yet followed, with the
+    /// `match`ing fields (pairs of fields of `event` and `after`) equal
+    Follows {
+        name: String,
+        event: String,
+        after: String,
+        #[serde(default, rename = "match")]
+        matching: Vec<(String, String)>,
+    },
+}
+
+/// Invariants over the events emitted in a sequence, loaded from a JSON file:
+/// ```json
+/// {
+///   "events": [
+///     {"name": "Deposit", "signature": "Deposit(address indexed user,uint256 amount)", "contract": "Vault"},
+///     {"name": "Withdraw", "signature": "Withdraw(address indexed user,uint256 amount)", "contract": "Vault"},
+///     {"name": "Approval", "signature": "Approval(address indexed owner,address indexed spender,uint256 value)"},
+///     {"name": "Transfer", "signature": "Transfer(address indexed from,address indexed to,uint256 value)"}
+///   ],
+///   "invariants": [
+///     {"type": "sum", "name": "deposits", "terms": ["+Deposit.amount", "-Withdraw.amount"],
+///      "balance_delta": {"token": "DAI", "holder": "Vault"}},
+///     {"type": "follows", "name": "approved", "event": "Transfer", "after": "Approval", "match": [["from", "owner"]]}
+///   ]
+/// }
+/// ```
+/// Fields are named or numbered (from 0) by their position in the signature, dynamic types
+/// (strings, bytes, arrays) are not supported.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventInvariantConfig {
+    pub events: Vec<EventConfig>,
+    pub invariants: Vec<InvariantConfig>,
+}
+
+impl EventInvariantConfig {
+    pub fn from_file(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).expect("failed to read event invariants");
+        serde_json::from_str(&content).expect("failed to parse event invariants")
+    }
+}
+
+/// An event, resolved
+#[derive(Clone, Debug)]
+pub struct EventSpec {
+    pub name: String,
+    pub topic: EVMU256,
+    pub contract: Option<EVMAddress>,
+    /// (name, indexed) of each parameter
+    pub params: Vec<(String, bool)>,
+}
+
+impl EventSpec {
+    pub fn new(config: &EventConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        let (name, params) = config
+            .signature
+            .trim_end_matches(')')
+            .split_once('(')
+            .unwrap_or_else(|| panic!("invalid event signature {}", config.signature));
+        let mut types = vec![];
+        let mut fields = vec![];
+        for param in params.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let mut words = param.split_whitespace();
+            types.push(words.next().unwrap().to_string());
+            let rest = words.collect::<Vec<_>>();
+            let indexed = rest.contains(&"indexed");
+            let field = rest.into_iter().find(|w| *w != "indexed").unwrap_or_default();
+            fields.push((field.to_string(), indexed));
+        }
+        let canonical = format!("{}({})", name.trim(), types.join(","));
+        Self {
+            name: config.name.clone(),
+            topic: EVMU256::from_be_bytes(keccak256(canonical.as_bytes()).0),
+            contract: config.contract.as_ref().map(resolve),
+            params: fields,
+        }
+    }
+
+    /// Position of a field, by name or number
+    pub fn field(&self, field: &str) -> usize {
+        self.params
+            .iter()
+            .position(|(name, _)| name == field)
+            .or_else(|| field.parse().ok().filter(|idx| *idx < self.params.len()))
+            .unwrap_or_else(|| panic!("event {} has no field {}", self.name, field))
+    }
+
+    pub fn matches(&self, event: &EmittedEvent) -> bool {
+        event.topics.first() == Some(&self.topic) && self.contract.map_or(true, |c| c == event.address)
+    }
+
+    /// Value of the field at `idx` of an event
+    pub fn value(&self, event: &EmittedEvent, idx: usize) -> EVMU256 {
+        let indexed = self.params[idx].1;
+        let position = self.params[..idx].iter().filter(|(_, i)| *i == indexed).count();
+        if indexed {
+            event.topics.get(1 + position).cloned().unwrap_or(EVMU256::ZERO)
+        } else {
+            event
+                .data
+                .get(position * 32..position * 32 + 32)
+                .map_or(EVMU256::ZERO, EVMU256::from_be_slice)
+        }
+    }
+}
+
+/// An invariant, resolved (events are indices in [`EventOracle::events`])
+#[derive(Clone, Debug)]
+pub enum Invariant {
+    Sum {
+        name: String,
+        /// (event, field, negated)
+        terms: Vec<(usize, usize, bool)>,
+        rule: SumRule,
+        /// Index of the balanceOf call in the batch
+        balance: usize,
+        tolerance: EVMU256,
+    },
+    Follows {
+        name: String,
+        event: usize,
+        after: usize,
+        /// (field of event, field of after)
+        matching: Vec<(usize, usize)>,
+    },
+}
+
+impl Invariant {
+    fn name(&self) -> &String {
+        match self {
+            Invariant::Sum { name, .. } | Invariant::Follows { name, .. } => name,
+        }
+    }
+}
+
+/// Checks invariants over the events emitted along a sequence (recorded by
+/// [`crate::evm::middlewares::event_collector::EventCollector`]). Sums of event fields are
+/// compared to balance changes at each transaction (e.g., deposits minus withdrawals equal the
+/// change of the vault balance), and ordering invariants are checked over the whole sequence
+/// (e.g., each Transfer follows an Approval of its owner).
+pub struct EventOracle {
+    pub events: Vec<EventSpec>,
+    pub invariants: Vec<Invariant>,
+    /// balanceOf calls of the sum invariants
+    pub batch_call_txs: Vec<(EVMAddress, Bytes)>,
+}
+
+impl EventOracle {
+    pub fn new(config: &EventInvariantConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        let events = config.events.iter().map(|e| EventSpec::new(e, resolve)).collect::<Vec<_>>();
+        let event = |name: &String| {
+            events
+                .iter()
+                .position(|e| e.name == *name)
+                .unwrap_or_else(|| panic!("event {} is not declared", name))
+        };
+        let mut batch_call_txs = vec![];
+        let invariants = config
+            .invariants
+            .iter()
+            .map(|invariant| match invariant {
+                InvariantConfig::Sum {
+                    name,
+                    terms,
+                    rule,
+                    balance_delta,
+                    tolerance,
+                } => {
+                    let terms = terms
+                        .iter()
+                        .map(|term| {
+                            let negated = term.starts_with('-');
+                            let (e, field) = term
+                                .trim_start_matches(['+', '-'])
+                                .split_once('.')
+                                .unwrap_or_else(|| panic!("invalid term {}, expected +Event.field", term));
+                            let e = event(&e.to_string());
+                            (e, events[e].field(field), negated)
+                        })
+                        .collect();
+                    let mut data = vec![0u8; 4];
+                    set_hash("balanceOf(address)", &mut data);
+                    data.extend([0; 12]);
+                    data.extend(resolve(&balance_delta.holder).0);
+                    batch_call_txs.push((resolve(&balance_delta.token), Bytes::from(data)));
+                    Invariant::Sum {
+                        name: name.clone(),
+                        terms,
+                        rule: *rule,
+                        balance: batch_call_txs.len() - 1,
+                        tolerance: EVMU256::from(*tolerance),
+                    }
+                }
+                InvariantConfig::Follows {
+                    name,
+                    event: e,
+                    after,
+                    matching,
+                } => {
+                    let (e, after) = (event(e), event(after));
+                    Invariant::Follows {
+                        name: name.clone(),
+                        event: e,
+                        after,
+                        matching: matching
+                            .iter()
+                            .map(|(a, b)| (events[e].field(a), events[after].field(b)))
+                            .collect(),
+                    }
+                }
+            })
+            .collect();
+        Self {
+            events,
+            invariants,
+            batch_call_txs,
+        }
+    }
+
+    /// Topics of the events to record
+    pub fn topics(&self) -> HashSet<EVMU256> {
+        self.events.iter().map(|e| e.topic).collect()
+    }
+
+    /// (positive, negative) parts of the sum of `terms` over `events`
+    fn sum(&self, terms: &[(usize, usize, bool)], events: &[EmittedEvent]) -> (EVMU256, EVMU256) {
+        let (mut plus, mut minus) = (EVMU256::ZERO, EVMU256::ZERO);
+        for emitted in events {
+            for (event, field, negated) in terms {
+                let spec = &self.events[*event];
+                if !spec.matches(emitted) {
+                    continue;
+                }
+                let value = spec.value(emitted, *field);
+                if *negated {
+                    minus = minus.saturating_add(value);
+                } else {
+                    plus = plus.saturating_add(value);
+                }
+            }
+        }
+        (plus, minus)
+    }
+
+    /// Index of the first `event` of `events` not following an `after` event
+    fn unfollowed(&self, event: usize, after: usize, matching: &[(usize, usize)], events: &[EmittedEvent]) -> Option<usize> {
+        let (event, after) = (&self.events[event], &self.events[after]);
+        let mut pending: Vec<Vec<EVMU256>> = vec![];
+        for (idx, emitted) in events.iter().enumerate() {
+            if event.matches(emitted) {
+                let key = matching.iter().map(|(field, _)| event.value(emitted, *field)).collect::<Vec<_>>();
+                match pending.iter().position(|p| *p == key) {
+                    Some(followed) => {
+                        pending.remove(followed);
+                    }
+                    None => return Some(idx),
+                }
+            } else if after.matches(emitted) {
+                pending.push(matching.iter().map(|(_, field)| after.value(emitted, *field)).collect());
+            }
+        }
+        None
+    }
+
+    /// Description of the violation of a sum invariant: (events plus, events minus) against
+    /// (balance before, balance after)
+    fn check_sum(rule: SumRule, tolerance: EVMU256, events: (EVMU256, EVMU256), balances: (EVMU256, EVMU256)) -> Option<String> {
+        // sum = plus - minus and delta = after - before, compared without going negative
+        let lhs = events.0.saturating_add(balances.0);
+        let rhs = events.1.saturating_add(balances.1);
+        let violated = match rule {
+            SumRule::Equals => lhs.saturating_add(tolerance) < rhs || rhs.saturating_add(tolerance) < lhs,
+            SumRule::AtMost => lhs > rhs.saturating_add(tolerance),
+            SumRule::AtLeast => lhs.saturating_add(tolerance) < rhs,
+        };
+        if !violated {
+            return None;
+        }
+        let relation = match rule {
+            SumRule::Equals => "should equal",
+            SumRule::AtMost => "should be at most",
+            SumRule::AtLeast => "should be at least",
+        };
+        Some(format!(
+            "events sum to +{} -{}, which {} the balance change from {} to {}",
+            events.0, events.1, relation, balances.0, balances.1
+        ))
+    }
+}
+
+fn balance(outputs: &Vec<Vec<u8>>, idx: usize) -> EVMU256 {
+    match outputs.get(idx) {
+        Some(out) if out.len() >= 32 => EVMU256::from_be_slice(&out[..32]),
+        _ => EVMU256::ZERO,
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for EventOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let previous = ctx.pre_state.events.len();
+        let events = ctx.post_state.events.clone();
+        let (before, after) = if self.batch_call_txs.is_empty() {
+            (vec![], vec![])
+        } else {
+            (ctx.call_pre_batch(&self.batch_call_txs), ctx.call_post_batch(&self.batch_call_txs))
+        };
+
+        let mut bugs = vec![];
+        for invariant in &self.invariants {
+            let violation = match invariant {
+                Invariant::Sum {
+                    terms,
+                    rule,
+                    balance: idx,
+                    tolerance,
+                    ..
+                } => {
+                    // sums are additive, checking each transaction checks the sequence
+                    let sums = self.sum(terms, &events[previous.min(events.len())..]);
+                    Self::check_sum(*rule, *tolerance, sums, (balance(&before, *idx), balance(&after, *idx)))
+                }
+                Invariant::Follows {
+                    event,
+                    after: followed,
+                    matching,
+                    ..
+                } => match self.unfollowed(*event, *followed, matching, &events) {
+                    // violations of the previous transactions are already reported
+                    Some(idx) if idx >= previous => Some(format!(
+                        "{} (event {} of the sequence) does not follow a {}",
+                        self.events[*event].name, idx, self.events[*followed].name
+                    )),
+                    _ => None,
+                },
+            };
+            if let Some(violation) = violation {
+                unsafe {
+                    ORACLE_OUTPUT += format!("[event] invariant {} violated: {}\n", invariant.name(), violation).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                invariant.name().hash(&mut hasher);
+                bugs.push((hasher.finish() << 8) + EVENT_BUG_IDX);
+            }
+        }
+        bugs
+    }
+}
+
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_invariants() {
+        let config: EventInvariantConfig = serde_json::from_str(
+            r#"{
+                "events": [
+                    {"name": "Approval", "signature": "Approval(address indexed owner,address indexed spender,uint256 value)"},
+                    {"name": "Transfer", "signature": "Transfer(address indexed from,address indexed to,uint256 value)"}
+                ],
+                "invariants": [
+                    {"type": "sum", "name": "minted", "terms": ["+Transfer.value", "-Approval.2"],
+                     "balance_delta": {"token": "0x0000000000000000000000000000000000000001", "holder": "0x0000000000000000000000000000000000000002"}},
+                    {"type": "follows", "name": "approved", "event": "Transfer", "after": "Approval", "match": [["from", "owner"]]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let oracle = EventOracle::new(&config, &|addr| EVMAddress::from_str(addr).unwrap());
+        // Transfer(address,address,uint256)
+        assert_eq!(
+            format!("{:x}", oracle.events[1].topic),
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+
+        let word = |v: u64| EVMU256::from(v);
+        let event = |spec: usize, from: u64, value: u64| EmittedEvent {
+            address: EVMAddress::zero(),
+            topics: vec![oracle.events[spec].topic, word(from), word(9)],
+            data: word(value).to_be_bytes::<32>().to_vec(),
+        };
+        let events = vec![event(0, 1, 5), event(1, 1, 10), event(1, 2, 3)];
+        assert_eq!(oracle.sum(&[(1, 2, false), (0, 2, true)], &events), (word(13), word(5)));
+        // the transfer from 2 follows no approval of 2
+        assert_eq!(oracle.unfollowed(1, 0, &[(0, 0)], &events), Some(2));
+        assert_eq!(oracle.unfollowed(1, 0, &[(0, 0)], &events[..2]), None);
+
+        // +13 -5 against a balance going from 100 to 108
+        assert!(EventOracle::check_sum(SumRule::Equals, word(0), (word(13), word(5)), (word(100), word(108))).is_none());
+        assert!(EventOracle::check_sum(SumRule::Equals, word(0), (word(13), word(5)), (word(100), word(107))).is_some());
+        assert!(EventOracle::check_sum(SumRule::AtLeast, word(0), (word(13), word(5)), (word(100), word(107))).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
+use crate::evm::middlewares::block_context::BlockContextPayout;
+use crate::evm::oracles::FEE_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::generic_vm::vm_executor::GenericVM;
+use crate::input::VMInputT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::{Bytecode, Env};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Intrinsic gas of a transaction, not part of the estimated gas
+const INTRINSIC_GAS: u64 = 21000;
+
+/// Price paid per gas: the legacy gas price, or min(max fee, basefee + priority fee)
+pub fn effective_gas_price(env: &Env) -> EVMU256 {
+    match env.tx.gas_priority_fee {
+        Some(priority_fee) => env.tx.gas_price.min(env.block.basefee.saturating_add(priority_fee)),
+        None => env.tx.gas_price,
+    }
+}
+
+/// Detects payouts that the sender can game by choosing the fee (refunds, keeper rewards
+/// computed from tx.gasprice). Transactions paying the caller after reading the gas price
+/// (recorded by [`crate::evm::middlewares::block_context::BlockContextTracker`]) are
+/// re-executed at the other end of the gas price bounds, and reported when the payout grows with
+/// the gas price: tokens paid, or ETH paid beyond the value sent and the fee of the transaction
+/// (so that raising the gas price is profitable).
+pub struct FeeOracle {
+    /// Bounds (in wei) of the gas price
+    pub bounds: (u128, u128),
+}
+
+impl FeeOracle {
+    pub fn new(bounds: (u128, u128)) -> Self {
+        Self { bounds }
+    }
+}
+
+/// Gain of the caller from a payout at a gas price: tokens paid, or ETH paid beyond the value
+/// sent and the fee (None if not profitable)
+fn gain(payout: &BlockContextPayout, sent: EVMU256, price: EVMU256, gas: u64) -> Option<EVMU256> {
+    if payout.token.is_some() {
+        return Some(payout.amount);
+    }
+    let cost = sent.saturating_add(price.saturating_mul(EVMU256::from(gas + INTRINSIC_GAS)));
+    payout.amount.checked_sub(cost).filter(|gain| *gain > EVMU256::ZERO)
+}
+
+/// Gas price at the other end of the bounds
+fn other_price(price: EVMU256, (min, max): (u128, u128)) -> EVMU256 {
+    if price < EVMU256::from(max) {
+        EVMU256::from(max)
+    } else {
+        EVMU256::from(min)
+    }
+}
+
+/// Whether the gain at the higher gas price exceeds the one at the lower gas price
+fn gains_with_price(low: Option<EVMU256>, high: Option<EVMU256>) -> bool {
+    match (low, high) {
+        (_, None) => false,
+        (Some(low), Some(high)) => high > low,
+        (None, Some(_)) => true,
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for FeeOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let previous = ctx.pre_state.block_context_payouts.len();
+        let payouts = ctx
+            .post_state
+            .block_context_payouts
+            .iter()
+            .skip(previous)
+            .filter(|p| p.reads.iter().any(|r| r == "gasprice"))
+            .cloned()
+            .collect::<Vec<_>>();
+        if payouts.is_empty() {
+            return vec![];
+        }
+
+        // re-execute at the other end of the bounds
+        let price = effective_gas_price(ctx.input.get_vm_env());
+        let other_price = other_price(price, self.bounds);
+        let mut input = ctx.input.clone();
+        input.get_vm_env_mut().tx.gas_price = other_price;
+        input.get_vm_env_mut().tx.gas_priority_fee = None;
+        let result = ctx.executor.deref().borrow_mut().execute(&input, ctx.fuzz_state);
+        if result.reverted {
+            return vec![];
+        }
+        let other = &result.new_state.state;
+        let sent = ctx.input.get_txn_value().unwrap_or(EVMU256::ZERO);
+
+        let mut bugs = vec![];
+        for payout in payouts {
+            let other_payout = match other
+                .block_context_payouts
+                .iter()
+                .skip(previous)
+                .find(|p| p.contract == payout.contract && p.pc == payout.pc)
+            {
+                Some(p) => p,
+                None => continue,
+            };
+            let current = (price, gain(&payout, sent, price, ctx.post_state.gas_used), payout.amount);
+            let at_other = (other_price, gain(other_payout, sent, other_price, other.gas_used), other_payout.amount);
+            let (low, high) = if price < other_price { (current, at_other) } else { (at_other, current) };
+            if !gains_with_price(low.1, high.1) {
+                continue;
+            }
+            let asset = match payout.token {
+                Some(token) => format!("token {:?}", token),
+                None => "wei".to_string(),
+            };
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[fee] {:?} (pc {}) pays {} {} to {:?} at gas price {} and {} at gas price {}, the sender gains by raising the fee\n",
+                    payout.contract, payout.pc, low.2, asset, payout.recipient, low.0, high.2, high.0
+                )
+                .as_str();
+            }
+            let mut hasher = DefaultHasher::new();
+            payout.contract.hash(&mut hasher);
+            payout.pc.hash(&mut hasher);
+            bugs.push((hasher.finish() << 8) + FEE_BUG_IDX);
+        }
+        bugs
+    }
+}
+
+mod tests {
+    use super::*;
+
+    fn payout(token: Option<EVMAddress>, amount: u64) -> BlockContextPayout {
+        BlockContextPayout {
+            contract: EVMAddress::from_slice(&[1; 20]),
+            pc: 0,
+            reads: vec!["gasprice".to_string()],
+            recipient: EVMAddress::from_slice(&[2; 20]),
+            token,
+            amount: EVMU256::from(amount),
+        }
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        let mut env = Env::default();
+        env.tx.gas_price = EVMU256::from(100);
+        env.block.basefee = EVMU256::from(30);
+        assert_eq!(effective_gas_price(&env), EVMU256::from(100));
+        env.tx.gas_priority_fee = Some(EVMU256::from(20));
+        assert_eq!(effective_gas_price(&env), EVMU256::from(50));
+        // capped by the max fee
+        env.tx.gas_priority_fee = Some(EVMU256::from(90));
+        assert_eq!(effective_gas_price(&env), EVMU256::from(100));
+    }
+
+    #[test]
+    fn test_gain() {
+        let token = Some(EVMAddress::from_slice(&[3; 20]));
+        assert_eq!(gain(&payout(token, 5), EVMU256::ZERO, EVMU256::from(1), 0), Some(EVMU256::from(5)));
+        // ETH paid beyond the value sent and the fee
+        assert_eq!(
+            gain(&payout(None, 50_000), EVMU256::from(1000), EVMU256::from(1), 4000),
+            Some(EVMU256::from(24_000))
+        );
+        assert_eq!(gain(&payout(None, 25_000), EVMU256::ZERO, EVMU256::from(1), 4000), None);
+        assert_eq!(gain(&payout(None, 100), EVMU256::ZERO, EVMU256::from(1), 0), None);
+    }
+
+    #[test]
+    fn test_gains_with_price() {
+        let (low, high) = (Some(EVMU256::from(1)), Some(EVMU256::from(2)));
+        assert!(gains_with_price(low, high));
+        assert!(gains_with_price(None, high));
+        assert!(!gains_with_price(high, low));
+        assert!(!gains_with_price(low, low));
+        assert!(!gains_with_price(high, None));
+        assert_eq!(other_price(EVMU256::from(5), (1, 10)), EVMU256::from(10));
+        assert_eq!(other_price(EVMU256::from(10), (1, 10)), EVMU256::from(1));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::GAS_BOMB_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+/// Number of consecutive state sizes with increasing gas before reporting unbounded growth
+const GROWTH_SAMPLES: usize = 4;
+/// Minimum gas per storage slot for the growth to be considered unbounded (about an SLOAD per slot)
+const MIN_GAS_PER_SLOT: u64 = 2000;
+
+/// Detects denial of service through unbounded gas usage.
+///
+/// Reports transactions that exceed the gas ceiling (halted by the executor), and functions whose gas
+/// keeps growing with the number of storage slots of the contract (e.g., looping over an array that
+/// anyone can push to), which eventually exceed the block gas limit. The sequence of transactions
+/// leading to the report is the one growing the state.
+pub struct GasBombOracle {
+    pub gas_ceiling: u64,
+    /// (contract, function) => storage slots of the contract => max gas used
+    pub samples: RefCell<HashMap<(EVMAddress, [u8; 4]), BTreeMap<usize, u64>>>,
+}
+
+impl GasBombOracle {
+    pub fn new(gas_ceiling: u64) -> Self {
+        Self {
+            gas_ceiling,
+            samples: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn bug_idx(contract: EVMAddress, function: [u8; 4], growth: bool) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        contract.hash(&mut hasher);
+        function.hash(&mut hasher);
+        growth.hash(&mut hasher);
+        (hasher.finish() << 8) + GAS_BOMB_BUG_IDX
+    }
+
+    /// Gas per slot if gas strictly increases over the largest sampled state sizes, the
+    /// current state being the largest one
+    fn growth_rate(samples: &BTreeMap<usize, u64>, size: usize) -> Option<u64> {
+        if samples.len() < GROWTH_SAMPLES || samples.keys().last() != Some(&size) {
+            return None;
+        }
+        let points = samples.iter().rev().take(GROWTH_SAMPLES).collect::<Vec<_>>();
+        if !points.windows(2).all(|w| w[0].1 > w[1].1) {
+            return None;
+        }
+        let (last_size, last_gas) = points.first().unwrap();
+        let (first_size, first_gas) = points.last().unwrap();
+        let rate = (*last_gas - *first_gas) / (*last_size - *first_size) as u64;
+        if rate >= MIN_GAS_PER_SLOT {
+            Some(rate)
+        } else {
+            None
+        }
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for GasBombOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let contract = ctx.input.contract;
+        let function = match &ctx.input.data {
+            Some(abi) => abi.function,
+            None => return vec![],
+        };
+        let size = ctx.pre_state.get(&contract).map(|slots| slots.len()).unwrap_or(0);
+
+        if ctx.post_state.gas_exceeded {
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[gas_bomb] {:?}::0x{} exceeds the gas ceiling of {} with {} storage slots\n",
+                    contract,
+                    hex::encode(function),
+                    self.gas_ceiling,
+                    size
+                ).as_str();
+            }
+            return vec![Self::bug_idx(contract, function, false)];
+        }
+
+        let mut samples = self.samples.borrow_mut();
+        let gas = samples.entry((contract, function)).or_default().entry(size).or_insert(0);
+        if ctx.post_state.gas_used <= *gas {
+            return vec![];
+        }
+        *gas = ctx.post_state.gas_used;
+        match Self::growth_rate(&samples[&(contract, function)], size) {
+            Some(rate) => {
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[gas_bomb] gas of {:?}::0x{} grows unboundedly with the state, {} gas per storage slot \
+                        ({} gas with {} slots), exceeds the gas ceiling of {} after about {} more slots\n",
+                        contract,
+                        hex::encode(function),
+                        rate,
+                        ctx.post_state.gas_used,
+                        size,
+                        self.gas_ceiling,
+                        self.gas_ceiling.saturating_sub(ctx.post_state.gas_used) / rate
+                    ).as_str();
+                }
+                vec![Self::bug_idx(contract, function, true)]
+            }
+            None => vec![],
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_growth_rate() {
+        let linear = BTreeMap::from([(1, 30000), (2, 32500), (3, 35000), (4, 37500)]);
+        assert_eq!(GasBombOracle::growth_rate(&linear, 4), Some(2500));
+        // only the largest state triggers the report
+        assert_eq!(GasBombOracle::growth_rate(&linear, 3), None);
+
+        let constant = BTreeMap::from([(1, 30000), (2, 30000), (3, 30100), (4, 30200)]);
+        assert_eq!(GasBombOracle::growth_rate(&constant, 4), None);
+    }
+}
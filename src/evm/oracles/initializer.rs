@@ -0,0 +1,254 @@
+use crate::evm::contract_utils::ABIConfig;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::upgrade::EIP1967_IMPLEMENTATION_SLOT;
+use crate::evm::oracles::INITIALIZER_BUG_IDX;
+use crate::evm::types::{convert_h160_to_u256, convert_u256_to_h160, EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use crate::state::HasExecutionResult;
+use bytes::Bytes;
+use itertools::Itertools;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Names of initializer functions (lowercase, without version suffix)
+const INITIALIZER_NAMES: [&str; 5] = ["initialize", "reinitialize", "init", "setup", "set_up"];
+
+/// Revert reason of OpenZeppelin's `initializer` modifier (v4), first chunk pushed by the code
+const INITIALIZED_REASON: &[u8] = b"Initializable: contract is alrea";
+/// `InvalidInitialization()` raised by OpenZeppelin's `initializer` modifier (v5)
+const INVALID_INITIALIZATION: [u8; 4] = [0xf9, 0x2e, 0xe8, 0xa9];
+
+/// Whether the name is one of an initializer, e.g., `initialize`, `initializeV2`, `init`, `setUp`,
+/// or `__Ownable_init` (not `initiateWithdrawal` or `setUpFee`)
+fn is_initializer_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    if name.starts_with("__") && (name.ends_with("_init") || name.ends_with("_init_unchained")) {
+        return true;
+    }
+    let unversioned = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let unversioned = match unversioned.strip_suffix('v') {
+        Some(base) if unversioned.len() < name.len() => base,
+        _ => unversioned,
+    };
+    INITIALIZER_NAMES.contains(&unversioned)
+}
+
+/// Whether the code uses OpenZeppelin's `initializer` modifier
+pub fn uses_initializer_modifier(code: &[u8]) -> bool {
+    code.windows(INITIALIZED_REASON.len()).any(|w| w == INITIALIZED_REASON)
+        || code.windows(5).any(|w| w[0] == 0x63 && w[1..] == INVALID_INITIALIZATION)
+}
+
+/// Whether the function looks like an initializer: named like one, or, in contracts using the
+/// `initializer` modifier, named after initialization (e.g., `initializeMarket`)
+pub fn is_initializer(abi: &ABIConfig, modifier: bool) -> bool {
+    if abi.is_static || abi.is_constructor {
+        return false;
+    }
+    is_initializer_name(&abi.function_name) || (modifier && abi.function_name.to_lowercase().contains("initializ"))
+}
+
+/// Initializers of implementation contracts behind proxies, i.e., contracts whose address is in
+/// the EIP-1967 implementation slot of another contract
+pub fn implementation_initializers(
+    state: &EVMState,
+    initializers: &HashMap<(EVMAddress, [u8; 4]), String>,
+) -> Vec<(EVMAddress, [u8; 4])> {
+    let implementation_slot = EVMU256::from_str(EIP1967_IMPLEMENTATION_SLOT).unwrap();
+    let implementations = state
+        .state
+        .values()
+        .filter_map(|storage| storage.get(&implementation_slot))
+        .map(|v| convert_u256_to_h160(*v))
+        .collect::<HashSet<_>>();
+    initializers
+        .keys()
+        .filter(|(contract, _)| implementations.contains(contract))
+        .cloned()
+        .sorted()
+        .collect()
+}
+
+/// Whether `word` contains `address` (left-padded, or packed with other variables)
+fn contains_address(word: EVMU256, address: EVMAddress) -> bool {
+    word.to_be_bytes::<32>().windows(20).any(|w| w == address.0)
+}
+
+/// Detects initializers that can be called by an arbitrary sender (e.g., uninitialized proxies,
+/// implementation contracts that are never initialized themselves), reported when a successful
+/// call to an initializer stores the sender in the storage of the contract, i.e., the sender
+/// becomes the owner / admin.
+///
+/// Initializers are part of the corpus like other functions, so the fuzzer attempts them
+/// from its callers. Initializers of implementation contracts are also attempted at the beginning
+/// of the campaign ([`implementation_initializers`]).
+pub struct InitializerOracle {
+    /// (contract, function hash) => function name
+    pub initializers: HashMap<(EVMAddress, [u8; 4]), String>,
+}
+
+impl InitializerOracle {
+    /// `modifier_users` are the contracts using the `initializer` modifier
+    pub fn new(address_to_abi: &HashMap<EVMAddress, Vec<ABIConfig>>, modifier_users: &HashSet<EVMAddress>) -> Self {
+        Self {
+            initializers: address_to_abi
+                .iter()
+                .flat_map(|(address, abis)| {
+                    let modifier = modifier_users.contains(address);
+                    abis.iter()
+                        .filter(move |abi| is_initializer(abi, modifier))
+                        .map(|abi| ((*address, abi.function), abi.function_name.clone()))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for InitializerOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let (contract, caller) = (ctx.input.contract, ctx.input.caller);
+        let function = match &ctx.input.data {
+            Some(abi) => abi.function,
+            None => return vec![],
+        };
+        let name = match self.initializers.get(&(contract, function)) {
+            Some(name) => name,
+            None => return vec![],
+        };
+        if ctx.fuzz_state.get_execution_result().reverted {
+            return vec![];
+        }
+
+        let empty = HashMap::new();
+        let before = ctx.pre_state.get(&contract).unwrap_or(&empty);
+        let privileged = match ctx.post_state.get(&contract) {
+            Some(after) => after.iter().any(|(slot, value)| {
+                contains_address(*value, caller)
+                    && !before.get(slot).map_or(false, |v| contains_address(*v, caller))
+            }),
+            None => false,
+        };
+        if !privileged {
+            return vec![];
+        }
+
+        // the contract is the implementation of a proxy
+        let implementation_slot = EVMU256::from_str(EIP1967_IMPLEMENTATION_SLOT).unwrap();
+        let proxy = ctx.pre_state.state.iter().find_map(|(address, storage)| {
+            match storage.get(&implementation_slot) {
+                Some(v) if *v == convert_h160_to_u256(contract) => Some(*address),
+                _ => None,
+            }
+        });
+        unsafe {
+            ORACLE_OUTPUT += format!(
+                "[initializer] {:?}::{} can be called by arbitrary sender {:?}, which gains privileges{}\n",
+                contract,
+                name,
+                caller,
+                proxy
+                    .map(|p| format!(" (implementation of proxy {:?})", p))
+                    .unwrap_or_default()
+            ).as_str();
+        }
+        let mut hasher = DefaultHasher::new();
+        contract.hash(&mut hasher);
+        function.hash(&mut hasher);
+        vec![(hasher.finish() << 8) + INITIALIZER_BUG_IDX]
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_initializer() {
+        let abi = |name: &str| ABIConfig {
+            abi: "()".to_string(),
+            function: [0; 4],
+            function_name: name.to_string(),
+            is_static: false,
+            is_payable: false,
+            is_constructor: false,
+            input_names: vec![],
+        };
+        assert!(is_initializer(&abi("initialize"), false));
+        assert!(is_initializer(&abi("initializeV2"), false));
+        assert!(is_initializer(&abi("reinitialize"), false));
+        assert!(is_initializer(&abi("init"), false));
+        assert!(is_initializer(&abi("__Ownable_init"), false));
+        assert!(is_initializer(&abi("setUp"), false));
+        assert!(!is_initializer(&abi("transfer"), false));
+        // legitimately store msg.sender
+        assert!(!is_initializer(&abi("initiateWithdrawal"), true));
+        assert!(!is_initializer(&abi("setUpFee"), false));
+        assert!(!is_initializer(&abi("initializeMarket"), false));
+        assert!(is_initializer(&abi("initializeMarket"), true));
+    }
+
+    #[test]
+    fn test_uses_initializer_modifier() {
+        let mut code = vec![0x60, 0x80, 0x7f];
+        code.extend(INITIALIZED_REASON);
+        assert!(uses_initializer_modifier(&code));
+        assert!(uses_initializer_modifier(&[0x60, 0x80, 0x63, 0xf9, 0x2e, 0xe8, 0xa9, 0x00]));
+        assert!(!uses_initializer_modifier(&[0x60, 0x80, 0x60, 0x40, 0x52]));
+    }
+
+    #[test]
+    fn test_implementation_initializers() {
+        let (proxy, implementation, other) = (
+            EVMAddress::from_slice(&[0x01; 20]),
+            EVMAddress::from_slice(&[0x02; 20]),
+            EVMAddress::from_slice(&[0x03; 20]),
+        );
+        let mut state = EVMState::new();
+        state.insert(
+            proxy,
+            HashMap::from([(
+                EVMU256::from_str(EIP1967_IMPLEMENTATION_SLOT).unwrap(),
+                convert_h160_to_u256(implementation),
+            )]),
+        );
+        let initializers = HashMap::from([
+            ((implementation, [1; 4]), "initialize".to_string()),
+            ((other, [1; 4]), "initialize".to_string()),
+        ]);
+        assert_eq!(implementation_initializers(&state, &initializers), vec![(implementation, [1; 4])]);
+    }
+
+    #[test]
+    fn test_contains_address() {
+        let address = EVMAddress::from_slice(&[0x11; 20]);
+        assert!(contains_address(convert_h160_to_u256(address), address));
+        // packed with a bool (e.g., `address owner; bool initialized;`)
+        assert!(contains_address(convert_h160_to_u256(address) << 8, address));
+        assert!(!contains_address(EVMU256::from(1), address));
+    }
+}
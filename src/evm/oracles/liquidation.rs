@@ -0,0 +1,213 @@
+use crate::evm::contract_utils::{set_hash, ABIConfig};
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::price_mover::PRICE_TRIGGER;
+use crate::evm::oracles::LIQUIDATION_BUG_IDX;
+use crate::evm::types::{fixed_address, EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use crate::state::HasExecutionResult;
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// Kinds of violations, part of the bug hash
+const BAD_DEBT: u8 = 0;
+const HEALTHY_LIQUIDATED: u8 = 1;
+const HEALTH_WORSENED: u8 = 2;
+
+fn selector(signature: &str) -> [u8; 4] {
+    let mut function = [0u8; 4];
+    set_hash(signature, &mut function);
+    function
+}
+
+fn word(out: &Vec<u8>) -> Option<EVMU256> {
+    if out.len() >= 32 {
+        Some(EVMU256::from_be_slice(&out[..32]))
+    } else {
+        None
+    }
+}
+
+/// Whether `address` is one of the (left-padded) arguments in `args`
+fn is_argument(args: &[u8], address: EVMAddress) -> bool {
+    args.chunks(32)
+        .any(|w| w.len() == 32 && w[..12].iter().all(|b| *b == 0) && w[12..] == address.0)
+}
+
+/// Checks liquidations of a lending market in liquidation scenarios (see [`crate::evm::lending`]):
+/// - bad debt of the market must not increase, unless a price is moved
+/// - positions of actors that are healthy before a liquidation must not be liquidated (i.e.,
+///   the liquidation bonus is stolen)
+/// - a liquidation must not lower the health factor of the liquidated actor
+pub struct LiquidationOracle {
+    pub market: EVMAddress,
+    /// (role, address)
+    pub actors: Vec<(String, EVMAddress)>,
+    pub health_threshold: EVMU256,
+    /// Liquidation functions of the market, function hash => name
+    pub liquidations: HashMap<[u8; 4], String>,
+    /// health of each actor, followed by the bad debt
+    pub batch_call_txs: Vec<(EVMAddress, Bytes)>,
+    pub has_bad_debt: bool,
+}
+
+impl LiquidationOracle {
+    pub fn new(
+        market: EVMAddress,
+        actors: Vec<(String, EVMAddress)>,
+        health: &str,
+        health_threshold: EVMU256,
+        bad_debt: Option<&str>,
+        liquidate: &str,
+        abis: &Vec<ABIConfig>,
+    ) -> Self {
+        let liquidations = abis
+            .iter()
+            .filter(|abi| !abi.is_static && abi.function_name.starts_with(liquidate))
+            .map(|abi| (abi.function, abi.function_name.clone()))
+            .collect::<HashMap<_, _>>();
+        assert!(!liquidations.is_empty(), "no liquidation function {}* in the market", liquidate);
+
+        let health = selector(health);
+        let mut batch_call_txs = actors
+            .iter()
+            .map(|(_, actor)| {
+                let mut data = health.to_vec();
+                data.extend([0; 12]);
+                data.extend(actor.0);
+                (market, Bytes::from(data))
+            })
+            .collect::<Vec<_>>();
+        if let Some(bad_debt) = bad_debt {
+            batch_call_txs.push((market, Bytes::from(selector(bad_debt).to_vec())));
+        }
+        Self {
+            market,
+            actors,
+            health_threshold,
+            liquidations,
+            batch_call_txs,
+            has_bad_debt: bad_debt.is_some(),
+        }
+    }
+
+    fn report(&self, kind: u8, function: [u8; 4], actor: Option<EVMAddress>, msg: String) -> u64 {
+        unsafe {
+            ORACLE_OUTPUT += format!("[liquidation] {}\n", msg).as_str();
+        }
+        let mut hasher = DefaultHasher::new();
+        kind.hash(&mut hasher);
+        function.hash(&mut hasher);
+        actor.hash(&mut hasher);
+        (hasher.finish() << 8) + LIQUIDATION_BUG_IDX
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for LiquidationOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if ctx.fuzz_state.get_execution_result().reverted {
+            return vec![];
+        }
+        let (contract, caller) = (ctx.input.contract, ctx.input.caller);
+        let (function, args) = match &ctx.input.data {
+            Some(abi) => (abi.function, abi.get_bytes_vec()),
+            None => ([0; 4], vec![]),
+        };
+        let liquidation = if contract == self.market {
+            self.liquidations.get(&function)
+        } else {
+            None
+        };
+        let price_moved = contract == fixed_address(PRICE_TRIGGER);
+        if liquidation.is_none() && (price_moved || !self.has_bad_debt) {
+            return vec![];
+        }
+
+        let before = ctx.call_pre_batch(&self.batch_call_txs);
+        let after = ctx.call_post_batch(&self.batch_call_txs);
+        let mut bugs = vec![];
+
+        if self.has_bad_debt && !price_moved {
+            let idx = self.actors.len();
+            if let (Some(pre), Some(post)) = (word(&before[idx]), word(&after[idx])) {
+                if post > pre {
+                    bugs.push(self.report(
+                        BAD_DEBT,
+                        function,
+                        None,
+                        format!("bad debt of market {:?} increases from {} to {} (caller {:?})", self.market, pre, post, caller),
+                    ));
+                }
+            }
+        }
+
+        if let Some(name) = liquidation {
+            let mut reported = HashSet::new();
+            for (idx, (role, actor)) in self.actors.iter().enumerate() {
+                if !is_argument(&args, *actor) || !reported.insert(*actor) {
+                    continue;
+                }
+                let (pre, post) = match (word(&before[idx]), word(&after[idx])) {
+                    (Some(pre), Some(post)) => (pre, post),
+                    _ => continue,
+                };
+                if pre >= self.health_threshold && pre != post {
+                    bugs.push(self.report(
+                        HEALTHY_LIQUIDATED,
+                        function,
+                        Some(*actor),
+                        format!("{} liquidates healthy position of {} {:?} (health factor {})", name, role, actor, pre),
+                    ));
+                } else if post < pre {
+                    bugs.push(self.report(
+                        HEALTH_WORSENED,
+                        function,
+                        Some(*actor),
+                        format!("{} lowers the health factor of {} {:?} from {} to {}", name, role, actor, pre, post),
+                    ));
+                }
+            }
+        }
+        bugs
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_argument() {
+        let actor = fixed_address("00000000000000000000000000000000000b0a01");
+        let mut args = vec![0u8; 12];
+        args.extend(actor.0);
+        args.extend([0xffu8; 32]);
+        assert!(is_argument(&args, actor));
+        assert!(!is_argument(&args[32..], actor));
+        assert!(!is_argument(&args, fixed_address("00000000000000000000000000000000000b0a02")));
+    }
+}
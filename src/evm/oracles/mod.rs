@@ -1,8 +1,33 @@
+pub mod block_context;
+pub mod bridge;
+pub mod constructor;
+pub mod delegatecall;
+pub mod differential;
 pub mod echidna;
+pub mod epoch;
+pub mod event;
+pub mod eip7702;
+pub mod fee;
 pub mod erc20;
+pub mod eth_conservation;
 pub mod function;
+pub mod gas_bomb;
+pub mod initializer;
+pub mod liquidation;
+pub mod plugin;
+#[cfg(feature = "wasm_plugins")]
+pub mod wasm_plugin;
+pub mod reentrancy;
+pub mod rounding;
+pub mod sandwich;
 pub mod selfdestruct;
+pub mod signature;
+pub mod solvency;
+pub mod truncation;
+pub mod tx_origin;
 pub mod typed_bug;
+pub mod unchecked_call;
+pub mod upgrade;
 pub mod v2_pair;
 
 pub static ERC20_BUG_IDX: u64 = 0;
@@ -11,3 +36,86 @@ pub static V2_PAIR_BUG_IDX: u64 = 2;
 pub static TYPED_BUG_BUG_IDX: u64 = 4;
 pub static SELFDESTRUCT_BUG_IDX: u64 = 5;
 pub static ECHIDNA_BUG_IDX: u64 = 6;
+pub static UPGRADE_BUG_IDX: u64 = 7;
+pub static DELEGATED_EOA_BUG_IDX: u64 = 8;
+pub static GAS_BOMB_BUG_IDX: u64 = 9;
+pub static SANDWICH_BUG_IDX: u64 = 10;
+pub static SIGNATURE_BUG_IDX: u64 = 11;
+pub static TX_ORIGIN_BUG_IDX: u64 = 12;
+pub static DELEGATECALL_BUG_IDX: u64 = 13;
+pub static INITIALIZER_BUG_IDX: u64 = 14;
+pub static TRUNCATION_BUG_IDX: u64 = 15;
+pub static ROUNDING_BUG_IDX: u64 = 16;
+pub static DIFFERENTIAL_BUG_IDX: u64 = 17;
+pub static SOLVENCY_BUG_IDX: u64 = 18;
+pub static LIQUIDATION_BUG_IDX: u64 = 19;
+pub static PLUGIN_BUG_IDX: u64 = 20;
+pub static REENTRANCY_BUG_IDX: u64 = 21;
+pub static BLOCK_CONTEXT_BUG_IDX: u64 = 22;
+pub static CONSTRUCTOR_BUG_IDX: u64 = 23;
+pub static EPOCH_BUG_IDX: u64 = 24;
+pub static BRIDGE_BUG_IDX: u64 = 25;
+pub static UNCHECKED_CALL_BUG_IDX: u64 = 26;
+pub static FEE_BUG_IDX: u64 = 27;
+pub static EVENT_BUG_IDX: u64 = 28;
+pub static ETH_CONSERVATION_BUG_IDX: u64 = 29;
+
+/// Kind of the bugs (low byte of the bug indices) reported by an oracle, by its name, to enable /
+/// disable it (`--disable-oracles` and the control socket)
+pub fn oracle_kind(name: &str) -> Option<u64> {
+    let kind = match name {
+        "erc20" => ERC20_BUG_IDX,
+        "function" => FUNCTION_BUG_IDX,
+        "v2_pair" => V2_PAIR_BUG_IDX,
+        "typed_bug" => TYPED_BUG_BUG_IDX,
+        "selfdestruct" => SELFDESTRUCT_BUG_IDX,
+        "echidna" => ECHIDNA_BUG_IDX,
+        "upgrade" => UPGRADE_BUG_IDX,
+        "delegated_eoa" => DELEGATED_EOA_BUG_IDX,
+        "gas_bomb" => GAS_BOMB_BUG_IDX,
+        "sandwich" => SANDWICH_BUG_IDX,
+        "signature" => SIGNATURE_BUG_IDX,
+        "tx_origin" => TX_ORIGIN_BUG_IDX,
+        "delegatecall" => DELEGATECALL_BUG_IDX,
+        "initializer" => INITIALIZER_BUG_IDX,
+        "truncation" => TRUNCATION_BUG_IDX,
+        "rounding" => ROUNDING_BUG_IDX,
+        "differential" => DIFFERENTIAL_BUG_IDX,
+        "solvency" => SOLVENCY_BUG_IDX,
+        "liquidation" => LIQUIDATION_BUG_IDX,
+        "plugin" => PLUGIN_BUG_IDX,
+        "reentrancy" => REENTRANCY_BUG_IDX,
+        "block_context" => BLOCK_CONTEXT_BUG_IDX,
+        "constructor" => CONSTRUCTOR_BUG_IDX,
+        "epoch" => EPOCH_BUG_IDX,
+        "bridge" => BRIDGE_BUG_IDX,
+        "unchecked_call" => UNCHECKED_CALL_BUG_IDX,
+        "fee" => FEE_BUG_IDX,
+        "event" => EVENT_BUG_IDX,
+        "eth_conservation" => ETH_CONSERVATION_BUG_IDX,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_oracle_kind() {
+        let names = [
+            "erc20", "function", "v2_pair", "typed_bug", "selfdestruct", "echidna", "upgrade", "delegated_eoa",
+            "gas_bomb", "sandwich", "signature", "tx_origin", "delegatecall", "initializer", "truncation",
+            "rounding", "differential", "solvency", "liquidation", "plugin", "reentrancy", "block_context",
+            "constructor", "epoch", "bridge", "unchecked_call", "fee", "event", "eth_conservation",
+        ];
+        let kinds = names.iter().map(|name| oracle_kind(name).unwrap()).collect::<HashSet<_>>();
+        // each oracle is toggled on its own
+        assert_eq!(kinds.len(), names.len());
+        assert!(kinds.iter().all(|kind| *kind <= 0xff));
+        assert_eq!(oracle_kind("fee"), Some(FEE_BUG_IDX));
+        assert_eq!(oracle_kind("unknown"), None);
+    }
+}
@@ -0,0 +1,241 @@
+/// Custom oracles implemented as WASM modules (`--oracle-plugin`, see [`super::wasm_plugin`])
+/// or in Python (see the `python` bindings)
+///
+/// After each successful transaction, the oracle receives a JSON document (version
+/// [`PLUGIN_INTERFACE_VERSION`]) describing the transaction:
+/// ```json
+/// {
+///   "version": 1,
+///   "tx": {"caller": "0x..", "contract": "0x..", "value": "0x..", "data": "..", "output": ".."},
+///   "storage": {"0x<address>": {"0x<slot>": {"pre": "0x..", "post": "0x.."}}},
+///   "trace": [{"type": "Call", "from": "0x..", "to": "0x..", ...}, {"type": "Log", ...}],
+///   "balances": [{"token": "0x..", "owner": "0x..", "pre": "0x..", "post": "0x.."}]
+/// }
+/// ```
+/// `storage` contains the slots changed by the transaction, `trace` the steps recorded by
+/// [`crate::evm::middlewares::call_tracer::CallTracer`], and `balances` the balances of the
+/// callers, the contract and the transfer parties of every token emitting a `Transfer` event.
+///
+/// The oracle returns a JSON array of violations, `[{"id": 1, "message": ".."}]`, where
+/// violations with the same id are considered the same bug.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::call_tracer::TraceStep;
+use crate::evm::oracles::PLUGIN_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::OracleCtx;
+use crate::state::HasExecutionResult;
+
+/// Version of the JSON document passed to plugins, bumped on breaking changes
+pub const PLUGIN_INTERFACE_VERSION: u64 = 1;
+
+/// keccak256("Transfer(address,address,uint256)")
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// A violation reported by a plugin
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PluginViolation {
+    pub id: u64,
+    pub message: String,
+}
+
+/// Changed storage slots, address => slot => (pre, post)
+fn storage_diff(pre: &EVMState, post: &EVMState) -> Value {
+    let empty = HashMap::new();
+    let mut diff = Map::new();
+    for (address, storage) in &post.state {
+        let before = pre.get(address).unwrap_or(&empty);
+        let slots = storage
+            .iter()
+            .filter(|(slot, value)| before.get(slot) != Some(value))
+            .map(|(slot, value)| {
+                (
+                    format!("{:#x}", slot),
+                    json!({"pre": before.get(slot).cloned().unwrap_or_default(), "post": value}),
+                )
+            })
+            .collect::<Map<String, Value>>();
+        if !slots.is_empty() {
+            diff.insert(format!("{:?}", address), Value::Object(slots));
+        }
+    }
+    Value::Object(diff)
+}
+
+/// Tokens emitting `Transfer` events in the trace, with the parties of the transfers
+fn transfers(trace: &Vec<TraceStep>) -> Vec<(EVMAddress, BTreeSet<EVMAddress>)> {
+    let topic = EVMU256::from_str(TRANSFER_TOPIC).unwrap();
+    let to_address = |v: &EVMU256| EVMAddress::from_slice(&v.to_be_bytes::<32>()[12..]);
+    let mut tokens: Vec<(EVMAddress, BTreeSet<EVMAddress>)> = vec![];
+    for step in trace {
+        if let TraceStep::Log { address, topics, .. } = step {
+            if topics.len() != 3 || topics[0] != topic {
+                continue;
+            }
+            let idx = match tokens.iter().position(|(token, _)| token == address) {
+                Some(idx) => idx,
+                None => {
+                    tokens.push((*address, BTreeSet::new()));
+                    tokens.len() - 1
+                }
+            };
+            tokens[idx].1.insert(to_address(&topics[1]));
+            tokens[idx].1.insert(to_address(&topics[2]));
+        }
+    }
+    tokens
+}
+
+/// Document describing the transaction just executed, passed to custom oracles
+pub fn tx_document(
+    ctx: &mut OracleCtx<
+        EVMState,
+        EVMAddress,
+        Bytecode,
+        Bytes,
+        EVMAddress,
+        EVMU256,
+        Vec<u8>,
+        EVMInput,
+        EVMFuzzState,
+        ConciseEVMInput
+    >,
+    trace: Vec<TraceStep>,
+) -> Value {
+    let (contract, caller) = (ctx.input.contract, ctx.input.caller);
+
+    // balances of the parties of token transfers, the callers and the contract
+    let mut balance_queries = vec![];
+    for (token, parties) in transfers(&trace) {
+        let owners = parties
+            .into_iter()
+            .chain(ctx.fuzz_state.callers_pool.iter().cloned())
+            .chain([contract])
+            .collect::<BTreeSet<_>>();
+        for owner in owners {
+            let mut data = hex::decode("70a08231").unwrap();
+            data.extend([0; 12]);
+            data.extend(owner.0);
+            balance_queries.push((owner, (token, Bytes::from(data))));
+        }
+    }
+    let calls = balance_queries.iter().map(|(_, call)| call.clone()).collect::<Vec<_>>();
+    let (pre, post) = if calls.is_empty() {
+        (vec![], vec![])
+    } else {
+        (ctx.call_pre_batch(&calls), ctx.call_post_batch(&calls))
+    };
+    let amount = |out: &Vec<u8>| EVMU256::try_from_be_slice(out.as_slice()).unwrap_or(EVMU256::ZERO);
+    let balances = balance_queries
+        .iter()
+        .enumerate()
+        .map(|(idx, (owner, (token, _)))| {
+            json!({"token": token, "owner": owner, "pre": amount(&pre[idx]), "post": amount(&post[idx])})
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "version": PLUGIN_INTERFACE_VERSION,
+        "tx": {
+            "caller": caller,
+            "contract": contract,
+            "value": ctx.input.txn_value.unwrap_or_default(),
+            "data": hex::encode(ctx.input.data.as_ref().map(|d| d.get_bytes()).unwrap_or_default()),
+            "output": hex::encode(&ctx.fuzz_state.get_execution_result().output),
+        },
+        "storage": storage_diff(ctx.pre_state, &ctx.post_state),
+        "trace": trace,
+        "balances": balances,
+    })
+}
+
+/// Parse the violations returned by a plugin
+pub fn parse_violations(out: &[u8]) -> Vec<PluginViolation> {
+    serde_json::from_slice(out).unwrap_or_else(|e| {
+        warn!("[plugin] invalid output ({}): {}", e, String::from_utf8_lossy(out));
+        vec![]
+    })
+}
+
+/// Report violations of the custom oracle `name`, returns their bug hashes
+pub fn report_violations(name: &str, violations: Vec<PluginViolation>) -> Vec<u64> {
+    violations
+        .into_iter()
+        .map(|violation| {
+            unsafe {
+                ORACLE_OUTPUT += format!("[plugin {}] {}\n", name, violation.message).as_str();
+            }
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            violation.id.hash(&mut hasher);
+            (hasher.finish() << 8) + PLUGIN_BUG_IDX
+        })
+        .collect()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_violations() {
+        assert_eq!(
+            parse_violations(br#"[{"id": 1, "message": "price manipulated"}]"#),
+            vec![PluginViolation { id: 1, message: "price manipulated".to_string() }]
+        );
+        assert!(parse_violations(b"[]").is_empty());
+        assert!(parse_violations(b"not json").is_empty());
+    }
+
+    #[test]
+    fn test_transfers() {
+        let token = EVMAddress::from_slice(&[1; 20]);
+        let (from, to) = (EVMAddress::from_slice(&[2; 20]), EVMAddress::from_slice(&[3; 20]));
+        let word = |a: EVMAddress| EVMU256::from_be_slice(&a.0);
+        let trace = vec![TraceStep::Log {
+            address: token,
+            topics: vec![EVMU256::from_str(TRANSFER_TOPIC).unwrap(), word(from), word(to)],
+            data: String::new(),
+        }];
+        assert_eq!(transfers(&trace), vec![(token, BTreeSet::from([from, to]))]);
+    }
+
+    #[test]
+    fn test_storage_diff() {
+        let (a, b) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let mut pre = EVMState::new();
+        pre.insert(a, HashMap::from([(EVMU256::from(1), EVMU256::from(10)), (EVMU256::from(2), EVMU256::from(20))]));
+        let mut post = pre.clone();
+        post.insert(a, HashMap::from([(EVMU256::from(1), EVMU256::from(11)), (EVMU256::from(2), EVMU256::from(20))]));
+        post.insert(b, HashMap::from([(EVMU256::from(3), EVMU256::from(30))]));
+
+        let diff = storage_diff(&pre, &post);
+        // unchanged slots are left out, new slots start at zero
+        assert_eq!(diff[format!("{:?}", a)].as_object().unwrap().len(), 1);
+        assert_eq!(diff[format!("{:?}", a)]["0x1"], json!({"pre": EVMU256::from(10), "post": EVMU256::from(11)}));
+        assert_eq!(diff[format!("{:?}", b)]["0x3"], json!({"pre": EVMU256::ZERO, "post": EVMU256::from(30)}));
+        assert_eq!(storage_diff(&pre, &pre), json!({}));
+    }
+
+    #[test]
+    fn test_report_violations() {
+        let violation = |id| PluginViolation { id, message: "price manipulated".to_string() };
+        let hashes = report_violations("a.wasm", vec![violation(1), violation(1), violation(2)]);
+        // same bug for the same plugin and id
+        assert_eq!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+        assert_ne!(hashes[0], report_violations("b.wasm", vec![violation(1)])[0]);
+        assert!(hashes.iter().all(|hash| hash & 0xff == PLUGIN_BUG_IDX));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::REENTRANCY_BUG_IDX;
+use crate::evm::types::{convert_h160_to_u256, EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::input::VMInputT;
+use crate::oracle::{Oracle, OracleCtx};
+use crate::state::HasExecutionResult;
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// ERC-7201 slot of OpenZeppelin v5 `ReentrancyGuard` (upgradeable) and `ReentrancyGuardTransient`
+pub const OZ_GUARD_SLOT: &str = "0x9b779b17422d0df92223018b32b4d1fa46e071723d6817e2486d003becc55f00";
+/// Values of storage sentinel guards when not entered / entered (OpenZeppelin `_status`, solmate `locked`)
+const NOT_ENTERED: u64 = 1;
+const ENTERED: u64 = 2;
+
+/// Detects reentrancy: reported when a reentrant transaction (executed after a control leak, while
+/// the call of the leaking contract is suspended) modifies the storage of the suspended contract.
+///
+/// Standard nonReentrant guards are recognized, storage sentinels (slots going from 1 to 2 in the
+/// transaction leaking control) and transient-slot locks, so that guarded functions reverting on
+/// reentrancy are not reported and writes to the guard itself are ignored. Reentrancy bypassing
+/// the guard is still reported: through unguarded functions of the suspended contract
+/// (cross-function), and, if the suspended contract is guarded, through guarded contracts it
+/// references in its storage (cross-contract).
+pub struct ReentrancyOracle {
+    /// Contract => storage sentinel guard slots
+    pub guards: RefCell<HashMap<EVMAddress, HashSet<EVMU256>>>,
+}
+
+/// Slots set from [`NOT_ENTERED`] to [`ENTERED`] by a transaction
+pub fn sentinel_slots(pre: Option<&HashMap<EVMU256, EVMU256>>, post: &HashMap<EVMU256, EVMU256>) -> Vec<EVMU256> {
+    post.iter()
+        .filter(|(slot, value)| {
+            **value == EVMU256::from(ENTERED)
+                && pre.and_then(|pre| pre.get(slot)) == Some(&EVMU256::from(NOT_ENTERED))
+        })
+        .map(|(slot, _)| *slot)
+        .collect()
+}
+
+impl ReentrancyOracle {
+    pub fn new() -> Self {
+        Self {
+            guards: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Storage guard slots of a contract, learned or at the OpenZeppelin v5 slot
+    fn guard_slots(&self, contract: &EVMAddress, state: &EVMState) -> HashSet<EVMU256> {
+        let mut slots = self.guards.borrow().get(contract).cloned().unwrap_or_default();
+        let oz_slot = EVMU256::from_str(OZ_GUARD_SLOT).unwrap();
+        match state.get(contract).and_then(|storage| storage.get(&oz_slot)) {
+            Some(v) if *v == EVMU256::from(NOT_ENTERED) || *v == EVMU256::from(ENTERED) => {
+                slots.insert(oz_slot);
+            }
+            _ => {}
+        }
+        slots
+    }
+
+    /// Whether the contract holds a transient-slot lock (e.g., OpenZeppelin
+    /// `ReentrancyGuardTransient`), transient storage is only set during the transaction
+    fn transient_locked(contract: &EVMAddress, state: &EVMState) -> bool {
+        state
+            .transient_storage
+            .get(contract)
+            .map_or(false, |slots| slots.values().any(|v| *v != EVMU256::ZERO))
+    }
+
+    fn changed_slots(
+        contract: &EVMAddress,
+        pre: &EVMState,
+        post: &EVMState,
+        ignored: &HashSet<EVMU256>,
+    ) -> Vec<EVMU256> {
+        let empty = HashMap::new();
+        let before = pre.get(contract).unwrap_or(&empty);
+        post.get(contract)
+            .unwrap_or(&empty)
+            .iter()
+            .filter(|(slot, value)| !ignored.contains(slot) && before.get(slot).unwrap_or(&EVMU256::ZERO) != *value)
+            .map(|(slot, _)| *slot)
+            .collect()
+    }
+
+    fn bug_idx(contract: EVMAddress, function: [u8; 4]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        contract.hash(&mut hasher);
+        function.hash(&mut hasher);
+        (hasher.finish() << 8) + REENTRANCY_BUG_IDX
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for ReentrancyOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        // learn the storage sentinels set by the transactions leaking control
+        if ctx.post_state.post_execution.len() > ctx.pre_state.post_execution.len() {
+            let mut guards = self.guards.borrow_mut();
+            for leak in &ctx.post_state.post_execution[ctx.pre_state.post_execution.len()..] {
+                if let Some(post) = ctx.post_state.get(&leak.address) {
+                    let slots = sentinel_slots(ctx.pre_state.get(&leak.address), post);
+                    guards.entry(leak.address).or_default().extend(slots);
+                }
+            }
+        }
+
+        if ctx.input.is_step()
+            || ctx.pre_state.post_execution.is_empty()
+            || ctx.fuzz_state.get_execution_result().reverted
+        {
+            return vec![];
+        }
+        let function = ctx.input.data.as_ref().map_or([0; 4], |abi| abi.function);
+        let suspended = ctx
+            .pre_state
+            .post_execution
+            .iter()
+            .map(|leak| leak.address)
+            .collect::<HashSet<_>>();
+
+        let mut bugs = vec![];
+        for contract in &suspended {
+            let guard = self.guard_slots(contract, ctx.pre_state);
+            let guarded = !guard.is_empty() || Self::transient_locked(contract, ctx.pre_state);
+            let changed = Self::changed_slots(contract, ctx.pre_state, &ctx.post_state, &guard);
+            if !changed.is_empty() {
+                let kind = if guarded {
+                    "cross-function reentrancy bypassing the guard of"
+                } else {
+                    "reentrancy modifying"
+                };
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[reentrancy] {} {:?} through {:?}::0x{}, slots modified while its call is suspended: {:?}\n",
+                        kind,
+                        contract,
+                        ctx.input.get_contract(),
+                        hex::encode(function),
+                        changed
+                    ).as_str();
+                }
+                bugs.push(Self::bug_idx(*contract, function));
+            }
+            if !guarded {
+                continue;
+            }
+
+            // guarded contracts referenced by the suspended one are expected to be protected too
+            let storage = ctx.pre_state.get(contract).cloned().unwrap_or_default();
+            let referenced = ctx
+                .post_state
+                .state
+                .keys()
+                .filter(|other| !suspended.contains(other) && storage.values().any(|v| *v == convert_h160_to_u256(**other)))
+                .cloned()
+                .collect::<Vec<_>>();
+            for other in referenced {
+                let other_guard = self.guard_slots(&other, ctx.pre_state);
+                if other_guard.is_empty() && !Self::transient_locked(&other, ctx.pre_state) {
+                    continue;
+                }
+                let changed = Self::changed_slots(&other, ctx.pre_state, &ctx.post_state, &other_guard);
+                if changed.is_empty() {
+                    continue;
+                }
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[reentrancy] cross-contract reentrancy bypassing the guard of {:?}: {:?} modified through {:?}::0x{}, slots: {:?}\n",
+                        contract,
+                        other,
+                        ctx.input.get_contract(),
+                        hex::encode(function),
+                        changed
+                    ).as_str();
+                }
+                bugs.push(Self::bug_idx(other, function));
+            }
+        }
+        bugs
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentinel_slots() {
+        let pre = HashMap::from([
+            (EVMU256::from(0), EVMU256::from(NOT_ENTERED)),
+            (EVMU256::from(1), EVMU256::from(NOT_ENTERED)),
+            (EVMU256::from(2), EVMU256::from(5)),
+        ]);
+        let post = HashMap::from([
+            // guard entered
+            (EVMU256::from(0), EVMU256::from(ENTERED)),
+            // unchanged
+            (EVMU256::from(1), EVMU256::from(NOT_ENTERED)),
+            (EVMU256::from(2), EVMU256::from(ENTERED)),
+            // new slot
+            (EVMU256::from(3), EVMU256::from(ENTERED)),
+        ]);
+        assert_eq!(sentinel_slots(Some(&pre), &post), vec![EVMU256::from(0)]);
+        assert!(sentinel_slots(None, &post).is_empty());
+    }
+
+    #[test]
+    fn test_transient_locked() {
+        let (guarded, other) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let mut state = EVMState::new();
+        state
+            .transient_storage
+            .insert(guarded, HashMap::from([(EVMU256::from_str(OZ_GUARD_SLOT).unwrap(), EVMU256::from(1))]));
+        state.transient_storage.insert(other, HashMap::from([(EVMU256::ZERO, EVMU256::ZERO)]));
+        assert!(ReentrancyOracle::transient_locked(&guarded, &state));
+        // lock released
+        assert!(!ReentrancyOracle::transient_locked(&other, &state));
+        assert!(!ReentrancyOracle::transient_locked(&EVMAddress::zero(), &state));
+    }
+}
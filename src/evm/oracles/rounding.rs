@@ -0,0 +1,70 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::ROUNDING_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Detects rounding errors in accounting (e.g., share math rounding in the wrong direction),
+/// i.e., stored values diverging from the same computation in arbitrary precision (recorded by
+/// [`crate::evm::middlewares::rounding::RoundingTracker`]).
+pub struct RoundingOracle;
+
+impl RoundingOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for RoundingOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let previous = ctx.pre_state.rounding_errors.len();
+        ctx.post_state
+            .rounding_errors
+            .iter()
+            .skip(previous)
+            .map(|e| {
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[rounding] {:?} (pc {}) stores {} to slot {} while the exact value is {} ({}% off)\n",
+                        e.contract,
+                        e.pc,
+                        e.stored,
+                        e.slot,
+                        e.exact,
+                        e.error_ppm as f64 / 10000.0
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                e.contract.hash(&mut hasher);
+                e.pc.hash(&mut hasher);
+                (hasher.finish() << 8) + ROUNDING_BUG_IDX
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,274 @@
+use crate::evm::abi::AArray;
+use crate::evm::contract_utils::ABIConfig;
+use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
+use crate::evm::oracles::SANDWICH_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use crate::state::HasExecutionResult;
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Functions whose output depends on the price, i.e., can be sandwiched
+const PRICE_SENSITIVE: [&str; 12] = [
+    "swap", "deposit", "mint", "addliquidity", "removeliquidity", "buy", "sell", "redeem", "withdraw", "zap",
+    "exchange", "trade",
+];
+
+/// Minimum loss of a victim transaction (in percent of the expected output) to be reported
+const MIN_LOSS_PERCENT: u64 = 1;
+
+/// Amount received by a transaction, the last word of its output (e.g., amountOut of a swap,
+/// or the last element of amounts returned by Uniswap routers)
+pub fn output_amount(output: &[u8]) -> Option<EVMU256> {
+    if output.len() < 32 || output.len() % 32 != 0 {
+        return None;
+    }
+    Some(EVMU256::from_be_slice(&output[output.len() - 32..]))
+}
+
+/// Lowercase words of a camelCase or snake_case name (e.g., `amountOutMin` => amount, out, min)
+fn name_tokens(name: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            prev_lower = false;
+            tokens.push(String::new());
+            continue;
+        }
+        if (c.is_ascii_uppercase() && prev_lower) || tokens.is_empty() {
+            tokens.push(String::new());
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        tokens.last_mut().unwrap().push(c.to_ascii_lowercase());
+    }
+    tokens.into_iter().filter(|t| !t.is_empty()).collect()
+}
+
+/// Slippage protection of a price sensitive function, determined by the names of its arguments
+#[derive(Clone, Debug)]
+pub struct SlippageParams {
+    pub name: String,
+    /// Index of the minimum output argument (e.g., amountOutMin)
+    pub min_out: Option<usize>,
+    /// Index of the deadline argument
+    pub deadline: Option<usize>,
+}
+
+impl SlippageParams {
+    pub fn from_abi(abi: &ABIConfig) -> Option<Self> {
+        let name = abi.function_name.to_lowercase();
+        if abi.is_static || abi.is_constructor || !PRICE_SENSITIVE.iter().any(|p| name.contains(p)) {
+            return None;
+        }
+        let position = |pred: &dyn Fn(&[String]) -> bool| {
+            abi.input_names
+                .iter()
+                .position(|n| pred(&name_tokens(n)))
+        };
+        Some(Self {
+            name: abi.function_name.clone(),
+            min_out: position(&|t| t.iter().any(|t| t == "min" || t == "minimum" || t == "slippage")),
+            deadline: position(&|t| {
+                t.iter().any(|t| t == "deadline" || t.starts_with("expir") || t == "validuntil")
+                    || t.windows(2).any(|w| w[0] == "valid" && w[1] == "until")
+            }),
+        })
+    }
+}
+
+/// Detects slippage and sandwich vulnerabilities using victim transactions.
+///
+/// Victim transactions are also executed on the storage before the transaction preceding them,
+/// so that the preceding transaction acts as front-running. When a victim transaction of a price
+/// sensitive function succeeds but receives less than without the front-run, the function is
+/// reported if it has no minimum output argument, accepts a minimum output of 0, or has no
+/// deadline. When the attacker earns (with flashloan enabled) after victim transactions lost value
+/// to front-runs, the sandwich is reported.
+pub struct SandwichOracle {
+    /// function hash => slippage protection
+    pub params: HashMap<[u8; 4], SlippageParams>,
+}
+
+impl SandwichOracle {
+    pub fn new(abis: &Vec<ABIConfig>) -> Self {
+        Self {
+            params: abis
+                .iter()
+                .filter_map(|abi| SlippageParams::from_abi(abi).map(|p| (abi.function, p)))
+                .collect(),
+        }
+    }
+
+    fn bug_idx(contract: EVMAddress, function: [u8; 4], issue: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        contract.hash(&mut hasher);
+        function.hash(&mut hasher);
+        issue.hash(&mut hasher);
+        (hasher.finish() << 8) + SANDWICH_BUG_IDX
+    }
+
+    /// Issues of the slippage protection of a victim transaction
+    fn slippage_issues(&self, input: &EVMInput) -> Vec<&'static str> {
+        let abi = match &input.data {
+            Some(abi) => abi,
+            None => return vec![],
+        };
+        let params = match self.params.get(&abi.function) {
+            Some(params) => params,
+            None => return vec![],
+        };
+        let mut issues = vec![];
+        match params.min_out {
+            None => issues.push("no minimum output"),
+            Some(idx) => {
+                let mut abi = abi.clone();
+                let zero = abi
+                    .b
+                    .as_any()
+                    .downcast_mut::<AArray>()
+                    .and_then(|args| args.data.get(idx).map(|arg| arg.get_bytes_vec().iter().all(|b| *b == 0)))
+                    .unwrap_or(false);
+                if zero {
+                    issues.push("minimum output of 0");
+                }
+            }
+        }
+        if params.deadline.is_none() {
+            issues.push("no deadline");
+        }
+        issues
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for SandwichOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let contract = ctx.input.contract;
+
+        if ctx.input.is_victim() {
+            // raw calldata, the slippage protection is unknown
+            let function = match &ctx.input.data {
+                Some(abi) => abi.function,
+                None => return vec![],
+            };
+            let expected = ctx.post_state.victim_fair_output.as_ref().and_then(|out| output_amount(out));
+            let received = output_amount(&ctx.fuzz_state.get_execution_result().output);
+            let (expected, received) = match (expected, received) {
+                (Some(expected), Some(received)) if received < expected => (expected, received),
+                _ => return vec![],
+            };
+            if (expected - received).saturating_mul(EVMU256::from(100)) < expected.saturating_mul(EVMU256::from(MIN_LOSS_PERCENT)) {
+                return vec![];
+            }
+            return self
+                .slippage_issues(ctx.input)
+                .into_iter()
+                .map(|issue| {
+                    unsafe {
+                        ORACLE_OUTPUT += format!(
+                            "[sandwich] {:?}::{} has {}, victim receives {} after the preceding transaction instead of {} without it\n",
+                            contract,
+                            self.params[&function].name,
+                            issue,
+                            received,
+                            expected
+                        ).as_str();
+                    }
+                    Self::bug_idx(contract, function, issue)
+                })
+                .collect();
+        }
+
+        let flashloan_data = &ctx.post_state.flashloan_data;
+        if ctx.pre_state.victim_loss > EVMU256::ZERO && flashloan_data.earned > flashloan_data.owed {
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[sandwich] attacker earns {} after victim transactions lost {} to the transactions preceding them\n",
+                    flashloan_data.earned - flashloan_data.owed,
+                    ctx.pre_state.victim_loss
+                ).as_str();
+            }
+            return vec![Self::bug_idx(contract, [0; 4], "sandwich")];
+        }
+        vec![]
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slippage_params() {
+        let abi = |name: &str, inputs: Vec<&str>| ABIConfig {
+            abi: "()".to_string(),
+            function: [0; 4],
+            function_name: name.to_string(),
+            is_static: false,
+            is_payable: false,
+            is_constructor: false,
+            input_names: inputs.iter().map(|s| s.to_string()).collect(),
+        };
+        let router = SlippageParams::from_abi(&abi(
+            "swapExactTokensForTokens",
+            vec!["amountIn", "amountOutMin", "path", "to", "deadline"],
+        ))
+        .unwrap();
+        assert_eq!(router.min_out, Some(1));
+        assert_eq!(router.deadline, Some(4));
+
+        let unprotected = SlippageParams::from_abi(&abi("swap", vec!["amountIn"])).unwrap();
+        assert_eq!(unprotected.min_out, None);
+        assert_eq!(unprotected.deadline, None);
+
+        assert!(SlippageParams::from_abi(&abi("transfer", vec!["to", "amount"])).is_none());
+
+        // whole words only
+        let admin = SlippageParams::from_abi(&abi("swap", vec!["admin", "minimalist", "expected"])).unwrap();
+        assert_eq!(admin.min_out, None);
+        let snake = SlippageParams::from_abi(&abi("swap", vec!["amount_in", "_minOut", "valid_until"])).unwrap();
+        assert_eq!(snake.min_out, Some(1));
+        assert_eq!(snake.deadline, Some(2));
+    }
+
+    #[test]
+    fn test_name_tokens() {
+        assert_eq!(name_tokens("amountOutMin"), vec!["amount", "out", "min"]);
+        assert_eq!(name_tokens("_min_amount"), vec!["min", "amount"]);
+        assert_eq!(name_tokens("admin"), vec!["admin"]);
+        assert_eq!(name_tokens("amount0Min"), vec!["amount0", "min"]);
+    }
+
+    #[test]
+    fn test_output_amount() {
+        let mut output = vec![0; 64];
+        output[63] = 42;
+        assert_eq!(output_amount(&output), Some(EVMU256::from(42)));
+        assert_eq!(output_amount(&[1, 2, 3]), None);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::SIGNATURE_BUG_IDX;
+use crate::evm::signature::SignatureRecord;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Detects signature replay and permit misuse with modeled signatures (see [`crate::evm::signature`]).
+///
+/// Reports a signature that is accepted again for the same message:
+/// - on another chain id, i.e., the message does not contain the chain id
+/// - in a transaction from another sender than the one it was given to, i.e., the message does not
+///   contain the spender
+/// - otherwise, i.e., the message does not contain a nonce
+pub struct SignatureOracle;
+
+impl SignatureOracle {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn issue(record: &SignatureRecord) -> &'static str {
+        if record.last_chain_id != record.chain_id {
+            "accepted on another chain (missing chain id)"
+        } else if record.last_caller != record.caller {
+            "accepted for an unintended spender"
+        } else {
+            "accepted twice (missing nonce)"
+        }
+    }
+}
+
+/// Signatures used before the transaction and accepted again in it, a signature used several times
+/// within its first transaction is not reported (e.g., checked by a function and its callee)
+fn replayed<'a>(pre: &[SignatureRecord], post: &'a [SignatureRecord]) -> Vec<&'a SignatureRecord> {
+    post.iter()
+        .filter(|record| {
+            let prev_uses = pre
+                .iter()
+                .find(|x| x.r == record.r && x.s == record.s && x.v == record.v)
+                .map(|x| x.uses)
+                .unwrap_or(0);
+            prev_uses > 0 && record.uses > prev_uses
+        })
+        .collect()
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for SignatureOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let contract = ctx.input.contract;
+        let function = ctx.input.data.as_ref().map(|abi| abi.function).unwrap_or_default();
+        replayed(&ctx.pre_state.signatures, &ctx.post_state.signatures)
+            .into_iter()
+            .map(|record| {
+                let issue = Self::issue(record);
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[signature] signature of {:?} is {} by {:?}::0x{}\n",
+                        record.signer,
+                        issue,
+                        contract,
+                        hex::encode(function)
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                contract.hash(&mut hasher);
+                function.hash(&mut hasher);
+                issue.hash(&mut hasher);
+                (hasher.finish() << 8) + SIGNATURE_BUG_IDX
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replayed() {
+        let record = |r: u64, uses: u32| SignatureRecord {
+            v: 27,
+            r: EVMU256::from(r),
+            s: EVMU256::from(1),
+            hash: EVMU256::ZERO,
+            signer: EVMAddress::zero(),
+            chain_id: EVMU256::from(1),
+            caller: EVMAddress::zero(),
+            uses,
+            last_chain_id: EVMU256::from(1),
+            last_caller: EVMAddress::zero(),
+        };
+        // recovered twice in the transaction it is first used in
+        assert!(replayed(&[], &[record(1, 2)]).is_empty());
+        // used in a previous transaction
+        assert_eq!(replayed(&[record(1, 1)], &[record(1, 2)]), vec![&record(1, 2)]);
+        // not used in this transaction
+        assert!(replayed(&[record(1, 2)], &[record(1, 2)]).is_empty());
+    }
+}
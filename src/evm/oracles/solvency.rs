@@ -0,0 +1,218 @@
+use crate::evm::contract_utils::set_hash;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::SOLVENCY_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A view function of the protocol reporting an amount owed to users (e.g., `totalAssets()`
+/// of a vault, or `totalDeposits()`)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LiabilityConfig {
+    /// Address or contract name
+    pub contract: String,
+    /// Function signature, e.g., `totalAssets()`
+    pub function: String,
+    /// Asset the liability is denominated in (address or contract name), can be omitted
+    /// with a single asset
+    #[serde(default)]
+    pub asset: Option<String>,
+}
+
+/// Solvency invariant of a protocol, loaded from a JSON file:
+/// ```json
+/// {
+///   "protocol": ["Vault", "Strategy"],
+///   "assets": ["0x6b175474e89094c44da98b954eedeac495271d0f"],
+///   "liabilities": [{"contract": "Vault", "function": "totalAssets()"}]
+/// }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SolvencyConfig {
+    /// Contracts holding the funds of the protocol (address or contract name)
+    pub protocol: Vec<String>,
+    /// Tokens counted as assets of the protocol (address or contract name)
+    pub assets: Vec<String>,
+    pub liabilities: Vec<LiabilityConfig>,
+}
+
+impl SolvencyConfig {
+    pub fn from_file(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).expect("failed to read solvency config");
+        serde_json::from_str(&content).expect("failed to parse solvency config")
+    }
+}
+
+/// A liability, resolved
+#[derive(Clone, Debug)]
+pub struct Liability {
+    pub contract: EVMAddress,
+    pub function: String,
+    /// Index of the asset
+    pub asset: usize,
+}
+
+/// Detects protocols becoming insolvent, i.e., the balance of an asset held by the protocol
+/// contracts is lower than the liabilities denominated in it. Only transitions from a solvent
+/// state are reported, so that the funding of the protocol in the initial state does not matter.
+pub struct SolvencyOracle {
+    pub protocol: Vec<EVMAddress>,
+    pub assets: Vec<EVMAddress>,
+    pub liabilities: Vec<Liability>,
+    /// balanceOf calls (asset, protocol contract) followed by liability calls
+    pub batch_call_txs: Vec<(EVMAddress, Bytes)>,
+}
+
+impl SolvencyOracle {
+    pub fn new(config: &SolvencyConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        let protocol = config.protocol.iter().map(resolve).collect::<Vec<_>>();
+        let assets = config.assets.iter().map(resolve).collect::<Vec<_>>();
+        let liabilities = config
+            .liabilities
+            .iter()
+            .map(|l| {
+                let asset = match &l.asset {
+                    Some(asset) => assets
+                        .iter()
+                        .position(|a| *a == resolve(asset))
+                        .unwrap_or_else(|| panic!("asset {} of liability {} is not an asset", asset, l.function)),
+                    None if assets.len() == 1 => 0,
+                    None => panic!("asset of liability {} is required with multiple assets", l.function),
+                };
+                Liability {
+                    contract: resolve(&l.contract),
+                    function: l.function.clone(),
+                    asset,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut batch_call_txs = vec![];
+        let mut balance_of = [0u8; 4];
+        set_hash("balanceOf(address)", &mut balance_of);
+        for asset in &assets {
+            for holder in &protocol {
+                let mut data = balance_of.to_vec();
+                data.extend([0; 12]);
+                data.extend(holder.0);
+                batch_call_txs.push((*asset, Bytes::from(data)));
+            }
+        }
+        for liability in &liabilities {
+            let mut selector = [0u8; 4];
+            set_hash(&liability.function, &mut selector);
+            batch_call_txs.push((liability.contract, Bytes::from(selector.to_vec())));
+        }
+        Self {
+            protocol,
+            assets,
+            liabilities,
+            batch_call_txs,
+        }
+    }
+
+    /// (assets, liabilities) of each asset from the outputs of the batch calls
+    fn balances(&self, outputs: &Vec<Vec<u8>>) -> Vec<(EVMU256, EVMU256)> {
+        let amount = |out: &Vec<u8>| {
+            if out.len() >= 32 {
+                EVMU256::from_be_slice(&out[..32])
+            } else {
+                EVMU256::ZERO
+            }
+        };
+        let mut balances = vec![(EVMU256::ZERO, EVMU256::ZERO); self.assets.len()];
+        let holders = self.protocol.len();
+        for (idx, out) in outputs.iter().enumerate() {
+            if idx < self.assets.len() * holders {
+                let held = &mut balances[idx / holders].0;
+                *held = held.saturating_add(amount(out));
+            } else {
+                let liability = &self.liabilities[idx - self.assets.len() * holders];
+                let owed = &mut balances[liability.asset].1;
+                *owed = owed.saturating_add(amount(out));
+            }
+        }
+        balances
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for SolvencyOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let after = self.balances(&ctx.call_post_batch(&self.batch_call_txs));
+        if after.iter().all(|(held, owed)| held >= owed) {
+            return vec![];
+        }
+        let before = self.balances(&ctx.call_pre_batch(&self.batch_call_txs));
+        after
+            .iter()
+            .zip(before.iter())
+            .enumerate()
+            .filter(|(_, ((held, owed), (held_before, owed_before)))| held < owed && held_before >= owed_before)
+            .map(|(idx, ((held, owed), _))| {
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[solvency] protocol holds {} of asset {:?} while owing {}\n",
+                        held,
+                        self.assets[idx],
+                        owed
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                self.assets[idx].hash(&mut hasher);
+                (hasher.finish() << 8) + SOLVENCY_BUG_IDX
+            })
+            .collect()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balances() {
+        let config = SolvencyConfig {
+            protocol: vec!["0x0000000000000000000000000000000000000001".to_string(), "0x0000000000000000000000000000000000000002".to_string()],
+            assets: vec!["0x0000000000000000000000000000000000000003".to_string()],
+            liabilities: vec![LiabilityConfig {
+                contract: "0x0000000000000000000000000000000000000001".to_string(),
+                function: "totalAssets()".to_string(),
+                asset: None,
+            }],
+        };
+        let resolve = |s: &String| s.parse::<EVMAddress>().unwrap();
+        let oracle = SolvencyOracle::new(&config, &resolve);
+        assert_eq!(oracle.batch_call_txs.len(), 3);
+
+        let word = |v: u64| EVMU256::from(v).to_be_bytes::<32>().to_vec();
+        // 10 + 5 held, 20 owed
+        let balances = oracle.balances(&vec![word(10), word(5), word(20)]);
+        assert_eq!(balances, vec![(EVMU256::from(15), EVMU256::from(20))]);
+    }
+}
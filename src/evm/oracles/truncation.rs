@@ -0,0 +1,68 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::TRUNCATION_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Detects unsafe downcasts, i.e., arithmetic results silently truncated by narrowing casts
+/// before being stored (recorded by [`crate::evm::middlewares::truncation::TruncationTracker`]).
+pub struct TruncationOracle;
+
+impl TruncationOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for TruncationOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let previous = ctx.pre_state.truncations.len();
+        ctx.post_state
+            .truncations
+            .iter()
+            .skip(previous)
+            .map(|t| {
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[truncation] {:?} (pc {}) stores {} truncated to {} bits: {}\n",
+                        t.contract,
+                        t.pc,
+                        t.original,
+                        t.bits,
+                        t.truncated
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                t.contract.hash(&mut hasher);
+                t.pc.hash(&mut hasher);
+                (hasher.finish() << 8) + TRUNCATION_BUG_IDX
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT};
+use crate::evm::oracles::TX_ORIGIN_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{Oracle, OracleCtx};
+use crate::state::HasExecutionResult;
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Detects authorization based on tx.origin, reported when a transaction passing a tx.origin
+/// check (recorded by [`crate::evm::middlewares::origin_auth::OriginAuth`]) while msg.sender
+/// is an intermediary contract succeeds, i.e., the owner can be phished into authorizing
+/// the attacker's contract.
+pub struct TxOriginOracle;
+
+impl TxOriginOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for TxOriginOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        if ctx.fuzz_state.get_execution_result().reverted {
+            return vec![];
+        }
+        let via = if ctx.input.is_via_intermediary() {
+            "the intermediary contract"
+        } else {
+            "a contract"
+        };
+        ctx.post_state
+            .origin_auth
+            .difference(&ctx.pre_state.origin_auth)
+            .map(|(contract, pc)| {
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[tx_origin] authorization by tx.origin at {:?} (pc {}) passed by {} on behalf of {:?}\n",
+                        contract,
+                        pc,
+                        via,
+                        ctx.input.caller
+                    ).as_str();
+                }
+                let mut hasher = DefaultHasher::new();
+                contract.hash(&mut hasher);
+                pc.hash(&mut hasher);
+                (hasher.finish() << 8) + TX_ORIGIN_BUG_IDX
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,82 @@
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::unchecked_call::{FORCED, FORCE_FAIL};
+use crate::evm::oracles::UNCHECKED_CALL_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::generic_vm::vm_executor::GenericVM;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Reports low-level calls whose success flag is dropped (recorded by
+/// [`crate::evm::middlewares::unchecked_call::UncheckedCallTracker`]) when it makes the state
+/// inconsistent: the transaction is re-executed with the call forced to fail, and the bug is
+/// reported if it still succeeds and leaves the storage of the caller as if the call had
+/// succeeded (e.g., a payout marked as paid while the ETH never left).
+pub struct UncheckedCallOracle;
+
+impl UncheckedCallOracle {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for UncheckedCallOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let known = ctx.pre_state.unchecked_calls.len();
+        let calls = ctx.post_state.unchecked_calls[known.min(ctx.post_state.unchecked_calls.len())..].to_vec();
+        let mut bugs = vec![];
+        for call in calls {
+            let result = unsafe {
+                FORCE_FAIL = Some((call.contract, call.pc));
+                FORCED = false;
+                let result = ctx.executor.deref().borrow_mut().execute(ctx.input, ctx.fuzz_state);
+                FORCE_FAIL = None;
+                if !FORCED {
+                    continue;
+                }
+                result
+            };
+            if result.reverted || result.new_state.state.get(&call.contract) != ctx.post_state.get(&call.contract) {
+                continue;
+            }
+            unsafe {
+                ORACLE_OUTPUT += format!(
+                    "[unchecked_call] {:?} ignores the failure of its call to {:?} (pc {}, value {}), its storage is the same whether the call succeeds or fails\n",
+                    call.contract, call.target, call.pc, call.value
+                )
+                .as_str();
+            }
+            let mut hasher = DefaultHasher::new();
+            call.contract.hash(&mut hasher);
+            call.pc.hash(&mut hasher);
+            bugs.push((hasher.finish() << 8) + UNCHECKED_CALL_BUG_IDX);
+        }
+        bugs
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::oracles::UPGRADE_BUG_IDX;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::oracle::{Oracle, OracleCtx};
+use bytes::Bytes;
+use itertools::Itertools;
+use revm_primitives::Bytecode;
+use crate::fuzzer::ORACLE_OUTPUT;
+
+/// Detects storage layout corruption introduced by an upgrade.
+///
+/// When the upgrade is performed, getters without arguments exposed by both the old and the new
+/// implementation are called through the proxy on the state right before the upgrade (old
+/// implementation) and right after it (new implementation).
+/// Since the upgrade itself only changes the implementation slot, any difference in return values
+/// indicates that the new implementation interprets the existing storage differently.
+pub struct UpgradeOracle {
+    pub proxy: EVMAddress,
+    pub slot: EVMU256,
+    pub trigger: EVMAddress,
+    pub batch_call_txs: Vec<(EVMAddress, Bytes)>,
+    pub names: HashMap<Vec<u8>, String>,
+}
+
+impl UpgradeOracle {
+    pub fn new(
+        proxy: EVMAddress,
+        slot: EVMU256,
+        trigger: EVMAddress,
+        getters: Vec<Vec<u8>>,
+        names: HashMap<Vec<u8>, String>,
+    ) -> Self {
+        Self {
+            proxy,
+            slot,
+            trigger,
+            batch_call_txs: getters
+                .into_iter()
+                .map(|getter| (proxy, Bytes::from(getter)))
+                .collect_vec(),
+            names,
+        }
+    }
+
+    fn implementation_slot(&self, state: &EVMState) -> Option<EVMU256> {
+        state.state.get(&self.proxy).and_then(|x| x.get(&self.slot)).cloned()
+    }
+}
+
+impl
+    Oracle<
+        EVMState,
+        EVMAddress,
+        Bytecode,
+        Bytes,
+        EVMAddress,
+        EVMU256,
+        Vec<u8>,
+        EVMInput,
+        EVMFuzzState,
+        ConciseEVMInput
+    > for UpgradeOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        // only check the transaction that performs the upgrade
+        if ctx.input.contract != self.trigger
+            || self.implementation_slot(ctx.pre_state) == self.implementation_slot(&ctx.post_state)
+        {
+            return vec![];
+        }
+
+        let before = ctx.call_pre_batch(&self.batch_call_txs);
+        let after = ctx.call_post_batch(&self.batch_call_txs);
+        before
+            .iter()
+            .zip(after.iter())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(idx, (before, after))| {
+                let func = self.batch_call_txs[idx].1.to_vec();
+                unsafe {
+                    ORACLE_OUTPUT += format!(
+                        "[upgrade] storage layout corrupted, {} returns 0x{} before upgrade and 0x{} after upgrade of {:?}\n",
+                        self.names.get(&func).unwrap_or(&hex::encode(&func)),
+                        hex::encode(before),
+                        hex::encode(after),
+                        self.proxy
+                    ).as_str();
+                }
+                (idx << 8) as u64 + UPGRADE_BUG_IDX
+            })
+            .collect_vec()
+    }
+}
@@ -0,0 +1,220 @@
+/// Custom oracles implemented as WASM modules (`--oracle-plugin`), only built with the
+/// `wasm_plugins` feature
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use bytes::Bytes;
+use revm_primitives::Bytecode;
+use tracing::warn;
+
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::call_tracer::CallTracer;
+use crate::evm::oracles::plugin::{parse_violations, report_violations, tx_document};
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMOracleCtx, EVMU256};
+use crate::evm::vm::EVMState;
+use crate::oracle::{Oracle, OracleCtx};
+
+/// Fuel (roughly, WASM instructions) a plugin may consume per transaction, beyond which it traps
+const PLUGIN_FUEL: u64 = 100_000_000;
+
+/// Oracle implemented as a WASM module.
+///
+/// The module needs to export `memory`, `alloc(len: i32) -> i32` and
+/// `oracle(ptr: i32, len: i32) -> i64` (same convention as
+/// [`crate::evm::precompiles::WasmPrecompile`]): the return value is
+/// `(out_ptr << 32) | out_len` pointing to the violations, or negative on failure.
+/// The input buffer is reused across transactions, when a larger one is needed the previous one
+/// is passed to `dealloc(ptr: i32, len: i32)` if exported.
+pub struct WasmOracle {
+    pub path: String,
+    pub tracer: Rc<RefCell<CallTracer>>,
+    instance: RefCell<WasmInstance>,
+}
+
+struct WasmInstance {
+    store: wasmi::Store<()>,
+    memory: wasmi::Memory,
+    alloc: wasmi::TypedFunc<i32, i32>,
+    dealloc: Option<wasmi::TypedFunc<(i32, i32), ()>>,
+    oracle: wasmi::TypedFunc<(i32, i32), i64>,
+    /// Input buffer allocated in the module, (pointer, capacity)
+    buffer: Option<(i32, usize)>,
+}
+
+impl WasmInstance {
+    fn new(wasm: &[u8]) -> Self {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = wasmi::Engine::new(&config);
+        let module = wasmi::Module::new(&engine, wasm).expect("failed to load oracle plugin");
+        let mut store = wasmi::Store::new(&engine, ());
+        store.add_fuel(PLUGIN_FUEL).expect("failed to add fuel");
+        let linker = <wasmi::Linker<()>>::new(&engine);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|x| x.start(&mut store))
+            .expect("failed to instantiate oracle plugin");
+        Self {
+            memory: instance
+                .get_memory(&store, "memory")
+                .expect("oracle plugin does not export memory"),
+            alloc: instance
+                .get_typed_func::<i32, i32>(&store, "alloc")
+                .expect("oracle plugin does not export alloc"),
+            dealloc: instance.get_typed_func::<(i32, i32), ()>(&store, "dealloc").ok(),
+            oracle: instance
+                .get_typed_func::<(i32, i32), i64>(&store, "oracle")
+                .expect("oracle plugin does not export oracle"),
+            store,
+            buffer: None,
+        }
+    }
+
+    /// Buffer for `len` bytes of input, only allocated when the current one is too small
+    fn buffer(&mut self, len: usize) -> Result<i32, String> {
+        if let Some((ptr, capacity)) = self.buffer {
+            if len <= capacity {
+                return Ok(ptr);
+            }
+            if let Some(dealloc) = &self.dealloc {
+                dealloc
+                    .call(&mut self.store, (ptr, capacity as i32))
+                    .map_err(|e| format!("dealloc failed: {}", e))?;
+            }
+            self.buffer = None;
+        }
+        let capacity = len.next_power_of_two();
+        let ptr = self
+            .alloc
+            .call(&mut self.store, capacity as i32)
+            .map_err(|e| format!("alloc failed: {}", e))?;
+        self.buffer = Some((ptr, capacity));
+        Ok(ptr)
+    }
+
+    fn call(&mut self, input: &[u8]) -> Result<Vec<u8>, String> {
+        // each transaction gets the same fuel
+        let remaining = self.store.consume_fuel(0).map_err(|e| e.to_string())?;
+        self.store
+            .add_fuel(PLUGIN_FUEL.saturating_sub(remaining))
+            .map_err(|e| e.to_string())?;
+
+        let ptr = self.buffer(input.len())?;
+        self.memory
+            .write(&mut self.store, ptr as usize, input)
+            .map_err(|e| e.to_string())?;
+        let ret = self
+            .oracle
+            .call(&mut self.store, (ptr, input.len() as i32))
+            .map_err(|e| e.to_string())?;
+        if ret < 0 {
+            return Err(format!("oracle returned {}", ret));
+        }
+        let (out_ptr, out_len) = ((ret >> 32) as usize, (ret & 0xffffffff) as usize);
+        let mut out = vec![0; out_len];
+        self.memory
+            .read(&self.store, out_ptr, &mut out)
+            .map_err(|e| e.to_string())?;
+        Ok(out)
+    }
+}
+
+impl WasmOracle {
+    pub fn from_file(path: &str, tracer: Rc<RefCell<CallTracer>>) -> Self {
+        let wasm = std::fs::read(path).unwrap_or_else(|_| panic!("oracle plugin {} not found", path));
+        Self {
+            path: path.to_string(),
+            tracer,
+            instance: RefCell::new(WasmInstance::new(&wasm)),
+        }
+    }
+}
+
+impl Oracle<EVMState, EVMAddress, Bytecode, Bytes, EVMAddress, EVMU256, Vec<u8>, EVMInput, EVMFuzzState, ConciseEVMInput>
+for WasmOracle
+{
+    fn transition(&self, _ctx: &mut EVMOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut OracleCtx<
+            EVMState,
+            EVMAddress,
+            Bytecode,
+            Bytes,
+            EVMAddress,
+            EVMU256,
+            Vec<u8>,
+            EVMInput,
+            EVMFuzzState,
+            ConciseEVMInput
+        >,
+        _stage: u64,
+    ) -> Vec<u64> {
+        let document = tx_document(ctx, self.tracer.borrow().steps.clone());
+        let out = match self.instance.borrow_mut().call(document.to_string().as_bytes()) {
+            Ok(out) => out,
+            Err(e) => {
+                warn!("[plugin {}] failed: {}", self.path, e);
+                return vec![];
+            }
+        };
+        report_violations(&self.path, parse_violations(&out))
+    }
+}
+
+mod tests {
+    use super::*;
+
+    /// Module exporting `alloc` (returning 1024) and `oracle` (returning `[]` at 0)
+    const EMPTY_PLUGIN: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x0c, 0x02, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7e, // types
+        0x03, 0x03, 0x02, 0x00, 0x01, // functions
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        0x07, 0x1b, 0x03, // exports
+        0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, // memory
+        0x05, 0x61, 0x6c, 0x6c, 0x6f, 0x63, 0x00, 0x00, // alloc
+        0x06, 0x6f, 0x72, 0x61, 0x63, 0x6c, 0x65, 0x00, 0x01, // oracle
+        0x0a, 0x0c, 0x02, // code
+        0x05, 0x00, 0x41, 0x80, 0x08, 0x0b, // i32.const 1024
+        0x04, 0x00, 0x42, 0x02, 0x0b, // i64.const 2
+        0x0b, 0x08, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x02, 0x5b, 0x5d, // data "[]" at 0
+    ];
+
+    /// Same as [`EMPTY_PLUGIN`], but `oracle` loops forever
+    const LOOPING_PLUGIN: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x0c, 0x02, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7e, // types
+        0x03, 0x03, 0x02, 0x00, 0x01, // functions
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        0x07, 0x1b, 0x03, // exports
+        0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, // memory
+        0x05, 0x61, 0x6c, 0x6c, 0x6f, 0x63, 0x00, 0x00, // alloc
+        0x06, 0x6f, 0x72, 0x61, 0x63, 0x6c, 0x65, 0x00, 0x01, // oracle
+        0x0a, 0x11, 0x02, // code
+        0x05, 0x00, 0x41, 0x80, 0x08, 0x0b, // i32.const 1024
+        0x09, 0x00, 0x03, 0x40, 0x0c, 0x00, 0x0b, 0x42, 0x00, 0x0b, // loop br 0 end, i64.const 0
+    ];
+
+    #[test]
+    fn test_wasm_buffer_reused() {
+        let mut instance = WasmInstance::new(EMPTY_PLUGIN);
+        assert_eq!(instance.call(b"{}").unwrap(), b"[]");
+        assert_eq!(instance.buffer, Some((1024, 2)));
+        assert_eq!(instance.call(b"{ }").unwrap(), b"[]");
+        assert_eq!(instance.buffer, Some((1024, 4)));
+        assert_eq!(instance.call(b"{}").unwrap(), b"[]");
+        assert_eq!(instance.buffer, Some((1024, 4)));
+    }
+
+    #[test]
+    fn test_wasm_fuel() {
+        let mut instance = WasmInstance::new(LOOPING_PLUGIN);
+        assert!(instance.call(b"{}").is_err());
+        // the fuel is refilled for the next transaction
+        assert!(instance.call(b"{}").is_err());
+    }
+}
@@ -0,0 +1,472 @@
+/// Power schedules deciding how many mutants of an input are executed (its energy)
+///
+/// - `default`: random energy (1 - 128), as LibAFL's mutational stage
+/// - `explore`: constant low energy, cycles through the corpus quickly
+/// - `exploit`: high energy, more for inputs that already led to new coverage
+/// - `fast`: AFLFast-like, energy grows with the times an input was fuzzed and is higher for
+///   inputs exercising rarely hit branches
+/// - `rare`: FairFuzz-like, inputs hitting the rarest branches get most energy
+///
+/// With sequence-length-aware energy, inputs executed on VM states built by longer transaction
+/// sequences get more energy (with all schedules but `default`). With rare-branch boosting, inputs
+/// hitting rare branches get [`RARE_BOOST`] times more energy (with all schedules). Statistics of
+/// the schedule are written to `[WORKDIR]/power_schedule.json`.
+///
+/// Inputs reaching code changed since the revision given to `--focus-diff` get [`DIFF_BOOST`]
+/// times more energy.
+///
+/// In multi-target campaigns, the energy is also scaled by the coverage progress of the group of
+/// targets the input calls (see [`TargetGroups`]).
+///
+/// Hit counts of branches over all executions are tracked by [`BranchHitObserver`].
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+use libafl::bolts::tuples::Named;
+use libafl::corpus::Corpus;
+use libafl::executors::ExitKind;
+use libafl::mutators::{MutationResult, Mutator};
+use libafl::observers::Observer;
+use libafl::prelude::{HasMetadata, HasRand, Rand};
+use libafl::stages::Stage;
+use libafl::state::HasCorpus;
+use libafl::{impl_serdeany, Error, Evaluator};
+use serde::{Deserialize, Serialize};
+
+use crate::evm::host::JMP_MAP;
+use crate::evm::input::EVMInput;
+use crate::evm::middlewares::diff_coverage::DIFF_HITS;
+use crate::evm::function_weights::FunctionWeights;
+use crate::evm::target_groups::TargetGroups;
+use crate::evm::types::EVMFuzzState;
+use crate::generic_vm::vm_executor::MAP_SIZE;
+use crate::input::VMInputT;
+
+/// Energy unit of the schedules
+pub const BASE_ENERGY: u64 = 32;
+/// Maximum energy of an input
+pub const MAX_ENERGY: u64 = 1024;
+/// Energy multiplier of inputs hitting rare branches
+pub const RARE_BOOST: u64 = 4;
+/// Energy multiplier of inputs reaching changed code
+pub const DIFF_BOOST: u64 = 8;
+/// Statistics are written every this many rounds
+const STATS_INTERVAL: u64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerSchedule {
+    Default,
+    Fast,
+    Explore,
+    Exploit,
+    RareBranch,
+}
+
+impl PowerSchedule {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "default" => Some(PowerSchedule::Default),
+            "fast" => Some(PowerSchedule::Fast),
+            "explore" => Some(PowerSchedule::Explore),
+            "exploit" => Some(PowerSchedule::Exploit),
+            "rare" => Some(PowerSchedule::RareBranch),
+            _ => None,
+        }
+    }
+
+    /// Whether the schedule needs the hit counts of branches
+    pub fn tracks_branches(&self) -> bool {
+        matches!(self, PowerSchedule::Fast | PowerSchedule::RareBranch)
+    }
+}
+
+/// Scheduling data of an input in the corpus
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct EntryStats {
+    /// Times the input was selected
+    pub fuzzed: u64,
+    /// Inputs added to the corpus from its mutants
+    pub found: u64,
+    /// Branches (indices of the jump map) hit when the input was added
+    pub branches: Vec<usize>,
+    /// Whether the input reached changed code (`--focus-diff`)
+    pub reaches_diff: bool,
+}
+
+/// Energy spent and inputs found for a sequence length
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SequenceStats {
+    pub energy: u64,
+    pub found: u64,
+}
+
+/// Metadata of [`PowerMutationalStage`] stored in the state
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PowerScheduleMetadata {
+    pub entries: HashMap<usize, EntryStats>,
+    /// Hit count of each branch over all executions
+    pub branch_hits: Vec<u64>,
+    pub rounds: u64,
+    pub energy_total: u64,
+    pub found_total: u64,
+    /// Sequence length -> stats
+    pub sequences: HashMap<usize, SequenceStats>,
+    /// Rounds boosted for hitting rare branches
+    pub rare_boosted: u64,
+}
+
+impl_serdeany!(PowerScheduleMetadata);
+
+impl PowerScheduleMetadata {
+    /// Average hit count of `branches` (of all hit branches if `None`)
+    fn average_hits(&self, branches: Option<&Vec<usize>>) -> f64 {
+        let hits = match branches {
+            Some(branches) => branches.iter().map(|b| self.branch_hits[*b]).collect::<Vec<_>>(),
+            None => self.branch_hits.iter().filter(|h| **h > 0).cloned().collect(),
+        };
+        if hits.is_empty() {
+            return 0.0;
+        }
+        hits.iter().sum::<u64>() as f64 / hits.len() as f64
+    }
+
+    /// Hit count under which a branch is rare: the smallest power of two not lower than the
+    /// hit count of the rarest branch
+    fn rare_threshold(&self) -> u64 {
+        self.branch_hits
+            .iter()
+            .filter(|h| **h > 0)
+            .min()
+            .map(|min| min.next_power_of_two())
+            .unwrap_or(0)
+    }
+
+    /// Whether the input hits a rare branch
+    pub fn hits_rare_branch(&self, entry: &EntryStats) -> bool {
+        let threshold = self.rare_threshold();
+        entry.branches.iter().any(|b| self.branch_hits[*b] <= threshold)
+    }
+
+    /// Count the branches hit by an execution
+    fn record_hits(&mut self, jmp_map: &[u8]) {
+        for (idx, hits) in jmp_map.iter().enumerate() {
+            if *hits > 0 {
+                self.branch_hits[idx] += 1;
+            }
+        }
+    }
+}
+
+/// Energy of an input hitting rare branches with rare-branch boosting
+fn rare_boosted(iterations: u64) -> u64 {
+    (iterations * RARE_BOOST).min(MAX_ENERGY)
+}
+
+fn ensure_metadata(state: &mut EVMFuzzState) {
+    if !state.has_metadata::<PowerScheduleMetadata>() {
+        state.metadata_mut().insert(PowerScheduleMetadata {
+            branch_hits: vec![0; MAP_SIZE],
+            ..Default::default()
+        });
+    }
+}
+
+/// Observer counting the hits of each branch over all executions (in [`PowerScheduleMetadata`])
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BranchHitObserver {
+    enabled: bool,
+}
+
+impl BranchHitObserver {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl Named for BranchHitObserver {
+    fn name(&self) -> &str {
+        "branch_hits"
+    }
+}
+
+impl<I> Observer<I, EVMFuzzState> for BranchHitObserver {
+    fn post_exec(&mut self, state: &mut EVMFuzzState, _input: &I, _exit_kind: &ExitKind) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+        ensure_metadata(state);
+        let meta = state.metadata_mut().get_mut::<PowerScheduleMetadata>().unwrap();
+        unsafe {
+            meta.record_hits(&JMP_MAP);
+        }
+        Ok(())
+    }
+}
+
+/// Energy of an input, `None` for the default (random) schedule
+pub fn energy(
+    schedule: PowerSchedule,
+    entry: &EntryStats,
+    meta: &PowerScheduleMetadata,
+    sequence_len: Option<usize>,
+) -> Option<u64> {
+    // inputs exercising frequently hit branches get less energy
+    let frequency_factor = || {
+        let entry_hits = meta.average_hits(Some(&entry.branches));
+        if entry.branches.is_empty() || entry_hits == 0.0 {
+            return 1.0;
+        }
+        (meta.average_hits(None) / entry_hits).clamp(0.25, 4.0)
+    };
+    let energy = match schedule {
+        PowerSchedule::Default => return None,
+        PowerSchedule::Explore => BASE_ENERGY as f64,
+        PowerSchedule::Exploit => (BASE_ENERGY * 4 * (1 + entry.found.min(3))) as f64,
+        PowerSchedule::Fast => (BASE_ENERGY << entry.fuzzed.min(4)) as f64 * frequency_factor(),
+        PowerSchedule::RareBranch => {
+            if meta.hits_rare_branch(entry) {
+                (BASE_ENERGY * 8) as f64
+            } else {
+                (BASE_ENERGY / 2) as f64
+            }
+        }
+    };
+    let energy = match sequence_len {
+        Some(len) => energy * (1.0 + len.min(8) as f64 * 0.25),
+        None => energy,
+    };
+    Some((energy as u64).clamp(1, MAX_ENERGY))
+}
+
+/// Mutational stage executing as many mutants of the input as its energy
+#[derive(Debug)]
+pub struct PowerMutationalStage<M> {
+    mutator: M,
+    schedule: PowerSchedule,
+    sequence_aware: bool,
+    rare_boost: bool,
+    work_dir: String,
+    target_groups: Option<TargetGroups>,
+    function_weights: Option<FunctionWeights>,
+}
+
+impl<M> PowerMutationalStage<M> {
+    pub fn new(mutator: M, schedule: PowerSchedule, sequence_aware: bool, rare_boost: bool, work_dir: String) -> Self {
+        Self {
+            mutator,
+            schedule,
+            sequence_aware,
+            rare_boost,
+            work_dir,
+            target_groups: None,
+            function_weights: None,
+        }
+    }
+
+    /// Allocate energy across the targets of a multi-target campaign
+    pub fn set_target_groups(&mut self, target_groups: TargetGroups) {
+        self.target_groups = Some(target_groups);
+    }
+
+    /// Scale the energy of inputs by the weight of the function they call
+    pub fn set_function_weights(&mut self, function_weights: FunctionWeights) {
+        self.function_weights = Some(function_weights);
+    }
+
+    /// Whether branches hit by new inputs should be recorded
+    fn tracks_branches(&self) -> bool {
+        self.rare_boost || self.schedule.tracks_branches()
+    }
+
+    fn record_stats(&self, meta: &PowerScheduleMetadata) {
+        let stats = serde_json::json!({
+            "schedule": format!("{:?}", self.schedule),
+            "sequence_aware": self.sequence_aware,
+            "rare_boost": self.rare_boost,
+            "rare_boosted": meta.rare_boosted,
+            "rounds": meta.rounds,
+            "executions": meta.energy_total,
+            "found": meta.found_total,
+            "average_energy": meta.energy_total as f64 / meta.rounds.max(1) as f64,
+            "found_per_1k_executions": meta.found_total as f64 * 1000.0 / meta.energy_total.max(1) as f64,
+            "sequences": meta.sequences,
+            "target_groups": self.target_groups.as_ref().map(|groups| &groups.progress),
+        });
+        let path = format!("{}/power_schedule.json", self.work_dir);
+        if let Ok(mut file) = File::create(path) {
+            let _ = file.write_all(serde_json::to_string_pretty(&stats).unwrap().as_bytes());
+        }
+    }
+}
+
+fn hit_branches() -> Vec<usize> {
+    unsafe { (0..MAP_SIZE).filter(|idx| JMP_MAP[*idx] > 0).collect() }
+}
+
+impl<E, EM, M, Z> Stage<E, EM, EVMFuzzState, Z> for PowerMutationalStage<M>
+where
+    M: Mutator<EVMInput, EVMFuzzState>,
+    Z: Evaluator<E, EM, EVMInput, EVMFuzzState>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut EVMFuzzState,
+        manager: &mut EM,
+        corpus_idx: usize,
+    ) -> Result<(), Error> {
+        ensure_metadata(state);
+        let original = state.corpus().get(corpus_idx)?.borrow_mut().load_input()?.clone();
+        let sequence_len = original.get_staged_state().trace.transactions.len();
+
+        let (iterations, rare, reaches_diff) = {
+            let meta = state.metadata().get::<PowerScheduleMetadata>().unwrap();
+            let entry = meta.entries.get(&corpus_idx).cloned().unwrap_or_default();
+            (
+                energy(self.schedule, &entry, meta, self.sequence_aware.then_some(sequence_len)),
+                self.rare_boost && meta.hits_rare_branch(&entry),
+                entry.reaches_diff,
+            )
+        };
+        let mut iterations = iterations.unwrap_or_else(|| 1 + state.rand_mut().below(128));
+        if rare {
+            iterations = rare_boosted(iterations);
+        }
+        if reaches_diff {
+            iterations = (iterations * DIFF_BOOST).min(MAX_ENERGY);
+        }
+        let target = original.get_contract();
+        if let Some(groups) = &self.target_groups {
+            iterations = ((iterations as f64 * groups.weight(&target)) as u64).clamp(1, MAX_ENERGY);
+        }
+        if let (Some(weights), Some(abi)) = (self.function_weights.as_mut(), original.get_data_abi()) {
+            iterations = ((iterations as f64 * weights.weight(&abi.function)) as u64).clamp(1, MAX_ENERGY);
+        }
+
+        let mut found = 0;
+        for i in 0..iterations {
+            let mut input = original.clone();
+            if self.mutator.mutate(state, &mut input, i as i32)? == MutationResult::Skipped {
+                continue;
+            }
+            let diff_hits = unsafe { DIFF_HITS };
+            let (_, new_idx) = fuzzer.evaluate_input(state, executor, manager, input)?;
+            self.mutator.post_exec(state, i as i32, new_idx)?;
+
+            if let Some(new_idx) = new_idx {
+                found += 1;
+                let branches = if self.tracks_branches() { hit_branches() } else { vec![] };
+                let meta = state.metadata_mut().get_mut::<PowerScheduleMetadata>().unwrap();
+                meta.entries.insert(
+                    new_idx,
+                    EntryStats {
+                        branches,
+                        reaches_diff: unsafe { DIFF_HITS } != diff_hits,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        if let Some(groups) = self.target_groups.as_mut() {
+            groups.record(&target, iterations, found);
+        }
+        let meta = state.metadata_mut().get_mut::<PowerScheduleMetadata>().unwrap();
+        let entry = meta.entries.entry(corpus_idx).or_default();
+        entry.fuzzed += 1;
+        entry.found += found;
+        meta.rounds += 1;
+        meta.energy_total += iterations;
+        meta.found_total += found;
+        if rare {
+            meta.rare_boosted += 1;
+        }
+        let sequence = meta.sequences.entry(sequence_len).or_default();
+        sequence.energy += iterations;
+        sequence.found += found;
+        if meta.rounds % STATS_INTERVAL == 0 {
+            let meta = meta.clone();
+            self.record_stats(&meta);
+        }
+        Ok(())
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::state::FuzzState;
+
+    #[test]
+    fn test_energy() {
+        let mut meta = PowerScheduleMetadata {
+            branch_hits: vec![0; MAP_SIZE],
+            ..Default::default()
+        };
+        meta.branch_hits[1] = 1000;
+        meta.branch_hits[2] = 3;
+        let common = EntryStats {
+            fuzzed: 2,
+            found: 0,
+            branches: vec![1],
+            reaches_diff: false,
+        };
+        let rare = EntryStats {
+            fuzzed: 2,
+            found: 0,
+            branches: vec![2],
+            reaches_diff: false,
+        };
+        assert_eq!(energy(PowerSchedule::Default, &rare, &meta, None), None);
+        assert_eq!(energy(PowerSchedule::Explore, &rare, &meta, None), Some(BASE_ENERGY));
+        assert_eq!(energy(PowerSchedule::RareBranch, &rare, &meta, None), Some(BASE_ENERGY * 8));
+        assert_eq!(energy(PowerSchedule::RareBranch, &common, &meta, None), Some(BASE_ENERGY / 2));
+        assert!(energy(PowerSchedule::Fast, &rare, &meta, None) > energy(PowerSchedule::Fast, &common, &meta, None));
+        assert_eq!(energy(PowerSchedule::Explore, &rare, &meta, Some(4)), Some(BASE_ENERGY * 2));
+        assert!(meta.hits_rare_branch(&rare));
+        assert!(!meta.hits_rare_branch(&common));
+    }
+
+    #[test]
+    fn test_record_hits() {
+        let mut meta = PowerScheduleMetadata {
+            branch_hits: vec![0; MAP_SIZE],
+            ..Default::default()
+        };
+        let mut jmp_map = vec![0u8; MAP_SIZE];
+        jmp_map[3] = 5;
+        jmp_map[7] = 1;
+        meta.record_hits(&jmp_map);
+        jmp_map[7] = 0;
+        meta.record_hits(&jmp_map);
+        // executions hitting a branch are counted, not hits in an execution
+        assert_eq!((meta.branch_hits[3], meta.branch_hits[7], meta.branch_hits[0]), (2, 1, 0));
+
+        let rare = EntryStats {
+            branches: vec![7],
+            ..Default::default()
+        };
+        let common = EntryStats {
+            branches: vec![3],
+            ..Default::default()
+        };
+        assert!(meta.hits_rare_branch(&rare));
+        assert!(!meta.hits_rare_branch(&common));
+    }
+
+    #[test]
+    fn test_rare_boosted() {
+        assert_eq!(rare_boosted(BASE_ENERGY), BASE_ENERGY * RARE_BOOST);
+        assert_eq!(rare_boosted(MAX_ENERGY / 2), MAX_ENERGY);
+    }
+
+    #[test]
+    fn test_branch_hit_observer_disabled() {
+        let mut state: EVMFuzzState = FuzzState::new(0);
+        BranchHitObserver::new(false).post_exec(&mut state, &(), &ExitKind::Ok).unwrap();
+        assert!(!state.has_metadata::<PowerScheduleMetadata>());
+        BranchHitObserver::new(true).post_exec(&mut state, &(), &ExitKind::Ok).unwrap();
+        assert!(state.has_metadata::<PowerScheduleMetadata>());
+    }
+}
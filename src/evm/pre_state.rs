@@ -0,0 +1,205 @@
+/// Pre-state of the fuzzing senders, established before the campaign (`--pre-state`)
+///
+/// Functions requiring prior approvals or positions (e.g., `withdraw` of a vault, `claim` of a
+/// staking contract) are only reachable after an onboarding flow that the fuzzer would have to
+/// rediscover in every sequence. The pre-state declares it instead: approvals given by each
+/// sender and calls (deposits, stakes, ...) made by each sender, executed after the deployment
+/// of the targets so that the initial state of the campaign already contains them.
+use std::str::FromStr;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::evm::contract_utils::set_hash;
+use crate::evm::types::{EVMAddress, EVMU256};
+
+/// An approval given by each sender
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApprovalConfig {
+    /// Address or contract name of the token
+    pub token: String,
+    /// Address or contract name of the spender
+    pub spender: String,
+    /// Allowance, unlimited (`max`) by default
+    #[serde(default = "default_amount")]
+    pub amount: String,
+}
+
+fn default_amount() -> String {
+    "max".to_string()
+}
+
+/// A call made by each sender, e.g., a deposit or a stake
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PositionConfig {
+    /// Address or contract name
+    pub contract: String,
+    /// Function signature, e.g., `deposit(uint256,address)`
+    pub function: String,
+    /// Static arguments: numbers, `max`, `true` / `false`, addresses, contract names or `sender`
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// ETH sent with the call
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// Pre-state of the senders, loaded from a JSON file:
+/// ```json
+/// {
+///   "senders": ["0x8EF508Aca04B32Ff3ba5003177cb18BfA6Cd79dd"],
+///   "approvals": [{"token": "DAI", "spender": "Vault"}],
+///   "positions": [
+///     {"contract": "DAI", "function": "mint(address,uint256)", "args": ["sender", "1000000000000000000000"]},
+///     {"contract": "Vault", "function": "deposit(uint256,address)", "args": ["1000000000000000000", "sender"]}
+///   ]
+/// }
+/// ```
+/// Senders are added to the callers of the fuzzer, all callers are onboarded if none is given.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PreStateConfig {
+    #[serde(default)]
+    pub senders: Vec<String>,
+    #[serde(default)]
+    pub approvals: Vec<ApprovalConfig>,
+    #[serde(default)]
+    pub positions: Vec<PositionConfig>,
+}
+
+impl PreStateConfig {
+    pub fn from_file(path: &str) -> Self {
+        let content = std::fs::read_to_string(path).expect("failed to read pre-state");
+        serde_json::from_str(&content).expect("failed to parse pre-state")
+    }
+}
+
+/// An argument of a setup call
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetupArg {
+    /// The sender making the call
+    Sender,
+    Word(EVMU256),
+}
+
+/// A call made by each sender, resolved
+#[derive(Clone, Debug)]
+pub struct SetupCall {
+    pub contract: EVMAddress,
+    pub function: String,
+    pub args: Vec<SetupArg>,
+    pub value: EVMU256,
+}
+
+impl SetupCall {
+    /// Calldata of the call made by `sender`
+    pub fn calldata(&self, sender: EVMAddress) -> Bytes {
+        let mut data = vec![0u8; 4];
+        set_hash(&self.function, &mut data);
+        for arg in &self.args {
+            match arg {
+                SetupArg::Sender => {
+                    data.extend([0; 12]);
+                    data.extend(sender.0);
+                }
+                SetupArg::Word(word) => data.extend(word.to_be_bytes::<32>()),
+            }
+        }
+        Bytes::from(data)
+    }
+}
+
+/// Pre-state, resolved
+#[derive(Clone, Debug, Default)]
+pub struct PreState {
+    /// Senders onboarded (all callers if empty)
+    pub senders: Vec<EVMAddress>,
+    /// Approvals followed by positions
+    pub calls: Vec<SetupCall>,
+}
+
+pub fn parse_amount(amount: &str) -> Option<EVMU256> {
+    if amount == "max" {
+        return Some(EVMU256::MAX);
+    }
+    EVMU256::from_str(amount).ok()
+}
+
+pub fn parse_arg(arg: &str, resolve: &dyn Fn(&String) -> EVMAddress) -> SetupArg {
+    match arg {
+        "sender" => SetupArg::Sender,
+        "true" => SetupArg::Word(EVMU256::from(1)),
+        "false" => SetupArg::Word(EVMU256::ZERO),
+        _ if arg.starts_with("0x") && arg.len() == 42 => {
+            let address = resolve(&arg.to_string());
+            SetupArg::Word(EVMU256::from_be_slice(&address.0))
+        }
+        _ => match parse_amount(arg) {
+            Some(word) => SetupArg::Word(word),
+            None => SetupArg::Word(EVMU256::from_be_slice(&resolve(&arg.to_string()).0)),
+        },
+    }
+}
+
+impl PreState {
+    pub fn new(config: &PreStateConfig, resolve: &dyn Fn(&String) -> EVMAddress) -> Self {
+        let mut calls = config
+            .approvals
+            .iter()
+            .map(|approval| SetupCall {
+                contract: resolve(&approval.token),
+                function: "approve(address,uint256)".to_string(),
+                args: vec![
+                    parse_arg(&approval.spender, resolve),
+                    SetupArg::Word(
+                        parse_amount(&approval.amount)
+                            .unwrap_or_else(|| panic!("invalid amount {} approved", approval.amount)),
+                    ),
+                ],
+                value: EVMU256::ZERO,
+            })
+            .collect::<Vec<_>>();
+        calls.extend(config.positions.iter().map(|position| SetupCall {
+            contract: resolve(&position.contract),
+            function: position.function.clone(),
+            args: position.args.iter().map(|arg| parse_arg(arg, resolve)).collect(),
+            value: position.value.as_ref().map_or(EVMU256::ZERO, |value| {
+                parse_amount(value).unwrap_or_else(|| panic!("invalid value {} of {}", value, position.function))
+            }),
+        }));
+        Self {
+            senders: config.senders.iter().map(resolve).collect(),
+            calls,
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calldata() {
+        let config: PreStateConfig = serde_json::from_str(
+            r#"{
+                "approvals": [{"token": "Token", "spender": "Vault"}],
+                "positions": [{"contract": "Vault", "function": "deposit(uint256,address)", "args": ["0x10", "sender"]}]
+            }"#,
+        )
+        .unwrap();
+        let token = EVMAddress::from_str("0x0000000000000000000000000000000000000001").unwrap();
+        let vault = EVMAddress::from_str("0x0000000000000000000000000000000000000002").unwrap();
+        let sender = EVMAddress::from_str("0x0000000000000000000000000000000000000003").unwrap();
+        let pre_state = PreState::new(&config, &|name| if name == "Token" { token } else { vault });
+
+        assert_eq!(pre_state.calls[0].contract, token);
+        let approve = pre_state.calls[0].calldata(sender);
+        // approve(address,uint256)
+        assert_eq!(approve[..4], [0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(approve[16..36], vault.0);
+        assert_eq!(approve[36..], [0xff; 32]);
+
+        let deposit = pre_state.calls[1].calldata(sender);
+        assert_eq!(deposit.len(), 68);
+        assert_eq!(deposit[35], 0x10);
+        assert_eq!(deposit[48..68], sender.0);
+    }
+}
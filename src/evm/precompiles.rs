@@ -0,0 +1,95 @@
+/// User-defined precompiles (e.g., zk verifiers, oracle precompiles of appchains)
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Handler of a custom precompile, returns None if the precompile call fails (reverts)
+pub trait CustomPrecompile {
+    fn call(&mut self, input: &[u8]) -> Option<Vec<u8>>;
+}
+
+impl<F> CustomPrecompile for F
+where
+    F: FnMut(&[u8]) -> Option<Vec<u8>>,
+{
+    fn call(&mut self, input: &[u8]) -> Option<Vec<u8>> {
+        self(input)
+    }
+}
+
+pub type PrecompileHandler = Rc<RefCell<dyn CustomPrecompile>>;
+
+/// Precompile implemented as a WASM module.
+///
+/// The module needs to export `memory`, `alloc(len: i32) -> i32` and
+/// `precompile(ptr: i32, len: i32) -> i64`. The return value of `precompile` is
+/// `(out_ptr << 32) | out_len`, or negative if the call fails.
+#[cfg(feature = "wasm_plugins")]
+pub struct WasmPrecompile {
+    store: wasmi::Store<()>,
+    memory: wasmi::Memory,
+    alloc: wasmi::TypedFunc<i32, i32>,
+    precompile: wasmi::TypedFunc<(i32, i32), i64>,
+}
+
+#[cfg(feature = "wasm_plugins")]
+impl WasmPrecompile {
+    pub fn from_file(path: &str) -> Self {
+        let wasm = std::fs::read(path).unwrap_or_else(|_| panic!("wasm file {} not found", path));
+        let engine = wasmi::Engine::default();
+        let module = wasmi::Module::new(&engine, &wasm[..]).expect("failed to load wasm module");
+        let mut store = wasmi::Store::new(&engine, ());
+        let linker = <wasmi::Linker<()>>::new(&engine);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|x| x.start(&mut store))
+            .expect("failed to instantiate wasm module");
+        Self {
+            memory: instance
+                .get_memory(&store, "memory")
+                .expect("wasm module does not export memory"),
+            alloc: instance
+                .get_typed_func::<i32, i32>(&store, "alloc")
+                .expect("wasm module does not export alloc"),
+            precompile: instance
+                .get_typed_func::<(i32, i32), i64>(&store, "precompile")
+                .expect("wasm module does not export precompile"),
+            store,
+        }
+    }
+}
+
+#[cfg(feature = "wasm_plugins")]
+impl CustomPrecompile for WasmPrecompile {
+    fn call(&mut self, input: &[u8]) -> Option<Vec<u8>> {
+        let ptr = self.alloc.call(&mut self.store, input.len() as i32).ok()?;
+        self.memory.write(&mut self.store, ptr as usize, input).ok()?;
+        let ret = self
+            .precompile
+            .call(&mut self.store, (ptr, input.len() as i32))
+            .ok()?;
+        if ret < 0 {
+            return None;
+        }
+        let (out_ptr, out_len) = ((ret >> 32) as usize, (ret & 0xffffffff) as usize);
+        let mut out = vec![0; out_len];
+        self.memory.read(&self.store, out_ptr, &mut out).ok()?;
+        Some(out)
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closure_precompile() {
+        let handler: PrecompileHandler = Rc::new(RefCell::new(|input: &[u8]| {
+            if input.is_empty() {
+                None
+            } else {
+                Some(input.iter().rev().cloned().collect())
+            }
+        }));
+        assert_eq!(handler.borrow_mut().call(&[1, 2, 3]), Some(vec![3, 2, 1]));
+        assert_eq!(handler.borrow_mut().call(&[]), None);
+    }
+}
@@ -0,0 +1,302 @@
+/// Valuation of receipt tokens in the flashloan profit accounting
+///
+/// Exploits often extract value as tokens representing a claim on other tokens: LP tokens of
+/// Uniswap V2 style pairs, Uniswap V3 position NFTs, and aToken / cToken style lending receipts.
+/// Such tokens have no DEX route of their own, so their balance changes are translated into
+/// changes of the underlying tokens, valued through their pools: an LP token is its share of both
+/// reserves, a V3 position the amounts its liquidity represents at the current price of its pool
+/// (plus the fees owed), an aToken its underlying 1:1 and a cToken its underlying at the exchange
+/// rate. Receipts are valued on the states before and after the transaction, so manipulating the
+/// value of a receipt (e.g., donating to the pair) is accounted for as well.
+use std::collections::HashSet;
+
+use bytes::Bytes;
+
+use crate::evm::contract_utils::set_hash;
+use crate::evm::types::{convert_u256_to_h160, EVMAddress, EVMOracleCtx, EVMU256};
+
+/// Maximum number of V3 positions of an owner valued
+const MAX_POSITIONS: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Receipt {
+    /// Uniswap V2 style pair token
+    Lp,
+    /// Uniswap V3 style NonfungiblePositionManager
+    V3Positions,
+    /// Aave style aToken, rebasing 1:1 with its underlying
+    AToken,
+    /// Compound style cToken, redeemable at the exchange rate (scaled by 1e18)
+    CToken,
+}
+
+fn selector(signature: &str) -> Vec<u8> {
+    let mut function = [0u8; 4];
+    set_hash(signature, &mut function);
+    function.to_vec()
+}
+
+fn words(out: &[u8]) -> Vec<EVMU256> {
+    out.chunks(32)
+        .filter(|w| w.len() == 32)
+        .map(EVMU256::from_be_slice)
+        .collect()
+}
+
+/// Signed 24 bits integer (sign extended to 256 bits in the ABI)
+fn int24(word: EVMU256) -> i32 {
+    let bytes = word.to_be_bytes::<32>();
+    i32::from_be_bytes([bytes[28], bytes[29], bytes[30], bytes[31]])
+}
+
+fn address_arg(address: EVMAddress) -> Vec<u8> {
+    let mut arg = vec![0; 12];
+    arg.extend_from_slice(address.0.as_slice());
+    arg
+}
+
+fn to_u256(amount: f64) -> EVMU256 {
+    if amount.is_finite() && amount > 0.0 {
+        EVMU256::from(amount as u128)
+    } else {
+        EVMU256::ZERO
+    }
+}
+
+/// Amounts of token0 / token1 represented by `liquidity` between two ticks at the current price
+pub fn position_amounts(liquidity: u128, sqrt_price_x96: EVMU256, tick_lower: i32, tick_upper: i32) -> (f64, f64) {
+    let liquidity = liquidity as f64;
+    let price = u128::try_from(sqrt_price_x96 >> 48).unwrap_or(u128::MAX) as f64 / 2f64.powi(48);
+    let lower = 1.0001f64.powf(tick_lower as f64 / 2.0);
+    let upper = 1.0001f64.powf(tick_upper as f64 / 2.0);
+    if price <= lower {
+        (liquidity * (upper - lower) / (lower * upper), 0.0)
+    } else if price >= upper {
+        (0.0, liquidity * (upper - lower))
+    } else {
+        (liquidity * (upper - price) / (price * upper), liquidity * (price - lower))
+    }
+}
+
+impl Receipt {
+    /// Kind of receipt of a contract, from the names of its functions
+    pub fn detect(abi_names: &HashSet<String>) -> Option<Self> {
+        let has = |names: &[&str]| names.iter().all(|name| abi_names.contains(*name));
+        if has(&["positions", "ownerOf", "decreaseLiquidity", "collect"]) {
+            Some(Self::V3Positions)
+        } else if has(&["getReserves", "token0", "token1", "totalSupply"]) {
+            Some(Self::Lp)
+        } else if has(&["UNDERLYING_ASSET_ADDRESS"]) {
+            Some(Self::AToken)
+        } else if has(&["exchangeRateStored", "underlying"]) {
+            Some(Self::CToken)
+        } else {
+            None
+        }
+    }
+
+    fn call(ctx: &mut EVMOracleCtx<'_>, pre: bool, to: EVMAddress, data: Vec<u8>) -> Vec<EVMU256> {
+        let batch = vec![(to, Bytes::from(data))];
+        let out = if pre {
+            ctx.call_pre_batch(&batch)
+        } else {
+            ctx.call_post_batch(&batch)
+        };
+        out.first().map(|out| words(out)).unwrap_or_default()
+    }
+
+    fn call_address(ctx: &mut EVMOracleCtx<'_>, to: EVMAddress, signature: &str) -> Option<EVMAddress> {
+        Self::call(ctx, false, to, selector(signature))
+            .first()
+            .map(|word| convert_u256_to_h160(*word))
+    }
+
+    /// Underlying tokens of an LP position of `balance` liquidity tokens
+    fn lp_underlying(ctx: &mut EVMOracleCtx<'_>, pair: EVMAddress, pre: bool, balance: EVMU256) -> Vec<EVMU256> {
+        let reserves = Self::call(ctx, pre, pair, selector("getReserves()"));
+        let supply = Self::call(ctx, pre, pair, selector("totalSupply()"));
+        match (reserves.get(0), reserves.get(1), supply.first()) {
+            (Some(r0), Some(r1), Some(supply)) if *supply > EVMU256::ZERO => vec![
+                balance.saturating_mul(*r0) / *supply,
+                balance.saturating_mul(*r1) / *supply,
+            ],
+            _ => vec![EVMU256::ZERO, EVMU256::ZERO],
+        }
+    }
+
+    /// Underlying amounts of the V3 positions of `owner`, token => amount
+    fn v3_underlying(
+        ctx: &mut EVMOracleCtx<'_>,
+        manager: EVMAddress,
+        pre: bool,
+        owner: EVMAddress,
+    ) -> Vec<(EVMAddress, EVMU256)> {
+        let count = match Self::call(ctx, pre, manager, [selector("balanceOf(address)"), address_arg(owner)].concat()).first() {
+            Some(count) => usize::try_from(*count).unwrap_or(MAX_POSITIONS).min(MAX_POSITIONS),
+            None => return vec![],
+        };
+        let factory = match Self::call_address(ctx, manager, "factory()") {
+            Some(factory) => factory,
+            None => return vec![],
+        };
+        let mut amounts = vec![];
+        for index in 0..count {
+            let id = match Self::call(
+                ctx,
+                pre,
+                manager,
+                [
+                    selector("tokenOfOwnerByIndex(address,uint256)"),
+                    address_arg(owner),
+                    EVMU256::from(index).to_be_bytes::<32>().to_vec(),
+                ]
+                .concat(),
+            )
+            .first()
+            {
+                Some(id) => *id,
+                None => continue,
+            };
+            // nonce, operator, token0, token1, fee, tickLower, tickUpper, liquidity,
+            // feeGrowthInside0LastX128, feeGrowthInside1LastX128, tokensOwed0, tokensOwed1
+            let position = Self::call(
+                ctx,
+                pre,
+                manager,
+                [selector("positions(uint256)"), id.to_be_bytes::<32>().to_vec()].concat(),
+            );
+            if position.len() < 12 {
+                continue;
+            }
+            let (token0, token1) = (convert_u256_to_h160(position[2]), convert_u256_to_h160(position[3]));
+            let pool = Self::call(
+                ctx,
+                pre,
+                factory,
+                [
+                    selector("getPool(address,address,uint24)"),
+                    address_arg(token0),
+                    address_arg(token1),
+                    position[4].to_be_bytes::<32>().to_vec(),
+                ]
+                .concat(),
+            );
+            let sqrt_price = match pool.first() {
+                Some(pool) => Self::call(ctx, pre, convert_u256_to_h160(*pool), selector("slot0()")),
+                None => continue,
+            };
+            let (amount0, amount1) = match sqrt_price.first() {
+                Some(sqrt_price) => position_amounts(
+                    u128::try_from(position[7]).unwrap_or(u128::MAX),
+                    *sqrt_price,
+                    int24(position[5]),
+                    int24(position[6]),
+                ),
+                None => continue,
+            };
+            amounts.push((token0, to_u256(amount0).saturating_add(position[10])));
+            amounts.push((token1, to_u256(amount1).saturating_add(position[11])));
+        }
+        amounts
+    }
+
+    /// Translate the balance change of `owner` in the receipt token into changes of the
+    /// underlying tokens: (token, prev_balance, new_balance)
+    pub fn underlying_changes(
+        &self,
+        ctx: &mut EVMOracleCtx<'_>,
+        receipt: EVMAddress,
+        owner: EVMAddress,
+        prev_balance: EVMU256,
+        new_balance: EVMU256,
+    ) -> Vec<(EVMAddress, EVMU256, EVMU256)> {
+        match self {
+            Self::Lp => {
+                let tokens = [
+                    Self::call_address(ctx, receipt, "token0()"),
+                    Self::call_address(ctx, receipt, "token1()"),
+                ];
+                let prev = Self::lp_underlying(ctx, receipt, true, prev_balance);
+                let new = Self::lp_underlying(ctx, receipt, false, new_balance);
+                tokens
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, token)| Some((*token.as_ref()?, prev[i], new[i])))
+                    .collect()
+            }
+            Self::V3Positions => {
+                let prev = Self::v3_underlying(ctx, receipt, true, owner);
+                let new = Self::v3_underlying(ctx, receipt, false, owner);
+                let tokens = prev.iter().chain(new.iter()).map(|(token, _)| *token).collect::<HashSet<_>>();
+                let total = |amounts: &Vec<(EVMAddress, EVMU256)>, token: EVMAddress| {
+                    amounts
+                        .iter()
+                        .filter(|(t, _)| *t == token)
+                        .fold(EVMU256::ZERO, |acc, (_, amount)| acc.saturating_add(*amount))
+                };
+                tokens
+                    .into_iter()
+                    .map(|token| (token, total(&prev, token), total(&new, token)))
+                    .collect()
+            }
+            Self::AToken => match Self::call_address(ctx, receipt, "UNDERLYING_ASSET_ADDRESS()") {
+                Some(underlying) => vec![(underlying, prev_balance, new_balance)],
+                None => vec![],
+            },
+            Self::CToken => {
+                let underlying = match Self::call_address(ctx, receipt, "underlying()") {
+                    Some(underlying) => underlying,
+                    None => return vec![],
+                };
+                let scale = EVMU256::from(1_000_000_000_000_000_000u128);
+                let prev_rate = Self::call(ctx, true, receipt, selector("exchangeRateStored()"));
+                let new_rate = Self::call(ctx, false, receipt, selector("exchangeRateStored()"));
+                match (prev_rate.first(), new_rate.first()) {
+                    (Some(prev_rate), Some(new_rate)) => vec![(
+                        underlying,
+                        prev_balance.saturating_mul(*prev_rate) / scale,
+                        new_balance.saturating_mul(*new_rate) / scale,
+                    )],
+                    _ => vec![],
+                }
+            }
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<HashSet<_>>();
+        assert_eq!(
+            Receipt::detect(&names(&["getReserves", "token0", "token1", "totalSupply", "swap"])),
+            Some(Receipt::Lp)
+        );
+        assert_eq!(
+            Receipt::detect(&names(&["positions", "ownerOf", "decreaseLiquidity", "collect", "balanceOf"])),
+            Some(Receipt::V3Positions)
+        );
+        assert_eq!(Receipt::detect(&names(&["exchangeRateStored", "underlying", "mint"])), Some(Receipt::CToken));
+        assert_eq!(Receipt::detect(&names(&["balanceOf", "transfer"])), None);
+    }
+
+    #[test]
+    fn test_position_amounts() {
+        // price 1 (tick 0), position in [-100, 100]: both tokens in equal amounts
+        let one = EVMU256::from(1u8) << 96;
+        let (amount0, amount1) = position_amounts(1_000_000, one, -100, 100);
+        assert!(amount0 > 0.0 && (amount0 - amount1).abs() / amount1 < 1e-3);
+
+        // price above the range: only token1
+        let (amount0, amount1) = position_amounts(1_000_000, one, -200, -100);
+        assert_eq!(amount0, 0.0);
+        assert!(amount1 > 0.0);
+
+        // price below the range: only token0
+        let (amount0, amount1) = position_amounts(1_000_000, one, 100, 200);
+        assert!(amount0 > 0.0);
+        assert_eq!(amount1, 0.0);
+    }
+}
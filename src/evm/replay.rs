@@ -0,0 +1,235 @@
+/// Re-execution of sequences in the replayable format
+///
+/// Sequences are replayed inside the fuzz loop (e.g., when a solution is reported), so
+/// replaying must not leak into the campaign: the executor's state, the execution result
+/// and the coverage maps are restored afterwards.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::evm::host::{CALL_UNTIL, CMP_MAP, JMP_MAP, READ_MAP, WRITE_MAP};
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::call_tracer::{CallTracer, TxTrace};
+use crate::evm::types::{EVMExecutionResult, EVMFuzzState, EVMStagedVMState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+use crate::generic_vm::vm_executor::{GenericVM, MAP_SIZE};
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::ConciseSerde;
+use crate::state::HasExecutionResult;
+
+type Executor = EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>;
+
+/// A transaction of a replayed sequence
+pub struct ReplayedTx {
+    pub concise: ConciseEVMInput,
+    /// State the transaction is executed on
+    pub pre_state: EVMStagedVMState,
+    pub result: EVMExecutionResult,
+}
+
+impl ReplayedTx {
+    /// State after the transaction, a reverted transaction leaves the state untouched
+    pub fn post_state(&self) -> &EVMStagedVMState {
+        if self.result.reverted {
+            &self.pre_state
+        } else {
+            &self.result.new_state
+        }
+    }
+}
+
+/// What replaying overwrites
+struct Snapshot {
+    evmstate: EVMState,
+    execution_result: EVMExecutionResult,
+    jmp_map: [u8; MAP_SIZE],
+    cmp_map: [EVMU256; MAP_SIZE],
+    read_map: [bool; MAP_SIZE],
+    write_map: [u8; MAP_SIZE],
+}
+
+impl Snapshot {
+    fn take(executor: &Executor, state: &EVMFuzzState) -> Self {
+        unsafe {
+            Self {
+                evmstate: executor.host.evmstate.clone(),
+                execution_result: state.get_execution_result_cloned(),
+                jmp_map: JMP_MAP,
+                cmp_map: CMP_MAP,
+                read_map: READ_MAP,
+                write_map: WRITE_MAP,
+            }
+        }
+    }
+
+    fn restore(self, executor: &mut Executor, state: &mut EVMFuzzState) {
+        executor.host.evmstate = self.evmstate;
+        state.set_execution_result(self.execution_result);
+        unsafe {
+            JMP_MAP = self.jmp_map;
+            CMP_MAP = self.cmp_map;
+            READ_MAP = self.read_map;
+            WRITE_MAP = self.write_map;
+        }
+    }
+}
+
+/// Parse a sequence in the replayable format
+pub fn parse_sequence(txs: &str) -> Vec<ConciseEVMInput> {
+    txs.split('\n')
+        .filter(|txn| txn.len() >= 4)
+        .map(|txn| ConciseEVMInput::deserialize_concise(txn.as_bytes()))
+        .collect()
+}
+
+/// Replay a sequence from `initial_state`, `on_tx` is called after each transaction. Returns
+/// the final state.
+pub fn replay<F>(
+    executor: &mut Executor,
+    state: &mut EVMFuzzState,
+    initial_state: &EVMStagedVMState,
+    txs: &str,
+    mut on_tx: F,
+) -> EVMStagedVMState
+where
+    F: FnMut(&mut Executor, &ReplayedTx),
+{
+    let snapshot = Snapshot::take(executor, state);
+    let mut vm_state = initial_state.clone();
+    for concise in parse_sequence(txs) {
+        let (input, call_until) = concise.to_input(vm_state.clone());
+        unsafe {
+            CALL_UNTIL = call_until;
+        }
+        let result = executor.execute(&input, state);
+        let tx = ReplayedTx {
+            concise,
+            pre_state: vm_state,
+            result,
+        };
+        on_tx(executor, &tx);
+        vm_state = tx.post_state().clone();
+    }
+    unsafe {
+        CALL_UNTIL = u32::MAX;
+    }
+    snapshot.restore(executor, state);
+    vm_state
+}
+
+/// A violating sequence replayed with the call tracer attached
+pub struct SolutionReplay {
+    pub txs: Vec<ReplayedTx>,
+    /// Trace of each transaction
+    pub traces: Vec<TxTrace>,
+    /// SHA3 preimages observed during the sequence
+    pub preimages: HashMap<EVMU256, Vec<u8>>,
+    /// Environment values beyond the bounds
+    pub env_violations: Vec<String>,
+    pub final_state: EVMStagedVMState,
+}
+
+impl SolutionReplay {
+    pub fn new(executor: &mut Executor, state: &mut EVMFuzzState, initial_state: &EVMStagedVMState, txs: &str) -> Self {
+        let tracer = Rc::new(RefCell::new(CallTracer::new()));
+        executor.host.add_middlewares(tracer.clone());
+        let (mut replayed, mut traces, mut env_violations) = (vec![], vec![], vec![]);
+        let final_state = replay(executor, state, initial_state, txs, |executor, tx| {
+            traces.push(TxTrace {
+                tx: tx.concise.serialize_string(),
+                reverted: tx.result.reverted,
+                output: hex::encode(&tx.result.output),
+                steps: tracer.borrow_mut().take(),
+            });
+            if let Some(bounds) = &executor.host.env_bounds {
+                env_violations.extend(bounds.violations(&executor.host.env));
+            }
+            replayed.push(ReplayedTx {
+                concise: tx.concise.clone(),
+                pre_state: tx.pre_state.clone(),
+                result: tx.result.clone(),
+            });
+        });
+        executor.host.remove_middlewares(tracer.clone());
+        let preimages = std::mem::take(&mut tracer.borrow_mut().preimages);
+        Self {
+            txs: replayed,
+            traces,
+            preimages,
+            env_violations,
+            final_state,
+        }
+    }
+
+    /// Hash of the state after each transaction, None if the transaction reverted
+    pub fn outcomes(&self) -> Vec<Option<u64>> {
+        self.txs
+            .iter()
+            .map(|tx| (!tx.result.reverted).then(|| tx.result.new_state.state.get_hash()))
+            .collect()
+    }
+}
+
+/// Replays each violating sequence once, the solution hooks share the replay
+pub struct SolutionReplayer {
+    executor: Rc<RefCell<Executor>>,
+    initial_state: EVMStagedVMState,
+    last: Option<(String, Rc<SolutionReplay>)>,
+}
+
+impl SolutionReplayer {
+    pub fn new(executor: Rc<RefCell<Executor>>, initial_state: EVMStagedVMState) -> Self {
+        Self {
+            executor,
+            initial_state,
+            last: None,
+        }
+    }
+
+    pub fn replay(&mut self, state: &mut EVMFuzzState, txs: &str) -> Rc<SolutionReplay> {
+        match &self.last {
+            Some((last_txs, replay)) if last_txs == txs => replay.clone(),
+            _ => {
+                let replay = Rc::new(SolutionReplay::new(
+                    &mut self.executor.borrow_mut(),
+                    state,
+                    &self.initial_state,
+                    txs,
+                ));
+                self.last = Some((txs.to_string(), replay.clone()));
+                replay
+            }
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::EVMAddress;
+
+    #[test]
+    fn test_parse_sequence() {
+        assert!(parse_sequence("").is_empty());
+        assert!(parse_sequence("\n\n  \n").is_empty());
+    }
+
+    #[test]
+    fn test_post_state() {
+        let address = EVMAddress::zero();
+        let mut written = EVMState::new();
+        written.insert(address, HashMap::from([(EVMU256::ZERO, EVMU256::from(1))]));
+        let mut tx = ReplayedTx {
+            concise: ConciseEVMInput::default(),
+            pre_state: EVMStagedVMState::new_with_state(EVMState::new()),
+            result: EVMExecutionResult {
+                reverted: true,
+                new_state: EVMStagedVMState::new_with_state(written),
+                ..EVMExecutionResult::empty_result()
+            },
+        };
+        // a reverted transaction leaves the state untouched
+        assert!(tx.post_state().state.get(&address).is_none());
+        tx.result.reverted = false;
+        assert!(tx.post_state().state.get(&address).is_some());
+    }
+}
@@ -0,0 +1,139 @@
+/// Adaptive sequence lengths
+///
+/// Deeply stateful protocols need long transaction sequences while simple tokens waste time on
+/// them. For each target function, the executions of the function are counted by the length of
+/// the sequence they end (bucketed by powers of two), along with those finding new coverage.
+/// When a state is picked for an input, a length bucket is chosen by its rate of new coverage
+/// for the function, and the infant state scheduler is sampled for a state built by a sequence
+/// of that length. Once coverage plateaus, the counts are halved so that the distribution adapts
+/// to the new plateau.
+use std::collections::HashMap;
+
+use crate::evm::types::EVMAddress;
+
+/// Length buckets: 1, 2, 3-4, 5-8, 9-16, 17+
+pub const BUCKETS: usize = 6;
+/// Executions without new coverage starting a new plateau
+const PLATEAU_EXECUTIONS: u64 = 20000;
+
+/// Whether infant states are picked by the lengths yielding coverage (`--adaptive-length`)
+pub static mut ADAPTIVE_LENGTH: bool = false;
+pub static mut SEQUENCE_LENGTHS: Option<SequenceLengths> = None;
+
+/// Bucket of a sequence length
+pub fn bucket(len: usize) -> usize {
+    let mut bucket = 0;
+    while bucket + 1 < BUCKETS && len > 1 << bucket {
+        bucket += 1;
+    }
+    bucket
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BucketStats {
+    pub executions: u64,
+    pub found: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SequenceLengths {
+    /// Function (contract, selector) => stats by length bucket
+    pub functions: HashMap<(EVMAddress, [u8; 4]), [BucketStats; BUCKETS]>,
+    /// Executions since coverage was last found
+    pub stalled: u64,
+    /// Plateaus reached so far
+    pub plateaus: usize,
+}
+
+impl SequenceLengths {
+    pub fn record(&mut self, contract: EVMAddress, selector: [u8; 4], len: usize, found: bool) {
+        let stats = &mut self.functions.entry((contract, selector)).or_default()[bucket(len)];
+        stats.executions += 1;
+        if found {
+            stats.found += 1;
+            self.stalled = 0;
+        } else {
+            self.stalled += 1;
+        }
+        if self.stalled >= PLATEAU_EXECUTIONS {
+            self.stalled = 0;
+            self.plateaus += 1;
+            for stats in self.functions.values_mut().flat_map(|buckets| buckets.iter_mut()) {
+                stats.executions /= 2;
+                stats.found /= 2;
+            }
+        }
+    }
+
+    /// Length bucket to pick a state from for a function, weighted by the rate of new coverage
+    /// (`r` is random)
+    pub fn choose(&self, contract: EVMAddress, selector: [u8; 4], r: u64) -> usize {
+        let weights = match self.functions.get(&(contract, selector)) {
+            // Laplace smoothing, untried lengths start at 50%
+            Some(buckets) => buckets.map(|stats| (stats.found + 1) * 1000 / (stats.executions + 2)),
+            None => [1; BUCKETS],
+        };
+        let mut point = r % weights.iter().sum::<u64>();
+        for (bucket, weight) in weights.iter().enumerate() {
+            if point < *weight {
+                return bucket;
+            }
+            point -= weight;
+        }
+        BUCKETS - 1
+    }
+}
+
+fn sequence_lengths() -> &'static mut SequenceLengths {
+    unsafe { SEQUENCE_LENGTHS.get_or_insert_with(Default::default) }
+}
+
+/// Record whether a transaction ending a sequence of `len` transactions found new coverage
+pub fn record_length(contract: EVMAddress, data: &[u8], len: usize, found: bool) {
+    if !unsafe { ADAPTIVE_LENGTH } || data.len() < 4 {
+        return;
+    }
+    sequence_lengths().record(contract, [data[0], data[1], data[2], data[3]], len, found);
+}
+
+/// Length bucket of the sequence to extend for a function, None if not adaptive (`r` is random)
+pub fn preferred_bucket(contract: EVMAddress, selector: [u8; 4], r: u64) -> Option<usize> {
+    if !unsafe { ADAPTIVE_LENGTH } {
+        return None;
+    }
+    Some(sequence_lengths().choose(contract, selector, r))
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_lengths() {
+        assert_eq!((1..=20).map(bucket).collect::<Vec<_>>(), vec![0, 1, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 5, 5, 5, 5]);
+
+        let (token, vault) = (EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20]));
+        let selector = [0xa9, 0x05, 0x9c, 0xbb];
+        let mut lengths = SequenceLengths::default();
+        for i in 0..200 {
+            // the token is covered by short sequences, the vault by long ones
+            lengths.record(token, selector, 1, i % 4 == 0);
+            lengths.record(token, selector, 12, false);
+            lengths.record(vault, selector, 1, false);
+            lengths.record(vault, selector, 12, i % 4 == 0);
+        }
+        let picks = |contract| {
+            let mut picks = [0; BUCKETS];
+            (0..1000u64).for_each(|r| picks[lengths.choose(contract, selector, r * 7919)] += 1);
+            picks
+        };
+        assert!(picks(token)[0] > picks(token)[4] * 10);
+        assert!(picks(vault)[4] > picks(vault)[0] * 10);
+
+        // coverage plateaus, the counts decay
+        for _ in 0..PLATEAU_EXECUTIONS {
+            lengths.record(token, selector, 1, false);
+        }
+        assert_eq!(lengths.plateaus, 1);
+        assert!(lengths.functions[&(vault, selector)][4].executions <= 100);
+    }
+}
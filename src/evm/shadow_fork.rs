@@ -0,0 +1,271 @@
+/// Shadow-fork validation of findings in onchain mode (`--shadow-fork`)
+///
+/// Violating sequences are replayed on a fresh fork of the chain at the block being fuzzed, run
+/// by an external node (anvil), and only reported if every transaction behaves as in ItyFuzz:
+/// same success and return data. Findings that don't reproduce are artifacts of the
+/// approximations of the fuzzing host (e.g., unlimited balances, middlewares, storage fetched
+/// lazily) and are set aside as unconfirmed. Contracts deployed by ItyFuzz are copied onto the
+/// fork with their initial storage, callers are funded and impersonated, and timestamps are
+/// moved forward as in the sequence (block numbers are not).
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::mempool::request;
+use crate::evm::replay::SolutionReplay;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+
+/// Time given to the node to fork the chain
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Balance of the callers on the fork
+const BALANCE: &str = "0x100000000000000000000000000000000000000000000000000";
+const GAS: &str = "0x1c9c380";
+
+fn hex_address(address: &EVMAddress) -> String {
+    format!("0x{}", hex::encode(address.0))
+}
+
+fn hex_word(v: &EVMU256) -> String {
+    format!("0x{}", hex::encode(v.to_be_bytes::<32>()))
+}
+
+/// Whether a transaction (followed by `next`) can be sent outside ItyFuzz: reentrant
+/// transactions and the intermediary contract only exist in ItyFuzz
+fn replayable(tx: &ConciseEVMInput, next: Option<&ConciseEVMInput>) -> bool {
+    let leaked = next.map_or(false, |next| next.step);
+    !(tx.step || tx.layer > 0 || leaked || tx.via_intermediary)
+}
+
+/// Report line of the `idx`-th transaction sent to the fork, and whether it behaves as in
+/// ItyFuzz (`reverted`, returning `output`)
+fn compare(idx: usize, reverted: bool, output: &[u8], result: Result<(bool, Vec<u8>), String>) -> (String, bool) {
+    match result {
+        Ok((success, _)) if success == reverted => (
+            format!(
+                "[{}] divergence: ItyFuzz {} while the fork {}",
+                idx,
+                if reverted { "reverts" } else { "succeeds" },
+                if success { "succeeds" } else { "reverts" }
+            ),
+            false,
+        ),
+        Ok((true, fork_output)) if fork_output != output => (
+            format!(
+                "[{}] divergence: ItyFuzz returns 0x{} while the fork returns 0x{}",
+                idx,
+                hex::encode(output),
+                hex::encode(&fork_output)
+            ),
+            false,
+        ),
+        Ok(_) => (format!("[{}] ok", idx), true),
+        Err(e) => (format!("[{}] error: {}", idx, e), false),
+    }
+}
+
+/// A node forking the chain, killed when dropped
+struct ForkNode {
+    child: Child,
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl ForkNode {
+    fn spawn(node: &str, rpc: &str, block: &str) -> Result<Self, String> {
+        // a free port, released right before the node binds it
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map_err(|e| format!("no free port: {}", e))?
+            .port();
+        let mut command = Command::new(node);
+        command
+            .args(["--fork-url", rpc, "--port", &port.to_string(), "--auto-impersonate", "--silent"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if block != "latest" {
+            let number = u64::from_str_radix(block.trim_start_matches("0x"), 16).map_err(|e| e.to_string())?;
+            command.args(["--fork-block-number", &number.to_string()]);
+        }
+        let child = command.spawn().map_err(|e| format!("failed to run {}: {}", node, e))?;
+        let fork = Self {
+            child,
+            url: format!("http://127.0.0.1:{}", port),
+            client: reqwest::blocking::Client::new(),
+        };
+        let start = Instant::now();
+        while fork.call("eth_blockNumber", json!([])).is_none() {
+            if start.elapsed() > STARTUP_TIMEOUT {
+                return Err(format!("{} did not start in {:?}", node, STARTUP_TIMEOUT));
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        Ok(fork)
+    }
+
+    fn call(&self, method: &str, params: Value) -> Option<Value> {
+        request(&self.client, &self.url, method, params)
+    }
+
+    /// Copy a contract deployed by ItyFuzz (absent from the chain) onto the fork
+    fn copy_contract(&self, address: &EVMAddress, code: &[u8], state: &EVMState) {
+        let hex = hex_address(address);
+        let on_chain = self
+            .call("eth_getCode", json!([hex, "latest"]))
+            .map_or(true, |code| code.as_str().map_or(true, |code| code.len() > 2));
+        if on_chain {
+            return;
+        }
+        self.call("anvil_setCode", json!([hex, format!("0x{}", hex::encode(code))]));
+        if let Some(storage) = state.get(address) {
+            for (slot, value) in storage {
+                self.call("anvil_setStorageAt", json!([hex, hex_word(slot), hex_word(value)]));
+            }
+        }
+    }
+
+    /// Success and return data of a transaction sent to the fork
+    fn send(&self, tx: &ConciseEVMInput) -> Result<(bool, Vec<u8>), String> {
+        let data = tx.data.as_ref().map(|d| d.get_bytes()).unwrap_or_default();
+        let params = json!({
+            "from": hex_address(&tx.caller),
+            "to": hex_address(&tx.contract),
+            "data": format!("0x{}", hex::encode(data)),
+            "value": format!("{:#x}", tx.txn_value.unwrap_or_default()),
+            "gas": GAS,
+        });
+        // the return data is only available from a call on the same state
+        let output = self
+            .call("eth_call", json!([params, "latest"]))
+            .and_then(|out| out.as_str().map(|out| hex::decode(out.trim_start_matches("0x")).unwrap_or_default()))
+            .unwrap_or_default();
+        let hash = self
+            .call("eth_sendTransaction", json!([params]))
+            .ok_or("eth_sendTransaction failed")?;
+        let receipt = self
+            .call("eth_getTransactionReceipt", json!([hash]))
+            .ok_or("no receipt")?;
+        Ok((receipt["status"].as_str() == Some("0x1"), output))
+    }
+}
+
+impl Drop for ForkNode {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct ShadowFork {
+    /// Path to the node forking the chain (anvil)
+    pub node: String,
+    pub rpc: String,
+    /// Block fuzzed (hex), or `latest`
+    pub block: String,
+}
+
+impl ShadowFork {
+    pub fn new(node: &str, rpc: &str, block: &str) -> Self {
+        Self {
+            node: node.to_string(),
+            rpc: rpc.to_string(),
+            block: block.to_string(),
+        }
+    }
+
+    /// Replay a violating sequence replayed on ItyFuzz on a fresh fork, returns the report (a line
+    /// per transaction) and whether the sequence reproduces
+    pub fn validate(
+        &self,
+        executor: &EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+        replay: &SolutionReplay,
+    ) -> (Vec<String>, bool) {
+        let fork = match ForkNode::spawn(&self.node, &self.rpc, &self.block) {
+            Ok(fork) => fork,
+            Err(e) => return (vec![format!("shadow fork unavailable: {}", e)], false),
+        };
+        let mut timestamp = EVMU256::ZERO;
+        let mut report = vec![];
+        let mut reproduced = true;
+        for (idx, tx) in replay.txs.iter().enumerate() {
+            let (concise, res) = (&tx.concise, &tx.result);
+
+            if !replayable(concise, replay.txs.get(idx + 1).map(|next| &next.concise)) {
+                report.push(format!("[{}] can't be replayed outside ItyFuzz (control leak / intermediary)", idx));
+                reproduced = false;
+                break;
+            }
+            if let Some(code) = executor.host.code.get(&concise.contract) {
+                fork.copy_contract(&concise.contract, code.original_bytecode_slice(), &tx.pre_state.state);
+            }
+            fork.call("anvil_setBalance", json!([hex_address(&concise.caller), BALANCE]));
+            if concise.env.block.timestamp > timestamp {
+                timestamp = concise.env.block.timestamp;
+                fork.call("evm_setNextBlockTimestamp", json!([format!("{:#x}", timestamp)]));
+            }
+
+            let mut result = Err("not sent".to_string());
+            for _ in 0..concise.repeat.max(1) {
+                result = fork.send(concise);
+            }
+            let (line, same) = compare(idx, res.reverted, &res.output, result);
+            report.push(line);
+            reproduced &= same;
+        }
+        (report, reproduced)
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex_address(&EVMAddress::from_slice(&[0xab; 20])), format!("0x{}", "ab".repeat(20)));
+        assert_eq!(hex_word(&EVMU256::from(1)), format!("0x{}01", "00".repeat(31)));
+    }
+
+    #[test]
+    fn test_replayable() {
+        let tx = ConciseEVMInput::default();
+        assert!(replayable(&tx, None));
+        let step = ConciseEVMInput {
+            step: true,
+            ..Default::default()
+        };
+        // the transaction leaks control to the next one
+        assert!(!replayable(&tx, Some(&step)));
+        assert!(!replayable(&step, None));
+        let via_intermediary = ConciseEVMInput {
+            via_intermediary: true,
+            ..Default::default()
+        };
+        assert!(!replayable(&via_intermediary, Some(&tx)));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare(0, false, &[1], Ok((true, vec![1]))), ("[0] ok".to_string(), true));
+        // reverting on both sides, the output is not compared
+        assert!(compare(0, true, &[1], Ok((false, vec![]))).1);
+        assert_eq!(
+            compare(1, false, &[], Ok((false, vec![]))),
+            ("[1] divergence: ItyFuzz succeeds while the fork reverts".to_string(), false)
+        );
+        assert_eq!(
+            compare(2, false, &[1], Ok((true, vec![2]))),
+            ("[2] divergence: ItyFuzz returns 0x01 while the fork returns 0x02".to_string(), false)
+        );
+        assert!(!compare(3, false, &[], Err("no receipt".to_string())).1);
+    }
+
+    #[test]
+    fn test_missing_node() {
+        assert!(ForkNode::spawn("/nonexistent/anvil", "http://127.0.0.1:1", "latest").is_err());
+        assert!(ForkNode::spawn("/nonexistent/anvil", "http://127.0.0.1:1", "0xzz").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+/// Model of signatures (EIP-2612 permit, EIP-712 signed messages, ...) verified by ecrecover
+///
+/// The fuzzer cannot sign messages for its callers, so ecrecover is replaced by a model: a signature
+/// never seen before is considered as signed by one of the callers (chosen by the last byte of `r`)
+/// for whatever message it is checked against, i.e., the caller signs the message it is asked to.
+/// The signature is then bound to the message, so that reusing it (observed signatures are reused
+/// by the fuzzer by mutating the transactions using them) only recovers the signer for the same
+/// message, like a real signature. Observed signatures are also replayed by the mutator in other
+/// contexts (as the arguments of another transaction, on another chain id, or from another sender).
+use revm_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+
+use crate::evm::abi::{AArray, BoxedABI, A256};
+use crate::evm::types::{EVMAddress, EVMU256};
+
+/// Address of the ecrecover precompile
+pub const ECRECOVER_ADDRESS: &str = "0000000000000000000000000000000000000001";
+
+/// A signature observed during the execution
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignatureRecord {
+    pub v: u8,
+    pub r: EVMU256,
+    pub s: EVMU256,
+    /// Message the signature is bound to
+    pub hash: EVMU256,
+    pub signer: EVMAddress,
+    /// Chain id and sender of the transaction the signature is first used in
+    pub chain_id: EVMU256,
+    pub caller: EVMAddress,
+    /// Times the signature is successfully recovered
+    pub uses: u32,
+    /// Chain id and sender of the transaction the signature is last used in
+    pub last_chain_id: EVMU256,
+    pub last_caller: EVMAddress,
+}
+
+fn word(input: &[u8], idx: usize) -> [u8; 32] {
+    let mut w = [0; 32];
+    let start = (idx * 32).min(input.len());
+    let end = (idx * 32 + 32).min(input.len());
+    w[..end - start].copy_from_slice(&input[start..end]);
+    w
+}
+
+/// Modeled ecrecover, returns the output of the precompile
+pub fn ecrecover(
+    signers: &Vec<EVMAddress>,
+    records: &mut Vec<SignatureRecord>,
+    input: &[u8],
+    chain_id: EVMU256,
+    caller: EVMAddress,
+) -> Vec<u8> {
+    let (hash, v, r, s) = (word(input, 0), word(input, 1), word(input, 2), word(input, 3));
+    // same validity checks as the precompile
+    if v[..31].iter().any(|b| *b != 0) || (v[31] != 27 && v[31] != 28) || signers.is_empty() {
+        return vec![];
+    }
+    let (v, hash, r, s) = (
+        v[31],
+        EVMU256::from_be_bytes(hash),
+        EVMU256::from_be_bytes(r),
+        EVMU256::from_be_bytes(s),
+    );
+
+    let signer = match records.iter_mut().find(|x| x.v == v && x.r == r && x.s == s) {
+        Some(record) if record.hash == hash => {
+            record.uses += 1;
+            record.last_chain_id = chain_id;
+            record.last_caller = caller;
+            record.signer
+        }
+        // the signature is for another message, recovers to an unrelated address
+        Some(_) => EVMAddress::from_slice(&keccak256(&input[..input.len().min(128)]).0[12..]),
+        None => {
+            let signer = signers[(r.byte(0) as usize) % signers.len()];
+            records.push(SignatureRecord {
+                v,
+                r,
+                s,
+                hash,
+                signer,
+                chain_id,
+                caller,
+                uses: 1,
+                last_chain_id: chain_id,
+                last_caller: caller,
+            });
+            signer
+        }
+    };
+    [vec![0; 12], signer.0.to_vec()].concat()
+}
+
+/// Write the signature into the (v, r, s) arguments of a call (e.g., `permit(owner, spender,
+/// value, deadline, v, r, s)`), returns whether the call has such arguments
+pub fn set_signature_args(abi: &mut BoxedABI, record: &SignatureRecord) -> bool {
+    let args = match abi.b.as_any().downcast_mut::<AArray>() {
+        Some(args) => args,
+        None => return false,
+    };
+    for idx in 0..args.data.len().saturating_sub(2) {
+        let sizes = args.data[idx..idx + 3]
+            .iter_mut()
+            .map(|arg| {
+                arg.b
+                    .as_any()
+                    .downcast_mut::<A256>()
+                    .filter(|arg| !arg.is_address)
+                    .map(|arg| arg.data.len())
+            })
+            .collect::<Vec<_>>();
+        if sizes == [Some(1), Some(32), Some(32)] {
+            let values = [
+                vec![record.v],
+                record.r.to_be_bytes::<32>().to_vec(),
+                record.s.to_be_bytes::<32>().to_vec(),
+            ];
+            for (arg, value) in args.data[idx..idx + 3].iter_mut().zip(values) {
+                arg.b.set_bytes(value);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::abi::get_abi_type_boxed;
+
+    fn input(hash: u8, r: u8) -> Vec<u8> {
+        let mut input = vec![0; 128];
+        input[31] = hash;
+        input[63] = 27;
+        input[95] = r;
+        input[127] = 1;
+        input
+    }
+
+    #[test]
+    fn test_ecrecover() {
+        let signers = vec![EVMAddress::from_slice(&[1; 20]), EVMAddress::from_slice(&[2; 20])];
+        let mut records = vec![];
+        let (chain_id, caller) = (EVMU256::from(1), EVMAddress::zero());
+
+        // fresh signature is signed by signers[r % 2]
+        let out = ecrecover(&signers, &mut records, &input(1, 1), chain_id, caller);
+        assert_eq!(out[12..], signers[1].0);
+        // reused for the same message
+        let out = ecrecover(&signers, &mut records, &input(1, 1), chain_id, caller);
+        assert_eq!(out[12..], signers[1].0);
+        assert_eq!(records[0].uses, 2);
+        // reused for another message
+        let out = ecrecover(&signers, &mut records, &input(2, 1), chain_id, caller);
+        assert_ne!(out[12..], signers[1].0);
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn test_set_signature_args() {
+        let signers = vec![EVMAddress::from_slice(&[1; 20])];
+        let mut records = vec![];
+        ecrecover(&signers, &mut records, &input(1, 7), EVMU256::from(1), EVMAddress::zero());
+
+        let mut permit = get_abi_type_boxed(&"(address,address,uint256,uint256,uint8,bytes32,bytes32)".to_string());
+        assert!(set_signature_args(&mut permit, &records[0]));
+        let bytes = permit.get_bytes_vec();
+        assert_eq!(bytes[4 * 32 + 31], 27);
+        assert_eq!(bytes[5 * 32 + 31], 7);
+        assert_eq!(bytes[6 * 32 + 31], 1);
+
+        let mut transfer = get_abi_type_boxed(&"(address,uint256)".to_string());
+        assert!(!set_signature_args(&mut transfer, &records[0]));
+    }
+}
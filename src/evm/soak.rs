@@ -0,0 +1,337 @@
+/// Soak mode (`--soak`): continuous fuzzing for weeks, as a monitoring daemon
+///
+/// The campaign runs forever (violations don't stop it) and is divided into segments: at the end
+/// of each segment, the findings, plot_data, summaries and coverage reports of the work dir are
+/// moved to `[WORKDIR]/segments/<n>_<unix time>/` (older segments beyond the retention are
+/// deleted), and the campaign statistics restart. Every digest interval, a digest of the progress
+/// since the previous one (executions, new branches, new findings per oracle) is appended to
+/// `[WORKDIR]/soak.log` (rotated by size) and optionally posted to a webhook (Slack incoming
+/// webhooks work as is). Every cull interval, the on-disk corpus is minimized (see
+/// [`crate::evm::cmin`]) and replaced so that it stays small across restarts.
+use std::collections::BTreeMap;
+use tracing::warn;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use glob::glob;
+use libafl::prelude::{Corpus, HasCorpus, HasExecutions};
+use serde::Serialize;
+
+use crate::evm::campaign_stats::CampaignStats;
+use crate::evm::types::EVMFuzzState;
+
+/// Fuzzing iterations between two checks of the soak timers
+pub const SOAK_POLL_ITERATIONS: u64 = 1000;
+/// Files of the work dir moved into a segment, besides the vulnerabilities directory
+const SEGMENT_FILES: [&str; 7] = [
+    "plot_data",
+    "summary.json",
+    "summary.txt",
+    "coverage.json",
+    "cov_*.txt",
+    "branch_cov_*.txt",
+    "soak.log.*",
+];
+
+#[derive(Clone, Debug)]
+pub struct SoakConfig {
+    pub segment: Duration,
+    pub digest: Duration,
+    /// Interval between two corpus culls, never culled if not set
+    pub cull: Option<Duration>,
+    pub webhook: Option<String>,
+    /// Segments kept, all if 0
+    pub keep: usize,
+    /// Size (in bytes) of soak.log before it is rotated
+    pub max_log: u64,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            segment: Duration::from_secs(24 * 3600),
+            digest: Duration::from_secs(3600),
+            cull: Some(Duration::from_secs(6 * 3600)),
+            webhook: None,
+            keep: 30,
+            max_log: 10 << 20,
+        }
+    }
+}
+
+/// Parse durations like `90s`, `30m`, `6h`, `7d` (seconds if no unit)
+pub fn parse_duration(s: &str) -> Duration {
+    let s = s.trim();
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let number: u64 = number.parse().unwrap_or_else(|_| panic!("invalid duration {}", s));
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        "w" => 7 * 24 * 3600,
+        _ => panic!("invalid duration unit in {}, expected s, m, h, d or w", s),
+    };
+    Duration::from_secs(number * seconds)
+}
+
+/// Parse sizes like `512K`, `100M`, `1G` (bytes if no unit)
+fn parse_size(s: &str) -> u64 {
+    let s = s.trim();
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let number: u64 = number.parse().unwrap_or_else(|_| panic!("invalid size {}", s));
+    match unit.to_uppercase().as_str() {
+        "" | "B" => number,
+        "K" => number << 10,
+        "M" => number << 20,
+        "G" => number << 30,
+        _ => panic!("invalid size unit in {}, expected K, M or G", s),
+    }
+}
+
+impl SoakConfig {
+    /// Parse settings like `segment=1d,digest=1h,cull=6h,keep=14,log=100M,webhook=https://...`,
+    /// unset settings keep their defaults (`cull=0` never culls)
+    pub fn parse(spec: &str) -> Self {
+        let mut config = Self::default();
+        for item in spec.split(',').filter(|item| !item.is_empty()) {
+            let (key, value) = item
+                .split_once('=')
+                .unwrap_or_else(|| panic!("invalid soak setting {}, expected <setting>=<value>", item));
+            match key.trim() {
+                "segment" => config.segment = parse_duration(value),
+                "digest" => config.digest = parse_duration(value),
+                "cull" => config.cull = Some(parse_duration(value)).filter(|d| !d.is_zero()),
+                "webhook" => config.webhook = Some(value.trim().to_string()),
+                "keep" => config.keep = value.trim().parse().expect("invalid number of segments kept"),
+                "log" => config.max_log = parse_size(value),
+                other => panic!("unknown soak setting {}, expected segment, digest, cull, webhook, keep or log", other),
+            }
+        }
+        config
+    }
+}
+
+/// Progress since the previous digest
+#[derive(Clone, Debug, Serialize)]
+pub struct Digest {
+    pub time: u64,
+    pub uptime: u64,
+    pub segment: usize,
+    pub executions: usize,
+    pub execs_per_sec: f64,
+    pub corpus: usize,
+    pub coverage: usize,
+    pub new_coverage: usize,
+    pub findings: usize,
+    /// Oracle => violations since the previous digest
+    pub new_findings: BTreeMap<String, usize>,
+}
+
+impl Digest {
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "[ityfuzz soak] segment {}, up {}h: {} execs ({:.1}/s), corpus {}, coverage {} (+{}), findings {}",
+            self.segment,
+            self.uptime / 3600,
+            self.executions,
+            self.execs_per_sec,
+            self.corpus,
+            self.coverage,
+            self.new_coverage,
+            self.findings
+        );
+        if !self.new_findings.is_empty() {
+            let new = self
+                .new_findings
+                .iter()
+                .map(|(oracle, count)| format!("{} {}", count, oracle))
+                .collect::<Vec<_>>();
+            text.push_str(&format!(" (new: {})", new.join(", ")));
+        }
+        text
+    }
+}
+
+pub struct Soak {
+    pub config: SoakConfig,
+    work_dir: String,
+    start: Instant,
+    segment_start: Instant,
+    last_digest: Instant,
+    last_cull: Instant,
+    pub segment: usize,
+    /// Executions, coverage and violations per oracle at the previous digest
+    executions: usize,
+    coverage: usize,
+    oracle_counts: BTreeMap<String, usize>,
+    /// Violations found by the previous segments
+    previous_findings: usize,
+    client: reqwest::blocking::Client,
+}
+
+impl Soak {
+    pub fn new(config: SoakConfig, work_dir: &str) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            work_dir: work_dir.to_string(),
+            start: now,
+            segment_start: now,
+            last_digest: now,
+            last_cull: now,
+            segment: 0,
+            executions: 0,
+            coverage: 0,
+            oracle_counts: BTreeMap::new(),
+            previous_findings: 0,
+            client: reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap(),
+        }
+    }
+
+    pub fn digest_due(&self) -> bool {
+        self.last_digest.elapsed() >= self.config.digest
+    }
+
+    pub fn cull_due(&self) -> bool {
+        self.config.cull.map_or(false, |cull| self.last_cull.elapsed() >= cull)
+    }
+
+    pub fn segment_due(&self) -> bool {
+        self.segment_start.elapsed() >= self.config.segment
+    }
+
+    /// Write the digest of the progress since the previous one to soak.log and the webhook
+    pub fn digest(&mut self, stats: &CampaignStats, state: &mut EVMFuzzState) -> Digest {
+        let executions = *state.executions();
+        // the branches are those of the whole campaign, but only known after the first sample of
+        // a segment
+        let coverage = stats.branches.len().max(self.coverage);
+        let elapsed = self.last_digest.elapsed().as_secs_f64().max(1e-3);
+        let new_findings = stats
+            .oracle_counts
+            .iter()
+            .map(|(oracle, count)| (oracle.clone(), count - self.oracle_counts.get(oracle).cloned().unwrap_or(0)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let digest = Digest {
+            time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            uptime: self.start.elapsed().as_secs(),
+            segment: self.segment,
+            executions,
+            execs_per_sec: executions.saturating_sub(self.executions) as f64 / elapsed,
+            corpus: state.corpus().count(),
+            coverage,
+            new_coverage: coverage - self.coverage,
+            findings: self.previous_findings + stats.violations,
+            new_findings,
+        };
+        self.last_digest = Instant::now();
+        self.executions = executions;
+        self.coverage = coverage;
+        self.oracle_counts = stats.oracle_counts.clone();
+
+        crate::status!("{}", digest.text());
+        self.log(&serde_json::to_string(&digest).unwrap());
+        if let Some(webhook) = &self.config.webhook {
+            let mut body = serde_json::json!({ "text": digest.text() });
+            body["digest"] = serde_json::to_value(&digest).unwrap();
+            if let Err(e) = self.client.post(webhook).json(&body).send() {
+                warn!("[soak] failed to post the digest: {}", e);
+            }
+        }
+        digest
+    }
+
+    /// Append a line to soak.log, which is renamed to soak.log.<unix time> once it exceeds the
+    /// maximum size (the rotated logs are moved with the segment)
+    fn log(&self, line: &str) {
+        let path = format!("{}/soak.log", self.work_dir);
+        if std::fs::metadata(&path).map_or(false, |m| m.len() >= self.config.max_log) {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+            let _ = std::fs::rename(&path, format!("{}.{}", path, now));
+        }
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    /// Replace the corpus by its minimized version, `minimize` minimizes a corpus directory into
+    /// another one
+    pub fn cull(&mut self, minimize: &mut dyn FnMut(&str, &str) -> (usize, usize)) {
+        self.last_cull = Instant::now();
+        let corpus = format!("{}/corpus", self.work_dir);
+        if !Path::new(&corpus).exists() {
+            return;
+        }
+        let culled = format!("{}/corpus_culled", self.work_dir);
+        let _ = std::fs::remove_dir_all(&culled);
+        let (before, after) = minimize(&corpus, &culled);
+        // new corpus items are numbered after the culled ones, the names can't collide
+        if std::fs::remove_dir_all(&corpus).is_ok() && std::fs::rename(&culled, &corpus).is_ok() {
+            self.log(&serde_json::json!({"cull": {"before": before, "after": after}}).to_string());
+        }
+    }
+
+    /// Move the artifacts of the segment to `segments/`, and drop the oldest segments beyond the
+    /// retention. The campaign statistics of the segment are to be restarted by the caller.
+    pub fn rotate(&mut self, stats: &CampaignStats) {
+        stats.write_summary();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let segments = format!("{}/segments", self.work_dir);
+        let dir = format!("{}/{}_{}", segments, self.segment, now);
+        std::fs::create_dir_all(&dir).expect("Failed to create the segment directory");
+        let _ = std::fs::rename(format!("{}/vulnerabilities", self.work_dir), format!("{}/vulnerabilities", dir));
+        for pattern in SEGMENT_FILES {
+            for file in glob(&format!("{}/{}", self.work_dir, pattern)).expect("Failed to read glob pattern").flatten() {
+                let _ = std::fs::rename(&file, format!("{}/{}", dir, file.file_name().unwrap().to_str().unwrap()));
+            }
+        }
+        if self.config.keep > 0 {
+            let mut kept = std::fs::read_dir(&segments)
+                .map(|entries| entries.flatten().map(|e| e.path()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            // segments are numbered in order
+            kept.sort_by_key(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.split('_').next())
+                    .and_then(|idx| idx.parse::<usize>().ok())
+            });
+            let excess = kept.len().saturating_sub(self.config.keep);
+            for path in &kept[..excess] {
+                let _ = std::fs::remove_dir_all(path);
+            }
+        }
+        self.log(&serde_json::json!({"segment": self.segment, "archived": dir}).to_string());
+
+        self.previous_findings += stats.violations;
+        self.oracle_counts.clear();
+        self.segment += 1;
+        self.segment_start = Instant::now();
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_duration("90"), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m"), Duration::from_secs(1800));
+        assert_eq!(parse_duration("2d"), Duration::from_secs(2 * 24 * 3600));
+        assert_eq!(parse_size("100M"), 100 << 20);
+
+        let config = SoakConfig::parse("segment=7d,cull=0,webhook=https://hooks.slack.com/services/T0/B0/x?a=b,keep=4");
+        assert_eq!(config.segment, Duration::from_secs(7 * 24 * 3600));
+        assert_eq!(config.digest, Duration::from_secs(3600));
+        assert_eq!(config.cull, None);
+        assert_eq!(config.webhook, Some("https://hooks.slack.com/services/T0/B0/x?a=b".to_string()));
+        assert_eq!(config.keep, 4);
+    }
+}
@@ -0,0 +1,232 @@
+/// Net state diff of a violating sequence, showing the impact of a finding
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::middlewares::upgrade::EIP1967_IMPLEMENTATION_SLOT;
+use crate::evm::replay::SolutionReplay;
+use crate::evm::types::{EVMAddress, EVMFuzzState, EVMStagedVMState, EVMU256};
+use crate::evm::vm::{EVMExecutor, EVMState};
+use crate::generic_vm::vm_executor::GenericVM;
+
+/// bytes32(uint256(keccak256('eip1967.proxy.admin')) - 1)
+const EIP1967_ADMIN_SLOT: &str = "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+/// bytes32(uint256(keccak256('eip1967.proxy.beacon')) - 1)
+const EIP1967_BEACON_SLOT: &str = "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+
+/// balanceOf(address)
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SlotDiff {
+    pub address: EVMAddress,
+    pub slot: EVMU256,
+    pub label: Option<String>,
+    pub before: EVMU256,
+    pub after: EVMU256,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BalanceDiff {
+    pub token: EVMAddress,
+    pub holder: EVMAddress,
+    pub before: EVMU256,
+    pub after: EVMU256,
+    /// Signed change of the balance, in decimal
+    pub delta: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub storage: Vec<SlotDiff>,
+    pub token_balances: Vec<BalanceDiff>,
+    /// Net ETH / token value earned by the attacker (tracked with flashloan enabled)
+    pub earned: String,
+    /// Net ETH / token value owed by the attacker (tracked with flashloan enabled)
+    pub owed: String,
+}
+
+/// Label of well-known storage slots
+pub fn slot_label(slot: EVMU256) -> Option<String> {
+    [
+        (EIP1967_IMPLEMENTATION_SLOT, "eip1967.proxy.implementation"),
+        (EIP1967_ADMIN_SLOT, "eip1967.proxy.admin"),
+        (EIP1967_BEACON_SLOT, "eip1967.proxy.beacon"),
+    ]
+    .iter()
+    .find(|(s, _)| EVMU256::from_str(s).unwrap() == slot)
+    .map(|(_, label)| label.to_string())
+}
+
+fn signed_delta(before: EVMU256, after: EVMU256) -> String {
+    if after >= before {
+        format!("+{}", after - before)
+    } else {
+        format!("-{}", before - after)
+    }
+}
+
+/// Whether the contract looks like an ERC20 token (has balanceOf in its dispatcher)
+fn has_balance_of(executor: &EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>, address: &EVMAddress) -> bool {
+    match executor.host.code.get(address) {
+        Some(code) => code
+            .original_bytecode_slice()
+            .windows(5)
+            .any(|w| w[0] == 0x63 && w[1..] == BALANCE_OF),
+        None => false,
+    }
+}
+
+fn balances(
+    executor: &mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &mut EVMFuzzState,
+    vm_state: &EVMState,
+    queries: &Vec<(EVMAddress, EVMAddress)>,
+) -> Vec<Option<EVMU256>> {
+    let calls = queries
+        .iter()
+        .map(|(token, holder)| {
+            let mut data = BALANCE_OF.to_vec();
+            data.extend([0; 12]);
+            data.extend(holder.0);
+            (*token, Bytes::from(data))
+        })
+        .collect();
+    executor
+        .fast_static_call(&calls, vm_state, state)
+        .iter()
+        .map(|out| {
+            if out.len() == 32 {
+                Some(EVMU256::from_be_slice(out))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Storage slots changed between two states
+fn storage_diff(before: &EVMState, after: &EVMState) -> Vec<SlotDiff> {
+    let empty = HashMap::new();
+    let mut storage = vec![];
+    let addresses = before.state.keys().chain(after.state.keys()).cloned().collect::<BTreeSet<_>>();
+    for address in &addresses {
+        let (slots_before, slots_after) = (
+            before.get(address).unwrap_or(&empty),
+            after.get(address).unwrap_or(&empty),
+        );
+        let slots = slots_before.keys().chain(slots_after.keys()).cloned().collect::<BTreeSet<_>>();
+        for slot in slots {
+            let (old, new) = (
+                slots_before.get(&slot).cloned().unwrap_or_default(),
+                slots_after.get(&slot).cloned().unwrap_or_default(),
+            );
+            if old != new {
+                storage.push(SlotDiff {
+                    address: *address,
+                    slot,
+                    label: slot_label(slot),
+                    before: old,
+                    after: new,
+                });
+            }
+        }
+    }
+    storage
+}
+
+/// Net storage and token balance changes of a replayed violating sequence. Reverted transactions
+/// leave the state untouched, as in the debugger.
+pub fn state_diff(
+    executor: &mut EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>,
+    state: &mut EVMFuzzState,
+    initial_state: &EVMStagedVMState,
+    replay: &SolutionReplay,
+) -> StateDiff {
+    let before = &initial_state.state;
+    let after = &replay.final_state.state;
+    let storage = storage_diff(before, after);
+
+    let mut holders = BTreeSet::new();
+    for tx in &replay.txs {
+        holders.insert(tx.concise.caller);
+        holders.insert(tx.concise.contract);
+    }
+    holders.extend(before.state.keys().chain(after.state.keys()));
+
+    // only contracts whose storage changed can have balances changed
+    let queries = storage
+        .iter()
+        .map(|diff| diff.address)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|token| has_balance_of(executor, token))
+        .flat_map(|token| holders.iter().map(move |holder| (token, *holder)))
+        .collect::<Vec<_>>();
+    let balances_before = balances(executor, state, before, &queries);
+    let balances_after = balances(executor, state, after, &queries);
+    let token_balances = queries
+        .iter()
+        .zip(balances_before.iter().zip(balances_after.iter()))
+        .filter_map(|((token, holder), balances)| match balances {
+            (Some(old), Some(new)) if old != new => Some(BalanceDiff {
+                token: *token,
+                holder: *holder,
+                before: *old,
+                after: *new,
+                delta: signed_delta(*old, *new),
+            }),
+            _ => None,
+        })
+        .collect();
+
+    StateDiff {
+        storage,
+        token_balances,
+        earned: after.flashloan_data.earned.to_string(),
+        owed: after.flashloan_data.owed.to_string(),
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    #[test]
+    fn test_slot_label() {
+        let slot = EVMU256::from_str(EIP1967_IMPLEMENTATION_SLOT).unwrap();
+        assert_eq!(slot_label(slot), Some("eip1967.proxy.implementation".to_string()));
+        assert_eq!(slot_label(EVMU256::from(1)), None);
+    }
+
+    #[test]
+    fn test_signed_delta() {
+        assert_eq!(signed_delta(EVMU256::from(10), EVMU256::from(15)), "+5");
+        assert_eq!(signed_delta(EVMU256::from(15), EVMU256::from(10)), "-5");
+    }
+
+    #[test]
+    fn test_storage_diff() {
+        let address = fixed_address("0000000000000000000000000000000000000001");
+        let mut before = EVMState::new();
+        before.insert(
+            address,
+            HashMap::from([(EVMU256::from(0), EVMU256::from(1)), (EVMU256::from(1), EVMU256::from(2))]),
+        );
+        let mut after = before.clone();
+        after.get_mut(&address).unwrap().insert(EVMU256::from(1), EVMU256::from(3));
+        after.insert(
+            fixed_address("0000000000000000000000000000000000000002"),
+            HashMap::from([(EVMU256::from(0), EVMU256::ZERO)]),
+        );
+
+        let diff = storage_diff(&before, &after);
+        assert_eq!(diff.len(), 1);
+        assert_eq!((diff[0].address, diff[0].slot), (address, EVMU256::from(1)));
+        assert_eq!((diff[0].before, diff[0].after), (EVMU256::from(2), EVMU256::from(3)));
+        assert!(storage_diff(&before, &before).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+/// Storage slot labels from the storage layout output of solc
+///
+/// Layouts are loaded from `<Contract>_storage.json` (`solc --storage-layout -o <dir>`) or the
+/// `storage-layout` entries of combined.json (`--combined-json storage-layout,...`). Slots of
+/// state variables are labeled with their names. Slots of mapping values and dynamic array
+/// elements are labeled by following the keccak preimages observed during execution back to
+/// the slot of the variable, e.g., `balances[0x8ef5...79dd]` or `users[3].debt`.
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::evm::types::{EVMAddress, EVMU256};
+
+/// Maximum number of slots of a struct / static array followed when labeling
+const MAX_VALUE_SLOTS: u64 = 64;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageVar {
+    pub label: String,
+    pub slot: String,
+    pub offset: u64,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageType {
+    pub encoding: String,
+    pub label: String,
+    #[serde(rename = "numberOfBytes")]
+    pub number_of_bytes: String,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub base: Option<String>,
+    #[serde(default)]
+    pub members: Vec<StorageVar>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct StorageLayout {
+    #[serde(default)]
+    pub storage: Vec<StorageVar>,
+    #[serde(default)]
+    pub types: Option<HashMap<String, StorageType>>,
+}
+
+fn slot_of(var: &StorageVar) -> EVMU256 {
+    EVMU256::from_str(&var.slot).expect("invalid slot in storage layout")
+}
+
+impl StorageLayout {
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).expect("failed to parse storage layout")
+    }
+
+    fn ty(&self, id: &str) -> Option<&StorageType> {
+        self.types.as_ref()?.get(id)
+    }
+
+    /// Slots taken by a value of a type
+    fn slots_of(&self, id: &str) -> u64 {
+        self.ty(id)
+            .and_then(|ty| ty.number_of_bytes.parse::<u64>().ok())
+            .map_or(1, |bytes| ((bytes + 31) / 32).max(1))
+    }
+
+    /// Label of the `idx`-th slot of a value of a type stored at `base_label`
+    fn inner_label(&self, base_label: String, id: &str, idx: u64) -> (String, String) {
+        let ty = match self.ty(id) {
+            Some(ty) => ty,
+            None => return (base_label, id.to_string()),
+        };
+        if !ty.members.is_empty() {
+            // the last member starting at or before the slot
+            if let Some(member) = ty
+                .members
+                .iter()
+                .filter(|m| slot_of(m) <= EVMU256::from(idx))
+                .max_by_key(|m| (slot_of(m), m.offset))
+            {
+                let rest = idx - slot_of(member).as_limbs()[0];
+                return self.inner_label(format!("{}.{}", base_label, member.label), &member.ty, rest);
+            }
+        }
+        if ty.encoding == "inplace" && ty.base.is_some() {
+            // static array
+            let base = ty.base.as_ref().unwrap();
+            let per_elem = self.slots_of(base);
+            return self.inner_label(format!("{}[{}]", base_label, idx / per_elem), base, idx % per_elem);
+        }
+        (base_label, id.to_string())
+    }
+
+    /// Label of a slot of a state variable (or of a struct / static array in it), and its type
+    fn var_label(&self, slot: EVMU256) -> Option<(String, String)> {
+        let packed = self.storage.iter().filter(|v| slot_of(v) == slot).collect::<Vec<_>>();
+        if !packed.is_empty() {
+            let label = packed.iter().map(|v| v.label.clone()).collect::<Vec<_>>().join(", ");
+            return Some(match packed.as_slice() {
+                [var] => self.inner_label(label, &var.ty, 0),
+                _ => (label, String::new()),
+            });
+        }
+        let var = self
+            .storage
+            .iter()
+            .filter(|v| slot_of(v) < slot && slot - slot_of(v) < EVMU256::from(self.slots_of(&v.ty)))
+            .max_by_key(|v| slot_of(v))?;
+        Some(self.inner_label(var.label.clone(), &var.ty, (slot - slot_of(var)).as_limbs()[0]))
+    }
+
+    /// Label of a slot and its type, given keccak preimages observed during execution
+    pub fn label(&self, slot: EVMU256, preimages: &HashMap<EVMU256, Vec<u8>>) -> Option<(String, String)> {
+        if let Some(label) = self.var_label(slot) {
+            return Some(label);
+        }
+        // mapping value or dynamic array element at a small offset of a hash
+        (0..MAX_VALUE_SLOTS)
+            .filter(|offset| slot >= EVMU256::from(*offset))
+            .find_map(|offset| {
+                let preimage = preimages.get(&(slot - EVMU256::from(offset)))?;
+                match preimage.len() {
+                    64 => {
+                        let (parent, parent_ty) = self.label(EVMU256::from_be_slice(&preimage[32..]), preimages)?;
+                        let ty = self.ty(&parent_ty).filter(|ty| ty.encoding == "mapping")?;
+                        let key = format_key(ty.key.as_deref().and_then(|k| self.ty(k)), &preimage[..32]);
+                        let value = ty.value.clone()?;
+                        Some(self.inner_label(format!("{}[{}]", parent, key), &value, offset))
+                    }
+                    32 => {
+                        let (parent, parent_ty) = self.label(EVMU256::from_be_slice(preimage), preimages)?;
+                        let ty = self.ty(&parent_ty).filter(|ty| ty.encoding == "dynamic_array")?;
+                        let base = ty.base.clone()?;
+                        let per_elem = self.slots_of(&base);
+                        Some(self.inner_label(format!("{}[{}]", parent, offset / per_elem), &base, offset % per_elem))
+                    }
+                    _ => None,
+                }
+            })
+    }
+}
+
+/// Mapping key in a readable form according to its type
+fn format_key(ty: Option<&StorageType>, key: &[u8]) -> String {
+    let value = EVMU256::from_be_slice(key);
+    match ty.map(|t| t.label.as_str()) {
+        Some(label) if label.starts_with("address") || label.starts_with("contract") => {
+            format!("0x{}", hex::encode(&key[12..]))
+        }
+        Some(label) if label.starts_with("uint") || label == "bool" => value.to_string(),
+        _ => format!("{:#x}", value),
+    }
+}
+
+/// Storage layouts of the deployed contracts and keccak preimages observed when replaying
+/// violating sequences
+#[derive(Clone, Debug, Default)]
+pub struct StorageLabels {
+    pub layouts: HashMap<EVMAddress, StorageLayout>,
+    pub preimages: HashMap<EVMU256, Vec<u8>>,
+}
+
+impl StorageLabels {
+    pub fn label(&self, address: &EVMAddress, slot: EVMU256) -> Option<String> {
+        self.layouts
+            .get(address)?
+            .label(slot, &self.preimages)
+            .map(|(label, _)| label)
+    }
+}
+
+/// Storage layouts in the `storage-layout` entries of combined.json, by contract name
+pub fn parse_combined_layouts(json: &str) -> HashMap<String, StorageLayout> {
+    let map_json = serde_json::from_str::<Value>(json).expect("failed to parse combined.json");
+    let mut layouts = HashMap::new();
+    if let Some(contracts) = map_json["contracts"].as_object() {
+        for (name, info) in contracts {
+            let layout = match &info["storage-layout"] {
+                Value::String(s) => serde_json::from_str(s).ok(),
+                Value::Object(_) => serde_json::from_value(info["storage-layout"].clone()).ok(),
+                _ => None,
+            };
+            if let Some(layout) = layout {
+                layouts.insert(name.split(':').last().unwrap().to_string(), layout);
+            }
+        }
+    }
+    layouts
+}
+
+mod tests {
+    use super::*;
+    use revm_primitives::keccak256;
+
+    const LAYOUT: &str = r#"{
+        "storage": [
+            {"label": "owner", "offset": 0, "slot": "0", "type": "t_address"},
+            {"label": "balances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_uint256)"},
+            {"label": "users", "offset": 0, "slot": "2", "type": "t_array(t_struct(User)_storage)dyn_storage"}
+        ],
+        "types": {
+            "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+            "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+            "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+            "t_array(t_struct(User)_storage)dyn_storage": {"base": "t_struct(User)_storage", "encoding": "dynamic_array", "label": "struct User[]", "numberOfBytes": "32"},
+            "t_struct(User)_storage": {"encoding": "inplace", "label": "struct User", "numberOfBytes": "64", "members": [
+                {"label": "collateral", "offset": 0, "slot": "0", "type": "t_uint256"},
+                {"label": "debt", "offset": 0, "slot": "1", "type": "t_uint256"}
+            ]}
+        }
+    }"#;
+
+    #[test]
+    fn test_label() {
+        let layout = StorageLayout::from_json(LAYOUT);
+        let mut preimages = HashMap::new();
+        assert_eq!(layout.label(EVMU256::ZERO, &preimages).unwrap().0, "owner");
+
+        let mut key = vec![0u8; 12];
+        key.extend([0x11; 20]);
+        let mapping_preimage = [key, EVMU256::from(1).to_be_bytes::<32>().to_vec()].concat();
+        let mapping_slot = EVMU256::from_be_bytes(keccak256(&mapping_preimage).0);
+        preimages.insert(mapping_slot, mapping_preimage);
+        assert_eq!(
+            layout.label(mapping_slot, &preimages).unwrap().0,
+            format!("balances[0x{}]", hex::encode([0x11; 20]))
+        );
+
+        let array_preimage = EVMU256::from(2).to_be_bytes::<32>().to_vec();
+        let array_slot = EVMU256::from_be_bytes(keccak256(&array_preimage).0);
+        preimages.insert(array_slot, array_preimage);
+        // second field of the element at index 3
+        assert_eq!(
+            layout.label(array_slot + EVMU256::from(7), &preimages).unwrap().0,
+            "users[3].debt"
+        );
+        assert!(layout.label(EVMU256::from(100), &preimages).is_none());
+    }
+}
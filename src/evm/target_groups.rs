@@ -0,0 +1,139 @@
+/// Budget allocation across the targets of a multi-target campaign (`--target-groups`)
+///
+/// Related deployments (e.g., every market of a lending protocol, or all pools of a DEX) are
+/// fuzzed in a single campaign sharing the corpus, the oracles and the RPC cache. Targets are
+/// grouped with `--target-groups "markets=0xa...,0xb...;pools=0xc...,0xd..."` (by address, or by
+/// contract name for local targets), or one group per target with `--target-groups auto`. The
+/// energy of an input is scaled by the coverage progress of the group of the contract it calls:
+/// groups that recently produced new corpus entries per execution get more, stalled ones less.
+use std::collections::HashMap;
+use tracing::warn;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::evm::contract_utils::ContractInfo;
+use crate::evm::types::EVMAddress;
+
+/// Range of the energy multiplier of a group
+const MIN_WEIGHT: f64 = 0.25;
+const MAX_WEIGHT: f64 = 4.0;
+/// Executions a group is credited initially, so that new groups are neither starved nor favored
+const PRIOR_EXECUTIONS: u64 = 1000;
+
+/// Progress of a group of targets
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GroupProgress {
+    pub executions: u64,
+    pub found: u64,
+}
+
+impl GroupProgress {
+    fn rate(&self) -> f64 {
+        (self.found as f64 + 1.0) / (self.executions + PRIOR_EXECUTIONS) as f64
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TargetGroups {
+    /// Group of each target
+    pub groups: HashMap<EVMAddress, String>,
+    /// One group per target, for targets not in `groups`
+    pub auto: bool,
+    pub progress: HashMap<String, GroupProgress>,
+}
+
+impl TargetGroups {
+    /// Parse `auto` or `<group>=<address or contract name>,...;...`, contract names are resolved
+    /// with the deployed contracts
+    pub fn parse(spec: &str, contracts: &[ContractInfo]) -> Self {
+        if spec.trim() == "auto" {
+            return Self {
+                auto: true,
+                ..Default::default()
+            };
+        }
+        let mut groups = HashMap::new();
+        for group in spec.split(';').filter(|g| !g.trim().is_empty()) {
+            let (name, members) = group.split_once('=').expect("target group should be <group>=<targets>");
+            for member in members.split(',').map(|m| m.trim()) {
+                let address = EVMAddress::from_str(member).ok().or_else(|| {
+                    contracts
+                        .iter()
+                        .find(|c| c.name.trim_end_matches('*').split('/').last() == Some(member))
+                        .map(|c| c.deployed_address)
+                });
+                match address {
+                    Some(address) => {
+                        groups.insert(address, name.trim().to_string());
+                    }
+                    None => warn!("[target-groups] unknown target {}", member),
+                }
+            }
+        }
+        Self {
+            groups,
+            ..Default::default()
+        }
+    }
+
+    pub fn group_of(&self, target: &EVMAddress) -> Option<String> {
+        match self.groups.get(target) {
+            Some(group) => Some(group.clone()),
+            None if self.auto => Some(format!("0x{}", hex::encode(target))),
+            None => None,
+        }
+    }
+
+    /// Energy multiplier of inputs calling `target`: progress rate of its group relative to the
+    /// average rate of all groups
+    pub fn weight(&self, target: &EVMAddress) -> f64 {
+        let rate = match self.group_of(target) {
+            Some(group) => self.progress.get(&group).cloned().unwrap_or_default().rate(),
+            None => return 1.0,
+        };
+        let rates = self.progress.values().map(|p| p.rate()).collect::<Vec<_>>();
+        if rates.is_empty() {
+            return 1.0;
+        }
+        let average = rates.iter().sum::<f64>() / rates.len() as f64;
+        (rate / average).clamp(MIN_WEIGHT, MAX_WEIGHT)
+    }
+
+    pub fn record(&mut self, target: &EVMAddress, executions: u64, found: u64) {
+        if let Some(group) = self.group_of(target) {
+            let progress = self.progress.entry(group).or_default();
+            progress.executions += executions;
+            progress.found += found;
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    #[test]
+    fn test_group_weights() {
+        let a = fixed_address("1111111111111111111111111111111111111111");
+        let b = fixed_address("2222222222222222222222222222222222222222");
+        let c = fixed_address("3333333333333333333333333333333333333333");
+        let mut groups = TargetGroups::parse(
+            "markets=0x1111111111111111111111111111111111111111;pools=0x2222222222222222222222222222222222222222",
+            &[],
+        );
+        assert_eq!(groups.group_of(&a), Some("markets".to_string()));
+        assert_eq!(groups.group_of(&c), None);
+        assert_eq!(groups.weight(&a), 1.0);
+
+        // markets keep finding new inputs, pools are stalled
+        groups.record(&a, 10000, 100);
+        groups.record(&b, 10000, 0);
+        assert!(groups.weight(&a) > 1.0);
+        assert!(groups.weight(&b) < 1.0);
+        assert_eq!(groups.weight(&c), 1.0);
+
+        let auto = TargetGroups::parse("auto", &[]);
+        assert_eq!(auto.group_of(&c), Some("0x3333333333333333333333333333333333333333".to_string()));
+    }
+}
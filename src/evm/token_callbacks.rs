@@ -0,0 +1,150 @@
+/// Token callback reentrancy modeling (`--token-callbacks`, `--nft-callbacks`)
+///
+/// The fuzzer's contract callers are registered as ERC-777 recipient / sender hooks in the
+/// ERC-1820 registry and act as ERC-1363 receivers, and / or as ERC-721 / ERC-1155 receivers
+/// (so that safeMint / safeTransfer to them succeeds). When a token calls one of their hooks
+/// during a transfer, control is leaked to the fuzzer (like any call to an attacker-controlled
+/// contract), so that reentrant sequences are injected in the middle of the transfer. Once the
+/// sequence is done, the hook returns what the token expects.
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use revm_primitives::keccak256;
+
+use crate::evm::types::{fixed_address, EVMAddress};
+
+/// ERC-1820 registry, deployed at the same address on every chain
+pub const ERC1820_REGISTRY: &str = "1820a4B7618BdE71Dce8cdc73aAcF9a4c6a6A7F24";
+
+fn selector(signature: &str) -> [u8; 4] {
+    keccak256(signature.as_bytes()).0[..4].try_into().unwrap()
+}
+
+/// Hooks called by fungible tokens, and whether they must return their selector
+pub const FUNGIBLE_HOOKS: [(&str, bool); 4] = [
+    // ERC-777
+    ("tokensReceived(address,address,address,uint256,bytes,bytes)", false),
+    ("tokensToSend(address,address,address,uint256,bytes,bytes)", false),
+    // ERC-1363
+    ("onTransferReceived(address,address,uint256,bytes)", true),
+    ("onApprovalReceived(address,uint256,bytes)", true),
+];
+
+/// Hooks called by NFTs on safe transfers / mints, all must return their selector
+pub const NFT_HOOKS: [(&str, bool); 3] = [
+    // ERC-721
+    ("onERC721Received(address,address,uint256,bytes)", true),
+    // ERC-1155
+    ("onERC1155Received(address,address,uint256,uint256,bytes)", true),
+    ("onERC1155BatchReceived(address,address,uint256[],uint256[],bytes)", true),
+];
+
+/// ERC-777 interfaces implemented by the callers
+const INTERFACES: [&str; 2] = ["ERC777TokensRecipient", "ERC777TokensSender"];
+
+#[derive(Clone, Debug, Default)]
+pub struct TokenCallbacks {
+    /// Accounts implementing the hooks
+    pub accounts: HashSet<EVMAddress>,
+    /// Hooks implemented, and whether they must return their selector
+    pub hooks: Vec<(&'static str, bool)>,
+}
+
+impl TokenCallbacks {
+    pub fn new(accounts: HashSet<EVMAddress>, hooks: Vec<(&'static str, bool)>) -> Self {
+        Self { accounts, hooks }
+    }
+
+    pub fn registry() -> EVMAddress {
+        fixed_address(ERC1820_REGISTRY)
+    }
+
+    /// Return data of a call to the ERC-1820 registry, None if the call should go to the registry
+    /// itself (i.e., it does not concern the hooks of our accounts)
+    ///
+    /// Offline, the registry is not deployed, so registrations succeed without effect and lookups
+    /// of other accounts find no implementer.
+    pub fn registry_call(&self, input: &[u8], deployed: bool) -> Option<Bytes> {
+        if input.len() < 4 {
+            return None;
+        }
+        if input[..4] == selector("getInterfaceImplementer(address,bytes32)") && input.len() >= 68 {
+            let account = EVMAddress::from_slice(&input[16..36]);
+            let is_hook = INTERFACES.iter().any(|i| keccak256(i.as_bytes()).0 == input[36..68]);
+            if is_hook && self.accounts.contains(&account) {
+                return Some(Bytes::from([vec![0; 12], account.0.to_vec()].concat()));
+            }
+            if !deployed {
+                return Some(Bytes::from(vec![0; 32]));
+            }
+        }
+        if !deployed {
+            return Some(Bytes::new());
+        }
+        None
+    }
+
+    /// Return data the hook called with `input` on `target` must eventually produce, None if the
+    /// call is not a hook of our accounts
+    pub fn hook_return(&self, target: &EVMAddress, input: &[u8]) -> Option<Vec<u8>> {
+        if input.len() < 4 || !self.accounts.contains(target) {
+            return None;
+        }
+        self.hooks
+            .iter()
+            .find(|(sig, _)| input[..4] == selector(sig))
+            .map(|(sig, returns_selector)| {
+                if *returns_selector {
+                    let mut ret = selector(sig).to_vec();
+                    ret.resize(32, 0);
+                    ret
+                } else {
+                    vec![]
+                }
+            })
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_callbacks() {
+        let account = fixed_address("e1A425f1AC34A8a441566f93c82dD730639c8510");
+        let callbacks = TokenCallbacks::new(HashSet::from([account]), FUNGIBLE_HOOKS.to_vec());
+        assert_eq!(selector("tokensReceived(address,address,address,uint256,bytes,bytes)"), [0x00, 0x23, 0xde, 0x29]);
+        assert_eq!(selector("onTransferReceived(address,address,uint256,bytes)"), [0x88, 0xa7, 0xca, 0x5c]);
+
+        let ret = callbacks.hook_return(&account, &hex::decode("88a7ca5c").unwrap()).unwrap();
+        assert_eq!(ret[..4], [0x88, 0xa7, 0xca, 0x5c]);
+        assert_eq!(ret.len(), 32);
+        assert_eq!(callbacks.hook_return(&account, &hex::decode("0023de29").unwrap()), Some(vec![]));
+        assert_eq!(callbacks.hook_return(&EVMAddress::zero(), &hex::decode("0023de29").unwrap()), None);
+
+        let lookup = [
+            selector("getInterfaceImplementer(address,bytes32)").to_vec(),
+            vec![0; 12],
+            account.0.to_vec(),
+            keccak256(b"ERC777TokensRecipient").0.to_vec(),
+        ]
+        .concat();
+        let implementer = callbacks.registry_call(&lookup, true).unwrap();
+        assert_eq!(implementer[12..], account.0);
+        let other = [&lookup[..16], &[0; 20], &lookup[36..]].concat();
+        assert_eq!(callbacks.registry_call(&other, true), None);
+        assert_eq!(callbacks.registry_call(&other, false), Some(Bytes::from(vec![0; 32])));
+    }
+
+    #[test]
+    fn test_nft_callbacks() {
+        let account = fixed_address("e1A425f1AC34A8a441566f93c82dD730639c8510");
+        let callbacks = TokenCallbacks::new(HashSet::from([account]), NFT_HOOKS.to_vec());
+        let ret = callbacks.hook_return(&account, &hex::decode("150b7a02").unwrap()).unwrap();
+        assert_eq!(ret[..4], [0x15, 0x0b, 0x7a, 0x02]);
+        let ret = callbacks.hook_return(&account, &hex::decode("f23a6e61").unwrap()).unwrap();
+        assert_eq!(ret[..4], [0xf2, 0x3a, 0x6e, 0x61]);
+        let ret = callbacks.hook_return(&account, &hex::decode("bc197c81").unwrap()).unwrap();
+        assert_eq!(ret[..4], [0xbc, 0x19, 0x7c, 0x81]);
+        assert_eq!(callbacks.hook_return(&account, &hex::decode("88a7ca5c").unwrap()), None);
+    }
+}
@@ -0,0 +1,183 @@
+/// LLM-assisted triage of findings (feature `llm_triage`)
+///
+/// For each finding, a short summary is requested from a chat completions endpoint (OpenAI
+/// compatible): what the violating sequence does, which invariant broke and the suspected root
+/// cause, given the oracle output, the trace of the sequence and the source of the contract
+/// triggering the violation. Findings reported by the same oracles on the same functions are
+/// summarized once. The summary is written to `[WORKDIR]/vulnerabilities/[N]_triage.md` and
+/// attached to the finding in `findings.json`.
+///
+/// The API key is read from `ITYFUZZ_LLM_API_KEY`. The prompt contains the source of the
+/// targets, don't enable it on code that must not leave the machine.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::Duration;
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::evm::findings::{classify, AffectedFunction};
+use crate::evm::srcmap::parser::BASE_PATH;
+use crate::evm::types::{EVMAddress, ProjectSourceMapTy};
+
+pub const API_KEY_ENV: &str = "ITYFUZZ_LLM_API_KEY";
+/// Characters of source sent in a prompt
+const MAX_SOURCE_CHARS: usize = 16000;
+/// Characters of trace sent in a prompt
+const MAX_TRACE_CHARS: usize = 8000;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+const SYSTEM_PROMPT: &str = "You triage findings of a smart contract fuzzer for an auditor. Given the \
+    oracle report, the transaction trace and the source of the contract, answer in at most 150 \
+    words of markdown with three bullets: **Sequence** (what the transactions do), **Invariant** \
+    (which invariant broke) and **Root cause** (the suspected flaw in the source, with the \
+    function and line if you can tell). Say so if the finding looks like a false positive.";
+
+/// Cut a text to its first `max` characters
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}\n[truncated]", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// Source file mapped to most instructions of a contract, with its content
+fn main_source(address: &EVMAddress, sourcemap: &ProjectSourceMapTy) -> Option<(String, String)> {
+    let locations = sourcemap.get(address)?.as_ref()?;
+    let file = locations
+        .values()
+        .filter_map(|loc| loc.file.clone())
+        .counts()
+        .into_iter()
+        .max_by_key(|(_, count)| *count)?
+        .0;
+    let content = fs::read_to_string(unsafe { BASE_PATH.clone() } + file.as_str()).ok()?;
+    Some((file, content))
+}
+
+/// Oracles and functions of a finding, findings with the same key share their summary
+fn dedup_key(bugs: &Vec<u64>, affected: &[AffectedFunction]) -> String {
+    let oracles = bugs.iter().map(|bug| classify(*bug).oracle).collect::<HashSet<_>>();
+    format!(
+        "{}|{}",
+        oracles.into_iter().sorted().join(","),
+        affected.iter().map(|f| format!("{:?}.{}", f.contract, f.function)).join(",")
+    )
+}
+
+pub struct Triage {
+    pub endpoint: String,
+    pub model: String,
+    api_key: String,
+    pub sourcemap: ProjectSourceMapTy,
+    /// Summaries by deduplication key
+    summaries: HashMap<String, String>,
+}
+
+impl Triage {
+    pub fn new(endpoint: &str, model: &str, sourcemap: ProjectSourceMapTy) -> Self {
+        let api_key = std::env::var(API_KEY_ENV)
+            .unwrap_or_else(|_| panic!("--llm-triage requires the API key in {}", API_KEY_ENV));
+        Self {
+            endpoint: endpoint.to_string(),
+            model: model.to_string(),
+            api_key,
+            sourcemap,
+            summaries: HashMap::new(),
+        }
+    }
+
+    pub fn prompt(&self, oracle_output: &str, trace: &str, affected: &[AffectedFunction]) -> String {
+        let mut prompt = format!(
+            "## Oracle report\n{}\n\n## Trace\n{}\n",
+            oracle_output.trim(),
+            truncate(trace, MAX_TRACE_CHARS)
+        );
+        // the source of the contract triggering the violation
+        if let Some((file, content)) = affected.last().and_then(|f| main_source(&f.contract, &self.sourcemap)) {
+            prompt.push_str(&format!("\n## Source ({})\n{}\n", file, truncate(&content, MAX_SOURCE_CHARS)));
+        }
+        prompt
+    }
+
+    fn complete(&self, prompt: String) -> Option<String> {
+        let client = reqwest::blocking::Client::builder().timeout(TIMEOUT).build().ok()?;
+        let body = json!({
+            "model": self.model,
+            "messages": [
+                {"role": "system", "content": SYSTEM_PROMPT},
+                {"role": "user", "content": prompt},
+            ],
+        });
+        let response = client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .and_then(|response| response.error_for_status());
+        let response: Value = match response.and_then(|response| response.json()) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("[triage] summary request failed: {}", e);
+                return None;
+            }
+        };
+        response["choices"][0]["message"]["content"].as_str().map(|s| s.trim().to_string())
+    }
+
+    /// Summary of a finding, None if the endpoint failed
+    pub fn summarize(
+        &mut self,
+        bugs: &Vec<u64>,
+        oracle_output: &str,
+        trace: &str,
+        affected: &[AffectedFunction],
+    ) -> Option<String> {
+        let key = dedup_key(bugs, affected);
+        if let Some(summary) = self.summaries.get(&key) {
+            return Some(summary.clone());
+        }
+        info!("[triage] summarizing finding");
+        let summary = self.complete(self.prompt(oracle_output, trace, affected))?;
+        self.summaries.insert(key, summary.clone());
+        Some(summary)
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::oracles::{REENTRANCY_BUG_IDX, TX_ORIGIN_BUG_IDX};
+
+    #[test]
+    fn test_triage_prompt() {
+        assert_eq!(truncate("abcdef", 10), "abcdef");
+        assert_eq!(truncate("abcdef", 3), "abc\n[truncated]");
+
+        let withdraw = AffectedFunction {
+            contract: EVMAddress::from_slice(&[1; 20]),
+            contract_name: Some("Vault".to_string()),
+            function: "withdraw".to_string(),
+        };
+        // the same oracles on the same functions share their summary
+        assert_eq!(
+            dedup_key(&vec![(1 << 8) + REENTRANCY_BUG_IDX], &[withdraw.clone()]),
+            dedup_key(&vec![(2 << 8) + REENTRANCY_BUG_IDX], &[withdraw.clone()])
+        );
+        assert_ne!(
+            dedup_key(&vec![REENTRANCY_BUG_IDX], &[withdraw.clone()]),
+            dedup_key(&vec![TX_ORIGIN_BUG_IDX], &[withdraw.clone()])
+        );
+
+        let triage = Triage {
+            endpoint: String::new(),
+            model: String::new(),
+            api_key: String::new(),
+            sourcemap: HashMap::new(),
+            summaries: HashMap::new(),
+        };
+        let prompt = triage.prompt("[Reentrancy] withdraw reentered\n", "[Sender] 0x...", &[withdraw]);
+        assert!(prompt.contains("## Oracle report\n[Reentrancy] withdraw reentered\n"));
+        assert!(!prompt.contains("## Source"));
+    }
+}
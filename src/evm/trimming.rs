@@ -0,0 +1,154 @@
+/// Trimming stage (`--trim`)
+///
+/// Once per corpus entry, tries to run the input on the ancestors of its VM state (shortening
+/// the transaction sequence leading to it) and to halve its dynamic args, keeping each change
+/// only if the coverage signature of the input (branches with hit count buckets, and whether it
+/// reverted) is preserved. Hit counts are bucketed by their magnitude.
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use libafl::corpus::Corpus;
+use libafl::prelude::HasMetadata;
+use libafl::stages::Stage;
+use libafl::state::HasCorpus;
+use libafl::{impl_serdeany, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::evm::host::JMP_MAP;
+use crate::evm::input::{ConciseEVMInput, EVMInput};
+use crate::evm::types::EVMFuzzState;
+use crate::evm::vm::{EVMExecutor, EVMState};
+use crate::generic_vm::vm_executor::{GenericVM, MAP_SIZE};
+use crate::input::VMInputT;
+use crate::state::HasInfantStateState;
+
+/// Maximum attempts to shrink the args of an input
+const MAX_SHRINK_ATTEMPTS: usize = 16;
+
+/// Metadata of [`TrimmingStage`] stored in the state
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TrimMetadata {
+    /// Corpus entries already trimmed
+    pub trimmed: HashSet<usize>,
+    /// Transactions removed from sequences
+    pub txs_removed: usize,
+    /// Bytes removed from args
+    pub bytes_removed: usize,
+}
+
+impl_serdeany!(TrimMetadata);
+
+pub struct TrimmingStage {
+    enabled: bool,
+    executor: Rc<RefCell<EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>>>,
+}
+
+impl std::fmt::Debug for TrimmingStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrimmingStage").field("enabled", &self.enabled).finish()
+    }
+}
+
+impl TrimmingStage {
+    pub fn new(
+        enabled: bool,
+        executor: Rc<RefCell<EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput>>>,
+    ) -> Self {
+        Self { enabled, executor }
+    }
+
+    /// Coverage signature of executing the input
+    fn signature(&self, input: &EVMInput, state: &mut EVMFuzzState) -> u64 {
+        unsafe {
+            JMP_MAP = [0; MAP_SIZE];
+        }
+        let res = self.executor.borrow_mut().execute(input, state);
+        let mut hasher = DefaultHasher::new();
+        res.reverted.hash(&mut hasher);
+        unsafe {
+            for (idx, hits) in JMP_MAP.iter().enumerate() {
+                if *hits > 0 {
+                    (idx, hits.leading_zeros()).hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+}
+
+impl<E, EM, Z> Stage<E, EM, EVMFuzzState, Z> for TrimmingStage {
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut EVMFuzzState,
+        _manager: &mut EM,
+        corpus_idx: usize,
+    ) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if !state.has_metadata::<TrimMetadata>() {
+            state.metadata_mut().insert(TrimMetadata::default());
+        }
+        if !state.metadata_mut().get_mut::<TrimMetadata>().unwrap().trimmed.insert(corpus_idx) {
+            return Ok(());
+        }
+
+        let original = state.corpus().get(corpus_idx)?.borrow_mut().load_input()?.clone();
+        // step inputs resume a call in their VM state, which can't be replaced
+        if original.is_step() || !original.get_staged_state().initialized {
+            return Ok(());
+        }
+        let signature = self.signature(&original, state);
+        let mut input = original.clone();
+        let mut txs_removed = 0;
+
+        // run the input on ancestors of its VM state
+        while let Some(parent_idx) = input.get_staged_state().trace.from_idx {
+            let parent = match state.get_infant_state_state().corpus().get(parent_idx) {
+                Ok(testcase) => testcase.borrow().input().clone(),
+                Err(_) => None,
+            };
+            let parent = match parent {
+                Some(parent) => parent,
+                None => break,
+            };
+            let removed = input.get_staged_state().trace.transactions.len();
+            let mut candidate = input.clone();
+            candidate.set_staged_state(parent, parent_idx);
+            if self.signature(&candidate, state) != signature {
+                break;
+            }
+            txs_removed += removed;
+            input = candidate;
+        }
+
+        // halve dynamic args
+        let args_size = |input: &EVMInput| input.get_data_abi().map(|abi| abi.get_bytes().len()).unwrap_or(0);
+        let size = args_size(&input);
+        for _ in 0..MAX_SHRINK_ATTEMPTS {
+            let mut candidate = input.clone();
+            let shrunk = match candidate.get_data_abi_mut() {
+                Some(abi) => abi.shrink(),
+                None => false,
+            };
+            if !shrunk || self.signature(&candidate, state) != signature {
+                break;
+            }
+            input = candidate;
+        }
+        let bytes_removed = size.saturating_sub(args_size(&input));
+
+        if txs_removed > 0 || bytes_removed > 0 {
+            let meta = state.metadata_mut().get_mut::<TrimMetadata>().unwrap();
+            meta.txs_removed += txs_removed;
+            meta.bytes_removed += bytes_removed;
+            *state.corpus().get(corpus_idx)?.borrow_mut().input_mut() = Some(input);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+/// Cost of the TWAP manipulations violating sequences rely on (`--twap-cost`)
+///
+/// A sequence relies on a TWAP when it reads the price accumulators of a Uniswap V2 pair
+/// (`price0CumulativeLast()` / `price1CumulativeLast()`) or the observations of a Uniswap V3 pool
+/// (`observe(uint32[])`) after moving the price of the pool. The capital needed to move the price
+/// that far is computed from the liquidity of the pool (constant product, fees ignored), and the
+/// number of blocks is the number of blocks the sequence lets pass while the price is moved, as a
+/// TWAP only accumulates the manipulated price over time.
+use std::collections::{BTreeMap, HashMap};
+
+use crate::evm::middlewares::call_tracer::{TraceStep, TxTrace};
+use crate::evm::replay::parse_sequence;
+use crate::evm::types::{EVMAddress, EVMU256};
+use crate::evm::uniswap::reserve_parser;
+
+/// Storage slot of the reserves of Uniswap V2 pairs
+const V2_RESERVES_SLOT: u64 = 8;
+/// Storage slot of slot0 (sqrtPriceX96, tick, ...) of Uniswap V3 pools
+const V3_SLOT0_SLOT: u64 = 0;
+/// Storage slot of the active liquidity of Uniswap V3 pools
+const V3_LIQUIDITY_SLOT: u64 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolKind {
+    V2,
+    V3,
+}
+
+impl PoolKind {
+    /// Kind of pool whose TWAP is read by a call, if any
+    fn of_twap_read(input: &str) -> Option<Self> {
+        match input.get(..8)? {
+            // price0CumulativeLast(), price1CumulativeLast()
+            "5909c0d5" | "5a3d5493" => Some(Self::V2),
+            // observe(uint32[])
+            "883bdbfd" => Some(Self::V3),
+            _ => None,
+        }
+    }
+
+    fn price_slot(&self) -> EVMU256 {
+        EVMU256::from(match self {
+            Self::V2 => V2_RESERVES_SLOT,
+            Self::V3 => V3_SLOT0_SLOT,
+        })
+    }
+}
+
+/// A TWAP manipulation a violating sequence relies on
+#[derive(Clone, Debug)]
+pub struct TwapManipulation {
+    pub pool: EVMAddress,
+    pub kind: PoolKind,
+    /// Largest move of the price of token0 (in token1), as a ratio to the initial price
+    pub price_ratio: f64,
+    /// Blocks passed while the price is moved
+    pub blocks: u64,
+    /// Token sold into the pool to move the price (0 or 1)
+    pub token_in: u8,
+    /// Capital needed, in multiples of the (virtual) reserve of the token sold into the pool
+    pub capital_multiple: f64,
+    /// Capital needed, in the token sold into the pool, if the liquidity of the pool is known
+    pub capital: Option<f64>,
+}
+
+impl TwapManipulation {
+    pub fn describe(&self) -> String {
+        format!(
+            "{:?} pool 0x{}: price moved {:.4}x for {} blocks, requires selling {} token{} ({:.4}x the pool reserve)",
+            self.kind,
+            hex::encode(self.pool),
+            self.price_ratio,
+            self.blocks,
+            self.capital.map_or("an unknown amount of".to_string(), |c| format!("{:.0}", c)),
+            self.token_in,
+            self.capital_multiple
+        )
+    }
+}
+
+pub fn to_f64(v: EVMU256) -> f64 {
+    v.as_limbs()
+        .iter()
+        .enumerate()
+        .map(|(i, limb)| *limb as f64 * 2f64.powi(64 * i as i32))
+        .sum()
+}
+
+/// Price of token0 in token1 and reserve of token0 / token1 (virtual for V3) of a pool, given its
+/// price slot and (for V3) its liquidity
+fn price_and_reserves(kind: PoolKind, slot: EVMU256, liquidity: Option<EVMU256>) -> (f64, Option<(f64, f64)>) {
+    match kind {
+        PoolKind::V2 => {
+            let (r0, r1) = reserve_parser(&slot);
+            let (r0, r1) = (to_f64(r0), to_f64(r1));
+            (if r0 == 0.0 { 0.0 } else { r1 / r0 }, Some((r0, r1)))
+        }
+        PoolKind::V3 => {
+            let sqrt_price = to_f64(slot & ((EVMU256::from(1) << 160) - EVMU256::from(1))) / 2f64.powi(96);
+            let reserves = liquidity
+                .map(to_f64)
+                .filter(|_| sqrt_price > 0.0)
+                .map(|l| (l / sqrt_price, l * sqrt_price));
+            (sqrt_price * sqrt_price, reserves)
+        }
+    }
+}
+
+/// TWAP manipulations a violating sequence (in the replayable format) relies on, given the
+/// trace of each of its transactions
+pub fn twap_manipulations(txs: &str, traces: &[TxTrace]) -> Vec<TwapManipulation> {
+    let inputs = parse_sequence(txs);
+
+    let mut read = BTreeMap::new();
+    // (address, slot) => (value before the sequence, (tx index, new value) of each write)
+    let mut writes: HashMap<(EVMAddress, EVMU256), (EVMU256, Vec<(usize, EVMU256)>)> = HashMap::new();
+    let mut liquidity = HashMap::new();
+    for (idx, trace) in traces.iter().enumerate() {
+        for step in &trace.steps {
+            match step {
+                TraceStep::Call { to, input, .. } => {
+                    if let Some(kind) = PoolKind::of_twap_read(input) {
+                        read.entry(*to).or_insert(kind);
+                    }
+                }
+                TraceStep::SLoad { address, slot, value, .. } if *slot == EVMU256::from(V3_LIQUIDITY_SLOT) => {
+                    liquidity.entry(*address).or_insert(*value);
+                }
+                TraceStep::SStore { address, slot, old, new, .. } => {
+                    if *slot == EVMU256::from(V3_LIQUIDITY_SLOT) {
+                        liquidity.entry(*address).or_insert(*old);
+                    }
+                    writes
+                        .entry((*address, *slot))
+                        .or_insert((*old, vec![]))
+                        .1
+                        .push((idx, *new));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    read.into_iter()
+        .filter_map(|(pool, kind)| {
+            let (initial, moves) = writes.get(&(pool, kind.price_slot()))?;
+            let (initial_price, reserves) = price_and_reserves(kind, *initial, liquidity.get(&pool).cloned());
+            if initial_price == 0.0 {
+                return None;
+            }
+            // the move furthest from the initial price
+            let (first_move, price_ratio) = moves
+                .iter()
+                .map(|(idx, value)| (*idx, price_and_reserves(kind, *value, None).0 / initial_price))
+                .fold((None, 1.0f64), |(first, ratio), (idx, r)| {
+                    (first.or(Some(idx)), if r.ln().abs() > ratio.ln().abs() { r } else { ratio })
+                });
+            let blocks = inputs
+                .iter()
+                .skip(first_move? + 1)
+                .map(|tx| tx.advance.blocks)
+                .sum();
+            // selling token1 raises the price of token0, and the other way around
+            let (token_in, capital_multiple) = if price_ratio >= 1.0 {
+                (1, price_ratio.sqrt() - 1.0)
+            } else {
+                (0, 1.0 / price_ratio.sqrt() - 1.0)
+            };
+            let capital = reserves.map(|(r0, r1)| capital_multiple * if token_in == 0 { r0 } else { r1 });
+            Some(TwapManipulation {
+                pool,
+                kind,
+                price_ratio,
+                blocks,
+                token_in,
+                capital_multiple,
+                capital,
+            })
+        })
+        .collect()
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    fn reserves(r0: u64, r1: u64) -> EVMU256 {
+        (EVMU256::from(r0) << 112) | EVMU256::from(r1)
+    }
+
+    #[test]
+    fn test_v2_manipulation() {
+        let pool = fixed_address("1111111111111111111111111111111111111111");
+        let trace = |steps: Vec<TraceStep>| TxTrace {
+            tx: String::new(),
+            reverted: false,
+            output: String::new(),
+            steps,
+        };
+        let traces = vec![
+            // price of token0 quadrupled by selling token1
+            trace(vec![TraceStep::SStore {
+                address: pool,
+                slot: EVMU256::from(V2_RESERVES_SLOT),
+                old: reserves(1000, 1000),
+                new: reserves(500, 2000),
+                label: None,
+            }]),
+            trace(vec![TraceStep::Call {
+                kind: "STATICCALL".to_string(),
+                from: EVMAddress::zero(),
+                to: pool,
+                value: EVMU256::ZERO,
+                input: "5909c0d5".to_string(),
+            }]),
+        ];
+        let manipulations = twap_manipulations("", &traces);
+        assert_eq!(manipulations.len(), 1);
+        let m = &manipulations[0];
+        assert_eq!(m.kind, PoolKind::V2);
+        assert!((m.price_ratio - 4.0).abs() < 1e-9);
+        assert_eq!(m.token_in, 1);
+        assert!((m.capital_multiple - 1.0).abs() < 1e-9);
+        assert!((m.capital.unwrap() - 1000.0).abs() < 1e-6);
+    }
+}
@@ -54,6 +54,13 @@ pub fn convert_u256_to_h160(v: EVMU256) -> EVMAddress {
     EVMAddress::from_slice(&data[12..32])
 }
 
+/// convert H160 to U256 by left padding with zeros
+pub fn convert_h160_to_u256(v: EVMAddress) -> EVMU256 {
+    let mut data: [u8; 32] = [0; 32];
+    data[12..32].copy_from_slice(v.0.as_slice());
+    EVMU256::from_be_bytes(data)
+}
+
 /// multiply a float by 10^decimals and convert to U512
 pub fn float_scale_to_u512(v: f64, decimals: u32) -> U512 {
     // todo(@shou) make this sound
@@ -97,10 +104,17 @@ pub fn bytes_to_u64(v: &[u8]) -> u64 {
 }
 
 mod tests {
-    use crate::evm::types::{as_u64, EVMU256};
+    use crate::evm::types::{as_u64, convert_h160_to_u256, convert_u256_to_h160, fixed_address, EVMU256};
 
     #[test]
     fn test_as_u64() {
         assert_eq!(as_u64(EVMU256::from(100)), 100)
     }
+
+    #[test]
+    fn test_convert_h160_to_u256() {
+        let addr = fixed_address("8EF508Aca04B32Ff3ba5003177cb18BfA6Cd79dd");
+        assert_eq!(convert_u256_to_h160(convert_h160_to_u256(addr)), addr);
+        assert_eq!(convert_h160_to_u256(fixed_address("0000000000000000000000000000000000000100")), EVMU256::from(256));
+    }
 }
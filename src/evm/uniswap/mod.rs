@@ -1,5 +1,7 @@
 use crate::evm::abi::{AArray, AEmpty, BoxedABI, A256};
+use tracing::debug;
 
+use crate::evm::known_addresses::known_addresses;
 use crate::evm::onchain::endpoints::Chain;
 
 use crypto::digest::Digest;
@@ -46,6 +48,17 @@ impl UniswapProvider {
             _ => None,
         }
     }
+
+    /// Name of the provider in the known addresses
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PancakeSwap => "pancakeswap",
+            Self::SushiSwap => "sushiswap",
+            Self::UniswapV2 => "uniswapv2",
+            Self::UniswapV3 => "uniswapv3",
+            Self::Biswap => "biswap",
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -161,40 +174,94 @@ pub struct TokenContext {
     pub address: EVMAddress,
 }
 
+/// Scale of [`PathContext::final_pegged_ratio`] (i.e., the ratio of WETH itself)
+pub const PEGGED_RATE_SCALE: u32 = 1_000_000;
+
+fn current_reserves(pair: &PairContext, reserve_data: &HashMap<EVMAddress, (EVMU256, EVMU256)>) -> (EVMU256, EVMU256) {
+    match reserve_data.get(&pair.pair_address) {
+        None => pair.initial_reserves,
+        Some(reserves) => reserves.clone(),
+    }
+}
+
+/// Sell `amount_in` of the token on the `side` of the pair, returns the amount of the other token
+/// received, and updates the reserves of the pair in `reserve_data`
+fn swap_out(
+    pair: &PairContext,
+    amount_in: EVMU256,
+    reserve_data: &mut HashMap<EVMAddress, (EVMU256, EVMU256)>,
+) -> EVMU256 {
+    let reserves = current_reserves(pair, reserve_data);
+    let swap_result = pair.get_amount_out(amount_in, reserves.0, reserves.1);
+    reserve_data.insert(
+        pair.pair_address,
+        if pair.side == 0 {
+            (swap_result.new_reserve_in, swap_result.new_reserve_out)
+        } else {
+            (swap_result.new_reserve_out, swap_result.new_reserve_in)
+        },
+    );
+    swap_result.amount
+}
+
+/// Buy `amount_out` of the token on the `side` of the pair, returns the amount of the other token
+/// paid (None if the pair can't provide it), and updates the reserves of the pair in `reserve_data`
+fn swap_in(
+    pair: &PairContext,
+    amount_out: EVMU256,
+    reserve_data: &mut HashMap<EVMAddress, (EVMU256, EVMU256)>,
+) -> Option<EVMU256> {
+    let reserves = current_reserves(pair, reserve_data);
+    let available = if pair.side == 0 { reserves.0 } else { reserves.1 };
+    if amount_out >= available {
+        return None;
+    }
+    let swap_result = pair.get_amount_in(amount_out, reserves.0, reserves.1);
+    reserve_data.insert(
+        pair.pair_address,
+        if pair.side == 0 {
+            (swap_result.new_reserve_out, swap_result.new_reserve_in)
+        } else {
+            (swap_result.new_reserve_in, swap_result.new_reserve_out)
+        },
+    );
+    Some(swap_result.amount)
+}
+
 impl PathContext {
+    /// WETH (scaled by [`PEGGED_RATE_SCALE`]) received by selling `amount_in` of the token
+    /// through the route, with the price impact of each swap
     pub fn get_amount_out(
         &self,
         amount_in: EVMU256,
         reserve_data: &mut HashMap<EVMAddress, (EVMU256, EVMU256)>,
     ) -> EVMU256 {
         let mut amount_in = amount_in;
+        for pair in self.route.iter() {
+            amount_in = swap_out(&pair.deref().borrow(), amount_in, reserve_data);
+        }
+        match self.final_pegged_pair.deref().borrow().as_ref() {
+            // sell the pegged token for WETH on its pair rather than at the spot rate
+            Some(pegged) => swap_out(pegged, amount_in, reserve_data) * EVMU256::from(PEGGED_RATE_SCALE),
+            None => amount_in * self.final_pegged_ratio,
+        }
+    }
 
-        // address => (new reserve0, new reserve1)
+    /// WETH (scaled by [`PEGGED_RATE_SCALE`]) needed to buy `amount_out` of the token through
+    /// the route, with the price impact of each swap, None if the route lacks liquidity
+    pub fn get_cost(
+        &self,
+        amount_out: EVMU256,
+        reserve_data: &mut HashMap<EVMAddress, (EVMU256, EVMU256)>,
+    ) -> Option<EVMU256> {
+        let mut amount = amount_out;
         for pair in self.route.iter() {
-            let p = pair.deref().borrow();
-            let reserves = match reserve_data.get(&p.pair_address) {
-                None => p.initial_reserves,
-                Some(reserves) => reserves.clone(),
-            };
-            let swap_result = p.get_amount_out(amount_in, reserves.0, reserves.1);
-            reserve_data.insert(
-                p.pair_address,
-                (
-                    if p.side == 0 {
-                        swap_result.new_reserve_in
-                    } else {
-                        swap_result.new_reserve_out
-                    },
-                    if p.side == 0 {
-                        swap_result.new_reserve_out
-                    } else {
-                        swap_result.new_reserve_in
-                    },
-                ),
-            );
-            amount_in = swap_result.amount;
+            amount = swap_in(&pair.deref().borrow(), amount, reserve_data)?;
+        }
+        match self.final_pegged_pair.deref().borrow().as_ref() {
+            Some(pegged) => Some(swap_in(pegged, amount, reserve_data)? * EVMU256::from(PEGGED_RATE_SCALE)),
+            None => Some(amount * self.final_pegged_ratio),
         }
-        amount_in * self.final_pegged_ratio
     }
 
     pub fn get_amount_in(
@@ -215,7 +282,7 @@ impl PathContext {
                 initial_reserve.1
             }
         } * EVMU256::from(percentage) / EVMU256::from(1000);
-        println!("amount_out: {}", amount_out);
+        debug!("amount_out: {}", amount_out);
 
         // address => (new reserve0, new reserve1)
 
@@ -386,30 +453,50 @@ pub fn liquidate_all_token(
     )
 }
 
+/// WETH (scaled by [`PEGGED_RATE_SCALE`]) needed to buy back all tokens, each one through its
+/// cheapest route given the price impact of the previous purchases, and the resulting reserves
+///
+/// Tokens without any route are ignored (as in [`liquidate_all_token`]), tokens no route has
+/// enough liquidity for can't be bought back, so their cost is [`EVMU256::MAX`].
+pub fn acquire_all_token(
+    tokens: Vec<(&TokenContext, EVMU256)>,
+    initial_reserve_data: HashMap<EVMAddress, (EVMU256, EVMU256)>,
+) -> (EVMU256, HashMap<EVMAddress, (EVMU256, EVMU256)>) {
+    let mut reserve_data = initial_reserve_data;
+    let mut total_cost = EVMU256::ZERO;
+    for (token, amt) in tokens {
+        if token.swaps.len() == 0 {
+            continue;
+        }
+        let cheapest = token
+            .swaps
+            .iter()
+            .filter_map(|path| {
+                let mut reserves = reserve_data.clone();
+                path.get_cost(amt, &mut reserves).map(|cost| (cost, reserves))
+            })
+            .min_by_key(|(cost, _)| *cost);
+        match cheapest {
+            Some((cost, reserves)) => {
+                total_cost = total_cost.saturating_add(cost);
+                reserve_data = reserves;
+            }
+            None => return (EVMU256::MAX, reserve_data),
+        }
+    }
+    (total_cost, reserve_data)
+}
+
 pub fn get_uniswap_info(provider: &UniswapProvider, chain: &Chain) -> UniswapInfo {
-    match (provider, chain) {
-        (&UniswapProvider::PancakeSwap, &Chain::BSC) => UniswapInfo {
-            pool_fee: 25,
-            router: EVMAddress::from_str("0x10ed43c718714eb63d5aa57b78b54704e256024e").unwrap(),
-            factory: EVMAddress::from_str("0xca143ce32fe78f1f7019d7d551a6402fc5350c73").unwrap(),
-            init_code_hash: hex::decode(
-                "00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5",
-            )
-            .unwrap(),
-        },
-        (&UniswapProvider::UniswapV2, &Chain::ETH) => UniswapInfo {
-            pool_fee: 3,
-            router: EVMAddress::from_str("0x7a250d5630b4cf539739df2c5dacb4c659f2488d").unwrap(),
-            factory: EVMAddress::from_str("0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f").unwrap(),
-            init_code_hash: hex::decode(
-                "96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f",
-            )
-            .unwrap(),
-        },
-        _ => panic!(
-            "Uniswap provider {:?} @ chain {:?} not supported",
-            provider, chain
-        ),
+    let info = known_addresses()
+        .chain(&chain.to_lowercase())
+        .and_then(|addresses| addresses.router(provider.name()))
+        .unwrap_or_else(|| panic!("Uniswap provider {:?} @ chain {:?} not supported", provider, chain));
+    UniswapInfo {
+        pool_fee: info.pool_fee,
+        router: EVMAddress::from_str(&info.router).expect("invalid router address"),
+        factory: EVMAddress::from_str(&info.factory).expect("invalid factory address"),
+        init_code_hash: hex::decode(&info.init_code_hash).expect("invalid init code hash"),
     }
 }
 
@@ -445,10 +532,6 @@ impl UniswapInfo {
         reserve_in: EVMU256,
         reserve_out: EVMU256,
     ) -> SwapResult {
-        println!("calculate_amounts_in amount_out: {}", amount_out);
-        println!("calculate_amounts_in reserve_in: {}", reserve_in);
-        println!("calculate_amounts_in reserve_out: {}", reserve_out);
-
         let adjusted_amount_out = if amount_out > reserve_out {
             reserve_out - EVMU256::from(1)
         } else {
@@ -465,7 +548,6 @@ impl UniswapInfo {
             };
         }
         let amount_in = (numerator / denominator) + EVMU256::from(1);
-        println!("calculate_amounts_in amount_in: {}", amount_in);
         SwapResult {
             amount: amount_in,
             new_reserve_in: reserve_in + amount_in,
@@ -862,6 +944,45 @@ mod tests {
         assert_eq!(amt, EVMU256::from(49 as u64));
     }
 
+    #[test]
+    fn test_acquire_all_token() {
+        // 1000 * 100 * 10000 / (900 * 9975) + 1 = 112
+        let mut reserve_data = HashMap::new();
+        reserve_data.insert(
+            EVMAddress::from_str("0x0000000000000000000000000000000000000000").unwrap(),
+            (EVMU256::from(1000), EVMU256::from(1000 as u64)),
+        );
+        let t1 = TokenContext {
+            swaps: vec![PathContext {
+                route: vec![wrap!(PairContext {
+                    pair_address: EVMAddress::from_str("0x0000000000000000000000000000000000000000").unwrap(),
+                    side: 0,
+                    uniswap_info: Arc::new(get_uniswap_info(&UniswapProvider::PancakeSwap, &Chain::BSC)),
+                    initial_reserves: (Default::default(), Default::default()),
+                    next_hop: Default::default(),
+                })],
+                final_pegged_ratio: EVMU256::from(1),
+                final_pegged_pair: Rc::new(RefCell::new(None)),
+            }],
+            is_weth: false,
+            weth_address: Default::default(),
+            address: Default::default(),
+        };
+        let (cost, reserves) = acquire_all_token(vec![(&t1, EVMU256::from(100))], reserve_data.clone());
+        assert_eq!(cost, EVMU256::from(112));
+        assert_eq!(
+            reserves.get(&EVMAddress::from_str("0x0000000000000000000000000000000000000000").unwrap()),
+            Some(&(EVMU256::from(900), EVMU256::from(1112)))
+        );
+
+        // buying back costs more than selling earns
+        let (earned, _) = liquidate_all_token(vec![(&t1, EVMU256::from(100))], reserve_data.clone());
+        assert_eq!(earned, EVMU256::from(90));
+
+        let (cost, _) = acquire_all_token(vec![(&t1, EVMU256::from(1000))], reserve_data);
+        assert_eq!(cost, EVMU256::MAX);
+    }
+
     #[test]
     fn test_reserve_parser() {
         let (r0, r1) = reserve_parser(&EVMU256::from_str_radix(
@@ -1,5 +1,6 @@
 /// EVM executor implementation
 use itertools::Itertools;
+use tracing::{debug, warn};
 use std::borrow::{Borrow, BorrowMut};
 use std::cell::RefCell;
 use std::cmp::{max, min};
@@ -36,15 +37,29 @@ use revm_interpreter::{CallContext, CallScheme, Contract, InstructionResult, Int
 use revm_interpreter::InstructionResult::ControlLeak;
 use revm_primitives::{Bytecode, LatestSpec};
 
+use crate::evm::budget::record_abort;
 use crate::evm::bytecode_analyzer;
+use crate::evm::call_value::record_value;
+use crate::evm::seq_length::record_length;
+use crate::evm::middlewares::native_flow::transfer;
 use crate::evm::host::{
     FuzzHost, CMP_MAP, COVERAGE_NOT_CHANGED, GLOBAL_CALL_CONTEXT, JMP_MAP, READ_MAP,
     RET_OFFSET, RET_SIZE, STATE_CHANGE, WRITE_MAP,
 };
-use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
+use crate::evm::input::{BlockAdvance, ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy, INTERMEDIARY, VICTIM_TXS};
 use crate::evm::middlewares::middleware::{Middleware, MiddlewareType};
+use crate::evm::middlewares::call_tracer::CallTracer;
 use crate::evm::onchain::flashloan::FlashloanData;
-use crate::evm::types::{EVMAddress, EVMU256};
+use crate::evm::oracles::sandwich::output_amount;
+use crate::evm::signature::SignatureRecord;
+use crate::evm::differential::{divergence, AddressNormalizer, Effects};
+use crate::evm::middlewares::block_context::BlockContextPayout;
+use crate::evm::middlewares::constructor::ConstructorFinding;
+use crate::evm::middlewares::rounding::RoundingError;
+use crate::evm::middlewares::truncation::Truncation;
+use crate::evm::middlewares::unchecked_call::UncheckedCall;
+use crate::evm::middlewares::event_collector::EmittedEvent;
+use crate::evm::types::{fixed_address, EVMAddress, EVMU256};
 use crate::evm::uniswap::generate_uniswap_router_call;
 use crate::generic_vm::vm_executor::{ExecutionResult, GenericVM, MAP_SIZE};
 use crate::generic_vm::vm_state::VMStateT;
@@ -114,6 +129,11 @@ pub struct PostExecutionCtx {
 
     pub must_step: bool,
     pub constraints: Vec<Constraint>,
+
+    /// Return data of the call that leaked control, if it is known (e.g., the selector returned
+    /// by token hooks), otherwise it is provided by the step input
+    #[serde(default)]
+    pub return_data: Option<Vec<u8>>,
 }
 
 impl PostExecutionCtx {
@@ -152,11 +172,61 @@ pub struct EVMState {
     pub selfdestruct_hit: bool,
     /// bug type call in solidity type
     pub typed_bug: HashSet<String>,
+    /// control handed to EIP-7702 delegated EOA that passed an EOA check?
+    pub delegated_eoa_hit: bool,
+    /// EIP-1153 transient storage, cleared when the transaction (including reentrant calls) finishes
+    pub transient_storage: HashMap<EVMAddress, HashMap<EVMU256, EVMU256>>,
+    /// Estimated gas used by the last transaction (0 if gas is not estimated)
+    pub gas_used: u64,
+    /// Whether the last transaction is halted for exceeding the gas ceiling
+    pub gas_exceeded: bool,
+    /// Output of the last transaction executed on the storage before the transaction preceding
+    /// it, if it is a victim transaction (i.e., what the victim expects without being front-run)
+    pub victim_fair_output: Option<Vec<u8>>,
+    /// Storage before the last transaction of the sequence (with victim transactions), victim
+    /// transactions are also executed on it to tell what the preceding transaction costs them
+    #[serde(skip)]
+    pub before_last_tx: Option<HashMap<EVMAddress, HashMap<EVMU256, EVMU256>>>,
+    /// Total amount victim transactions in the sequence received less than expected
+    pub victim_loss: EVMU256,
+    /// Signatures observed in the sequence (with signatures modeled)
+    pub signatures: Vec<SignatureRecord>,
+    /// tx.origin authorization checks (contract, pc) passed while msg.sender differs from tx.origin
+    pub origin_auth: HashSet<(EVMAddress, usize)>,
+    /// Arithmetic results truncated by narrowing casts and stored
+    pub truncations: Vec<Truncation>,
+    /// Payouts to the caller depending on the block context (coinbase, basefee, prevrandao)
+    pub block_context_payouts: Vec<BlockContextPayout>,
+    /// Bugs of the constructors of the targets, found during their deployment
+    pub constructor_findings: Vec<ConstructorFinding>,
+    /// Low-level calls whose success flag is not checked before changing state
+    pub unchecked_calls: Vec<UncheckedCall>,
+    /// Events of interest emitted along the sequence leading to this state
+    pub events: Vec<EmittedEvent>,
+    /// Stored values diverging from their exact (arbitrary-precision) value
+    pub rounding_errors: Vec<RoundingError>,
+    /// Divergence of the last transaction between the target and the shadow version
+    /// (with differential fuzzing)
+    pub differential: Option<String>,
+    /// Blocks and time advanced by the sequence leading to the state
+    pub advance: BlockAdvance,
+    /// Number of transactions of the sequence leading to the state
+    pub depth: usize,
+    /// Native ETH held by the tracked contracts (`--eth-accounting`)
+    pub native_balances: HashMap<EVMAddress, EVMU256>,
+    /// Deployment nonces of accounts, used for zkSync Era address derivation, part of the state
+    /// so that contracts created by a sequence get the same addresses when it is replayed
+    pub deployment_nonces: HashMap<EVMAddress, u64>,
+    /// Addresses from the calldata of the sequence written to storage (with delegatecall
+    /// hijacking), i.e., attacker-controlled addresses in writable storage
+    pub attacker_addresses: HashSet<EVMAddress>,
 }
 
 
 pub trait EVMStateT {
     fn get_constraints(&self) -> Vec<Constraint>;
+    /// Number of transactions of the sequence leading to the state
+    fn get_depth(&self) -> usize;
 }
 
 impl EVMStateT for EVMState {
@@ -166,6 +236,10 @@ impl EVMStateT for EVMState {
             None => vec![],
         }
     }
+
+    fn get_depth(&self) -> usize {
+        self.depth
+    }
 }
 
 
@@ -180,6 +254,27 @@ impl Default for EVMState {
             bug_hit: false,
             selfdestruct_hit: false,
             typed_bug: Default::default(),
+            delegated_eoa_hit: false,
+            transient_storage: Default::default(),
+            gas_used: 0,
+            gas_exceeded: false,
+            victim_fair_output: None,
+            before_last_tx: None,
+            victim_loss: EVMU256::ZERO,
+            signatures: vec![],
+            origin_auth: Default::default(),
+            truncations: vec![],
+            block_context_payouts: vec![],
+            constructor_findings: vec![],
+            unchecked_calls: vec![],
+            events: vec![],
+            rounding_errors: vec![],
+            differential: None,
+            advance: Default::default(),
+            depth: 0,
+            native_balances: HashMap::new(),
+            deployment_nonces: HashMap::new(),
+            attacker_addresses: HashSet::new(),
         }
     }
 }
@@ -199,6 +294,13 @@ impl VMStateT for EVMState {
                 j.1.hash(&mut s);
             }
         }
+        if !self.advance.is_zero() {
+            self.advance.hash(&mut s);
+        }
+        for (address, balance) in self.native_balances.iter().sorted_by_key(|k| k.0) {
+            address.0.hash(&mut s);
+            balance.hash(&mut s);
+        }
         s.finish()
     }
 
@@ -251,6 +353,27 @@ impl EVMState {
             bug_hit: false,
             selfdestruct_hit: false,
             typed_bug: Default::default(),
+            delegated_eoa_hit: false,
+            transient_storage: Default::default(),
+            gas_used: 0,
+            gas_exceeded: false,
+            victim_fair_output: None,
+            before_last_tx: None,
+            victim_loss: EVMU256::ZERO,
+            signatures: vec![],
+            origin_auth: Default::default(),
+            truncations: vec![],
+            block_context_payouts: vec![],
+            constructor_findings: vec![],
+            unchecked_calls: vec![],
+            events: vec![],
+            rounding_errors: vec![],
+            differential: None,
+            advance: Default::default(),
+            depth: 0,
+            native_balances: HashMap::new(),
+            deployment_nonces: HashMap::new(),
+            attacker_addresses: HashSet::new(),
         }
     }
 
@@ -264,6 +387,11 @@ impl EVMState {
         self.state.get_mut(address)
     }
 
+    /// Get all storage slots of a specific contract (mutable), empty if the contract has none
+    pub fn get_or_default(&mut self, address: EVMAddress) -> &mut HashMap<EVMU256, EVMU256> {
+        self.state.entry(address).or_default()
+    }
+
     /// Insert all storage slots of a specific contract
     pub fn insert(&mut self, address: EVMAddress, storage: HashMap<EVMU256, EVMU256>) {
         self.state.insert(address, storage);
@@ -277,6 +405,32 @@ pub static mut IS_FAST_CALL: bool = false;
 /// - Fast call is a call that does not change the state of the contract
 pub static mut IS_FAST_CALL_STATIC: bool = false;
 
+/// Whether coverage of executions that end up reverting is credited (i.e., kept in the
+/// coverage map), as the revert often happens deep in interesting code
+pub static mut REVERT_COVERAGE: bool = true;
+
+/// Whether an execution ending with `ret` is reverted
+fn is_reverted(ret: &InstructionResult) -> bool {
+    !matches!(
+        ret,
+        InstructionResult::Return |
+            InstructionResult::Stop |
+            InstructionResult::ControlLeak |
+            InstructionResult::SelfDestruct |
+            InstructionResult::ArbitraryExternalCallAddressBounded(_, _)
+    )
+}
+
+/// Roll back the coverage of a reverted execution unless `credit` is set, returns whether
+/// the coverage was rolled back
+fn rollback_coverage(jmp_map: &mut [u8], reverted: bool, credit: bool) -> bool {
+    if !reverted || credit {
+        return false;
+    }
+    jmp_map.iter_mut().for_each(|hit| *hit = 0);
+    true
+}
+
 /// EVM executor, wrapper of revm
 #[derive(Debug, Clone)]
 pub struct EVMExecutor<I, S, VS, CI>
@@ -291,6 +445,14 @@ where
     deployer: EVMAddress,
     /// Known arbitrary (caller,pc)
     pub _known_arbitrary: HashSet<(EVMAddress, usize)>,
+    /// Differential fuzzing, (target, shadow): transactions to the target are also executed
+    /// on the shadow version and compared
+    pub differential: Option<(EVMAddress, EVMAddress)>,
+    /// Replaces the address of the shadow version by the address of the target in its effects
+    differential_normalizer: Option<AddressNormalizer>,
+    /// Tracer attached to the host, cleared before each transaction so that it only contains
+    /// the steps of the last one (e.g., for oracle plugins)
+    pub tx_tracer: Option<Rc<RefCell<CallTracer>>>,
     phandom: PhantomData<(I, S, VS, CI)>,
 }
 
@@ -335,10 +497,47 @@ where
             host: fuzz_host,
             deployer,
             _known_arbitrary: Default::default(),
+            differential: None,
+            differential_normalizer: None,
+            tx_tracer: None,
             phandom: PhantomData,
         }
     }
 
+    /// Execute transactions to `target` on `shadow` as well and compare their effects
+    pub fn set_differential(&mut self, target: EVMAddress, shadow: EVMAddress) {
+        self.differential = Some((target, shadow));
+        self.differential_normalizer = Some(AddressNormalizer::new(shadow, target));
+    }
+
+    /// Call `address` on the state of the host and keep the changes if the call succeeds (e.g.,
+    /// approvals given by the senders before the campaign), returns whether it succeeded
+    pub fn setup_call(&mut self, address: EVMAddress, data: Bytes, value: EVMU256, from: EVMAddress, state: &mut S) -> bool {
+        let code = match self.host.code.get(&address) {
+            Some(code) => code.clone(),
+            None => return false,
+        };
+        let call = Contract::new_with_context_analyzed(
+            data,
+            code,
+            &CallContext {
+                address,
+                caller: from,
+                code_address: address,
+                apparent_value: value,
+                scheme: CallScheme::Call,
+            },
+        );
+        let snapshot = self.host.evmstate.clone();
+        let mut interp = Interpreter::new(call, 1e10 as u64, false);
+        let ret = self.host.run_inspect(&mut interp, state);
+        if !matches!(ret, InstructionResult::Return | InstructionResult::Stop) {
+            self.host.evmstate = snapshot;
+            return false;
+        }
+        true
+    }
+
     /// Execute from a specific program counter and context
     ///
     /// `call_ctx` is the context of the call (e.g., caller address, callee address, etc.)
@@ -367,6 +566,12 @@ where
             self.host.bug_hit = false;
             self.host.selfdestruct_hit = false;
             self.host.current_typed_bug = vec![];
+            self.host.eoa_checked = false;
+            self.host.delegated_eoa_hit = false;
+            self.host.gas_used = 0;
+            if let Some(budget) = self.host.budget.as_mut() {
+                budget.reset();
+            }
             // Initially, there is no state change
             unsafe {
                 STATE_CHANGE = false;
@@ -374,10 +579,34 @@ where
         }
 
         self.host.evmstate = vm_state.clone();
+        // transient storage lives until the transaction finishes, reentrant calls on
+        // control leaked states still see it (e.g., transient reentrancy locks)
+        if post_exec.is_none() && !vm_state.has_post_execution() {
+            self.host.evmstate.transient_storage.clear();
+        }
         self.host.env = input.get_vm_env().clone();
+        // delegated EOAs (EIP-7702) send their transactions themselves, tx.origin is the caller
+        if self.host.delegations.contains_key(&self.host.origin) {
+            self.host.env.tx.caller = self.host.origin;
+        }
+        // blocks and time advanced by the sequence so far (including this transaction)
+        self.host.env.block.number = self.host.env.block.number.saturating_add(EVMU256::from(vm_state.advance.blocks));
+        self.host.env.block.timestamp = self.host.env.block.timestamp.saturating_add(EVMU256::from(vm_state.advance.seconds));
+        if let Some(chain_spec) = &self.host.chain_spec {
+            chain_spec.apply_env(&mut self.host.env);
+        }
+        if let Some(bounds) = &self.host.env_bounds {
+            if bounds.enforce {
+                bounds.clamp(&mut self.host.env);
+            }
+        }
+        self.host.blob_env = input.get_blob_env().clone();
         self.host.access_pattern = input.get_access_pattern().clone();
         self.host.call_count = 0;
         self.host.randomness = input.get_randomness();
+        if post_exec.is_none() {
+            self.host.tx_input = data.clone();
+        }
         let mut repeats = input.get_repeat();
         // Ensure that the call context is correct
         unsafe {
@@ -391,7 +620,7 @@ where
             .get(&call_ctx.code_address) {
             Some(i) => i.clone(),
             None => {
-                println!("no code @ {:?}, did you forget to deploy?", call_ctx.code_address);
+                warn!("no code @ {:?}, did you forget to deploy?", call_ctx.code_address);
                 return IntermediateExecutionResult {
                     output: Bytes::new(),
                     new_state: EVMState::default(),
@@ -427,13 +656,17 @@ where
                     post_exec_ctx.memory.len(),
                 ));
                 interp.memory.set(0, &post_exec_ctx.memory);
+                let ret = match &post_exec_ctx.return_data {
+                    Some(ret) => Bytes::from(ret.clone()),
+                    // we remove the first 4 bytes because the first 4 bytes is the function hash (00000000 here)
+                    None => data.slice(4..),
+                };
                 interp.memory.set(
                     post_exec_ctx.output_offset,
-                    &data[4..min(post_exec_ctx.output_len + 4, data.len())],
+                    &ret[..min(post_exec_ctx.output_len, ret.len())],
                 );
                 // set return buffer as the input
-                // we remove the first 4 bytes because the first 4 bytes is the function hash (00000000 here)
-                interp.return_data_buffer = data.slice(4..);
+                interp.return_data_buffer = ret;
                 interp
             }
         } else {
@@ -579,6 +812,47 @@ where
         }
 
         let mut cleanup = true;
+        let pre_vm_state = vm_state.clone();
+        // advance blocks and time before the transaction, kept in the resulting state
+        if !is_step {
+            vm_state.advance = vm_state.advance.add(input.get_advance());
+            vm_state.depth += 1;
+            // ETH force-fed before the transaction and sent along with it
+            transfer(&mut vm_state, None, input.get_contract(), input.get_force_feed());
+            transfer(&mut vm_state, None, input.get_contract(), input.get_txn_value().unwrap_or(EVMU256::ZERO));
+        }
+
+        if let Some(tracer) = &self.tx_tracer {
+            tracer.deref().borrow_mut().take();
+        }
+
+        // execute the victim transaction without the preceding transaction (i.e., the front-run)
+        // first to get what it expects
+        let mut victim_fair_output = None;
+        if input.is_victim() && !is_step {
+            if let Some(storage) = pre_vm_state.before_last_tx.clone() {
+                let mut baseline = pre_vm_state.clone();
+                baseline.state = storage;
+                let fair = self.fast_call(
+                    input.get_contract(),
+                    data.clone(),
+                    unsafe { VMStateT::as_any(&baseline).downcast_ref_unchecked::<VS>() },
+                    state,
+                    input.get_txn_value().unwrap_or(EVMU256::ZERO),
+                    input.get_caller(),
+                );
+                if fair.ret == InstructionResult::Return || fair.ret == InstructionResult::Stop {
+                    victim_fair_output = Some(fair.output.to_vec());
+                }
+            }
+        }
+
+        // the same transaction is executed on the shadow version later
+        let differential = self
+            .differential
+            .filter(|(target, _)| *target == input.get_contract() && !is_step);
+        let differential_data = data.clone();
+        self.host.recorded_logs = differential.map(|_| vec![]);
 
         loop {
             // Execute the transaction
@@ -605,7 +879,8 @@ where
                 self.execute_from_pc(
                     &CallContext {
                         address: contract_address,
-                        caller,
+                        // the caller interacts with the intermediary, which then calls the contract
+                        caller: if input.is_via_intermediary() { fixed_address(INTERMEDIARY) } else { caller },
                         code_address: contract_address,
                         apparent_value: value,
                         scheme: CallScheme::Call,
@@ -630,6 +905,46 @@ where
             }
         }
         let mut r = r.unwrap();
+
+        // the execution exceeds its step / time budget, it is aborted without effect and its
+        // coverage is discarded so that it doesn't get into the corpus
+        if self.host.budget_exceeded() {
+            record_abort();
+            unsafe {
+                JMP_MAP.iter_mut().for_each(|hit| *hit = 0);
+                STATE_CHANGE = false;
+            }
+            self.host.coverage_changed = false;
+            return ExecutionResult {
+                output: vec![],
+                reverted: true,
+                new_state: StagedVMState::new_uninitialized(),
+                additional_info: None,
+            };
+        }
+
+        // the transaction runs out of the gas ceiling, it doesn't revert so that the
+        // gas bomb oracle sees it, but the state is left untouched
+        if self.host.gas_exceeded() {
+            let mut new_state = pre_vm_state;
+            new_state.gas_used = self.host.gas_used;
+            new_state.gas_exceeded = true;
+            new_state.victim_fair_output = None;
+            unsafe {
+                STATE_CHANGE = false;
+                return ExecutionResult {
+                    output: vec![],
+                    reverted: false,
+                    new_state: StagedVMState::new_with_state(
+                        VMStateT::as_any(&new_state)
+                            .downcast_ref_unchecked::<VS>()
+                            .clone(),
+                    ),
+                    additional_info: None,
+                };
+            }
+        }
+
         match r.ret {
             ControlLeak | InstructionResult::ArbitraryExternalCallAddressBounded(_,_) => unsafe {
                 let global_ctx = GLOBAL_CALL_CONTEXT
@@ -673,6 +988,8 @@ where
                         }
                         _ => unreachable!(),
                     },
+
+                    return_data: self.host.leak_return.take(),
                 });
             },
             _ => {}
@@ -680,21 +997,90 @@ where
 
         r.new_state.bug_hit = vm_state.bug_hit || self.host.bug_hit;
         r.new_state.selfdestruct_hit = vm_state.selfdestruct_hit || self.host.selfdestruct_hit;
+        r.new_state.delegated_eoa_hit = vm_state.delegated_eoa_hit || self.host.delegated_eoa_hit;
+        r.new_state.gas_used = self.host.gas_used;
+        r.new_state.gas_exceeded = false;
+        if let Some(fair) = &victim_fair_output {
+            if r.ret == InstructionResult::Return || r.ret == InstructionResult::Stop {
+                if let (Some(expected), Some(received)) = (output_amount(fair), output_amount(&r.output)) {
+                    if received < expected {
+                        r.new_state.victim_loss = r.new_state.victim_loss.saturating_add(expected - received);
+                    }
+                }
+            }
+        }
+        r.new_state.victim_fair_output = victim_fair_output;
+        if unsafe { VICTIM_TXS } && !input.is_step() {
+            r.new_state.before_last_tx = Some(pre_vm_state.state.clone());
+        }
+        r.new_state.differential = None;
+        if let Some((target, shadow)) = differential {
+            let succeeded = |ret: &InstructionResult| matches!(ret, InstructionResult::Return | InstructionResult::Stop);
+            let target_logs = self.host.recorded_logs.replace(vec![]).unwrap_or_default();
+            let shadow_res = unsafe {
+                self.fast_call(
+                    shadow,
+                    differential_data,
+                    VMStateT::as_any(&pre_vm_state).downcast_ref_unchecked::<VS>(),
+                    state,
+                    input.get_txn_value().unwrap_or(EVMU256::ZERO),
+                    input.get_caller(),
+                )
+            };
+            let shadow_logs = self.host.recorded_logs.take().unwrap_or_default();
+            let target_effects = Effects::new(
+                target,
+                shadow,
+                succeeded(&r.ret),
+                r.output.to_vec(),
+                target_logs,
+                &pre_vm_state,
+                &r.new_state,
+                None,
+            );
+            let shadow_effects = Effects::new(
+                shadow,
+                target,
+                succeeded(&shadow_res.ret),
+                shadow_res.output.to_vec(),
+                shadow_logs,
+                &pre_vm_state,
+                &shadow_res.new_state,
+                self.differential_normalizer.as_ref(),
+            );
+            r.new_state.differential = divergence(&target_effects, &shadow_effects);
+            // keep the state of the shadow version in sync
+            if let Some(storage) = shadow_res.new_state.state.get(&shadow) {
+                r.new_state.state.insert(shadow, storage.clone());
+            }
+        }
         r.new_state.typed_bug = HashSet::from_iter(
             vm_state.typed_bug.iter().cloned().chain(
                 self.host.current_typed_bug.iter().cloned()
             )
         );
 
+        let reverted = is_reverted(&r.ret);
+
+        // learn which values payable functions accept
+        if !is_step {
+            if let Some(value) = input.get_txn_value() {
+                record_value(input.get_contract(), &data, value, reverted);
+            }
+        }
+
         unsafe {
+            // coverage is rolled back along with the state
+            if rollback_coverage(&mut JMP_MAP, reverted, REVERT_COVERAGE) {
+                self.host.coverage_changed = false;
+            }
+            // learn which sequence lengths yield coverage for the function
+            if !is_step {
+                record_length(input.get_contract(), &data, r.new_state.depth, self.host.coverage_changed);
+            }
             ExecutionResult {
                 output: r.output.to_vec(),
-                reverted: match r.ret {
-                    InstructionResult::Return | InstructionResult::Stop | InstructionResult::ControlLeak
-                    | InstructionResult::SelfDestruct
-                    | InstructionResult::ArbitraryExternalCallAddressBounded(_,_) => false,
-                    _ => true,
-                },
+                reverted,
                 new_state: StagedVMState::new_with_state(
                     VMStateT::as_any(&mut r.new_state)
                         .downcast_ref_unchecked::<VS>()
@@ -749,6 +1135,7 @@ where
         deployed_address: EVMAddress,
         state: &mut S,
     ) -> Option<EVMAddress> {
+        let code = self.host.load_init_code(deployed_address, code.bytes().clone());
         let deployer = Contract::new(
             constructor_args.unwrap_or(Bytes::new()),
             code,
@@ -767,10 +1154,10 @@ where
             IN_DEPLOY = false;
         }
         if r != InstructionResult::Return {
-            println!("deploy failed: {:?}", r);
+            warn!("deploy failed: {:?}", r);
             return None;
         }
-        println!(
+        debug!(
             "deployer = 0x{} contract = {:?}",
             hex::encode(self.deployer),
             hex::encode(interp.return_value())
@@ -893,6 +1280,7 @@ where
             self.host.call_count = 0;
             self.host.current_typed_bug = vec![];
             self.host.randomness = vec![9];
+            self.host.gas_used = 0;
         }
 
         let res = data.iter()
@@ -948,12 +1336,13 @@ mod tests {
     use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputTy};
     use crate::evm::mutator::AccessPattern;
     use crate::evm::types::{generate_random_address, EVMFuzzState, EVMU256};
-    use crate::evm::vm::{EVMExecutor, EVMState};
+    use crate::evm::vm::{is_reverted, rollback_coverage, EVMExecutor, EVMState};
     use crate::generic_vm::vm_executor::{GenericVM, MAP_SIZE};
     use crate::state::FuzzState;
     use crate::state_input::StagedVMState;
     use bytes::Bytes;
     use libafl::prelude::{tuple_list, StdScheduler};
+    use revm_interpreter::InstructionResult;
     use revm_primitives::Bytecode;
     use std::cell::RefCell;
     use std::path::Path;
@@ -1020,6 +1409,11 @@ mod tests {
             input_type: EVMInputTy::ABI,
             randomness: vec![],
             repeat: 1,
+            blob_env: Default::default(),
+            victim: false,
+            via_intermediary: false,
+            advance: Default::default(),
+            force_feed: EVMU256::ZERO,
         };
 
         let mut state = FuzzState::new(0);
@@ -1060,6 +1454,11 @@ mod tests {
             input_type: EVMInputTy::ABI,
             randomness: vec![],
             repeat: 1,
+            blob_env: Default::default(),
+            victim: false,
+            via_intermediary: false,
+            advance: Default::default(),
+            force_feed: EVMU256::ZERO,
         };
 
         let execution_result_5 = evm_executor.execute(&input_5, &mut state);
@@ -1077,4 +1476,25 @@ mod tests {
         assert_eq!(cov_changed, true);
         assert_eq!(execution_result_5.reverted, true);
     }
+
+    #[test]
+    fn test_is_reverted() {
+        assert!(!is_reverted(&InstructionResult::Return));
+        assert!(!is_reverted(&InstructionResult::Stop));
+        assert!(!is_reverted(&InstructionResult::ControlLeak));
+        assert!(is_reverted(&InstructionResult::Revert));
+        assert!(is_reverted(&InstructionResult::OutOfGas));
+    }
+
+    #[test]
+    fn test_rollback_coverage() {
+        let mut jmp_map = vec![0u8; MAP_SIZE];
+        jmp_map[1] = 1;
+        // coverage of successful executions and credited reverts is kept
+        assert!(!rollback_coverage(&mut jmp_map, false, false));
+        assert!(!rollback_coverage(&mut jmp_map, true, true));
+        assert_eq!(jmp_map[1], 1);
+        assert!(rollback_coverage(&mut jmp_map, true, false));
+        assert!(jmp_map.iter().all(|hit| *hit == 0));
+    }
 }
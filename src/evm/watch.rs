@@ -0,0 +1,131 @@
+/// Watch mode (`--watch`): rebuild and re-fuzz when the sources of the project change
+///
+/// The campaign polls the Solidity sources of the project between fuzzing iterations and stops
+/// once they change. The project is then rebuilt (with `forge build` / `npx hardhat compile`
+/// unless a build command is given) and a new campaign is started on the new artifacts. The
+/// corpus of the previous campaigns is remapped onto the addresses the contracts are deployed at
+/// in the new campaign and re-executed first, so that coverage is refreshed instead of
+/// rediscovered.
+use std::collections::HashMap;
+use tracing::{error, info, warn};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use glob::glob;
+
+use crate::evm::types::EVMAddress;
+
+/// Fuzzing iterations between two polls of the sources
+pub const WATCH_POLL_ITERATIONS: u64 = 1000;
+
+/// Name of the file in the work dir mapping contracts to their addresses in the last campaign
+pub const WATCH_ADDRESSES_FILE: &str = "watch_addresses.json";
+
+/// Build command of a Foundry / Hardhat project
+pub fn build_command(project: &str) -> Option<String> {
+    let root = Path::new(project);
+    if root.join("foundry.toml").exists() {
+        Some("forge build".to_string())
+    } else if root.join("hardhat.config.js").exists() || root.join("hardhat.config.ts").exists() {
+        Some("npx hardhat compile".to_string())
+    } else {
+        None
+    }
+}
+
+pub struct SourceWatcher {
+    project: String,
+    build: Option<String>,
+    mtimes: HashMap<PathBuf, SystemTime>,
+}
+
+impl SourceWatcher {
+    pub fn new(project: &str, build: Option<String>) -> Self {
+        Self {
+            project: project.to_string(),
+            build: build.or_else(|| build_command(project)),
+            mtimes: Self::snapshot(project),
+        }
+    }
+
+    fn snapshot(project: &str) -> HashMap<PathBuf, SystemTime> {
+        glob(&format!("{}/**/*.sol", project.trim_end_matches('/')))
+            .expect("invalid project path")
+            .flatten()
+            .filter(|path| !path.components().any(|c| c.as_os_str() == "node_modules"))
+            .filter_map(|path| {
+                let mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, mtime))
+            })
+            .collect()
+    }
+
+    /// Whether sources were added, removed or modified since the last call
+    pub fn changed(&mut self) -> bool {
+        let mtimes = Self::snapshot(&self.project);
+        let changed = mtimes != self.mtimes;
+        self.mtimes = mtimes;
+        changed
+    }
+
+    pub fn wait_for_change(&mut self) {
+        while !self.changed() {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+    }
+
+    /// Rebuild the project, returns whether the build succeeded
+    pub fn rebuild(&self) -> bool {
+        let build = match &self.build {
+            Some(build) => build,
+            None => return true,
+        };
+        info!("[watch] rebuilding: {}", build);
+        match Command::new("sh").arg("-c").arg(build).current_dir(&self.project).status() {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                warn!("[watch] build failed ({}), waiting for changes", status);
+                false
+            }
+            Err(e) => {
+                error!("[watch] failed to run build command: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Replace the addresses contracts were deployed at in the previous campaign by their addresses
+/// in the current one, in a sequence (in the replayable format)
+pub fn remap_addresses(
+    txs: &str,
+    previous: &HashMap<String, EVMAddress>,
+    current: &HashMap<String, EVMAddress>,
+) -> String {
+    let mut remapped = txs.to_string();
+    for (name, old) in previous {
+        if let Some(new) = current.get(name).filter(|new| *new != old) {
+            remapped = remapped.replace(&hex::encode(old), &hex::encode(new));
+        }
+    }
+    remapped
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    #[test]
+    fn test_remap_addresses() {
+        let old = fixed_address("1111111111111111111111111111111111111111");
+        let new = fixed_address("2222222222222222222222222222222222222222");
+        let previous = HashMap::from([("main".to_string(), old)]);
+        let current = HashMap::from([("main".to_string(), new)]);
+        let txs = format!("{{\"contract\":\"0x{}\",\"data\":\"a9059cbb000000000000000000000000{}\"}}", hex::encode(old), hex::encode(old));
+        let remapped = remap_addresses(&txs, &previous, &current);
+        assert!(!remapped.contains(&hex::encode(old)));
+        assert_eq!(remapped.matches(&hex::encode(new)).count(), 2);
+        assert_eq!(remap_addresses(&txs, &previous, &HashMap::new()), txs);
+    }
+}
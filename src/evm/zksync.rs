@@ -0,0 +1,150 @@
+/// Compatibility layer for zkSync Era targets
+///
+/// Era differs from Ethereum in a few ways that make forked states diverge quickly:
+/// system contracts live in the kernel space (0x8000 - 0xffff) and are not regular
+/// EVM contracts, contract addresses are derived differently, and the gas price of
+/// pubdata is exposed through the SystemContext contract.
+use crate::evm::contract_utils::set_hash;
+use crate::evm::onchain::endpoints::Chain;
+use crate::evm::types::{EVMAddress, EVMU256};
+use bytes::Bytes;
+use revm_primitives::keccak256;
+
+/// Default L2 gas per pubdata byte
+pub const DEFAULT_GAS_PER_PUBDATA_BYTE: u64 = 800;
+
+pub const BOOTLOADER_ADDRESS: u16 = 0x8001;
+pub const ACCOUNT_CODE_STORAGE_ADDRESS: u16 = 0x8002;
+pub const NONCE_HOLDER_ADDRESS: u16 = 0x8003;
+pub const KNOWN_CODES_STORAGE_ADDRESS: u16 = 0x8004;
+pub const IMMUTABLE_SIMULATOR_ADDRESS: u16 = 0x8005;
+pub const CONTRACT_DEPLOYER_ADDRESS: u16 = 0x8006;
+pub const FORCE_DEPLOYER_ADDRESS: u16 = 0x8007;
+pub const L1_MESSENGER_ADDRESS: u16 = 0x8008;
+pub const MSG_VALUE_SIMULATOR_ADDRESS: u16 = 0x8009;
+pub const L2_ETH_TOKEN_ADDRESS: u16 = 0x800a;
+pub const SYSTEM_CONTEXT_ADDRESS: u16 = 0x800b;
+pub const BOOTLOADER_UTILITIES_ADDRESS: u16 = 0x800c;
+pub const EVENT_WRITER_ADDRESS: u16 = 0x800d;
+pub const COMPRESSOR_ADDRESS: u16 = 0x800e;
+pub const COMPLEX_UPGRADER_ADDRESS: u16 = 0x800f;
+
+pub fn system_contract_address(id: u16) -> EVMAddress {
+    let mut addr = EVMAddress::zero();
+    addr.0[18..].copy_from_slice(&id.to_be_bytes());
+    addr
+}
+
+/// Whether the address is in the kernel space of Era, where system contracts live
+pub fn is_system_contract(address: EVMAddress) -> bool {
+    address.0[..18].iter().all(|x| *x == 0)
+        && u16::from_be_bytes([address.0[18], address.0[19]]) >= 0x8000
+}
+
+fn selector(name: &str) -> [u8; 4] {
+    let mut out = [0u8; 4];
+    set_hash(name, &mut out);
+    out
+}
+
+/// Environment of Era that system contracts expose
+#[derive(Clone, Debug)]
+pub struct ZkSyncEnv {
+    pub chain_id: u32,
+    pub gas_per_pubdata_byte: u64,
+}
+
+impl Default for ZkSyncEnv {
+    fn default() -> Self {
+        Self {
+            chain_id: Chain::ZKSYNC.get_chain_id(),
+            gas_per_pubdata_byte: DEFAULT_GAS_PER_PUBDATA_BYTE,
+        }
+    }
+}
+
+impl ZkSyncEnv {
+    /// Handle a call to a system contract that has no code in the fuzzer
+    /// (system contracts are not EVM contracts). Returns None if the call should revert.
+    pub fn call_system_contract(
+        &self,
+        address: EVMAddress,
+        input: &Bytes,
+        block_number: EVMU256,
+        timestamp: EVMU256,
+        gas_price: EVMU256,
+    ) -> Option<Bytes> {
+        let id = u16::from_be_bytes([address.0[18], address.0[19]]);
+        if input.len() < 4 {
+            return Some(Bytes::new());
+        }
+        let sig: [u8; 4] = input[0..4].try_into().unwrap();
+        let ret = |v: EVMU256| Some(Bytes::from(v.to_be_bytes::<32>().to_vec()));
+        match id {
+            SYSTEM_CONTEXT_ADDRESS => {
+                if sig == selector("chainId()") {
+                    ret(EVMU256::from(self.chain_id))
+                } else if sig == selector("gasPerPubdataByte()") {
+                    ret(EVMU256::from(self.gas_per_pubdata_byte))
+                } else if sig == selector("gasPrice()") {
+                    ret(gas_price)
+                } else if sig == selector("getBlockNumber()") {
+                    ret(block_number)
+                } else if sig == selector("getBlockTimestamp()") {
+                    ret(timestamp)
+                } else {
+                    Some(Bytes::new())
+                }
+            }
+            // deployments need to go through CREATE / CREATE2 in the fuzzer
+            CONTRACT_DEPLOYER_ADDRESS | FORCE_DEPLOYER_ADDRESS | COMPLEX_UPGRADER_ADDRESS => None,
+            _ => Some(Bytes::new()),
+        }
+    }
+}
+
+/// Address derivation of CREATE on Era
+/// keccak256(keccak256("zksyncCreate") || bytes32(sender) || bytes32(nonce))
+pub fn create_address(sender: EVMAddress, nonce: u64) -> EVMAddress {
+    let mut data = keccak256("zksyncCreate".as_bytes()).0.to_vec();
+    data.extend([0u8; 12]);
+    data.extend(sender.0);
+    data.extend(EVMU256::from(nonce).to_be_bytes::<32>());
+    EVMAddress::from_slice(&keccak256(&data).0[12..])
+}
+
+/// Address derivation of CREATE2 on Era
+/// keccak256(keccak256("zksyncCreate2") || bytes32(sender) || salt || bytecodeHash || keccak256(input))
+pub fn create2_address(
+    sender: EVMAddress,
+    salt: EVMU256,
+    bytecode_hash: [u8; 32],
+    constructor_input: &[u8],
+) -> EVMAddress {
+    let mut data = keccak256("zksyncCreate2".as_bytes()).0.to_vec();
+    data.extend([0u8; 12]);
+    data.extend(sender.0);
+    data.extend(salt.to_be_bytes::<32>());
+    data.extend(bytecode_hash);
+    data.extend(keccak256(constructor_input).0);
+    EVMAddress::from_slice(&keccak256(&data).0[12..])
+}
+
+mod tests {
+    use super::*;
+    use crate::evm::types::fixed_address;
+
+    #[test]
+    fn test_system_contract() {
+        assert!(is_system_contract(system_contract_address(SYSTEM_CONTEXT_ADDRESS)));
+        assert!(!is_system_contract(fixed_address("0000000000000000000000000000000000000001")));
+        assert!(!is_system_contract(fixed_address("8b21e662154b4bbc1ec0754d0238875fe3d22fa6")));
+    }
+
+    #[test]
+    fn test_create_address() {
+        let sender = fixed_address("8b21e662154b4bbc1ec0754d0238875fe3d22fa6");
+        assert_ne!(create_address(sender, 0), create_address(sender, 1));
+        assert_eq!(create_address(sender, 0), create_address(sender, 0));
+    }
+}
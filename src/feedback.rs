@@ -4,7 +4,8 @@
 use crate::generic_vm::vm_executor::{GenericVM, MAP_SIZE};
 use crate::generic_vm::vm_state::VMStateT;
 use crate::input::{ConciseSerde, VMInputT};
-use crate::oracle::{BugMetadata, Oracle, OracleCtx, Producer};
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::oracle::{is_bug_disabled, BugMetadata, Oracle, OracleCtx, Producer};
 use crate::scheduler::HasVote;
 use crate::state::{HasExecutionResult, HasInfantStateState, InfantStateState};
 use crate::state_input::StagedVMState;
@@ -187,10 +188,19 @@ where
                 input.get_staged_state().stage[idx]
             };
 
-            for bug_idx in self.oracle[idx]
+            let output_len = unsafe { ORACLE_OUTPUT.len() };
+            let bugs = self.oracle[idx]
                 .deref()
                 .borrow()
-                .oracle(&mut oracle_ctx, original_stage) {
+                .oracle(&mut oracle_ctx, original_stage);
+            // bugs of disabled oracles are dropped along with their reports
+            let enabled = bugs.iter().filter(|bug_idx| !is_bug_disabled(**bug_idx)).cloned().collect::<Vec<_>>();
+            if enabled.is_empty() && !bugs.is_empty() {
+                unsafe {
+                    ORACLE_OUTPUT.truncate(output_len);
+                }
+            }
+            for bug_idx in enabled {
                 let metadata = oracle_ctx.fuzz_state.metadata_mut().get_mut::<BugMetadata>().unwrap();
                 if metadata.known_bugs.contains(&bug_idx) || has_post_exec {
                     continue;
@@ -6,13 +6,9 @@ use crate::{
     state_input::StagedVMState,
 };
 use std::collections::hash_map::DefaultHasher;
+use tracing::{debug, info};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
-
-use std::path::Path;
-use std::process::exit;
 use std::{marker::PhantomData, time::Duration};
 
 use crate::generic_vm::vm_executor::MAP_SIZE;
@@ -29,12 +25,12 @@ use libafl::{
     stages::StagesTuple,
     start_timer,
     state::{HasClientPerfMonitor, HasCorpus, HasExecutions, HasMetadata, HasSolutions},
-    Error, Evaluator, ExecuteInputResult,
+    impl_serdeany, Error, Evaluator, ExecuteInputResult,
 };
 
 use crate::evm::host::JMP_MAP;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use libafl::prelude::HasRand;
 use primitive_types::H256;
@@ -48,6 +44,50 @@ const STATS_TIMEOUT_DEFAULT: Duration = Duration::from_millis(100);
 pub static mut RUN_FOREVER: bool = false;
 pub static mut ORACLE_OUTPUT: String = String::new();
 
+/// Reports (oracle output and trace) of the solutions found, stored in the state metadata
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SolutionsMetadata {
+    pub reports: Vec<String>,
+}
+
+impl_serdeany!(SolutionsMetadata);
+
+/// Called with the fuzzer state and the replayable violating sequence when a solution is found,
+/// returns additional artifacts (file suffix, content) to be written along with the solution
+pub type SolutionHook<S> = Box<dyn FnMut(&mut S, &str) -> Vec<(String, String)>>;
+
+pub struct SolutionHooks<S>(pub Vec<SolutionHook<S>>);
+
+impl<S> Debug for SolutionHooks<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SolutionHooks({})", self.0.len())
+    }
+}
+
+/// Called with the fuzzer state and the replayable violating sequence before a solution is
+/// reported, returns why the violation could not be confirmed (e.g., it does not reproduce on a
+/// shadow fork), None if it is confirmed
+pub type ValidationHook<S> = Box<dyn FnMut(&mut S, &str) -> Option<String>>;
+
+pub struct ValidationHooks<S>(pub Vec<ValidationHook<S>>);
+
+impl<S> Debug for ValidationHooks<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ValidationHooks({})", self.0.len())
+    }
+}
+
+/// Called between fuzzing iterations with the fuzzer state (e.g., to apply operator commands),
+/// returns inputs to be added to the corpus
+pub type ControlHook<I, S> = Box<dyn FnMut(&mut S) -> Vec<I>>;
+
+pub struct ControlHooks<I, S>(pub Vec<ControlHook<I, S>>);
+
+impl<I, S> Debug for ControlHooks<I, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ControlHooks({})", self.0.len())
+    }
+}
 
 /// A fuzzer that implements ItyFuzz logic using LibAFL's [`Fuzzer`] trait
 ///
@@ -95,6 +135,14 @@ where
     phantom: PhantomData<(I, S, OT, VS, Loc, Addr, Out, CI)>,
     /// work dir path
     work_dir: String,
+    /// Hooks producing additional artifacts of solutions (e.g., execution traces)
+    solution_hooks: SolutionHooks<S>,
+    /// Hooks called between fuzzing iterations (e.g., runtime control)
+    control_hooks: ControlHooks<I, S>,
+    /// Hooks confirming solutions before they are reported
+    validation_hooks: ValidationHooks<S>,
+    /// Set when a solution is found and the campaign does not run forever, ends the fuzzing loops
+    stopped: bool,
 }
 
 impl<'a, VS, Loc, Addr, Out, CS, IS, F, IF, IFR, I, OF, S, OT, CI>
@@ -133,9 +181,28 @@ where
             work_dir,
             minimizer_map: Default::default(),
             phantom: PhantomData,
+            solution_hooks: SolutionHooks(vec![]),
+            control_hooks: ControlHooks(vec![]),
+            validation_hooks: ValidationHooks(vec![]),
+            stopped: false,
         }
     }
 
+    /// Add a hook called when a solution is found
+    pub fn add_solution_hook(&mut self, hook: SolutionHook<S>) {
+        self.solution_hooks.0.push(hook);
+    }
+
+    /// Add a hook called between fuzzing iterations
+    pub fn add_control_hook(&mut self, hook: ControlHook<I, S>) {
+        self.control_hooks.0.push(hook);
+    }
+
+    /// Add a hook confirming solutions before they are reported
+    pub fn add_validation_hook(&mut self, hook: ValidationHook<S>) {
+        self.validation_hooks.0.push(hook);
+    }
+
     /// Called every time a new testcase is added to the corpus
     /// Setup the minimizer map
     pub fn on_add_corpus(
@@ -214,6 +281,16 @@ where
         state: &mut S,
         manager: &mut EM,
     ) -> Result<usize, libafl::Error> {
+        // apply control hooks, inputs they return are added to the corpus
+        for hook in self.control_hooks.0.iter_mut() {
+            for input in hook(state) {
+                let mut testcase = Testcase::new(input);
+                testcase.set_exec_time(Duration::from_secs(0));
+                let idx = state.corpus_mut().add(testcase)?;
+                self.scheduler.on_add(state, idx)?;
+            }
+        }
+
         let idx = self.scheduler.next(state)?;
         state.set_current_input_idx(idx);
 
@@ -235,13 +312,37 @@ where
         state: &mut S,
         manager: &mut EM,
     ) -> Result<usize, Error> {
+        let mut ret = 0;
         let mut last = current_time();
         // now report stats to manager every 0.1 sec
         let monitor_timeout = STATS_TIMEOUT_DEFAULT;
-        loop {
-            self.fuzz_one(stages, executor, state, manager)?;
+        while !self.stopped {
+            ret = self.fuzz_one(stages, executor, state, manager)?;
             last = manager.maybe_report_progress(state, last, monitor_timeout)?;
         }
+        Ok(ret)
+    }
+
+    /// Fuzz loop for `iters` iterations, returns early once a solution is found
+    fn fuzz_loop_for(
+        &mut self,
+        stages: &mut ST,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        iters: u64,
+    ) -> Result<usize, Error> {
+        let mut ret = 0;
+        let mut last = current_time();
+        let monitor_timeout = STATS_TIMEOUT_DEFAULT;
+        for _ in 0..iters {
+            if self.stopped {
+                break;
+            }
+            ret = self.fuzz_one(stages, executor, state, manager)?;
+            last = manager.maybe_report_progress(state, last, monitor_timeout)?;
+        }
+        Ok(ret)
     }
 }
 
@@ -270,23 +371,16 @@ macro_rules! dump_file {
                     txn_text
                 );
                 if $print {
-                    println!("============= New Corpus Item =============");
-                    println!("{}", data);
-                    println!("==========================================");
-                }
-
-                // write to file
-                let path = Path::new($corpus_path.as_str());
-                if !path.exists() {
-                    std::fs::create_dir_all(path).unwrap();
+                    debug!("new corpus item\n{}", data);
                 }
-                let mut file =
-                    File::create(format!("{}/{}", $corpus_path, unsafe { DUMP_FILE_COUNT })).unwrap();
-                file.write_all(data.as_bytes()).unwrap();
 
-                let mut replayable_file =
-                    File::create(format!("{}/{}_replayable", $corpus_path, unsafe { DUMP_FILE_COUNT })).unwrap();
-                replayable_file.write_all(txn_text_replayable.as_bytes()).unwrap();
+                // write to storage
+                let storage = $crate::storage::storage();
+                storage.put(&format!("{}/{}", $corpus_path, unsafe { DUMP_FILE_COUNT }), data.as_bytes());
+                storage.put(
+                    &format!("{}/{}_replayable", $corpus_path, unsafe { DUMP_FILE_COUNT }),
+                    txn_text_replayable.as_bytes(),
+                );
             }
         }
     };
@@ -300,24 +394,18 @@ macro_rules! dump_txn {
                 unsafe {
                     DUMP_FILE_COUNT += 1;
                 }
-                // write to file
-                let path = Path::new($corpus_path.as_str());
-                if !path.exists() {
-                    std::fs::create_dir_all(path).unwrap();
-                }
-
                 let concise_input = ConciseEVMInput::from_input($input, &EVMExecutionResult::empty_result());
 
                 let txn_text = concise_input.serialize_string();
                 let txn_text_replayable = String::from_utf8(concise_input.serialize_concise()).unwrap();
 
-                let mut file =
-                    File::create(format!("{}/{}_seed", $corpus_path, unsafe { DUMP_FILE_COUNT })).unwrap();
-                file.write_all(txn_text.as_bytes()).unwrap();
-
-                let mut replayable_file =
-                    File::create(format!("{}/{}_seed_replayable", $corpus_path, unsafe { DUMP_FILE_COUNT })).unwrap();
-                replayable_file.write_all(txn_text_replayable.as_bytes()).unwrap();
+                // write to storage
+                let storage = $crate::storage::storage();
+                storage.put(&format!("{}/{}_seed", $corpus_path, unsafe { DUMP_FILE_COUNT }), txn_text.as_bytes());
+                storage.put(
+                    &format!("{}/{}_seed_replayable", $corpus_path, unsafe { DUMP_FILE_COUNT }),
+                    txn_text_replayable.as_bytes(),
+                );
             }
         }
     };
@@ -441,6 +529,37 @@ where
             }
         }
 
+        // solutions not confirmed by the validation hooks are set aside in [WORKDIR]/unconfirmed
+        // rather than reported
+        if matches!(res, ExecuteInputResult::Solution) && !unsafe { REPLAY } && !self.validation_hooks.0.is_empty() {
+            let txs = state.get_execution_result().new_state.trace.clone().to_file_str(state);
+            let reasons = self
+                .validation_hooks
+                .0
+                .iter_mut()
+                .filter_map(|hook| hook(state, &txs))
+                .collect::<Vec<_>>();
+            if !reasons.is_empty() {
+                info!("[unconfirmed] violation set aside: {}", reasons.join("; "));
+
+                #[cfg(feature = "print_txn_corpus")]
+                {
+                    let unconfirmed_dir = format!("{}/unconfirmed", self.work_dir.as_str());
+                    dump_file!(state, unconfirmed_dir, false);
+                    crate::storage::storage().put(
+                        &format!("{}/{}_unconfirmed.txt", unconfirmed_dir, unsafe { DUMP_FILE_COUNT }),
+                        format!("{}\n{}", unsafe { ORACLE_OUTPUT.clone() }, reasons.join("\n")).as_bytes(),
+                    );
+                }
+
+                self.objective.discard_metadata(state, &input)?;
+                unsafe {
+                    ORACLE_OUTPUT = String::new();
+                }
+                return Ok((ExecuteInputResult::None, None));
+            }
+        }
+
         let final_res = match res {
             // not interesting input, just check whether we should replace it due to better fav factor
             ExecuteInputResult::None => {
@@ -515,7 +634,7 @@ where
                     unsafe {ORACLE_OUTPUT.clone()},
                 );
 
-                println!("\n\n\n😊😊 Found violations! \n\n");
+                crate::status!("\n\n\n😊😊 Found violations! \n\n");
                 let cur_report = format!(
                     "================ Oracle ================\n{}\n================ Trace ================\n{}\n",
                     unsafe { ORACLE_OUTPUT.clone() },
@@ -526,16 +645,32 @@ where
                         .clone()
                         .to_string(state)
                 );
-                println!("{}", cur_report);
+                crate::status!("{}", cur_report);
+                if !state.has_metadata::<SolutionsMetadata>() {
+                    state.metadata_mut().insert(SolutionsMetadata::default());
+                }
+                state.metadata_mut().get_mut::<SolutionsMetadata>().unwrap().reports.push(cur_report);
 
                 #[cfg(feature = "print_txn_corpus")]
                 {
                     let vulns_dir = format!("{}/vulnerabilities", self.work_dir.as_str());
                     dump_file!(state, vulns_dir, false);
+
+                    if !unsafe { REPLAY } {
+                        let txs = state.get_execution_result().new_state.trace.clone().to_file_str(state);
+                        for hook in self.solution_hooks.0.iter_mut() {
+                            for (suffix, content) in hook(state, &txs) {
+                                crate::storage::storage().put(
+                                    &format!("{}/{}_{}", vulns_dir, unsafe { DUMP_FILE_COUNT }, suffix),
+                                    content.as_bytes(),
+                                );
+                            }
+                        }
+                    }
                 }
 
                 if !unsafe { RUN_FOREVER } {
-                    exit(0);
+                    self.stopped = true;
                 }
 
                 return Ok((res, None));
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{executor::FuzzExecutor, fuzzer::ItyFuzzer};
+use libafl::feedbacks::Feedback;
+use libafl::prelude::{MapFeedback, QueueScheduler, SimpleEventManager};
+use libafl::stages::StdMutationalStage;
+use libafl::{
+    prelude::{tuple_list, MaxMapFeedback, SimpleMonitor, StdMapObserver},
+    Fuzzer,
+};
+use crate::feedback::{CmpFeedback, DataflowFeedback, OracleFeedback};
+use crate::generic_vm::vm_executor::GenericVM;
+use crate::oracle::Oracle;
+
+use crate::cairo::corpus_initializer::CairoCorpusInitializer;
+use crate::cairo::input::CairoInput;
+use crate::mutator::HavocMutator;
+use crate::cairo::oracles::typed_bug::TypedBugOracle;
+use crate::cairo::types::CairoFuzzState;
+use crate::cairo::vm::CairoVM;
+use crate::scheduler::SortedDroppingScheduler;
+use crate::state::FuzzState;
+
+pub struct CairoFuzzConfig {
+    /// Glob pattern of contract classes (`*.contract_class.json`)
+    pub target: String,
+    pub work_dir: String,
+    pub seed: u64,
+}
+
+pub fn cairo_fuzzer(config: &CairoFuzzConfig) {
+    let mut state: CairoFuzzState = FuzzState::new(config.seed);
+    let mut vm: CairoVM<CairoFuzzState> = CairoVM::new();
+    let monitor = SimpleMonitor::new(|s| crate::status!("{}", s));
+    let mut mgr = SimpleEventManager::new(monitor);
+
+    let infant_scheduler = SortedDroppingScheduler::new();
+    let scheduler = QueueScheduler::new();
+
+    CairoCorpusInitializer::new(&mut state, &mut vm, &scheduler, &infant_scheduler)
+        .setup(config.target.clone());
+
+    let vm_ref = Rc::new(RefCell::new(vm));
+
+    let jmp_observer = StdMapObserver::new("jmp", vm_ref.borrow().get_jmp());
+    let mut feedback: MapFeedback<CairoInput, _, _, _, CairoFuzzState, _> = MaxMapFeedback::new(&jmp_observer);
+    feedback.init_state(&mut state).expect("Failed to init state");
+
+    let mutator = HavocMutator::new(&infant_scheduler);
+
+    let std_stage = StdMutationalStage::new(mutator);
+    let mut stages = tuple_list!(std_stage);
+
+    let mut executor = FuzzExecutor::new(vm_ref.clone(), tuple_list!(jmp_observer));
+
+    let infant_feedback = CmpFeedback::new(vm_ref.borrow().get_cmp(), &infant_scheduler, vm_ref.clone());
+    let infant_result_feedback = DataflowFeedback::new(vm_ref.borrow().get_read(), vm_ref.borrow().get_write());
+
+    let mut oracles: Vec<Rc<RefCell<dyn Oracle<_, _, _, _, _, _, _, _, _, _>>>> =
+        vec![Rc::new(RefCell::new(TypedBugOracle::new()))];
+    let mut producers = vec![];
+
+    let objective = OracleFeedback::new(&mut oracles, &mut producers, vm_ref.clone());
+
+    let mut fuzzer = ItyFuzzer::new(
+        scheduler,
+        &infant_scheduler,
+        feedback,
+        infant_feedback,
+        infant_result_feedback,
+        objective,
+        config.work_dir.clone(),
+    );
+    fuzzer
+        .fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)
+        .expect("Fuzzing failed");
+}
@@ -1,8 +1,9 @@
 use bytes::Bytes;
+use tracing::{debug, info, warn};
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
 use std::path::Path;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -13,9 +14,9 @@ use crate::{
     executor::FuzzExecutor, fuzzer::ItyFuzzer,
 };
 use libafl::feedbacks::Feedback;
-use libafl::prelude::{HasMetadata, ShMemProvider};
+use libafl::prelude::{Corpus, HasCorpus, HasExecutions, HasMetadata, HasSolutions, ShMemProvider};
 use libafl::prelude::{QueueScheduler, SimpleEventManager};
-use libafl::stages::{CalibrationStage, StdMutationalStage};
+use libafl::stages::CalibrationStage;
 use libafl::{
     prelude::{tuple_list, MaxMapFeedback, SimpleMonitor, StdMapObserver},
     Evaluator, Fuzzer,
@@ -25,23 +26,26 @@ use itertools::Itertools;
 
 use crate::evm::host::{ACTIVE_MATCH_EXT_CALL, CMP_MAP, JMP_MAP, PANIC_ON_BUG, READ_MAP, WRITE_MAP, WRITE_RELATIONSHIPS};
 use crate::evm::host::{CALL_UNTIL};
-use crate::evm::vm::EVMState;
+use crate::evm::vm::{EVMState, REVERT_COVERAGE};
 use crate::feedback::{CmpFeedback, DataflowFeedback, OracleFeedback};
 
-use crate::scheduler::SortedDroppingScheduler;
-use crate::state::{FuzzState, HasCaller, HasExecutionResult};
+use crate::scheduler::{HasReportCorpus, SortedDroppingScheduler, MEMORY_CAP};
+use crate::state::{FuzzState, HasCaller, HasExecutionResult, HasInfantStateState, HasItyState};
+use crate::generic_vm::vm_executor::GenericVM;
 use crate::state_input::StagedVMState;
 
 use crate::evm::config::Config;
+use crate::evm::control::{serve, set_focus, set_frozen, ControlCommand};
 use crate::evm::corpus_initializer::EVMCorpusInitializer;
-use crate::evm::input::{ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy};
+use crate::evm::input::{parse_gas_price_bounds, ConciseEVMInput, EVMInput, EVMInputT, EVMInputTy, ACTORS, DEFAULT_MAX_GAS_PRICE, GAS_PRICE_BOUNDS, INTERMEDIARY_TXS, VICTIM_TXS};
 
 use crate::evm::mutator::{AccessPattern, FuzzMutator};
 use crate::evm::onchain::flashloan::Flashloan;
-use crate::evm::onchain::onchain::OnChain;
+use crate::evm::onchain::onchain::{OnChain, BLACKLIST_ADDR};
 use crate::evm::onchain::selfdestruct::{Selfdestruct};
+use crate::evm::power_schedule::{BranchHitObserver, PowerMutationalStage};
 use crate::evm::presets::pair::PairPreset;
-use crate::evm::types::{EVMAddress, EVMFuzzMutator, EVMFuzzState, EVMU256, fixed_address};
+use crate::evm::types::{convert_u256_to_h160, EVMAddress, EVMFuzzMutator, EVMFuzzState, EVMU256, fixed_address};
 use primitive_types::{H160, U256};
 use revm_primitives::{BlockEnv, Bytecode, Env};
 use revm_primitives::bitvec::view::BitViewSized;
@@ -49,12 +53,97 @@ use crate::evm::abi::ABIAddressToInstanceMap;
 use crate::evm::concolic::concolic_host::ConcolicHost;
 use crate::evm::feedbacks::Sha3WrappedFeedback;
 use crate::evm::middlewares::coverage::Coverage;
+use crate::evm::middlewares::diff_coverage::DiffCoverage;
+use crate::evm::focus_diff::{changed_lines, changed_pcs};
 use crate::evm::middlewares::branch_coverage::BranchCoverage;
+use crate::evm::middlewares::call_tracer::CallTracer;
+use crate::evm::cross_evm::{cross_check, cross_check_corpus};
+use crate::evm::shadow_fork::ShadowFork;
+use crate::evm::findings::FindingsExporter;
+use crate::evm::debugger::Debugger;
+use crate::evm::exploit::generate_exploit;
+use crate::evm::known_addresses::{known_addresses, load_known_addresses};
+use crate::evm::replay::SolutionReplayer;
+use crate::evm::state_diff::state_diff;
+use crate::evm::storage_layout::StorageLabels;
+use crate::evm::budget::{ExecBudget, BUDGET_ABORTS};
+use crate::evm::target_groups::TargetGroups;
+use crate::evm::function_weights::{parse_table, set_only_fuzz, FunctionWeights};
+use crate::evm::campaign_stats::CampaignStats;
+use crate::evm::corpus_import::{load_sequences, to_concise};
+use crate::evm::watch::{remap_addresses, SourceWatcher, WATCH_ADDRESSES_FILE, WATCH_POLL_ITERATIONS};
+use crate::evm::soak::{Soak, SoakConfig, SOAK_POLL_ITERATIONS};
 use crate::evm::middlewares::sha3_bypass::{Sha3Bypass, Sha3TaintAnalysis};
 use crate::evm::oracles::echidna::EchidnaOracle;
+use crate::evm::oracles::differential::DifferentialOracle;
+use crate::evm::oracles::initializer::{implementation_initializers, uses_initializer_modifier, InitializerOracle};
+use crate::evm::oracles::rounding::RoundingOracle;
+use crate::evm::oracles::solvency::{SolvencyConfig, SolvencyOracle};
+use crate::evm::oracles::epoch::{EpochConfig, EpochOracle};
+use crate::evm::oracles::event::{EventInvariantConfig, EventOracle};
+use crate::evm::oracles::oracle_kind;
+use crate::oracle::{set_bug_kind_enabled, BugMetadata};
+use crate::evm::pre_state::{PreState, PreStateConfig};
+use crate::evm::bridge::{Bridge, BridgeConfig};
+use crate::evm::oracles::bridge::BridgeOracle;
+use crate::evm::oracles::liquidation::LiquidationOracle;
+use crate::evm::lending::{LendingConfig, LendingSetup};
+use crate::evm::middlewares::price_mover::{price_trigger_contract, Price, PriceMover};
+use crate::evm::middlewares::chainlink::{chainlink_trigger_contract, ChainlinkFeeds, ChainlinkMover, FeedBounds};
+use crate::evm::oracles::sandwich::SandwichOracle;
+use crate::evm::oracles::truncation::TruncationOracle;
+use crate::evm::oracles::tx_origin::TxOriginOracle;
+use crate::evm::oracles::reentrancy::ReentrancyOracle;
+use crate::evm::oracles::block_context::BlockContextOracle;
+use crate::evm::oracles::constructor::ConstructorOracle;
+use crate::evm::oracles::unchecked_call::UncheckedCallOracle;
+use crate::evm::oracles::fee::FeeOracle;
+use crate::evm::oracles::upgrade::UpgradeOracle;
+use crate::evm::oracles::eip7702::{DelegatedEOAOracle, DELEGATION_TARGET};
+use crate::evm::oracles::delegatecall::DelegatecallOracle;
+use crate::evm::oracles::erc20::IERC20OracleFlashloan;
+use crate::evm::oracles::gas_bomb::GasBombOracle;
+use crate::evm::oracles::selfdestruct::SelfdestructOracle;
+use crate::evm::oracles::signature::SignatureOracle;
+use crate::evm::oracles::typed_bug::TypedBugOracle;
+use crate::evm::oracles::v2_pair::PairBalanceOracle;
+use crate::evm::producers::erc20::ERC20Producer;
+use crate::evm::producers::pair::PairProducer;
+use crate::evm::token_callbacks::{TokenCallbacks, FUNGIBLE_HOOKS, NFT_HOOKS};
+use crate::evm::mocks::MockSynthesis;
+use crate::evm::twap::twap_manipulations;
+use crate::evm::chain_spec::ChainSpec;
+use crate::evm::bundle::{build_bundle, BundleConfig};
+use crate::evm::mempool::{pending_in_sequence, subscribe, PendingTx};
+use crate::evm::env_bounds::{unrealistic_env, EnvBounds, ForkPoint};
+use crate::evm::cmin::minimize_corpus;
+use crate::evm::zksync::ZkSyncEnv;
+#[cfg(feature = "wasm_plugins")]
+use crate::evm::precompiles::WasmPrecompile;
+#[cfg(feature = "wasm_plugins")]
+use crate::evm::oracles::wasm_plugin::WasmOracle;
+#[cfg(feature = "scripting")]
+use crate::evm::middlewares::script::ScriptMiddleware;
+#[cfg(feature = "llm_triage")]
+use crate::evm::triage::Triage;
+use crate::evm::middlewares::origin_auth::OriginAuth;
+use crate::evm::middlewares::block_context::BlockContextTracker;
+use crate::evm::middlewares::constructor::ConstructorTracker;
+use crate::evm::middlewares::unchecked_call::UncheckedCallTracker;
+use crate::evm::middlewares::call_value::CallValueTracker;
+use crate::evm::call_value::PAYABLE_VALUES;
+use crate::evm::seq_length::ADAPTIVE_LENGTH;
+use crate::evm::middlewares::event_collector::EventCollector;
+use crate::evm::middlewares::native_flow::{NativeFlowTracker, NATIVE_TRACKED};
+use crate::evm::oracles::eth_conservation::EthConservationOracle;
+use crate::evm::middlewares::rounding::RoundingTracker;
+use crate::evm::middlewares::truncation::TruncationTracker;
+use crate::evm::middlewares::upgrade::{upgrade_trigger_contract, Upgrade, EIP1967_IMPLEMENTATION_SLOT, UPGRADE_TRIGGER};
 use crate::evm::srcmap::parser::BASE_PATH;
-use crate::fuzzer::{REPLAY, RUN_FOREVER};
+use crate::evm::trimming::TrimmingStage;
+use crate::fuzzer::{DUMP_FILE_COUNT, ORACLE_OUTPUT, REPLAY, RUN_FOREVER};
 use crate::input::{ConciseSerde, VMInputT};
+use crate::storage::{open_storage, STORAGE};
 
 struct ABIConfig {
     abi: String,
@@ -74,10 +163,15 @@ pub fn evm_fuzzer(
     if !path.exists() {
         std::fs::create_dir(path).unwrap();
     }
+    unsafe {
+        STORAGE = Some(open_storage(&config.storage, &config.work_dir, config.storage_endpoint.as_deref()));
+    }
 
     let cov_middleware = Rc::new(RefCell::new(Coverage::new()));
+    cov_middleware.borrow_mut().by_code = config.coverage_by_code;
+    cov_middleware.borrow_mut().per_address = config.coverage_per_address;
 
-    let monitor = SimpleMonitor::new(|s| println!("{}", s));
+    let monitor = SimpleMonitor::new(|s| crate::status!("{}", s));
     let mut mgr = SimpleEventManager::new(monitor);
     let infant_scheduler = SortedDroppingScheduler::new();
     let mut scheduler = QueueScheduler::new();
@@ -102,8 +196,27 @@ pub fn evm_fuzzer(
         // Selfdestruct end
     }
 
+    unsafe {
+        MEMORY_CAP = config.memory_cap;
+    }
+
+    if let Some(path) = &config.known_addresses {
+        load_known_addresses(path);
+    }
+    // known addresses (routers, tokens, flashloan providers) of the forked chain
+    let chain_addresses = config
+        .onchain
+        .as_ref()
+        .and_then(|onchain| known_addresses().chain(&onchain.chain_name))
+        .cloned();
+
     let onchain_middleware = match config.onchain.clone() {
-        Some(onchain) => {
+        Some(mut onchain) => {
+            if let Some(cap) = config.memory_cap {
+                // a quarter of the cap goes to fetched code and storage (an eighth each), the
+                // rest is left to infant states and the fuzzer itself
+                onchain.set_memory_cap(cap / 4);
+            }
             Some({
                 let mid = Rc::new(RefCell::new(
                     OnChain::<EVMState, EVMInput, EVMFuzzState>::new(
@@ -135,7 +248,23 @@ pub fn evm_fuzzer(
         BASE_PATH = config.base_path;
     }
 
-    if config.run_forever {
+    // verified sources of the onchain targets and their implementations, to read the reports
+    if let Some(onchain) = &config.onchain {
+        let addresses = config
+            .contract_loader
+            .contracts
+            .iter()
+            .filter(|contract| contract.is_code_deployed)
+            .map(|contract| contract.deployed_address)
+            .chain(config.contract_loader.implementation_source_maps.keys().cloned());
+        for address in addresses {
+            if let Some(source) = onchain.fetch_source(address) {
+                source.write(&format!("{}/sources/{:?}", config.work_dir, address));
+            }
+        }
+    }
+
+    if config.run_forever || config.soak.is_some() {
         unsafe {
             RUN_FOREVER = true;
         }
@@ -145,6 +274,13 @@ pub fn evm_fuzzer(
         PANIC_ON_BUG = config.panic_on_bug;
     }
 
+    let pair_producer = Rc::new(RefCell::new(PairProducer::new()));
+    let erc20_producer = Rc::new(RefCell::new(ERC20Producer::new()));
+    let flashloan_oracle = Rc::new(RefCell::new(IERC20OracleFlashloan::new(
+        pair_producer.clone(),
+        erc20_producer.clone(),
+    )));
+
     if config.flashloan {
         // we should use real balance of tokens in the contract instead of providing flashloan
         // to contract as well for on chain env
@@ -168,7 +304,7 @@ pub fn evm_fuzzer(
                 config.onchain.clone().unwrap(),
                 config.price_oracle,
                 onchain_middleware.unwrap(),
-                config.flashloan_oracle,
+                flashloan_oracle.clone(),
             ));
         }
     }
@@ -178,17 +314,280 @@ pub fn evm_fuzzer(
         fuzz_host.add_middlewares(Rc::new(RefCell::new(Sha3Bypass::new(sha3_taint.clone()))));
     }
 
+    if config.tx_origin_oracle {
+        unsafe {
+            INTERMEDIARY_TXS = true;
+        }
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(OriginAuth::new())));
+    }
+
+    if config.truncation_oracle {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(TruncationTracker::new())));
+    }
+
+    if config.block_context_oracle || config.fee_oracle {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(BlockContextTracker::new())));
+    }
+
+    if config.constructor_oracle {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(ConstructorTracker::new())));
+    }
+
+    if config.unchecked_call_oracle {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(UncheckedCallTracker::new())));
+    }
+
+    if config.payable_values {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(CallValueTracker::new())));
+    }
+
+    if let Some(epsilon) = config.rounding_epsilon {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(RoundingTracker::new(epsilon))));
+    }
+
+    let mut contract_loader = config.contract_loader.clone();
+
+    // resolve a contract given by address or name
+    let resolve = |target: &String| {
+        if target.starts_with("0x") {
+            EVMAddress::from_str(target).expect("invalid address")
+        } else {
+            contract_loader
+                .contracts
+                .iter()
+                .find(|c| c.name == *target)
+                .unwrap_or_else(|| panic!("contract {} not found", target))
+                .deployed_address
+        }
+    };
+
+    // oracles and contracts out of the threat model of the engagement
+    for name in &config.disabled_oracles {
+        set_bug_kind_enabled(oracle_kind(name).unwrap_or_else(|| panic!("unknown oracle {}", name)), false);
+    }
+    for contract in &config.frozen_contracts {
+        set_frozen(resolve(contract), true);
+    }
+
+    // upgrade scenario: proxy, new implementation and implementation slot
+    let upgrade = config.upgrade_proxy.as_ref().map(|proxy| {
+        let proxy = resolve(proxy);
+        let implementation = resolve(
+            config
+                .upgrade_implementation
+                .as_ref()
+                .expect("upgrade implementation is required when upgrade proxy is set"),
+        );
+        let slot = EVMU256::from_str(
+            config
+                .upgrade_slot
+                .as_ref()
+                .map(|x| x.as_str())
+                .unwrap_or(EIP1967_IMPLEMENTATION_SLOT),
+        )
+        .expect("invalid upgrade slot");
+        (proxy, implementation, slot)
+    });
+
+    // differential fuzzing: the shadow version is only executed along with the target
+    if config.differential_shadow.is_some() && config.differential_target.is_none() {
+        panic!("--differential-shadow requires --differential-target");
+    }
+    let differential = config.differential_target.as_ref().map(|target| {
+        let shadow = resolve(
+            config
+                .differential_shadow
+                .as_ref()
+                .expect("differential shadow is required when differential target is set"),
+        );
+        (resolve(target), shadow)
+    });
+    if let Some((_, shadow)) = differential {
+        unsafe {
+            BLACKLIST_ADDR.get_or_insert_with(HashSet::new).insert(shadow);
+        }
+    }
+
+    let solvency = config
+        .solvency_config
+        .as_ref()
+        .map(|path| SolvencyOracle::new(&SolvencyConfig::from_file(path), &resolve));
+
+    let epoch = config
+        .epoch_invariants
+        .as_ref()
+        .map(|path| EpochOracle::new(&EpochConfig::from_file(path), &resolve));
+
+    // events of interest are recorded along the sequence for the event invariants
+    let events = config
+        .event_invariants
+        .as_ref()
+        .map(|path| EventOracle::new(&EventInvariantConfig::from_file(path), &resolve));
+    if let Some(events) = &events {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(EventCollector::new(events.topics()))));
+    }
+
+    // ETH held by the contracts of the ETH accounting is tracked along the sequence
+    let eth_conservation = if config.eth_accounting.is_empty() {
+        None
+    } else {
+        let oracle = EthConservationOracle::new(&config.eth_accounting, &resolve);
+        unsafe {
+            NATIVE_TRACKED = Some(oracle.accounting.iter().map(|a| a.contract).collect());
+        }
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(NativeFlowTracker::new())));
+        Some(oracle)
+    };
+
+    // approvals and positions of the senders, established after the deployment
+    let pre_state = config
+        .pre_state
+        .as_ref()
+        .map(|path| PreState::new(&PreStateConfig::from_file(path), &resolve));
+
+    // bridge harness: messengers, receiver, bridged token and escrow
+    let bridge = config
+        .bridge_config
+        .as_ref()
+        .map(|path| Bridge::new(&BridgeConfig::from_file(path), &resolve));
+
+    // liquidation scenario: market, actors, prices moved by the price trigger, and the funding
+    // and positions of the actors established after the deployment
+    let lending = config.lending_config.as_ref().map(|path| {
+        let lending = LendingConfig::from_file(path);
+        let actors = lending
+            .actors
+            .iter()
+            .map(|(role, actor)| (role.clone(), resolve(actor)))
+            .collect::<Vec<_>>();
+        let prices = lending
+            .prices
+            .iter()
+            .map(|p| Price {
+                feed: resolve(&p.feed),
+                slot: EVMU256::from_str(&p.slot).expect("invalid price slot"),
+                base: p.base.as_ref().map(|b| EVMU256::from_str(b).expect("invalid base price")),
+                min_percent: p.min_percent,
+                max_percent: p.max_percent,
+            })
+            .collect::<Vec<_>>();
+        let setup = LendingSetup::new(&lending, &resolve);
+        (resolve(&lending.market), actors, prices, lending, setup)
+    });
+
+    if let Some((proxy, implementation, slot)) = upgrade {
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(Upgrade::new(proxy, implementation, slot))));
+        contract_loader.contracts.push(upgrade_trigger_contract());
+    }
+
+    if let Some((_, _, prices, _, _)) = &lending {
+        if !prices.is_empty() {
+            fuzz_host.add_middlewares(Rc::new(RefCell::new(PriceMover::new(prices.clone()))));
+            contract_loader.contracts.push(price_trigger_contract());
+        }
+    }
+
+    if let Some(spec) = &config.chainlink {
+        fuzz_host.chainlink = Some(ChainlinkFeeds::new(FeedBounds::parse(spec)));
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(ChainlinkMover::new())));
+        contract_loader.contracts.push(chainlink_trigger_contract());
+    }
+
+    if let Some(path) = &config.chain_spec {
+        let chain_spec = ChainSpec::from_file(path);
+        contract_loader.contracts.extend(chain_spec.system_contracts());
+        fuzz_host.set_chain_spec(chain_spec);
+    }
+
+    if let Some(spec) = &config.env_bounds {
+        let fork = match &config.onchain {
+            Some(onchain) => {
+                let (number, timestamp, basefee) = onchain.fetch_blk_env();
+                ForkPoint {
+                    number,
+                    timestamp,
+                    basefee,
+                }
+            }
+            None => ForkPoint::from_env(&Env::default()),
+        };
+        fuzz_host.env_bounds = Some(EnvBounds::parse(spec, fork, !config.env_bounds_report));
+    }
+
+    let gas_price_bounds = config
+        .gas_price_bounds
+        .as_ref()
+        .map_or((0, DEFAULT_MAX_GAS_PRICE), |spec| parse_gas_price_bounds(spec));
+    unsafe {
+        GAS_PRICE_BOUNDS = gas_price_bounds;
+    }
+
+    if config.zksync {
+        fuzz_host.zksync = Some(ZkSyncEnv::default());
+    }
+
+    if let Some(gas_ceiling) = config.gas_ceiling {
+        fuzz_host.set_gas_ceiling(gas_ceiling);
+    } else if config.fee_oracle {
+        // gas is only estimated with a ceiling, the fee oracle needs the fee paid
+        fuzz_host.set_gas_ceiling(u64::MAX);
+    }
+
+    if config.step_limit.is_some() || config.exec_timeout.is_some() {
+        fuzz_host.budget = Some(ExecBudget::new(config.step_limit, config.exec_timeout));
+    }
+
+    for precompile in &config.wasm_precompiles {
+        let (address, path) = precompile
+            .split_once('=')
+            .expect("wasm precompile should be in the format of <address>=<path>");
+        let address = EVMAddress::from_str(address).expect("invalid precompile address");
+        #[cfg(feature = "wasm_plugins")]
+        fuzz_host.register_precompile(address, Rc::new(RefCell::new(WasmPrecompile::from_file(path))));
+        #[cfg(not(feature = "wasm_plugins"))]
+        panic!("wasm precompile {:?} at {} requires wasm_plugins feature", address, path);
+    }
+
+    for path in &config.middleware_scripts {
+        #[cfg(feature = "scripting")]
+        fuzz_host.add_middlewares(Rc::new(RefCell::new(ScriptMiddleware::from_file(path))));
+        #[cfg(not(feature = "scripting"))]
+        panic!("middleware script {} requires scripting feature", path);
+    }
+
+    for middleware in &config.middlewares {
+        fuzz_host.add_middlewares(middleware.clone());
+    }
+
+    // oracle plugins get the trace of the transaction
+    let plugin_tracer = match config.tx_tracer.clone() {
+        Some(tracer) => Some(tracer),
+        None if !config.oracle_plugins.is_empty() => Some(Rc::new(RefCell::new(CallTracer::new()))),
+        None => None,
+    };
+    if let Some(tracer) = &plugin_tracer {
+        fuzz_host.add_middlewares(tracer.clone());
+    }
+
     let mut evm_executor: EVMExecutor<EVMInput, EVMFuzzState, EVMState, ConciseEVMInput> =
         EVMExecutor::new(fuzz_host, deployer);
+    evm_executor.tx_tracer = plugin_tracer.clone();
 
-    if config.replay_file.is_some() {
-        // add coverage middleware for replay
+    if config.replay_file.is_some() || config.control.is_some() {
+        // add coverage middleware for replay and coverage dumps
         evm_executor.host.add_middlewares(cov_middleware.clone());
+    }
+
+    if config.replay_file.is_some() {
         unsafe {
             REPLAY = true;
         }
     }
 
+    if let Some(pattern) = &config.only_fuzz {
+        set_only_fuzz(pattern);
+    }
+
     let mut corpus_initializer = EVMCorpusInitializer::new(
         &mut evm_executor,
         &mut scheduler,
@@ -200,7 +599,19 @@ pub fn evm_fuzzer(
     #[cfg(feature = "use_presets")]
     corpus_initializer.register_preset(&PairPreset {});
 
-    let artifacts = corpus_initializer.initialize(&mut config.contract_loader.clone());
+    if let Some(pre_state) = pre_state {
+        corpus_initializer.set_pre_state(pre_state);
+    }
+
+    if let Some(bridge) = &bridge {
+        corpus_initializer.set_bridge(bridge.clone());
+    }
+
+    if let Some((_, _, _, _, setup)) = &lending {
+        corpus_initializer.set_lending(setup.clone());
+    }
+
+    let artifacts = corpus_initializer.initialize(&mut contract_loader);
 
     let mut instance_map = ABIAddressToInstanceMap::new();
     artifacts.address_to_abi_object.iter().for_each(
@@ -212,8 +623,83 @@ pub fn evm_fuzzer(
         instance_map
     );
 
+    // well-known tokens of the chain are used as address arguments
+    if let Some(addresses) = &chain_addresses {
+        for token in addresses.tokens() {
+            state.add_address(&token);
+        }
+    }
+
     evm_executor.host.initialize(state);
 
+    unsafe {
+        REVERT_COVERAGE = config.revert_coverage;
+        PAYABLE_VALUES = config.payable_values;
+        ADAPTIVE_LENGTH = config.adaptive_length;
+    }
+
+    // EIP-7702: EOA callers delegate to a contract
+    if config.eip7702 {
+        let target = fixed_address(DELEGATION_TARGET);
+        for caller in state.callers_pool.clone() {
+            if !evm_executor.host.code.contains_key(&caller) {
+                evm_executor.host.set_delegation(caller, target);
+            }
+        }
+    }
+
+    // signatures verified by ecrecover are modeled as signed by the callers
+    if config.signature_oracle {
+        evm_executor.host.set_signers(state.callers_pool.clone());
+    }
+
+    if let Some((target, shadow)) = differential {
+        evm_executor.set_differential(target, shadow);
+    }
+
+    // transactions of the actors of the liquidation scenario are labeled with their role
+    if let Some((_, actors, _, _, _)) = &lending {
+        unsafe {
+            ACTORS = actors.clone();
+        }
+    }
+
+    // track executions reaching code changed since the given revision
+    if let Some(rev) = config.focus_diff.clone() {
+        let diff_coverage = DiffCoverage::new(changed_pcs(&changed_lines(&rev), &artifacts.address_to_sourcemap));
+        info!("[focus-diff] {} changed PCs since {}", diff_coverage.total(), rev);
+        evm_executor.host.add_middlewares(Rc::new(RefCell::new(diff_coverage)));
+    }
+
+    // delegatecalls to attacker-controlled addresses run the hijack implementation
+    if config.delegatecall_oracle {
+        evm_executor.host.hijack_delegatecall = true;
+    }
+
+    // contract callers implement the token hooks
+    if config.token_callbacks || config.nft_callbacks {
+        let accounts = state
+            .callers_pool
+            .iter()
+            .filter(|caller| evm_executor.host.code.contains_key(*caller))
+            .cloned()
+            .collect::<HashSet<_>>();
+        let mut hooks = vec![];
+        if config.token_callbacks {
+            hooks.extend(FUNGIBLE_HOOKS);
+        }
+        if config.nft_callbacks {
+            hooks.extend(NFT_HOOKS);
+        }
+        evm_executor.host.token_callbacks = Some(TokenCallbacks::new(accounts, hooks));
+    }
+
+    // calls to missing dependencies are served by mocks
+    if config.mock_missing {
+        let callers = state.callers_pool.iter().cloned().collect::<HashSet<_>>();
+        evm_executor.host.mocks = Some(MockSynthesis::new(callers));
+    }
+
     // now evm executor is ready, we can clone it
 
     let evm_executor_ref = Rc::new(RefCell::new(evm_executor));
@@ -225,12 +711,32 @@ pub fn evm_fuzzer(
     let calibration = CalibrationStage::new(&feedback);
     let mutator: EVMFuzzMutator<'_> = FuzzMutator::new(&infant_scheduler);
 
-    let std_stage = StdMutationalStage::new(mutator);
-    let mut stages = tuple_list!(calibration, std_stage);
+    let mut power_stage = PowerMutationalStage::new(
+        mutator,
+        config.power_schedule,
+        config.sequence_energy,
+        config.rare_boost,
+        config.work_dir.clone(),
+    );
+    if let Some(spec) = &config.target_groups {
+        power_stage.set_target_groups(TargetGroups::parse(spec, &contract_loader.contracts));
+    }
+    if let Some(spec) = &config.function_weights {
+        let functions = artifacts
+            .address_to_abi
+            .values()
+            .flatten()
+            .map(|abi| abi.function_name.clone())
+            .collect::<Vec<_>>();
+        power_stage.set_function_weights(FunctionWeights::new(parse_table(spec), &functions));
+    }
+    let trimming = TrimmingStage::new(config.trim, evm_executor_ref.clone());
+    let mut stages = tuple_list!(calibration, trimming, power_stage);
 
 
 
-    let mut executor = FuzzExecutor::new(evm_executor_ref.clone(), tuple_list!(jmp_observer));
+    let branch_observer = BranchHitObserver::new(config.rare_boost || config.power_schedule.tracks_branches());
+    let mut executor = FuzzExecutor::new(evm_executor_ref.clone(), tuple_list!(jmp_observer, branch_observer));
 
     #[cfg(feature = "deployer_is_attacker")]
     state.add_caller(&deployer);
@@ -239,6 +745,38 @@ pub fn evm_fuzzer(
 
     let mut oracles = config.oracle;
 
+    if config.pair_oracle {
+        oracles.push(Rc::new(RefCell::new(PairBalanceOracle::new(pair_producer.clone()))));
+    }
+
+    if config.ierc20_oracle {
+        oracles.push(flashloan_oracle.clone());
+    }
+
+    if config.selfdestruct_oracle {
+        oracles.push(Rc::new(RefCell::new(SelfdestructOracle::new())));
+    }
+
+    if config.eip7702 {
+        oracles.push(Rc::new(RefCell::new(DelegatedEOAOracle::new())));
+    }
+
+    if let Some(gas_ceiling) = config.gas_ceiling {
+        oracles.push(Rc::new(RefCell::new(GasBombOracle::new(gas_ceiling))));
+    }
+
+    if config.signature_oracle {
+        oracles.push(Rc::new(RefCell::new(SignatureOracle::new())));
+    }
+
+    if config.delegatecall_oracle {
+        oracles.push(Rc::new(RefCell::new(DelegatecallOracle::new())));
+    }
+
+    if config.typed_bug_oracle {
+        oracles.push(Rc::new(RefCell::new(TypedBugOracle::new())));
+    }
+
     if config.echidna_oracle {
         let echidna_oracle = EchidnaOracle::new(
             artifacts.address_to_abi.iter()
@@ -272,9 +810,162 @@ pub fn evm_fuzzer(
         oracles.push(Rc::new(RefCell::new(echidna_oracle)));
     }
 
+    if let Some((proxy, implementation, slot)) = upgrade {
+        // getters of the old implementation (or of the proxy if unknown) also exposed by the new
+        // one, a getter added by the upgrade has nothing to be compared with
+        let getters_of = |address: &EVMAddress| {
+            artifacts
+                .address_to_abi
+                .get(address)
+                .map(|abis| {
+                    abis.iter()
+                        .filter(|abi| abi.is_static && abi.abi == "()")
+                        .cloned()
+                        .collect_vec()
+                })
+                .unwrap_or_default()
+        };
+        let old_implementation = artifacts
+            .initial_state
+            .state
+            .state
+            .get(&proxy)
+            .and_then(|storage| storage.get(&slot))
+            .map(|x| convert_u256_to_h160(*x));
+        let mut old_getters = old_implementation.map(|x| getters_of(&x)).unwrap_or_default();
+        if old_getters.is_empty() {
+            old_getters = getters_of(&proxy);
+        }
+        let new_getters = getters_of(&implementation);
+        let getters = old_getters
+            .into_iter()
+            .filter(|abi| new_getters.iter().any(|x| x.function == abi.function))
+            .unique_by(|abi| abi.function)
+            .collect_vec();
+        oracles.push(Rc::new(RefCell::new(UpgradeOracle::new(
+            proxy,
+            slot,
+            fixed_address(UPGRADE_TRIGGER),
+            getters.iter().map(|abi| abi.function.to_vec()).collect_vec(),
+            getters
+                .iter()
+                .map(|abi| (abi.function.to_vec(), abi.function_name.clone()))
+                .collect::<HashMap<Vec<u8>, String>>(),
+        ))));
+    }
+
+    if config.sandwich_oracle {
+        unsafe {
+            VICTIM_TXS = true;
+        }
+        let abis = artifacts.address_to_abi.values().flatten().cloned().collect_vec();
+        oracles.push(Rc::new(RefCell::new(SandwichOracle::new(&abis))));
+    }
+
+    if differential.is_some() {
+        oracles.push(Rc::new(RefCell::new(DifferentialOracle::new())));
+    }
+
+    // initializers of implementation contracts are attempted at the beginning of the campaign
+    let mut initializer_attempts = vec![];
+    if config.initializer_oracle {
+        let modifier_users = evm_executor_ref
+            .borrow()
+            .host
+            .code
+            .iter()
+            .filter(|(_, code)| uses_initializer_modifier(code.original_bytecode_slice()))
+            .map(|(address, _)| *address)
+            .collect();
+        let oracle = InitializerOracle::new(&artifacts.address_to_abi, &modifier_users);
+        initializer_attempts = implementation_initializers(&artifacts.initial_state.state, &oracle.initializers);
+        oracles.push(Rc::new(RefCell::new(oracle)));
+    }
+
+    if config.rounding_epsilon.is_some() {
+        oracles.push(Rc::new(RefCell::new(RoundingOracle::new())));
+    }
+
+    if config.truncation_oracle {
+        oracles.push(Rc::new(RefCell::new(TruncationOracle::new())));
+    }
+
+    if config.tx_origin_oracle {
+        oracles.push(Rc::new(RefCell::new(TxOriginOracle::new())));
+    }
+
+    if config.reentrancy_oracle {
+        oracles.push(Rc::new(RefCell::new(ReentrancyOracle::new())));
+    }
+
+    if config.block_context_oracle {
+        oracles.push(Rc::new(RefCell::new(BlockContextOracle::new())));
+    }
+
+    if config.fee_oracle {
+        oracles.push(Rc::new(RefCell::new(FeeOracle::new(gas_price_bounds))));
+    }
+
+    if config.constructor_oracle {
+        oracles.push(Rc::new(RefCell::new(ConstructorOracle::new())));
+    }
+
+    if config.unchecked_call_oracle {
+        oracles.push(Rc::new(RefCell::new(UncheckedCallOracle::new())));
+    }
+
+    if let Some((market, actors, _, lending, _)) = lending {
+        oracles.push(Rc::new(RefCell::new(LiquidationOracle::new(
+            market,
+            actors,
+            &lending.health,
+            EVMU256::from_str(&lending.health_threshold).expect("invalid health threshold"),
+            lending.bad_debt.as_deref(),
+            &lending.liquidate,
+            artifacts.address_to_abi.get(&market).expect("market has no ABI"),
+        ))));
+    }
+
+    if let Some(solvency) = solvency {
+        oracles.push(Rc::new(RefCell::new(solvency)));
+    }
+
+    if let Some(epoch) = epoch {
+        oracles.push(Rc::new(RefCell::new(epoch)));
+    }
+
+    if let Some(events) = events {
+        oracles.push(Rc::new(RefCell::new(events)));
+    }
+
+    if let Some(eth_conservation) = eth_conservation {
+        oracles.push(Rc::new(RefCell::new(eth_conservation)));
+    }
+
+    if let Some(bridge) = bridge {
+        oracles.push(Rc::new(RefCell::new(BridgeOracle::new(bridge))));
+    }
+
+    for path in &config.oracle_plugins {
+        #[cfg(feature = "wasm_plugins")]
+        oracles.push(Rc::new(RefCell::new(WasmOracle::from_file(
+            path,
+            plugin_tracer.clone().unwrap(),
+        ))));
+        #[cfg(not(feature = "wasm_plugins"))]
+        panic!("oracle plugin {} requires wasm_plugins feature", path);
+    }
 
     let mut producers = config.producers;
 
+    if config.ierc20_oracle || config.pair_oracle {
+        producers.push(pair_producer);
+    }
+
+    if config.ierc20_oracle {
+        producers.push(erc20_producer);
+    }
+
     let objective = OracleFeedback::new(&mut oracles, &mut producers, evm_executor_ref.clone());
     let wrapped_feedback = Sha3WrappedFeedback::new(
         feedback,
@@ -290,15 +981,540 @@ pub fn evm_fuzzer(
         infant_feedback,
         infant_result_feedback,
         objective,
-        config.work_dir,
+        config.work_dir.clone(),
     );
-    match config.replay_file {
-        None => {
+
+    // label storage slots in reports with the storage layouts from solc
+    let storage_labels = Rc::new(RefCell::new(StorageLabels::default()));
+    for contract in &contract_loader.contracts {
+        let name = contract.name.trim_end_matches('*').split('/').last().unwrap_or_default();
+        if let Some(layout) = contract_loader.storage_layouts.get(name) {
+            storage_labels
+                .borrow_mut()
+                .layouts
+                .insert(contract.deployed_address, layout.clone());
+        }
+    }
+
+    // violating sequences are replayed once, the replay is shared by the hooks below
+    let replayer = Rc::new(RefCell::new(SolutionReplayer::new(
+        evm_executor_ref.clone(),
+        artifacts.initial_state.clone(),
+    )));
+
+    // persist the execution trace of violating sequences
+    let trace_replayer = replayer.clone();
+    let trace_labels = storage_labels.clone();
+    fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+        let replay = trace_replayer.borrow_mut().replay(state, txs);
+        let mut traces = replay.traces.clone();
+        let mut labels = trace_labels.borrow_mut();
+        if !labels.layouts.is_empty() {
+            // preimages are kept for labeling the state diff
+            labels.preimages = replay.preimages.clone();
+            traces
+                .iter_mut()
+                .flat_map(|trace| trace.steps.iter_mut())
+                .for_each(|step| step.label_slot(|address, slot| labels.label(address, slot)));
+        }
+        vec![(
+            "trace.json".to_string(),
+            serde_json::to_string_pretty(&traces).expect("failed to serialize trace"),
+        )]
+    }));
+
+    // persist the net state diff of violating sequences
+    let diff_replayer = replayer.clone();
+    let diff_executor = evm_executor_ref.clone();
+    let diff_initial_state = artifacts.initial_state.clone();
+    let diff_labels = storage_labels.clone();
+    fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+        let replay = diff_replayer.borrow_mut().replay(state, txs);
+        let mut diff = state_diff(&mut diff_executor.borrow_mut(), state, &diff_initial_state, &replay);
+        let labels = diff_labels.borrow();
+        diff.storage
+            .iter_mut()
+            .filter(|slot_diff| slot_diff.label.is_none())
+            .for_each(|slot_diff| slot_diff.label = labels.label(&slot_diff.address, slot_diff.slot));
+        vec![(
+            "state_diff.json".to_string(),
+            serde_json::to_string_pretty(&diff).expect("failed to serialize state diff"),
+        )]
+    }));
+
+    // generate the attacker contract performing violating sequences atomically
+    fuzzer.add_solution_hook(Box::new(move |_state: &mut EVMFuzzState, txs: &str| {
+        vec![("exploit.sol".to_string(), generate_exploit(txs, chain_addresses.as_ref()))]
+    }));
+
+    // cross-check violating sequences against an external EVM
+    if let Some(evm) = config.cross_evm.clone() {
+        let cross_replayer = replayer.clone();
+        let cross_executor = evm_executor_ref.clone();
+        let cross_work_dir = config.work_dir.clone();
+        fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+            let replay = cross_replayer.borrow_mut().replay(state, txs);
+            let report = cross_check(&cross_executor.borrow(), state, &replay, &evm, &cross_work_dir);
+            vec![("cross_evm.txt".to_string(), report.join("\n"))]
+        }));
+    }
+
+    // only report findings reproducing on a fork of the chain at the same block
+    if let Some(node) = config.shadow_fork.clone() {
+        let onchain = config.onchain.as_ref().expect("--shadow-fork requires onchain mode");
+        let shadow_fork = ShadowFork::new(&node, &onchain.endpoint_url, &onchain.block_number);
+        let shadow_replayer = replayer.clone();
+        let shadow_executor = evm_executor_ref.clone();
+        fuzzer.add_validation_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+            let replay = shadow_replayer.borrow_mut().replay(state, txs);
+            let (report, reproduced) = shadow_fork.validate(&shadow_executor.borrow(), &replay);
+            if reproduced {
+                info!("[shadow-fork] finding reproduced on a fork at block {}", shadow_fork.block);
+                None
+            } else {
+                Some(format!("does not reproduce on the shadow fork:\n{}", report.join("\n")))
+            }
+        }));
+    }
+
+    // classify violating sequences depending on environment values beyond the bounds
+    if config.env_bounds.is_some() && config.env_bounds_report {
+        let env_replayer = replayer.clone();
+        let env_executor = evm_executor_ref.clone();
+        let env_initial_state = artifacts.initial_state.clone();
+        let env_work_dir = config.work_dir.clone();
+        fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+            let replay = env_replayer.borrow_mut().replay(state, txs);
+            let violations =
+                unrealistic_env(&mut env_executor.borrow_mut(), state, &env_initial_state, txs, &replay);
+            if violations.is_empty() {
+                return vec![];
+            }
+            warn!("[env-bounds] finding depends on an unrealistic environment:\n{}", violations.join("\n"));
+            let mut index = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(format!("{}/vulnerabilities/unrealistic_env", env_work_dir))
+                .expect("failed to open unrealistic env index");
+            writeln!(index, "{}", unsafe { DUMP_FILE_COUNT }).unwrap();
+            vec![("unrealistic_env.txt".to_string(), violations.join("\n"))]
+        }));
+    }
+
+    // findings are tagged with the price deviations they require
+    if config.chainlink.is_some() {
+        let chainlink_executor = evm_executor_ref.clone();
+        fuzzer.add_solution_hook(Box::new(move |_state: &mut EVMFuzzState, txs: &str| {
+            let moves = match &chainlink_executor.borrow().host.chainlink {
+                Some(chainlink) => chainlink.describe_moves(txs),
+                None => vec![],
+            };
+            if moves.is_empty() {
+                return vec![];
+            }
+            info!("[chainlink] finding requires moving feeds:\n{}", moves.join("\n"));
+            vec![("chainlink.txt".to_string(), moves.join("\n"))]
+        }));
+    }
+
+    // cost of the TWAP manipulations findings rely on
+    if config.twap_cost {
+        let twap_replayer = replayer.clone();
+        let twap_work_dir = config.work_dir.clone();
+        let max_capital = config.twap_max_capital;
+        fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+            let replay = twap_replayer.borrow_mut().replay(state, txs);
+            let manipulations = twap_manipulations(txs, &replay.traces);
+            if manipulations.is_empty() {
+                return vec![];
+            }
+            let mut report = manipulations.iter().map(|m| m.describe()).collect::<Vec<_>>();
+            let impractical = manipulations
+                .iter()
+                .any(|m| max_capital.map_or(false, |max| m.capital_multiple > max));
+            if impractical {
+                report.push(format!("downgraded: requires more than {}x the pool reserve", max_capital.unwrap()));
+                let mut index = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(format!("{}/vulnerabilities/impractical_twap", twap_work_dir))
+                    .expect("failed to open impractical TWAP index");
+                writeln!(index, "{}", unsafe { DUMP_FILE_COUNT }).unwrap();
+            }
+            info!("[twap] finding relies on TWAP manipulation:\n{}", report.join("\n"));
+            vec![("twap.txt".to_string(), report.join("\n"))]
+        }));
+    }
+
+    let contract_names: HashMap<EVMAddress, String> = contract_loader
+        .contracts
+        .iter()
+        .filter(|contract| !contract.name.starts_with("0x"))
+        .map(|contract| {
+            let name = contract.name.trim_end_matches('*').split('/').last().unwrap_or_default();
+            (contract.deployed_address, name.to_string())
+        })
+        .collect();
+
+    // summarize findings with an LLM, the summary is attached to the finding exported below
+    let summary: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    if config.llm_triage {
+        #[cfg(feature = "llm_triage")]
+        {
+            let mut triage = Triage::new(&config.llm_endpoint, &config.llm_model, artifacts.address_to_sourcemap.clone());
+            let triage_summary = summary.clone();
+            let triage_names = contract_names.clone();
+            fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+                let bugs = state.metadata().get::<BugMetadata>().unwrap().current_bugs.clone();
+                let trace = state.get_execution_result().new_state.trace.clone().to_string(state);
+                let affected = crate::evm::findings::affected_functions(txs, &triage_names);
+                let result = triage.summarize(&bugs, unsafe { ORACLE_OUTPUT.as_str() }, &trace, &affected);
+                *triage_summary.borrow_mut() = result.clone();
+                result.map(|summary| vec![("triage.md".to_string(), summary)]).unwrap_or_default()
+            }));
+        }
+        #[cfg(not(feature = "llm_triage"))]
+        panic!("--llm-triage requires llm_triage feature");
+    }
+
+    // export findings to [WORKDIR]/findings.json, after the other artifacts are written
+    let mut findings = FindingsExporter::new(&config.work_dir, contract_names);
+    fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+        let bugs = state.metadata().get::<BugMetadata>().unwrap().current_bugs.clone();
+        findings.export(
+            unsafe { DUMP_FILE_COUNT },
+            &bugs,
+            unsafe { ORACLE_OUTPUT.as_str() },
+            txs,
+            &state.get_execution_result().new_state.state,
+            summary.borrow_mut().take(),
+        );
+        vec![]
+    }));
+
+    // start fuzzed sequences with pending transactions to the targets
+    if config.mempool {
+        let onchain = config.onchain.as_ref().expect("--mempool requires on-chain fuzzing");
+        let receiver = subscribe(onchain.endpoint_url.clone(), artifacts.address_to_abi.keys().cloned().collect());
+        let pending: Rc<RefCell<Vec<PendingTx>>> = Rc::new(RefCell::new(vec![]));
+        let mempool_pending = pending.clone();
+        let mempool_executor = evm_executor_ref.clone();
+        let mempool_initial_state = artifacts.initial_state.clone();
+        let mempool_scheduler = infant_scheduler.clone();
+        fuzzer.add_control_hook(Box::new(move |state: &mut EVMFuzzState| {
+            while let Ok(tx) = receiver.try_recv() {
+                let input = match tx.to_input(state, mempool_initial_state.clone()) {
+                    Some(input) => input,
+                    None => {
+                        debug!("[mempool] {} calls an unknown function, skipped", tx.hash);
+                        continue;
+                    }
+                };
+                let res = mempool_executor.borrow_mut().execute(&input, state);
+                if res.reverted {
+                    debug!("[mempool] {} reverts, skipped", tx.hash);
+                    continue;
+                }
+                let mut new_state = res.new_state.clone();
+                new_state.trace.from_idx = Some(input.get_state_idx());
+                new_state.trace.add_input(ConciseEVMInput::from_input(&input, &res));
+                let idx = state.add_infant_state(&new_state, &mempool_scheduler, input.get_state_idx());
+                mempool_scheduler.sponsor_state(state.get_infant_state_state(), idx, 3);
+                debug!("[mempool] screening {}", tx.hash);
+                mempool_pending.borrow_mut().push(tx);
+            }
+            vec![]
+        }));
+        fuzzer.add_solution_hook(Box::new(move |_state: &mut EVMFuzzState, txs: &str| {
+            let pending = pending.borrow();
+            let enabling = pending_in_sequence(&pending, txs);
+            if enabling.is_empty() {
+                return vec![];
+            }
+            for tx in &enabling {
+                crate::status!("[mempool] ALERT: pending transaction {} enables the violation", tx.hash);
+            }
+            vec![(
+                "mempool.txt".to_string(),
+                enabling.iter().map(|tx| tx.hash.clone()).join("\n"),
+            )]
+        }));
+    }
+
+    // export profitable sequences as signed bundles
+    if let Some(path) = &config.bundle_config {
+        let onchain = config.onchain.as_ref().expect("--bundle-config requires on-chain fuzzing");
+        let bundle_config = BundleConfig::from_file(path);
+        let rpc = onchain.endpoint_url.clone();
+        let chain_id = onchain.chain_id as u64;
+        fuzzer.add_solution_hook(Box::new(move |state: &mut EVMFuzzState, txs: &str| {
+            let flashloan_data = &state.get_execution_result().new_state.state.flashloan_data;
+            if flashloan_data.earned <= flashloan_data.owed {
+                return vec![];
+            }
+            match build_bundle(&bundle_config, &rpc, chain_id, txs) {
+                Ok(bundle) => vec![(
+                    "bundle.json".to_string(),
+                    serde_json::to_string_pretty(&bundle).expect("failed to serialize bundle"),
+                )],
+                Err(e) => {
+                    warn!("[bundle] {}", e);
+                    vec![]
+                }
+            }
+        }));
+    }
+
+    // apply commands received on the control socket
+    if let Some(addr) = config.control.clone() {
+        let receiver = serve(&addr);
+        let control_cov = cov_middleware.clone();
+        let control_sourcemap = artifacts.address_to_sourcemap.clone();
+        let control_initial_state = artifacts.initial_state.clone();
+        let control_contracts: HashMap<String, EVMAddress> = contract_loader
+            .contracts
+            .iter()
+            .map(|c| (c.name.clone(), c.deployed_address))
+            .collect();
+        fuzzer.add_control_hook(Box::new(move |state: &mut EVMFuzzState| {
+            let mut seeds = vec![];
+            let mut paused = false;
+            loop {
+                let command = if paused {
+                    receiver.recv().ok()
+                } else {
+                    receiver.try_recv().ok()
+                };
+                match command {
+                    None => break,
+                    Some(ControlCommand::Pause) => {
+                        info!("[control] paused");
+                        paused = true;
+                    }
+                    Some(ControlCommand::Resume) => {
+                        info!("[control] resumed");
+                        paused = false;
+                    }
+                    Some(ControlCommand::InjectSeed(tx)) => {
+                        let (input, _) = ConciseEVMInput::deserialize_concise(tx.as_bytes())
+                            .to_input(control_initial_state.clone());
+                        seeds.push(input);
+                    }
+                    Some(ControlCommand::Focus(functions)) => {
+                        info!("[control] focus: {:?}", functions.iter().map(hex::encode).collect_vec());
+                        set_focus(functions);
+                    }
+                    Some(ControlCommand::Dump) => {
+                        crate::status!(
+                            "[control] executions: {}, corpus: {}, solutions: {}, aborted: {}",
+                            state.executions(),
+                            state.corpus().count(),
+                            state.solutions().count(),
+                            unsafe { BUDGET_ABORTS }
+                        );
+                        control_cov.borrow_mut().record_instruction_coverage(&control_sourcemap);
+                    }
+                    Some(ControlCommand::ToggleOracles(oracles, enabled)) => {
+                        for name in oracles {
+                            match oracle_kind(&name) {
+                                Some(kind) => set_bug_kind_enabled(kind, enabled),
+                                None => warn!("[control] unknown oracle {}", name),
+                            }
+                        }
+                    }
+                    Some(ControlCommand::Freeze(contracts, frozen)) => {
+                        for contract in contracts {
+                            let address = match control_contracts.get(&contract) {
+                                Some(address) => Some(*address),
+                                None => EVMAddress::from_str(&contract).ok(),
+                            };
+                            match address {
+                                Some(address) => set_frozen(address, frozen),
+                                None => warn!("[control] unknown contract {}", contract),
+                            }
+                        }
+                    }
+                }
+            }
+            seeds
+        }));
+    }
+
+    // AFL-style plot_data and end-of-campaign summary
+    let campaign_stats = Rc::new(RefCell::new(CampaignStats::new(&config.work_dir)));
+    let sample_stats = campaign_stats.clone();
+    fuzzer.add_control_hook(Box::new(move |state: &mut EVMFuzzState| {
+        sample_stats.borrow_mut().sample(state);
+        vec![]
+    }));
+    let violation_stats = campaign_stats.clone();
+    fuzzer.add_solution_hook(Box::new(move |_state: &mut EVMFuzzState, _txs: &str| {
+        let mut stats = violation_stats.borrow_mut();
+        stats.record_violation(&unsafe { ORACLE_OUTPUT.clone() });
+        stats.write_summary();
+        vec![]
+    }));
+
+    if let Some(file) = config.debug_file {
+        let transactions = std::fs::read_to_string(&file).expect("Failed to read solution file");
+        let mut evm_executor = evm_executor_ref.borrow_mut();
+        Debugger::new(&mut evm_executor, artifacts.initial_state.clone(), &transactions).run(state);
+        return;
+    }
+
+    if let Some(corpus) = config.cmin_corpus {
+        let output = config.cmin_output.unwrap_or(format!("{}/corpus_min", config.work_dir));
+        let mut evm_executor = evm_executor_ref.borrow_mut();
+        minimize_corpus(&mut evm_executor, state, &artifacts.initial_state, &corpus, &output);
+        return;
+    }
+
+    if let Some(corpus) = config.cross_evm_corpus {
+        let evm = config.cross_evm.as_ref().expect("--cross-evm-corpus requires --cross-evm");
+        let mut evm_executor = evm_executor_ref.borrow_mut();
+        cross_check_corpus(&mut evm_executor, state, &artifacts.initial_state, &corpus, evm, &config.work_dir);
+        return;
+    }
+
+    // seed the corpus with the sequences of other fuzzers
+    if let Some(pattern) = &config.import_corpus {
+        let sequences = load_sequences(pattern);
+        let mut imported = 0;
+        for sequence in &sequences {
+            let mut vm_state = artifacts.initial_state.clone();
+            for tx in to_concise(sequence, state) {
+                let (input, _) = tx.to_input(vm_state.clone());
+                fuzzer
+                    .evaluate_input_events(state, &mut executor, &mut mgr, input, false)
+                    .expect("Failed to execute imported sequence");
+                vm_state = state.get_execution_result().new_state.clone();
+                imported += 1;
+            }
+        }
+        info!("[import] executed {} transactions of {} imported sequences", imported, sequences.len());
+    }
+
+    // attempt the initializers of implementation contracts from each caller
+    for (contract, function) in &initializer_attempts {
+        let abi = match artifacts
+            .address_to_abi_object
+            .get(contract)
+            .and_then(|abis| abis.iter().find(|abi| abi.function == *function))
+        {
+            Some(abi) => abi.clone(),
+            None => continue,
+        };
+        for caller in state.callers_pool.clone() {
+            let tx = ConciseEVMInput {
+                caller,
+                contract: *contract,
+                data: Some(abi.clone()),
+                call_leak: u32::MAX,
+                ..Default::default()
+            };
+            let (input, _) = tx.to_input(artifacts.initial_state.clone());
+            fuzzer
+                .evaluate_input_events(state, &mut executor, &mut mgr, input, false)
+                .expect("Failed to attempt initializer");
+        }
+    }
+
+    if let Some(project) = config.watch.clone() {
+        let mut watcher = SourceWatcher::new(&project, None);
+
+        // remap the corpus of the previous campaigns onto the new deployment and re-run it, the
+        // sequences still increasing coverage are written back to the corpus
+        let addresses: HashMap<String, EVMAddress> = contract_loader
+            .contracts
+            .iter()
+            .map(|c| (c.name.clone(), c.deployed_address))
+            .collect();
+        let addresses_file = format!("{}/{}", config.work_dir, WATCH_ADDRESSES_FILE);
+        let previous: HashMap<String, EVMAddress> = std::fs::read_to_string(&addresses_file)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        std::fs::write(&addresses_file, serde_json::to_string_pretty(&addresses).unwrap())
+            .expect("Failed to write deployed addresses");
+        let corpus = glob(&format!("{}/corpus/*_replayable", config.work_dir))
+            .expect("Failed to read glob pattern")
+            .flatten()
+            .map(|path| {
+                let txs = std::fs::read_to_string(&path).expect("Failed to read corpus file");
+                std::fs::remove_file(&path).expect("Failed to remove corpus file");
+                remap_addresses(&txs, &previous, &addresses)
+            })
+            .collect_vec();
+        for txs in &corpus {
+            let mut vm_state = artifacts.initial_state.clone();
+            for txn in txs.split('\n').filter(|txn| txn.len() >= 4) {
+                let (input, call_until) = ConciseEVMInput::deserialize_concise(txn.as_bytes()).to_input(vm_state.clone());
+                unsafe {
+                    CALL_UNTIL = call_until;
+                }
+                fuzzer
+                    .evaluate_input_events(state, &mut executor, &mut mgr, input, false)
+                    .expect("Failed to re-run corpus");
+                vm_state = state.get_execution_result().new_state.clone();
+            }
+        }
+        unsafe {
+            CALL_UNTIL = u32::MAX;
+        }
+        info!("[watch] re-ran {} corpus sequences, fuzzing until {} changes", corpus.len(), project);
+
+        loop {
+            fuzzer
+                .fuzz_loop_for(&mut stages, &mut executor, state, &mut mgr, WATCH_POLL_ITERATIONS)
+                .expect("Fuzzing failed");
+            if watcher.changed() {
+                info!("[watch] sources changed, restarting");
+                return;
+            }
+        }
+    }
+
+    if let Some(spec) = &config.soak {
+        let mut soak = Soak::new(SoakConfig::parse(spec), &config.work_dir);
+        info!("[soak] fuzzing in segments of {:?}, digests every {:?}", soak.config.segment, soak.config.digest);
+        loop {
             fuzzer
-                .fuzz_loop(&mut stages, &mut executor, state, &mut mgr)
+                .fuzz_loop_for(&mut stages, &mut executor, state, &mut mgr, SOAK_POLL_ITERATIONS)
                 .expect("Fuzzing failed");
+            if soak.digest_due() {
+                soak.digest(&campaign_stats.borrow(), state);
+            }
+            if soak.cull_due() {
+                let mut evm_executor = evm_executor_ref.borrow_mut();
+                soak.cull(&mut |corpus, output| {
+                    minimize_corpus(&mut evm_executor, state, &artifacts.initial_state, corpus, output)
+                });
+            }
+            if soak.segment_due() {
+                soak.rotate(&campaign_stats.borrow());
+                *campaign_stats.borrow_mut() = CampaignStats::new(&config.work_dir);
+            }
         }
+    }
+
+    match config.replay_file {
+        None => match config.iterations {
+            Some(iterations) => {
+                fuzzer
+                    .fuzz_loop_for(&mut stages, &mut executor, state, &mut mgr, iterations)
+                    .expect("Fuzzing failed");
+                campaign_stats.borrow().write_summary();
+            }
+            None => {
+                fuzzer
+                    .fuzz_loop(&mut stages, &mut executor, state, &mut mgr)
+                    .expect("Fuzzing failed");
+                campaign_stats.borrow().write_summary();
+            }
+        },
         Some(files) => {
+            let tracer = Rc::new(RefCell::new(CallTracer::new()));
+            evm_executor_ref.borrow_mut().host.add_middlewares(tracer.clone());
             let initial_vm_state = artifacts.initial_state.clone();
             for file in glob(files.as_str()).expect("Failed to read glob pattern") {
                 let mut f = File::open(file.expect("glob issue")).expect("Failed to open file");
@@ -326,20 +1542,24 @@ pub fn evm_fuzzer(
                         .evaluate_input_events(state, &mut executor, &mut mgr, inp, false)
                         .unwrap();
 
-                    println!("============ Execution result {} =============", idx);
-                    println!(
+                    crate::status!("============ Execution result {} =============", idx);
+                    crate::status!(
                         "reverted: {:?}",
                         state.get_execution_result().clone().reverted
                     );
-                    println!(
+                    crate::status!(
                         "trace: {:?}",
                         state.get_execution_result().clone().new_state.trace
                     );
-                    println!(
+                    crate::status!(
                         "output: {:?}",
                         hex::encode(state.get_execution_result().clone().output)
                     );
-                    println!("================================================");
+                    crate::status!("steps:");
+                    for step in tracer.borrow_mut().take() {
+                        crate::status!("  {}", step);
+                    }
+                    crate::status!("================================================");
 
                     vm_state = state.get_execution_result().new_state.clone();
                 }
@@ -1,2 +1,6 @@
 pub mod evm_fuzzer;
 pub mod move_fuzzer;
+#[cfg(feature = "cairo_support")]
+pub mod cairo_fuzzer;
+#[cfg(feature = "cosmwasm_support")]
+pub mod cosmwasm_fuzzer;
@@ -35,8 +35,18 @@ use crate::r#move::movevm::MoveVM;
 #[cfg(feature = "sui_support")]
 use crate::r#move::mutator::MoveFuzzMutator;
 #[cfg(feature = "sui_support")]
+use crate::r#move::onchain::AptosOnChainConfig;
+#[cfg(feature = "sui_support")]
+use move_core_types::account_address::AccountAddress;
+#[cfg(feature = "sui_support")]
 use crate::r#move::oracles::typed_bug::TypedBugOracle;
 #[cfg(feature = "sui_support")]
+use crate::r#move::oracles::arithmetic_abort::ArithmeticAbortOracle;
+#[cfg(feature = "sui_support")]
+use crate::r#move::oracles::capability_leak::CapabilityLeakOracle;
+#[cfg(feature = "sui_support")]
+use crate::r#move::oracles::resource_leak::ResourceLeakOracle;
+#[cfg(feature = "sui_support")]
 use crate::r#move::scheduler::{MoveTestcaseScheduler, MoveVMStateScheduler};
 #[cfg(feature = "sui_support")]
 use crate::r#move::types::MoveFuzzState;
@@ -48,6 +58,9 @@ pub struct MoveFuzzConfig {
     pub target: String,
     pub work_dir: String,
     pub seed: u64,
+    /// Aptos fullnode REST API, fuzz modules published at target addresses if set
+    pub onchain_url: Option<String>,
+    pub onchain_version: Option<u64>,
 }
 
 pub static mut MOVE_ENABLED: bool = cfg!(feature = "move_support");
@@ -58,7 +71,11 @@ pub fn move_fuzzer(
 ) {
     let mut state: MoveFuzzState = FuzzState::new(config.seed);
     let mut vm: MoveVM<MoveFunctionInput, MoveFuzzState> = MoveVM::new();
-    let monitor = SimpleMonitor::new(|s| println!("{}", s));
+    vm.enable_coverage(config.work_dir.clone());
+    if let Some(url) = &config.onchain_url {
+        vm.set_onchain(AptosOnChainConfig::new(url.clone(), config.onchain_version));
+    }
+    let monitor = SimpleMonitor::new(|s| crate::status!("{}", s));
     let mut mgr = SimpleEventManager::new(monitor);
 
     let infant_scheduler = MoveVMStateScheduler {
@@ -69,12 +86,23 @@ pub fn move_fuzzer(
     };
 
     {
-        MoveCorpusInitializer::new(
+        let mut initializer = MoveCorpusInitializer::new(
             &mut state,
             &mut vm,
             &scheduler,
             &infant_scheduler,
-        ).setup(vec![config.target.clone()]);
+        );
+        if config.onchain_url.is_some() {
+            initializer.setup_onchain(
+                config
+                    .target
+                    .split(',')
+                    .map(|x| AccountAddress::from_hex_literal(x).expect("invalid address"))
+                    .collect(),
+            );
+        } else {
+            initializer.setup(vec![config.target.clone()]);
+        }
     }
 
     let vm_ref = Rc::new(RefCell::new(vm));
@@ -98,7 +126,10 @@ pub fn move_fuzzer(
     let infant_result_feedback = DataflowFeedback::new(vm_ref.borrow().get_read(), vm_ref.borrow().get_write());
 
     let mut oracles: Vec<Rc<RefCell<dyn Oracle<_, _, _, _, _, _, _, _, _, _>>>> = vec![
-        Rc::new(RefCell::new(TypedBugOracle::new()))
+        Rc::new(RefCell::new(TypedBugOracle::new())),
+        Rc::new(RefCell::new(ArithmeticAbortOracle::new())),
+        Rc::new(RefCell::new(CapabilityLeakOracle::new())),
+        Rc::new(RefCell::new(ResourceLeakOracle::new())),
     ];
     let mut producers = vec![];
 
@@ -5,6 +5,7 @@
 
 extern crate core;
 
+pub mod builder;
 pub mod cache;
 pub mod r#const;
 pub mod evm;
@@ -15,14 +16,25 @@ pub mod fuzzers;
 pub mod generic_vm;
 pub mod indexed_corpus;
 pub mod input;
+pub mod logger;
+pub mod mutator;
 pub mod oracle;
 pub mod scheduler;
 pub mod state;
 pub mod state_input;
+pub mod storage;
 pub mod telemetry;
 pub mod tracer;
 pub mod mutation_utils;
 
+pub use builder::FuzzerBuilder;
+
 
 #[cfg(feature = "sui_support")]
-pub mod r#move;
\ No newline at end of file
+pub mod r#move;
+
+#[cfg(feature = "cairo_support")]
+pub mod cairo;
+
+#[cfg(feature = "cosmwasm_support")]
+pub mod cosmwasm;
\ No newline at end of file
@@ -0,0 +1,68 @@
+//! Logging of the fuzzing campaign
+//!
+//! Diagnostics are emitted through `tracing` and written to stderr, either human readable or as
+//! JSON lines (`--log-json`). Levels are set per module with `RUST_LOG` (e.g.,
+//! `RUST_LOG=info,ityfuzz::evm::onchain=debug`), `info` by default and `warn` with `--quiet`.
+//!
+//! Stdout only carries the status output of the campaign (the statistics of the monitor, reports
+//! of violations and summaries of subcommands), printed with [`status!`], which `--quiet`
+//! silences. Logs never end up on stdout and status lines never end up in the logs.
+use tracing_subscriber::EnvFilter;
+
+pub static mut QUIET: bool = false;
+
+/// Print a line of status output to stdout, unless `--quiet`
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !unsafe { $crate::logger::QUIET } {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Filter of the logs, the `directives` of `RUST_LOG` if set, otherwise `info` (`warn` if quiet)
+fn filter(quiet: bool, directives: Option<&str>) -> EnvFilter {
+    directives
+        .and_then(|directives| EnvFilter::try_new(directives).ok())
+        .unwrap_or_else(|| EnvFilter::new(if quiet { "warn" } else { "info" }))
+}
+
+/// Install the global subscriber, a no-op if it is already installed
+pub fn init_logger(quiet: bool, json: bool) {
+    unsafe {
+        QUIET = quiet;
+    }
+    let filter = filter(quiet, std::env::var(EnvFilter::DEFAULT_ENV).ok().as_deref());
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    let _ = if json {
+        builder.json().try_init()
+    } else {
+        builder.with_target(false).try_init()
+    };
+}
+
+mod tests {
+    use tracing_subscriber::filter::LevelFilter;
+
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        assert_eq!(filter(false, None).max_level_hint(), Some(LevelFilter::INFO));
+        assert_eq!(filter(true, None).max_level_hint(), Some(LevelFilter::WARN));
+        // RUST_LOG takes precedence, with per-module levels
+        assert_eq!(filter(true, Some("debug")).max_level_hint(), Some(LevelFilter::DEBUG));
+        assert_eq!(
+            filter(false, Some("info,ityfuzz::evm::onchain=trace")).max_level_hint(),
+            Some(LevelFilter::TRACE)
+        );
+    }
+
+    #[test]
+    fn test_init_logger_twice() {
+        init_logger(false, false);
+        init_logger(false, true);
+        assert!(!unsafe { QUIET });
+    }
+}
@@ -0,0 +1,72 @@
+/// Natives of Aptos, used instead of the Sui natives when forking state from an Aptos fullnode
+///
+/// The Move stdlib natives are registered at 0x1 like on Aptos. Of the Aptos framework natives,
+/// creating signers and emitting events (dropped) are modeled, others abort when called.
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use move_binary_format::errors::PartialVMResult;
+use move_core_types::account_address::AccountAddress;
+use move_core_types::gas_algebra::InternalGas;
+use move_core_types::identifier::Identifier;
+use move_stdlib::natives::{all_natives, GasParameters};
+use move_vm_runtime::native_functions::{NativeContext, NativeFunction, NativeFunctionTable};
+use move_vm_types::loaded_data::runtime_types::Type;
+use move_vm_types::natives::function::NativeResult;
+use move_vm_types::values::Value;
+
+type RawNative = fn(&mut NativeContext, Vec<Type>, VecDeque<Value>) -> PartialVMResult<NativeResult>;
+
+/// `account::create_signer(addr: address): signer`
+fn create_signer(_context: &mut NativeContext, _ty_args: Vec<Type>, mut args: VecDeque<Value>) -> PartialVMResult<NativeResult> {
+    let address = args.pop_back().expect("address argument").value_as::<AccountAddress>()?;
+    Ok(NativeResult::ok(InternalGas::zero(), vec![Value::signer(address)].into()))
+}
+
+/// `event::write_to_event_store<T>(guid: vector<u8>, count: u64, msg: T)`
+fn drop_event(_context: &mut NativeContext, _ty_args: Vec<Type>, _args: VecDeque<Value>) -> PartialVMResult<NativeResult> {
+    Ok(NativeResult::ok(InternalGas::zero(), vec![].into()))
+}
+
+const FRAMEWORK_NATIVES: [(&str, &str, RawNative); 4] = [
+    ("account", "create_signer", create_signer),
+    ("create_signer", "create_signer", create_signer),
+    ("event", "write_to_event_store", drop_event),
+    ("event", "write_module_event_to_store", drop_event),
+];
+
+pub fn aptos_natives() -> NativeFunctionTable {
+    let mut natives = all_natives(AccountAddress::ONE, GasParameters::zeros());
+    natives.extend(FRAMEWORK_NATIVES.iter().map(|(module, name, native)| {
+        (
+            AccountAddress::ONE,
+            Identifier::new(*module).unwrap(),
+            Identifier::new(*name).unwrap(),
+            Arc::new(*native) as NativeFunction,
+        )
+    }));
+    natives
+}
+
+mod tests {
+    use super::*;
+    use move_vm_runtime::native_functions::NativeFunctions;
+
+    fn has_native(natives: &NativeFunctionTable, module: &str, name: &str) -> bool {
+        natives
+            .iter()
+            .any(|(address, m, n, _)| *address == AccountAddress::ONE && m.as_str() == module && n.as_str() == name)
+    }
+
+    #[test]
+    fn test_aptos_natives() {
+        let natives = aptos_natives();
+        assert!(has_native(&natives, "account", "create_signer"));
+        assert!(has_native(&natives, "event", "write_to_event_store"));
+        assert!(has_native(&natives, "vector", "borrow"));
+        // no Sui natives
+        assert!(!has_native(&natives, "tx_context", "derive_id"));
+        assert!(!natives.iter().any(|(address, _, _, _)| *address == AccountAddress::TWO));
+        assert!(NativeFunctions::new(natives).is_ok());
+    }
+}
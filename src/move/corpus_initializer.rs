@@ -80,6 +80,12 @@ impl<'a> MoveCorpusInitializer<'a>
         self.initialize_glob(targets);
     }
 
+    /// Setup with modules published at `addresses` on chain
+    pub fn setup_onchain(&mut self, addresses: Vec<AccountAddress>) {
+        self.basic_setup();
+        self.initialize_onchain(addresses);
+    }
+
     pub fn basic_setup(&mut self) {
         // setup callers
         self.state.add_caller(&AccountAddress::random());
@@ -136,6 +142,35 @@ impl<'a> MoveCorpusInitializer<'a>
         self.add_module(modules, modules_dependencies);
     }
 
+    /// Fetch modules published at `addresses` and their dependencies from chain.
+    /// Only natives provided by the Move stdlib / Sui are available.
+    pub fn initialize_onchain(&mut self, addresses: Vec<AccountAddress>) {
+        let onchain = self.executor.onchain.as_mut().expect("onchain is not enabled");
+        let mut modules = vec![];
+        for address in addresses {
+            for module in onchain.fetch_account_modules(address) {
+                modules.push(CompiledModule::deserialize_no_check_bounds(&module).expect("invalid module"));
+            }
+        }
+
+        let mut known = modules.iter().map(|m| m.self_id()).collect::<HashSet<_>>();
+        let mut to_fetch = modules.iter().flat_map(|m| m.immediate_dependencies()).collect_vec();
+        let mut modules_dependencies = vec![];
+        while let Some(module_id) = to_fetch.pop() {
+            if known.contains(&module_id) {
+                continue;
+            }
+            known.insert(module_id.clone());
+            let module = onchain
+                .fetch_module(&module_id)
+                .unwrap_or_else(|| panic!("dependency {} not found onchain", module_id));
+            let module = CompiledModule::deserialize_no_check_bounds(&module).expect("invalid module");
+            to_fetch.extend(module.immediate_dependencies());
+            modules_dependencies.push(module);
+        }
+        self.add_module(modules, modules_dependencies);
+    }
+
     fn extract_constants(&mut self, module: &CompiledModule) {
         let constant_pool = self.state.metadata_mut()
             .get_mut::<ConstantPoolMetadata>()
@@ -228,6 +263,10 @@ impl<'a> MoveCorpusInitializer<'a>
             }
 
             for (_, func) in funcs {
+                // transactions (PTBs) can only call public or entry functions
+                if func.is_friend_or_private() && !func.is_entry() {
+                    continue;
+                }
                 let input = self.build_input(&module_id, func.clone());
                 match input {
                     Some(input) => {
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use itertools::Itertools;
+use move_binary_format::file_format::Bytecode;
+use move_vm_runtime::loader::Function;
+
+/// Key of a function in coverage reports, `address::module::function`
+pub fn function_key(func: &Function) -> String {
+    match func.module_id() {
+        Some(module) => format!("{}::{}", module, func.name.as_str()),
+        None => format!("script::{}", func.name.as_str()),
+    }
+}
+
+/// Instruction and branch coverage of Move functions, reported per module and function
+/// in the same format as the EVM coverage middleware
+#[derive(Clone, Debug)]
+pub struct MoveCoverage {
+    pub pc_coverage: HashMap<String, HashSet<u16>>,
+    pub total_instr: HashMap<String, usize>,
+    pub total_branch_set: HashMap<String, HashSet<u16>>,
+    pub branch_coverage: HashMap<String, HashSet<(u16, bool)>>,
+    pub work_dir: String,
+    /// cached keys of functions (by address of the loaded function)
+    keys: HashMap<usize, String>,
+    last_report: Instant,
+}
+
+impl MoveCoverage {
+    pub fn new(work_dir: String) -> Self {
+        Self {
+            pc_coverage: HashMap::new(),
+            total_instr: HashMap::new(),
+            total_branch_set: HashMap::new(),
+            branch_coverage: HashMap::new(),
+            work_dir,
+            keys: HashMap::new(),
+            last_report: Instant::now(),
+        }
+    }
+
+    /// Register instructions and branches of a deployed function
+    pub fn on_insert(&mut self, func: &Function) {
+        if func.is_native() {
+            return;
+        }
+        let key = function_key(func);
+        let code = func.code();
+        self.total_instr.insert(key.clone(), code.len());
+        self.total_branch_set.insert(
+            key,
+            code.iter()
+                .enumerate()
+                .filter(|(_, instr)| matches!(instr, Bytecode::BrTrue(_) | Bytecode::BrFalse(_)))
+                .map(|(pc, _)| pc as u16)
+                .collect(),
+        );
+    }
+
+    /// Record the execution of instruction at `pc`, `branch` is whether the branch is taken
+    /// if the instruction is a conditional branch
+    pub fn on_step(&mut self, func: &Arc<Function>, pc: u16, branch: Option<bool>) {
+        let key = self
+            .keys
+            .entry(Arc::as_ptr(func) as usize)
+            .or_insert_with(|| function_key(func))
+            .clone();
+        self.pc_coverage.entry(key.clone()).or_default().insert(pc);
+        if let Some(taken) = branch {
+            self.branch_coverage.entry(key).or_default().insert((pc, taken));
+        }
+    }
+
+    /// Dump the report at most every `interval_secs` seconds
+    pub fn record_coverage_throttled(&mut self, interval_secs: u64) {
+        if self.last_report.elapsed().as_secs() >= interval_secs {
+            self.last_report = Instant::now();
+            self.record_instruction_coverage();
+        }
+    }
+
+    /// Covered / total instructions and branches of each function
+    fn summary(&self) -> String {
+        let mut data = format!("=================== Coverage Report ===================\n");
+        for (func, total) in self.total_instr.iter().sorted_by_key(|(k, _)| k.clone()) {
+            let cov = self.pc_coverage.get(func).map(|x| x.len()).unwrap_or(0);
+            if *total > 2 {
+                data.push_str(
+                    format!(
+                        "Function: {}, Instruction Coverage: {} / {} ({:.2}%)\n",
+                        func,
+                        cov,
+                        total,
+                        cov as f64 / *total as f64 * 100.0
+                    )
+                    .as_str(),
+                );
+            }
+        }
+        for (func, branches) in self.total_branch_set.iter().sorted_by_key(|(k, _)| k.clone()) {
+            let total = branches.len() * 2;
+            let cov = self.branch_coverage.get(func).map(|x| x.len()).unwrap_or(0);
+            if total > 0 {
+                data.push_str(
+                    format!(
+                        "Function: {}, Branch Coverage: {} / {} ({:.2}%)\n",
+                        func,
+                        cov,
+                        total,
+                        cov as f64 / total as f64 * 100.0
+                    )
+                    .as_str(),
+                );
+            }
+        }
+
+        data
+    }
+
+    /// Instructions and branch sides not covered of each function
+    fn details(&self) -> String {
+        let mut detail_cov_report = String::new();
+
+        detail_cov_report.push_str("\n\nNot Covered Instructions:\n");
+        for (func, total) in self.total_instr.iter().sorted_by_key(|(k, _)| k.clone()) {
+            let empty = HashSet::new();
+            let covered = self.pc_coverage.get(func).unwrap_or(&empty);
+            let not_covered = (0..*total as u16).filter(|pc| !covered.contains(pc)).collect_vec();
+            if not_covered.len() > 0 {
+                detail_cov_report.push_str(&format!(
+                    "==================== {} ====================\nPC: {:?}\n\n",
+                    func, not_covered
+                ));
+            }
+        }
+
+        detail_cov_report.push_str("\n\nNot Covered Branches:\n");
+        for (func, branches) in self.total_branch_set.iter().sorted_by_key(|(k, _)| k.clone()) {
+            let empty = HashSet::new();
+            let covered = self.branch_coverage.get(func).unwrap_or(&empty);
+            detail_cov_report.push_str(&format!("==================== {} ====================\n", func));
+            for pc in branches.iter().sorted() {
+                let count = covered.iter().filter(|(covered_pc, _)| covered_pc == pc).count();
+                if count < 2 {
+                    detail_cov_report.push_str(&format!("PC:{:x}, uncovered sides:{}\n\n", pc, 2 - count));
+                }
+            }
+        }
+
+        detail_cov_report
+    }
+
+    pub fn record_instruction_coverage(&mut self) {
+        let mut data = self.summary();
+        crate::status!("\n\n{}", data);
+
+        data.push_str(self.details().as_str());
+        data.push_str("\n\n\n");
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .append(false)
+            .create(true)
+            .open(format!(
+                "{}/cov_{}.txt",
+                self.work_dir.clone(),
+                SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+            ))
+            .unwrap();
+        file.write_all(data.as_bytes()).unwrap();
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut coverage = MoveCoverage::new("work_dir".to_string());
+        let func = "0x3::m::f".to_string();
+        coverage.total_instr.insert(func.clone(), 4);
+        coverage.total_branch_set.insert(func.clone(), HashSet::from([1]));
+        coverage.pc_coverage.insert(func.clone(), HashSet::from([0, 1]));
+        coverage.branch_coverage.insert(func.clone(), HashSet::from([(1, true)]));
+
+        let summary = coverage.summary();
+        assert!(summary.contains("Function: 0x3::m::f, Instruction Coverage: 2 / 4 (50.00%)"));
+        assert!(summary.contains("Function: 0x3::m::f, Branch Coverage: 1 / 2 (50.00%)"));
+        let details = coverage.details();
+        assert!(details.contains("PC: [2, 3]"));
+        assert!(details.contains("PC:1, uncovered sides:1"));
+
+        // both sides of the branch taken
+        coverage.branch_coverage.get_mut(&func).unwrap().insert((1, false));
+        assert!(!coverage.details().contains("uncovered sides"));
+    }
+}
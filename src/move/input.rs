@@ -773,6 +773,10 @@ impl VMInputT<MoveVMState, ModuleId, AccountAddress, ConciseMoveInput> for MoveF
     }
 
     fn set_staged_state(&mut self, state: MoveStagedVMState, idx: usize) {
+        // calls on a state with a PTB in progress continue it
+        if let Some(sender) = state.state.ptb_sender {
+            self.caller = sender;
+        }
         self.vm_state = state;
         self.vm_state_idx = idx;
     }
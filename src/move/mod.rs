@@ -7,6 +7,10 @@ pub mod types;
 pub mod vm_state;
 pub mod mutator;
 pub mod corpus_initializer;
+pub mod coverage;
 pub mod scheduler;
 pub mod oracles;
+pub mod onchain;
+pub mod aptos_natives;
+pub mod object;
 
@@ -49,6 +49,13 @@ use sui_types::object::{Object, Owner};
 use sui_types::storage::ChildObjectResolver;
 use crate::r#move::corpus_initializer::is_tx_context;
 use crate::state::HasCaller;
+use crate::r#move::coverage::MoveCoverage;
+use crate::r#move::onchain::AptosOnChainConfig;
+use crate::r#move::aptos_natives::aptos_natives;
+use crate::r#move::oracles::arithmetic_abort::writes_state;
+use crate::r#move::object::{can_access, continues_ptb, is_capability, object_gate, object_id, ptb_sender_after, with_tx_sender};
+use move_core_types::vm_status::StatusCode;
+use tracing::warn;
 
 pub static mut MOVE_COV_MAP: [u8; MAP_SIZE] = [0u8; MAP_SIZE];
 pub static mut MOVE_CMP_MAP: [u128; MAP_SIZE] = [0; MAP_SIZE];
@@ -61,6 +68,10 @@ pub struct MoveVM<I, S> {
     pub loader: Loader,
     pub protocol_config: ProtocolConfig,
     pub native_context: NativeContextExtensions<'static>,
+    /// instruction / branch coverage of functions, None if disabled
+    pub coverage: Option<MoveCoverage>,
+    /// Aptos fullnode to fetch modules and resources from, None if not onchain
+    pub onchain: Option<AptosOnChainConfig>,
     _phantom: std::marker::PhantomData<(I, S)>,
 }
 
@@ -139,14 +150,55 @@ impl<I, S> MoveVM<I, S> {
             loader: Loader::new(Self::get_natives(), Default::default()),
             protocol_config: Self::get_protocol_config(),
             native_context: Self::get_extension(),
+            coverage: None,
+            onchain: None,
             _phantom: Default::default(),
         }
     }
 
+    /// Fork state from an Aptos fullnode, must be set before deploying modules as Aptos natives
+    /// replace the Sui ones
+    pub fn set_onchain(&mut self, onchain: AptosOnChainConfig) {
+        assert!(self.functions.is_empty(), "onchain must be set before deploying modules");
+        self.onchain = Some(onchain);
+        self.loader = Loader::new(self.natives(), Default::default());
+    }
+
+    /// Fetch resources that are accessed but not in the state from chain
+    fn fetch_resources(&mut self, missing: Vec<(AccountAddress, Type)>, vm_state: &mut MoveVMState) {
+        let onchain = self.onchain.as_mut().expect("onchain not enabled");
+        for (addr, ty) in missing {
+            if let (Ok(TypeTag::Struct(tag)), Ok(layout)) =
+                (self.loader.type_to_type_tag(&ty), self.loader.type_to_type_layout(&ty))
+            {
+                if let Some(value) = onchain
+                    .fetch_resource(addr, &tag)
+                    .and_then(|bytes| Value::simple_deserialize(&bytes, &layout))
+                {
+                    vm_state.resources.entry(addr).or_default().insert(ty.clone(), value);
+                }
+            }
+            vm_state.fetched_resources.insert((addr, ty));
+        }
+    }
+
+    /// Track instruction and branch coverage, reports are written to `work_dir`
+    pub fn enable_coverage(&mut self, work_dir: String) {
+        self.coverage = Some(MoveCoverage::new(work_dir));
+    }
+
     pub fn get_natives() -> NativeFunctions {
         NativeFunctions::new(sui_move_natives_latest::all_natives(true)).expect("native functions")
     }
 
+    /// Natives of the chain fuzzed: Aptos in onchain mode, Sui otherwise
+    pub fn natives(&self) -> NativeFunctions {
+        match self.onchain {
+            Some(_) => NativeFunctions::new(aptos_natives()).expect("native functions"),
+            None => Self::get_natives(),
+        }
+    }
+
     pub fn get_protocol_config() -> ProtocolConfig {
         ProtocolConfig::get_for_max_version_UNSAFE()
     }
@@ -213,9 +265,42 @@ impl<I, S> MoveVM<I, S> {
     }
 }
 
-pub struct MoveVMTracer;
+/// Coverage maps before an execution, restored when the execution is discarded. Instruction and
+/// branch coverage are sets the retried execution covers again, so they are not restored.
+struct MapsSnapshot {
+    cov_map: [u8; MAP_SIZE],
+    cmp_map: [u128; MAP_SIZE],
+    read_map: [bool; MAP_SIZE],
+    write_map: [u8; MAP_SIZE],
+}
+
+impl MapsSnapshot {
+    fn take() -> Self {
+        unsafe {
+            Self {
+                cov_map: MOVE_COV_MAP,
+                cmp_map: MOVE_CMP_MAP,
+                read_map: MOVE_READ_MAP,
+                write_map: MOVE_WRITE_MAP,
+            }
+        }
+    }
+
+    fn restore(&self) {
+        unsafe {
+            MOVE_COV_MAP = self.cov_map;
+            MOVE_CMP_MAP = self.cmp_map;
+            MOVE_READ_MAP = self.read_map;
+            MOVE_WRITE_MAP = self.write_map;
+        }
+    }
+}
+
+pub struct MoveVMTracer<'a> {
+    pub coverage: Option<&'a mut MoveCoverage>,
+}
 
-impl ItyFuzzTracer for MoveVMTracer {
+impl<'a> ItyFuzzTracer for MoveVMTracer<'a> {
     fn on_step(&mut self, interpreter: &Interpreter, frame: &Frame, pc: u16, instruction: &Bytecode) {
         macro_rules! fast_peek_back {
             ($interp: expr) => { &$interp.operand_stack.value[$interp.operand_stack.value.len() - 1] };
@@ -235,6 +320,15 @@ impl ItyFuzzTracer for MoveVMTracer {
             };
         }
 
+        if let Some(coverage) = self.coverage.as_mut() {
+            let branch = match (instruction, fast_peek_back!(interpreter)) {
+                (Bytecode::BrTrue(_), Value(ValueImpl::Bool(b))) => Some(*b),
+                (Bytecode::BrFalse(_), Value(ValueImpl::Bool(b))) => Some(!*b),
+                _ => None,
+            };
+            coverage.on_step(&frame.function, pc, branch);
+        }
+
         match instruction {
             // COV MAP
             Bytecode::BrTrue(offset) => {
@@ -437,7 +531,7 @@ where
         let func_off = self.loader.module_cache.read().functions.len();
         let module_name = module.name().to_owned();
         let deployed_module_idx = module.self_id();
-        self.loader.module_cache.write().insert(&Self::get_natives(),
+        self.loader.module_cache.write().insert(&self.natives(),
                                                 &MoveVMState::default(),
                                                 deployed_module_idx.clone(),
                                                 &module).expect("internal deploy error");
@@ -447,6 +541,9 @@ where
                 .entry(deployed_module_idx.clone())
                 .or_insert_with(HashMap::new)
                 .insert(f.name.to_owned(), f.clone());
+            if let Some(coverage) = self.coverage.as_mut() {
+                coverage.on_insert(f);
+            }
 
             for ty in &f.parameter_types {
                 meta.register_type_tag(ty.clone(), &self.loader);
@@ -479,6 +576,60 @@ where
     where
         MoveVMState: VMStateT,
     {
+        if self.onchain.is_none() {
+            return self.execute_with_state(input, state, input.get_state().clone());
+        }
+        let mut vm_state = input.get_state().clone();
+        let maps = MapsSnapshot::take();
+        loop {
+            let result = self.execute_with_state(input, state, vm_state.clone());
+            let missing = result.new_state.state.missing_resources.clone();
+            if missing.is_empty() {
+                return result;
+            }
+            // resources accessed are not fetched yet, discard the execution, fetch them and retry
+            maps.restore();
+            self.clear_context();
+            self.fetch_resources(missing, &mut vm_state);
+        }
+    }
+
+    fn get_jmp(&self) -> &'static mut [u8; MAP_SIZE] {
+        unsafe { &mut MOVE_COV_MAP }
+    }
+
+    fn get_read(&self) -> &'static mut [bool; MAP_SIZE] {
+        unsafe { &mut MOVE_READ_MAP }
+    }
+
+    fn get_write(&self) -> &'static mut [u8; MAP_SIZE] {
+        unsafe { &mut MOVE_WRITE_MAP }
+    }
+
+    fn get_cmp(&self) -> &'static mut [u128; MAP_SIZE] {
+        unsafe { &mut MOVE_CMP_MAP }
+    }
+
+    fn state_changed(&self) -> bool {
+        unsafe { MOVE_STATE_CHANGED }
+    }
+}
+
+impl<I, S> MoveVM<I, S>
+where
+    I: VMInputT<MoveVMState, ModuleId, AccountAddress, ConciseMoveInput> + MoveFunctionInputT,
+    S: HasMetadata + HasCaller<MoveAddress>,
+{
+    fn execute_with_state(
+        &mut self,
+        input: &I,
+        state: &mut S,
+        mut vm_state: MoveVMState,
+    ) -> ExecutionResult<ModuleId, AccountAddress, MoveVMState, MoveOutput, ConciseMoveInput> {
+        vm_state.missing_resources.clear();
+        vm_state.arithmetic_aborts.clear();
+        vm_state.capability_leaks.clear();
+        vm_state.resource_leaks.clear();
         let initial_function = self
             .functions
             .get(&input.module_id())
@@ -499,15 +650,46 @@ where
             runtime_limits_config: Default::default(),
         };
 
-        let mut vm_state = input.get_state().clone();
         unsafe {
             MOVE_STATE_CHANGED = false;
         }
 
+        // Sui rejects transactions using objects not accessible by the sender
+        let sender = input.get_caller();
+        let inaccessible = input
+            .args()
+            .iter()
+            .filter_map(|arg| object_id(&arg.value).and_then(|id| vm_state.objects.get(&id)))
+            .any(|owner| !can_access(owner, &sender));
+        if inaccessible || !continues_ptb(&vm_state.ptb_sender, &sender) {
+            return ExecutionResult {
+                new_state: StagedVMState::new_with_state(vm_state),
+                output: MoveOutput { vars: vec![] },
+                reverted: true,
+                additional_info: None,
+            };
+        }
+
         // set up initial frame
         let mut current_frame = {
             let mut locals = Locals::new(initial_function.local_count());
+            let type_tag_info = state.metadata().get::<TypeTagInfoMeta>().expect("type tag info not found");
             for (i, value) in input.args().into_iter().enumerate() {
+                if type_tag_info.is_tx_context(&initial_function.parameter_types[i]) {
+                    match with_tx_sender(&value.value, sender) {
+                        Ok(tx_context) => locals.store_loc(i, tx_context, false).unwrap(),
+                        Err(e) => {
+                            warn!("{}::{}: {}", input.module_id(), input.function_name(), e);
+                            return ExecutionResult {
+                                new_state: StagedVMState::new_with_state(vm_state),
+                                output: MoveOutput { vars: vec![] },
+                                reverted: true,
+                                additional_info: None,
+                            };
+                        }
+                    }
+                    continue;
+                }
                 locals.store_loc(i, value.clone().value, false).unwrap();
             }
             Frame {
@@ -521,6 +703,7 @@ where
 
         let mut call_stack = vec![];
         let mut reverted = false;
+        let mut arithmetic_abort = None;
         let mut native_called = false;
         let mut gas_meter = UnmeteredGasMeter {};
 
@@ -530,11 +713,27 @@ where
         loop {
             let resolver = current_frame.resolver(vm_state.link_context(), &self.loader);
             let ret =
-                current_frame.execute_code(&resolver, &mut interp, &mut vm_state, &mut gas_meter, &mut MoveVMTracer{});
+                current_frame.execute_code(&resolver, &mut interp, &mut vm_state, &mut gas_meter, &mut MoveVMTracer {
+                    coverage: self.coverage.as_mut(),
+                });
             // println!("{:?}", ret);
 
-            if ret.is_err() {
+            if let Err(e) = &ret {
                 // println!("reverted {:?}", ret);
+                // Move relies on aborts, only those on paths writing state are unexpected
+                let path_writes = call_stack
+                    .iter()
+                    .chain(std::iter::once(&current_frame))
+                    .any(|frame| writes_state(frame.function.code()));
+                if e.major_status() == StatusCode::ARITHMETIC_ERROR && initial_function.is_entry() && path_writes {
+                    arithmetic_abort = Some(format!(
+                        "{}::{} aborted with arithmetic error in {} at pc {}",
+                        input.module_id(),
+                        input.function_name(),
+                        current_frame.function.name.as_str(),
+                        current_frame.pc
+                    ));
+                }
                 reverted = true;
                 break;
             }
@@ -632,6 +831,23 @@ where
             }
         }
 
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record_coverage_throttled(60);
+        }
+
+        if let Some(abort) = arithmetic_abort {
+            // oracles only check non-reverted executions, so report it with the state unchanged
+            let mut new_state = input.get_state().clone();
+            new_state.arithmetic_aborts.push(abort);
+            self.clear_context();
+            return ExecutionResult {
+                new_state: StagedVMState::new_with_state(new_state),
+                output: MoveOutput { vars: vec![] },
+                reverted: false,
+                additional_info: None,
+            };
+        }
+
         let resolver = current_frame.resolver(vm_state.link_context(), &self.loader);
 
 
@@ -659,30 +875,58 @@ where
                 .iter()
         ) {
             add_value!(v, t, Gate::Own);
+            if let (Ok(TypeTag::Struct(tag)), Ok(abilities)) = (self.loader.type_to_type_tag(t), self.loader.abilities(t)) {
+                if is_capability(abilities, v) {
+                    vm_state.capability_leaks.push(format!("{} returned to {}", tag, sender));
+                }
+            }
             // println!("adding as own: {:?}", v);
             out.vars.push((t.clone(), v.clone()));
             // println!("val: {:?} {:?}", v, resolver.loader.type_to_type_tag(t));
         }
 
         if native_called {
+            for id in self.native_context.get::<ObjectRuntime>().state.deleted_ids.keys() {
+                vm_state.objects.remove(&AccountAddress::from(*id));
+            }
+
+            // addresses the sender knows, objects sent to other addresses are lost
+            let mut known_addresses = input
+                .args()
+                .iter()
+                .flat_map(|arg| match &arg.value.0 {
+                    ValueImpl::Address(addr) => vec![*addr],
+                    ValueImpl::Container(Container::VecAddress(addrs)) => (**addrs).borrow().clone(),
+                    _ => vec![],
+                })
+                .collect::<HashSet<_>>();
+            known_addresses.insert(sender);
+            known_addresses.extend(self.functions.keys().map(|module| *module.address()));
+
             for (uid, (owner, ty, value)) in &self.native_context.get::<ObjectRuntime>().state.transfers {
-                let gate = match owner {
-                    Owner::AddressOwner(addr) => {
-                        if state.has_caller(&MoveAddress::new(addr.to_vec().try_into().unwrap())) {
-                            Gate::MutRef
-                        } else {
-                            continue;
-                        }
+                let object_id = AccountAddress::from(*uid);
+                vm_state.objects.insert(object_id, owner.clone());
+                // object IDs can be used as arguments (e.g., ID / address) of other functions
+                state.add_address(&MoveAddress::new(object_id.into_bytes()));
+
+                if let (Owner::AddressOwner(addr), Ok(TypeTag::Struct(tag))) = (owner, self.loader.type_to_type_tag(ty)) {
+                    let addr = MoveAddress::new(addr.to_vec().try_into().unwrap());
+                    let capability = self.loader.abilities(ty).map_or(false, |abilities| is_capability(abilities, value));
+                    if state.has_caller(&addr) && capability {
+                        vm_state.capability_leaks.push(format!("{} ({}) transferred to {}", tag, object_id, addr));
+                    } else if !state.has_caller(&addr) && !known_addresses.contains(&addr) {
+                        vm_state.resource_leaks.push(format!("{} ({}) left at {}", tag, object_id, addr));
                     }
-                    Owner::ObjectOwner(addr) => {
+                }
+
+                if let Owner::AddressOwner(addr) = owner {
+                    if !state.has_caller(&MoveAddress::new(addr.to_vec().try_into().unwrap())) {
                         continue;
                     }
-                    Owner::Shared { .. } => {
-                        Gate::MutRef
-                    }
-                    Owner::Immutable => {
-                        Gate::Ref
-                    }
+                }
+                let gate = match object_gate(owner) {
+                    Some(gate) => gate,
+                    None => continue,
                 };
 
                 // println!("adding as {:?}: {:?}", gate, value);
@@ -710,6 +954,10 @@ where
             self.clear_context();
         }
 
+        if !reverted {
+            vm_state.ptb_sender = ptb_sender_after(vm_state._hot_potato, sender);
+        }
+
         ExecutionResult {
             new_state: StagedVMState::new_with_state(vm_state),
             output: out,
@@ -717,26 +965,6 @@ where
             additional_info: None
         }
     }
-
-    fn get_jmp(&self) -> &'static mut [u8; MAP_SIZE] {
-        unsafe { &mut MOVE_COV_MAP }
-    }
-
-    fn get_read(&self) -> &'static mut [bool; MAP_SIZE] {
-        unsafe { &mut MOVE_READ_MAP }
-    }
-
-    fn get_write(&self) -> &'static mut [u8; MAP_SIZE] {
-        unsafe { &mut MOVE_WRITE_MAP }
-    }
-
-    fn get_cmp(&self) -> &'static mut [u128; MAP_SIZE] {
-        unsafe { &mut MOVE_CMP_MAP }
-    }
-
-    fn state_changed(&self) -> bool {
-        unsafe { MOVE_STATE_CHANGED }
-    }
 }
 
 
@@ -752,6 +980,7 @@ impl ChildObjectResolver for DummyChildObjectResolver {
 mod tests {
     use std::borrow::Borrow;
     use std::cell::RefCell;
+    use std::collections::HashSet;
     use std::rc::Rc;
     use move_vm_types::loaded_data::runtime_types::CachedStructIndex;
     use move_vm_types::loaded_data::runtime_types::Type::Struct;
@@ -761,18 +990,28 @@ mod tests {
 
     use move_vm_types::values::{ContainerRef, Reference, ReferenceImpl, Value, ValueImpl};
 
+    type TestVM = MoveVM<
+        MoveFunctionInput,
+        FuzzState<MoveFunctionInput, MoveVMState, ModuleId, AccountAddress, MoveOutput, ConciseMoveInput>,
+    >;
+
     fn _run(
         bytecode: &str,
         args: Vec<CloneableValue>,
         func: &str,
+    ) -> ExecutionResult<ModuleId, AccountAddress, MoveVMState, MoveOutput, ConciseMoveInput> {
+        _run_on(&mut TestVM::new(), bytecode, args, func)
+    }
+
+    fn _run_on(
+        mv: &mut TestVM,
+        bytecode: &str,
+        args: Vec<CloneableValue>,
+        func: &str,
     ) -> ExecutionResult<ModuleId, AccountAddress, MoveVMState, MoveOutput, ConciseMoveInput> {
         let module_bytecode = hex::decode(bytecode).unwrap();
         let module = CompiledModule::deserialize_no_check_bounds(&module_bytecode).unwrap();
         let module_idx = module.self_id();
-        let mut mv = MoveVM::<
-            MoveFunctionInput,
-            FuzzState<MoveFunctionInput, MoveVMState, ModuleId, AccountAddress, MoveOutput, ConciseMoveInput>,
-        >::new();
         let _loc = mv
             .deploy(
                 module,
@@ -800,6 +1039,13 @@ mod tests {
                     values: Default::default(),
                     typed_bug: vec![],
                     ref_in_use: vec![],
+                    fetched_resources: Default::default(),
+                    missing_resources: vec![],
+                    objects: Default::default(),
+                    ptb_sender: None,
+                    arithmetic_aborts: vec![],
+                    capability_leaks: vec![],
+                    resource_leaks: vec![],
                 },
                 stage: vec![],
                 initialized: false,
@@ -829,6 +1075,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_coverage() {
+        // module 0x3::TestMod {
+        //         public fun test1(data: u64) : u64 {
+        //         data * 2
+        //     }
+        // }
+        let module_hex = "a11ceb0b0500000006010002030205050703070a0e0818200c38130000000100000001030007546573744d6f6405746573743100000000000000000000000000000000000000000000000000000000000000030001000001040b00060200000000000000180200";
+        let mut mv = TestVM::new();
+        mv.enable_coverage("work_dir".to_string());
+        _run_on(&mut mv, module_hex, vec![CloneableValue::from(Value::u64(20))], "test1");
+
+        let coverage = mv.coverage.as_ref().unwrap();
+        // move_loc, ld_u64, mul, ret
+        assert_eq!(coverage.total_instr.values().cloned().collect::<Vec<_>>(), vec![4]);
+        let key = coverage.total_instr.keys().next().unwrap();
+        assert!(key.ends_with("::TestMod::test1"));
+        assert_eq!(coverage.pc_coverage[key], HashSet::from([0, 1, 2, 3]));
+        assert!(coverage.total_branch_set[key].is_empty());
+        assert!(coverage.branch_coverage.is_empty());
+    }
+
     #[test]
     fn test_dropping() {
         // module 0x3::TestMod {
@@ -903,4 +1171,50 @@ mod tests {
                        "test2",
         );
     }
+
+    #[test]
+    fn test_maps_snapshot() {
+        let maps = MapsSnapshot::take();
+        unsafe {
+            MOVE_COV_MAP[1] = MOVE_COV_MAP[1].wrapping_add(1);
+            MOVE_WRITE_MAP[2] = MOVE_WRITE_MAP[2].wrapping_add(1);
+        }
+        maps.restore();
+        unsafe {
+            assert_eq!(MOVE_COV_MAP[1], maps.cov_map[1]);
+            assert_eq!(MOVE_WRITE_MAP[2], maps.write_map[2]);
+        }
+    }
+
+    fn onchain_vm() -> MoveVM<
+        MoveFunctionInput,
+        FuzzState<MoveFunctionInput, MoveVMState, ModuleId, AccountAddress, MoveOutput, ConciseMoveInput>,
+    > {
+        let mut mv = MoveVM::new();
+        mv.set_onchain(AptosOnChainConfig::new("http://localhost:8080/v1".to_string(), None));
+        mv
+    }
+
+    #[test]
+    fn test_onchain_deploy() {
+        // module 0x3::TestMod { public fun test1(data: u64) : u64 { data * 2 } }
+        let module_hex = "a11ceb0b0500000006010002030205050703070a0e0818200c38130000000100000001030007546573744d6f6405746573743100000000000000000000000000000000000000000000000000000000000000030001000001040b00060200000000000000180200";
+        let module = CompiledModule::deserialize_no_check_bounds(&hex::decode(module_hex).unwrap()).unwrap();
+        let mut mv = onchain_vm();
+        mv.deploy(module, None, AccountAddress::new([0; 32]), &mut FuzzState::new(0));
+        assert_eq!(mv.functions.len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_onchain_after_deploy() {
+        let module_hex = "a11ceb0b0500000006010002030205050703070a0e0818200c38130000000100000001030007546573744d6f6405746573743100000000000000000000000000000000000000000000000000000000000000030001000001040b00060200000000000000180200";
+        let module = CompiledModule::deserialize_no_check_bounds(&hex::decode(module_hex).unwrap()).unwrap();
+        let mut mv = MoveVM::<
+            MoveFunctionInput,
+            FuzzState<MoveFunctionInput, MoveVMState, ModuleId, AccountAddress, MoveOutput, ConciseMoveInput>,
+        >::new();
+        mv.deploy(module, None, AccountAddress::new([0; 32]), &mut FuzzState::new(0));
+        mv.set_onchain(AptosOnChainConfig::new("http://localhost:8080/v1".to_string(), None));
+    }
 }
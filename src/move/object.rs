@@ -0,0 +1,170 @@
+/// Helpers for Sui's object model
+///
+/// An object is a struct with `key` ability whose first field is `UID { id: ID { bytes: address } }`.
+/// Objects are either owned by an address (only the owner can use them in a transaction),
+/// shared (anyone can use them by mutable reference), immutable (by reference only),
+/// or wrapped / owned by other objects (not accessible from transactions).
+use move_core_types::abilities::AbilitySet;
+use move_core_types::account_address::AccountAddress;
+use move_vm_types::values::{Container, ContainerRef, Value, ValueImpl};
+use sui_types::object::Owner;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::r#move::vm_state::Gate;
+
+fn first_field(v: &ValueImpl) -> Option<ValueImpl> {
+    match v {
+        ValueImpl::Container(Container::Struct(fields)) => (**fields).borrow().get(0).cloned(),
+        ValueImpl::ContainerRef(ContainerRef::Local(Container::Struct(fields))) => {
+            (**fields).borrow().get(0).cloned()
+        }
+        _ => None,
+    }
+}
+
+/// ID of an object, None if the value is not an object
+pub fn object_id(value: &Value) -> Option<AccountAddress> {
+    let uid = first_field(&value.0)?;
+    let id = first_field(&uid)?;
+    match first_field(&id)? {
+        ValueImpl::Address(addr) => Some(addr),
+        _ => None,
+    }
+}
+
+/// How a transaction can use an object with given owner, None if the object is not
+/// accessible from transactions.
+/// Owned objects can be taken by value, shared objects by mutable reference and
+/// immutable objects by reference.
+pub fn object_gate(owner: &Owner) -> Option<Gate> {
+    match owner {
+        Owner::AddressOwner(_) => Some(Gate::Own),
+        Owner::Shared { .. } => Some(Gate::MutRef),
+        Owner::Immutable => Some(Gate::Ref),
+        Owner::ObjectOwner(_) => None,
+    }
+}
+
+/// Whether `sender` can use the object with given owner in its transaction
+pub fn can_access(owner: &Owner, sender: &AccountAddress) -> bool {
+    match owner {
+        Owner::AddressOwner(addr) => addr.to_vec() == sender.to_vec(),
+        Owner::ObjectOwner(_) => false,
+        Owner::Shared { .. } | Owner::Immutable => true,
+    }
+}
+
+fn field_count(v: &ValueImpl) -> Option<usize> {
+    match v {
+        ValueImpl::Container(Container::Struct(fields)) => Some((**fields).borrow().len()),
+        ValueImpl::ContainerRef(ContainerRef::Local(Container::Struct(fields))) => Some((**fields).borrow().len()),
+        _ => None,
+    }
+}
+
+/// Whether the object grants privileges to its holder (e.g., AdminCap): an object that can be
+/// neither copied nor dropped and carries nothing but its UID, i.e., it only proves authority
+pub fn is_capability(abilities: AbilitySet, value: &Value) -> bool {
+    abilities.has_key() && !abilities.has_copy() && !abilities.has_drop() && field_count(&value.0) == Some(1)
+}
+
+/// TxContext (passed as `&mut TxContext`) with sender replaced, so that the sender
+/// of a transaction is consistent across all calls and with the object ownership
+pub fn with_tx_sender(tx_context: &Value, sender: AccountAddress) -> Result<Value, String> {
+    let mut fields = match &tx_context.0 {
+        ValueImpl::ContainerRef(ContainerRef::Local(Container::Struct(fields))) => {
+            (**fields).borrow().clone()
+        }
+        _ => return Err(format!("invalid tx context {:?}", tx_context)),
+    };
+    match fields.first() {
+        Some(ValueImpl::Address(_)) => fields[0] = ValueImpl::Address(sender),
+        _ => return Err(format!("tx context without sender {:?}", tx_context)),
+    }
+    Ok(Value(ValueImpl::ContainerRef(ContainerRef::Local(Container::Struct(Rc::new(
+        RefCell::new(fields),
+    ))))))
+}
+
+/// Calls of a programmable transaction block (PTB) are sent by the same sender: a call consuming
+/// hot potatoes (e.g., a flash loan receipt) returned by a previous call continues its PTB
+pub fn continues_ptb(ptb_sender: &Option<AccountAddress>, sender: &AccountAddress) -> bool {
+    match ptb_sender {
+        Some(ptb_sender) => ptb_sender == sender,
+        None => true,
+    }
+}
+
+/// Sender of the PTB in progress after a call by `sender`, None once all hot potatoes are consumed
+pub fn ptb_sender_after(hot_potatoes: usize, sender: AccountAddress) -> Option<AccountAddress> {
+    (hot_potatoes > 0).then_some(sender)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_id() {
+        let id = AccountAddress::random();
+        let wrap = |v: ValueImpl| ValueImpl::Container(Container::Struct(Rc::new(RefCell::new(vec![v]))));
+        let object = Value(ValueImpl::Container(Container::Struct(Rc::new(RefCell::new(vec![
+            wrap(wrap(ValueImpl::Address(id))),
+            ValueImpl::U64(1),
+        ])))));
+        assert_eq!(object_id(&object), Some(id));
+        assert_eq!(object_id(&Value(ValueImpl::U64(1))), None);
+    }
+
+    fn tx_context(fields: Vec<ValueImpl>) -> Value {
+        Value(ValueImpl::ContainerRef(ContainerRef::Local(Container::Struct(Rc::new(RefCell::new(fields))))))
+    }
+
+    #[test]
+    fn test_with_tx_sender() {
+        let sender = AccountAddress::random();
+        let ctx = tx_context(vec![ValueImpl::Address(AccountAddress::ZERO), ValueImpl::U64(7)]);
+        let replaced = with_tx_sender(&ctx, sender).unwrap();
+        match &replaced.0 {
+            ValueImpl::ContainerRef(ContainerRef::Local(Container::Struct(fields))) => {
+                let fields = (**fields).borrow();
+                assert!(matches!(fields[0], ValueImpl::Address(addr) if addr == sender));
+                assert!(matches!(fields[1], ValueImpl::U64(7)));
+            }
+            _ => panic!("tx context is not a struct reference"),
+        }
+
+        assert!(with_tx_sender(&Value(ValueImpl::U64(1)), sender).is_err());
+        assert!(with_tx_sender(&tx_context(vec![]), sender).is_err());
+        assert!(with_tx_sender(&tx_context(vec![ValueImpl::U64(1)]), sender).is_err());
+    }
+
+    #[test]
+    fn test_is_capability() {
+        use move_core_types::abilities::Ability;
+        let uid = ValueImpl::Container(Container::Struct(Rc::new(RefCell::new(vec![ValueImpl::Address(
+            AccountAddress::random(),
+        )]))));
+        let object = |fields: Vec<ValueImpl>| Value(ValueImpl::Container(Container::Struct(Rc::new(RefCell::new(fields)))));
+        let key = AbilitySet(Ability::Key as u8);
+        let key_store = AbilitySet(Ability::Key as u8 | Ability::Store as u8);
+
+        // struct AdminCap has key { id: UID }
+        assert!(is_capability(key, &object(vec![uid.clone()])));
+        assert!(is_capability(key_store, &object(vec![uid.clone()])));
+        // struct Pool has key { id: UID, balance: u64 }
+        assert!(!is_capability(key, &object(vec![uid.clone(), ValueImpl::U64(1)])));
+        // not an object
+        assert!(!is_capability(AbilitySet(Ability::Drop as u8), &object(vec![uid.clone()])));
+        assert!(!is_capability(AbilitySet(Ability::Key as u8 | Ability::Drop as u8), &object(vec![uid])));
+    }
+
+    #[test]
+    fn test_ptb_sender() {
+        let (alice, bob) = (AccountAddress::random(), AccountAddress::random());
+        assert!(continues_ptb(&None, &bob));
+        assert!(continues_ptb(&Some(alice), &alice));
+        assert!(!continues_ptb(&Some(alice), &bob));
+        assert_eq!(ptb_sender_after(1, alice), Some(alice));
+        assert_eq!(ptb_sender_after(0, alice), None);
+    }
+}
@@ -0,0 +1,115 @@
+/// Fetches modules and resources from an Aptos fullnode (REST API) for onchain fuzzing
+use std::collections::HashMap;
+use tracing::{debug, warn};
+use std::time::Duration;
+use move_core_types::account_address::AccountAddress;
+use move_core_types::language_storage::{ModuleId, StructTag};
+use serde_json::Value;
+
+#[derive(Clone, Debug)]
+pub struct AptosOnChainConfig {
+    /// e.g., https://fullnode.mainnet.aptoslabs.com/v1
+    pub endpoint_url: String,
+    /// ledger version to fork from, latest if None
+    pub ledger_version: Option<u64>,
+    pub client: reqwest::blocking::Client,
+
+    module_cache: HashMap<ModuleId, Option<Vec<u8>>>,
+    resource_cache: HashMap<(AccountAddress, StructTag), Option<Vec<u8>>>,
+}
+
+impl AptosOnChainConfig {
+    pub fn new(endpoint_url: String, ledger_version: Option<u64>) -> Self {
+        Self {
+            endpoint_url: endpoint_url.trim_end_matches('/').to_string(),
+            ledger_version,
+            client: reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(20))
+                .build()
+                .expect("build client failed"),
+            module_cache: Default::default(),
+            resource_cache: Default::default(),
+        }
+    }
+
+    fn url(&self, path: String) -> String {
+        match self.ledger_version {
+            Some(version) => format!("{}{}?ledger_version={}", self.endpoint_url, path, version),
+            None => format!("{}{}", self.endpoint_url, path),
+        }
+    }
+
+    fn get(&self, url: String, bcs: bool) -> Option<Vec<u8>> {
+        let mut req = self.client.get(url.clone());
+        if bcs {
+            req = req.header("Accept", "application/x-bcs");
+        }
+        match req.send() {
+            Ok(resp) => {
+                if !resp.status().is_success() {
+                    return None;
+                }
+                resp.bytes().ok().map(|x| x.to_vec())
+            }
+            Err(e) => {
+                warn!("failed to fetch {}: {:?}", url, e);
+                None
+            }
+        }
+    }
+
+    fn decode_bytecode(module: &Value) -> Option<Vec<u8>> {
+        module["bytecode"]
+            .as_str()
+            .and_then(|x| hex::decode(x.trim_start_matches("0x")).ok())
+    }
+
+    /// Bytecode of all modules published at `address`
+    pub fn fetch_account_modules(&mut self, address: AccountAddress) -> Vec<Vec<u8>> {
+        let url = self.url(format!("/accounts/{}/modules", address.to_hex_literal()));
+        debug!("fetching modules from {}", url);
+        let resp = match self.get(url, false) {
+            Some(resp) => resp,
+            None => return vec![],
+        };
+        match serde_json::from_slice::<Value>(&resp) {
+            Ok(Value::Array(modules)) => modules.iter().filter_map(Self::decode_bytecode).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Bytecode of a module, None if it is not published
+    pub fn fetch_module(&mut self, module_id: &ModuleId) -> Option<Vec<u8>> {
+        if let Some(module) = self.module_cache.get(module_id) {
+            return module.clone();
+        }
+        let url = self.url(format!(
+            "/accounts/{}/module/{}",
+            module_id.address().to_hex_literal(),
+            module_id.name()
+        ));
+        debug!("fetching module from {}", url);
+        let module = self
+            .get(url, false)
+            .and_then(|resp| serde_json::from_slice::<Value>(&resp).ok())
+            .and_then(|json| Self::decode_bytecode(&json));
+        self.module_cache.insert(module_id.clone(), module.clone());
+        module
+    }
+
+    /// BCS encoded resource, None if the account does not hold the resource
+    pub fn fetch_resource(&mut self, address: AccountAddress, struct_tag: &StructTag) -> Option<Vec<u8>> {
+        let key = (address, struct_tag.clone());
+        if let Some(resource) = self.resource_cache.get(&key) {
+            return resource.clone();
+        }
+        let url = self.url(format!(
+            "/accounts/{}/resource/{}",
+            address.to_hex_literal(),
+            struct_tag
+        ));
+        let resource = self.get(url, true);
+        self.resource_cache.insert(key, resource.clone());
+        resource
+    }
+}
@@ -0,0 +1,85 @@
+use crate::oracle::Oracle;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use itertools::Itertools;
+use move_binary_format::CompiledModule;
+use move_binary_format::file_format::Bytecode;
+use move_core_types::language_storage::ModuleId;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::r#move::input::{ConciseMoveInput, MoveFunctionInput};
+use crate::r#move::oracles::ARITHMETIC_ABORT_BUG_IDX;
+
+use crate::r#move::types::{MoveAddress, MoveFuzzState, MoveOracleCtx, MoveOutput, MoveSlotTy};
+use crate::r#move::vm_state::MoveVMState;
+
+/// Reports entry functions aborting with arithmetic errors (overflow, underflow, division by zero)
+/// on paths writing state, which are usually unchecked user inputs rather than intended aborts.
+/// Move relies on aborts, so those in functions only reading or computing are not reported.
+pub struct ArithmeticAbortOracle;
+
+/// Whether the code writes state: through mutable references (e.g., fields of objects) or to
+/// global storage
+pub fn writes_state(code: &[Bytecode]) -> bool {
+    code.iter().any(|instr| {
+        matches!(
+            instr,
+            Bytecode::WriteRef
+                | Bytecode::MoveTo(_)
+                | Bytecode::MoveToGeneric(_)
+                | Bytecode::MoveFrom(_)
+                | Bytecode::MoveFromGeneric(_)
+                | Bytecode::MutBorrowGlobal(_)
+                | Bytecode::MutBorrowGlobalGeneric(_)
+        )
+    })
+}
+
+impl ArithmeticAbortOracle {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Oracle<MoveVMState, MoveAddress, CompiledModule, MoveFunctionInput, ModuleId, MoveSlotTy, MoveOutput, MoveFunctionInput, MoveFuzzState, ConciseMoveInput>
+for ArithmeticAbortOracle {
+    fn transition(&self, _ctx: &mut MoveOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut MoveOracleCtx<'_>,
+        _stage: u64,
+    ) -> Vec<u64> {
+        ctx.post_state.arithmetic_aborts.iter().map(|finding| {
+            unsafe {
+                ORACLE_OUTPUT += format!("[arithmetic_abort] {}\n", finding).as_str();
+            }
+            let mut hasher = DefaultHasher::new();
+            finding.hash(&mut hasher);
+            (hasher.finish() << 8) + ARITHMETIC_ABORT_BUG_IDX
+        }).collect_vec()
+    }
+}
+
+mod tests {
+    use super::*;
+    use move_binary_format::file_format::StructDefinitionIndex;
+
+    #[test]
+    fn test_writes_state() {
+        // a + b
+        assert!(!writes_state(&[Bytecode::CopyLoc(0), Bytecode::CopyLoc(1), Bytecode::Add, Bytecode::Ret]));
+        // *r = *r + 1
+        assert!(writes_state(&[
+            Bytecode::CopyLoc(0),
+            Bytecode::ReadRef,
+            Bytecode::LdU64(1),
+            Bytecode::Add,
+            Bytecode::MoveLoc(0),
+            Bytecode::WriteRef,
+            Bytecode::Ret,
+        ]));
+        assert!(writes_state(&[Bytecode::MutBorrowGlobal(StructDefinitionIndex(0)), Bytecode::Pop, Bytecode::Ret]));
+    }
+}
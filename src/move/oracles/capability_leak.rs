@@ -0,0 +1,43 @@
+use crate::oracle::Oracle;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use itertools::Itertools;
+use move_binary_format::CompiledModule;
+use move_core_types::language_storage::ModuleId;
+use crate::fuzzer::ORACLE_OUTPUT;
+use crate::r#move::input::{ConciseMoveInput, MoveFunctionInput};
+use crate::r#move::oracles::CAPABILITY_LEAK_BUG_IDX;
+
+use crate::r#move::types::{MoveAddress, MoveFuzzState, MoveOracleCtx, MoveOutput, MoveSlotTy};
+use crate::r#move::vm_state::MoveVMState;
+
+/// Reports capability objects (e.g., AdminCap) transferred or returned to fuzzer-controlled addresses
+pub struct CapabilityLeakOracle;
+
+impl CapabilityLeakOracle {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Oracle<MoveVMState, MoveAddress, CompiledModule, MoveFunctionInput, ModuleId, MoveSlotTy, MoveOutput, MoveFunctionInput, MoveFuzzState, ConciseMoveInput>
+for CapabilityLeakOracle {
+    fn transition(&self, _ctx: &mut MoveOracleCtx<'_>, _stage: u64) -> u64 {
+        0
+    }
+
+    fn oracle(
+        &self,
+        ctx: &mut MoveOracleCtx<'_>,
+        _stage: u64,
+    ) -> Vec<u64> {
+        ctx.post_state.capability_leaks.iter().map(|finding| {
+            unsafe {
+                ORACLE_OUTPUT += format!("[capability_leak] {}\n", finding).as_str();
+            }
+            let mut hasher = DefaultHasher::new();
+            finding.hash(&mut hasher);
+            (hasher.finish() << 8) + CAPABILITY_LEAK_BUG_IDX
+        }).collect_vec()
+    }
+}
@@ -1,3 +1,9 @@
 pub mod typed_bug;
+pub mod arithmetic_abort;
+pub mod capability_leak;
+pub mod resource_leak;
 
 pub static TYPED_BUG_BUG_IDX: u64 = 4;
+pub static ARITHMETIC_ABORT_BUG_IDX: u64 = 5;
+pub static CAPABILITY_LEAK_BUG_IDX: u64 = 6;
+pub static RESOURCE_LEAK_BUG_IDX: u64 = 7;
@@ -1,6 +1,8 @@
 use crate::generic_vm::vm_state::VMStateT;
+use tracing::debug;
 
-use move_binary_format::errors::{PartialVMResult, VMResult};
+use move_binary_format::errors::{PartialVMError, PartialVMResult, VMResult};
+use move_core_types::vm_status::StatusCode;
 use move_core_types::account_address::AccountAddress;
 use move_core_types::effects::Op;
 use move_core_types::gas_algebra::NumBytes;
@@ -15,7 +17,7 @@ use move_vm_types::values::{Container, ContainerRef, GlobalValue, IndexedRef, Va
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::ops::Index;
 use libafl::prelude::{HasMetadata, Rand};
@@ -27,6 +29,7 @@ use serde_json::ser::State;
 use crate::evm::onchain::endpoints::Chain::POLYGON;
 use crate::r#move::input::StructAbilities;
 use crate::r#move::movevm::TypeTagInfoMeta;
+use sui_types::object::Owner;
 
 pub trait MoveVMStateT {
     fn values(&self) -> (&HashMap<Type, Vec<(GatedValue, usize)>>);
@@ -89,6 +92,25 @@ pub struct MoveVMState {
     pub typed_bug: Vec<String>,
 
     pub ref_in_use: Vec<(Type, GatedValue)>,
+
+    /// Resources already fetched from chain (including those that do not exist onchain)
+    pub fetched_resources: HashSet<(AccountAddress, Type)>,
+    /// Resources accessed but not fetched yet, the execution needs to be retried after fetching
+    pub missing_resources: Vec<(AccountAddress, Type)>,
+
+    /// Owners of Sui objects, keyed by object ID
+    pub objects: HashMap<AccountAddress, Owner>,
+    /// Sender of the programmable transaction block in progress, i.e., while hot potatoes
+    /// returned by its calls are not consumed yet
+    pub ptb_sender: Option<AccountAddress>,
+
+    /// Findings of the last execution, checked by Move oracles
+    /// Arithmetic errors (overflow, division by zero) in entry functions
+    pub arithmetic_aborts: Vec<String>,
+    /// Capability objects sent to or returned to fuzzer-controlled addresses
+    pub capability_leaks: Vec<String>,
+    /// Objects transferred to addresses that no party of the transaction controls
+    pub resource_leaks: Vec<String>,
 }
 
 impl MoveVMStateT for MoveVMState {
@@ -117,6 +139,13 @@ impl MoveVMState {
             values: HashMap::new(),
             typed_bug: vec![],
             ref_in_use: vec![],
+            fetched_resources: HashSet::new(),
+            missing_resources: vec![],
+            objects: HashMap::new(),
+            ptb_sender: None,
+            arithmetic_aborts: vec![],
+            capability_leaks: vec![],
+            resource_leaks: vec![],
         }
     }
 
@@ -185,7 +214,8 @@ impl MoveVMState {
             self.values.get_mut(ty).unwrap().push((value, 1));
         }
 
-        if !gate.is_ref() && !abilities.has_drop() && !abilities.has_store() {
+        // objects (with key) are stored by Sui, they are not hot potatoes
+        if !gate.is_ref() && !abilities.has_drop() && !abilities.has_store() && !abilities.has_key() {
             self._hot_potato += 1;
         }
 
@@ -248,7 +278,7 @@ impl MoveVMState {
                             .expect("StructAbilities not found")
                             .get_ability(ty)
                             .expect("StructAbilities of specific struct not inserted");
-                        if !struct_abilities.has_drop() && !struct_abilities.has_store() {
+                        if !struct_abilities.has_drop() && !struct_abilities.has_store() && !struct_abilities.has_key() {
                             self._hot_potato -= 1;
                         }
                     }
@@ -282,7 +312,7 @@ impl MoveVMState {
             .get_ability(ty)
             .expect("StructAbilities of specific struct not inserted");
 
-        if !struct_abilities.has_drop() && !struct_abilities.has_store() && !is_ref {
+        if !struct_abilities.has_drop() && !struct_abilities.has_store() && !struct_abilities.has_key() && !is_ref {
             self._hot_potato += 1;
         }
 
@@ -318,7 +348,7 @@ impl MoveVMState {
 
 
         if !ret_ty.is_ref() {
-            println!("looking for struct abilities for {:?} {:?}", value, ty);
+            debug!("looking for struct abilities for {:?} {:?}", value, ty);
             let struct_abilities = state
                 .metadata()
                 .get::<StructAbilities>()
@@ -326,7 +356,7 @@ impl MoveVMState {
                 .get_ability(ty)
                 .expect("StructAbilities of specific struct not inserted");
 
-            if !struct_abilities.has_drop() && !struct_abilities.has_store() {
+            if !struct_abilities.has_drop() && !struct_abilities.has_store() && !struct_abilities.has_key() {
                 self._hot_potato += 1;
             }
         }
@@ -353,6 +383,13 @@ impl Clone for MoveVMState {
             values: self.values.clone(),
             typed_bug: self.typed_bug.clone(),
             ref_in_use: self.ref_in_use.clone(),
+            fetched_resources: self.fetched_resources.clone(),
+            missing_resources: self.missing_resources.clone(),
+            objects: self.objects.clone(),
+            ptb_sender: self.ptb_sender,
+            arithmetic_aborts: self.arithmetic_aborts.clone(),
+            capability_leaks: self.capability_leaks.clone(),
+            resource_leaks: self.resource_leaks.clone(),
         }
     }
 }
@@ -414,15 +451,24 @@ impl DataStore for MoveVMState {
         addr: AccountAddress,
         ty: &Type,
     ) -> PartialVMResult<(&mut GlobalValue, Option<Option<NumBytes>>)> {
-        unreachable!("Sui doesn't support load_resource");
-        let data = self.resources.get(&addr).unwrap().get(ty).unwrap();
-
-        self._gv_slot.insert(
-            (addr, ty.clone()),
-            GlobalValue::cached(data.clone()).unwrap(),
-        );
+        // Sui doesn't use global storage, only Aptos onchain fuzzing reaches here
+        let key = (addr, ty.clone());
+        if !self._gv_slot.contains_key(&key) {
+            let gv = match self.resources.get(&addr).and_then(|x| x.get(ty)) {
+                Some(data) => GlobalValue::cached(data.clone()).unwrap(),
+                None => {
+                    if !self.fetched_resources.contains(&key) {
+                        // ask the executor to fetch it from chain and retry
+                        self.missing_resources.push(key);
+                        return Err(PartialVMError::new(StatusCode::MISSING_DATA));
+                    }
+                    GlobalValue::none()
+                }
+            };
+            self._gv_slot.insert(key.clone(), gv);
+        }
 
-        return Ok((self._gv_slot.get_mut(&(addr, ty.clone())).unwrap(), None));
+        return Ok((self._gv_slot.get_mut(&key).unwrap(), None));
     }
 
     fn load_module(&self, _module_id: &ModuleId) -> VMResult<Vec<u8>> {
@@ -589,6 +635,13 @@ impl Default for MoveVMState {
             values: HashMap::new(),
             typed_bug: vec![],
             ref_in_use: vec![],
+            fetched_resources: HashSet::new(),
+            missing_resources: vec![],
+            objects: HashMap::new(),
+            ptb_sender: None,
+            arithmetic_aborts: vec![],
+            capability_leaks: vec![],
+            resource_leaks: vec![],
         }
     }
 }
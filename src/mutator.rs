@@ -0,0 +1,96 @@
+use crate::generic_vm::vm_state::VMStateT;
+use crate::input::{ConciseSerde, VMInputT};
+use crate::state::{HasCaller, HasItyState, InfantStateState};
+use crate::state_input::StagedVMState;
+use libafl::inputs::Input;
+use libafl::mutators::{MutationResult, Mutator};
+use libafl::prelude::{HasMaxSize, HasMetadata, HasRand, Rand, Scheduler, State};
+use libafl::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Mutator for VMs whose inputs mutate themselves ([`VMInputT::mutate`]), stacking mutations and
+/// crossing over infant states (used by the Cairo and CosmWasm backends)
+pub struct HavocMutator<'a, VS, Loc, Addr, SC, CI>
+where
+    VS: Default + VMStateT,
+    SC: Scheduler<StagedVMState<Loc, Addr, VS, CI>, InfantStateState<Loc, Addr, VS, CI>>,
+    Addr: Serialize + DeserializeOwned + Debug + Clone,
+    Loc: Serialize + DeserializeOwned + Debug + Clone,
+    CI: Serialize + DeserializeOwned + Debug + Clone + ConciseSerde,
+{
+    pub infant_scheduler: &'a SC,
+    pub phantom: std::marker::PhantomData<(VS, Loc, Addr, CI)>,
+}
+
+impl<'a, VS, Loc, Addr, SC, CI> HavocMutator<'a, VS, Loc, Addr, SC, CI>
+where
+    VS: Default + VMStateT,
+    SC: Scheduler<StagedVMState<Loc, Addr, VS, CI>, InfantStateState<Loc, Addr, VS, CI>>,
+    Addr: Serialize + DeserializeOwned + Debug + Clone,
+    Loc: Serialize + DeserializeOwned + Debug + Clone,
+    CI: Serialize + DeserializeOwned + Debug + Clone + ConciseSerde,
+{
+    pub fn new(infant_scheduler: &'a SC) -> Self {
+        Self {
+            infant_scheduler,
+            phantom: Default::default(),
+        }
+    }
+}
+
+impl<'a, VS, Loc, Addr, I, S, SC, CI> Mutator<I, S> for HavocMutator<'a, VS, Loc, Addr, SC, CI>
+where
+    I: VMInputT<VS, Loc, Addr, CI> + Input,
+    S: State + HasRand + HasMaxSize + HasItyState<Loc, Addr, VS, CI> + HasCaller<Addr> + HasMetadata,
+    SC: Scheduler<StagedVMState<Loc, Addr, VS, CI>, InfantStateState<Loc, Addr, VS, CI>>,
+    VS: Default + VMStateT,
+    Addr: PartialEq + Debug + Serialize + DeserializeOwned + Clone,
+    Loc: Serialize + DeserializeOwned + Debug + Clone,
+    CI: Serialize + DeserializeOwned + Debug + Clone + ConciseSerde,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I, _stage_idx: i32) -> Result<MutationResult, Error> {
+        // If the state is not initialized, initialize it
+        if !input.get_staged_state().initialized {
+            let concrete = state.get_infant_state(self.infant_scheduler).unwrap();
+            input.set_staged_state(concrete.1, concrete.0);
+        }
+
+        let havoc_times = if state.rand_mut().below(100) < 60 {
+            state.rand_mut().below(10) + 1
+        } else {
+            1
+        };
+
+        let mut res = MutationResult::Skipped;
+        let mut tries = 0;
+        while res != MutationResult::Mutated && tries < 20 {
+            for _ in 0..havoc_times {
+                let mutated = match state.rand_mut().below(100) {
+                    // cross over infant state
+                    0..=5 => {
+                        let old_idx = input.get_state_idx();
+                        let (idx, new_state) = state.get_infant_state(self.infant_scheduler).unwrap();
+                        if idx == old_idx {
+                            MutationResult::Skipped
+                        } else {
+                            input.set_staged_state(new_state, idx);
+                            MutationResult::Mutated
+                        }
+                    }
+                    _ => input.mutate(state),
+                };
+                if mutated == MutationResult::Mutated {
+                    res = MutationResult::Mutated;
+                }
+            }
+            tries += 1;
+        }
+        Ok(res)
+    }
+
+    fn post_exec(&mut self, _state: &mut S, _stage_idx: i32, _corpus_idx: Option<usize>) -> Result<(), Error> {
+        Ok(())
+    }
+}
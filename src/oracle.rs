@@ -17,6 +17,29 @@ use std::ops::Deref;
 use std::rc::Rc;
 use libafl::impl_serdeany;
 
+/// Kinds of bugs (low byte of the bug indices) not reported, toggled at runtime
+pub static mut DISABLED_BUG_KINDS: Option<HashSet<u64>> = None;
+
+/// Whether the bug is of a kind whose oracle is disabled
+pub fn is_bug_disabled(bug_idx: u64) -> bool {
+    match unsafe { &DISABLED_BUG_KINDS } {
+        Some(disabled) => disabled.contains(&(bug_idx & 0xff)),
+        None => false,
+    }
+}
+
+/// Enable or disable the oracle reporting a kind of bugs
+pub fn set_bug_kind_enabled(kind: u64, enabled: bool) {
+    unsafe {
+        let disabled = DISABLED_BUG_KINDS.get_or_insert_with(HashSet::new);
+        if enabled {
+            disabled.remove(&kind);
+        } else {
+            disabled.insert(kind);
+        }
+    }
+}
+
 /// The context passed to the oracle
 pub struct OracleCtx<'a, VS, Addr, Code, By, Loc, SlotTy, Out, I, S: 'static, CI>
 where
@@ -143,3 +166,20 @@ pub struct BugMetadata {
 
 impl_serdeany!(BugMetadata);
 
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_bug_kinds() {
+        // a kind no oracle reports, not to interfere with other tests
+        let kind = 0xfe;
+        let bug_idx = (0x1234 << 8) + kind;
+        assert!(!is_bug_disabled(bug_idx));
+        set_bug_kind_enabled(kind, false);
+        assert!(is_bug_disabled(bug_idx));
+        assert!(!is_bug_disabled((0x1234 << 8) + kind - 1));
+        set_bug_kind_enabled(kind, true);
+        assert!(!is_bug_disabled(bug_idx));
+    }
+}
@@ -2,11 +2,13 @@
 /// Used to determine which input / VMState to fuzz next
 
 use libafl::corpus::Corpus;
+use tracing::{debug, error};
 use libafl::corpus::Testcase;
 use libafl::prelude::{HasMetadata, HasRand, Input, Rand};
 use libafl::schedulers::Scheduler;
 use libafl::state::HasCorpus;
 use libafl::{impl_serdeany, Error};
+use nix::unistd::{sysconf, SysconfVar};
 
 use serde::{Deserialize, Serialize};
 
@@ -34,6 +36,46 @@ pub const DROP_THRESHOLD: usize = 500;
 pub const PRUNE_AMT: usize = 250;
 /// If inputs (or VMState) has not been visited this many times, it will be ignored during pruning
 pub const VISIT_IGNORE_THRESHOLD: usize = 2;
+/// The minimum number of inputs (or VMState) kept when pruning for memory
+pub const MEMORY_PRUNE_FLOOR: usize = 50;
+/// The number of inputs (or VMState) added after pruning for memory before pruning for memory
+/// again, pruned entries are not necessarily returned to the OS right away
+pub const MEMORY_PRUNE_COOLDOWN: usize = 100;
+
+/// Resident memory (in bytes) beyond which the corpus is pruned regardless of its size,
+/// unbounded if None
+pub static mut MEMORY_CAP: Option<usize> = None;
+
+/// Resident memory of the process in bytes, None if unknown (only available on Linux)
+pub fn resident_memory() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    let page_size = sysconf(SysconfVar::PAGE_SIZE).ok()??;
+    Some(pages * page_size as usize)
+}
+
+fn over_memory_cap() -> bool {
+    match unsafe { MEMORY_CAP } {
+        Some(cap) => resident_memory().map_or(false, |rss| rss > cap),
+        None => false,
+    }
+}
+
+/// Whether to prune for memory, given whether the process is over [`MEMORY_CAP`] and the
+/// remaining cooldown since the last pruning for memory, which is updated
+fn memory_prune_due(over_cap: bool, cooldown: &mut usize) -> bool {
+    if !over_cap {
+        // memory went back under the cap, prune right away next time it is exceeded
+        *cooldown = 0;
+        return false;
+    }
+    if *cooldown > 0 {
+        *cooldown -= 1;
+        return false;
+    }
+    *cooldown = MEMORY_PRUNE_COOLDOWN;
+    true
+}
 
 /// A scheduler that drops inputs (or VMState) based on a voting mechanism
 #[derive(Debug, Clone)]
@@ -137,6 +179,8 @@ pub struct VoteData {
     pub deps: DependencyTree,
     /// To remove, for Move schedulers
     pub to_remove: Vec<usize>,
+    /// Inputs (or VMState) to add before pruning for memory again
+    pub memory_prune_cooldown: usize,
 }
 
 pub trait HasReportCorpus<S>
@@ -186,6 +230,7 @@ where
                 votes_total: 1,
                 deps: DependencyTree::new(),
                 to_remove: vec![],
+                memory_prune_cooldown: 0,
             });
         }
 
@@ -209,14 +254,24 @@ where
         {
             let mut corpus_size = state.corpus().count();
             let _corpus_mut = state.corpus_mut();
-            let data = state.metadata().get::<VoteData>().unwrap();
             #[cfg(feature = "full_trace")]
             {
                 corpus_size -= unsafe { REMOVED_CORPUS };
             }
 
-            // If the corpus is too large (> [`DROP_THRESHOLD`]), prune it
-            if corpus_size > DROP_THRESHOLD {
+            // If the corpus is too large (> [`DROP_THRESHOLD`]), prune it, prune half of it if
+            // the process uses more memory than [`MEMORY_CAP`] (at most once per
+            // [`MEMORY_PRUNE_COOLDOWN`] additions while it stays above)
+            let over_cap = {
+                let data = state.metadata_mut().get_mut::<VoteData>().unwrap();
+                memory_prune_due(
+                    corpus_size > MEMORY_PRUNE_FLOOR && over_memory_cap(),
+                    &mut data.memory_prune_cooldown,
+                )
+            };
+            let data = state.metadata().get::<VoteData>().unwrap();
+            if corpus_size > DROP_THRESHOLD || over_cap {
+                let prune_amt = if over_cap { corpus_size / 2 } else { PRUNE_AMT };
                 // get top 100 entries sorted by votes (descending)
                 let mut sorted: Vec<_> = data.votes_and_visits.iter().collect();
                 sorted.sort_by(|(_idx_1, (votes1, visits1)), (_idx_2, (votes2, visits2))| {
@@ -225,7 +280,7 @@ where
                     score_1.partial_cmp(&score_2).unwrap()
                 });
 
-                for i in sorted.iter().take(PRUNE_AMT) {
+                for i in sorted.iter().take(prune_amt) {
                     to_remove.push(*i.0);
                 }
 
@@ -281,7 +336,7 @@ where
             let data = state.metadata().get::<VoteData>().unwrap();
             use crate::r#const::DEBUG_PRINT_PERCENT;
             if random::<usize>() % DEBUG_PRINT_PERCENT == 0 {
-                println!(
+                debug!(
                     "======================= corpus size: {} =======================",
                     corpus_size
                 );
@@ -290,7 +345,7 @@ where
                     let inp = state.corpus().get(*idx).unwrap().clone();
                     match inp.into_inner().input() {
                         Some(x) => {
-                            println!(
+                            debug!(
                                 "idx: {}, votes: {}, visits: {}: {:?}",
                                 idx, votes, visits, x
                             );
@@ -298,7 +353,7 @@ where
                         _ => {}
                     }
                 }
-                println!("======================= corpus  =======================");
+                debug!("======================= corpus  =======================");
             }
         }
 
@@ -348,9 +403,9 @@ where
             if v.is_some() {
                 let (votes, _visits) = v.expect("scheduler metadata malformed");
                 *votes += increment;
-                println!("Voted for {}", idx);
+                debug!("Voted for {}", idx);
             } else {
-                println!("scheduler metadata malformed");
+                error!("scheduler metadata malformed");
             }
         }
 
@@ -386,4 +441,19 @@ mod tests {
         assert!(!tree.nodes.contains_key(&2));
         assert!(!tree.nodes.contains_key(&3));
     }
+
+    #[test]
+    fn test_memory_prune_cooldown() {
+        let mut cooldown = 0;
+        assert!(memory_prune_due(true, &mut cooldown));
+        // still over the cap right after pruning: wait for the cooldown
+        for _ in 0..MEMORY_PRUNE_COOLDOWN {
+            assert!(!memory_prune_due(true, &mut cooldown));
+        }
+        assert!(memory_prune_due(true, &mut cooldown));
+
+        // back under the cap: prune as soon as it is exceeded again
+        assert!(!memory_prune_due(false, &mut cooldown));
+        assert!(memory_prune_due(true, &mut cooldown));
+    }
 }
\ No newline at end of file
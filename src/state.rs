@@ -1,5 +1,6 @@
 /// Implements LibAFL's State trait supporting our fuzzing logic.
 use crate::indexed_corpus::IndexedInMemoryCorpus;
+use tracing::info;
 use crate::input::{ConciseSerde, VMInputT};
 
 use crate::state_input::StagedVMState;
@@ -199,7 +200,7 @@ where
         if lparam_seed == 0 {
             seed = current_nanos();
         }
-        println!("Seed: {}", seed);
+        info!("Seed: {}", seed);
         Self {
             infant_states_state: InfantStateState::new(),
             #[cfg(not(feature = "evaluation"))]
@@ -0,0 +1,245 @@
+//! Storage of the corpus and solutions of the campaign
+//!
+//! Entries (corpus items, violating sequences and their artifacts, `findings.json`) are
+//! addressed by their path in the on-disk layout of the work directory (e.g.,
+//! `work_dir/vulnerabilities/3_replayable`), whatever the backend storing them:
+//! - `disk` (default): files in the work directory
+//! - `sqlite:<path>`: an sqlite database (feature `sqlite_storage`), with a row per entry, so that
+//!   the campaign can be queried atomically while it runs, e.g.,
+//!   `SELECT count(*) FROM entries WHERE kind = 'vulnerabilities'`
+//! - `s3://<bucket>/<prefix>`: an S3-compatible bucket (feature `s3_storage`), credentials are read
+//!   from `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY`
+use std::fs;
+use std::path::Path;
+
+pub trait CorpusStorage {
+    /// Store an entry, replacing the entry at the same path
+    fn put(&mut self, path: &str, content: &[u8]);
+    /// Content of an entry
+    fn get(&self, path: &str) -> Option<Vec<u8>>;
+    /// Names of the entries of a directory
+    fn list(&self, dir: &str) -> Vec<String>;
+}
+
+/// Directory and name of an entry
+pub fn split_path(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+/// Files in the work directory, the layout ItyFuzz has always used
+pub struct DiskStorage;
+
+impl CorpusStorage for DiskStorage {
+    fn put(&mut self, path: &str, content: &[u8]) {
+        let (dir, _) = split_path(path);
+        if !dir.is_empty() && !Path::new(dir).exists() {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(path, content).unwrap_or_else(|e| panic!("failed to write {}: {}", path, e));
+    }
+
+    fn get(&self, path: &str) -> Option<Vec<u8>> {
+        fs::read(path).ok()
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "sqlite_storage")]
+pub struct SqliteStorage {
+    conn: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite_storage")]
+impl SqliteStorage {
+    pub fn open(path: &str) -> Self {
+        let conn = rusqlite::Connection::open(path).expect("failed to open the sqlite storage");
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS entries (
+                 path TEXT PRIMARY KEY,
+                 dir TEXT NOT NULL,
+                 name TEXT NOT NULL,
+                 kind TEXT NOT NULL,
+                 content BLOB NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS entries_dir ON entries (dir);
+             CREATE INDEX IF NOT EXISTS entries_kind ON entries (kind);",
+        )
+        .expect("failed to create the sqlite storage tables");
+        Self { conn }
+    }
+}
+
+#[cfg(feature = "sqlite_storage")]
+impl CorpusStorage for SqliteStorage {
+    fn put(&mut self, path: &str, content: &[u8]) {
+        let (dir, name) = split_path(path);
+        // kind of entry: corpus, vulnerabilities, unconfirmed, ...
+        let kind = split_path(dir).1;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO entries (path, dir, name, kind, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, strftime('%s', 'now'))",
+                rusqlite::params![path, dir, name, kind, content],
+            )
+            .unwrap_or_else(|e| panic!("failed to store {}: {}", path, e));
+    }
+
+    fn get(&self, path: &str) -> Option<Vec<u8>> {
+        self.conn
+            .query_row("SELECT content FROM entries WHERE path = ?1", [path], |row| row.get(0))
+            .ok()
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT name FROM entries WHERE dir = ?1 ORDER BY created_at, name")
+            .unwrap();
+        let names = statement
+            .query_map([dir], |row| row.get(0))
+            .unwrap()
+            .filter_map(|name| name.ok())
+            .collect();
+        names
+    }
+}
+
+#[cfg(feature = "s3_storage")]
+pub struct S3Storage {
+    bucket: s3::Bucket,
+    prefix: String,
+}
+
+#[cfg(feature = "s3_storage")]
+impl S3Storage {
+    /// Bucket at `s3://<bucket>/<prefix>`, on AWS (region from `AWS_REGION`) or on the
+    /// S3-compatible service at `endpoint`
+    pub fn open(url: &str, endpoint: Option<&str>) -> Self {
+        let (bucket, prefix) = url
+            .trim_start_matches("s3://")
+            .split_once('/')
+            .unwrap_or((url.trim_start_matches("s3://"), ""));
+        let region_name = std::env::var("AWS_REGION").unwrap_or("us-east-1".to_string());
+        let region = match endpoint {
+            Some(endpoint) => s3::Region::Custom {
+                region: region_name,
+                endpoint: endpoint.to_string(),
+            },
+            None => region_name.parse().expect("invalid AWS_REGION"),
+        };
+        let credentials = s3::creds::Credentials::default().expect("S3 credentials not found");
+        let mut bucket = s3::Bucket::new(bucket, region, credentials).expect("invalid S3 bucket");
+        if endpoint.is_some() {
+            bucket = bucket.with_path_style();
+        }
+        Self {
+            bucket,
+            prefix: prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn key(&self, path: &str) -> String {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        if self.prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.prefix, path)
+        }
+    }
+}
+
+#[cfg(feature = "s3_storage")]
+impl CorpusStorage for S3Storage {
+    fn put(&mut self, path: &str, content: &[u8]) {
+        let response = self
+            .bucket
+            .put_object(self.key(path), content)
+            .unwrap_or_else(|e| panic!("failed to store {}: {}", path, e));
+        if response.status_code() >= 300 {
+            panic!("failed to store {}: status {}", path, response.status_code());
+        }
+    }
+
+    fn get(&self, path: &str) -> Option<Vec<u8>> {
+        let response = self.bucket.get_object(self.key(path)).ok()?;
+        (response.status_code() < 300).then(|| response.bytes().to_vec())
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        let prefix = format!("{}/", self.key(dir));
+        self.bucket
+            .list(prefix.clone(), Some("/".to_string()))
+            .map(|pages| {
+                pages
+                    .iter()
+                    .flat_map(|page| page.contents.iter())
+                    .map(|object| object.key.trim_start_matches(&prefix).to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Storage of the campaign, set with `--storage` (disk if not set)
+pub static mut STORAGE: Option<Box<dyn CorpusStorage>> = None;
+
+pub fn storage() -> &'static mut Box<dyn CorpusStorage> {
+    unsafe { STORAGE.get_or_insert_with(|| Box::new(DiskStorage)) }
+}
+
+/// Backend of a storage spec (`disk`, `sqlite:<path>` or `s3://<bucket>/<prefix>`), the database
+/// defaulting to `[WORKDIR]/corpus.db`
+pub fn open_storage(spec: &str, work_dir: &str, endpoint: Option<&str>) -> Box<dyn CorpusStorage> {
+    if spec == "disk" {
+        return Box::new(DiskStorage);
+    }
+    if spec == "sqlite" || spec.starts_with("sqlite:") {
+        let path = match spec.strip_prefix("sqlite:") {
+            Some(path) if !path.is_empty() => path.to_string(),
+            _ => format!("{}/corpus.db", work_dir),
+        };
+        #[cfg(feature = "sqlite_storage")]
+        return Box::new(SqliteStorage::open(&path));
+        #[cfg(not(feature = "sqlite_storage"))]
+        panic!("sqlite storage {} requires sqlite_storage feature", path);
+    }
+    if spec.starts_with("s3://") {
+        #[cfg(feature = "s3_storage")]
+        return Box::new(S3Storage::open(spec, endpoint));
+        #[cfg(not(feature = "s3_storage"))]
+        panic!("S3 storage {} (endpoint {:?}) requires s3_storage feature", spec, endpoint);
+    }
+    panic!("unknown storage {}, expected disk, sqlite:<path> or s3://<bucket>/<prefix>", spec);
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_storage() {
+        assert_eq!(split_path("work_dir/corpus/3_replayable"), ("work_dir/corpus", "3_replayable"));
+        assert_eq!(split_path("findings.json"), ("", "findings.json"));
+
+        let dir = std::env::temp_dir().join(format!("ityfuzz_storage_{}", std::process::id()));
+        let corpus = format!("{}/corpus", dir.display());
+        let mut storage = open_storage("disk", &dir.display().to_string(), None);
+        storage.put(&format!("{}/1_replayable", corpus), b"{}");
+        storage.put(&format!("{}/1_replayable", corpus), b"[]");
+        assert_eq!(storage.get(&format!("{}/1_replayable", corpus)), Some(b"[]".to_vec()));
+        assert_eq!(storage.list(&corpus), vec!["1_replayable".to_string()]);
+        assert_eq!(storage.get(&format!("{}/2_replayable", corpus)), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.24;
+import "../../../solidity_utils/lib.sol";
+
+contract main {
+    function submit() public {
+        require(blobhash(0) != bytes32(0), "no blob");
+        if (block.blobbasefee > 1000) {
+            bug();
+        }
+    }
+}
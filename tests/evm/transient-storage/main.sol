@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.24;
+import "../../../solidity_utils/lib.sol";
+
+contract main {
+    // transient reentrancy lock, cleared after every transaction
+    modifier nonReentrant() {
+        assembly {
+            if tload(0) { revert(0, 0) }
+            tstore(0, 1)
+        }
+        _;
+        assembly {
+            tstore(0, 0)
+        }
+    }
+
+    function set(uint256 v) public nonReentrant {
+        assembly {
+            tstore(1, v)
+        }
+        check();
+    }
+
+    function check() internal view {
+        uint256 x;
+        assembly {
+            x := tload(1)
+        }
+        if (x == 0x1337) {
+            bug();
+        }
+    }
+}